@@ -1,3 +1,121 @@
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
 fn main() {
-    tauri_build::build()
+    tauri_build::build();
+
+    write_resource_manifest().expect("failed to write the resource manifest");
+}
+
+/// Where the bundler puts `path` under the resource dir: as written, with each `..`
+/// replaced by `_up_`.
+fn bundled_name(path: &Path) -> String {
+    path.components()
+        .map(|part| match part {
+            std::path::Component::ParentDir => "_up_".to_string(),
+            part => part.as_os_str().to_string_lossy().into_owned(),
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn files_under(dir: &Path, out: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            files_under(&path, out)?;
+        } else {
+            out.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// The directory a glob matches under: its components up to the first with a wildcard,
+/// or the whole path if it has none. Watching it reruns the build script when a
+/// matching file is added. `None` for a pattern at the top level, since watching the
+/// crate root would mean watching the target dir too.
+fn glob_root(pattern: &str) -> Option<PathBuf> {
+    let root: PathBuf = Path::new(pattern)
+        .components()
+        .take_while(|part| {
+            !part
+                .as_os_str()
+                .to_string_lossy()
+                .contains(['*', '?', '[', '{'])
+        })
+        .collect();
+    (!root.as_os_str().is_empty()).then_some(root)
+}
+
+/// Bundled name to source path for each file `bundle.resources` names, whether it's
+/// a list of paths and globs or a map of sources to targets. Directories and glob roots
+/// are watched as well as the files, so new files are picked up.
+fn resources(config: &Value) -> io::Result<BTreeMap<String, PathBuf>> {
+    let mut resources = BTreeMap::new();
+    match config.pointer("/bundle/resources") {
+        Some(Value::Array(patterns)) => {
+            for pattern in patterns.iter().filter_map(Value::as_str) {
+                if let Some(root) = glob_root(pattern) {
+                    println!("cargo:rerun-if-changed={}", root.display());
+                }
+                let paths = glob::glob(pattern)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+                for path in paths.filter_map(Result::ok) {
+                    let mut files = Vec::new();
+                    if path.is_dir() {
+                        files_under(&path, &mut files)?;
+                    } else {
+                        files.push(path);
+                    }
+                    for file in files {
+                        resources.insert(bundled_name(&file), file);
+                    }
+                }
+            }
+        }
+        Some(Value::Object(targets)) => {
+            for (source, target) in targets {
+                let (source, target) = (Path::new(source), target.as_str().unwrap_or_default());
+                if source.is_dir() {
+                    println!("cargo:rerun-if-changed={}", source.display());
+                    let mut files = Vec::new();
+                    files_under(source, &mut files)?;
+                    for file in files {
+                        let relative = file.strip_prefix(source).unwrap_or(&file);
+                        let name = Path::new(target).join(relative);
+                        resources.insert(bundled_name(&name), file);
+                    }
+                } else {
+                    resources.insert(bundled_name(Path::new(target)), source.to_path_buf());
+                }
+            }
+        }
+        _ => {}
+    }
+    Ok(resources)
+}
+
+/// Hashes every bundled resource into `$OUT_DIR/resources.manifest.json`, which
+/// `src/integrity.rs` compiles in and checks the installed files against.
+fn write_resource_manifest() -> io::Result<()> {
+    println!("cargo:rerun-if-changed=tauri.conf.json");
+    let config: Value = serde_json::from_slice(&fs::read("tauri.conf.json")?)?;
+    let mut manifest = BTreeMap::new();
+    for (name, source) in resources(&config)? {
+        println!("cargo:rerun-if-changed={}", source.display());
+        let mut hasher = Sha256::new();
+        io::copy(&mut File::open(&source)?, &mut hasher)?;
+        manifest.insert(name, format!("{:x}", hasher.finalize()));
+    }
+    let out = PathBuf::from(std::env::var_os("OUT_DIR").expect("cargo sets OUT_DIR"));
+    fs::write(
+        out.join("resources.manifest.json"),
+        serde_json::to_vec_pretty(&manifest)?,
+    )
 }
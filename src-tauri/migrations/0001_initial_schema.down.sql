@@ -0,0 +1,5 @@
+DROP TABLE IF EXISTS user_profile;
+DROP TABLE IF EXISTS preferences;
+DROP TABLE IF EXISTS sync_queue;
+DROP TABLE IF EXISTS folders;
+DROP TABLE IF EXISTS notes;
@@ -0,0 +1,10 @@
+CREATE TABLE IF NOT EXISTS downloads (
+  id TEXT PRIMARY KEY,
+  url TEXT NOT NULL,
+  dest TEXT NOT NULL,
+  expected_sha256 TEXT,
+  status TEXT NOT NULL,
+  error TEXT,
+  created_at TEXT DEFAULT (datetime('now')) NOT NULL,
+  updated_at TEXT DEFAULT (datetime('now')) NOT NULL
+);
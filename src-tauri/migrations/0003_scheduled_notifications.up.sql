@@ -0,0 +1,11 @@
+CREATE TABLE IF NOT EXISTS scheduled_notifications (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  title TEXT NOT NULL,
+  body TEXT NOT NULL,
+  at_ms INTEGER NOT NULL,
+  repeat TEXT,
+  native INTEGER DEFAULT 0 NOT NULL,
+  created_at TEXT DEFAULT (datetime('now')) NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_scheduled_notifications_at ON scheduled_notifications(at_ms);
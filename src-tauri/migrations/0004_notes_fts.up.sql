@@ -0,0 +1,29 @@
+-- Full-text index over note titles and plain-text bodies, kept in sync by triggers.
+-- External content: the index stores no copy of the text, only `notes.rowid`.
+CREATE VIRTUAL TABLE IF NOT EXISTS notes_fts USING fts5(
+  title,
+  content_text,
+  content = 'notes',
+  content_rowid = 'rowid',
+  tokenize = 'unicode61 remove_diacritics 2'
+);
+
+CREATE TRIGGER IF NOT EXISTS notes_fts_ai AFTER INSERT ON notes BEGIN
+  INSERT INTO notes_fts (rowid, title, content_text)
+  VALUES (new.rowid, new.title, new.content_text);
+END;
+
+CREATE TRIGGER IF NOT EXISTS notes_fts_ad AFTER DELETE ON notes BEGIN
+  INSERT INTO notes_fts (notes_fts, rowid, title, content_text)
+  VALUES ('delete', old.rowid, old.title, old.content_text);
+END;
+
+CREATE TRIGGER IF NOT EXISTS notes_fts_au AFTER UPDATE OF title, content_text ON notes BEGIN
+  INSERT INTO notes_fts (notes_fts, rowid, title, content_text)
+  VALUES ('delete', old.rowid, old.title, old.content_text);
+  INSERT INTO notes_fts (rowid, title, content_text)
+  VALUES (new.rowid, new.title, new.content_text);
+END;
+
+-- Index notes that existed before this migration.
+INSERT INTO notes_fts (notes_fts) VALUES ('rebuild');
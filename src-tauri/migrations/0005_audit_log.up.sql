@@ -0,0 +1,12 @@
+CREATE TABLE IF NOT EXISTS audit_log (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  timestamp_ms INTEGER NOT NULL,
+  window_label TEXT NOT NULL,
+  command TEXT NOT NULL,
+  arg_bytes INTEGER NOT NULL,
+  duration_us INTEGER NOT NULL,
+  handled INTEGER NOT NULL,
+  args TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_audit_log_timestamp ON audit_log(timestamp_ms);
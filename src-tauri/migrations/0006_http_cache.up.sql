@@ -0,0 +1,13 @@
+CREATE TABLE IF NOT EXISTS http_cache (
+  cache_key TEXT PRIMARY KEY,
+  url TEXT NOT NULL,
+  status INTEGER NOT NULL,
+  body TEXT NOT NULL,
+  etag TEXT,
+  last_modified TEXT,
+  stored_at_ms INTEGER NOT NULL,
+  last_used_ms INTEGER NOT NULL,
+  size_bytes INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_http_cache_last_used ON http_cache(last_used_ms);
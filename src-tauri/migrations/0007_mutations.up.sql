@@ -0,0 +1,15 @@
+CREATE TABLE IF NOT EXISTS mutations (
+  seq INTEGER PRIMARY KEY AUTOINCREMENT,
+  id TEXT NOT NULL UNIQUE,
+  entity TEXT NOT NULL,
+  op TEXT NOT NULL,
+  payload TEXT NOT NULL,
+  status TEXT DEFAULT 'pending' NOT NULL,
+  force INTEGER DEFAULT 0 NOT NULL,
+  remote TEXT,
+  attempts INTEGER DEFAULT 0 NOT NULL,
+  last_error TEXT,
+  created_ms INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_mutations_status ON mutations(status, seq);
@@ -0,0 +1,7 @@
+CREATE TABLE IF NOT EXISTS media (
+  hash TEXT PRIMARY KEY,
+  file_name TEXT NOT NULL,
+  mime TEXT NOT NULL,
+  size_bytes INTEGER NOT NULL,
+  created_at_ms INTEGER NOT NULL
+);
@@ -0,0 +1,13 @@
+CREATE TABLE IF NOT EXISTS metrics_samples (
+  recording_id TEXT NOT NULL,
+  timestamp_ms INTEGER NOT NULL,
+  uptime_ms INTEGER NOT NULL,
+  rss_bytes INTEGER NOT NULL,
+  private_bytes INTEGER,
+  cpu_percent REAL NOT NULL,
+  webview_rss_bytes INTEGER,
+  open_fd_count INTEGER,
+  thread_count INTEGER
+);
+
+CREATE INDEX IF NOT EXISTS idx_metrics_samples_recording ON metrics_samples(recording_id, timestamp_ms);
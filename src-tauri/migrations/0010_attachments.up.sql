@@ -0,0 +1,19 @@
+CREATE TABLE IF NOT EXISTS attachments (
+  hash TEXT PRIMARY KEY,
+  mime TEXT NOT NULL,
+  size_bytes INTEGER NOT NULL,
+  ref_count INTEGER DEFAULT 0 NOT NULL,
+  created_at_ms INTEGER NOT NULL,
+  -- When ref_count last reached zero, or when the blob was added; NULL while referenced.
+  unreferenced_since_ms INTEGER
+);
+
+CREATE TABLE IF NOT EXISTS attachment_links (
+  hash TEXT NOT NULL REFERENCES attachments(hash),
+  note_id TEXT NOT NULL,
+  PRIMARY KEY (hash, note_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_attachments_unreferenced ON attachments(unreferenced_since_ms)
+  WHERE ref_count = 0;
+CREATE INDEX IF NOT EXISTS idx_attachment_links_note ON attachment_links(note_id);
@@ -0,0 +1 @@
+ALTER TABLE audit_log RENAME COLUMN ok TO handled;
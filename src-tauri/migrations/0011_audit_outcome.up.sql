@@ -0,0 +1,2 @@
+-- Calls used to be recorded by whether a handler existed; now by whether they succeeded.
+ALTER TABLE audit_log RENAME COLUMN handled TO ok;
@@ -0,0 +1,104 @@
+package com.layers.badge
+
+import android.annotation.SuppressLint
+import android.app.Activity
+import android.app.NotificationChannel
+import android.app.NotificationManager
+import android.app.PendingIntent
+import android.content.Context
+import android.os.Build
+import androidx.core.app.NotificationCompat
+import androidx.core.app.NotificationManagerCompat
+import app.tauri.annotation.Command
+import app.tauri.annotation.InvokeArg
+import app.tauri.annotation.TauriPlugin
+import app.tauri.plugin.Invoke
+import app.tauri.plugin.JSObject
+import app.tauri.plugin.Plugin
+
+private const val CHANNEL_ID = "layers_badge"
+private const val NOTIFICATION_ID = 0xBAD6E
+private const val PREFS = "com.layers.badge"
+private const val COUNT_KEY = "count"
+
+@InvokeArg
+class CountArgs {
+    var count: Int = 0
+}
+
+/**
+ * Launchers badge the icon from the app's notifications rather than through an API, so
+ * the count is carried by one silent notification on a channel that only exists for it.
+ * The last count set is kept in preferences, since nothing can read the badge back.
+ */
+@TauriPlugin
+class BadgePlugin(private val activity: Activity) : Plugin(activity) {
+    private val prefs
+        get() = activity.getSharedPreferences(PREFS, Context.MODE_PRIVATE)
+
+    private fun ensureChannel() {
+        if (Build.VERSION.SDK_INT < 26) return
+        val manager = activity.getSystemService(NotificationManager::class.java)
+        if (manager.getNotificationChannel(CHANNEL_ID) != null) return
+        val channel = NotificationChannel(CHANNEL_ID, "Unread count", NotificationManager.IMPORTANCE_MIN)
+        channel.setShowBadge(true)
+        channel.setSound(null, null)
+        channel.enableVibration(false)
+        manager.createNotificationChannel(channel)
+    }
+
+    // Permission is checked through areNotificationsEnabled just above the post.
+    @SuppressLint("MissingPermission")
+    @Command
+    fun setCount(invoke: Invoke) {
+        val args = invoke.parseArgs(CountArgs::class.java)
+        val notifications = NotificationManagerCompat.from(activity)
+        if (args.count <= 0) {
+            notifications.cancel(NOTIFICATION_ID)
+            prefs.edit().putInt(COUNT_KEY, 0).apply()
+            invoke.resolve()
+            return
+        }
+        // False both before POST_NOTIFICATIONS is granted and when the user has turned
+        // notifications off, either of which hides the badge too.
+        if (!notifications.areNotificationsEnabled()) {
+            invoke.reject("notification permission not granted", "PermissionDenied")
+            return
+        }
+        ensureChannel()
+        val launch = activity.packageManager.getLaunchIntentForPackage(activity.packageName)
+        val notification = NotificationCompat.Builder(activity, CHANNEL_ID)
+            .setSmallIcon(activity.applicationInfo.icon)
+            .setContentTitle(activity.applicationInfo.loadLabel(activity.packageManager))
+            .setContentText("${args.count} unread")
+            .setNumber(args.count)
+            .setBadgeIconType(NotificationCompat.BADGE_ICON_SMALL)
+            .setPriority(NotificationCompat.PRIORITY_MIN)
+            .setSilent(true)
+            .setOnlyAlertOnce(true)
+            .setContentIntent(
+                launch?.let {
+                    PendingIntent.getActivity(activity, 0, it, PendingIntent.FLAG_IMMUTABLE)
+                },
+            )
+            .build()
+        try {
+            notifications.notify(NOTIFICATION_ID, notification)
+        } catch (e: SecurityException) {
+            invoke.reject("notification permission not granted", "PermissionDenied")
+            return
+        }
+        prefs.edit().putInt(COUNT_KEY, args.count).apply()
+        invoke.resolve()
+    }
+
+    @Command
+    fun getCount(invoke: Invoke) {
+        // Left out until a count has been set, which the Rust side reads as `None`.
+        val ret = JSObject()
+        if (prefs.contains(COUNT_KEY)) {
+            ret.put("count", prefs.getInt(COUNT_KEY, 0))
+        }
+        invoke.resolve(ret)
+    }
+}
@@ -0,0 +1,47 @@
+import Tauri
+import UIKit
+import UserNotifications
+import WebKit
+
+class CountArgs: Decodable {
+  let count: Int
+}
+
+class BadgePlugin: Plugin {
+  @objc public func setCount(_ invoke: Invoke) throws {
+    let args = try invoke.parseArgs(CountArgs.self)
+    let count = max(args.count, 0)
+    UNUserNotificationCenter.current().getNotificationSettings { settings in
+      // Clearing is always allowed; a new number only shows with badge permission.
+      if count > 0, settings.badgeSetting != .enabled {
+        invoke.reject("notification permission not granted", code: "PermissionDenied")
+        return
+      }
+      if #available(iOS 16.0, *) {
+        UNUserNotificationCenter.current().setBadgeCount(count) { error in
+          if let error = error {
+            invoke.reject(error.localizedDescription, code: "Failed")
+          } else {
+            invoke.resolve()
+          }
+        }
+      } else {
+        DispatchQueue.main.async {
+          UIApplication.shared.applicationIconBadgeNumber = count
+          invoke.resolve()
+        }
+      }
+    }
+  }
+
+  @objc public func getCount(_ invoke: Invoke) throws {
+    DispatchQueue.main.async {
+      invoke.resolve(["count": UIApplication.shared.applicationIconBadgeNumber])
+    }
+  }
+}
+
+@_cdecl("init_plugin_badge")
+func initPlugin() -> Plugin {
+  return BadgePlugin()
+}
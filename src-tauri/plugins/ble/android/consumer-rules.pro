@@ -0,0 +1,3 @@
+# Tauri instantiates the plugin and invokes its commands by reflection.
+-keep @app.tauri.annotation.TauriPlugin class com.layers.ble.** { *; }
+-keep @app.tauri.annotation.InvokeArg class com.layers.ble.** { *; }
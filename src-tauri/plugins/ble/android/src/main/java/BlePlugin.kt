@@ -0,0 +1,351 @@
+package com.layers.ble
+
+import android.Manifest
+import android.annotation.SuppressLint
+import android.app.Activity
+import android.bluetooth.BluetoothAdapter
+import android.bluetooth.BluetoothDevice
+import android.bluetooth.BluetoothGatt
+import android.bluetooth.BluetoothGattCallback
+import android.bluetooth.BluetoothGattCharacteristic
+import android.bluetooth.BluetoothManager
+import android.bluetooth.BluetoothProfile
+import android.bluetooth.BluetoothStatusCodes
+import android.bluetooth.le.ScanCallback
+import android.bluetooth.le.ScanFilter
+import android.bluetooth.le.ScanResult
+import android.bluetooth.le.ScanSettings
+import android.content.Context
+import android.os.Build
+import android.os.ParcelUuid
+import app.tauri.PermissionState
+import app.tauri.annotation.Command
+import app.tauri.annotation.InvokeArg
+import app.tauri.annotation.Permission
+import app.tauri.annotation.PermissionCallback
+import app.tauri.annotation.TauriPlugin
+import app.tauri.plugin.Channel
+import app.tauri.plugin.Invoke
+import app.tauri.plugin.JSArray
+import app.tauri.plugin.JSObject
+import app.tauri.plugin.Plugin
+import java.util.UUID
+import java.util.concurrent.ConcurrentHashMap
+
+@InvokeArg
+class ScanArgs {
+    /** Full lowercase UUIDs; empty reports every device. */
+    var services: Array<String> = arrayOf()
+    lateinit var channel: Channel
+}
+
+@InvokeArg
+class DeviceArgs {
+    lateinit var deviceId: String
+}
+
+@InvokeArg
+class CharacteristicArgs {
+    lateinit var deviceId: String
+    lateinit var service: String
+    lateinit var characteristic: String
+    /** Bytes as 0-255 numbers, as serde writes a `&[u8]`. Only for writes. */
+    var value: IntArray = intArrayOf()
+    var withResponse: Boolean = true
+}
+
+@TauriPlugin(
+    permissions = [
+        Permission(
+            strings = [Manifest.permission.BLUETOOTH_SCAN, Manifest.permission.BLUETOOTH_CONNECT],
+            alias = "bluetooth",
+        ),
+        Permission(strings = [Manifest.permission.ACCESS_FINE_LOCATION], alias = "location"),
+    ],
+)
+class BlePlugin(private val activity: Activity) : Plugin(activity) {
+    private var scanCallback: ScanCallback? = null
+    private val connections = ConcurrentHashMap<String, Connection>()
+
+    private val adapter: BluetoothAdapter?
+        get() = (activity.getSystemService(Context.BLUETOOTH_SERVICE) as BluetoothManager?)
+            ?.adapter
+            ?.takeIf { it.isEnabled }
+
+    /** Android 12 gave Bluetooth its own runtime permissions; before it, scanning needed location. */
+    private val permissionAlias: String
+        get() = if (Build.VERSION.SDK_INT >= 31) "bluetooth" else "location"
+
+    private fun granted() = getPermissionState(permissionAlias) == PermissionState.GRANTED
+
+    @Command
+    fun startScan(invoke: Invoke) {
+        if (granted()) {
+            scan(invoke)
+        } else {
+            requestPermissionForAlias(permissionAlias, invoke, "scanPermission")
+        }
+    }
+
+    @PermissionCallback
+    private fun scanPermission(invoke: Invoke) {
+        if (granted()) {
+            scan(invoke)
+        } else {
+            invoke.reject("Bluetooth permission denied", "PermissionDenied")
+        }
+    }
+
+    @SuppressLint("MissingPermission")
+    private fun scan(invoke: Invoke) {
+        val args = invoke.parseArgs(ScanArgs::class.java)
+        val scanner = adapter?.bluetoothLeScanner
+        if (scanner == null) {
+            invoke.reject("Bluetooth is off or missing", "AdapterUnavailable")
+            return
+        }
+        scanCallback?.let { scanner.stopScan(it) }
+        val filters = args.services.map {
+            ScanFilter.Builder().setServiceUuid(ParcelUuid.fromString(it)).build()
+        }
+        val settings = ScanSettings.Builder()
+            .setScanMode(ScanSettings.SCAN_MODE_LOW_LATENCY)
+            .build()
+        val callback = object : ScanCallback() {
+            override fun onScanResult(callbackType: Int, result: ScanResult) {
+                val uuids = JSArray()
+                result.scanRecord?.serviceUuids?.forEach { uuids.put(it.toString()) }
+                val device = JSObject()
+                device.put("id", result.device.address)
+                device.put("name", result.scanRecord?.deviceName ?: result.device.name)
+                device.put("rssi", result.rssi)
+                device.put("serviceUuids", uuids)
+                args.channel.send(device)
+            }
+        }
+        scanner.startScan(filters, settings, callback)
+        scanCallback = callback
+        invoke.resolve()
+    }
+
+    @SuppressLint("MissingPermission")
+    @Command
+    fun stopScan(invoke: Invoke) {
+        scanCallback?.let { adapter?.bluetoothLeScanner?.stopScan(it) }
+        scanCallback = null
+        invoke.resolve()
+    }
+
+    @Command
+    fun connect(invoke: Invoke) {
+        if (granted()) {
+            openConnection(invoke)
+        } else {
+            requestPermissionForAlias(permissionAlias, invoke, "connectPermission")
+        }
+    }
+
+    @PermissionCallback
+    private fun connectPermission(invoke: Invoke) {
+        if (granted()) {
+            openConnection(invoke)
+        } else {
+            invoke.reject("Bluetooth permission denied", "PermissionDenied")
+        }
+    }
+
+    @SuppressLint("MissingPermission")
+    private fun openConnection(invoke: Invoke) {
+        val args = invoke.parseArgs(DeviceArgs::class.java)
+        val adapter = adapter
+        if (adapter == null) {
+            invoke.reject("Bluetooth is off or missing", "AdapterUnavailable")
+            return
+        }
+        if (!BluetoothAdapter.checkBluetoothAddress(args.deviceId)) {
+            invoke.reject(args.deviceId, "DeviceNotFound")
+            return
+        }
+        connections[args.deviceId]?.let { existing ->
+            if (existing.ready) {
+                invoke.resolve()
+                return
+            }
+        }
+        val connection = Connection(args.deviceId)
+        connection.begin(invoke)
+        connections[args.deviceId] = connection
+        connection.gatt = adapter.getRemoteDevice(args.deviceId)
+            .connectGatt(activity, false, connection, BluetoothDevice.TRANSPORT_LE)
+    }
+
+    @SuppressLint("MissingPermission")
+    @Command
+    fun disconnect(invoke: Invoke) {
+        val args = invoke.parseArgs(DeviceArgs::class.java)
+        connections.remove(args.deviceId)?.gatt?.let {
+            it.disconnect()
+            it.close()
+        }
+        invoke.resolve()
+    }
+
+    @SuppressLint("MissingPermission")
+    @Command
+    fun read(invoke: Invoke) {
+        val args = invoke.parseArgs(CharacteristicArgs::class.java)
+        val (connection, characteristic) = find(invoke, args) ?: return
+        if (!connection.begin(invoke)) return
+        if (connection.gatt?.readCharacteristic(characteristic) != true) {
+            connection.take()?.reject("read could not be started", "Failed")
+        }
+    }
+
+    @SuppressLint("MissingPermission")
+    @Command
+    fun write(invoke: Invoke) {
+        val args = invoke.parseArgs(CharacteristicArgs::class.java)
+        val (connection, characteristic) = find(invoke, args) ?: return
+        if (!connection.begin(invoke)) return
+        val value = ByteArray(args.value.size) { args.value[it].toByte() }
+        val writeType = if (args.withResponse) {
+            BluetoothGattCharacteristic.WRITE_TYPE_DEFAULT
+        } else {
+            BluetoothGattCharacteristic.WRITE_TYPE_NO_RESPONSE
+        }
+        val gatt = connection.gatt
+        val started = if (Build.VERSION.SDK_INT >= 33) {
+            gatt?.writeCharacteristic(characteristic, value, writeType) == BluetoothStatusCodes.SUCCESS
+        } else {
+            @Suppress("DEPRECATION")
+            characteristic.value = value
+            characteristic.writeType = writeType
+            @Suppress("DEPRECATION")
+            gatt?.writeCharacteristic(characteristic) == true
+        }
+        if (!started) {
+            connection.take()?.reject("write could not be started", "Failed")
+        }
+    }
+
+    private fun find(
+        invoke: Invoke,
+        args: CharacteristicArgs,
+    ): Pair<Connection, BluetoothGattCharacteristic>? {
+        val connection = connections[args.deviceId]?.takeIf { it.ready }
+        if (connection == null) {
+            invoke.reject("not connected to ${args.deviceId}", "ConnectionFailed")
+            return null
+        }
+        val characteristic = connection.gatt
+            ?.getService(UUID.fromString(args.service))
+            ?.getCharacteristic(UUID.fromString(args.characteristic))
+        if (characteristic == null) {
+            invoke.reject("characteristic not found", "CharacteristicNotFound")
+            return null
+        }
+        return connection to characteristic
+    }
+
+    /**
+     * One device's GATT link. Android allows a single outstanding GATT operation, so a
+     * connection holds at most one pending invoke, resolved from the callbacks.
+     */
+    private inner class Connection(private val deviceId: String) : BluetoothGattCallback() {
+        var gatt: BluetoothGatt? = null
+
+        @Volatile
+        var ready = false
+        private var pending: Invoke? = null
+
+        fun begin(invoke: Invoke): Boolean = synchronized(this) {
+            if (pending != null) {
+                invoke.reject("another operation is in progress", "Failed")
+                return false
+            }
+            pending = invoke
+            true
+        }
+
+        fun take(): Invoke? = synchronized(this) { pending.also { pending = null } }
+
+        private fun reject(status: Int) {
+            val code = when (status) {
+                BluetoothGatt.GATT_READ_NOT_PERMITTED,
+                BluetoothGatt.GATT_WRITE_NOT_PERMITTED,
+                BluetoothGatt.GATT_INSUFFICIENT_AUTHENTICATION,
+                BluetoothGatt.GATT_INSUFFICIENT_AUTHORIZATION,
+                BluetoothGatt.GATT_INSUFFICIENT_ENCRYPTION,
+                -> "PermissionDenied"
+                else -> "Failed"
+            }
+            take()?.reject("GATT error $status", code)
+        }
+
+        @SuppressLint("MissingPermission")
+        override fun onConnectionStateChange(gatt: BluetoothGatt, status: Int, newState: Int) {
+            when {
+                newState == BluetoothProfile.STATE_CONNECTED && status == BluetoothGatt.GATT_SUCCESS ->
+                    gatt.discoverServices()
+                newState == BluetoothProfile.STATE_DISCONNECTED -> {
+                    ready = false
+                    connections.remove(deviceId, this)
+                    gatt.close()
+                    take()?.reject("disconnected (status $status)", "ConnectionFailed")
+                }
+            }
+        }
+
+        @SuppressLint("MissingPermission")
+        override fun onServicesDiscovered(gatt: BluetoothGatt, status: Int) {
+            if (status == BluetoothGatt.GATT_SUCCESS) {
+                ready = true
+                take()?.resolve()
+            } else {
+                take()?.reject("service discovery failed (status $status)", "ConnectionFailed")
+                gatt.disconnect()
+            }
+        }
+
+        override fun onCharacteristicRead(
+            gatt: BluetoothGatt,
+            characteristic: BluetoothGattCharacteristic,
+            value: ByteArray,
+            status: Int,
+        ) = finishRead(value, status)
+
+        @Deprecated("Called instead of the overload taking the value before Android 13")
+        override fun onCharacteristicRead(
+            gatt: BluetoothGatt,
+            characteristic: BluetoothGattCharacteristic,
+            status: Int,
+        ) {
+            @Suppress("DEPRECATION")
+            finishRead(characteristic.value ?: ByteArray(0), status)
+        }
+
+        private fun finishRead(value: ByteArray, status: Int) {
+            if (status != BluetoothGatt.GATT_SUCCESS) {
+                reject(status)
+                return
+            }
+            val bytes = JSArray()
+            value.forEach { bytes.put(it.toInt() and 0xff) }
+            val ret = JSObject()
+            ret.put("value", bytes)
+            take()?.resolve(ret)
+        }
+
+        override fun onCharacteristicWrite(
+            gatt: BluetoothGatt,
+            characteristic: BluetoothGattCharacteristic,
+            status: Int,
+        ) {
+            if (status == BluetoothGatt.GATT_SUCCESS) {
+                take()?.resolve()
+            } else {
+                reject(status)
+            }
+        }
+    }
+}
@@ -0,0 +1,9 @@
+// The app invokes the native commands from Rust, so no IPC commands are exposed.
+const COMMANDS: &[&str] = &[];
+
+fn main() {
+    tauri_plugin::Builder::new(COMMANDS)
+        .android_path("android")
+        .ios_path("ios")
+        .build();
+}
@@ -0,0 +1,279 @@
+import CoreBluetooth
+import Tauri
+import WebKit
+
+class ScanArgs: Decodable {
+  /// Full lowercase UUIDs; empty reports every device.
+  let services: [String]
+  let channel: Channel
+}
+
+class DeviceArgs: Decodable {
+  let deviceId: String
+}
+
+class CharacteristicArgs: Decodable {
+  let deviceId: String
+  let service: String
+  let characteristic: String
+  /// Only for writes.
+  let value: [UInt8]?
+  let withResponse: Bool?
+}
+
+struct DiscoveredDevice: Encodable {
+  let id: String
+  let name: String?
+  let rssi: Int
+  let serviceUuids: [String]
+}
+
+/// Everything runs on `queue`, the central's delegate queue, so the maps below need no
+/// locking.
+class BlePlugin: Plugin, CBCentralManagerDelegate, CBPeripheralDelegate {
+  private let queue = DispatchQueue(label: "com.layers.ble")
+  private lazy var central = CBCentralManager(delegate: self, queue: queue)
+  /// Work waiting for the central to settle on a state after it's created.
+  private var waiting: [(Invoke, () -> Void)] = []
+  private var scanChannel: Channel?
+  private var peripherals: [UUID: CBPeripheral] = [:]
+  private var connecting: [UUID: Invoke] = [:]
+  /// Services still discovering characteristics, per connecting peripheral.
+  private var undiscovered: [UUID: Int] = [:]
+  /// Reads and writes with a response, by peripheral and characteristic.
+  private var operations: [String: Invoke] = [:]
+
+  private func whenPoweredOn(_ invoke: Invoke, _ work: @escaping () -> Void) {
+    queue.async {
+      switch self.central.state {
+      case .poweredOn:
+        work()
+      case .unauthorized:
+        invoke.reject("Bluetooth permission denied", code: "PermissionDenied")
+      case .unsupported, .poweredOff:
+        invoke.reject("Bluetooth is off or missing", code: "AdapterUnavailable")
+      default:
+        self.waiting.append((invoke, work))
+      }
+    }
+  }
+
+  func centralManagerDidUpdateState(_ central: CBCentralManager) {
+    guard central.state != .unknown, central.state != .resetting else { return }
+    let waiting = self.waiting
+    self.waiting = []
+    for (invoke, work) in waiting {
+      whenPoweredOn(invoke, work)
+    }
+  }
+
+  @objc public func startScan(_ invoke: Invoke) throws {
+    let args = try invoke.parseArgs(ScanArgs.self)
+    whenPoweredOn(invoke) {
+      self.central.stopScan()
+      self.scanChannel = args.channel
+      let services = args.services.isEmpty ? nil : args.services.map { CBUUID(string: $0) }
+      self.central.scanForPeripherals(
+        withServices: services, options: [CBCentralManagerScanOptionAllowDuplicatesKey: true])
+      invoke.resolve()
+    }
+  }
+
+  @objc public func stopScan(_ invoke: Invoke) throws {
+    queue.async {
+      if self.central.state == .poweredOn {
+        self.central.stopScan()
+      }
+      self.scanChannel = nil
+      invoke.resolve()
+    }
+  }
+
+  func centralManager(
+    _ central: CBCentralManager, didDiscover peripheral: CBPeripheral,
+    advertisementData: [String: Any], rssi RSSI: NSNumber
+  ) {
+    // 127 means the signal strength couldn't be read.
+    guard let channel = scanChannel, RSSI.intValue != 127 else { return }
+    peripherals[peripheral.identifier] = peripheral
+    let uuids = advertisementData[CBAdvertisementDataServiceUUIDsKey] as? [CBUUID] ?? []
+    try? channel.send(
+      DiscoveredDevice(
+        id: peripheral.identifier.uuidString,
+        name: advertisementData[CBAdvertisementDataLocalNameKey] as? String ?? peripheral.name,
+        rssi: RSSI.intValue,
+        serviceUuids: uuids.map { $0.uuidString }))
+  }
+
+  @objc public func connect(_ invoke: Invoke) throws {
+    let args = try invoke.parseArgs(DeviceArgs.self)
+    whenPoweredOn(invoke) {
+      guard let id = UUID(uuidString: args.deviceId),
+        let peripheral = self.peripherals[id]
+          ?? self.central.retrievePeripherals(withIdentifiers: [id]).first
+      else {
+        invoke.reject(args.deviceId, code: "DeviceNotFound")
+        return
+      }
+      if peripheral.state == .connected {
+        invoke.resolve()
+        return
+      }
+      self.peripherals[id] = peripheral
+      self.connecting[id] = invoke
+      peripheral.delegate = self
+      self.central.connect(peripheral)
+    }
+  }
+
+  func centralManager(_ central: CBCentralManager, didConnect peripheral: CBPeripheral) {
+    peripheral.discoverServices(nil)
+  }
+
+  func centralManager(
+    _ central: CBCentralManager, didFailToConnect peripheral: CBPeripheral, error: Error?
+  ) {
+    connecting.removeValue(forKey: peripheral.identifier)?.reject(
+      error?.localizedDescription ?? "connection failed", code: "ConnectionFailed")
+  }
+
+  func centralManager(
+    _ central: CBCentralManager, didDisconnectPeripheral peripheral: CBPeripheral, error: Error?
+  ) {
+    let message = error?.localizedDescription ?? "disconnected"
+    connecting.removeValue(forKey: peripheral.identifier)?.reject(message, code: "ConnectionFailed")
+    let prefix = "\(peripheral.identifier.uuidString)/"
+    for key in operations.keys where key.hasPrefix(prefix) {
+      operations.removeValue(forKey: key)?.reject(message, code: "ConnectionFailed")
+    }
+  }
+
+  func peripheral(_ peripheral: CBPeripheral, didDiscoverServices error: Error?) {
+    let services = peripheral.services ?? []
+    if let error = error {
+      connecting.removeValue(forKey: peripheral.identifier)?.reject(
+        error.localizedDescription, code: "ConnectionFailed")
+      central.cancelPeripheralConnection(peripheral)
+      return
+    }
+    if services.isEmpty {
+      connecting.removeValue(forKey: peripheral.identifier)?.resolve()
+      return
+    }
+    undiscovered[peripheral.identifier] = services.count
+    for service in services {
+      peripheral.discoverCharacteristics(nil, for: service)
+    }
+  }
+
+  func peripheral(
+    _ peripheral: CBPeripheral, didDiscoverCharacteristicsFor service: CBService, error: Error?
+  ) {
+    let remaining = (undiscovered[peripheral.identifier] ?? 1) - 1
+    undiscovered[peripheral.identifier] = remaining
+    if remaining <= 0 {
+      undiscovered.removeValue(forKey: peripheral.identifier)
+      connecting.removeValue(forKey: peripheral.identifier)?.resolve()
+    }
+  }
+
+  @objc public func disconnect(_ invoke: Invoke) throws {
+    let args = try invoke.parseArgs(DeviceArgs.self)
+    queue.async {
+      if let id = UUID(uuidString: args.deviceId), let peripheral = self.peripherals[id] {
+        self.central.cancelPeripheralConnection(peripheral)
+      }
+      invoke.resolve()
+    }
+  }
+
+  private func key(_ peripheral: CBPeripheral, _ characteristic: CBCharacteristic) -> String {
+    "\(peripheral.identifier.uuidString)/\(characteristic.uuid.uuidString)"
+  }
+
+  /// Runs `work` on the queue with the connected characteristic, or rejects.
+  private func withCharacteristic(
+    _ invoke: Invoke, _ args: CharacteristicArgs,
+    _ work: @escaping (CBPeripheral, CBCharacteristic) -> Void
+  ) {
+    queue.async {
+      guard let id = UUID(uuidString: args.deviceId), let peripheral = self.peripherals[id],
+        peripheral.state == .connected
+      else {
+        invoke.reject("not connected to \(args.deviceId)", code: "ConnectionFailed")
+        return
+      }
+      let service = CBUUID(string: args.service)
+      let uuid = CBUUID(string: args.characteristic)
+      guard
+        let characteristic = peripheral.services?.first(where: { $0.uuid == service })?
+          .characteristics?.first(where: { $0.uuid == uuid })
+      else {
+        invoke.reject("characteristic not found", code: "CharacteristicNotFound")
+        return
+      }
+      let key = self.key(peripheral, characteristic)
+      if self.operations[key] != nil {
+        invoke.reject("another operation is in progress", code: "Failed")
+        return
+      }
+      work(peripheral, characteristic)
+    }
+  }
+
+  @objc public func read(_ invoke: Invoke) throws {
+    let args = try invoke.parseArgs(CharacteristicArgs.self)
+    withCharacteristic(invoke, args) { peripheral, characteristic in
+      self.operations[self.key(peripheral, characteristic)] = invoke
+      peripheral.readValue(for: characteristic)
+    }
+  }
+
+  @objc public func write(_ invoke: Invoke) throws {
+    let args = try invoke.parseArgs(CharacteristicArgs.self)
+    withCharacteristic(invoke, args) { peripheral, characteristic in
+      let data = Data(args.value ?? [])
+      if args.withResponse ?? true {
+        self.operations[self.key(peripheral, characteristic)] = invoke
+        peripheral.writeValue(data, for: characteristic, type: .withResponse)
+      } else {
+        peripheral.writeValue(data, for: characteristic, type: .withoutResponse)
+        invoke.resolve()
+      }
+    }
+  }
+
+  private func finish(_ invoke: Invoke?, _ error: Error?, _ result: JsonObject = [:]) {
+    guard let invoke = invoke else { return }
+    if let error = error as? CBATTError,
+      [.readNotPermitted, .writeNotPermitted, .insufficientAuthentication,
+       .insufficientAuthorization, .insufficientEncryption].contains(error.code)
+    {
+      invoke.reject(error.localizedDescription, code: "PermissionDenied")
+    } else if let error = error {
+      invoke.reject(error.localizedDescription, code: "Failed")
+    } else {
+      invoke.resolve(result)
+    }
+  }
+
+  func peripheral(
+    _ peripheral: CBPeripheral, didUpdateValueFor characteristic: CBCharacteristic, error: Error?
+  ) {
+    // Notifications arrive here too, with nothing waiting on them.
+    let invoke = operations.removeValue(forKey: key(peripheral, characteristic))
+    let bytes = [UInt8](characteristic.value ?? Data())
+    finish(invoke, error, ["value": bytes.map { Int($0) }])
+  }
+
+  func peripheral(
+    _ peripheral: CBPeripheral, didWriteValueFor characteristic: CBCharacteristic, error: Error?
+  ) {
+    finish(operations.removeValue(forKey: key(peripheral, characteristic)), error)
+  }
+}
+
+@_cdecl("init_plugin_ble")
+func initPlugin() -> Plugin {
+  return BlePlugin()
+}
@@ -0,0 +1,227 @@
+package com.layers.calendar
+
+import android.Manifest
+import android.app.Activity
+import android.content.ContentUris
+import android.content.ContentValues
+import android.provider.CalendarContract
+import android.provider.CalendarContract.Calendars
+import android.provider.CalendarContract.Events
+import android.provider.CalendarContract.Instances
+import app.tauri.PermissionState
+import app.tauri.annotation.Command
+import app.tauri.annotation.InvokeArg
+import app.tauri.annotation.Permission
+import app.tauri.annotation.PermissionCallback
+import app.tauri.annotation.TauriPlugin
+import app.tauri.plugin.Invoke
+import app.tauri.plugin.JSArray
+import app.tauri.plugin.JSObject
+import app.tauri.plugin.Plugin
+import java.util.TimeZone
+
+@InvokeArg
+class QueryArgs {
+    lateinit var calendarId: String
+    var startMs: Long = 0
+    var endMs: Long = 0
+}
+
+@InvokeArg
+class NewEventArgs {
+    /** The primary writable calendar when null. */
+    var calendarId: String? = null
+    var title: String = ""
+    var startMs: Long = 0
+    var endMs: Long = 0
+    var location: String? = null
+    var notes: String? = null
+    var allDay: Boolean = false
+}
+
+@TauriPlugin(
+    permissions = [
+        Permission(
+            strings = [Manifest.permission.READ_CALENDAR, Manifest.permission.WRITE_CALENDAR],
+            alias = "calendar",
+        ),
+    ],
+)
+class CalendarPlugin(private val activity: Activity) : Plugin(activity) {
+    private val granted: Boolean
+        get() = getPermissionState("calendar") == PermissionState.GRANTED
+
+    @Command
+    fun requestPermission(invoke: Invoke) {
+        if (granted) {
+            resolvePermission(invoke)
+        } else {
+            requestPermissionForAlias("calendar", invoke, "calendarPermission")
+        }
+    }
+
+    @PermissionCallback
+    private fun calendarPermission(invoke: Invoke) {
+        resolvePermission(invoke)
+    }
+
+    private fun resolvePermission(invoke: Invoke) {
+        val ret = JSObject()
+        ret.put("calendar", getPermissionState("calendar").toString())
+        invoke.resolve(ret)
+    }
+
+    /** Visible calendars only; hidden ones are synced but turned off in the calendar app. */
+    @Command
+    fun listCalendars(invoke: Invoke) {
+        if (!granted) {
+            invoke.reject("calendar permission denied", "PermissionDenied")
+            return
+        }
+        val projection = arrayOf(
+            Calendars._ID,
+            Calendars.CALENDAR_DISPLAY_NAME,
+            Calendars.CALENDAR_COLOR,
+            Calendars.CALENDAR_ACCESS_LEVEL,
+            Calendars.IS_PRIMARY,
+            Calendars.ACCOUNT_NAME,
+        )
+        try {
+            val list = JSArray()
+            activity.contentResolver.query(
+                Calendars.CONTENT_URI, projection, "${Calendars.VISIBLE} = 1", null, null,
+            )?.use { cursor ->
+                while (cursor.moveToNext()) {
+                    list.put(
+                        JSObject().apply {
+                            put("id", cursor.getLong(0).toString())
+                            put("title", cursor.getString(1) ?: "")
+                            put("color", String.format("#%06X", cursor.getInt(2) and 0xFFFFFF))
+                            put("writable", cursor.getInt(3) >= Calendars.CAL_ACCESS_CONTRIBUTOR)
+                            put("primary", cursor.getInt(4) == 1)
+                            cursor.getString(5)?.let { put("account", it) }
+                        },
+                    )
+                }
+            }
+            val ret = JSObject()
+            ret.put("calendars", list)
+            invoke.resolve(ret)
+        } catch (e: Exception) {
+            invoke.reject(e.message ?: "calendar query failed", "Failed")
+        }
+    }
+
+    /**
+     * One entry per occurrence, so a recurring event appears once for each time it falls in
+     * the range, every time with the id of the event itself.
+     */
+    @Command
+    fun queryEvents(invoke: Invoke) {
+        if (!granted) {
+            invoke.reject("calendar permission denied", "PermissionDenied")
+            return
+        }
+        val args = invoke.parseArgs(QueryArgs::class.java)
+        val uri = Instances.CONTENT_URI.buildUpon().also {
+            ContentUris.appendId(it, args.startMs)
+            ContentUris.appendId(it, args.endMs)
+        }.build()
+        val projection = arrayOf(
+            Instances.EVENT_ID,
+            Instances.TITLE,
+            Instances.BEGIN,
+            Instances.END,
+            Instances.EVENT_LOCATION,
+            Instances.DESCRIPTION,
+            Instances.ALL_DAY,
+        )
+        try {
+            if (!calendarExists(args.calendarId)) {
+                invoke.reject("no calendar with id ${args.calendarId}", "NotFound")
+                return
+            }
+            val list = JSArray()
+            activity.contentResolver.query(
+                uri, projection, "${Instances.CALENDAR_ID} = ?", arrayOf(args.calendarId),
+                "${Instances.BEGIN} ASC",
+            )?.use { cursor ->
+                while (cursor.moveToNext()) {
+                    list.put(
+                        JSObject().apply {
+                            put("id", cursor.getLong(0).toString())
+                            put("title", cursor.getString(1) ?: "")
+                            put("startMs", cursor.getLong(2))
+                            put("endMs", cursor.getLong(3))
+                            cursor.getString(4)?.takeIf { it.isNotEmpty() }?.let { put("location", it) }
+                            cursor.getString(5)?.takeIf { it.isNotEmpty() }?.let { put("notes", it) }
+                            put("allDay", cursor.getInt(6) == 1)
+                        },
+                    )
+                }
+            }
+            val ret = JSObject()
+            ret.put("events", list)
+            invoke.resolve(ret)
+        } catch (e: Exception) {
+            invoke.reject(e.message ?: "calendar query failed", "Failed")
+        }
+    }
+
+    private fun calendarExists(id: String): Boolean {
+        val calendarId = id.toLongOrNull() ?: return false
+        return activity.contentResolver.query(
+            ContentUris.withAppendedId(Calendars.CONTENT_URI, calendarId),
+            arrayOf(Calendars._ID), null, null, null,
+        )?.use { it.moveToFirst() } ?: false
+    }
+
+    /** The primary calendar if it accepts events, otherwise the first one that does. */
+    private fun defaultCalendar(): Long? = activity.contentResolver.query(
+        Calendars.CONTENT_URI,
+        arrayOf(Calendars._ID),
+        "${Calendars.VISIBLE} = 1 AND ${Calendars.CALENDAR_ACCESS_LEVEL} >= ?",
+        arrayOf(Calendars.CAL_ACCESS_CONTRIBUTOR.toString()),
+        "${Calendars.IS_PRIMARY} DESC",
+    )?.use { cursor -> if (cursor.moveToFirst()) cursor.getLong(0) else null }
+
+    @Command
+    fun createEvent(invoke: Invoke) {
+        if (!granted) {
+            invoke.reject("calendar permission denied", "PermissionDenied")
+            return
+        }
+        val args = invoke.parseArgs(NewEventArgs::class.java)
+        try {
+            val calendarId = when (val id = args.calendarId) {
+                null -> defaultCalendar() ?: run {
+                    invoke.reject("there is no calendar that accepts new events", "NotFound")
+                    return
+                }
+                else -> id.toLongOrNull()?.takeIf { calendarExists(id) } ?: run {
+                    invoke.reject("no calendar with id $id", "NotFound")
+                    return
+                }
+            }
+            val values = ContentValues().apply {
+                put(Events.CALENDAR_ID, calendarId)
+                put(Events.TITLE, args.title)
+                put(Events.DTSTART, args.startMs)
+                put(Events.DTEND, args.endMs)
+                args.location?.let { put(Events.EVENT_LOCATION, it) }
+                args.notes?.let { put(Events.DESCRIPTION, it) }
+                put(Events.ALL_DAY, if (args.allDay) 1 else 0)
+                // The provider requires all-day events to be midnight to midnight in UTC.
+                put(Events.EVENT_TIMEZONE, if (args.allDay) "UTC" else TimeZone.getDefault().id)
+            }
+            val uri = activity.contentResolver.insert(Events.CONTENT_URI, values)
+                ?: throw IllegalStateException("the calendar provider did not save the event")
+            val ret = JSObject()
+            ret.put("id", ContentUris.parseId(uri).toString())
+            ret.put("calendarId", calendarId.toString())
+            invoke.resolve(ret)
+        } catch (e: Exception) {
+            invoke.reject(e.message ?: "saving the event failed", "Failed")
+        }
+    }
+}
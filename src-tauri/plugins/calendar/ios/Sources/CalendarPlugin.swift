@@ -0,0 +1,198 @@
+import EventKit
+import Tauri
+import UIKit
+import WebKit
+
+class QueryArgs: Decodable {
+  let calendarId: String
+  let startMs: Double
+  let endMs: Double
+}
+
+class NewEventArgs: Decodable {
+  /// The default calendar for new events when nil.
+  let calendarId: String?
+  let title: String
+  let startMs: Double
+  let endMs: Double
+  let location: String?
+  let notes: String?
+  let allDay: Bool
+}
+
+/// All-day events cross the bridge as UTC midnights, as on Android, while EventKit keeps
+/// them as local dates; these move a day boundary between the two.
+private func utcMidnight(_ date: Date) -> Double {
+  let days = Calendar.current.dateComponents([.year, .month, .day], from: date)
+  var utc = Calendar(identifier: .gregorian)
+  utc.timeZone = TimeZone(identifier: "UTC")!
+  return (utc.date(from: days) ?? date).timeIntervalSince1970 * 1000
+}
+
+private func localMidnight(_ ms: Double) -> Date {
+  var utc = Calendar(identifier: .gregorian)
+  utc.timeZone = TimeZone(identifier: "UTC")!
+  let days = utc.dateComponents(
+    [.year, .month, .day], from: Date(timeIntervalSince1970: ms / 1000))
+  return Calendar.current.date(from: days) ?? Date(timeIntervalSince1970: ms / 1000)
+}
+
+private func hex(_ color: CGColor) -> String? {
+  guard let rgb = color.converted(
+    to: CGColorSpace(name: CGColorSpace.sRGB)!, intent: .defaultIntent, options: nil),
+    let components = rgb.components, components.count >= 3
+  else { return nil }
+  return String(
+    format: "#%02X%02X%02X", Int(components[0] * 255), Int(components[1] * 255),
+    Int(components[2] * 255))
+}
+
+class CalendarPlugin: Plugin {
+  private let store = EKEventStore()
+
+  private func state(_ status: EKAuthorizationStatus) -> String {
+    switch status {
+    case .notDetermined: return "prompt"
+    case .denied, .restricted: return "denied"
+    // iOS 17's write-only access can't read calendars or events.
+    case .writeOnly: return "denied"
+    // `.authorized` before iOS 17, `.fullAccess` from it.
+    default: return "granted"
+    }
+  }
+
+  private var granted: Bool {
+    state(EKEventStore.authorizationStatus(for: .event)) == "granted"
+  }
+
+  @objc public func requestPermission(_ invoke: Invoke) throws {
+    let status = EKEventStore.authorizationStatus(for: .event)
+    guard status == .notDetermined else {
+      invoke.resolve(["calendar": state(status)])
+      return
+    }
+    let completion: EKEventStoreRequestAccessCompletionHandler = { granted, _ in
+      invoke.resolve(["calendar": granted ? "granted" : "denied"])
+    }
+    if #available(iOS 17.0, *) {
+      store.requestFullAccessToEvents(completion: completion)
+    } else {
+      store.requestAccess(to: .event, completion: completion)
+    }
+  }
+
+  @objc public func listCalendars(_ invoke: Invoke) throws {
+    guard granted else {
+      invoke.reject("calendar permission denied", code: "PermissionDenied")
+      return
+    }
+    let primary = store.defaultCalendarForNewEvents?.calendarIdentifier
+    let calendars = store.calendars(for: .event).map { calendar -> [String: Any] in
+      var object: [String: Any] = [
+        "id": calendar.calendarIdentifier,
+        "title": calendar.title,
+        "writable": calendar.allowsContentModifications,
+        "primary": calendar.calendarIdentifier == primary,
+        "account": calendar.source.title,
+      ]
+      if let color = hex(calendar.cgColor) {
+        object["color"] = color
+      }
+      return object
+    }
+    invoke.resolve(["calendars": calendars])
+  }
+
+  /// Recurring events appear once per occurrence, each with the event's own identifier.
+  @objc public func queryEvents(_ invoke: Invoke) throws {
+    let args = try invoke.parseArgs(QueryArgs.self)
+    guard granted else {
+      invoke.reject("calendar permission denied", code: "PermissionDenied")
+      return
+    }
+    guard let calendar = store.calendar(withIdentifier: args.calendarId) else {
+      invoke.reject("no calendar with id \(args.calendarId)", code: "NotFound")
+      return
+    }
+    // Matching blocks, and a synced calendar may have many events in range.
+    DispatchQueue.global(qos: .userInitiated).async {
+      let predicate = self.store.predicateForEvents(
+        withStart: Date(timeIntervalSince1970: args.startMs / 1000),
+        end: Date(timeIntervalSince1970: args.endMs / 1000),
+        calendars: [calendar])
+      let events = self.store.events(matching: predicate).map(self.serialize)
+      invoke.resolve(["events": events])
+    }
+  }
+
+  private func serialize(_ event: EKEvent) -> [String: Any] {
+    var object: [String: Any] = [
+      "id": event.eventIdentifier ?? "",
+      "title": event.title ?? "",
+      "allDay": event.isAllDay,
+    ]
+    if event.isAllDay {
+      object["startMs"] = utcMidnight(event.startDate)
+      // EventKit ends all-day events at 23:59:59 on their last day.
+      object["endMs"] = utcMidnight(event.endDate.addingTimeInterval(1))
+    } else {
+      object["startMs"] = event.startDate.timeIntervalSince1970 * 1000
+      object["endMs"] = event.endDate.timeIntervalSince1970 * 1000
+    }
+    if let location = event.location, !location.isEmpty {
+      object["location"] = location
+    }
+    if let notes = event.notes, !notes.isEmpty {
+      object["notes"] = notes
+    }
+    return object
+  }
+
+  @objc public func createEvent(_ invoke: Invoke) throws {
+    let args = try invoke.parseArgs(NewEventArgs.self)
+    guard granted else {
+      invoke.reject("calendar permission denied", code: "PermissionDenied")
+      return
+    }
+    let calendar: EKCalendar
+    if let id = args.calendarId {
+      guard let found = store.calendar(withIdentifier: id) else {
+        invoke.reject("no calendar with id \(id)", code: "NotFound")
+        return
+      }
+      calendar = found
+    } else {
+      guard let found = store.defaultCalendarForNewEvents else {
+        invoke.reject("there is no calendar that accepts new events", code: "NotFound")
+        return
+      }
+      calendar = found
+    }
+    let event = EKEvent(eventStore: store)
+    event.calendar = calendar
+    event.title = args.title
+    event.isAllDay = args.allDay
+    if args.allDay {
+      event.startDate = localMidnight(args.startMs)
+      event.endDate = localMidnight(args.endMs).addingTimeInterval(-1)
+    } else {
+      event.startDate = Date(timeIntervalSince1970: args.startMs / 1000)
+      event.endDate = Date(timeIntervalSince1970: args.endMs / 1000)
+    }
+    event.location = args.location
+    event.notes = args.notes
+    do {
+      try store.save(event, span: .thisEvent, commit: true)
+      invoke.resolve([
+        "id": event.eventIdentifier ?? "", "calendarId": calendar.calendarIdentifier,
+      ])
+    } catch {
+      invoke.reject(error.localizedDescription, code: "Failed")
+    }
+  }
+}
+
+@_cdecl("init_plugin_calendar")
+func initPlugin() -> Plugin {
+  return CalendarPlugin()
+}
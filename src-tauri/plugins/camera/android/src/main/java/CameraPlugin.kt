@@ -0,0 +1,341 @@
+package com.layers.camera
+
+import android.Manifest
+import android.app.Activity
+import android.content.ContentValues
+import android.content.Intent
+import android.content.pm.PackageManager
+import android.graphics.Bitmap
+import android.graphics.BitmapFactory
+import android.graphics.Matrix
+import android.media.MediaMetadataRetriever
+import android.net.Uri
+import android.os.Build
+import android.os.Environment
+import android.provider.MediaStore
+import android.webkit.MimeTypeMap
+import androidx.activity.result.ActivityResult
+import androidx.core.content.FileProvider
+import androidx.exifinterface.media.ExifInterface
+import app.tauri.PermissionState
+import app.tauri.annotation.ActivityCallback
+import app.tauri.annotation.Command
+import app.tauri.annotation.InvokeArg
+import app.tauri.annotation.Permission
+import app.tauri.annotation.PermissionCallback
+import app.tauri.annotation.TauriPlugin
+import app.tauri.plugin.Invoke
+import app.tauri.plugin.JSObject
+import app.tauri.plugin.Plugin
+import java.io.File
+import java.io.IOException
+import java.io.InputStream
+import java.util.UUID
+import kotlin.concurrent.thread
+
+@InvokeArg
+class PhotoArgs {
+    var quality: Int = 90
+    var maxWidth: Int? = null
+    var maxHeight: Int? = null
+    var saveToGallery: Boolean = false
+    /** `camera` or `photoLibrary`. */
+    var source: String = "camera"
+}
+
+@InvokeArg
+class VideoArgs {
+    var maxDurationSecs: Int? = null
+    /** `low`, `medium` or `high`; capture intents only know low and high. */
+    var quality: String = "high"
+    var saveToGallery: Boolean = false
+    var source: String = "camera"
+}
+
+/** The largest scale, at most 1, at which an upright `width` x `height` image fits the limits. */
+private fun fitScale(width: Int, height: Int, maxWidth: Int?, maxHeight: Int?): Float {
+    var scale = 1f
+    if (maxWidth != null && width > maxWidth) scale = minOf(scale, maxWidth.toFloat() / width)
+    if (maxHeight != null && height > maxHeight) scale = minOf(scale, maxHeight.toFloat() / height)
+    return scale
+}
+
+/** Never a full-screen UI of its own: captures go through the system camera app, picks through the photo picker. */
+@TauriPlugin(
+    permissions = [
+        Permission(strings = [Manifest.permission.CAMERA], alias = "camera"),
+        Permission(strings = [Manifest.permission.WRITE_EXTERNAL_STORAGE], alias = "storage"),
+    ],
+)
+class CameraPlugin(private val activity: Activity) : Plugin(activity) {
+    /** Where the camera app was asked to write the capture in progress. */
+    private var captureFile: File? = null
+
+    private val dir: File
+        get() = File(activity.cacheDir, "camera").apply { mkdirs() }
+
+    private fun newFile(extension: String) = File(dir, "${UUID.randomUUID()}.$extension")
+
+    /** Aliases still to ask for: the camera for captures, storage for the gallery before Android 10. */
+    private fun missing(camera: Boolean, gallery: Boolean): Array<String> = listOfNotNull(
+        "camera".takeIf { camera },
+        "storage".takeIf { gallery && Build.VERSION.SDK_INT < Build.VERSION_CODES.Q },
+    ).filter { getPermissionState(it) != PermissionState.GRANTED }.toTypedArray()
+
+    @Command
+    fun takePhoto(invoke: Invoke) {
+        val args = invoke.parseArgs(PhotoArgs::class.java)
+        val missing = missing(args.source == "camera", args.saveToGallery)
+        if (missing.isEmpty()) startPhoto(invoke, args) else requestPermissionForAliases(missing, invoke, "photoPermission")
+    }
+
+    @PermissionCallback
+    private fun photoPermission(invoke: Invoke) {
+        val args = invoke.parseArgs(PhotoArgs::class.java)
+        if (missing(args.source == "camera", args.saveToGallery).isEmpty()) {
+            startPhoto(invoke, args)
+        } else {
+            invoke.reject("camera or storage permission denied", "PermissionDenied")
+        }
+    }
+
+    @Command
+    fun recordVideo(invoke: Invoke) {
+        val args = invoke.parseArgs(VideoArgs::class.java)
+        val missing = missing(args.source == "camera", args.saveToGallery)
+        if (missing.isEmpty()) startVideo(invoke, args) else requestPermissionForAliases(missing, invoke, "videoPermission")
+    }
+
+    @PermissionCallback
+    private fun videoPermission(invoke: Invoke) {
+        val args = invoke.parseArgs(VideoArgs::class.java)
+        if (missing(args.source == "camera", args.saveToGallery).isEmpty()) {
+            startVideo(invoke, args)
+        } else {
+            invoke.reject("camera or storage permission denied", "PermissionDenied")
+        }
+    }
+
+    private fun picker(mime: String): Intent =
+        if (Build.VERSION.SDK_INT >= Build.VERSION_CODES.TIRAMISU) {
+            Intent(MediaStore.ACTION_PICK_IMAGES).setType(mime)
+        } else {
+            Intent(Intent.ACTION_GET_CONTENT).setType(mime).addCategory(Intent.CATEGORY_OPENABLE)
+        }
+
+    /** A capture intent writing to a new cache file, or null if there's no camera app to take it. */
+    private fun capture(action: String, extension: String, invoke: Invoke): Intent? {
+        if (!activity.packageManager.hasSystemFeature(PackageManager.FEATURE_CAMERA_ANY)) {
+            invoke.reject("this device has no camera", "SourceUnavailable")
+            return null
+        }
+        val file = newFile(extension)
+        val uri = FileProvider.getUriForFile(activity, "${activity.packageName}.camera.fileprovider", file)
+        val intent = Intent(action)
+            .putExtra(MediaStore.EXTRA_OUTPUT, uri)
+            .addFlags(Intent.FLAG_GRANT_WRITE_URI_PERMISSION or Intent.FLAG_GRANT_READ_URI_PERMISSION)
+        if (intent.resolveActivity(activity.packageManager) == null) {
+            invoke.reject("no camera app is installed", "SourceUnavailable")
+            return null
+        }
+        captureFile = file
+        return intent
+    }
+
+    private fun startPhoto(invoke: Invoke, args: PhotoArgs) {
+        if (args.source == "photoLibrary") {
+            startActivityForResult(invoke, picker("image/*"), "photoPicked")
+            return
+        }
+        val intent = capture(MediaStore.ACTION_IMAGE_CAPTURE, "jpg", invoke) ?: return
+        startActivityForResult(invoke, intent, "photoTaken")
+    }
+
+    private fun startVideo(invoke: Invoke, args: VideoArgs) {
+        if (args.source == "photoLibrary") {
+            startActivityForResult(invoke, picker("video/*"), "videoPicked")
+            return
+        }
+        val intent = capture(MediaStore.ACTION_VIDEO_CAPTURE, "mp4", invoke) ?: return
+        args.maxDurationSecs?.let { intent.putExtra(MediaStore.EXTRA_DURATION_LIMIT, it) }
+        intent.putExtra(MediaStore.EXTRA_VIDEO_QUALITY, if (args.quality == "low") 0 else 1)
+        startActivityForResult(invoke, intent, "videoRecorded")
+    }
+
+    @ActivityCallback
+    private fun photoTaken(invoke: Invoke, result: ActivityResult) {
+        val file = captureFile
+        captureFile = null
+        if (result.resultCode != Activity.RESULT_OK || file == null || file.length() == 0L) {
+            file?.delete()
+            invoke.reject("capture cancelled", "Cancelled")
+            return
+        }
+        // Re-encoded below, so the camera app's original isn't kept.
+        finishPhoto(invoke, { file.inputStream() }, { file.delete() })
+    }
+
+    @ActivityCallback
+    private fun photoPicked(invoke: Invoke, result: ActivityResult) {
+        val uri = result.data?.data
+        if (result.resultCode != Activity.RESULT_OK || uri == null) {
+            invoke.reject("capture cancelled", "Cancelled")
+            return
+        }
+        finishPhoto(invoke, { activity.contentResolver.openInputStream(uri) }, {})
+    }
+
+    @ActivityCallback
+    private fun videoRecorded(invoke: Invoke, result: ActivityResult) {
+        val file = captureFile
+        captureFile = null
+        if (result.resultCode != Activity.RESULT_OK || file == null) {
+            file?.delete()
+            invoke.reject("capture cancelled", "Cancelled")
+            return
+        }
+        val returned = result.data?.data
+        thread {
+            try {
+                // Some camera apps ignore EXTRA_OUTPUT for video and return their own URI.
+                if (file.length() == 0L && returned != null) {
+                    copy(returned, file)
+                }
+                if (file.length() == 0L) {
+                    file.delete()
+                    invoke.reject("the camera app returned no video", "Failed")
+                    return@thread
+                }
+                finishVideo(invoke, file, "video/mp4")
+            } catch (e: Exception) {
+                invoke.reject(e.message ?: "recording failed", "Failed")
+            }
+        }
+    }
+
+    @ActivityCallback
+    private fun videoPicked(invoke: Invoke, result: ActivityResult) {
+        val uri = result.data?.data
+        if (result.resultCode != Activity.RESULT_OK || uri == null) {
+            invoke.reject("capture cancelled", "Cancelled")
+            return
+        }
+        thread {
+            try {
+                val mime = activity.contentResolver.getType(uri) ?: "video/mp4"
+                val extension = MimeTypeMap.getSingleton().getExtensionFromMimeType(mime) ?: "mp4"
+                val file = newFile(extension)
+                copy(uri, file)
+                finishVideo(invoke, file, mime)
+            } catch (e: Exception) {
+                invoke.reject(e.message ?: "reading the video failed", "Failed")
+            }
+        }
+    }
+
+    private fun copy(uri: Uri, to: File) {
+        val input = activity.contentResolver.openInputStream(uri) ?: throw IOException("can't open $uri")
+        input.use { from -> to.outputStream().use { from.copyTo(it) } }
+    }
+
+    /** Decodes at the smallest power-of-two sample that still covers the target size. */
+    private fun finishPhoto(invoke: Invoke, open: () -> InputStream?, cleanup: () -> Unit) {
+        val args = invoke.parseArgs(PhotoArgs::class.java)
+        // Decoding a full-size photo takes a while.
+        thread {
+            try {
+                val bounds = BitmapFactory.Options().apply { inJustDecodeBounds = true }
+                open()?.use { BitmapFactory.decodeStream(it, null, bounds) }
+                if (bounds.outWidth <= 0 || bounds.outHeight <= 0) {
+                    invoke.reject("not an image", "Failed")
+                    return@thread
+                }
+                val degrees = open()?.use { ExifInterface(it).rotationDegrees } ?: 0
+                val sideways = degrees == 90 || degrees == 270
+                val width = if (sideways) bounds.outHeight else bounds.outWidth
+                val height = if (sideways) bounds.outWidth else bounds.outHeight
+                val scale = fitScale(width, height, args.maxWidth, args.maxHeight)
+
+                var sample = 1
+                while (scale * 2 * sample <= 1f) sample *= 2
+                val decoded = open()?.use {
+                    BitmapFactory.decodeStream(it, null, BitmapFactory.Options().apply { inSampleSize = sample })
+                } ?: throw IOException("can't decode the image")
+                val matrix = Matrix().apply {
+                    val remaining = scale * sample
+                    postScale(remaining, remaining)
+                    postRotate(degrees.toFloat())
+                }
+                val bitmap = Bitmap.createBitmap(decoded, 0, 0, decoded.width, decoded.height, matrix, true)
+
+                val file = newFile("jpg")
+                file.outputStream().use { bitmap.compress(Bitmap.CompressFormat.JPEG, args.quality.coerceIn(0, 100), it) }
+                cleanup()
+                if (args.saveToGallery) saveToGallery(file, "image/jpeg", video = false)
+                invoke.resolve(media(file, bitmap.width, bitmap.height, "image/jpeg"))
+            } catch (e: Exception) {
+                invoke.reject(e.message ?: "processing the photo failed", "Failed")
+            }
+        }
+    }
+
+    private fun finishVideo(invoke: Invoke, file: File, mime: String) {
+        val args = invoke.parseArgs(VideoArgs::class.java)
+        val retriever = MediaMetadataRetriever()
+        val (width, height) = try {
+            retriever.setDataSource(file.path)
+            fun int(key: Int) = retriever.extractMetadata(key)?.toIntOrNull() ?: 0
+            val w = int(MediaMetadataRetriever.METADATA_KEY_VIDEO_WIDTH)
+            val h = int(MediaMetadataRetriever.METADATA_KEY_VIDEO_HEIGHT)
+            val rotation = int(MediaMetadataRetriever.METADATA_KEY_VIDEO_ROTATION)
+            if (rotation == 90 || rotation == 270) h to w else w to h
+        } finally {
+            retriever.release()
+        }
+        if (args.saveToGallery) saveToGallery(file, mime, video = true)
+        invoke.resolve(media(file, width, height, mime))
+    }
+
+    private fun media(file: File, width: Int, height: Int, mime: String) = JSObject().apply {
+        put("path", file.absolutePath)
+        put("width", width)
+        put("height", height)
+        put("sizeBytes", file.length())
+        put("mimeType", mime)
+    }
+
+    /** Into `Pictures/Layers` or `Movies/Layers`. */
+    private fun saveToGallery(file: File, mime: String, video: Boolean) {
+        val directory = if (video) Environment.DIRECTORY_MOVIES else Environment.DIRECTORY_PICTURES
+        val collection = if (video) {
+            MediaStore.Video.Media.EXTERNAL_CONTENT_URI
+        } else {
+            MediaStore.Images.Media.EXTERNAL_CONTENT_URI
+        }
+        val resolver = activity.contentResolver
+        val values = ContentValues().apply {
+            put(MediaStore.MediaColumns.DISPLAY_NAME, file.name)
+            put(MediaStore.MediaColumns.MIME_TYPE, mime)
+        }
+        if (Build.VERSION.SDK_INT >= Build.VERSION_CODES.Q) {
+            values.put(MediaStore.MediaColumns.RELATIVE_PATH, "$directory/Layers")
+            values.put(MediaStore.MediaColumns.IS_PENDING, 1)
+            val uri = resolver.insert(collection, values) ?: throw IOException("the gallery refused the file")
+            resolver.openOutputStream(uri)?.use { out -> file.inputStream().use { it.copyTo(out) } }
+            values.clear()
+            values.put(MediaStore.MediaColumns.IS_PENDING, 0)
+            resolver.update(uri, values, null, null)
+        } else {
+            // Before scoped storage the gallery indexes files where they lie.
+            val target = File(File(Environment.getExternalStoragePublicDirectory(directory), "Layers"), file.name)
+            target.parentFile?.mkdirs()
+            file.copyTo(target, overwrite = true)
+            @Suppress("DEPRECATION")
+            values.put(MediaStore.MediaColumns.DATA, target.absolutePath)
+            resolver.insert(collection, values) ?: throw IOException("the gallery refused the file")
+        }
+    }
+}
+
+/** Its own subclass, so the manifest entry can't clash with another library's provider. */
+class CameraFileProvider : FileProvider()
@@ -0,0 +1,29 @@
+// swift-tools-version:5.9
+
+import PackageDescription
+
+let package = Package(
+    name: "tauri-plugin-camera",
+    platforms: [
+        .iOS(.v14),
+        .macOS(.v10_13),
+    ],
+    products: [
+        .library(
+            name: "tauri-plugin-camera",
+            type: .static,
+            targets: ["tauri-plugin-camera"]),
+    ],
+    dependencies: [
+        // Copied next to this package by the plugin's build script.
+        .package(name: "Tauri", path: "../.tauri/tauri-api"),
+    ],
+    targets: [
+        .target(
+            name: "tauri-plugin-camera",
+            dependencies: [
+                .byName(name: "Tauri"),
+            ],
+            path: "Sources"),
+    ]
+)
@@ -0,0 +1,256 @@
+import AVFoundation
+import MobileCoreServices
+import Photos
+import Tauri
+import UIKit
+import WebKit
+
+class PhotoArgs: Decodable {
+  let quality: Int
+  let maxWidth: CGFloat?
+  let maxHeight: CGFloat?
+  let saveToGallery: Bool
+  /// `camera` or `photoLibrary`.
+  let source: String
+}
+
+class VideoArgs: Decodable {
+  let maxDurationSecs: Double?
+  /// `low`, `medium` or `high`.
+  let quality: String
+  let saveToGallery: Bool
+  let source: String
+}
+
+struct CapturedMedia: Encodable {
+  let path: String
+  let width: Int
+  let height: Int
+  let sizeBytes: UInt64
+  let mimeType: String
+}
+
+private func newFile(_ ext: String) throws -> URL {
+  let dir = FileManager.default.urls(for: .cachesDirectory, in: .userDomainMask)[0]
+    .appendingPathComponent("camera", isDirectory: true)
+  try FileManager.default.createDirectory(at: dir, withIntermediateDirectories: true)
+  return dir.appendingPathComponent("\(UUID().uuidString).\(ext)")
+}
+
+private func fileSize(_ url: URL) -> UInt64 {
+  let attributes = try? FileManager.default.attributesOfItem(atPath: url.path)
+  return (attributes?[.size] as? NSNumber)?.uint64Value ?? 0
+}
+
+/// Draws the image upright, scaled down to fit the limits; only actual pixels are counted,
+/// so the result has a scale of 1.
+private func fitted(_ image: UIImage, maxWidth: CGFloat?, maxHeight: CGFloat?) -> UIImage {
+  let width = image.size.width * image.scale
+  let height = image.size.height * image.scale
+  var scale: CGFloat = 1
+  if let maxWidth = maxWidth, width > maxWidth { scale = min(scale, maxWidth / width) }
+  if let maxHeight = maxHeight, height > maxHeight { scale = min(scale, maxHeight / height) }
+  let size = CGSize(width: (width * scale).rounded(), height: (height * scale).rounded())
+  let format = UIGraphicsImageRendererFormat.default()
+  format.scale = 1
+  return UIGraphicsImageRenderer(size: size, format: format).image { _ in
+    image.draw(in: CGRect(origin: .zero, size: size))
+  }
+}
+
+/// The picker's delegate, alive until it finishes.
+private class PickerDelegate: NSObject, UIImagePickerControllerDelegate,
+  UINavigationControllerDelegate
+{
+  var onFinish: (([UIImagePickerController.InfoKey: Any]?) -> Void)?
+
+  func imagePickerController(
+    _ picker: UIImagePickerController,
+    didFinishPickingMediaWithInfo info: [UIImagePickerController.InfoKey: Any]
+  ) {
+    picker.dismiss(animated: true)
+    onFinish?(info)
+  }
+
+  func imagePickerControllerDidCancel(_ picker: UIImagePickerController) {
+    picker.dismiss(animated: true)
+    onFinish?(nil)
+  }
+}
+
+class CameraPlugin: Plugin {
+  private var delegate: PickerDelegate?
+
+  /// The library picker runs out of process and needs no permission; the camera does.
+  private func authorize(
+    _ invoke: Invoke, camera: Bool, gallery: Bool, then: @escaping () -> Void
+  ) {
+    let galleryStep = {
+      guard gallery else { return then() }
+      PHPhotoLibrary.requestAuthorization(for: .addOnly) { status in
+        DispatchQueue.main.async {
+          if status == .authorized || status == .limited {
+            then()
+          } else {
+            invoke.reject("photo library permission denied", code: "PermissionDenied")
+          }
+        }
+      }
+    }
+    guard camera else { return galleryStep() }
+    AVCaptureDevice.requestAccess(for: .video) { granted in
+      DispatchQueue.main.async {
+        if granted {
+          galleryStep()
+        } else {
+          invoke.reject("camera permission denied", code: "PermissionDenied")
+        }
+      }
+    }
+  }
+
+  private func present(
+    _ invoke: Invoke, source: String, mediaType: CFString,
+    configure: (UIImagePickerController) -> Void,
+    onFinish: @escaping ([UIImagePickerController.InfoKey: Any]) -> Void
+  ) {
+    let sourceType: UIImagePickerController.SourceType =
+      source == "photoLibrary" ? .photoLibrary : .camera
+    guard UIImagePickerController.isSourceTypeAvailable(sourceType),
+      UIImagePickerController.availableMediaTypes(for: sourceType)?.contains(mediaType as String)
+        == true
+    else {
+      invoke.reject("this device can't provide that media here", code: "SourceUnavailable")
+      return
+    }
+    guard let presenter = manager.viewController else {
+      invoke.reject("no view controller to present from", code: "Failed")
+      return
+    }
+    let picker = UIImagePickerController()
+    picker.sourceType = sourceType
+    picker.mediaTypes = [mediaType as String]
+    configure(picker)
+    let delegate = PickerDelegate()
+    delegate.onFinish = { [weak self] info in
+      self?.delegate = nil
+      guard let info = info else {
+        invoke.reject("capture cancelled", code: "Cancelled")
+        return
+      }
+      onFinish(info)
+    }
+    self.delegate = delegate
+    picker.delegate = delegate
+    presenter.present(picker, animated: true)
+  }
+
+  @objc public func takePhoto(_ invoke: Invoke) throws {
+    let args = try invoke.parseArgs(PhotoArgs.self)
+    DispatchQueue.main.async {
+      self.authorize(invoke, camera: args.source == "camera", gallery: args.saveToGallery) {
+        self.present(invoke, source: args.source, mediaType: kUTTypeImage, configure: { _ in }) {
+          info in
+          guard let image = info[.originalImage] as? UIImage else {
+            invoke.reject("the picker returned no image", code: "Failed")
+            return
+          }
+          // Encoding a full-size photo takes a while.
+          DispatchQueue.global(qos: .userInitiated).async {
+            self.finishPhoto(invoke, image: image, args: args)
+          }
+        }
+      }
+    }
+  }
+
+  private func finishPhoto(_ invoke: Invoke, image: UIImage, args: PhotoArgs) {
+    let photo = fitted(image, maxWidth: args.maxWidth, maxHeight: args.maxHeight)
+    let quality = CGFloat(min(max(args.quality, 0), 100)) / 100
+    guard let data = photo.jpegData(compressionQuality: quality) else {
+      invoke.reject("encoding the photo failed", code: "Failed")
+      return
+    }
+    do {
+      let url = try newFile("jpg")
+      try data.write(to: url)
+      let media = CapturedMedia(
+        path: url.path, width: Int(photo.size.width), height: Int(photo.size.height),
+        sizeBytes: UInt64(data.count), mimeType: "image/jpeg")
+      finish(invoke, media: media, save: args.saveToGallery ? .image : nil, url: url)
+    } catch {
+      invoke.reject(error.localizedDescription, code: "Failed")
+    }
+  }
+
+  @objc public func recordVideo(_ invoke: Invoke) throws {
+    let args = try invoke.parseArgs(VideoArgs.self)
+    DispatchQueue.main.async {
+      self.authorize(invoke, camera: args.source == "camera", gallery: args.saveToGallery) {
+        self.present(
+          invoke, source: args.source, mediaType: kUTTypeMovie,
+          configure: { picker in
+            if let seconds = args.maxDurationSecs { picker.videoMaximumDuration = seconds }
+            switch args.quality {
+            case "low": picker.videoQuality = .typeLow
+            case "medium": picker.videoQuality = .typeMedium
+            default: picker.videoQuality = .typeHigh
+            }
+          }
+        ) { info in
+          guard let source = info[.mediaURL] as? URL else {
+            invoke.reject("the picker returned no video", code: "Failed")
+            return
+          }
+          self.finishVideo(invoke, source: source, args: args)
+        }
+      }
+    }
+  }
+
+  /// The picker's file is deleted once the delegate returns, so it's copied first.
+  private func finishVideo(_ invoke: Invoke, source: URL, args: VideoArgs) {
+    do {
+      let ext = source.pathExtension.isEmpty ? "mov" : source.pathExtension.lowercased()
+      let url = try newFile(ext)
+      try FileManager.default.copyItem(at: source, to: url)
+      var width = 0
+      var height = 0
+      if let track = AVURLAsset(url: url).tracks(withMediaType: .video).first {
+        let size = track.naturalSize.applying(track.preferredTransform)
+        width = Int(abs(size.width))
+        height = Int(abs(size.height))
+      }
+      let media = CapturedMedia(
+        path: url.path, width: width, height: height, sizeBytes: fileSize(url),
+        mimeType: ext == "mp4" ? "video/mp4" : "video/quicktime")
+      finish(invoke, media: media, save: args.saveToGallery ? .video : nil, url: url)
+    } catch {
+      invoke.reject(error.localizedDescription, code: "Failed")
+    }
+  }
+
+  private func finish(
+    _ invoke: Invoke, media: CapturedMedia, save: PHAssetResourceType?, url: URL
+  ) {
+    guard let save = save else {
+      invoke.resolve(media)
+      return
+    }
+    PHPhotoLibrary.shared().performChanges({
+      PHAssetCreationRequest.forAsset().addResource(with: save, fileURL: url, options: nil)
+    }) { saved, error in
+      if saved {
+        invoke.resolve(media)
+      } else {
+        invoke.reject(
+          error?.localizedDescription ?? "saving to the photo library failed", code: "Failed")
+      }
+    }
+  }
+}
+
+@_cdecl("init_plugin_camera")
+func initPlugin() -> Plugin {
+  return CameraPlugin()
+}
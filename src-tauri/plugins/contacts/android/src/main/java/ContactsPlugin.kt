@@ -0,0 +1,235 @@
+package com.layers.contacts
+
+import android.Manifest
+import android.app.Activity
+import android.content.ContentProviderOperation
+import android.content.ContentUris
+import android.net.Uri
+import android.provider.ContactsContract
+import android.provider.ContactsContract.CommonDataKinds.Email
+import android.provider.ContactsContract.CommonDataKinds.Phone
+import android.provider.ContactsContract.CommonDataKinds.StructuredName
+import android.util.Base64
+import app.tauri.PermissionState
+import app.tauri.annotation.Command
+import app.tauri.annotation.InvokeArg
+import app.tauri.annotation.Permission
+import app.tauri.annotation.PermissionCallback
+import app.tauri.annotation.TauriPlugin
+import app.tauri.plugin.Invoke
+import app.tauri.plugin.JSArray
+import app.tauri.plugin.JSObject
+import app.tauri.plugin.Plugin
+
+@InvokeArg
+class QueryArgs {
+    /** Matched against display names; every contact when null. */
+    var query: String? = null
+}
+
+@InvokeArg
+class NewContactArgs {
+    var givenName: String = ""
+    var familyName: String = ""
+    var phoneNumbers: Array<String> = arrayOf()
+    var emailAddresses: Array<String> = arrayOf()
+}
+
+private class ContactRow(val id: String, val displayName: String, val thumbnail: String?) {
+    var givenName = ""
+    var familyName = ""
+    val phoneNumbers = mutableListOf<String>()
+    val emailAddresses = mutableListOf<String>()
+}
+
+@TauriPlugin(
+    permissions = [
+        Permission(
+            strings = [Manifest.permission.READ_CONTACTS, Manifest.permission.WRITE_CONTACTS],
+            alias = "contacts",
+        ),
+    ],
+)
+class ContactsPlugin(private val activity: Activity) : Plugin(activity) {
+    private val granted: Boolean
+        get() = getPermissionState("contacts") == PermissionState.GRANTED
+
+    @Command
+    fun requestPermission(invoke: Invoke) {
+        if (granted) {
+            resolvePermission(invoke)
+        } else {
+            requestPermissionForAlias("contacts", invoke, "contactsPermission")
+        }
+    }
+
+    @PermissionCallback
+    private fun contactsPermission(invoke: Invoke) {
+        resolvePermission(invoke)
+    }
+
+    private fun resolvePermission(invoke: Invoke) {
+        val ret = JSObject()
+        ret.put("contacts", getPermissionState("contacts").toString())
+        invoke.resolve(ret)
+    }
+
+    /** Contacts first, then one pass over their names, numbers and addresses. */
+    @Command
+    fun getContacts(invoke: Invoke) {
+        if (!granted) {
+            invoke.reject("contacts permission denied", "PermissionDenied")
+            return
+        }
+        val args = invoke.parseArgs(QueryArgs::class.java)
+        val query = args.query?.takeIf { it.isNotBlank() }
+        try {
+            val contacts = readContacts(query)
+            readDetails(contacts)
+            val list = JSArray()
+            for (contact in contacts.values) {
+                list.put(
+                    JSObject().apply {
+                        put("id", contact.id)
+                        put("displayName", contact.displayName)
+                        put("givenName", contact.givenName)
+                        put("familyName", contact.familyName)
+                        put("phoneNumbers", JSArray(contact.phoneNumbers))
+                        put("emailAddresses", JSArray(contact.emailAddresses))
+                        contact.thumbnail?.let { put("thumbnail", it) }
+                    },
+                )
+            }
+            val ret = JSObject()
+            ret.put("contacts", list)
+            invoke.resolve(ret)
+        } catch (e: Exception) {
+            invoke.reject(e.message ?: "contacts query failed", "Failed")
+        }
+    }
+
+    private fun readContacts(query: String?): LinkedHashMap<String, ContactRow> {
+        val contacts = LinkedHashMap<String, ContactRow>()
+        val projection = arrayOf(
+            ContactsContract.Contacts._ID,
+            ContactsContract.Contacts.DISPLAY_NAME_PRIMARY,
+            ContactsContract.Contacts.PHOTO_THUMBNAIL_URI,
+        )
+        val uri = if (query != null) {
+            Uri.withAppendedPath(ContactsContract.Contacts.CONTENT_FILTER_URI, Uri.encode(query))
+        } else {
+            ContactsContract.Contacts.CONTENT_URI
+        }
+        activity.contentResolver.query(
+            uri, projection, null, null, ContactsContract.Contacts.DISPLAY_NAME_PRIMARY,
+        )?.use { cursor ->
+            while (cursor.moveToNext()) {
+                val id = cursor.getLong(0).toString()
+                contacts[id] = ContactRow(
+                    id,
+                    cursor.getString(1) ?: "",
+                    cursor.getString(2)?.let { thumbnail(Uri.parse(it)) },
+                )
+            }
+        }
+        return contacts
+    }
+
+    private fun readDetails(contacts: Map<String, ContactRow>) {
+        if (contacts.isEmpty()) return
+        val projection = arrayOf(
+            ContactsContract.Data.CONTACT_ID,
+            ContactsContract.Data.MIMETYPE,
+            ContactsContract.Data.DATA1,
+            ContactsContract.Data.DATA2,
+            ContactsContract.Data.DATA3,
+        )
+        val selection = "${ContactsContract.Data.MIMETYPE} IN (?, ?, ?)"
+        val selectionArgs = arrayOf(
+            StructuredName.CONTENT_ITEM_TYPE,
+            Phone.CONTENT_ITEM_TYPE,
+            Email.CONTENT_ITEM_TYPE,
+        )
+        activity.contentResolver.query(
+            ContactsContract.Data.CONTENT_URI, projection, selection, selectionArgs, null,
+        )?.use { cursor ->
+            while (cursor.moveToNext()) {
+                val contact = contacts[cursor.getLong(0).toString()] ?: continue
+                when (cursor.getString(1)) {
+                    StructuredName.CONTENT_ITEM_TYPE -> {
+                        // DATA2 and DATA3 are GIVEN_NAME and FAMILY_NAME for this type.
+                        contact.givenName = cursor.getString(3) ?: ""
+                        contact.familyName = cursor.getString(4) ?: ""
+                    }
+                    Phone.CONTENT_ITEM_TYPE -> cursor.getString(2)?.let { contact.phoneNumbers.add(it) }
+                    Email.CONTENT_ITEM_TYPE -> cursor.getString(2)?.let { contact.emailAddresses.add(it) }
+                }
+            }
+        }
+    }
+
+    private fun thumbnail(uri: Uri): String? = try {
+        activity.contentResolver.openInputStream(uri)?.use {
+            Base64.encodeToString(it.readBytes(), Base64.NO_WRAP)
+        }
+    } catch (e: Exception) {
+        null
+    }
+
+    /** Adds a raw contact to the default account and resolves with its aggregate's id. */
+    @Command
+    fun createContact(invoke: Invoke) {
+        if (!granted) {
+            invoke.reject("contacts permission denied", "PermissionDenied")
+            return
+        }
+        val args = invoke.parseArgs(NewContactArgs::class.java)
+        val ops = arrayListOf(
+            ContentProviderOperation.newInsert(ContactsContract.RawContacts.CONTENT_URI)
+                .withValue(ContactsContract.RawContacts.ACCOUNT_TYPE, null)
+                .withValue(ContactsContract.RawContacts.ACCOUNT_NAME, null)
+                .build(),
+            ContentProviderOperation.newInsert(ContactsContract.Data.CONTENT_URI)
+                .withValueBackReference(ContactsContract.Data.RAW_CONTACT_ID, 0)
+                .withValue(ContactsContract.Data.MIMETYPE, StructuredName.CONTENT_ITEM_TYPE)
+                .withValue(StructuredName.GIVEN_NAME, args.givenName)
+                .withValue(StructuredName.FAMILY_NAME, args.familyName)
+                .build(),
+        )
+        for (number in args.phoneNumbers) {
+            ops.add(
+                ContentProviderOperation.newInsert(ContactsContract.Data.CONTENT_URI)
+                    .withValueBackReference(ContactsContract.Data.RAW_CONTACT_ID, 0)
+                    .withValue(ContactsContract.Data.MIMETYPE, Phone.CONTENT_ITEM_TYPE)
+                    .withValue(Phone.NUMBER, number)
+                    .withValue(Phone.TYPE, Phone.TYPE_MOBILE)
+                    .build(),
+            )
+        }
+        for (address in args.emailAddresses) {
+            ops.add(
+                ContentProviderOperation.newInsert(ContactsContract.Data.CONTENT_URI)
+                    .withValueBackReference(ContactsContract.Data.RAW_CONTACT_ID, 0)
+                    .withValue(ContactsContract.Data.MIMETYPE, Email.CONTENT_ITEM_TYPE)
+                    .withValue(Email.ADDRESS, address)
+                    .withValue(Email.TYPE, Email.TYPE_OTHER)
+                    .build(),
+            )
+        }
+        try {
+            val results = activity.contentResolver.applyBatch(ContactsContract.AUTHORITY, ops)
+            val rawId = results[0].uri?.let { ContentUris.parseId(it) }
+                ?: throw IllegalStateException("raw contact was not created")
+            val contactId = activity.contentResolver.query(
+                ContentUris.withAppendedId(ContactsContract.RawContacts.CONTENT_URI, rawId),
+                arrayOf(ContactsContract.RawContacts.CONTACT_ID), null, null, null,
+            )?.use { cursor -> if (cursor.moveToFirst()) cursor.getLong(0) else null }
+                ?: throw IllegalStateException("contact was not aggregated")
+            val ret = JSObject()
+            ret.put("id", contactId.toString())
+            invoke.resolve(ret)
+        } catch (e: Exception) {
+            invoke.reject(e.message ?: "saving the contact failed", "Failed")
+        }
+    }
+}
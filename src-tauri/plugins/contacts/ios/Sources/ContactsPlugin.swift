@@ -0,0 +1,130 @@
+import Contacts
+import Tauri
+import UIKit
+import WebKit
+
+class QueryArgs: Decodable {
+  /// Matched against names; every contact when nil.
+  let query: String?
+}
+
+class NewContactArgs: Decodable {
+  let givenName: String
+  let familyName: String
+  let phoneNumbers: [String]
+  let emailAddresses: [String]
+}
+
+private let keys: [CNKeyDescriptor] = [
+  CNContactIdentifierKey as CNKeyDescriptor,
+  CNContactGivenNameKey as CNKeyDescriptor,
+  CNContactFamilyNameKey as CNKeyDescriptor,
+  CNContactPhoneNumbersKey as CNKeyDescriptor,
+  CNContactEmailAddressesKey as CNKeyDescriptor,
+  CNContactThumbnailImageDataKey as CNKeyDescriptor,
+  CNContactFormatter.descriptorForRequiredKeys(for: .fullName),
+]
+
+class ContactsPlugin: Plugin {
+  private let store = CNContactStore()
+
+  private func state(_ status: CNAuthorizationStatus) -> String {
+    switch status {
+    case .authorized: return "granted"
+    case .notDetermined: return "prompt"
+    case .denied, .restricted: return "denied"
+    // `.limited` from iOS 18, which still reads the contacts the user picked.
+    default: return "granted"
+    }
+  }
+
+  private var granted: Bool {
+    state(CNContactStore.authorizationStatus(for: .contacts)) == "granted"
+  }
+
+  @objc public func requestPermission(_ invoke: Invoke) throws {
+    let status = CNContactStore.authorizationStatus(for: .contacts)
+    guard status == .notDetermined else {
+      invoke.resolve(["contacts": state(status)])
+      return
+    }
+    store.requestAccess(for: .contacts) { granted, _ in
+      invoke.resolve(["contacts": granted ? "granted" : "denied"])
+    }
+  }
+
+  @objc public func getContacts(_ invoke: Invoke) throws {
+    let args = try invoke.parseArgs(QueryArgs.self)
+    guard granted else {
+      invoke.reject("contacts permission denied", code: "PermissionDenied")
+      return
+    }
+    // Fetching blocks, and the store may have to load a large address book.
+    DispatchQueue.global(qos: .userInitiated).async {
+      do {
+        var contacts: [CNContact] = []
+        if let query = args.query, !query.isEmpty {
+          contacts = try self.store.unifiedContacts(
+            matching: CNContact.predicateForContacts(matchingName: query), keysToFetch: keys)
+        } else {
+          let request = CNContactFetchRequest(keysToFetch: keys)
+          request.sortOrder = .userDefault
+          try self.store.enumerateContacts(with: request) { contact, _ in
+            contacts.append(contact)
+          }
+        }
+        invoke.resolve(["contacts": contacts.map(self.serialize)])
+      } catch {
+        invoke.reject(error.localizedDescription, code: "Failed")
+      }
+    }
+  }
+
+  private func serialize(_ contact: CNContact) -> [String: Any] {
+    var object: [String: Any] = [
+      "id": contact.identifier,
+      "displayName": CNContactFormatter.string(from: contact, style: .fullName) ?? "",
+      "givenName": contact.givenName,
+      "familyName": contact.familyName,
+      "phoneNumbers": contact.phoneNumbers.map { $0.value.stringValue },
+      "emailAddresses": contact.emailAddresses.map { $0.value as String },
+    ]
+    if let thumbnail = contact.thumbnailImageData {
+      object["thumbnail"] = thumbnail.base64EncodedString()
+    }
+    return object
+  }
+
+  /// Saves to the default container and resolves with the new contact's identifier.
+  @objc public func createContact(_ invoke: Invoke) throws {
+    let args = try invoke.parseArgs(NewContactArgs.self)
+    guard granted else {
+      invoke.reject("contacts permission denied", code: "PermissionDenied")
+      return
+    }
+    let contact = CNMutableContact()
+    contact.givenName = args.givenName
+    contact.familyName = args.familyName
+    contact.phoneNumbers = args.phoneNumbers.map {
+      CNLabeledValue(label: CNLabelPhoneNumberMobile, value: CNPhoneNumber(stringValue: $0))
+    }
+    contact.emailAddresses = args.emailAddresses.map {
+      CNLabeledValue(label: CNLabelOther, value: $0 as NSString)
+    }
+    DispatchQueue.global(qos: .userInitiated).async {
+      let request = CNSaveRequest()
+      request.add(contact, toContainerWithIdentifier: nil)
+      do {
+        try self.store.execute(request)
+        invoke.resolve(["id": contact.identifier])
+      } catch {
+        invoke.reject(error.localizedDescription, code: "Failed")
+      }
+    }
+  }
+}
+
+@_cdecl("init_plugin_contacts")
+func initPlugin() -> Plugin {
+  return ContactsPlugin()
+}
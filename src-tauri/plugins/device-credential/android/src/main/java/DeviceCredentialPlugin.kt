@@ -0,0 +1,90 @@
+package com.layers.devicecredential
+
+import android.app.Activity
+import androidx.biometric.BiometricManager
+import androidx.biometric.BiometricManager.Authenticators.BIOMETRIC_WEAK
+import androidx.biometric.BiometricManager.Authenticators.DEVICE_CREDENTIAL
+import androidx.biometric.BiometricPrompt
+import androidx.core.content.ContextCompat
+import androidx.fragment.app.FragmentActivity
+import app.tauri.annotation.Command
+import app.tauri.annotation.InvokeArg
+import app.tauri.annotation.TauriPlugin
+import app.tauri.plugin.Invoke
+import app.tauri.plugin.JSObject
+import app.tauri.plugin.Plugin
+
+@InvokeArg
+class AuthArgs {
+    var reason: String = ""
+}
+
+/**
+ * A `BiometricPrompt` that also offers the PIN, pattern or password. Unlike the biometric
+ * plugin's, it reports which of the two the user passed. Rejections use that plugin's codes,
+ * so both map to errors the same way.
+ */
+@TauriPlugin
+class DeviceCredentialPlugin(private val activity: Activity) : Plugin(activity) {
+    // BIOMETRIC_STRONG with DEVICE_CREDENTIAL isn't supported on API 28 and 29; WEAK is.
+    private val authenticators = BIOMETRIC_WEAK or DEVICE_CREDENTIAL
+
+    @Command
+    fun authenticate(invoke: Invoke) {
+        val args = invoke.parseArgs(AuthArgs::class.java)
+        when (BiometricManager.from(activity).canAuthenticate(authenticators)) {
+            BiometricManager.BIOMETRIC_SUCCESS -> {}
+            BiometricManager.BIOMETRIC_ERROR_NONE_ENROLLED -> {
+                invoke.reject("no PIN, pattern, password or biometric is set up", "noDeviceCredential")
+                return
+            }
+            else -> {
+                invoke.reject("device authentication is unavailable", "biometryNotAvailable")
+                return
+            }
+        }
+        // The Tauri activity is an AppCompatActivity, so the prompt can attach to it directly.
+        val host = activity as? FragmentActivity ?: run {
+            invoke.reject("the activity can't host a prompt", "systemError")
+            return
+        }
+        activity.runOnUiThread {
+            val callback = object : BiometricPrompt.AuthenticationCallback() {
+                override fun onAuthenticationSucceeded(result: BiometricPrompt.AuthenticationResult) {
+                    val factor =
+                        if (result.authenticationType == BiometricPrompt.AUTHENTICATION_RESULT_TYPE_DEVICE_CREDENTIAL) {
+                            "credential"
+                        } else {
+                            "biometric"
+                        }
+                    invoke.resolve(JSObject().put("factor", factor))
+                }
+
+                override fun onAuthenticationError(errorCode: Int, errString: CharSequence) {
+                    val code = when (errorCode) {
+                        BiometricPrompt.ERROR_USER_CANCELED,
+                        BiometricPrompt.ERROR_NEGATIVE_BUTTON -> "userCancel"
+                        BiometricPrompt.ERROR_CANCELED -> "systemCancel"
+                        BiometricPrompt.ERROR_LOCKOUT,
+                        BiometricPrompt.ERROR_LOCKOUT_PERMANENT -> "biometryLockout"
+                        BiometricPrompt.ERROR_NO_DEVICE_CREDENTIAL -> "noDeviceCredential"
+                        BiometricPrompt.ERROR_HW_NOT_PRESENT,
+                        BiometricPrompt.ERROR_HW_UNAVAILABLE -> "biometryNotAvailable"
+                        BiometricPrompt.ERROR_NO_BIOMETRICS -> "biometryNotEnrolled"
+                        else -> "systemError"
+                    }
+                    invoke.reject(errString.toString(), code)
+                }
+
+                // A mismatched finger keeps the prompt open for another try; nothing to report.
+                override fun onAuthenticationFailed() {}
+            }
+            // No negative button: the credential option takes its place.
+            val info = BiometricPrompt.PromptInfo.Builder()
+                .setTitle(args.reason)
+                .setAllowedAuthenticators(authenticators)
+                .build()
+            BiometricPrompt(host, ContextCompat.getMainExecutor(activity), callback).authenticate(info)
+        }
+    }
+}
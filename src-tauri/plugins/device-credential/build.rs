@@ -0,0 +1,8 @@
+// The app invokes the native commands from Rust, so no IPC commands are exposed.
+const COMMANDS: &[&str] = &[];
+
+fn main() {
+    tauri_plugin::Builder::new(COMMANDS)
+        .android_path("android")
+        .build();
+}
@@ -0,0 +1,142 @@
+package com.layers.device
+
+import android.app.Activity
+import android.app.ActivityManager
+import android.content.BroadcastReceiver
+import android.content.ComponentCallbacks2
+import android.content.Context
+import android.content.Intent
+import android.content.IntentFilter
+import android.content.res.Configuration
+import android.os.BatteryManager
+import android.view.View
+import android.webkit.WebView
+import androidx.core.graphics.Insets
+import androidx.core.view.ViewCompat
+import androidx.core.view.WindowInsetsCompat
+import app.tauri.annotation.Command
+import app.tauri.annotation.InvokeArg
+import app.tauri.annotation.TauriPlugin
+import app.tauri.plugin.Channel
+import app.tauri.plugin.Invoke
+import app.tauri.plugin.JSObject
+import app.tauri.plugin.Plugin
+
+@InvokeArg
+class WatchArgs {
+    lateinit var channel: Channel
+}
+
+@TauriPlugin
+class DevicePlugin(private val activity: Activity) : Plugin(activity) {
+    private var webView: View? = null
+    private var batteryReceiver: BroadcastReceiver? = null
+    private var memoryCallbacks: ComponentCallbacks2? = null
+
+    override fun load(webView: WebView) {
+        super.load(webView)
+        this.webView = webView
+    }
+
+    override fun onDestroy() {
+        batteryReceiver?.let { activity.unregisterReceiver(it) }
+        batteryReceiver = null
+        memoryCallbacks?.let { activity.unregisterComponentCallbacks(it) }
+        memoryCallbacks = null
+        super.onDestroy()
+    }
+
+    /**
+     * Safe-area insets in CSS pixels: how far the system bars and display cutout reach into
+     * the webview, which is nothing at all unless the app draws edge to edge.
+     */
+    @Command
+    fun getCapabilities(invoke: Invoke) {
+        activity.runOnUiThread {
+            val decor = activity.window.decorView
+            val bars = ViewCompat.getRootWindowInsets(decor)?.getInsets(
+                WindowInsetsCompat.Type.systemBars() or WindowInsetsCompat.Type.displayCutout(),
+            ) ?: Insets.NONE
+            val view = webView ?: decor
+            val origin = IntArray(2)
+            view.getLocationInWindow(origin)
+            val density = activity.resources.displayMetrics.density
+            fun css(px: Int) = (maxOf(px, 0) / density).toDouble()
+
+            val insets = JSObject().apply {
+                put("top", css(bars.top - origin[1]))
+                put("left", css(bars.left - origin[0]))
+                put("bottom", css(bars.bottom - (decor.height - origin[1] - view.height)))
+                put("right", css(bars.right - (decor.width - origin[0] - view.width)))
+            }
+            val manager = activity.getSystemService(Context.ACTIVITY_SERVICE) as ActivityManager
+            val ret = JSObject()
+            ret.put("safeAreaInsets", insets)
+            ret.put("memoryClassMb", manager.memoryClass)
+            invoke.resolve(ret)
+        }
+    }
+
+    /** Sends the level and charging state now and whenever either changes. */
+    @Command
+    fun watchBattery(invoke: Invoke) {
+        val args = invoke.parseArgs(WatchArgs::class.java)
+        batteryReceiver?.let { activity.unregisterReceiver(it) }
+        val receiver = object : BroadcastReceiver() {
+            private var last: Pair<Int, Boolean>? = null
+
+            override fun onReceive(context: Context, intent: Intent) {
+                val level = intent.getIntExtra(BatteryManager.EXTRA_LEVEL, -1)
+                val scale = intent.getIntExtra(BatteryManager.EXTRA_SCALE, -1)
+                if (level < 0 || scale <= 0) return
+                val status = intent.getIntExtra(BatteryManager.EXTRA_STATUS, -1)
+                val charging = status == BatteryManager.BATTERY_STATUS_CHARGING ||
+                    status == BatteryManager.BATTERY_STATUS_FULL
+                // The broadcast also fires for voltage and temperature changes.
+                val percent = level * 100 / scale
+                if (last == percent to charging) return
+                last = percent to charging
+                args.channel.send(
+                    JSObject().apply {
+                        put("level", percent / 100.0)
+                        put("charging", charging)
+                    },
+                )
+            }
+        }
+        // A sticky broadcast, so the current state arrives straight away.
+        activity.registerReceiver(receiver, IntentFilter(Intent.ACTION_BATTERY_CHANGED))
+        batteryReceiver = receiver
+        invoke.resolve()
+    }
+
+    /**
+     * Sends `critical` when the system is about to kill processes, this one included, and not
+     * `critical` when it merely wants memory back. UI_HIDDEN and BACKGROUND only say where the
+     * app is, which the lifecycle events already cover.
+     */
+    @Command
+    fun watchMemory(invoke: Invoke) {
+        val args = invoke.parseArgs(WatchArgs::class.java)
+        memoryCallbacks?.let { activity.unregisterComponentCallbacks(it) }
+        fun send(critical: Boolean) = args.channel.send(JSObject().put("critical", critical))
+        val callbacks = object : ComponentCallbacks2 {
+            override fun onTrimMemory(level: Int) {
+                when (level) {
+                    ComponentCallbacks2.TRIM_MEMORY_RUNNING_MODERATE,
+                    ComponentCallbacks2.TRIM_MEMORY_RUNNING_LOW,
+                    ComponentCallbacks2.TRIM_MEMORY_MODERATE -> send(false)
+                    ComponentCallbacks2.TRIM_MEMORY_RUNNING_CRITICAL,
+                    ComponentCallbacks2.TRIM_MEMORY_COMPLETE -> send(true)
+                }
+            }
+
+            override fun onLowMemory() = send(true)
+
+            override fun onConfigurationChanged(newConfig: Configuration) {}
+        }
+        activity.registerComponentCallbacks(callbacks)
+        memoryCallbacks = callbacks
+        invoke.resolve()
+    }
+}
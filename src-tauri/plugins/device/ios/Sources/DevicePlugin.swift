@@ -0,0 +1,91 @@
+import Tauri
+import UIKit
+import WebKit
+
+class WatchArgs: Decodable {
+  let channel: Channel
+}
+
+struct MemoryWarning: Encodable {
+  let critical: Bool
+}
+
+struct BatteryStatus: Encodable {
+  let level: Float
+  let charging: Bool
+}
+
+class DevicePlugin: Plugin {
+  private var webView: WKWebView?
+  private var batteryChannel: Channel?
+  private var lastBattery: (Float, Bool)?
+  private var memoryChannel: Channel?
+
+  override func load(webview: WKWebView) {
+    self.webView = webview
+  }
+
+  /// A web view's safe area is in points, which are CSS pixels. iOS has no memory class.
+  @objc public func getCapabilities(_ invoke: Invoke) throws {
+    DispatchQueue.main.async {
+      let insets = self.webView?.safeAreaInsets ?? .zero
+      invoke.resolve([
+        "safeAreaInsets": [
+          "top": insets.top, "right": insets.right, "bottom": insets.bottom, "left": insets.left,
+        ]
+      ])
+    }
+  }
+
+  /// Sends the level and charging state now and whenever either changes.
+  @objc public func watchBattery(_ invoke: Invoke) throws {
+    let args = try invoke.parseArgs(WatchArgs.self)
+    DispatchQueue.main.async {
+      self.batteryChannel = args.channel
+      UIDevice.current.isBatteryMonitoringEnabled = true
+      let center = NotificationCenter.default
+      center.removeObserver(self, name: UIDevice.batteryLevelDidChangeNotification, object: nil)
+      center.removeObserver(self, name: UIDevice.batteryStateDidChangeNotification, object: nil)
+      center.addObserver(
+        self, selector: #selector(self.batteryChanged),
+        name: UIDevice.batteryLevelDidChangeNotification, object: nil)
+      center.addObserver(
+        self, selector: #selector(self.batteryChanged),
+        name: UIDevice.batteryStateDidChangeNotification, object: nil)
+      self.batteryChanged()
+      invoke.resolve()
+    }
+  }
+
+  @objc private func batteryChanged() {
+    let device = UIDevice.current
+    // -1 on the simulator, which has no battery.
+    guard let channel = batteryChannel, device.batteryLevel >= 0 else { return }
+    let charging = device.batteryState == .charging || device.batteryState == .full
+    if let last = lastBattery, last.0 == device.batteryLevel, last.1 == charging { return }
+    lastBattery = (device.batteryLevel, charging)
+    try? channel.send(BatteryStatus(level: device.batteryLevel, charging: charging))
+  }
+
+  /// iOS has a single level of warning, sent shortly before it starts terminating apps.
+  @objc public func watchMemory(_ invoke: Invoke) throws {
+    let args = try invoke.parseArgs(WatchArgs.self)
+    DispatchQueue.main.async {
+      self.memoryChannel = args.channel
+      let center = NotificationCenter.default
+      let name = UIApplication.didReceiveMemoryWarningNotification
+      center.removeObserver(self, name: name, object: nil)
+      center.addObserver(self, selector: #selector(self.memoryWarning), name: name, object: nil)
+      invoke.resolve()
+    }
+  }
+
+  @objc private func memoryWarning() {
+    try? memoryChannel?.send(MemoryWarning(critical: true))
+  }
+}
+
+@_cdecl("init_plugin_device")
+func initPlugin() -> Plugin {
+  return DevicePlugin()
+}
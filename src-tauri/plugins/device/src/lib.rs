@@ -0,0 +1,4 @@
+//! Native halves of the device details `tauri-plugin-device-info` doesn't cover. There is
+//! no Rust API here: `layers` registers the Android and iOS plugins itself (see
+//! `src/device_info.rs`), and depends on this crate only so the Tauri CLI builds and
+//! links them.
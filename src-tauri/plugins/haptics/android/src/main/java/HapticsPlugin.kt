@@ -0,0 +1,74 @@
+package com.layers.haptics
+
+import android.app.Activity
+import android.content.Context
+import android.os.Build
+import android.os.VibrationEffect
+import android.os.Vibrator
+import android.os.VibratorManager
+import app.tauri.annotation.Command
+import app.tauri.annotation.InvokeArg
+import app.tauri.annotation.TauriPlugin
+import app.tauri.plugin.Invoke
+import app.tauri.plugin.JSObject
+import app.tauri.plugin.Plugin
+
+@InvokeArg
+class FeedbackArgs {
+    /** One of the Rust side's `HapticStyle` names, in camelCase. */
+    lateinit var style: String
+}
+
+/**
+ * Android has no generator API like iOS, so each style is a one-shot pulse whose length
+ * and strength approximate the iOS feel. Strength is ignored on vibrators without
+ * amplitude control, which leaves only the length to tell styles apart.
+ */
+@TauriPlugin
+class HapticsPlugin(private val activity: Activity) : Plugin(activity) {
+    private val vibrator: Vibrator?
+        get() = if (Build.VERSION.SDK_INT >= 31) {
+            (activity.getSystemService(Context.VIBRATOR_MANAGER_SERVICE) as VibratorManager?)
+                ?.defaultVibrator
+        } else {
+            @Suppress("DEPRECATION")
+            activity.getSystemService(Context.VIBRATOR_SERVICE) as Vibrator?
+        }
+
+    /** Milliseconds and amplitude (1-255) per style. */
+    private fun pulse(style: String): Pair<Long, Int>? = when (style) {
+        "selection" -> 5L to 40
+        "light" -> 10L to 60
+        "soft" -> 20L to 50
+        "medium" -> 20L to 120
+        "rigid" -> 15L to 200
+        "heavy" -> 30L to 255
+        "success" -> 40L to 120
+        "warning" -> 60L to 180
+        "error" -> 80L to 255
+        else -> null
+    }
+
+    @Command
+    fun feedback(invoke: Invoke) {
+        val args = invoke.parseArgs(FeedbackArgs::class.java)
+        val vibrator = vibrator
+        if (vibrator == null || !vibrator.hasVibrator()) {
+            resolve(invoke, false)
+            return
+        }
+        val (duration, amplitude) = pulse(args.style) ?: run {
+            invoke.reject("unknown haptic style ${args.style}", "Failed")
+            return
+        }
+        val strength = if (vibrator.hasAmplitudeControl()) amplitude else VibrationEffect.DEFAULT_AMPLITUDE
+        vibrator.vibrate(VibrationEffect.createOneShot(duration, strength))
+        resolve(invoke, true)
+    }
+
+    private fun resolve(invoke: Invoke, supported: Boolean) {
+        val ret = JSObject()
+        ret.put("supported", supported)
+        invoke.resolve(ret)
+    }
+}
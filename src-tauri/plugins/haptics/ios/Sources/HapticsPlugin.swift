@@ -0,0 +1,39 @@
+import CoreHaptics
+import Tauri
+import UIKit
+import WebKit
+
+class FeedbackArgs: Decodable {
+  /// One of the Rust side's `HapticStyle` names, in camelCase.
+  let style: String
+}
+
+/// Devices without a Taptic Engine ignore the generators rather than failing, so every
+/// style resolves, with `supported` saying whether anything was felt.
+class HapticsPlugin: Plugin {
+  @objc public func feedback(_ invoke: Invoke) throws {
+    let args = try invoke.parseArgs(FeedbackArgs.self)
+    DispatchQueue.main.async {
+      switch args.style {
+      case "light": UIImpactFeedbackGenerator(style: .light).impactOccurred()
+      case "medium": UIImpactFeedbackGenerator(style: .medium).impactOccurred()
+      case "heavy": UIImpactFeedbackGenerator(style: .heavy).impactOccurred()
+      case "rigid": UIImpactFeedbackGenerator(style: .rigid).impactOccurred()
+      case "soft": UIImpactFeedbackGenerator(style: .soft).impactOccurred()
+      case "success": UINotificationFeedbackGenerator().notificationOccurred(.success)
+      case "warning": UINotificationFeedbackGenerator().notificationOccurred(.warning)
+      case "error": UINotificationFeedbackGenerator().notificationOccurred(.error)
+      case "selection": UISelectionFeedbackGenerator().selectionChanged()
+      default:
+        invoke.reject("unknown haptic style \(args.style)", code: "Failed")
+        return
+      }
+      invoke.resolve(["supported": CHHapticEngine.capabilitiesForHardware().supportsHaptics])
+    }
+  }
+}
+
+@_cdecl("init_plugin_haptics")
+func initPlugin() -> Plugin {
+  return HapticsPlugin()
+}
@@ -0,0 +1,148 @@
+package com.layers.keystore
+
+import android.app.Activity
+import android.security.keystore.KeyGenParameterSpec
+import android.security.keystore.KeyProperties
+import app.tauri.annotation.Command
+import app.tauri.annotation.InvokeArg
+import app.tauri.annotation.TauriPlugin
+import app.tauri.plugin.Invoke
+import app.tauri.plugin.JSObject
+import app.tauri.plugin.Plugin
+import java.io.File
+import java.security.GeneralSecurityException
+import java.security.KeyStore
+import java.security.MessageDigest
+import javax.crypto.Cipher
+import javax.crypto.KeyGenerator
+import javax.crypto.SecretKey
+import javax.crypto.spec.GCMParameterSpec
+
+private const val PROVIDER = "AndroidKeyStore"
+private const val ALIAS = "layers-credentials"
+private const val TRANSFORMATION = "AES/GCM/NoPadding"
+private const val IV_BYTES = 12
+private const val TAG_BITS = 128
+
+@InvokeArg
+class EntryArgs {
+    var service: String = ""
+    var account: String = ""
+}
+
+@InvokeArg
+class SetArgs {
+    var service: String = ""
+    var account: String = ""
+    var secret: String = ""
+}
+
+/**
+ * Credentials sealed with AES-GCM under a key that never leaves the Android Keystore, in
+ * secure hardware where the device has it. Only ciphertext reaches the disk, one file per
+ * entry in the no-backup directory: a restored backup couldn't be decrypted without the
+ * key anyway. The entry's name is bound in as associated data, so a file copied over
+ * another entry's won't open.
+ */
+@TauriPlugin
+class KeystorePlugin(private val activity: Activity) : Plugin(activity) {
+    private val dir: File by lazy { File(activity.noBackupFilesDir, "keystore").apply { mkdirs() } }
+
+    @Synchronized
+    private fun key(): SecretKey {
+        val store = KeyStore.getInstance(PROVIDER).apply { load(null) }
+        (store.getKey(ALIAS, null) as? SecretKey)?.let { return it }
+        val generator = KeyGenerator.getInstance(KeyProperties.KEY_ALGORITHM_AES, PROVIDER)
+        generator.init(
+            KeyGenParameterSpec.Builder(
+                ALIAS,
+                KeyProperties.PURPOSE_ENCRYPT or KeyProperties.PURPOSE_DECRYPT,
+            )
+                .setBlockModes(KeyProperties.BLOCK_MODE_GCM)
+                .setEncryptionPaddings(KeyProperties.ENCRYPTION_PADDING_NONE)
+                .setKeySize(256)
+                .build(),
+        )
+        return generator.generateKey()
+    }
+
+    private fun name(service: String, account: String) = "$service\u0000$account".toByteArray()
+
+    /** Hashed so any service and account make a valid file name. */
+    private fun file(name: ByteArray): File {
+        val digest = MessageDigest.getInstance("SHA-256").digest(name)
+        return File(dir, digest.joinToString("") { "%02x".format(it) })
+    }
+
+    /** Runs `work`, rejecting with `Unavailable` if the Keystore or the disk fails. */
+    private fun guarded(invoke: Invoke, work: () -> Unit) {
+        try {
+            work()
+        } catch (e: GeneralSecurityException) {
+            invoke.reject("the Android Keystore failed: ${e.message}", "Unavailable")
+        } catch (e: java.io.IOException) {
+            invoke.reject("credential storage failed: ${e.message}", "Unavailable")
+        }
+    }
+
+    @Command
+    fun set(invoke: Invoke) {
+        val args = invoke.parseArgs(SetArgs::class.java)
+        guarded(invoke) {
+            val name = name(args.service, args.account)
+            val cipher = Cipher.getInstance(TRANSFORMATION)
+            // The Keystore picks the IV; it won't take one from outside.
+            cipher.init(Cipher.ENCRYPT_MODE, key())
+            cipher.updateAAD(name)
+            val sealed = cipher.iv + cipher.doFinal(args.secret.toByteArray())
+
+            val target = file(name)
+            val tmp = File(dir, "${target.name}.tmp")
+            tmp.writeBytes(sealed)
+            if (!tmp.renameTo(target)) {
+                tmp.delete()
+                throw java.io.IOException("couldn't replace ${target.name}")
+            }
+            invoke.resolve()
+        }
+    }
+
+    @Command
+    fun get(invoke: Invoke) {
+        val args = invoke.parseArgs(EntryArgs::class.java)
+        guarded(invoke) {
+            val name = name(args.service, args.account)
+            val target = file(name)
+            if (!target.exists()) {
+                invoke.reject("no credential stored for this service and account", "NotFound")
+                return@guarded
+            }
+            val sealed = target.readBytes()
+            if (sealed.size <= IV_BYTES) {
+                invoke.reject("the stored credential is corrupted", "Unavailable")
+                return@guarded
+            }
+            val cipher = Cipher.getInstance(TRANSFORMATION)
+            cipher.init(
+                Cipher.DECRYPT_MODE,
+                key(),
+                GCMParameterSpec(TAG_BITS, sealed, 0, IV_BYTES),
+            )
+            cipher.updateAAD(name)
+            val secret = cipher.doFinal(sealed, IV_BYTES, sealed.size - IV_BYTES)
+            invoke.resolve(JSObject().put("secret", String(secret)))
+        }
+    }
+
+    @Command
+    fun delete(invoke: Invoke) {
+        val args = invoke.parseArgs(EntryArgs::class.java)
+        val target = file(name(args.service, args.account))
+        when {
+            !target.exists() ->
+                invoke.reject("no credential stored for this service and account", "NotFound")
+            target.delete() -> invoke.resolve()
+            else -> invoke.reject("couldn't delete the credential", "Unavailable")
+        }
+    }
+}
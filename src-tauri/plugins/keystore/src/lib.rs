@@ -0,0 +1,3 @@
+//! Native half of the OS credential store on Android, where keyring has no backend.
+//! There is no Rust API here: `layers` registers the Android plugin itself (see
+//! `src/keychain.rs`), and depends on this crate only so the Tauri CLI builds and links it.
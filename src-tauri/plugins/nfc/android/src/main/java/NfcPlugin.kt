@@ -0,0 +1,233 @@
+package com.layers.nfc
+
+import android.app.Activity
+import android.app.PendingIntent
+import android.content.Intent
+import android.nfc.FormatException
+import android.nfc.NdefMessage
+import android.nfc.NdefRecord
+import android.nfc.NfcAdapter
+import android.nfc.Tag
+import android.nfc.TagLostException
+import android.nfc.tech.Ndef
+import android.nfc.tech.NdefFormatable
+import android.os.Build
+import android.util.Base64
+import app.tauri.annotation.Command
+import app.tauri.annotation.InvokeArg
+import app.tauri.annotation.TauriPlugin
+import app.tauri.plugin.Channel
+import app.tauri.plugin.Invoke
+import app.tauri.plugin.JSArray
+import app.tauri.plugin.JSObject
+import app.tauri.plugin.Plugin
+import java.io.IOException
+import kotlin.concurrent.thread
+
+@InvokeArg
+class ScanArgs {
+    lateinit var channel: Channel
+}
+
+/** Byte fields cross the bridge as base64. */
+@InvokeArg
+class RecordArgs {
+    var tnf: Int = 0
+    var type: String = ""
+    var id: String = ""
+    var payload: String = ""
+}
+
+@InvokeArg
+class WriteArgs {
+    var records: Array<RecordArgs> = arrayOf()
+}
+
+private fun base64(bytes: ByteArray?): String = Base64.encodeToString(bytes ?: ByteArray(0), Base64.NO_WRAP)
+
+/**
+ * Tags reach the app through the adapter's foreground dispatch, which hands every tag to the
+ * activity while it's in front, only while a scan or a write is waiting for one.
+ */
+@TauriPlugin
+class NfcPlugin(private val activity: Activity) : Plugin(activity) {
+    private val adapter: NfcAdapter? = NfcAdapter.getDefaultAdapter(activity)
+    private var channel: Channel? = null
+    /** The next tag is written instead of reported. */
+    private var pendingWrite: Pair<NdefMessage, Invoke>? = null
+
+    private val dispatching: Boolean
+        get() = channel != null || pendingWrite != null
+
+    private fun ready(invoke: Invoke): Boolean {
+        val adapter = adapter
+        if (adapter == null) {
+            invoke.reject("this device has no NFC", "NotSupported")
+            return false
+        }
+        if (!adapter.isEnabled) {
+            invoke.reject("NFC is turned off", "AdapterDisabled")
+            return false
+        }
+        return true
+    }
+
+    private fun enableDispatch() {
+        val adapter = adapter ?: return
+        val intent = Intent(activity, activity.javaClass).addFlags(Intent.FLAG_ACTIVITY_SINGLE_TOP)
+        // Mutable so the system can attach the tag to it.
+        val flags = if (Build.VERSION.SDK_INT >= Build.VERSION_CODES.S) PendingIntent.FLAG_MUTABLE else 0
+        val pending = PendingIntent.getActivity(activity, 0, intent, flags)
+        // No filters or tech lists: every tag comes to us while we're in front.
+        adapter.enableForegroundDispatch(activity, pending, null, null)
+    }
+
+    /** Dispatch can only change on the main thread, and only while the activity is resumed. */
+    private fun updateDispatch() {
+        activity.runOnUiThread {
+            try {
+                if (dispatching) enableDispatch() else adapter?.disableForegroundDispatch(activity)
+            } catch (e: IllegalStateException) {
+                // Paused; onResume enables it again if it's still wanted.
+            }
+        }
+    }
+
+    override fun onResume() {
+        super.onResume()
+        if (dispatching) updateDispatch()
+    }
+
+    override fun onPause() {
+        super.onPause()
+        if (dispatching) adapter?.disableForegroundDispatch(activity)
+    }
+
+    override fun onNewIntent(intent: Intent) {
+        super.onNewIntent(intent)
+        val tag = if (Build.VERSION.SDK_INT >= Build.VERSION_CODES.TIRAMISU) {
+            intent.getParcelableExtra(NfcAdapter.EXTRA_TAG, Tag::class.java)
+        } else {
+            @Suppress("DEPRECATION")
+            intent.getParcelableExtra(NfcAdapter.EXTRA_TAG)
+        } ?: return
+
+        val write = pendingWrite
+        if (write != null) {
+            pendingWrite = null
+            updateDispatch()
+            // Tag I/O blocks, and this is the main thread.
+            thread { write(tag, write.first, write.second) }
+            return
+        }
+        channel?.send(serialize(tag))
+    }
+
+    private fun serialize(tag: Tag): JSObject {
+        val records = JSArray()
+        // Read when the tag was discovered, so this does no I/O.
+        Ndef.get(tag)?.cachedNdefMessage?.records?.forEach { record ->
+            records.put(
+                JSObject().apply {
+                    put("tnf", record.tnf.toInt())
+                    put("type", base64(record.type))
+                    put("id", base64(record.id))
+                    put("payload", base64(record.payload))
+                },
+            )
+        }
+        return JSObject().apply {
+            put("tagId", tag.id.joinToString("") { "%02x".format(it) })
+            put("technologies", JSArray(tag.techList.map { it.substringAfterLast('.') }))
+            put("ndefRecords", records)
+        }
+    }
+
+    private fun write(tag: Tag, message: NdefMessage, invoke: Invoke) {
+        try {
+            val ndef = Ndef.get(tag)
+            if (ndef != null) {
+                ndef.connect()
+                ndef.use {
+                    if (!it.isWritable) {
+                        invoke.reject("the tag is read-only", "ReadOnly")
+                        return
+                    }
+                    if (message.byteArrayLength > it.maxSize) {
+                        invoke.reject(
+                            "the message needs ${message.byteArrayLength} bytes and the tag holds ${it.maxSize}",
+                            "TooLarge",
+                        )
+                        return
+                    }
+                    it.writeNdefMessage(message)
+                }
+            } else {
+                val formatable = NdefFormatable.get(tag)
+                if (formatable == null) {
+                    invoke.reject("the tag can't hold NDEF data", "Failed")
+                    return
+                }
+                formatable.connect()
+                formatable.use { it.format(message) }
+            }
+            invoke.resolve()
+        } catch (e: TagLostException) {
+            invoke.reject("the tag moved away before the write finished", "TagLost")
+        } catch (e: IOException) {
+            invoke.reject(e.message ?: "writing the tag failed", "Failed")
+        } catch (e: FormatException) {
+            invoke.reject(e.message ?: "the tag's NDEF data is malformed", "Failed")
+        }
+    }
+
+    @Command
+    fun startScan(invoke: Invoke) {
+        val args = invoke.parseArgs(ScanArgs::class.java)
+        if (!ready(invoke)) return
+        channel = args.channel
+        updateDispatch()
+        invoke.resolve()
+    }
+
+    @Command
+    fun stopScan(invoke: Invoke) {
+        channel = null
+        updateDispatch()
+        invoke.resolve()
+    }
+
+    /** Resolves once the next tag has been written; a newer write replaces this one. */
+    @Command
+    fun writeNdef(invoke: Invoke) {
+        val args = invoke.parseArgs(WriteArgs::class.java)
+        if (!ready(invoke)) return
+        val message = try {
+            NdefMessage(
+                args.records.map {
+                    NdefRecord(
+                        it.tnf.toShort(),
+                        Base64.decode(it.type, Base64.DEFAULT),
+                        Base64.decode(it.id, Base64.DEFAULT),
+                        Base64.decode(it.payload, Base64.DEFAULT),
+                    )
+                }.toTypedArray(),
+            )
+        } catch (e: IllegalArgumentException) {
+            invoke.reject(e.message ?: "invalid NDEF record", "InvalidInput")
+            return
+        }
+        pendingWrite?.second?.reject("replaced by a newer write", "Failed")
+        pendingWrite = Pair(message, invoke)
+        updateDispatch()
+    }
+
+    /** Gives up on the waiting write, which rejects with `Timeout`. */
+    @Command
+    fun cancelWrite(invoke: Invoke) {
+        pendingWrite?.second?.reject("no tag was scanned in time", "Timeout")
+        pendingWrite = null
+        updateDispatch()
+        invoke.resolve()
+    }
+}
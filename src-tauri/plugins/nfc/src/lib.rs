@@ -0,0 +1,3 @@
+//! Native half of the NFC tag access, Android only. There is no Rust API here: `layers`
+//! registers the Android plugin itself (see `src/nfc.rs`), and depends on this crate only
+//! so the Tauri CLI builds and links it.
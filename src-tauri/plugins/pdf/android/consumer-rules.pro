@@ -0,0 +1,5 @@
+# Tauri instantiates the plugin and invokes its commands by reflection.
+-keep @app.tauri.annotation.TauriPlugin class com.layers.pdf.** { *; }
+-keep @app.tauri.annotation.InvokeArg class com.layers.pdf.** { *; }
+# Reaches package-private constructors in android.print, so it has to stay in that package.
+-keep class android.print.LayersPdfWriter { *; }
@@ -0,0 +1,50 @@
+package android.print
+
+import android.os.Bundle
+import android.os.CancellationSignal
+import android.os.ParcelFileDescriptor
+
+/**
+ * Runs a [PrintDocumentAdapter] to a file without the print dialog. The adapter callbacks'
+ * constructors are package-private, which is the only reason this lives in `android.print`.
+ */
+class LayersPdfWriter(private val attributes: PrintAttributes) {
+    interface Callback {
+        fun onLaidOut()
+        fun onFinished()
+        fun onFailed(message: String)
+    }
+
+    fun write(adapter: PrintDocumentAdapter, output: ParcelFileDescriptor, callback: Callback) {
+        fun fail(message: CharSequence?) {
+            adapter.onFinish()
+            callback.onFailed(message?.toString() ?: "the WebView could not print the page")
+        }
+
+        adapter.onStart()
+        adapter.onLayout(null, attributes, CancellationSignal(), object : PrintDocumentAdapter.LayoutResultCallback() {
+            override fun onLayoutFinished(info: PrintDocumentInfo, changed: Boolean) {
+                callback.onLaidOut()
+                adapter.onWrite(
+                    arrayOf(PageRange.ALL_PAGES),
+                    output,
+                    CancellationSignal(),
+                    object : PrintDocumentAdapter.WriteResultCallback() {
+                        override fun onWriteFinished(pages: Array<PageRange>) {
+                            adapter.onFinish()
+                            callback.onFinished()
+                        }
+
+                        override fun onWriteFailed(error: CharSequence?) = fail(error)
+
+                        override fun onWriteCancelled() = fail("printing was cancelled")
+                    },
+                )
+            }
+
+            override fun onLayoutFailed(error: CharSequence?) = fail(error)
+
+            override fun onLayoutCancelled() = fail("printing was cancelled")
+        }, Bundle())
+    }
+}
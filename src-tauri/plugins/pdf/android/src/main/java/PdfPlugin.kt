@@ -0,0 +1,140 @@
+package com.layers.pdf
+
+import android.annotation.SuppressLint
+import android.app.Activity
+import android.os.ParcelFileDescriptor
+import android.print.LayersPdfWriter
+import android.print.PrintAttributes
+import android.webkit.WebResourceError
+import android.webkit.WebResourceRequest
+import android.webkit.WebView
+import android.webkit.WebViewClient
+import app.tauri.annotation.Command
+import app.tauri.annotation.InvokeArg
+import app.tauri.annotation.TauriPlugin
+import app.tauri.plugin.Channel
+import app.tauri.plugin.Invoke
+import app.tauri.plugin.JSObject
+import app.tauri.plugin.Plugin
+import java.io.File
+import java.io.IOException
+import kotlin.math.roundToInt
+
+/** Sizes are in points; Rust has already turned the page for landscape. */
+@InvokeArg
+class GenerateArgs {
+    lateinit var html: String
+    lateinit var path: String
+    var pageWidth: Double = 0.0
+    var pageHeight: Double = 0.0
+    var margin: Double = 0.0
+    var headerHtml: String? = null
+    var footerHtml: String? = null
+    lateinit var onProgress: Channel
+}
+
+/**
+ * Loads the HTML into an offscreen WebView and writes its print document straight to the
+ * file, as `PrintManager` would after its dialog. The page therefore paginates like Chrome's
+ * print preview, `@page` and `break-before` included.
+ */
+@TauriPlugin
+class PdfPlugin(private val activity: Activity) : Plugin(activity) {
+    @SuppressLint("SetJavaScriptEnabled")
+    @Command
+    fun generatePdf(invoke: Invoke) {
+        val args = invoke.parseArgs(GenerateArgs::class.java)
+        activity.runOnUiThread {
+            val webView = WebView(activity)
+            webView.settings.javaScriptEnabled = true
+            webView.webViewClient = object : WebViewClient() {
+                // onPageFinished follows a failed load too; only one may answer.
+                private var answered = false
+
+                override fun onPageFinished(view: WebView, url: String?) {
+                    if (answered) return
+                    answered = true
+                    progress(args, 30)
+                    write(view, args, invoke)
+                }
+
+                override fun onReceivedError(
+                    view: WebView,
+                    request: WebResourceRequest,
+                    error: WebResourceError,
+                ) {
+                    if (!request.isForMainFrame || answered) return
+                    answered = true
+                    view.destroy()
+                    invoke.reject(error.description.toString(), "htmlParseError")
+                }
+            }
+            webView.loadDataWithBaseURL(null, document(args), "text/html", "utf-8", null)
+        }
+    }
+
+    /**
+     * Chrome repeats a table's header and footer groups on every printed page, which is the
+     * only per-page furniture it offers. They sit inside the margins, above and below the
+     * body, rather than in them.
+     */
+    private fun document(args: GenerateArgs): String {
+        if (args.headerHtml == null && args.footerHtml == null) return args.html
+        return """
+            <!DOCTYPE html>
+            <html><head><style>
+              table.layers-page { width: 100%; border-collapse: collapse; }
+              table.layers-page > * > tr > td { padding: 0; }
+            </style></head><body style="margin: 0"><table class="layers-page">
+              <thead><tr><td>${args.headerHtml.orEmpty()}</td></tr></thead>
+              <tfoot><tr><td>${args.footerHtml.orEmpty()}</td></tr></tfoot>
+              <tbody><tr><td>${args.html}</td></tr></tbody>
+            </table></body></html>
+        """.trimIndent()
+    }
+
+    private fun write(webView: WebView, args: GenerateArgs, invoke: Invoke) {
+        fun mils(points: Double) = (points * 1000 / 72).roundToInt()
+        val margin = mils(args.margin)
+        val attributes = PrintAttributes.Builder()
+            .setMediaSize(
+                PrintAttributes.MediaSize("layers", "Layers", mils(args.pageWidth), mils(args.pageHeight))
+            )
+            .setResolution(PrintAttributes.Resolution("pdf", "PDF", 300, 300))
+            .setColorMode(PrintAttributes.COLOR_MODE_COLOR)
+            .setMinMargins(PrintAttributes.Margins(margin, margin, margin, margin))
+            .build()
+        val output = try {
+            ParcelFileDescriptor.open(
+                File(args.path),
+                ParcelFileDescriptor.MODE_CREATE or
+                    ParcelFileDescriptor.MODE_TRUNCATE or
+                    ParcelFileDescriptor.MODE_WRITE_ONLY,
+            )
+        } catch (e: IOException) {
+            webView.destroy()
+            invoke.reject(e.message ?: "could not open ${args.path}", "outputWriteFailed")
+            return
+        }
+        val adapter = webView.createPrintDocumentAdapter("layers")
+        LayersPdfWriter(attributes).write(adapter, output, object : LayersPdfWriter.Callback {
+            override fun onLaidOut() = progress(args, 60)
+
+            override fun onFinished() {
+                output.close()
+                webView.destroy()
+                invoke.resolve()
+            }
+
+            override fun onFailed(message: String) {
+                output.close()
+                webView.destroy()
+                invoke.reject(message, "outputWriteFailed")
+            }
+        })
+    }
+
+    private fun progress(args: GenerateArgs, percent: Int) {
+        args.onProgress.send(JSObject().put("percent", percent))
+    }
+}
@@ -0,0 +1,122 @@
+import Tauri
+import UIKit
+import WebKit
+
+/// Sizes are in points; Rust has already turned the page for landscape.
+class GenerateArgs: Decodable {
+  let html: String
+  let path: String
+  let pageWidth: CGFloat
+  let pageHeight: CGFloat
+  let margin: CGFloat
+  let headerHtml: String?
+  let footerHtml: String?
+  let onProgress: Channel
+}
+
+struct Progress: Encodable {
+  let percent: Int
+}
+
+enum PdfError: Error {
+  case htmlParse(String)
+  case outputWriteFailed(String)
+
+  var code: String {
+    switch self {
+    case .htmlParse: return "htmlParseError"
+    case .outputWriteFailed: return "outputWriteFailed"
+    }
+  }
+
+  var message: String {
+    switch self {
+    case .htmlParse(let message), .outputWriteFailed(let message): return message
+    }
+  }
+}
+
+/// Draws the header and footer into the top and bottom margins of every page.
+class PageRenderer: UIPrintPageRenderer {
+  var header: NSAttributedString?
+  var footer: NSAttributedString?
+
+  override func drawHeaderForPage(at pageIndex: Int, in headerRect: CGRect) {
+    header?.draw(with: headerRect, options: .usesLineFragmentOrigin, context: nil)
+  }
+
+  override func drawFooterForPage(at pageIndex: Int, in footerRect: CGRect) {
+    footer?.draw(with: footerRect, options: .usesLineFragmentOrigin, context: nil)
+  }
+}
+
+class PdfPlugin: Plugin {
+  /// Print formatters and HTML attributed strings are made on the main thread only.
+  @objc public func generatePdf(_ invoke: Invoke) throws {
+    let args = try invoke.parseArgs(GenerateArgs.self)
+    DispatchQueue.main.async {
+      do {
+        try self.render(args)
+        invoke.resolve()
+      } catch let error as PdfError {
+        invoke.reject(error.message, code: error.code)
+      } catch {
+        invoke.reject(error.localizedDescription, code: "failed")
+      }
+    }
+  }
+
+  private func attributed(_ html: String) throws -> NSAttributedString {
+    do {
+      return try NSAttributedString(
+        data: Data(html.utf8),
+        options: [
+          .documentType: NSAttributedString.DocumentType.html,
+          .characterEncoding: String.Encoding.utf8.rawValue,
+        ],
+        documentAttributes: nil)
+    } catch {
+      throw PdfError.htmlParse(error.localizedDescription)
+    }
+  }
+
+  private func render(_ args: GenerateArgs) throws {
+    let renderer = PageRenderer()
+    renderer.addPrintFormatter(
+      UIMarkupTextPrintFormatter(markupText: args.html), startingAtPageAt: 0)
+    renderer.header = try args.headerHtml.map(attributed)
+    renderer.footer = try args.footerHtml.map(attributed)
+    // The header and footer take the top and bottom margins, drawn or not; the printable
+    // rect is only inset at the sides.
+    renderer.headerHeight = args.margin
+    renderer.footerHeight = args.margin
+
+    // Outside a print job the renderer has no paper; these keys are how it's given one.
+    let paper = CGRect(x: 0, y: 0, width: args.pageWidth, height: args.pageHeight)
+    renderer.setValue(NSValue(cgRect: paper), forKey: "paperRect")
+    renderer.setValue(
+      NSValue(cgRect: paper.insetBy(dx: args.margin, dy: 0)), forKey: "printableRect")
+
+    let data = NSMutableData()
+    UIGraphicsBeginPDFContextToData(data, paper, nil)
+    let pages = renderer.numberOfPages
+    renderer.prepare(forDrawingPages: NSRange(location: 0, length: pages))
+    for page in 0..<pages {
+      UIGraphicsBeginPDFPage()
+      renderer.drawPage(at: page, in: UIGraphicsGetPDFContextBounds())
+      try? args.onProgress.send(Progress(percent: (page + 1) * 100 / pages))
+    }
+    UIGraphicsEndPDFContext()
+
+    do {
+      try data.write(to: URL(fileURLWithPath: args.path), options: .atomic)
+    } catch {
+      throw PdfError.outputWriteFailed(error.localizedDescription)
+    }
+  }
+}
+
+@_cdecl("init_plugin_pdf")
+func initPlugin() -> Plugin {
+  return PdfPlugin()
+}
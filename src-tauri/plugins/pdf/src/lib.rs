@@ -0,0 +1,3 @@
+//! Native halves of HTML to PDF rendering on mobile. There is no Rust API here: `layers`
+//! registers the Android and iOS plugins itself (see `src/pdf.rs`), and depends on this
+//! crate only so the Tauri CLI builds and links them.
@@ -0,0 +1,3 @@
+# Tauri instantiates the plugin and invokes its commands by reflection.
+-keep @app.tauri.annotation.TauriPlugin class com.layers.quickactions.** { *; }
+-keep @app.tauri.annotation.InvokeArg class com.layers.quickactions.** { *; }
@@ -0,0 +1,129 @@
+package com.layers.quickactions
+
+import android.app.Activity
+import android.content.Intent
+import android.graphics.BitmapFactory
+import androidx.core.content.pm.ShortcutInfoCompat
+import androidx.core.content.pm.ShortcutManagerCompat
+import androidx.core.graphics.drawable.IconCompat
+import app.tauri.annotation.Command
+import app.tauri.annotation.InvokeArg
+import app.tauri.annotation.TauriPlugin
+import app.tauri.plugin.Channel
+import app.tauri.plugin.Invoke
+import app.tauri.plugin.JSObject
+import app.tauri.plugin.Plugin
+
+private const val ACTION_QUICK_ACTION = "com.layers.quickactions.LAUNCH"
+private const val EXTRA_ID = "com.layers.quickactions.ID"
+
+@InvokeArg
+class ActionArgs {
+    lateinit var id: String
+    lateinit var title: String
+    /** A system icon name; `iconPath` is used instead when this is null. */
+    var icon: String? = null
+    var iconPath: String? = null
+}
+
+@InvokeArg
+class SetArgs {
+    var actions: Array<ActionArgs> = arrayOf()
+}
+
+@InvokeArg
+class WatchArgs {
+    lateinit var channel: Channel
+}
+
+/** Android has no shortcut icon set of its own, so the system names map to framework drawables. */
+private fun systemIcon(name: String): Int? = when (name) {
+    "compose" -> android.R.drawable.ic_menu_edit
+    "search" -> android.R.drawable.ic_menu_search
+    "share" -> android.R.drawable.ic_menu_share
+    "add" -> android.R.drawable.ic_menu_add
+    "favorite" -> android.R.drawable.btn_star_big_on
+    "time" -> android.R.drawable.ic_menu_recent_history
+    "mail" -> android.R.drawable.ic_dialog_email
+    "location" -> android.R.drawable.ic_menu_mylocation
+    else -> null
+}
+
+/**
+ * Quick actions are dynamic shortcuts whose intent reopens this activity with the action's ID.
+ * A cold launch carries it in the activity's starting intent, a warm one in `onNewIntent`.
+ */
+@TauriPlugin
+class QuickActionsPlugin(private val activity: Activity) : Plugin(activity) {
+    private var channel: Channel? = null
+    /** Launches seen before Rust started listening. */
+    private val pending = mutableListOf<String>()
+
+    init {
+        activity.intent?.let { launched(it) }
+    }
+
+    override fun onNewIntent(intent: Intent) {
+        super.onNewIntent(intent)
+        launched(intent)
+    }
+
+    private fun launched(intent: Intent) {
+        if (intent.action != ACTION_QUICK_ACTION) return
+        val id = intent.getStringExtra(EXTRA_ID) ?: return
+        // Launchers rank shortcuts by use.
+        ShortcutManagerCompat.reportShortcutUsed(activity, id)
+        val channel = channel
+        if (channel == null) {
+            pending.add(id)
+        } else {
+            channel.send(JSObject().apply { put("id", id) })
+        }
+    }
+
+    @Command
+    fun setQuickActions(invoke: Invoke) {
+        val args = invoke.parseArgs(SetArgs::class.java)
+        val shortcuts = args.actions.map { action ->
+            val icon = action.icon?.let { name ->
+                systemIcon(name)?.let { IconCompat.createWithResource(activity, it) }
+            } ?: action.iconPath?.let { path ->
+                BitmapFactory.decodeFile(path)?.let { IconCompat.createWithBitmap(it) }
+            }
+            if (icon == null) {
+                invoke.reject("can't load the icon for `${action.id}`", "InvalidIcon")
+                return
+            }
+            val intent = Intent(activity, activity.javaClass)
+                .setAction(ACTION_QUICK_ACTION)
+                .putExtra(EXTRA_ID, action.id)
+                .addFlags(Intent.FLAG_ACTIVITY_SINGLE_TOP)
+            // Android has no subtitle line to put `subtitle` on.
+            ShortcutInfoCompat.Builder(activity, action.id)
+                .setShortLabel(action.title)
+                .setIcon(icon)
+                .setIntent(intent)
+                .build()
+        }
+        try {
+            if (!ShortcutManagerCompat.setDynamicShortcuts(activity, shortcuts)) {
+                invoke.reject("the launcher is rate-limiting shortcut updates", "Failed")
+                return
+            }
+        } catch (e: IllegalArgumentException) {
+            invoke.reject(e.message ?: "invalid shortcut", "InvalidInput")
+            return
+        }
+        invoke.resolve()
+    }
+
+    /** Sends `{id}` for every quick-action launch, starting with any that came first. */
+    @Command
+    fun watchLaunches(invoke: Invoke) {
+        val args = invoke.parseArgs(WatchArgs::class.java)
+        channel = args.channel
+        pending.forEach { id -> args.channel.send(JSObject().apply { put("id", id) }) }
+        pending.clear()
+        invoke.resolve()
+    }
+}
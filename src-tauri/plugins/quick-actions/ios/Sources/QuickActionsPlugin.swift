@@ -0,0 +1,115 @@
+import ObjectiveC
+import Tauri
+import UIKit
+import WebKit
+
+class ActionArgs: Decodable {
+  let id: String
+  let title: String
+  let subtitle: String?
+  /// A system icon name; `iconPath` is used instead when this is nil.
+  let icon: String?
+  let iconPath: String?
+}
+
+class SetArgs: Decodable {
+  let actions: [ActionArgs]
+}
+
+class WatchArgs: Decodable {
+  let channel: Channel
+}
+
+struct Launch: Encodable {
+  let id: String
+}
+
+private func systemIcon(_ name: String) -> UIApplicationShortcutIcon.IconType? {
+  switch name {
+  case "compose": return .compose
+  case "search": return .search
+  case "share": return .share
+  case "add": return .add
+  case "favorite": return .favorite
+  case "time": return .time
+  case "mail": return .mail
+  case "location": return .location
+  default: return nil
+  }
+}
+
+private typealias PerformAction = @convention(block) (
+  AnyObject, UIApplication, UIApplicationShortcutItem, @escaping (Bool) -> Void
+) -> Void
+
+class QuickActionsPlugin: Plugin {
+  private var channel: Channel?
+  /// Launches seen before Rust started listening.
+  private var pending: [String] = []
+
+  /// The app delegate belongs to tao and doesn't handle shortcut items, so the handler is
+  /// added to its class. iOS hands over the item that launched the app only after
+  /// `didFinishLaunching` returns, which is after plugins load.
+  override func load(webview: WKWebView) {
+    guard let delegate = UIApplication.shared.delegate else { return }
+    let handler: PerformAction = { [weak self] _, _, item, completion in
+      self?.launched(item.type)
+      completion(true)
+    }
+    let selector = #selector(
+      UIApplicationDelegate.application(_:performActionFor:completionHandler:))
+    class_addMethod(
+      type(of: delegate), selector, imp_implementationWithBlock(handler), "v@:@@@?")
+  }
+
+  private func launched(_ id: String) {
+    if let channel = channel {
+      try? channel.send(Launch(id: id))
+    } else {
+      pending.append(id)
+    }
+  }
+
+  @objc public func setQuickActions(_ invoke: Invoke) throws {
+    let args = try invoke.parseArgs(SetArgs.self)
+    var items: [UIApplicationShortcutItem] = []
+    for action in args.actions {
+      let icon: UIApplicationShortcutIcon
+      if let type = action.icon.flatMap(systemIcon) {
+        icon = UIApplicationShortcutIcon(type: type)
+      } else if let path = action.iconPath, UIImage(named: path) != nil {
+        // Only images in the app bundle can be shortcut icons.
+        icon = UIApplicationShortcutIcon(templateImageName: path)
+      } else {
+        invoke.reject("can't load the icon for `\(action.id)`", code: "InvalidIcon")
+        return
+      }
+      items.append(
+        UIApplicationShortcutItem(
+          type: action.id, localizedTitle: action.title, localizedSubtitle: action.subtitle,
+          icon: icon, userInfo: nil))
+    }
+    DispatchQueue.main.async {
+      UIApplication.shared.shortcutItems = items
+      invoke.resolve()
+    }
+  }
+
+  /// Sends `{id}` for every quick-action launch, starting with any that came first.
+  @objc public func watchLaunches(_ invoke: Invoke) throws {
+    let args = try invoke.parseArgs(WatchArgs.self)
+    DispatchQueue.main.async {
+      self.channel = args.channel
+      for id in self.pending {
+        try? args.channel.send(Launch(id: id))
+      }
+      self.pending.removeAll()
+      invoke.resolve()
+    }
+  }
+}
+
+@_cdecl("init_plugin_quick_actions")
+func initPlugin() -> Plugin {
+  return QuickActionsPlugin()
+}
@@ -0,0 +1,93 @@
+package com.layers.scanner
+
+import android.Manifest
+import android.app.Activity
+import androidx.activity.result.ActivityResult
+import app.tauri.PermissionState
+import app.tauri.annotation.ActivityCallback
+import app.tauri.annotation.Command
+import app.tauri.annotation.InvokeArg
+import app.tauri.annotation.Permission
+import app.tauri.annotation.PermissionCallback
+import app.tauri.annotation.TauriPlugin
+import app.tauri.plugin.Invoke
+import app.tauri.plugin.JSObject
+import app.tauri.plugin.Plugin
+import com.journeyapps.barcodescanner.ScanIntentResult
+import com.journeyapps.barcodescanner.ScanOptions
+
+@InvokeArg
+class ScanArgs {
+    /** `BarcodeFormat` names from the Rust side, e.g. `qrCode`. */
+    var formats: Array<String> = arrayOf()
+}
+
+@TauriPlugin(
+    permissions = [
+        Permission(strings = [Manifest.permission.CAMERA], alias = "camera"),
+    ],
+)
+class ScannerPlugin(private val activity: Activity) : Plugin(activity) {
+    @Command
+    fun scan(invoke: Invoke) {
+        if (getPermissionState("camera") == PermissionState.GRANTED) {
+            startScan(invoke)
+        } else {
+            requestPermissionForAlias("camera", invoke, "cameraPermission")
+        }
+    }
+
+    @PermissionCallback
+    private fun cameraPermission(invoke: Invoke) {
+        if (getPermissionState("camera") == PermissionState.GRANTED) {
+            startScan(invoke)
+        } else {
+            invoke.reject("camera permission denied", "PermissionDenied")
+        }
+    }
+
+    private fun startScan(invoke: Invoke) {
+        val args = invoke.parseArgs(ScanArgs::class.java)
+        val formats = args.formats.mapNotNull { FORMATS[it] }
+        val options = ScanOptions().apply {
+            if (formats.isNotEmpty()) {
+                setDesiredBarcodeFormats(formats)
+            }
+            setBeepEnabled(false)
+            setOrientationLocked(false)
+            setPrompt("")
+        }
+        startActivityForResult(invoke, options.createScanIntent(activity), "scanned")
+    }
+
+    /** ZXing's capture activity reports no result points, so there is no bounding box. */
+    @ActivityCallback
+    private fun scanned(invoke: Invoke, result: ActivityResult) {
+        val scan = ScanIntentResult.parseActivityResult(result.resultCode, result.data)
+        val contents = scan.contents
+        if (contents == null) {
+            invoke.reject("scan cancelled", "Cancelled")
+            return
+        }
+        val format = FORMATS.entries.firstOrNull { it.value == scan.formatName }?.key
+        if (format == null) {
+            invoke.reject("unsupported barcode format: ${scan.formatName}", "Failed")
+            return
+        }
+        val ret = JSObject()
+        ret.put("rawValue", contents)
+        ret.put("format", format)
+        invoke.resolve(ret)
+    }
+
+    companion object {
+        private val FORMATS = mapOf(
+            "qrCode" to ScanOptions.QR_CODE,
+            "ean13" to ScanOptions.EAN_13,
+            "ean8" to ScanOptions.EAN_8,
+            "code128" to ScanOptions.CODE_128,
+            "dataMatrix" to ScanOptions.DATA_MATRIX,
+            "pdf417" to ScanOptions.PDF_417,
+        )
+    }
+}
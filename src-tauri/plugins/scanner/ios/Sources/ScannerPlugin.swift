@@ -0,0 +1,176 @@
+import AVFoundation
+import Tauri
+import UIKit
+import Vision
+import WebKit
+
+class ScanArgs: Decodable {
+  /// `BarcodeFormat` names from the Rust side, e.g. `qrCode`.
+  let formats: [String]
+}
+
+private let symbologies: [String: VNBarcodeSymbology] = [
+  "qrCode": .qr,
+  "ean13": .ean13,
+  "ean8": .ean8,
+  "code128": .code128,
+  "dataMatrix": .dataMatrix,
+  "pdf417": .pdf417,
+]
+
+/// Full-screen camera preview that runs Vision's barcode detector on each frame until
+/// one matches, then hands the observation back.
+class ScannerViewController: UIViewController, AVCaptureVideoDataOutputSampleBufferDelegate {
+  private let session = AVCaptureSession()
+  private let output = AVCaptureVideoDataOutput()
+  private let queue = DispatchQueue(label: "com.layers.scanner.frames")
+  private let symbologies: [VNBarcodeSymbology]
+  private var finished = false
+  var onFinish: ((VNBarcodeObservation?, CGSize) -> Void)?
+
+  init(symbologies: [VNBarcodeSymbology]) {
+    self.symbologies = symbologies
+    super.init(nibName: nil, bundle: nil)
+    modalPresentationStyle = .fullScreen
+  }
+
+  required init?(coder: NSCoder) {
+    fatalError("init(coder:) is not supported")
+  }
+
+  func configure() throws {
+    guard let device = AVCaptureDevice.default(for: .video) else {
+      throw NSError(domain: "scanner", code: 0, userInfo: [NSLocalizedDescriptionKey: "no camera"])
+    }
+    let input = try AVCaptureDeviceInput(device: device)
+    session.beginConfiguration()
+    session.sessionPreset = .high
+    if session.canAddInput(input) { session.addInput(input) }
+    output.alwaysDiscardsLateVideoFrames = true
+    output.setSampleBufferDelegate(self, queue: queue)
+    if session.canAddOutput(output) { session.addOutput(output) }
+    session.commitConfiguration()
+  }
+
+  override func viewDidLoad() {
+    super.viewDidLoad()
+    view.backgroundColor = .black
+    let preview = AVCaptureVideoPreviewLayer(session: session)
+    preview.videoGravity = .resizeAspectFill
+    preview.frame = view.bounds
+    view.layer.addSublayer(preview)
+
+    let cancel = UIButton(type: .system)
+    cancel.setTitle("Cancel", for: .normal)
+    cancel.setTitleColor(.white, for: .normal)
+    cancel.addTarget(self, action: #selector(cancelTapped), for: .touchUpInside)
+    cancel.translatesAutoresizingMaskIntoConstraints = false
+    view.addSubview(cancel)
+    NSLayoutConstraint.activate([
+      cancel.centerXAnchor.constraint(equalTo: view.centerXAnchor),
+      cancel.bottomAnchor.constraint(equalTo: view.safeAreaLayoutGuide.bottomAnchor, constant: -24),
+    ])
+  }
+
+  override func viewWillAppear(_ animated: Bool) {
+    super.viewWillAppear(animated)
+    queue.async { self.session.startRunning() }
+  }
+
+  override func viewWillDisappear(_ animated: Bool) {
+    super.viewWillDisappear(animated)
+    queue.async { self.session.stopRunning() }
+  }
+
+  @objc private func cancelTapped() {
+    finish(nil, .zero)
+  }
+
+  private func finish(_ observation: VNBarcodeObservation?, _ size: CGSize) {
+    DispatchQueue.main.async {
+      guard !self.finished else { return }
+      self.finished = true
+      self.dismiss(animated: true) { self.onFinish?(observation, size) }
+    }
+  }
+
+  func captureOutput(
+    _ output: AVCaptureOutput, didOutput sampleBuffer: CMSampleBuffer,
+    from connection: AVCaptureConnection
+  ) {
+    guard !finished, let buffer = CMSampleBufferGetImageBuffer(sampleBuffer) else { return }
+    let size = CGSize(
+      width: CVPixelBufferGetWidth(buffer), height: CVPixelBufferGetHeight(buffer))
+    let request = VNDetectBarcodesRequest()
+    request.symbologies = symbologies
+    try? VNImageRequestHandler(cvPixelBuffer: buffer, options: [:]).perform([request])
+    if let match = request.results?.first(where: { $0.payloadStringValue != nil }) {
+      finish(match, size)
+    }
+  }
+}
+
+class ScannerPlugin: Plugin {
+  @objc public func scan(_ invoke: Invoke) throws {
+    let args = try invoke.parseArgs(ScanArgs.self)
+    let wanted = args.formats.compactMap { symbologies[$0] }
+    let selected = wanted.isEmpty ? Array(symbologies.values) : wanted
+
+    switch AVCaptureDevice.authorizationStatus(for: .video) {
+    case .authorized:
+      present(invoke, selected)
+    case .notDetermined:
+      AVCaptureDevice.requestAccess(for: .video) { granted in
+        if granted {
+          self.present(invoke, selected)
+        } else {
+          invoke.reject("camera permission denied", code: "PermissionDenied")
+        }
+      }
+    default:
+      invoke.reject("camera permission denied", code: "PermissionDenied")
+    }
+  }
+
+  private func present(_ invoke: Invoke, _ selected: [VNBarcodeSymbology]) {
+    DispatchQueue.main.async {
+      guard let presenter = self.manager.viewController else {
+        invoke.reject("no view controller to present from", code: "Failed")
+        return
+      }
+      let controller = ScannerViewController(symbologies: selected)
+      do {
+        try controller.configure()
+      } catch {
+        invoke.reject(error.localizedDescription, code: "Failed")
+        return
+      }
+      controller.onFinish = { observation, size in
+        guard let observation = observation, let value = observation.payloadStringValue else {
+          invoke.reject("scan cancelled", code: "Cancelled")
+          return
+        }
+        let format = symbologies.first { $0.value == observation.symbology }?.key ?? ""
+        // Vision's box is normalized with a bottom-left origin; report it in frame
+        // pixels from the top left.
+        let box = observation.boundingBox
+        invoke.resolve([
+          "rawValue": value,
+          "format": format,
+          "boundingBox": [
+            "x": box.minX * size.width,
+            "y": (1 - box.maxY) * size.height,
+            "width": box.width * size.width,
+            "height": box.height * size.height,
+          ],
+        ])
+      }
+      presenter.present(controller, animated: true)
+    }
+  }
+}
+
+@_cdecl("init_plugin_scanner")
+func initPlugin() -> Plugin {
+  return ScannerPlugin()
+}
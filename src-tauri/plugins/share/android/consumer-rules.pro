@@ -0,0 +1,5 @@
+# Tauri instantiates the plugin and invokes its commands by reflection, and the
+# provider is only named in the manifest.
+-keep @app.tauri.annotation.TauriPlugin class com.layers.share.** { *; }
+-keep @app.tauri.annotation.InvokeArg class com.layers.share.** { *; }
+-keep class com.layers.share.ShareFileProvider
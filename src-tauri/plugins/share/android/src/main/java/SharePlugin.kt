@@ -0,0 +1,227 @@
+package com.layers.share
+
+import android.app.Activity
+import android.app.PendingIntent
+import android.content.BroadcastReceiver
+import android.content.ClipData
+import android.content.ComponentName
+import android.content.Context
+import android.content.Intent
+import android.content.IntentFilter
+import android.net.Uri
+import android.os.Build
+import android.os.Handler
+import android.os.Looper
+import android.webkit.MimeTypeMap
+import android.webkit.WebView
+import androidx.activity.result.ActivityResult
+import androidx.core.content.ContextCompat
+import androidx.core.content.FileProvider
+import app.tauri.annotation.ActivityCallback
+import app.tauri.annotation.Command
+import app.tauri.annotation.InvokeArg
+import app.tauri.annotation.TauriPlugin
+import app.tauri.plugin.Invoke
+import app.tauri.plugin.JSObject
+import app.tauri.plugin.Plugin
+import java.io.File
+
+@InvokeArg
+class ShareTextArgs {
+    lateinit var text: String
+    var title: String? = null
+}
+
+@InvokeArg
+class ShareFileArgs {
+    lateinit var path: String
+    var mime: String? = null
+}
+
+/** One entry of the Rust side's flattened `ShareContent`; `type` says which fields are set. */
+@InvokeArg
+class ShareItem {
+    lateinit var type: String
+    var text: String? = null
+    var url: String? = null
+    var path: String? = null
+    var mime: String? = null
+}
+
+@InvokeArg
+class ShareArgs {
+    var items: Array<ShareItem> = arrayOf()
+}
+
+/** Its own subclass, so the manifest entry can't clash with another library's provider. */
+class ShareFileProvider : FileProvider()
+
+@TauriPlugin
+class SharePlugin(private val activity: Activity) : Plugin(activity) {
+    /** Package of the target picked in the last chooser, reported by [chosenReceiver]. */
+    private var chosen: String? = null
+
+    private val chosenReceiver = object : BroadcastReceiver() {
+        override fun onReceive(context: Context, intent: Intent) {
+            val component = if (Build.VERSION.SDK_INT >= 33) {
+                intent.getParcelableExtra(Intent.EXTRA_CHOSEN_COMPONENT, ComponentName::class.java)
+            } else {
+                @Suppress("DEPRECATION")
+                intent.getParcelableExtra(Intent.EXTRA_CHOSEN_COMPONENT)
+            }
+            chosen = component?.packageName
+        }
+    }
+
+    override fun load(webView: WebView) {
+        super.load(webView)
+        ContextCompat.registerReceiver(
+            activity,
+            chosenReceiver,
+            IntentFilter(CHOSEN_ACTION),
+            ContextCompat.RECEIVER_NOT_EXPORTED,
+        )
+    }
+
+    @Command
+    fun shareText(invoke: Invoke) {
+        val args = invoke.parseArgs(ShareTextArgs::class.java)
+        val intent = Intent(Intent.ACTION_SEND).apply {
+            type = "text/plain"
+            putExtra(Intent.EXTRA_TEXT, args.text)
+            args.title?.let { putExtra(Intent.EXTRA_SUBJECT, it) }
+        }
+        present(invoke, intent, args.title)
+    }
+
+    @Command
+    fun shareFile(invoke: Invoke) {
+        val args = invoke.parseArgs(ShareFileArgs::class.java)
+        val file = File(args.path)
+        if (!file.isFile) {
+            invoke.reject("file not found: ${args.path}")
+            return
+        }
+        val uri = try {
+            contentUri(file)
+        } catch (e: Exception) {
+            invoke.reject("can't share ${args.path}: ${e.message}")
+            return
+        }
+        val mime = args.mime
+            ?: MimeTypeMap.getSingleton().getMimeTypeFromExtension(file.extension.lowercase())
+            ?: "application/octet-stream"
+        val intent = Intent(Intent.ACTION_SEND).apply {
+            type = mime
+            putExtra(Intent.EXTRA_STREAM, uri)
+            // The grant below only covers URIs in the clip data.
+            clipData = ClipData.newRawUri(file.name, uri)
+            addFlags(Intent.FLAG_GRANT_READ_URI_PERMISSION)
+        }
+        present(invoke, intent, null)
+    }
+
+    /**
+     * Text and links travel together as one `EXTRA_TEXT`, since targets only read one;
+     * several files go out as `ACTION_SEND_MULTIPLE`.
+     */
+    @Command
+    fun share(invoke: Invoke) {
+        val args = invoke.parseArgs(ShareArgs::class.java)
+        val text = args.items.mapNotNull {
+            when (it.type) {
+                "text" -> it.text
+                "url" -> it.url
+                else -> null
+            }
+        }
+        val files = args.items.filter { it.type == "file" }
+        val uris = ArrayList<Uri>()
+        for (item in files) {
+            val file = File(item.path ?: "")
+            if (!file.isFile) {
+                invoke.reject("file not found: ${item.path}")
+                return
+            }
+            try {
+                uris.add(contentUri(file))
+            } catch (e: Exception) {
+                invoke.reject("can't share ${item.path}: ${e.message}")
+                return
+            }
+        }
+        val mimes = files.map { it.mime ?: "application/octet-stream" }.distinct()
+        val intent = if (uris.size > 1) {
+            Intent(Intent.ACTION_SEND_MULTIPLE).putParcelableArrayListExtra(Intent.EXTRA_STREAM, uris)
+        } else {
+            Intent(Intent.ACTION_SEND).apply {
+                uris.firstOrNull()?.let { putExtra(Intent.EXTRA_STREAM, it) }
+            }
+        }
+        intent.type = when {
+            mimes.isEmpty() -> "text/plain"
+            mimes.size == 1 -> mimes[0]
+            mimes.map { it.substringBefore('/') }.distinct().size == 1 ->
+                "${mimes[0].substringBefore('/')}/*"
+            else -> "*/*"
+        }
+        if (text.isNotEmpty()) {
+            intent.putExtra(Intent.EXTRA_TEXT, text.joinToString("\n"))
+        }
+        if (uris.isNotEmpty()) {
+            // The grant below only covers URIs in the clip data.
+            val clip = ClipData.newRawUri(null, uris[0])
+            uris.drop(1).forEach { clip.addItem(ClipData.Item(it)) }
+            intent.clipData = clip
+            intent.addFlags(Intent.FLAG_GRANT_READ_URI_PERMISSION)
+        }
+        present(invoke, intent, null)
+    }
+
+    /** Files outside the provider's roots are shared as a copy in the cache. */
+    private fun contentUri(file: File): Uri {
+        val authority = "${activity.packageName}.share.fileprovider"
+        return try {
+            FileProvider.getUriForFile(activity, authority, file)
+        } catch (e: IllegalArgumentException) {
+            val copy = File(File(activity.cacheDir, "share"), file.name)
+            copy.parentFile?.mkdirs()
+            file.copyTo(copy, overwrite = true)
+            FileProvider.getUriForFile(activity, authority, copy)
+        }
+    }
+
+    private fun present(invoke: Invoke, intent: Intent, title: String?) {
+        chosen = null
+        val callback = Intent(CHOSEN_ACTION).setPackage(activity.packageName)
+        val mutable = if (Build.VERSION.SDK_INT >= 31) PendingIntent.FLAG_MUTABLE else 0
+        val sender = PendingIntent.getBroadcast(
+            activity,
+            0,
+            callback,
+            PendingIntent.FLAG_UPDATE_CURRENT or mutable,
+        ).intentSender
+        startActivityForResult(invoke, Intent.createChooser(intent, title, sender), "chooserClosed")
+    }
+
+    /**
+     * The chooser always finishes with RESULT_CANCELED, so whether a target was picked
+     * comes from [chosenReceiver], whose broadcast can land just after this.
+     */
+    @ActivityCallback
+    private fun chooserClosed(invoke: Invoke, @Suppress("UNUSED_PARAMETER") result: ActivityResult) {
+        Handler(Looper.getMainLooper()).postDelayed({
+            val target = chosen
+            chosen = null
+            val ret = JSObject()
+            ret.put("completed", target != null)
+            target?.let { ret.put("activity", it) }
+            invoke.resolve(ret)
+        }, BROADCAST_GRACE_MS)
+    }
+
+    companion object {
+        private const val CHOSEN_ACTION = "com.layers.share.CHOSEN"
+        private const val BROADCAST_GRACE_MS = 300L
+    }
+}
@@ -0,0 +1,131 @@
+import Tauri
+import UIKit
+import WebKit
+
+class ShareTextArgs: Decodable {
+  let text: String
+  let title: String?
+}
+
+class ShareFileArgs: Decodable {
+  let path: String
+  let mime: String?
+}
+
+/// One entry of the Rust side's flattened `ShareContent`; `type` says which fields are set.
+class ShareItem: Decodable {
+  let type: String
+  let text: String?
+  let url: String?
+  let path: String?
+}
+
+class ShareArgs: Decodable {
+  let items: [ShareItem]
+}
+
+/// Shares text with a subject line, which targets like Mail use as the title.
+class TextItem: NSObject, UIActivityItemSource {
+  let text: String
+  let subject: String?
+
+  init(text: String, subject: String?) {
+    self.text = text
+    self.subject = subject
+  }
+
+  func activityViewControllerPlaceholderItem(_ controller: UIActivityViewController) -> Any {
+    text
+  }
+
+  func activityViewController(
+    _ controller: UIActivityViewController,
+    itemForActivityType activityType: UIActivity.ActivityType?
+  ) -> Any? {
+    text
+  }
+
+  func activityViewController(
+    _ controller: UIActivityViewController,
+    subjectForActivityType activityType: UIActivity.ActivityType?
+  ) -> String {
+    subject ?? ""
+  }
+}
+
+class SharePlugin: Plugin {
+  @objc public func shareText(_ invoke: Invoke) throws {
+    let args = try invoke.parseArgs(ShareTextArgs.self)
+    present(invoke, items: [TextItem(text: args.text, subject: args.title)])
+  }
+
+  /// Files in the app container are shared by URL; the system hands out access itself.
+  @objc public func shareFile(_ invoke: Invoke) throws {
+    let args = try invoke.parseArgs(ShareFileArgs.self)
+    guard FileManager.default.fileExists(atPath: args.path) else {
+      invoke.reject("file not found: \(args.path)")
+      return
+    }
+    present(invoke, items: [URL(fileURLWithPath: args.path)])
+  }
+
+  @objc public func share(_ invoke: Invoke) throws {
+    let args = try invoke.parseArgs(ShareArgs.self)
+    var items: [Any] = []
+    for item in args.items {
+      switch item.type {
+      case "text":
+        items.append(item.text ?? "")
+      case "url":
+        guard let url = item.url.flatMap(URL.init(string:)) else {
+          invoke.reject("invalid URL: \(item.url ?? "")")
+          return
+        }
+        items.append(url)
+      default:
+        let path = item.path ?? ""
+        guard FileManager.default.fileExists(atPath: path) else {
+          invoke.reject("file not found: \(path)")
+          return
+        }
+        items.append(URL(fileURLWithPath: path))
+      }
+    }
+    present(invoke, items: items)
+  }
+
+  /// Resolves when the sheet is dismissed, with the chosen activity type if any.
+  private func present(_ invoke: Invoke, items: [Any]) {
+    DispatchQueue.main.async {
+      guard let presenter = self.manager.viewController else {
+        invoke.reject("no view controller to present from")
+        return
+      }
+      let controller = UIActivityViewController(activityItems: items, applicationActivities: nil)
+      controller.completionWithItemsHandler = { activityType, completed, _, error in
+        if let error = error {
+          invoke.reject(error.localizedDescription)
+          return
+        }
+        var result: JsonObject = ["completed": completed]
+        if let activityType = activityType {
+          result["activity"] = activityType.rawValue
+        }
+        invoke.resolve(result)
+      }
+      // iPad presents the sheet as a popover, which needs an anchor.
+      if let popover = controller.popoverPresentationController {
+        popover.sourceView = presenter.view
+        popover.sourceRect = CGRect(
+          x: presenter.view.bounds.midX, y: presenter.view.bounds.midY, width: 0, height: 0)
+        popover.permittedArrowDirections = []
+      }
+      presenter.present(controller, animated: true)
+    }
+  }
+}
+
+@_cdecl("init_plugin_share")
+func initPlugin() -> Plugin {
+  return SharePlugin()
+}
@@ -0,0 +1,3 @@
+//! Native halves of the share sheet. There is no Rust API here: `layers` registers the
+//! Android and iOS plugins itself (see `src/share.rs`), and depends on this crate only
+//! so the Tauri CLI builds and links them.
@@ -0,0 +1,303 @@
+package com.layers.speech
+
+import android.Manifest
+import android.app.Activity
+import android.content.Intent
+import android.os.Bundle
+import android.speech.RecognitionListener
+import android.speech.RecognizerIntent
+import android.speech.SpeechRecognizer
+import android.speech.tts.TextToSpeech
+import android.speech.tts.UtteranceProgressListener
+import app.tauri.PermissionState
+import app.tauri.annotation.Command
+import app.tauri.annotation.InvokeArg
+import app.tauri.annotation.Permission
+import app.tauri.annotation.PermissionCallback
+import app.tauri.annotation.TauriPlugin
+import app.tauri.plugin.Channel
+import app.tauri.plugin.Invoke
+import app.tauri.plugin.JSArray
+import app.tauri.plugin.JSObject
+import app.tauri.plugin.Plugin
+import java.util.concurrent.ConcurrentHashMap
+
+@InvokeArg
+class SpeakArgs {
+    var id: Long = 0
+    var text: String = ""
+    /** The engine's default voice when null. */
+    var voiceId: String? = null
+    var rate: Float = 1f
+    var pitch: Float = 1f
+    lateinit var onEvent: Channel
+}
+
+@InvokeArg
+class StopArgs {
+    var id: Long = 0
+}
+
+@InvokeArg
+class DictationArgs {
+    /** A BCP 47 tag; the system language when null. */
+    var language: String? = null
+    lateinit var onTranscript: Channel
+}
+
+@TauriPlugin(
+    permissions = [
+        Permission(strings = [Manifest.permission.RECORD_AUDIO], alias = "microphone"),
+    ],
+)
+class SpeechPlugin(private val activity: Activity) : Plugin(activity) {
+    /** Bound on first use; binding to the engine's service takes a moment. */
+    private var tts: TextToSpeech? = null
+    private var ttsReady = false
+    /** Work waiting for the engine to bind, told whether it did. */
+    private val waiting = mutableListOf<(Boolean) -> Unit>()
+    /** Channels of the utterances still being read, by id. */
+    private val utterances = ConcurrentHashMap<String, Channel>()
+    /** Only touched on the main thread, where its callbacks run too. */
+    private var recognizer: SpeechRecognizer? = null
+
+    private val progress = object : UtteranceProgressListener() {
+        override fun onStart(utteranceId: String) {}
+
+        override fun onDone(utteranceId: String) = end(utteranceId, false)
+
+        @Deprecated("Deprecated in Java")
+        override fun onError(utteranceId: String) = end(utteranceId, true)
+
+        override fun onError(utteranceId: String, errorCode: Int) = end(utteranceId, true)
+
+        /** Stopped, or flushed by the next utterance. */
+        override fun onStop(utteranceId: String, interrupted: Boolean) = end(utteranceId, true)
+
+        /** Android 8 and later; earlier engines don't report words. */
+        override fun onRangeStart(utteranceId: String, start: Int, end: Int, frame: Int) {
+            utterances[utteranceId]?.send(
+                JSObject().apply {
+                    put("type", "boundary")
+                    put("start", start)
+                    put("length", end - start)
+                },
+            )
+        }
+    }
+
+    private fun end(utteranceId: String, interrupted: Boolean) {
+        utterances.remove(utteranceId)?.send(
+            JSObject().apply {
+                put("type", "end")
+                put("interrupted", interrupted)
+            },
+        )
+    }
+
+    /** Runs `work` once the engine is bound, or rejects if it can't be. */
+    private fun withEngine(invoke: Invoke, work: (TextToSpeech) -> Unit) {
+        val pending: (Boolean) -> Unit = { ready ->
+            if (ready) {
+                work(tts!!)
+            } else {
+                invoke.reject("no text-to-speech engine is available", "Unavailable")
+            }
+        }
+        val ready = synchronized(this) {
+            if (!ttsReady) {
+                waiting.add(pending)
+                if (tts == null) {
+                    tts = TextToSpeech(activity, ::onEngineInit)
+                }
+            }
+            ttsReady
+        }
+        if (ready) {
+            pending(true)
+        }
+    }
+
+    private fun onEngineInit(status: Int) {
+        val ready = status == TextToSpeech.SUCCESS
+        val callbacks = synchronized(this) {
+            if (ready) {
+                tts?.setOnUtteranceProgressListener(progress)
+                ttsReady = true
+            } else {
+                // Dropped so the next call tries to bind again.
+                tts?.shutdown()
+                tts = null
+            }
+            waiting.toList().also { waiting.clear() }
+        }
+        callbacks.forEach { it(ready) }
+    }
+
+    /** Installed voices only; ones the engine would have to download first are left out. */
+    @Command
+    fun listVoices(invoke: Invoke) {
+        withEngine(invoke) { tts ->
+            val list = JSArray()
+            tts.voices.orEmpty()
+                .filterNot { it.features.contains(TextToSpeech.Engine.KEY_FEATURE_NOT_INSTALLED) }
+                .sortedBy { it.name }
+                .forEach { voice ->
+                    list.put(
+                        JSObject().apply {
+                            put("id", voice.name)
+                            put("name", voice.name)
+                            put("language", voice.locale.toLanguageTag())
+                        },
+                    )
+                }
+            val ret = JSObject()
+            ret.put("voices", list)
+            invoke.resolve(ret)
+        }
+    }
+
+    @Command
+    fun speak(invoke: Invoke) {
+        val args = invoke.parseArgs(SpeakArgs::class.java)
+        if (args.text.length > TextToSpeech.getMaxSpeechInputLength()) {
+            invoke.reject(
+                "text longer than ${TextToSpeech.getMaxSpeechInputLength()} characters",
+                "InvalidInput",
+            )
+            return
+        }
+        withEngine(invoke) { tts ->
+            val voice = if (args.voiceId != null) {
+                tts.voices?.firstOrNull { it.name == args.voiceId }
+            } else {
+                tts.defaultVoice
+            }
+            if (voice == null && args.voiceId != null) {
+                invoke.reject("no voice with id ${args.voiceId}", "VoiceNotFound")
+                return@withEngine
+            }
+            voice?.let { tts.voice = it }
+            tts.setSpeechRate(args.rate)
+            tts.setPitch(args.pitch)
+            val id = args.id.toString()
+            utterances[id] = args.onEvent
+            if (tts.speak(args.text, TextToSpeech.QUEUE_FLUSH, null, id) == TextToSpeech.SUCCESS) {
+                invoke.resolve()
+            } else {
+                utterances.remove(id)
+                invoke.reject("the engine wouldn't queue the utterance", "Failed")
+            }
+        }
+    }
+
+    /** One utterance plays at a time, so stopping everything stops this one. */
+    @Command
+    fun stop(invoke: Invoke) {
+        val args = invoke.parseArgs(StopArgs::class.java)
+        if (utterances.containsKey(args.id.toString())) {
+            tts?.stop()
+        }
+        invoke.resolve()
+    }
+
+    @Command
+    fun startDictation(invoke: Invoke) {
+        if (getPermissionState("microphone") == PermissionState.GRANTED) {
+            listen(invoke)
+        } else {
+            requestPermissionForAlias("microphone", invoke, "microphonePermission")
+        }
+    }
+
+    @PermissionCallback
+    private fun microphonePermission(invoke: Invoke) {
+        if (getPermissionState("microphone") == PermissionState.GRANTED) {
+            listen(invoke)
+        } else {
+            invoke.reject("microphone", "PermissionDenied")
+        }
+    }
+
+    private fun listen(invoke: Invoke) {
+        val args = invoke.parseArgs(DictationArgs::class.java)
+        if (!SpeechRecognizer.isRecognitionAvailable(activity)) {
+            invoke.reject("no speech recognition service is installed", "Unavailable")
+            return
+        }
+        activity.runOnUiThread {
+            if (recognizer != null) {
+                invoke.reject("dictation is already running", "Busy")
+                return@runOnUiThread
+            }
+            val recognizer = SpeechRecognizer.createSpeechRecognizer(activity)
+            this.recognizer = recognizer
+            recognizer.setRecognitionListener(Listener(recognizer, args.onTranscript))
+            val intent = Intent(RecognizerIntent.ACTION_RECOGNIZE_SPEECH).apply {
+                putExtra(RecognizerIntent.EXTRA_LANGUAGE_MODEL, RecognizerIntent.LANGUAGE_MODEL_FREE_FORM)
+                putExtra(RecognizerIntent.EXTRA_PARTIAL_RESULTS, true)
+                args.language?.let { putExtra(RecognizerIntent.EXTRA_LANGUAGE, it) }
+            }
+            recognizer.startListening(intent)
+            invoke.resolve()
+        }
+    }
+
+    /** The recognizer stops by itself after a pause; the final transcript follows. */
+    @Command
+    fun stopDictation(invoke: Invoke) {
+        activity.runOnUiThread {
+            recognizer?.stopListening()
+            invoke.resolve()
+        }
+    }
+
+    /** Sends a final transcript exactly once, with the last partial one if recognition fails. */
+    private inner class Listener(
+        private val owner: SpeechRecognizer,
+        private val channel: Channel,
+    ) : RecognitionListener {
+        private var last = ""
+
+        private fun send(text: String, isFinal: Boolean) {
+            channel.send(
+                JSObject().apply {
+                    put("text", text)
+                    put("isFinal", isFinal)
+                },
+            )
+        }
+
+        private fun finish(text: String) {
+            if (recognizer !== owner) return
+            send(text, true)
+            owner.destroy()
+            recognizer = null
+        }
+
+        private fun best(bundle: Bundle?): String? =
+            bundle?.getStringArrayList(SpeechRecognizer.RESULTS_RECOGNITION)?.firstOrNull()
+
+        override fun onPartialResults(partialResults: Bundle?) {
+            val text = best(partialResults) ?: return
+            last = text
+            send(text, false)
+        }
+
+        override fun onResults(results: Bundle?) = finish(best(results) ?: last)
+
+        override fun onError(error: Int) = finish(last)
+
+        override fun onReadyForSpeech(params: Bundle?) {}
+
+        override fun onBeginningOfSpeech() {}
+
+        override fun onRmsChanged(rmsdB: Float) {}
+
+        override fun onBufferReceived(buffer: ByteArray?) {}
+
+        override fun onEndOfSpeech() {}
+
+        override fun onEvent(eventType: Int, params: Bundle?) {}
+    }
+}
@@ -0,0 +1,257 @@
+import AVFoundation
+import Speech
+import Tauri
+import UIKit
+import WebKit
+
+class SpeakArgs: Decodable {
+  let id: UInt64
+  let text: String
+  /// The default voice for the user's language when nil.
+  let voiceId: String?
+  let rate: Float
+  let pitch: Float
+  let onEvent: Channel
+}
+
+class StopArgs: Decodable {
+  let id: UInt64
+}
+
+class DictationArgs: Decodable {
+  /// A BCP 47 tag; the system language when nil.
+  let language: String?
+  let onTranscript: Channel
+}
+
+struct BoundaryEvent: Encodable {
+  let type = "boundary"
+  let start: Int
+  let length: Int
+}
+
+struct EndEvent: Encodable {
+  let type = "end"
+  let interrupted: Bool
+}
+
+struct Transcript: Encodable {
+  let text: String
+  let isFinal: Bool
+}
+
+/// AVSpeech rates run from a minimum through a default to a maximum rather than as
+/// multiples; a quarter or four times normal reaches either end.
+private func speechRate(_ multiple: Float) -> Float {
+  let steps = max(-1, min(1, log2(multiple) / 2))
+  let normal = AVSpeechUtteranceDefaultSpeechRate
+  return steps >= 0
+    ? normal + (AVSpeechUtteranceMaximumSpeechRate - normal) * steps
+    : normal + (normal - AVSpeechUtteranceMinimumSpeechRate) * steps
+}
+
+private class Dictation {
+  let engine = AVAudioEngine()
+  let request = SFSpeechAudioBufferRecognitionRequest()
+  let recognizer: SFSpeechRecognizer
+  var task: SFSpeechRecognitionTask?
+  /// The last partial transcript, sent as the final one if recognition fails.
+  var last = ""
+
+  init(recognizer: SFSpeechRecognizer) {
+    self.recognizer = recognizer
+  }
+
+  /// Stops the microphone; the recognizer goes on to send its final transcript.
+  func stopListening() {
+    guard engine.isRunning else { return }
+    engine.stop()
+    engine.inputNode.removeTap(onBus: 0)
+    request.endAudio()
+  }
+}
+
+/// State is only touched on the main queue, where the synthesizer's delegate calls
+/// arrive too.
+class SpeechPlugin: Plugin, AVSpeechSynthesizerDelegate {
+  private let synthesizer = AVSpeechSynthesizer()
+  /// Utterances being read, with the id and channel each came with.
+  private var utterances: [ObjectIdentifier: (id: UInt64, channel: Channel)] = [:]
+  private var dictation: Dictation?
+
+  override func load(webview: WKWebView) {
+    synthesizer.delegate = self
+  }
+
+  @objc public func listVoices(_ invoke: Invoke) throws {
+    let voices = AVSpeechSynthesisVoice.speechVoices().map { voice in
+      ["id": voice.identifier, "name": voice.name, "language": voice.language]
+    }
+    invoke.resolve(["voices": voices])
+  }
+
+  @objc public func speak(_ invoke: Invoke) throws {
+    let args = try invoke.parseArgs(SpeakArgs.self)
+    DispatchQueue.main.async {
+      let utterance = AVSpeechUtterance(string: args.text)
+      if let voiceId = args.voiceId {
+        guard let voice = AVSpeechSynthesisVoice(identifier: voiceId) else {
+          invoke.reject("no voice with id \(voiceId)", code: "VoiceNotFound")
+          return
+        }
+        utterance.voice = voice
+      }
+      utterance.rate = speechRate(args.rate)
+      utterance.pitchMultiplier = args.pitch
+      self.synthesizer.stopSpeaking(at: .immediate)
+      self.utterances[ObjectIdentifier(utterance)] = (args.id, args.onEvent)
+      self.synthesizer.speak(utterance)
+      invoke.resolve()
+    }
+  }
+
+  /// One utterance plays at a time, so stopping everything stops this one.
+  @objc public func stop(_ invoke: Invoke) throws {
+    let args = try invoke.parseArgs(StopArgs.self)
+    DispatchQueue.main.async {
+      if self.utterances.values.contains(where: { $0.id == args.id }) {
+        self.synthesizer.stopSpeaking(at: .immediate)
+      }
+      invoke.resolve()
+    }
+  }
+
+  func speechSynthesizer(
+    _ synthesizer: AVSpeechSynthesizer, willSpeakRangeOfSpeechString characterRange: NSRange,
+    utterance: AVSpeechUtterance
+  ) {
+    guard let reading = utterances[ObjectIdentifier(utterance)] else { return }
+    try? reading.channel.send(
+      BoundaryEvent(start: characterRange.location, length: characterRange.length))
+  }
+
+  func speechSynthesizer(
+    _ synthesizer: AVSpeechSynthesizer, didFinish utterance: AVSpeechUtterance
+  ) {
+    end(utterance, interrupted: false)
+  }
+
+  func speechSynthesizer(
+    _ synthesizer: AVSpeechSynthesizer, didCancel utterance: AVSpeechUtterance
+  ) {
+    end(utterance, interrupted: true)
+  }
+
+  private func end(_ utterance: AVSpeechUtterance, interrupted: Bool) {
+    guard let reading = utterances.removeValue(forKey: ObjectIdentifier(utterance)) else {
+      return
+    }
+    try? reading.channel.send(EndEvent(interrupted: interrupted))
+  }
+
+  /// Speech recognition first, then the microphone, each asked for once.
+  private func authorize(_ invoke: Invoke, then: @escaping () -> Void) {
+    SFSpeechRecognizer.requestAuthorization { status in
+      guard status == .authorized else {
+        DispatchQueue.main.async {
+          invoke.reject("speech recognition", code: "PermissionDenied")
+        }
+        return
+      }
+      AVAudioSession.sharedInstance().requestRecordPermission { granted in
+        DispatchQueue.main.async {
+          if granted {
+            then()
+          } else {
+            invoke.reject("microphone", code: "PermissionDenied")
+          }
+        }
+      }
+    }
+  }
+
+  @objc public func startDictation(_ invoke: Invoke) throws {
+    let args = try invoke.parseArgs(DictationArgs.self)
+    authorize(invoke) {
+      guard self.dictation == nil else {
+        invoke.reject("dictation is already running", code: "Busy")
+        return
+      }
+      let recognizer =
+        args.language.map { SFSpeechRecognizer(locale: Locale(identifier: $0)) }
+        ?? SFSpeechRecognizer()
+      guard let recognizer = recognizer, recognizer.isAvailable else {
+        invoke.reject(
+          "no speech recognition for \(args.language ?? "the system language") right now",
+          code: "Unavailable")
+        return
+      }
+      do {
+        try self.listen(recognizer, channel: args.onTranscript)
+        invoke.resolve()
+      } catch {
+        invoke.reject(error.localizedDescription, code: "Failed")
+      }
+    }
+  }
+
+  private func listen(_ recognizer: SFSpeechRecognizer, channel: Channel) throws {
+    let session = AVAudioSession.sharedInstance()
+    try session.setCategory(.playAndRecord, mode: .measurement, options: [.duckOthers, .defaultToSpeaker])
+    try session.setActive(true, options: .notifyOthersOnDeactivation)
+
+    let dictation = Dictation(recognizer: recognizer)
+    dictation.request.shouldReportPartialResults = true
+    let input = dictation.engine.inputNode
+    input.installTap(onBus: 0, bufferSize: 1024, format: input.outputFormat(forBus: 0)) {
+      buffer, _ in
+      dictation.request.append(buffer)
+    }
+    dictation.engine.prepare()
+    do {
+      try dictation.engine.start()
+    } catch {
+      input.removeTap(onBus: 0)
+      try? session.setActive(false, options: .notifyOthersOnDeactivation)
+      throw error
+    }
+
+    dictation.task = recognizer.recognitionTask(with: dictation.request) { result, error in
+      DispatchQueue.main.async {
+        // Anything after the final transcript belongs to a dictation already over.
+        guard self.dictation === dictation else { return }
+        let transcript: Transcript
+        if let result = result {
+          transcript = Transcript(
+            text: result.bestTranscription.formattedString, isFinal: result.isFinal)
+        } else {
+          transcript = Transcript(text: dictation.last, isFinal: true)
+        }
+        dictation.last = transcript.text
+        try? channel.send(transcript)
+        if transcript.isFinal || error != nil {
+          if !transcript.isFinal {
+            try? channel.send(Transcript(text: dictation.last, isFinal: true))
+          }
+          dictation.stopListening()
+          self.dictation = nil
+          try? AVAudioSession.sharedInstance().setActive(
+            false, options: .notifyOthersOnDeactivation)
+        }
+      }
+    }
+    self.dictation = dictation
+  }
+
+  @objc public func stopDictation(_ invoke: Invoke) throws {
+    DispatchQueue.main.async {
+      self.dictation?.stopListening()
+      invoke.resolve()
+    }
+  }
+}
+
+@_cdecl("init_plugin_speech")
+func initPlugin() -> Plugin {
+  return SpeechPlugin()
+}
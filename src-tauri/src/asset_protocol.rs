@@ -0,0 +1,265 @@
+//! Assets held in memory and served through the `asset-mem://` scheme, for measuring
+//! loads without the filesystem in the way. A registered key is served at
+//! `asset-mem://localhost/<key>`, or `http://asset-mem.localhost/<key>` on Windows and
+//! Android, as with [`crate::media`].
+//!
+//! Rust code can also fill an asset from a channel with [`register_asset_stream`].
+//! Custom scheme responses can't be streamed, so requests for it wait until the channel
+//! closes and are then answered in full.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use bytes::Bytes;
+use serde::Serialize;
+use tauri::http::{header, HeaderValue, Request, Response, StatusCode};
+use tauri::{AppHandle, Manager, Runtime, State, UriSchemeContext, UriSchemeResponder};
+use tokio::sync::{mpsc, watch};
+
+use crate::media::{self, Unsatisfiable};
+
+pub const SCHEME: &str = "asset-mem";
+
+const MAX_KEY_LEN: usize = 512;
+
+#[derive(Debug, thiserror::Error, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "camelCase")]
+pub enum AssetError {
+    #[error("asset is {size} bytes, over the {max} byte limit")]
+    #[serde(rename_all = "camelCase")]
+    TooLarge { size: u64, max: u64 },
+    #[error("invalid asset key: {0}")]
+    InvalidKey(String),
+    #[error("invalid content type: {0}")]
+    InvalidContentType(String),
+    #[error("no asset registered as {0}")]
+    NotFound(String),
+}
+
+#[derive(Clone)]
+enum Stream {
+    Receiving,
+    Done(Bytes),
+    /// Went over the size limit; requests get 413.
+    TooLarge,
+}
+
+#[derive(Clone)]
+enum Body {
+    Ready(Bytes),
+    Streaming(watch::Receiver<Stream>),
+}
+
+#[derive(Clone)]
+pub struct AssetEntry {
+    content_type: String,
+    body: Body,
+    /// Tells a finished stream whether its key has been registered again since.
+    generation: u64,
+}
+
+#[derive(Default)]
+pub struct Assets {
+    entries: Mutex<HashMap<String, AssetEntry>>,
+    next_generation: AtomicU64,
+}
+
+impl Assets {
+    fn insert(&self, key: String, content_type: String, body: Body) -> u64 {
+        let generation = self.next_generation.fetch_add(1, Ordering::Relaxed);
+        self.entries.lock().unwrap().insert(
+            key,
+            AssetEntry {
+                content_type,
+                body,
+                generation,
+            },
+        );
+        generation
+    }
+
+    /// Runs `f` on the entry only if it's still the registration from `generation`.
+    fn update(&self, key: &str, generation: u64, f: impl FnOnce(&mut HashMap<String, AssetEntry>)) {
+        let mut entries = self.entries.lock().unwrap();
+        if entries
+            .get(key)
+            .is_some_and(|entry| entry.generation == generation)
+        {
+            f(&mut entries);
+        }
+    }
+}
+
+/// Keys become URL paths, so they may contain `/` but nothing that needs escaping to
+/// survive a round trip.
+fn validate(key: &str, content_type: &str) -> Result<(), AssetError> {
+    let valid_key = !key.is_empty()
+        && key.len() <= MAX_KEY_LEN
+        && !key.starts_with('/')
+        && key
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"-._~/".contains(&b));
+    if !valid_key {
+        return Err(AssetError::InvalidKey(key.to_string()));
+    }
+    if HeaderValue::from_str(content_type).is_err() || content_type.is_empty() {
+        return Err(AssetError::InvalidContentType(content_type.to_string()));
+    }
+    Ok(())
+}
+
+fn max_size<R: Runtime>(app: &AppHandle<R>) -> u64 {
+    crate::settings::current(app).asset_max_size_bytes
+}
+
+fn serve(request: &Request<Vec<u8>>, content_type: &str, data: &[u8]) -> Response<Vec<u8>> {
+    let len = data.len() as u64;
+    let range = request
+        .headers()
+        .get(header::RANGE)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| media::parse_range(value, len))
+        .transpose();
+    let builder = Response::builder()
+        .header(header::CONTENT_TYPE, content_type)
+        .header(header::ACCEPT_RANGES, "bytes")
+        // A key can be registered again with different contents.
+        .header(header::CACHE_CONTROL, "no-store");
+    let response = match range {
+        Ok(Some(Some(range))) => builder
+            .status(StatusCode::PARTIAL_CONTENT)
+            .header(
+                header::CONTENT_RANGE,
+                format!("bytes {}-{}/{len}", range.start, range.end),
+            )
+            .body(data[range.start as usize..=range.end as usize].to_vec()),
+        Ok(_) => builder.status(StatusCode::OK).body(data.to_vec()),
+        Err(Unsatisfiable) => builder
+            .status(StatusCode::RANGE_NOT_SATISFIABLE)
+            .header(header::CONTENT_RANGE, format!("bytes */{len}"))
+            .body(Vec::new()),
+    };
+    response.unwrap_or_else(|_| media::status(StatusCode::INTERNAL_SERVER_ERROR))
+}
+
+async fn respond<R: Runtime>(app: &AppHandle<R>, request: &Request<Vec<u8>>) -> Response<Vec<u8>> {
+    let key = request.uri().path().trim_start_matches('/');
+    let Some(entry) = app
+        .state::<Assets>()
+        .entries
+        .lock()
+        .unwrap()
+        .get(key)
+        .cloned()
+    else {
+        return media::status(StatusCode::NOT_FOUND);
+    };
+
+    let data = match entry.body {
+        Body::Ready(data) => data,
+        Body::Streaming(mut stream) => {
+            let finished = stream
+                .wait_for(|state| !matches!(state, Stream::Receiving))
+                .await
+                .map(|state| state.clone());
+            match finished {
+                Ok(Stream::Done(data)) => data,
+                Ok(_) => return media::status(StatusCode::PAYLOAD_TOO_LARGE),
+                // The filling task ended without finishing.
+                Err(_) => return media::status(StatusCode::INTERNAL_SERVER_ERROR),
+            }
+        }
+    };
+    serve(request, &entry.content_type, &data)
+}
+
+/// The `asset-mem://` handler, registered in `run()`. Async because requests for a
+/// streaming asset wait for it to finish.
+pub fn handle<R: Runtime>(
+    ctx: UriSchemeContext<'_, R>,
+    request: Request<Vec<u8>>,
+    responder: UriSchemeResponder,
+) {
+    let app = ctx.app_handle().clone();
+    tauri::async_runtime::spawn(async move {
+        responder.respond(respond(&app, &request).await);
+    });
+}
+
+/// Serves whatever arrives on `chunks` as `key` once the sender closes. Replaces any
+/// asset already registered as `key`; if the total goes over the size limit the asset is
+/// dropped and waiting requests get 413.
+// For Rust-side producers; nothing in the app feeds one yet.
+#[allow(dead_code)]
+pub fn register_asset_stream<R: Runtime>(
+    app: &AppHandle<R>,
+    key: String,
+    content_type: String,
+    mut chunks: mpsc::Receiver<Bytes>,
+) -> Result<(), AssetError> {
+    validate(&key, &content_type)?;
+    let max = max_size(app);
+    let (progress, stream) = watch::channel(Stream::Receiving);
+    let generation =
+        app.state::<Assets>()
+            .insert(key.clone(), content_type, Body::Streaming(stream));
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let mut buffer = Vec::new();
+        while let Some(chunk) = chunks.recv().await {
+            if (buffer.len() + chunk.len()) as u64 > max {
+                tracing::warn!(%key, max, "streamed asset over the size limit");
+                app.state::<Assets>().update(&key, generation, |entries| {
+                    entries.remove(&key);
+                });
+                let _ = progress.send(Stream::TooLarge);
+                return;
+            }
+            buffer.extend_from_slice(&chunk);
+        }
+        let data = Bytes::from(buffer);
+        app.state::<Assets>().update(&key, generation, |entries| {
+            if let Some(entry) = entries.get_mut(&key) {
+                entry.body = Body::Ready(data.clone());
+            }
+        });
+        tracing::debug!(%key, size = data.len(), "streamed asset ready");
+        let _ = progress.send(Stream::Done(data));
+    });
+    Ok(())
+}
+
+/// Replaces any asset already registered as `key`.
+#[tauri::command]
+pub fn register_asset<R: Runtime>(
+    key: String,
+    data: Vec<u8>,
+    content_type: String,
+    app: AppHandle<R>,
+    assets: State<'_, Assets>,
+) -> Result<(), AssetError> {
+    validate(&key, &content_type)?;
+    let max = max_size(&app);
+    if data.len() as u64 > max {
+        return Err(AssetError::TooLarge {
+            size: data.len() as u64,
+            max,
+        });
+    }
+    assets.insert(key, content_type, Body::Ready(Bytes::from(data)));
+    Ok(())
+}
+
+/// Requests already waiting on a streaming asset still get it once it finishes.
+#[tauri::command]
+pub fn unregister_asset(key: String, assets: State<'_, Assets>) -> Result<(), AssetError> {
+    assets
+        .entries
+        .lock()
+        .unwrap()
+        .remove(&key)
+        .map(|_| ())
+        .ok_or(AssetError::NotFound(key))
+}
@@ -0,0 +1,422 @@
+//! Content-addressed attachments for notes under `$APPDATA/attachments`, sharded by the
+//! first byte of their SHA-256 (`ab/cdef…`). Adding identical contents twice stores one
+//! blob. The `attachments` table counts each blob's references from `attachment_links`,
+//! and [`attachment_gc`] deletes blobs nothing has referenced for [`GC_GRACE`].
+//!
+//! Adds and collection of the same hash are serialised by [`Attachments`], so a blob
+//! re-added while it is being collected is never left with a row and no file.
+
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tauri::{AppHandle, Manager, Runtime, State};
+use tokio::sync::{Mutex, MutexGuard};
+
+use crate::db::{Db, DbError};
+use crate::{media, scope};
+
+const ATTACHMENTS_DIR: &str = "attachments";
+/// How long a blob stays unreferenced before [`attachment_gc`] may delete it, so undoing
+/// a deletion or pasting an attachment into another note still finds it.
+const GC_GRACE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+const LOCK_STRIPES: usize = 64;
+
+#[derive(Debug, thiserror::Error, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "camelCase")]
+pub enum AttachmentError {
+    #[error("not found: {0}")]
+    NotFound(String),
+    #[error("invalid input: {0}")]
+    InvalidInput(String),
+    #[error("{0}")]
+    Scope(String),
+    #[error("{0}")]
+    Io(String),
+    #[error(transparent)]
+    Db(#[from] DbError),
+}
+
+impl From<io::Error> for AttachmentError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e.to_string())
+    }
+}
+
+impl From<sqlx::Error> for AttachmentError {
+    fn from(e: sqlx::Error) -> Self {
+        Self::Db(e.into())
+    }
+}
+
+/// A file to copy in, or contents the frontend already has, like a pasted image.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AttachmentSource {
+    Path(String),
+    Bytes(Vec<u8>),
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Attachment {
+    pub hash: String,
+    pub size_bytes: u64,
+    pub mime: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GcReport {
+    pub dry_run: bool,
+    /// Blobs deleted, or that would be.
+    pub deleted: u32,
+    pub reclaimed_bytes: u64,
+}
+
+/// Per-hash locks, striped so the set stays fixed-size.
+pub struct Attachments {
+    locks: Vec<Mutex<()>>,
+}
+
+impl Default for Attachments {
+    fn default() -> Self {
+        Self {
+            locks: (0..LOCK_STRIPES).map(|_| Mutex::new(())).collect(),
+        }
+    }
+}
+
+impl Attachments {
+    async fn lock(&self, hash: &str) -> MutexGuard<'_, ()> {
+        let stripe = u8::from_str_radix(&hash[..2], 16).unwrap_or(0) as usize % LOCK_STRIPES;
+        self.locks[stripe].lock().await
+    }
+}
+
+fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as i64)
+}
+
+fn attachments_root<R: Runtime>(app: &AppHandle<R>) -> Result<PathBuf, AttachmentError> {
+    app.path()
+        .app_data_dir()
+        .map(|dir| dir.join(ATTACHMENTS_DIR))
+        .map_err(|e| AttachmentError::Io(e.to_string()))
+}
+
+/// Takes a valid hash, which [`media::is_hash`] guarantees is at least two characters.
+fn blob_path(root: &Path, hash: &str) -> PathBuf {
+    root.join(&hash[..2]).join(&hash[2..])
+}
+
+fn ensure_hash(hash: &str) -> Result<(), AttachmentError> {
+    if media::is_hash(hash) {
+        Ok(())
+    } else {
+        Err(AttachmentError::InvalidInput(format!(
+            "`{hash}` is not a SHA-256 hash"
+        )))
+    }
+}
+
+/// Writes `bytes` into `root` under a temporary name, the way [`media::copy_hashed`]
+/// does for files.
+fn write_hashed(bytes: &[u8], root: &Path) -> io::Result<(PathBuf, String, u64)> {
+    fs::create_dir_all(root)?;
+    let tmp = root.join(format!(".import-{}", uuid::Uuid::new_v4()));
+    let result = File::create(&tmp).and_then(|mut output| {
+        output.write_all(bytes)?;
+        output.sync_all()
+    });
+    if let Err(e) = result {
+        let _ = fs::remove_file(&tmp);
+        return Err(e);
+    }
+    let hash = Sha256::digest(bytes)
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect();
+    Ok((tmp, hash, bytes.len() as u64))
+}
+
+/// Stores an attachment and returns its hash; adding contents that are already stored
+/// returns the existing blob. A new blob has no references until [`attachment_link`].
+#[tauri::command]
+pub async fn attachment_add<R: Runtime>(
+    source: AttachmentSource,
+    app: AppHandle<R>,
+    db: State<'_, Db>,
+    attachments: State<'_, Attachments>,
+) -> Result<Attachment, AttachmentError> {
+    let pool = db.pool()?;
+    let root = attachments_root(&app)?;
+
+    // Large files are hashed while they're copied rather than read into memory.
+    let (mime, (tmp, hash, size)) = match source {
+        AttachmentSource::Path(path) => {
+            let src =
+                scope::ensure_allowed(&app, Path::new(&path)).map_err(AttachmentError::Scope)?;
+            if !src.is_file() {
+                return Err(AttachmentError::NotFound(src.display().to_string()));
+            }
+            tauri::async_runtime::spawn_blocking({
+                let root = root.clone();
+                move || {
+                    Ok::<_, io::Error>((media::mime_type(&src), media::copy_hashed(&src, &root)?))
+                }
+            })
+            .await
+            .map_err(|e| AttachmentError::Io(e.to_string()))??
+        }
+        AttachmentSource::Bytes(bytes) => tauri::async_runtime::spawn_blocking({
+            let root = root.clone();
+            move || {
+                let mime = infer::get(&bytes)
+                    .map_or("application/octet-stream", |kind| kind.mime_type())
+                    .to_string();
+                Ok::<_, io::Error>((mime, write_hashed(&bytes, &root)?))
+            }
+        })
+        .await
+        .map_err(|e| AttachmentError::Io(e.to_string()))??,
+    };
+
+    let _guard = attachments.lock(&hash).await;
+    let result = async {
+        let dest = blob_path(&root, &hash);
+        let existing: Option<(String, i64)> =
+            sqlx::query_as("SELECT mime, size_bytes FROM attachments WHERE hash = ?")
+                .bind(&hash)
+                .fetch_optional(pool)
+                .await?;
+        if dest.is_file() {
+            let _ = fs::remove_file(&tmp);
+        } else {
+            // Also restores a blob whose file went missing.
+            fs::create_dir_all(dest.parent().unwrap_or(&root))?;
+            fs::rename(&tmp, &dest)?;
+        }
+
+        if let Some((mime, size)) = existing {
+            // Restarts the grace period, so a blob added again just before it would be
+            // collected survives until the caller links it.
+            sqlx::query(
+                "UPDATE attachments SET unreferenced_since_ms = ?
+                 WHERE hash = ? AND ref_count = 0",
+            )
+            .bind(now_ms())
+            .bind(&hash)
+            .execute(pool)
+            .await?;
+            return Ok(Attachment {
+                hash: hash.clone(),
+                size_bytes: size as u64,
+                mime,
+            });
+        }
+
+        let now = now_ms();
+        sqlx::query(
+            "INSERT INTO attachments (hash, mime, size_bytes, created_at_ms, unreferenced_since_ms)
+             VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(&hash)
+        .bind(&mime)
+        .bind(size as i64)
+        .bind(now)
+        .bind(now)
+        .execute(pool)
+        .await?;
+        tracing::info!(%hash, size, "attachment added");
+        Ok::<_, AttachmentError>(Attachment {
+            hash: hash.clone(),
+            size_bytes: size,
+            mime,
+        })
+    }
+    .await;
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    result
+}
+
+/// Records that `note_id` uses the attachment. Linking the same pair twice counts once;
+/// returns the attachment's reference count.
+#[tauri::command]
+pub async fn attachment_link(
+    hash: String,
+    note_id: String,
+    db: State<'_, Db>,
+) -> Result<u32, AttachmentError> {
+    ensure_hash(&hash)?;
+    let mut tx = db.pool()?.begin().await?;
+    let exists: Option<(i64,)> = sqlx::query_as("SELECT ref_count FROM attachments WHERE hash = ?")
+        .bind(&hash)
+        .fetch_optional(&mut *tx)
+        .await?;
+    let Some((mut ref_count,)) = exists else {
+        return Err(AttachmentError::NotFound(hash));
+    };
+
+    let inserted =
+        sqlx::query("INSERT OR IGNORE INTO attachment_links (hash, note_id) VALUES (?, ?)")
+            .bind(&hash)
+            .bind(&note_id)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+    if inserted > 0 {
+        (ref_count,) = sqlx::query_as(
+            "UPDATE attachments SET ref_count = ref_count + 1, unreferenced_since_ms = NULL
+             WHERE hash = ? RETURNING ref_count",
+        )
+        .bind(&hash)
+        .fetch_one(&mut *tx)
+        .await?;
+    }
+    tx.commit().await?;
+    Ok(ref_count as u32)
+}
+
+/// Drops `note_id`'s reference. The blob stays for [`GC_GRACE`] after its last one goes;
+/// returns the attachment's reference count.
+#[tauri::command]
+pub async fn attachment_unlink(
+    hash: String,
+    note_id: String,
+    db: State<'_, Db>,
+) -> Result<u32, AttachmentError> {
+    ensure_hash(&hash)?;
+    let mut tx = db.pool()?.begin().await?;
+    let removed = sqlx::query("DELETE FROM attachment_links WHERE hash = ? AND note_id = ?")
+        .bind(&hash)
+        .bind(&note_id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+    let row: Option<(i64,)> = if removed > 0 {
+        // The CASE sees the count from before this update.
+        sqlx::query_as(
+            "UPDATE attachments SET ref_count = ref_count - 1,
+               unreferenced_since_ms = CASE WHEN ref_count = 1 THEN ? ELSE unreferenced_since_ms END
+             WHERE hash = ? RETURNING ref_count",
+        )
+        .bind(now_ms())
+        .bind(&hash)
+        .fetch_optional(&mut *tx)
+        .await?
+    } else {
+        sqlx::query_as("SELECT ref_count FROM attachments WHERE hash = ?")
+            .bind(&hash)
+            .fetch_optional(&mut *tx)
+            .await?
+    };
+    tx.commit().await?;
+    row.map(|(ref_count,)| ref_count as u32)
+        .ok_or(AttachmentError::NotFound(hash))
+}
+
+/// Deletes blobs that have had no references for [`GC_GRACE`], along with temporary
+/// files left by interrupted adds. With `dry_run`, only reports what would go.
+#[tauri::command]
+pub async fn attachment_gc<R: Runtime>(
+    dry_run: bool,
+    app: AppHandle<R>,
+    db: State<'_, Db>,
+    attachments: State<'_, Attachments>,
+) -> Result<GcReport, AttachmentError> {
+    let pool = db.pool()?;
+    let root = attachments_root(&app)?;
+    let cutoff = now_ms() - GC_GRACE.as_millis() as i64;
+    let candidates: Vec<(String, i64)> = sqlx::query_as(
+        "SELECT hash, size_bytes FROM attachments
+         WHERE ref_count = 0 AND unreferenced_since_ms <= ?",
+    )
+    .bind(cutoff)
+    .fetch_all(pool)
+    .await?;
+
+    let mut report = GcReport {
+        dry_run,
+        ..GcReport::default()
+    };
+    if dry_run {
+        report.deleted = candidates.len() as u32;
+        report.reclaimed_bytes = candidates.iter().map(|(_, size)| *size as u64).sum();
+    } else {
+        for (hash, _) in candidates {
+            let _guard = attachments.lock(&hash).await;
+            let mut tx = pool.begin().await?;
+            // Checked again: the blob may have been linked or re-added since.
+            let deleted: Option<(i64,)> = sqlx::query_as(
+                "DELETE FROM attachments
+                 WHERE hash = ? AND ref_count = 0 AND unreferenced_since_ms <= ?
+                 RETURNING size_bytes",
+            )
+            .bind(&hash)
+            .bind(cutoff)
+            .fetch_optional(&mut *tx)
+            .await?;
+            let Some((size,)) = deleted else {
+                continue;
+            };
+            match fs::remove_file(blob_path(&root, &hash)) {
+                Ok(()) => {}
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => {
+                    // Dropping the transaction keeps the row, so the next run retries.
+                    tracing::warn!(%hash, error = %e, "failed to delete attachment");
+                    continue;
+                }
+            }
+            tx.commit().await?;
+            report.deleted += 1;
+            report.reclaimed_bytes += size as u64;
+        }
+    }
+
+    let stale = tauri::async_runtime::spawn_blocking(move || stale_imports(&root, dry_run))
+        .await
+        .map_err(|e| AttachmentError::Io(e.to_string()))?;
+    report.reclaimed_bytes += stale;
+    tracing::info!(
+        dry_run,
+        deleted = report.deleted,
+        reclaimed_bytes = report.reclaimed_bytes,
+        "attachment gc finished"
+    );
+    Ok(report)
+}
+
+/// Temporary files from adds interrupted more than [`GC_GRACE`] ago, removed unless
+/// `dry_run`; returns their total size.
+fn stale_imports(root: &Path, dry_run: bool) -> u64 {
+    let Ok(entries) = fs::read_dir(root) else {
+        return 0;
+    };
+    let mut reclaimed = 0;
+    for entry in entries.flatten() {
+        if !entry.file_name().to_string_lossy().starts_with(".import-") {
+            continue;
+        }
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        let stale = metadata
+            .modified()
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .is_some_and(|age| age >= GC_GRACE);
+        if stale && (dry_run || fs::remove_file(entry.path()).is_ok()) {
+            reclaimed += metadata.len();
+        }
+    }
+    reclaimed
+}
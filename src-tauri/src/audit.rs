@@ -0,0 +1,556 @@
+//! Audit trail of the commands the webview invokes.
+//!
+//! Tauri gives the app's invoke handler a resolver it can't wrap, so [`wrap`] hands each
+//! call back to [`Webview::on_message`] with a responder of its own, which sees the
+//! response on its way to the webview. That records whether the command succeeded and
+//! times it to the end, async ones included. Plugin commands (`plugin:*|*`) never reach
+//! the invoke handler, so entries only cover the app's own commands.
+
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::io;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::{Executor, SqlitePool};
+use tauri::ipc::{CallbackFn, Invoke, InvokeBody, InvokeResponse};
+use tauri::webview::InvokeRequest;
+use tauri::{AppHandle, Manager, Runtime, State, Webview};
+use tauri_plugin_store::StoreExt;
+
+use crate::db::{Db, DbError};
+
+/// Preferences key the mode is persisted under.
+const MODE_KEY: &str = "audit_mode";
+const PREFERENCES_STORE: &str = "preferences.json";
+const RECENT_CAPACITY: usize = 1000;
+const FLUSH_INTERVAL: Duration = Duration::from_secs(2);
+/// Serialized arguments kept per entry in [`AuditMode::Full`].
+const MAX_ARGS_BYTES: usize = 4 * 1024;
+const DEFAULT_LIMIT: u32 = 200;
+
+/// Commands whose arguments carry secrets, which are never stored even in full mode.
+/// Entries are written to the app database, so that includes the passphrases that
+/// unlock it.
+const REDACTED_PREFIXES: &[&str] = &[
+    "secret_",
+    "keychain_",
+    "secure_store_",
+    "export_backup",
+    "import_backup",
+    "open_encrypted_db",
+    "change_passphrase",
+    "db_set_passphrase",
+    "db_unlock",
+    "db_change_passphrase",
+];
+
+/// Argument names, matched ignoring case anywhere in a key such as `newPassphrase`, whose
+/// values are left out of full-mode entries for every command.
+const REDACTED_ARGS: &[&str] = &["passphrase", "password", "token"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AuditMode {
+    Off,
+    Metadata,
+    /// Metadata plus the serialized arguments, capped at 4 KB.
+    Full,
+}
+
+impl AuditMode {
+    fn from_u8(mode: u8) -> Self {
+        match mode {
+            0 => Self::Off,
+            2 => Self::Full,
+            _ => Self::Metadata,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditEntry {
+    pub timestamp_ms: i64,
+    pub window: String,
+    pub command: String,
+    pub arg_bytes: u64,
+    pub duration_us: u64,
+    /// Whether the command returned `Ok`. Calls to commands that don't exist, or that
+    /// were rate limited or refused, count as errors.
+    pub ok: bool,
+    pub args: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct AuditFilter {
+    /// Matches commands starting with this.
+    pub command: Option<String>,
+    pub window: Option<String>,
+    pub since_ms: Option<i64>,
+    pub errors_only: bool,
+    /// Newest entries first; defaults to 200.
+    pub limit: Option<u32>,
+}
+
+impl AuditFilter {
+    fn matches(&self, entry: &AuditEntry) -> bool {
+        self.command
+            .as_ref()
+            .is_none_or(|c| entry.command.starts_with(c.as_str()))
+            && self.window.as_ref().is_none_or(|w| entry.window == *w)
+            && self
+                .since_ms
+                .is_none_or(|since| entry.timestamp_ms >= since)
+            && (!self.errors_only || !entry.ok)
+    }
+}
+
+struct Recent {
+    entries: VecDeque<AuditEntry>,
+    /// Everything from this time on is still held in `entries`.
+    complete_since_ms: i64,
+}
+
+/// The newest entries in memory, and those not yet written to the database.
+pub struct AuditLog {
+    mode: AtomicU8,
+    recent: Mutex<Recent>,
+    unsaved: Mutex<Vec<AuditEntry>>,
+}
+
+impl Default for AuditLog {
+    fn default() -> Self {
+        Self {
+            mode: AtomicU8::new(AuditMode::Metadata as u8),
+            recent: Mutex::new(Recent {
+                entries: VecDeque::with_capacity(RECENT_CAPACITY),
+                complete_since_ms: now_ms(),
+            }),
+            unsaved: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+impl AuditLog {
+    fn mode(&self) -> AuditMode {
+        AuditMode::from_u8(self.mode.load(Ordering::Relaxed))
+    }
+
+    fn record(&self, entry: AuditEntry) {
+        {
+            let mut recent = self.recent.lock().unwrap();
+            if recent.entries.len() == RECENT_CAPACITY {
+                if let Some(evicted) = recent.entries.pop_front() {
+                    recent.complete_since_ms = evicted.timestamp_ms + 1;
+                }
+            }
+            recent.entries.push_back(entry.clone());
+        }
+        self.unsaved.lock().unwrap().push(entry);
+    }
+
+    /// Whether memory still holds every entry since `since_ms`.
+    fn covers(&self, since_ms: Option<i64>) -> bool {
+        let recent = self.recent.lock().unwrap();
+        since_ms.is_some_and(|since| since >= recent.complete_since_ms)
+    }
+
+    fn recent_matching(&self, filter: &AuditFilter, limit: usize) -> Vec<AuditEntry> {
+        let recent = self.recent.lock().unwrap();
+        recent
+            .entries
+            .iter()
+            .rev()
+            .filter(|entry| filter.matches(entry))
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+
+    async fn flush(&self, pool: &SqlitePool) -> Result<(), sqlx::Error> {
+        let entries = std::mem::take(&mut *self.unsaved.lock().unwrap());
+        if entries.is_empty() {
+            return Ok(());
+        }
+        let mut tx = pool.begin().await?;
+        for entry in &entries {
+            sqlx::query(
+                "INSERT INTO audit_log
+                 (timestamp_ms, window_label, command, arg_bytes, duration_us, ok, args)
+                 VALUES (?, ?, ?, ?, ?, ?, ?)",
+            )
+            .bind(entry.timestamp_ms)
+            .bind(&entry.window)
+            .bind(&entry.command)
+            .bind(entry.arg_bytes as i64)
+            .bind(entry.duration_us as i64)
+            .bind(entry.ok)
+            .bind(&entry.args)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await
+    }
+}
+
+fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or_default()
+}
+
+/// Counts serialized bytes without keeping them, so metadata mode doesn't allocate a
+/// copy of every argument.
+struct ByteCount(u64);
+
+impl io::Write for ByteCount {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn truncate(mut text: String, max: usize) -> String {
+    if text.len() > max {
+        let mut end = max;
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        text.truncate(end);
+    }
+    text
+}
+
+/// The argument size, and the arguments themselves when they're to be kept. Raw
+/// binary bodies are only counted.
+fn measure(body: &InvokeBody, keep: bool) -> (u64, Option<String>) {
+    match body {
+        InvokeBody::Raw(bytes) => (bytes.len() as u64, None),
+        InvokeBody::Json(json) => measure_json(json, keep),
+    }
+}
+
+/// The size is of the arguments as sent, before any are redacted.
+fn measure_json(json: &Value, keep: bool) -> (u64, Option<String>) {
+    let mut count = ByteCount(0);
+    let _ = serde_json::to_writer(&mut count, json);
+    if !keep {
+        return (count.0, None);
+    }
+    let mut kept = json.clone();
+    redact(&mut kept);
+    (count.0, Some(truncate(kept.to_string(), MAX_ARGS_BYTES)))
+}
+
+fn redact(json: &mut Value) {
+    match json {
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                let key = key.to_ascii_lowercase();
+                if REDACTED_ARGS.iter().any(|name| key.contains(name)) {
+                    *value = Value::from("[redacted]");
+                } else {
+                    redact(value);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(redact),
+        _ => {}
+    }
+}
+
+/// A call being timed, until [`AuditLog::finish`] records it.
+pub(crate) struct PendingEntry {
+    started: Instant,
+    timestamp_ms: i64,
+    command: String,
+    arg_bytes: u64,
+    args: Option<String>,
+}
+
+impl AuditLog {
+    fn begin(
+        &self,
+        command: &str,
+        measure: impl FnOnce(bool) -> (u64, Option<String>),
+    ) -> Option<PendingEntry> {
+        let mode = self.mode();
+        if mode == AuditMode::Off {
+            return None;
+        }
+        let started = Instant::now();
+        let keep =
+            mode == AuditMode::Full && !REDACTED_PREFIXES.iter().any(|p| command.starts_with(p));
+        let (arg_bytes, args) = measure(keep);
+        Some(PendingEntry {
+            started,
+            timestamp_ms: now_ms(),
+            command: command.to_string(),
+            arg_bytes,
+            args,
+        })
+    }
+
+    /// Starts timing a call that doesn't go through [`wrap`], such as one of the calls a
+    /// batch runs; `None` when auditing is off.
+    pub(crate) fn begin_json(&self, command: &str, args: &Value) -> Option<PendingEntry> {
+        self.begin(command, |keep| measure_json(args, keep))
+    }
+
+    pub(crate) fn finish(&self, pending: PendingEntry, window: &str, ok: bool) {
+        self.record(AuditEntry {
+            timestamp_ms: pending.timestamp_ms,
+            window: window.to_string(),
+            command: pending.command,
+            arg_bytes: pending.arg_bytes,
+            duration_us: pending.started.elapsed().as_micros() as u64,
+            ok,
+            args: pending.args,
+        });
+    }
+}
+
+thread_local! {
+    /// Set while [`wrap`] hands a call back to Tauri, so the call goes straight to the
+    /// handler when it comes round again. Tauri dispatches app commands on the thread
+    /// that received them, and nothing the webview sends can set it.
+    static REDISPATCHED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Wraps the app's generated invoke handler so every call through it is recorded once
+/// it has been answered.
+pub fn wrap<R: Runtime>(
+    handler: impl Fn(Invoke<R>) -> bool + Send + Sync + 'static,
+) -> impl Fn(Invoke<R>) -> bool + Send + Sync + 'static {
+    move |invoke| {
+        if REDISPATCHED.replace(false) {
+            return handler(invoke);
+        }
+        let webview = invoke.message.webview();
+        let Some(audit) = webview.try_state::<AuditLog>() else {
+            return handler(invoke);
+        };
+        let Some(pending) = audit.begin(invoke.message.command(), |keep| {
+            measure(invoke.message.payload(), keep)
+        }) else {
+            return handler(invoke);
+        };
+        // The call already passed the ACL for where it came from; the webview's own URL
+        // gets it past the same check again.
+        let Ok(url) = webview.url() else {
+            let handled = handler(invoke);
+            audit.finish(pending, webview.label(), handled);
+            return handled;
+        };
+
+        let request = InvokeRequest {
+            cmd: invoke.message.command().to_string(),
+            // The responder below answers through the original resolver instead.
+            callback: CallbackFn(0),
+            error: CallbackFn(0),
+            url,
+            body: invoke.message.payload().clone(),
+            headers: invoke.message.headers().clone(),
+            invoke_key: webview.app_handle().invoke_key().to_string(),
+        };
+        let resolver = invoke.resolver;
+        let responder = Box::new(
+            move |webview: Webview<R>, _: String, response: InvokeResponse, _, _| {
+                let ok = matches!(response, InvokeResponse::Ok(_));
+                if let Some(audit) = webview.try_state::<AuditLog>() {
+                    audit.finish(pending, webview.label(), ok);
+                }
+                match response {
+                    InvokeResponse::Ok(body) => resolver.resolve(body),
+                    InvokeResponse::Err(error) => resolver.invoke_error(error),
+                }
+            },
+        );
+        REDISPATCHED.set(true);
+        webview.on_message(request, responder);
+        REDISPATCHED.set(false);
+        true
+    }
+}
+
+/// Loads the persisted mode and writes entries to the database in the background.
+/// Entries from the last couple of seconds before the app quits may not be saved.
+pub fn spawn<R: Runtime>(app: &AppHandle<R>) {
+    let audit = app.state::<AuditLog>();
+    let mode = app
+        .store(PREFERENCES_STORE)
+        .ok()
+        .and_then(|store| store.get(MODE_KEY))
+        .and_then(|mode| serde_json::from_value::<AuditMode>(mode).ok());
+    if let Some(mode) = mode {
+        audit.mode.store(mode as u8, Ordering::Relaxed);
+    }
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let db = app.state::<Db>();
+        let Ok(pool) = db.ready_pool().await else {
+            return;
+        };
+        let audit = app.state::<AuditLog>();
+        let mut interval = tokio::time::interval(FLUSH_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(e) = audit.flush(pool).await {
+                tracing::warn!(error = %e, "failed to save audit log");
+            }
+        }
+    });
+}
+
+type Row = (i64, String, String, i64, i64, bool, Option<String>);
+
+/// Newest first. Served from memory when it still holds everything the filter asks
+/// for, otherwise from the database.
+#[tauri::command]
+pub async fn get_audit_log(
+    filter: Option<AuditFilter>,
+    audit: State<'_, AuditLog>,
+    db: State<'_, Db>,
+) -> Result<Vec<AuditEntry>, DbError> {
+    let filter = filter.unwrap_or_default();
+    let limit = filter.limit.unwrap_or(DEFAULT_LIMIT);
+    if audit.covers(filter.since_ms) {
+        return Ok(audit.recent_matching(&filter, limit as usize));
+    }
+
+    let pool = match db.pool() {
+        Ok(pool) => pool,
+        // Until migrations finish, this session's entries are all there is to show.
+        Err(DbError::MigrationsPending | DbError::Locked) => {
+            return Ok(audit.recent_matching(&filter, limit as usize))
+        }
+        Err(e) => return Err(e),
+    };
+    audit.flush(pool).await?;
+
+    let command = filter.command.map(|c| {
+        let escaped = c
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_");
+        format!("{escaped}%")
+    });
+    let rows: Vec<Row> = sqlx::query_as(
+        "SELECT timestamp_ms, window_label, command, arg_bytes, duration_us, ok, args
+         FROM audit_log
+         WHERE (?1 IS NULL OR command LIKE ?1 ESCAPE '\\')
+           AND (?2 IS NULL OR window_label = ?2)
+           AND (?3 IS NULL OR timestamp_ms >= ?3)
+           AND (?4 = 0 OR ok = 0)
+         ORDER BY timestamp_ms DESC, id DESC LIMIT ?5",
+    )
+    .bind(command)
+    .bind(filter.window)
+    .bind(filter.since_ms)
+    .bind(filter.errors_only)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(
+            |(timestamp_ms, window, command, arg_bytes, duration_us, ok, args)| AuditEntry {
+                timestamp_ms,
+                window,
+                command,
+                arg_bytes: arg_bytes as u64,
+                duration_us: duration_us as u64,
+                ok,
+                args,
+            },
+        )
+        .collect())
+}
+
+#[tauri::command]
+pub async fn clear_audit_log(audit: State<'_, AuditLog>, db: State<'_, Db>) -> Result<(), DbError> {
+    {
+        let mut recent = audit.recent.lock().unwrap();
+        recent.entries.clear();
+        recent.complete_since_ms = now_ms();
+    }
+    audit.unsaved.lock().unwrap().clear();
+    db.pool()?.execute("DELETE FROM audit_log").await?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn set_audit_mode<R: Runtime>(
+    mode: AuditMode,
+    app: AppHandle<R>,
+    audit: State<'_, AuditLog>,
+) -> Result<(), String> {
+    audit.mode.store(mode as u8, Ordering::Relaxed);
+    let store = app.store(PREFERENCES_STORE).map_err(|e| e.to_string())?;
+    store.set(MODE_KEY, serde_json::to_value(mode).unwrap_or(Value::Null));
+    store.save().map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    /// Metadata mode has to add well under a millisecond a call. Everything [`wrap`]
+    /// does besides calling Tauri is here, and even an unoptimized build has to manage
+    /// it in a tenth of that.
+    #[test]
+    fn metadata_mode_overhead() {
+        const CALLS: u32 = 10_000;
+        let audit = AuditLog::default();
+        let args = json!({
+            "sql": "SELECT * FROM notes WHERE id = ?",
+            "params": [42],
+            "body": "x".repeat(2048),
+        });
+
+        let started = Instant::now();
+        for _ in 0..CALLS {
+            let pending = audit.begin_json("sql_select", &args).unwrap();
+            audit.finish(pending, "main", true);
+        }
+        let per_call = started.elapsed() / CALLS;
+
+        assert!(
+            per_call < Duration::from_micros(100),
+            "{per_call:?} per call"
+        );
+        let recent = audit.recent.lock().unwrap();
+        assert_eq!(recent.entries.len(), RECENT_CAPACITY);
+        assert!(recent.entries.iter().all(|entry| entry.args.is_none()));
+    }
+
+    #[test]
+    fn full_mode_redacts_secrets() {
+        let audit = AuditLog::default();
+        audit.mode.store(AuditMode::Full as u8, Ordering::Relaxed);
+        let args = json!({ "user": "ada", "options": { "newPassphrase": "hunter2" } });
+
+        let pending = audit.begin_json("update_profile", &args).unwrap();
+        let kept: Value = serde_json::from_str(pending.args.as_deref().unwrap()).unwrap();
+        assert_eq!(
+            kept,
+            json!({ "user": "ada", "options": { "newPassphrase": "[redacted]" } })
+        );
+        assert_eq!(pending.arg_bytes, args.to_string().len() as u64);
+        let pending = audit.begin_json("db_unlock", &args).unwrap();
+        assert_eq!(pending.args, None);
+    }
+}
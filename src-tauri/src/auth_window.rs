@@ -0,0 +1,406 @@
+//! Windows for third-party sign-in pages, with their own cookie jar and storage so an
+//! OAuth provider never shares a session with the app's origin, or with the previous
+//! test run.
+//!
+//! [`open_auth_window`] opens the provider's page in either an ephemeral data store
+//! (WKWebView's non-persistent store, a WebView2 InPrivate profile, an ephemeral
+//! WebKitGTK context) or a persistent one kept apart from the main window's. When the
+//! page redirects to [`crate::deep_link::AUTH_CALLBACK`], the window closes itself,
+//! emits [`CALLBACK_EVENT`] with the URL, and hands it to the deep-link router as if the
+//! OS had delivered it.
+//!
+//! Desktop only: mobile webviews can't open a second window, and sign-in there goes
+//! through the system browser.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use tauri::webview::Cookie;
+use tauri::{
+    AppHandle, Emitter, Manager, Runtime, Url, Webview, WebviewUrl, WebviewWindow, WindowEvent,
+};
+
+use crate::deep_link;
+
+pub const CALLBACK_EVENT: &str = "auth://callback";
+
+/// The only window [`get_cookies`] answers.
+const MAIN_WINDOW: &str = "main";
+
+/// Persistent auth windows share this store, and only with each other.
+#[cfg(any(windows, target_os = "linux"))]
+const DATA_DIR: &str = "auth-webview";
+#[cfg(target_os = "macos")]
+const DATA_STORE_ID: [u8; 16] = *b"layers-auth-view";
+
+#[derive(Debug, thiserror::Error, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "camelCase")]
+pub enum AuthWindowError {
+    #[error("invalid URL: {0}")]
+    InvalidUrl(String),
+    #[error("no window labelled {0}")]
+    WindowNotFound(String),
+    /// Carries the label of the webview that asked.
+    #[error("{0} may not read auth cookies")]
+    NotAllowed(String),
+    /// Carries `std::env::consts::OS`.
+    #[cfg_attr(
+        any(windows, target_os = "macos", target_os = "linux"),
+        allow(dead_code)
+    )]
+    #[error("clearing webview data is not supported on {0}")]
+    Unsupported(String),
+    #[error("{0}")]
+    Failed(String),
+}
+
+impl From<tauri::Error> for AuthWindowError {
+    fn from(e: tauri::Error) -> Self {
+        Self::Failed(e.to_string())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AuthWindowOptions {
+    /// Keep nothing on disk; the session ends with the window.
+    pub ephemeral: bool,
+    /// Clear the window's store when it closes, for a persistent store that should start
+    /// clean next time but still survive redirects between windows.
+    pub clear_on_close: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DataKind {
+    Cookies,
+    Cache,
+    LocalStorage,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthCookie {
+    pub name: String,
+    pub value: String,
+    pub domain: Option<String>,
+    pub path: Option<String>,
+    /// Unix seconds; `None` for a session cookie.
+    pub expires: Option<i64>,
+    pub http_only: bool,
+    pub secure: bool,
+}
+
+impl From<Cookie<'_>> for AuthCookie {
+    fn from(cookie: Cookie<'_>) -> Self {
+        Self {
+            name: cookie.name().to_string(),
+            value: cookie.value().to_string(),
+            domain: cookie.domain().map(str::to_string),
+            path: cookie.path().map(str::to_string),
+            expires: cookie.expires_datetime().map(|at| at.unix_timestamp()),
+            http_only: cookie.http_only().unwrap_or(false),
+            secure: cookie.secure().unwrap_or(false),
+        }
+    }
+}
+
+#[cfg(any(windows, target_os = "macos"))]
+async fn receive<T>(
+    rx: tokio::sync::oneshot::Receiver<Result<T, AuthWindowError>>,
+) -> Result<T, AuthWindowError> {
+    rx.await
+        .map_err(|_| AuthWindowError::Failed("the webview closed before replying".into()))?
+}
+
+#[cfg(windows)]
+mod native {
+    use tauri::{Runtime, WebviewWindow};
+    use webview2_com::ClearBrowsingDataCompletedHandler;
+    use webview2_com::Microsoft::Web::WebView2::Win32::{
+        ICoreWebView2Profile2, ICoreWebView2_13, COREWEBVIEW2_BROWSING_DATA_KINDS,
+        COREWEBVIEW2_BROWSING_DATA_KINDS_CACHE_STORAGE, COREWEBVIEW2_BROWSING_DATA_KINDS_COOKIES,
+        COREWEBVIEW2_BROWSING_DATA_KINDS_DISK_CACHE,
+        COREWEBVIEW2_BROWSING_DATA_KINDS_LOCAL_STORAGE,
+    };
+    use windows::core::Interface;
+
+    use super::{receive, AuthWindowError, DataKind};
+    use crate::pdf::Reply;
+
+    pub async fn clear<R: Runtime>(
+        webview: &WebviewWindow<R>,
+        kinds: &[DataKind],
+    ) -> Result<(), AuthWindowError> {
+        let kinds = kinds
+            .iter()
+            .map(|kind| match kind {
+                DataKind::Cookies => COREWEBVIEW2_BROWSING_DATA_KINDS_COOKIES,
+                // Service worker caches too, which a page's own cache busting can't reach.
+                DataKind::Cache => {
+                    COREWEBVIEW2_BROWSING_DATA_KINDS_DISK_CACHE
+                        | COREWEBVIEW2_BROWSING_DATA_KINDS_CACHE_STORAGE
+                }
+                DataKind::LocalStorage => COREWEBVIEW2_BROWSING_DATA_KINDS_LOCAL_STORAGE,
+            })
+            .fold(COREWEBVIEW2_BROWSING_DATA_KINDS(0), |all, kind| all | kind);
+        let (reply, rx) = Reply::new();
+        webview.with_webview(move |platform| {
+            let handler = reply.clone();
+            let started = unsafe {
+                (|| {
+                    let core: ICoreWebView2_13 = platform.controller().CoreWebView2()?.cast()?;
+                    let profile: ICoreWebView2Profile2 = core.Profile()?.cast()?;
+                    profile.ClearBrowsingData(
+                        kinds,
+                        &ClearBrowsingDataCompletedHandler::create(Box::new(move |result| {
+                            handler.send(result.map_err(|e| AuthWindowError::Failed(e.message())));
+                            Ok(())
+                        })),
+                    )
+                })()
+            };
+            if let Err(e) = started {
+                reply.send(Err(AuthWindowError::Failed(e.message())));
+            }
+        })?;
+        receive(rx).await
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod native {
+    use block2::RcBlock;
+    use objc2_foundation::{NSDate, NSSet, NSString};
+    use objc2_web_kit::{
+        WKWebView, WKWebsiteDataTypeCookies, WKWebsiteDataTypeDiskCache,
+        WKWebsiteDataTypeLocalStorage, WKWebsiteDataTypeMemoryCache,
+    };
+    use tauri::{Runtime, WebviewWindow};
+
+    use super::{receive, AuthWindowError, DataKind};
+    use crate::pdf::Reply;
+
+    pub async fn clear<R: Runtime>(
+        webview: &WebviewWindow<R>,
+        kinds: &[DataKind],
+    ) -> Result<(), AuthWindowError> {
+        let kinds = kinds.to_vec();
+        let (reply, rx) = Reply::new();
+        webview.with_webview(move |platform| unsafe {
+            let view = &*(platform.inner() as *const WKWebView);
+            let mut types: Vec<&NSString> = Vec::new();
+            for kind in kinds {
+                match kind {
+                    DataKind::Cookies => types.push(WKWebsiteDataTypeCookies),
+                    DataKind::Cache => {
+                        types.extend([WKWebsiteDataTypeDiskCache, WKWebsiteDataTypeMemoryCache])
+                    }
+                    DataKind::LocalStorage => types.push(WKWebsiteDataTypeLocalStorage),
+                }
+            }
+            let block = RcBlock::new(move || reply.send(Ok(())));
+            view.configuration()
+                .websiteDataStore()
+                .removeDataOfTypes_modifiedSince_completionHandler(
+                    &NSSet::from_slice(&types),
+                    &NSDate::distantPast(),
+                    &block,
+                );
+        })?;
+        receive(rx).await
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod native {
+    use tauri::{Runtime, WebviewWindow};
+    use webkit2gtk::glib::TimeSpan;
+    use webkit2gtk::{WebViewExt, WebsiteDataManagerExtManual, WebsiteDataTypes};
+
+    use super::{AuthWindowError, DataKind};
+    use crate::pdf::Reply;
+
+    pub async fn clear<R: Runtime>(
+        webview: &WebviewWindow<R>,
+        kinds: &[DataKind],
+    ) -> Result<(), AuthWindowError> {
+        let types = kinds
+            .iter()
+            .map(|kind| match kind {
+                DataKind::Cookies => WebsiteDataTypes::COOKIES,
+                DataKind::Cache => WebsiteDataTypes::DISK_CACHE | WebsiteDataTypes::MEMORY_CACHE,
+                DataKind::LocalStorage => WebsiteDataTypes::LOCAL_STORAGE,
+            })
+            .fold(WebsiteDataTypes::empty(), |all, kind| all | kind);
+        let (reply, rx) = Reply::new();
+        webview.with_webview(move |platform| {
+            let Some(manager) = platform.inner().website_data_manager() else {
+                reply.send(Err(AuthWindowError::Failed(
+                    "the webview has no data manager".into(),
+                )));
+                return;
+            };
+            // A zero time span clears everything, however old.
+            manager.clear(
+                types,
+                TimeSpan(0),
+                None::<&webkit2gtk::gio::Cancellable>,
+                move |result| {
+                    reply.send(result.map_err(|e| AuthWindowError::Failed(e.to_string())));
+                },
+            );
+        })?;
+        rx.await
+            .map_err(|_| AuthWindowError::Failed("the webview closed before replying".into()))?
+    }
+}
+
+#[cfg(not(any(windows, target_os = "macos", target_os = "linux")))]
+mod native {
+    use tauri::{Runtime, WebviewWindow};
+
+    use super::{AuthWindowError, DataKind};
+
+    pub async fn clear<R: Runtime>(
+        _webview: &WebviewWindow<R>,
+        _kinds: &[DataKind],
+    ) -> Result<(), AuthWindowError> {
+        Err(AuthWindowError::Unsupported(std::env::consts::OS.into()))
+    }
+}
+
+fn is_callback(url: &Url) -> bool {
+    let callback = Url::parse(deep_link::AUTH_CALLBACK).expect("the callback URL parses");
+    url.scheme() == callback.scheme()
+        && url.host_str() == callback.host_str()
+        && url.path().trim_end_matches('/') == callback.path()
+}
+
+fn window<R: Runtime>(
+    app: &AppHandle<R>,
+    label: &str,
+) -> Result<WebviewWindow<R>, AuthWindowError> {
+    app.get_webview_window(label)
+        .ok_or_else(|| AuthWindowError::WindowNotFound(label.to_string()))
+}
+
+/// Clears the store, then lets the close go ahead. `destroy` skips `CloseRequested`, so
+/// this runs once.
+fn clear_on_close<R: Runtime>(window: &WebviewWindow<R>) {
+    let closing = window.clone();
+    let cleared = Arc::new(AtomicBool::new(false));
+    window.on_window_event(move |event| {
+        let WindowEvent::CloseRequested { api, .. } = event else {
+            return;
+        };
+        if cleared.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        api.prevent_close();
+        let window = closing.clone();
+        tauri::async_runtime::spawn(async move {
+            if let Err(e) = window.clear_all_browsing_data() {
+                tracing::warn!(label = window.label(), error = %e, "failed to clear auth window data");
+            }
+            let _ = window.destroy();
+        });
+    });
+}
+
+/// Opens `url` in a new window with its own data store and returns the window's label.
+/// Persistent stores are per-profile on Windows and Linux and need macOS 14; earlier
+/// macOS shares the default store unless `ephemeral` is set.
+#[tauri::command]
+pub fn open_auth_window<R: Runtime>(
+    url: String,
+    options: Option<AuthWindowOptions>,
+    app: AppHandle<R>,
+) -> Result<String, AuthWindowError> {
+    let options = options.unwrap_or_default();
+    let url = Url::parse(&url).map_err(|e| AuthWindowError::InvalidUrl(format!("{url}: {e}")))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(AuthWindowError::InvalidUrl(format!(
+            "{url}: only http and https pages can be opened"
+        )));
+    }
+    let label = format!("auth-{}", uuid::Uuid::new_v4().simple());
+
+    let handle = app.clone();
+    let redirected = label.clone();
+    let builder = tauri::WebviewWindowBuilder::new(&app, &label, WebviewUrl::External(url))
+        .title("Sign in")
+        .inner_size(500.0, 700.0)
+        .on_navigation(move |url| {
+            if !is_callback(url) {
+                return true;
+            }
+            tracing::info!(label = %redirected, "auth window reached the callback");
+            let _ = handle.emit(CALLBACK_EVENT, url.as_str());
+            deep_link::dispatch(&handle, std::slice::from_ref(url));
+            let window = handle.get_webview_window(&redirected);
+            // Not from inside the webview's own navigation callback.
+            tauri::async_runtime::spawn(async move {
+                if let Some(window) = window {
+                    let _ = window.close();
+                }
+            });
+            false
+        });
+    let builder = if options.ephemeral {
+        builder.incognito(true)
+    } else {
+        #[cfg(any(windows, target_os = "linux"))]
+        let builder = builder.data_directory(app.path().app_local_data_dir()?.join(DATA_DIR));
+        #[cfg(target_os = "macos")]
+        let builder = builder.data_store_identifier(DATA_STORE_ID);
+        builder
+    };
+    let window = builder.build()?;
+    if options.clear_on_close {
+        clear_on_close(&window);
+    }
+    Ok(label)
+}
+
+/// Clears `kinds` from the data store behind `label`. For the main window, or any window
+/// sharing its store, that's the app's own data too.
+#[tauri::command]
+pub async fn clear_webview_data<R: Runtime>(
+    label: String,
+    kinds: Vec<DataKind>,
+    app: AppHandle<R>,
+) -> Result<(), AuthWindowError> {
+    if kinds.is_empty() {
+        return Ok(());
+    }
+    native::clear(&window(&app, &label)?, &kinds).await
+}
+
+/// Every cookie in the store behind `label`, HTTP-only ones included, or only those
+/// sent to `url_filter`. Only the main window may ask; pages in other windows, auth
+/// windows included, could otherwise lift a session they were never given.
+#[tauri::command]
+pub async fn get_cookies<R: Runtime>(
+    label: String,
+    url_filter: Option<String>,
+    app: AppHandle<R>,
+    webview: Webview<R>,
+) -> Result<Vec<AuthCookie>, AuthWindowError> {
+    if webview.label() != MAIN_WINDOW {
+        return Err(AuthWindowError::NotAllowed(webview.label().to_string()));
+    }
+    let window = window(&app, &label)?;
+    let filter = url_filter
+        .map(|url| Url::parse(&url).map_err(|e| AuthWindowError::InvalidUrl(format!("{url}: {e}"))))
+        .transpose()?;
+    // WebView2 deadlocks if cookies are read on a thread that has to pump its messages.
+    let cookies = tauri::async_runtime::spawn_blocking(move || match filter {
+        Some(url) => window.cookies_for_url(url),
+        None => window.cookies(),
+    })
+    .await
+    .map_err(|e| AuthWindowError::Failed(e.to_string()))??;
+    Ok(cookies.into_iter().map(AuthCookie::from).collect())
+}
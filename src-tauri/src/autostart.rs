@@ -0,0 +1,464 @@
+//! Launch at login through each desktop's own mechanism: the `Run` registry key on
+//! Windows, a LaunchAgent on macOS, and an XDG autostart entry on Linux.
+//!
+//! Logins launched this way to start minimized pass [`AUTOSTART_FLAG`], and the app
+//! then stays in the tray instead of showing its windows.
+
+use std::io;
+use std::path::PathBuf;
+
+use serde::Serialize;
+use tauri::{AppHandle, Runtime};
+
+pub const AUTOSTART_FLAG: &str = "--autostarted";
+
+#[derive(Debug, thiserror::Error, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "camelCase")]
+pub enum AutostartError {
+    /// Sandboxed builds must register through their store or a portal instead.
+    #[error("launch at login can't be changed from a sandboxed build ({0})")]
+    Sandboxed(&'static str),
+    #[error("permission denied: {0}")]
+    PermissionDenied(String),
+    /// The write succeeded but reading it back disagrees, e.g. policy removed it.
+    #[error("the login item was written but didn't take effect")]
+    NotApplied,
+    #[error("{0}")]
+    Io(String),
+}
+
+impl From<io::Error> for AutostartError {
+    fn from(e: io::Error) -> Self {
+        match e.kind() {
+            io::ErrorKind::PermissionDenied => Self::PermissionDenied(e.to_string()),
+            _ => Self::Io(e.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct AutostartStatus {
+    pub enabled: bool,
+    /// Whether login launches start in the tray.
+    pub minimized: bool,
+}
+
+/// Whether this launch came from a login item created with `minimized`.
+pub fn launched_minimized() -> bool {
+    std::env::args().any(|arg| arg == AUTOSTART_FLAG)
+}
+
+/// What the login item runs. An AppImage runs from a new mount point each launch, so
+/// the image itself is registered.
+fn program() -> Result<PathBuf, AutostartError> {
+    match std::env::var_os("APPIMAGE") {
+        Some(image) if cfg!(target_os = "linux") => Ok(PathBuf::from(image)),
+        _ => Ok(std::env::current_exe()?),
+    }
+}
+
+#[cfg(windows)]
+mod native {
+    use std::ffi::c_void;
+    use std::path::Path;
+    use std::ptr::null_mut;
+
+    use tauri::{AppHandle, Runtime};
+    use windows_sys::Win32::Foundation::{ERROR_ACCESS_DENIED, ERROR_FILE_NOT_FOUND};
+    use windows_sys::Win32::System::Registry::{
+        RegDeleteKeyValueW, RegGetValueW, RegSetKeyValueW, HKEY_CURRENT_USER, REG_SZ,
+        RRF_RT_REG_BINARY, RRF_RT_REG_SZ,
+    };
+
+    use super::{AutostartError, AUTOSTART_FLAG};
+
+    const RUN_KEY: &str = r"Software\Microsoft\Windows\CurrentVersion\Run";
+    /// Where Task Manager's startup tab records entries the user turned off.
+    const APPROVED_KEY: &str =
+        r"Software\Microsoft\Windows\CurrentVersion\Explorer\StartupApproved\Run";
+
+    fn wide(s: &str) -> Vec<u16> {
+        s.encode_utf16().chain(Some(0)).collect()
+    }
+
+    fn check(status: u32) -> Result<(), AutostartError> {
+        match status {
+            0 => Ok(()),
+            ERROR_ACCESS_DENIED => Err(AutostartError::PermissionDenied(
+                "the registry refused the change".into(),
+            )),
+            code => Err(std::io::Error::from_raw_os_error(code as i32).into()),
+        }
+    }
+
+    fn read_command(name: &str) -> Result<Option<String>, AutostartError> {
+        let (key, value) = (wide(RUN_KEY), wide(name));
+        let mut size = 0u32;
+        // SAFETY: both strings are NUL-terminated; with a null buffer only the size in
+        // bytes is written to `size`.
+        let status = unsafe {
+            RegGetValueW(
+                HKEY_CURRENT_USER,
+                key.as_ptr(),
+                value.as_ptr(),
+                RRF_RT_REG_SZ,
+                null_mut(),
+                null_mut(),
+                &mut size,
+            )
+        };
+        if status == ERROR_FILE_NOT_FOUND {
+            return Ok(None);
+        }
+        check(status)?;
+
+        let mut data = vec![0u16; (size as usize).div_ceil(2)];
+        // SAFETY: `data` holds the `size` bytes the first call asked for.
+        let status = unsafe {
+            RegGetValueW(
+                HKEY_CURRENT_USER,
+                key.as_ptr(),
+                value.as_ptr(),
+                RRF_RT_REG_SZ,
+                null_mut(),
+                data.as_mut_ptr().cast::<c_void>(),
+                &mut size,
+            )
+        };
+        check(status)?;
+        let len = data.iter().position(|&c| c == 0).unwrap_or(data.len());
+        Ok(Some(String::from_utf16_lossy(&data[..len])))
+    }
+
+    /// Task Manager marks a disabled entry with an odd first byte.
+    fn disabled_in_task_manager(name: &str) -> bool {
+        let (key, value) = (wide(APPROVED_KEY), wide(name));
+        let mut data = [0u8; 16];
+        let mut size = data.len() as u32;
+        // SAFETY: both strings are NUL-terminated and `data` is `size` bytes long.
+        let status = unsafe {
+            RegGetValueW(
+                HKEY_CURRENT_USER,
+                key.as_ptr(),
+                value.as_ptr(),
+                RRF_RT_REG_BINARY,
+                null_mut(),
+                data.as_mut_ptr().cast::<c_void>(),
+                &mut size,
+            )
+        };
+        status == 0 && data[0] & 1 == 1
+    }
+
+    fn delete(key: &str, name: &str) -> Result<(), AutostartError> {
+        let (key, value) = (wide(key), wide(name));
+        // SAFETY: both strings are NUL-terminated.
+        let status = unsafe { RegDeleteKeyValueW(HKEY_CURRENT_USER, key.as_ptr(), value.as_ptr()) };
+        match status {
+            ERROR_FILE_NOT_FOUND => Ok(()),
+            status => check(status),
+        }
+    }
+
+    pub fn sandbox() -> Option<&'static str> {
+        None
+    }
+
+    pub fn read<R: Runtime>(app: &AppHandle<R>) -> Result<Option<bool>, AutostartError> {
+        let name = &app.config().identifier;
+        if disabled_in_task_manager(name) {
+            return Ok(None);
+        }
+        Ok(read_command(name)?.map(|command| command.contains(AUTOSTART_FLAG)))
+    }
+
+    /// Turning it on from the app also clears a Task Manager opt-out, since both are
+    /// the user's choice and this one is newer.
+    pub fn write<R: Runtime>(
+        app: &AppHandle<R>,
+        program: &Path,
+        minimized: bool,
+    ) -> Result<(), AutostartError> {
+        let name = &app.config().identifier;
+        let mut command = format!("\"{}\"", program.display());
+        if minimized {
+            command.push(' ');
+            command.push_str(AUTOSTART_FLAG);
+        }
+        let (key, value, data) = (wide(RUN_KEY), wide(name), wide(&command));
+        // SAFETY: all three strings are NUL-terminated; `data` is passed with its length
+        // in bytes, terminator included, as REG_SZ requires.
+        let status = unsafe {
+            RegSetKeyValueW(
+                HKEY_CURRENT_USER,
+                key.as_ptr(),
+                value.as_ptr(),
+                REG_SZ,
+                data.as_ptr().cast::<c_void>(),
+                (data.len() * 2) as u32,
+            )
+        };
+        check(status)?;
+        delete(APPROVED_KEY, name)
+    }
+
+    pub fn remove<R: Runtime>(app: &AppHandle<R>) -> Result<(), AutostartError> {
+        delete(RUN_KEY, &app.config().identifier)
+    }
+}
+
+/// A per-user LaunchAgent, named after the bundle identifier. macOS lists it under
+/// Login Items, where the user can still switch it off without the file changing.
+#[cfg(target_os = "macos")]
+mod native {
+    use std::fs;
+    use std::io;
+    use std::path::{Path, PathBuf};
+
+    use tauri::{AppHandle, Manager, Runtime};
+
+    use super::{AutostartError, AUTOSTART_FLAG};
+
+    fn plist_path<R: Runtime>(app: &AppHandle<R>) -> Result<PathBuf, AutostartError> {
+        let home = app
+            .path()
+            .home_dir()
+            .map_err(|e| AutostartError::Io(e.to_string()))?;
+        Ok(home
+            .join("Library/LaunchAgents")
+            .join(format!("{}.plist", app.config().identifier)))
+    }
+
+    fn escape(s: &str) -> String {
+        s.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+    }
+
+    pub fn sandbox() -> Option<&'static str> {
+        std::env::var_os("APP_SANDBOX_CONTAINER_ID").map(|_| "App Sandbox")
+    }
+
+    pub fn read<R: Runtime>(app: &AppHandle<R>) -> Result<Option<bool>, AutostartError> {
+        match fs::read_to_string(plist_path(app)?) {
+            Ok(plist) => Ok(Some(
+                plist.contains(&format!("<string>{AUTOSTART_FLAG}</string>")),
+            )),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn write<R: Runtime>(
+        app: &AppHandle<R>,
+        program: &Path,
+        minimized: bool,
+    ) -> Result<(), AutostartError> {
+        let path = plist_path(app)?;
+        let flag = if minimized {
+            format!("\n\t\t<string>{AUTOSTART_FLAG}</string>")
+        } else {
+            String::new()
+        };
+        let plist = format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+	<key>Label</key>
+	<string>{}</string>
+	<key>ProgramArguments</key>
+	<array>
+		<string>{}</string>{flag}
+	</array>
+	<key>RunAtLoad</key>
+	<true/>
+</dict>
+</plist>
+"#,
+            escape(&app.config().identifier),
+            escape(&program.to_string_lossy()),
+        );
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, plist)?;
+        Ok(())
+    }
+
+    pub fn remove<R: Runtime>(app: &AppHandle<R>) -> Result<(), AutostartError> {
+        match fs::remove_file(plist_path(app)?) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// An entry in `$XDG_CONFIG_HOME/autostart`, which GNOME, KDE, and most other desktops
+/// start at login.
+#[cfg(target_os = "linux")]
+mod native {
+    use std::fs;
+    use std::io;
+    use std::path::{Path, PathBuf};
+
+    use tauri::{AppHandle, Manager, Runtime};
+
+    use super::{AutostartError, AUTOSTART_FLAG};
+
+    fn entry_path<R: Runtime>(app: &AppHandle<R>) -> Result<PathBuf, AutostartError> {
+        let config = app
+            .path()
+            .config_dir()
+            .map_err(|e| AutostartError::Io(e.to_string()))?;
+        Ok(config
+            .join("autostart")
+            .join(format!("{}.desktop", app.config().identifier)))
+    }
+
+    /// Quotes an `Exec` argument as the Desktop Entry spec requires.
+    fn quote(arg: &str) -> String {
+        let mut quoted = String::from("\"");
+        for c in arg.chars() {
+            if matches!(c, '"' | '`' | '$' | '\\') {
+                quoted.push('\\');
+            }
+            quoted.push(c);
+        }
+        quoted.push('"');
+        quoted
+    }
+
+    pub fn sandbox() -> Option<&'static str> {
+        if std::env::var_os("FLATPAK_ID").is_some() {
+            Some("Flatpak")
+        } else if std::env::var_os("SNAP").is_some() {
+            Some("Snap")
+        } else {
+            None
+        }
+    }
+
+    pub fn read<R: Runtime>(app: &AppHandle<R>) -> Result<Option<bool>, AutostartError> {
+        let entry = match fs::read_to_string(entry_path(app)?) {
+            Ok(entry) => entry,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let lines = || entry.lines().map(str::trim);
+        let disabled =
+            lines().any(|line| line == "Hidden=true" || line == "X-GNOME-Autostart-enabled=false");
+        if disabled {
+            return Ok(None);
+        }
+        let minimized = lines()
+            .filter_map(|line| line.strip_prefix("Exec="))
+            .any(|exec| exec.split_whitespace().any(|arg| arg == AUTOSTART_FLAG));
+        Ok(Some(minimized))
+    }
+
+    pub fn write<R: Runtime>(
+        app: &AppHandle<R>,
+        program: &Path,
+        minimized: bool,
+    ) -> Result<(), AutostartError> {
+        let path = entry_path(app)?;
+        let mut exec = quote(&program.to_string_lossy());
+        if minimized {
+            exec.push(' ');
+            exec.push_str(AUTOSTART_FLAG);
+        }
+        let name = app
+            .config()
+            .product_name
+            .clone()
+            .unwrap_or_else(|| app.package_info().name.clone());
+        let entry = format!(
+            "[Desktop Entry]\nType=Application\nName={name}\nExec={exec}\nTerminal=false\n\
+             X-GNOME-Autostart-enabled=true\n"
+        );
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, entry)?;
+        Ok(())
+    }
+
+    pub fn remove<R: Runtime>(app: &AppHandle<R>) -> Result<(), AutostartError> {
+        match fs::remove_file(entry_path(app)?) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(not(any(windows, target_os = "macos", target_os = "linux")))]
+mod native {
+    use std::path::Path;
+
+    use tauri::{AppHandle, Runtime};
+
+    use super::AutostartError;
+
+    pub fn sandbox() -> Option<&'static str> {
+        None
+    }
+
+    pub fn read<R: Runtime>(_app: &AppHandle<R>) -> Result<Option<bool>, AutostartError> {
+        Ok(None)
+    }
+
+    pub fn write<R: Runtime>(
+        _app: &AppHandle<R>,
+        _program: &Path,
+        _minimized: bool,
+    ) -> Result<(), AutostartError> {
+        Err(AutostartError::Io("not supported on this platform".into()))
+    }
+
+    pub fn remove<R: Runtime>(_app: &AppHandle<R>) -> Result<(), AutostartError> {
+        Ok(())
+    }
+}
+
+fn status<R: Runtime>(app: &AppHandle<R>) -> Result<AutostartStatus, AutostartError> {
+    let minimized = native::read(app)?;
+    Ok(AutostartStatus {
+        enabled: minimized.is_some(),
+        minimized: minimized.unwrap_or(false),
+    })
+}
+
+/// Returns the setting as read back from the OS, failing with
+/// [`AutostartError::NotApplied`] if that isn't what was asked for.
+#[tauri::command]
+pub fn set_autostart<R: Runtime>(
+    enabled: bool,
+    minimized: bool,
+    app: AppHandle<R>,
+) -> Result<AutostartStatus, AutostartError> {
+    if let Some(sandbox) = native::sandbox() {
+        return Err(AutostartError::Sandboxed(sandbox));
+    }
+    if enabled {
+        native::write(&app, &program()?, minimized)?;
+    } else {
+        native::remove(&app)?;
+    }
+
+    let status = status(&app)?;
+    let expected = AutostartStatus {
+        enabled,
+        minimized: enabled && minimized,
+    };
+    if status != expected {
+        tracing::warn!(?status, ?expected, "autostart change didn't take effect");
+        return Err(AutostartError::NotApplied);
+    }
+    Ok(status)
+}
+
+#[tauri::command]
+pub fn get_autostart<R: Runtime>(app: AppHandle<R>) -> Result<AutostartStatus, AutostartError> {
+    status(&app)
+}
@@ -0,0 +1,504 @@
+use std::fs;
+use std::io::{Cursor, Read, Write};
+use std::path::{Path, PathBuf};
+
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::{Aead, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use argon2::Argon2;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, Runtime, State};
+use tauri_plugin_dialog::DialogExt;
+use tauri_plugin_store::StoreExt;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+use zip::write::SimpleFileOptions;
+use zip::{ZipArchive, ZipWriter};
+
+use crate::db::{self, Db, DEFAULT_DB};
+use crate::migrations::MigrationRunner;
+use crate::scope;
+
+pub const PROGRESS_EVENT: &str = "backup://progress";
+
+/// Bumped whenever the archive layout changes incompatibly.
+const FORMAT_VERSION: u32 = 1;
+
+const MANIFEST_ENTRY: &str = "manifest.json";
+const DB_ENTRY: &str = "database.sqlite";
+const STORES_DIR: &str = "stores";
+
+/// Encrypted backups are `MAGIC || salt || nonce || ciphertext`; a plain backup is a zip.
+const MAGIC: &[u8; 8] = b"LYRBAK01";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+/// Store files that are bound to this install and useless on another device: the vault
+/// is encrypted with a per-install key that never leaves the machine.
+const EXCLUDED_STORES: &[&str] = &["secrets-vault.json"];
+
+/// A restore is unpacked here first and renamed to [`PENDING_DIR`] once complete, so a
+/// crash mid-extract never leaves a half-written restore to be applied.
+const STAGING_DIR: &str = "restore.tmp";
+const PENDING_DIR: &str = "restore.pending";
+/// Snapshots of the current data taken before each restore.
+const PRE_RESTORE_DIR: &str = "backups";
+/// SQLite's side files, which must not outlive the database they belong to.
+const DB_SIDE_FILES: &[&str] = &["-wal", "-shm"];
+
+#[derive(Debug, thiserror::Error, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "camelCase")]
+pub enum BackupError {
+    #[error("no destination was chosen")]
+    Cancelled,
+    #[error("backup is encrypted; a passphrase is required")]
+    PassphraseRequired,
+    #[error("decryption failed; wrong passphrase or corrupted backup")]
+    Decryption,
+    #[error("not a valid backup: {0}")]
+    InvalidArchive(String),
+    #[error("unsupported backup format version {0}")]
+    UnsupportedVersion(u32),
+    #[error("backup schema version {backup} is newer than this app's {supported}")]
+    NewerSchema { backup: i64, supported: i64 },
+    #[error("{0}")]
+    Scope(String),
+    #[error("backup io failed: {0}")]
+    Io(String),
+    #[error("{0}")]
+    Db(String),
+}
+
+impl From<std::io::Error> for BackupError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e.to_string())
+    }
+}
+
+impl From<zip::result::ZipError> for BackupError {
+    fn from(e: zip::result::ZipError) -> Self {
+        Self::InvalidArchive(e.to_string())
+    }
+}
+
+impl From<serde_json::Error> for BackupError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::InvalidArchive(e.to_string())
+    }
+}
+
+impl From<sqlx::Error> for BackupError {
+    fn from(e: sqlx::Error) -> Self {
+        Self::Db(e.to_string())
+    }
+}
+
+impl From<db::DbError> for BackupError {
+    fn from(e: db::DbError) -> Self {
+        Self::Db(e.to_string())
+    }
+}
+
+impl From<tauri::Error> for BackupError {
+    fn from(e: tauri::Error) -> Self {
+        Self::Io(e.to_string())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupManifest {
+    pub format_version: u32,
+    pub schema_version: i64,
+    pub app_version: String,
+    /// RFC 3339.
+    pub created_at: String,
+    pub stores: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum BackupOperation {
+    Export,
+    Import,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupProgress {
+    pub operation: BackupOperation,
+    pub stage: &'static str,
+    /// 0.0 to 1.0.
+    pub progress: f32,
+}
+
+fn emit_progress<R: Runtime>(
+    app: &AppHandle<R>,
+    operation: BackupOperation,
+    stage: &'static str,
+    progress: f32,
+) {
+    let _ = app.emit(
+        PROGRESS_EVENT,
+        BackupProgress {
+            operation,
+            stage,
+            progress,
+        },
+    );
+}
+
+fn derive_cipher(passphrase: &str, salt: &[u8]) -> Result<Aes256Gcm, BackupError> {
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| BackupError::Io(format!("key derivation failed: {e}")))?;
+    Ok(Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key)))
+}
+
+fn encrypt(passphrase: &str, plaintext: &[u8]) -> Result<Vec<u8>, BackupError> {
+    let mut salt = [0u8; SALT_LEN];
+    let mut nonce = [0u8; NONCE_LEN];
+    OsRng.fill_bytes(&mut salt);
+    OsRng.fill_bytes(&mut nonce);
+
+    let ciphertext = derive_cipher(passphrase, &salt)?
+        .encrypt(
+            Nonce::from_slice(&nonce),
+            Payload {
+                msg: plaintext,
+                aad: MAGIC,
+            },
+        )
+        .map_err(|_| BackupError::Io("encryption failed".into()))?;
+
+    let mut out = Vec::with_capacity(MAGIC.len() + SALT_LEN + NONCE_LEN + ciphertext.len());
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&salt);
+    out.extend_from_slice(&nonce);
+    out.extend_from_slice(&ciphertext);
+    Ok(out)
+}
+
+fn decrypt(passphrase: Option<&str>, data: Vec<u8>) -> Result<Vec<u8>, BackupError> {
+    let Some(rest) = data.strip_prefix(MAGIC) else {
+        return Ok(data);
+    };
+    let passphrase = passphrase.ok_or(BackupError::PassphraseRequired)?;
+    if rest.len() < SALT_LEN + NONCE_LEN {
+        return Err(BackupError::InvalidArchive("truncated header".into()));
+    }
+    let (salt, rest) = rest.split_at(SALT_LEN);
+    let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+
+    derive_cipher(passphrase, salt)?
+        .decrypt(
+            Nonce::from_slice(nonce),
+            Payload {
+                msg: ciphertext,
+                aad: MAGIC,
+            },
+        )
+        .map_err(|_| BackupError::Decryption)
+}
+
+/// Store files live in the app data dir, where `tauri_plugin_store` resolves relative
+/// paths.
+fn store_dir<R: Runtime>(app: &AppHandle<R>) -> Result<PathBuf, BackupError> {
+    Ok(app.path().app_data_dir()?)
+}
+
+fn is_backed_up_store(name: &str) -> bool {
+    name.ends_with(".json") && !EXCLUDED_STORES.contains(&name)
+}
+
+fn list_stores(dir: &Path) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut names: Vec<String> = entries
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_ok_and(|t| t.is_file()))
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|name| is_backed_up_store(name))
+        .collect();
+    names.sort();
+    names
+}
+
+/// Writes `bytes` beside `dest` and renames it into place, so a failed export never
+/// truncates an existing backup.
+fn write_atomic(dest: &Path, bytes: &[u8]) -> Result<(), BackupError> {
+    let mut tmp = dest.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+    fs::write(&tmp, bytes)?;
+    fs::rename(&tmp, dest).inspect_err(|_| {
+        let _ = fs::remove_file(&tmp);
+    })?;
+    Ok(())
+}
+
+/// Snapshots the database and stores into a zip. `VACUUM INTO` gives a consistent copy
+/// even while the frontend's connection keeps writing through the WAL.
+async fn build_archive<R: Runtime>(
+    app: &AppHandle<R>,
+    operation: BackupOperation,
+) -> Result<(BackupManifest, Vec<u8>), BackupError> {
+    let db = app.state::<Db>();
+    let pool = db.ready_pool().await?;
+
+    emit_progress(app, operation, "snapshot", 0.1);
+    let snapshot = db::resolve_db_path(app, DEFAULT_DB)?.with_extension("backup-snapshot");
+    let _ = fs::remove_file(&snapshot);
+    sqlx::query("VACUUM INTO ?")
+        .bind(snapshot.to_string_lossy().into_owned())
+        .execute(pool)
+        .await?;
+    let schema_version = MigrationRunner::current_version(pool).await?;
+
+    // Flush stores open in this process so the archive sees their latest values.
+    emit_progress(app, operation, "stores", 0.4);
+    let dir = store_dir(app)?;
+    let stores = list_stores(&dir);
+    for name in &stores {
+        if let Some(store) = app.get_store(name) {
+            if let Err(e) = store.save() {
+                tracing::warn!(store = %name, error = %e, "failed to flush store before backup");
+            }
+        }
+    }
+
+    let manifest = BackupManifest {
+        format_version: FORMAT_VERSION,
+        schema_version,
+        app_version: app.package_info().version.to_string(),
+        created_at: OffsetDateTime::now_utc()
+            .format(&Rfc3339)
+            .unwrap_or_default(),
+        stores,
+    };
+
+    emit_progress(app, operation, "compress", 0.6);
+    let archive = {
+        let manifest = manifest.clone();
+        let snapshot = snapshot.clone();
+        tauri::async_runtime::spawn_blocking(move || -> Result<Vec<u8>, BackupError> {
+            let options = SimpleFileOptions::default();
+            let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+
+            zip.start_file(MANIFEST_ENTRY, options)?;
+            zip.write_all(&serde_json::to_vec_pretty(&manifest)?)?;
+            zip.start_file(DB_ENTRY, options)?;
+            zip.write_all(&fs::read(&snapshot)?)?;
+            for name in &manifest.stores {
+                zip.start_file(format!("{STORES_DIR}/{name}"), options)?;
+                zip.write_all(&fs::read(dir.join(name))?)?;
+            }
+
+            Ok(zip.finish()?.into_inner())
+        })
+        .await
+    };
+    let _ = fs::remove_file(&snapshot);
+
+    let archive = archive.map_err(|e| BackupError::Io(e.to_string()))??;
+    Ok((manifest, archive))
+}
+
+async fn pick_destination<R: Runtime>(app: &AppHandle<R>) -> Result<PathBuf, BackupError> {
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    let date = OffsetDateTime::now_utc().date();
+    app.dialog()
+        .file()
+        .set_file_name(format!("layers-backup-{date}.zip"))
+        .add_filter("Layers backup", &["zip"])
+        .save_file(move |path| {
+            let _ = tx.send(path);
+        });
+
+    rx.await
+        .ok()
+        .flatten()
+        .ok_or(BackupError::Cancelled)?
+        .into_path()
+        .map_err(|e| BackupError::Io(e.to_string()))
+}
+
+/// Exports the database and store files to a zip, encrypted when `passphrase` is given.
+/// Without `dest_path` the user picks one in a save dialog; the chosen path is returned.
+#[tauri::command]
+pub async fn export_backup<R: Runtime>(
+    dest_path: Option<String>,
+    passphrase: Option<String>,
+    app: AppHandle<R>,
+) -> Result<String, BackupError> {
+    // A path typed into the OS dialog is the user's choice; one from the frontend isn't.
+    let dest = match dest_path {
+        Some(path) => scope::ensure_allowed(&app, Path::new(&path)).map_err(BackupError::Scope)?,
+        None => pick_destination(&app).await?,
+    };
+
+    let (_, archive) = build_archive(&app, BackupOperation::Export).await?;
+    let bytes = match passphrase.as_deref() {
+        Some(passphrase) => {
+            emit_progress(&app, BackupOperation::Export, "encrypt", 0.8);
+            encrypt(passphrase, &archive)?
+        }
+        None => archive,
+    };
+
+    emit_progress(&app, BackupOperation::Export, "write", 0.9);
+    write_atomic(&dest, &bytes)?;
+    emit_progress(&app, BackupOperation::Export, "done", 1.0);
+
+    tracing::info!(dest = %dest.display(), encrypted = passphrase.is_some(), "backup exported");
+    Ok(dest.to_string_lossy().into_owned())
+}
+
+fn read_manifest<T: Read + std::io::Seek>(
+    archive: &mut ZipArchive<T>,
+    supported: i64,
+) -> Result<BackupManifest, BackupError> {
+    let manifest: BackupManifest = serde_json::from_reader(archive.by_name(MANIFEST_ENTRY)?)?;
+    if manifest.format_version != FORMAT_VERSION {
+        return Err(BackupError::UnsupportedVersion(manifest.format_version));
+    }
+    if manifest.schema_version > supported {
+        return Err(BackupError::NewerSchema {
+            backup: manifest.schema_version,
+            supported,
+        });
+    }
+    Ok(manifest)
+}
+
+fn extract(
+    archive: &mut ZipArchive<Cursor<Vec<u8>>>,
+    manifest: &BackupManifest,
+    staging: &Path,
+) -> Result<(), BackupError> {
+    let _ = fs::remove_dir_all(staging);
+    fs::create_dir_all(staging.join(STORES_DIR))?;
+
+    let mut entries = vec![(DB_ENTRY.to_string(), staging.join(DB_ENTRY))];
+    for name in &manifest.stores {
+        // Names come from the archive; only plain file names inside `stores/` are valid.
+        if !is_backed_up_store(name) || Path::new(name).file_name() != Some(name.as_ref()) {
+            return Err(BackupError::InvalidArchive(format!(
+                "bad store name {name}"
+            )));
+        }
+        entries.push((
+            format!("{STORES_DIR}/{name}"),
+            staging.join(STORES_DIR).join(name),
+        ));
+    }
+
+    for (entry, path) in entries {
+        let mut file = archive.by_name(&entry)?;
+        let mut out = fs::File::create(&path)?;
+        std::io::copy(&mut file, &mut out)?;
+        out.sync_all()?;
+    }
+    fs::write(staging.join(MANIFEST_ENTRY), serde_json::to_vec(manifest)?)?;
+    Ok(())
+}
+
+/// Validates a backup, snapshots the current data to `backups/`, and stages the restore,
+/// then restarts the app. The staged files replace the live ones in
+/// [`apply_pending_restore`] before the database is opened, as open connections from
+/// both pools would otherwise keep writing to the old file.
+#[tauri::command]
+pub async fn import_backup<R: Runtime>(
+    src_path: String,
+    passphrase: Option<String>,
+    app: AppHandle<R>,
+    runner: State<'_, MigrationRunner>,
+) -> Result<BackupManifest, BackupError> {
+    let src = scope::ensure_allowed(&app, Path::new(&src_path)).map_err(BackupError::Scope)?;
+
+    emit_progress(&app, BackupOperation::Import, "read", 0.0);
+    let data = decrypt(passphrase.as_deref(), fs::read(&src)?)?;
+    let mut archive = ZipArchive::new(Cursor::new(data))?;
+    let manifest = read_manifest(&mut archive, runner.latest_version())?;
+
+    let (current, snapshot) = build_archive(&app, BackupOperation::Import).await?;
+    let backups = app.path().app_data_dir()?.join(PRE_RESTORE_DIR);
+    fs::create_dir_all(&backups)?;
+    let name = format!("pre-restore-{}.zip", current.created_at.replace(':', "-"));
+    write_atomic(&backups.join(name), &snapshot)?;
+
+    emit_progress(&app, BackupOperation::Import, "extract", 0.8);
+    let data_dir = app.path().app_data_dir()?;
+    let staging = data_dir.join(STAGING_DIR);
+    let pending = data_dir.join(PENDING_DIR);
+    let staged = {
+        let manifest = manifest.clone();
+        let staging = staging.clone();
+        tauri::async_runtime::spawn_blocking(move || extract(&mut archive, &manifest, &staging))
+            .await
+            .map_err(|e| BackupError::Io(e.to_string()))?
+    };
+    if let Err(e) = staged {
+        let _ = fs::remove_dir_all(&staging);
+        return Err(e);
+    }
+    let _ = fs::remove_dir_all(&pending);
+    fs::rename(&staging, &pending)?;
+
+    emit_progress(&app, BackupOperation::Import, "restarting", 1.0);
+    tracing::info!(
+        schema_version = manifest.schema_version,
+        created_at = %manifest.created_at,
+        "backup staged; restarting to restore"
+    );
+    app.request_restart();
+    Ok(manifest)
+}
+
+/// Swaps in a restore staged by [`import_backup`]. Runs in `setup` before [`Db::open`]
+/// and before anything loads a store, so nothing holds the files being replaced. Each
+/// step tolerates having already run, so a restore interrupted here resumes next launch.
+pub fn apply_pending_restore<R: Runtime>(app: &AppHandle<R>) -> Result<(), BackupError> {
+    let pending = app.path().app_data_dir()?.join(PENDING_DIR);
+    if !pending.is_dir() {
+        return Ok(());
+    }
+    let manifest: BackupManifest =
+        serde_json::from_slice(&fs::read(pending.join(MANIFEST_ENTRY))?)?;
+
+    let db_path = db::resolve_db_path(app, DEFAULT_DB)?;
+    for suffix in DB_SIDE_FILES {
+        let mut stale = db_path.as_os_str().to_owned();
+        stale.push(suffix);
+        let _ = fs::remove_file(PathBuf::from(stale));
+    }
+    move_file(&pending.join(DB_ENTRY), &db_path)?;
+
+    // Stores not in the backup are dropped so the restore isn't a mix of both devices.
+    let dir = store_dir(app)?;
+    for name in list_stores(&dir) {
+        if !manifest.stores.contains(&name) {
+            fs::remove_file(dir.join(&name))?;
+        }
+    }
+    for name in &manifest.stores {
+        move_file(&pending.join(STORES_DIR).join(name), &dir.join(name))?;
+    }
+
+    fs::remove_dir_all(&pending)?;
+    tracing::info!(created_at = %manifest.created_at, "restored backup");
+    Ok(())
+}
+
+/// The database lives in the config dir and the staging area in the data dir, which can
+/// be different file systems on Linux; then the file is copied beside `to` and renamed.
+fn move_file(from: &Path, to: &Path) -> Result<(), BackupError> {
+    if !from.exists() || fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    write_atomic(to, &fs::read(from)?)?;
+    fs::remove_file(from)?;
+    Ok(())
+}
@@ -0,0 +1,136 @@
+//! The number on the app icon. iOS sets it directly; Android launchers draw it from
+//! notifications, so the native half keeps a silent notification on its own channel
+//! whose number is the count. Desktop reports [`BadgeError::NotSupported`].
+//!
+//! The native halves live in `plugins/badge`, registered here like [`crate::share`].
+//! The commands are async because both halves finish on the main thread, which a sync
+//! command would be holding.
+
+use serde::Serialize;
+use tauri::{AppHandle, Runtime};
+
+#[derive(Debug, thiserror::Error, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "camelCase")]
+pub enum BadgeError {
+    #[cfg_attr(mobile, allow(dead_code))]
+    #[error("app badges are not supported on this platform")]
+    NotSupported,
+    /// Notifications are off for the app, which also hides its badge.
+    #[cfg_attr(desktop, allow(dead_code))]
+    #[error("notification permission not granted")]
+    PermissionDenied,
+    #[cfg_attr(desktop, allow(dead_code))]
+    #[error("badge update failed: {0}")]
+    Failed(String),
+}
+
+#[cfg(mobile)]
+mod native {
+    use serde::{Deserialize, Serialize};
+    use serde_json::Value;
+    use tauri::plugin::mobile::PluginInvokeError;
+    use tauri::plugin::{PluginHandle, TauriPlugin};
+    use tauri::{AppHandle, Manager, Runtime};
+    // Linked for its native halves; it has no Rust API.
+    use tauri_plugin_badge as _;
+
+    use super::BadgeError;
+
+    #[cfg(target_os = "ios")]
+    tauri::ios_plugin_binding!(init_plugin_badge);
+
+    struct Badge<R: Runtime>(PluginHandle<R>);
+
+    #[derive(Serialize)]
+    struct CountArgs {
+        count: u32,
+    }
+
+    #[derive(Deserialize)]
+    struct CountResponse {
+        count: Option<u32>,
+    }
+
+    pub fn plugin<R: Runtime>() -> TauriPlugin<R> {
+        tauri::plugin::Builder::new("badge")
+            .setup(|app, api| {
+                #[cfg(target_os = "android")]
+                let handle = api.register_android_plugin("com.layers.badge", "BadgePlugin")?;
+                #[cfg(target_os = "ios")]
+                let handle = api.register_ios_plugin(init_plugin_badge)?;
+                app.manage(Badge(handle));
+                Ok(())
+            })
+            .build()
+    }
+
+    fn from_plugin(e: PluginInvokeError) -> BadgeError {
+        match e {
+            PluginInvokeError::InvokeRejected(response) => match response.code.as_deref() {
+                Some("PermissionDenied") => BadgeError::PermissionDenied,
+                _ => BadgeError::Failed(response.message.unwrap_or_default()),
+            },
+            e => BadgeError::Failed(e.to_string()),
+        }
+    }
+
+    fn handle<R: Runtime>(app: &AppHandle<R>) -> Result<PluginHandle<R>, BadgeError> {
+        app.try_state::<Badge<R>>()
+            .map(|badge| badge.0.clone())
+            .ok_or_else(|| BadgeError::Failed("badge plugin not loaded".into()))
+    }
+
+    pub async fn set_count<R: Runtime>(app: &AppHandle<R>, count: u32) -> Result<(), BadgeError> {
+        handle(app)?
+            .run_mobile_plugin_async::<Value>("setCount", CountArgs { count })
+            .await
+            .map(|_| ())
+            .map_err(from_plugin)
+    }
+
+    pub async fn get_count<R: Runtime>(app: &AppHandle<R>) -> Result<Option<u32>, BadgeError> {
+        handle(app)?
+            .run_mobile_plugin_async::<CountResponse>("getCount", ())
+            .await
+            .map(|response| response.count)
+            .map_err(from_plugin)
+    }
+}
+
+#[cfg(desktop)]
+mod native {
+    use tauri::{AppHandle, Runtime};
+
+    use super::BadgeError;
+
+    pub async fn set_count<R: Runtime>(_app: &AppHandle<R>, _count: u32) -> Result<(), BadgeError> {
+        Err(BadgeError::NotSupported)
+    }
+
+    pub async fn get_count<R: Runtime>(_app: &AppHandle<R>) -> Result<Option<u32>, BadgeError> {
+        Err(BadgeError::NotSupported)
+    }
+}
+
+#[cfg(mobile)]
+pub fn plugin<R: Runtime>() -> tauri::plugin::TauriPlugin<R> {
+    native::plugin()
+}
+
+/// Zero removes the badge, as [`clear_badge`] does.
+#[tauri::command]
+pub async fn set_badge_count<R: Runtime>(count: u32, app: AppHandle<R>) -> Result<(), BadgeError> {
+    native::set_count(&app, count).await
+}
+
+#[tauri::command]
+pub async fn clear_badge<R: Runtime>(app: AppHandle<R>) -> Result<(), BadgeError> {
+    native::set_count(&app, 0).await
+}
+
+/// `None` on Android until the app has set a badge, since launchers don't say what
+/// they show.
+#[tauri::command]
+pub async fn get_badge_count<R: Runtime>(app: AppHandle<R>) -> Result<Option<u32>, BadgeError> {
+    native::get_count(&app).await
+}
@@ -0,0 +1,247 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+use futures::future::{join_all, BoxFuture};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::Executor;
+use tauri::{AppHandle, Manager, Runtime, Webview};
+use tauri_plugin_store::{Store, StoreExt};
+
+use crate::audit::AuditLog;
+use crate::db::{self, Db};
+use crate::rate_limit::RateLimiter;
+use crate::{scope, secrets};
+
+#[derive(Debug, Deserialize)]
+pub struct BatchCall {
+    pub command: String,
+    #[serde(default)]
+    pub payload: Value,
+}
+
+/// Exactly one of `ok` and `err` is set.
+#[derive(Debug, Serialize)]
+pub struct BatchResult {
+    pub ok: Option<Value>,
+    pub err: Option<String>,
+}
+
+impl From<Result<Value, String>> for BatchResult {
+    fn from(result: Result<Value, String>) -> Self {
+        match result {
+            Ok(value) => Self {
+                ok: Some(value),
+                err: None,
+            },
+            Err(e) => Self {
+                ok: None,
+                err: Some(e),
+            },
+        }
+    }
+}
+
+/// A command that can run inside [`batch_invoke`]. Handlers check the same scopes as the
+/// commands and plugins they stand in for, since batching must not widen what the
+/// webview can reach.
+pub trait BatchHandler<R: Runtime>: Send + Sync {
+    fn handle(
+        &self,
+        app: AppHandle<R>,
+        payload: Value,
+    ) -> BoxFuture<'static, Result<Value, String>>;
+}
+
+/// Plain async functions are handlers too.
+impl<R, F, Fut> BatchHandler<R> for F
+where
+    R: Runtime,
+    F: Fn(AppHandle<R>, Value) -> Fut + Send + Sync,
+    Fut: Future<Output = Result<Value, String>> + Send + 'static,
+{
+    fn handle(
+        &self,
+        app: AppHandle<R>,
+        payload: Value,
+    ) -> BoxFuture<'static, Result<Value, String>> {
+        Box::pin(self(app, payload))
+    }
+}
+
+pub struct BatchRegistry<R: Runtime> {
+    handlers: HashMap<String, Box<dyn BatchHandler<R>>>,
+}
+
+impl<R: Runtime> BatchRegistry<R> {
+    pub fn register(&mut self, command: &str, handler: impl BatchHandler<R> + 'static) {
+        self.handlers.insert(command.to_string(), Box::new(handler));
+    }
+}
+
+pub fn init<R: Runtime>(app: &AppHandle<R>) {
+    let mut registry = BatchRegistry::<R> {
+        handlers: HashMap::new(),
+    };
+    registry.register("store_get", store_get);
+    registry.register("store_set", store_set);
+    registry.register("sql_query", sql_query);
+    registry.register("read_file", read_file);
+    app.manage(registry);
+}
+
+fn args<T: DeserializeOwned>(payload: Value) -> Result<T, String> {
+    serde_json::from_value(payload).map_err(|e| format!("invalid payload: {e}"))
+}
+
+#[derive(Deserialize)]
+struct StoreArgs {
+    store: String,
+    key: String,
+    #[serde(default)]
+    value: Value,
+}
+
+/// Stores are named by a bare file name in the app data directory, like the `store`
+/// plugin's, and the secrets vault is only reachable through the `secret_*` commands.
+fn open_store<R: Runtime>(app: &AppHandle<R>, name: &str) -> Result<Arc<Store<R>>, String> {
+    let mut components = Path::new(name).components();
+    let file = match (components.next(), components.next()) {
+        (Some(Component::Normal(file)), None) => file.to_str().unwrap_or_default(),
+        _ => return Err(format!("{name} isn't a store file name")),
+    };
+    // Windows ignores case and trailing dots and spaces, so those name the vault too.
+    if file
+        .trim_end_matches(['.', ' '])
+        .eq_ignore_ascii_case(secrets::VAULT_STORE)
+    {
+        return Err(format!("{name} can't be accessed from a batch"));
+    }
+    app.store(file).map_err(|e| e.to_string())
+}
+
+async fn store_get<R: Runtime>(app: AppHandle<R>, payload: Value) -> Result<Value, String> {
+    let StoreArgs { store, key, .. } = args(payload)?;
+    Ok(open_store(&app, &store)?.get(key).unwrap_or(Value::Null))
+}
+
+async fn store_set<R: Runtime>(app: AppHandle<R>, payload: Value) -> Result<Value, String> {
+    let StoreArgs { store, key, value } = args(payload)?;
+    let store = open_store(&app, &store)?;
+    store.set(key, value);
+    store.save().map_err(|e| e.to_string())?;
+    Ok(Value::Null)
+}
+
+#[derive(Deserialize)]
+struct SqlArgs {
+    sql: String,
+    #[serde(default)]
+    params: Vec<Value>,
+}
+
+/// One `SELECT` (or `WITH … SELECT`) statement, so a batch can read the app database but
+/// not change it or attach another one.
+fn ensure_select(sql: &str) -> Result<(), String> {
+    let sql = sql.trim().trim_end_matches(';');
+    if sql.contains(';') {
+        return Err("only one statement can run per call".into());
+    }
+    let keyword = sql
+        .split(|c: char| !c.is_ascii_alphabetic())
+        .next()
+        .unwrap_or_default();
+    if !keyword.eq_ignore_ascii_case("select") && !keyword.eq_ignore_ascii_case("with") {
+        return Err("only SELECT statements can be batched".into());
+    }
+    Ok(())
+}
+
+/// All rows as JSON objects, like the `sql` plugin's `select`. The statement runs with
+/// `query_only` on as well, which catches a `WITH` that ends in a write.
+async fn sql_query<R: Runtime>(app: AppHandle<R>, payload: Value) -> Result<Value, String> {
+    let SqlArgs { sql, params } = args(payload)?;
+    ensure_select(&sql)?;
+    let db = app.state::<Db>();
+    let mut conn = db
+        .pool()
+        .map_err(|e| e.to_string())?
+        .acquire()
+        .await
+        .map_err(|e| e.to_string())?;
+    conn.execute("PRAGMA query_only = ON")
+        .await
+        .map_err(|e| e.to_string())?;
+    let mut query = sqlx::query(&sql);
+    for param in &params {
+        query = db::bind_value(query, param);
+    }
+    let rows = query.fetch_all(&mut *conn).await;
+    if let Err(e) = conn.execute("PRAGMA query_only = OFF").await {
+        // Back in the pool, the connection would refuse the app's own writes.
+        tracing::warn!(error = %e, "failed to reset query_only; dropping the connection");
+        conn.detach();
+    }
+    rows.map_err(|e| e.to_string())?
+        .iter()
+        .map(db::row_to_json)
+        .collect::<Result<Vec<_>, _>>()
+        .map(Value::from)
+        .map_err(|e| e.to_string())
+}
+
+#[derive(Deserialize)]
+struct ReadFileArgs {
+    path: PathBuf,
+}
+
+/// The file as UTF-8 text; binary files should go through `read_file_stream`.
+async fn read_file<R: Runtime>(app: AppHandle<R>, payload: Value) -> Result<Value, String> {
+    let ReadFileArgs { path } = args(payload)?;
+    let resolved = scope::ensure_allowed(&app, &path)?;
+    tokio::fs::read_to_string(&resolved)
+        .await
+        .map(Value::from)
+        .map_err(|e| format!("{}: {e}", path.display()))
+}
+
+/// Runs several calls in one IPC round trip. Calls run concurrently, so a batch must not
+/// rely on their order; results come back in the order of `calls`, and one failing call
+/// doesn't affect the others.
+///
+/// Each call is rate limited and audited under its own name, as it would be if invoked
+/// on its own; the invoke handler only sees the one `batch_invoke`.
+#[tauri::command]
+pub async fn batch_invoke<R: Runtime>(
+    calls: Vec<BatchCall>,
+    app: AppHandle<R>,
+    webview: Webview<R>,
+) -> Vec<BatchResult> {
+    let registry = app.state::<BatchRegistry<R>>();
+    let limiter = app.state::<RateLimiter>();
+    let audit = app.state::<AuditLog>();
+    let pending = calls.into_iter().map(|call| {
+        let entry = audit.begin_json(&call.command, &call.payload);
+        let handler = registry.handlers.get(&call.command);
+        let future = match (handler, limiter.check(&call.command)) {
+            (Some(handler), Ok(())) => handler.handle(app.clone(), call.payload),
+            (Some(_), Err(e)) => Box::pin(async move { Err(e.to_string()) }),
+            (None, _) => {
+                let command = call.command;
+                Box::pin(async move { Err(format!("`{command}` can't be batched")) })
+            }
+        };
+        let (audit, window) = (&audit, webview.label());
+        async move {
+            let result = future.await;
+            if let Some(entry) = entry {
+                audit.finish(entry, window, result.is_ok());
+            }
+            BatchResult::from(result)
+        }
+    });
+    join_all(pending).await
+}
@@ -0,0 +1,264 @@
+use serde::Serialize;
+use tauri::{AppHandle, Runtime};
+
+/// Most variants, like the results below, only occur on mobile.
+#[cfg_attr(desktop, allow(dead_code))]
+#[derive(Debug, thiserror::Error, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "camelCase")]
+pub enum BiometricError {
+    #[error("biometric authentication is not available on this device")]
+    NotAvailable,
+    #[error("no biometrics are enrolled on this device")]
+    NotEnrolled,
+    #[error("biometric authentication is locked after too many failed attempts")]
+    LockOut,
+    #[error("biometric authentication failed: {0}")]
+    SystemError(String),
+}
+
+#[cfg_attr(desktop, allow(dead_code))]
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum BiometricResult {
+    /// Passed without saying how: any [`authenticate_biometric`] success, and
+    /// [`authenticate_with_device_credential`] on iOS, which doesn't report the factor.
+    Authenticated,
+    AuthenticatedWithBiometric,
+    /// With the PIN, pattern, password or passcode.
+    AuthenticatedWithCredential,
+    Cancelled,
+}
+
+#[cfg_attr(desktop, allow(dead_code))]
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum BiometryKind {
+    None,
+    Fingerprint,
+    Face,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BiometricAvailability {
+    pub available: bool,
+    /// The hardware present, even if it can't be used right now.
+    pub kind: BiometryKind,
+    /// Why it can't be used, when `available` is `false`.
+    pub reason: Option<BiometricError>,
+}
+
+#[cfg(mobile)]
+mod native {
+    use tauri::plugin::mobile::PluginInvokeError;
+    use tauri::{AppHandle, Runtime};
+    use tauri_plugin_biometric::{AuthOptions, BiometricExt, BiometryType};
+
+    use super::{BiometricAvailability, BiometricError, BiometricResult, BiometryKind};
+
+    /// `plugins/device-credential`, which shares the biometric plugin's error codes.
+    #[cfg(target_os = "android")]
+    pub struct DeviceCredential<R: Runtime>(pub tauri::plugin::PluginHandle<R>);
+
+    /// Maps the plugin's error codes, shared by its Swift and Kotlin halves. `Ok` means
+    /// the prompt was dismissed rather than failed.
+    fn from_code(code: Option<&str>, message: String) -> Result<BiometricResult, BiometricError> {
+        match code {
+            Some("userCancel" | "systemCancel" | "appCancel" | "userFallback") => {
+                Ok(BiometricResult::Cancelled)
+            }
+            Some("biometryNotAvailable" | "passcodeNotSet" | "noDeviceCredential") => {
+                Err(BiometricError::NotAvailable)
+            }
+            Some("biometryNotEnrolled") => Err(BiometricError::NotEnrolled),
+            Some("biometryLockout") => Err(BiometricError::LockOut),
+            _ => Err(BiometricError::SystemError(message)),
+        }
+    }
+
+    fn from_plugin(e: tauri_plugin_biometric::Error) -> Result<BiometricResult, BiometricError> {
+        match e {
+            tauri_plugin_biometric::Error::PluginInvoke(PluginInvokeError::InvokeRejected(
+                response,
+            )) => from_code(
+                response.code.as_deref(),
+                response.message.unwrap_or_default(),
+            ),
+            e => Err(BiometricError::SystemError(e.to_string())),
+        }
+    }
+
+    pub fn authenticate<R: Runtime>(
+        app: &AppHandle<R>,
+        reason: String,
+    ) -> Result<BiometricResult, BiometricError> {
+        match app.biometric().authenticate(reason, AuthOptions::default()) {
+            Ok(()) => Ok(BiometricResult::Authenticated),
+            Err(e) => from_plugin(e),
+        }
+    }
+
+    /// The biometric plugin's prompt accepts the credential on Android too but doesn't say
+    /// which factor passed, so Android has its own.
+    #[cfg(target_os = "android")]
+    pub fn authenticate_with_device_credential<R: Runtime>(
+        app: &AppHandle<R>,
+        reason: String,
+    ) -> Result<BiometricResult, BiometricError> {
+        use tauri::Manager;
+
+        #[derive(serde::Serialize)]
+        struct Args {
+            reason: String,
+        }
+        #[derive(serde::Deserialize)]
+        struct Passed {
+            factor: String,
+        }
+
+        let handle = app
+            .try_state::<DeviceCredential<R>>()
+            .map(|plugin| plugin.0.clone())
+            .ok_or_else(|| {
+                BiometricError::SystemError("device credential plugin not loaded".into())
+            })?;
+        match handle.run_mobile_plugin::<Passed>("authenticate", Args { reason }) {
+            Ok(passed) if passed.factor == "credential" => {
+                Ok(BiometricResult::AuthenticatedWithCredential)
+            }
+            Ok(_) => Ok(BiometricResult::AuthenticatedWithBiometric),
+            Err(PluginInvokeError::InvokeRejected(response)) => from_code(
+                response.code.as_deref(),
+                response.message.unwrap_or_default(),
+            ),
+            Err(e) => Err(BiometricError::SystemError(e.to_string())),
+        }
+    }
+
+    /// `.deviceOwnerAuthentication` falls back to the passcode by itself, but `LAContext`
+    /// never says whether it did.
+    #[cfg(target_os = "ios")]
+    pub fn authenticate_with_device_credential<R: Runtime>(
+        app: &AppHandle<R>,
+        reason: String,
+    ) -> Result<BiometricResult, BiometricError> {
+        let options = AuthOptions {
+            allow_device_credential: true,
+            ..Default::default()
+        };
+        match app.biometric().authenticate(reason, options) {
+            Ok(()) => Ok(BiometricResult::Authenticated),
+            Err(e) => from_plugin(e),
+        }
+    }
+
+    pub fn availability<R: Runtime>(app: &AppHandle<R>) -> BiometricAvailability {
+        let status = match app.biometric().status() {
+            Ok(status) => status,
+            Err(e) => {
+                return BiometricAvailability {
+                    available: false,
+                    kind: BiometryKind::None,
+                    reason: from_plugin(e).err(),
+                }
+            }
+        };
+        let kind = match status.biometry_type {
+            BiometryType::None => BiometryKind::None,
+            BiometryType::TouchID => BiometryKind::Fingerprint,
+            BiometryType::FaceID => BiometryKind::Face,
+        };
+        let reason = (!status.is_available).then(|| {
+            from_code(
+                status.error_code.as_deref(),
+                status.error.unwrap_or_default(),
+            )
+            .err()
+            .unwrap_or(BiometricError::NotAvailable)
+        });
+        BiometricAvailability {
+            available: status.is_available,
+            kind,
+            reason,
+        }
+    }
+}
+
+/// Desktop platforms have no biometric prompt the app can use yet.
+#[cfg(desktop)]
+mod native {
+    use tauri::{AppHandle, Runtime};
+
+    use super::{BiometricAvailability, BiometricError, BiometricResult, BiometryKind};
+
+    pub fn authenticate<R: Runtime>(
+        _app: &AppHandle<R>,
+        _reason: String,
+    ) -> Result<BiometricResult, BiometricError> {
+        Err(BiometricError::NotAvailable)
+    }
+
+    pub fn authenticate_with_device_credential<R: Runtime>(
+        _app: &AppHandle<R>,
+        _reason: String,
+    ) -> Result<BiometricResult, BiometricError> {
+        Err(BiometricError::NotAvailable)
+    }
+
+    pub fn availability<R: Runtime>(_app: &AppHandle<R>) -> BiometricAvailability {
+        BiometricAvailability {
+            available: false,
+            kind: BiometryKind::None,
+            reason: Some(BiometricError::NotAvailable),
+        }
+    }
+}
+
+#[cfg(target_os = "android")]
+pub fn plugin<R: Runtime>() -> tauri::plugin::TauriPlugin<R> {
+    use tauri::Manager;
+    // Linked for its native half; it has no Rust API.
+    use tauri_plugin_device_credential as _;
+
+    tauri::plugin::Builder::new("device-credential")
+        .setup(|app, api| {
+            let handle = api
+                .register_android_plugin("com.layers.devicecredential", "DeviceCredentialPlugin")?;
+            app.manage(native::DeviceCredential(handle));
+            Ok(())
+        })
+        .build()
+}
+
+/// Prompts for Face ID, Touch ID, or fingerprint, showing `reason` where the platform
+/// displays one. Dismissing the prompt is [`BiometricResult::Cancelled`], not an error.
+#[tauri::command]
+pub async fn authenticate_biometric<R: Runtime>(
+    reason: String,
+    app: AppHandle<R>,
+) -> Result<BiometricResult, BiometricError> {
+    // The plugin call blocks until the prompt is dismissed.
+    tauri::async_runtime::spawn_blocking(move || native::authenticate(&app, reason))
+        .await
+        .map_err(|e| BiometricError::SystemError(e.to_string()))?
+}
+
+/// Whether [`authenticate_biometric`] can succeed, without showing a prompt.
+#[tauri::command]
+pub fn biometric_availability<R: Runtime>(app: AppHandle<R>) -> BiometricAvailability {
+    native::availability(&app)
+}
+
+/// Like [`authenticate_biometric`], but the PIN, pattern or passcode is accepted too, so
+/// it also works on devices without enrolled biometrics. Android reports which factor
+/// passed; iOS can't, and returns [`BiometricResult::Authenticated`].
+#[tauri::command]
+pub async fn authenticate_with_device_credential<R: Runtime>(
+    reason: String,
+    app: AppHandle<R>,
+) -> Result<BiometricResult, BiometricError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        native::authenticate_with_device_credential(&app, reason)
+    })
+    .await
+    .map_err(|e| BiometricError::SystemError(e.to_string()))?
+}
@@ -0,0 +1,679 @@
+//! Bluetooth Low Energy: scanning for peripherals and reading and writing their GATT
+//! characteristics. `btleplug` on desktop (BlueZ, WinRT and Core Bluetooth), and
+//! `plugins/ble` (Core Bluetooth and `android.bluetooth.le`) on iOS and Android.
+//!
+//! Devices are identified by their MAC address, except on macOS and iOS, where Core
+//! Bluetooth only hands out a per-app peripheral UUID. Service and characteristic UUIDs may be given in full or as
+//! 16-bit short forms like `180d`.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, Runtime, State};
+use uuid::Uuid;
+
+pub const DEVICE_DISCOVERED_EVENT: &str = "ble://device-discovered";
+
+/// The Bluetooth base UUID that 16- and 32-bit short UUIDs are offsets into.
+const BASE_UUID: u128 = 0x0000_0000_0000_1000_8000_0080_5f9b_34fb;
+
+#[derive(Debug, thiserror::Error, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "camelCase")]
+pub enum BleError {
+    #[error("no Bluetooth adapter is available")]
+    AdapterUnavailable,
+    #[error("Bluetooth permission denied")]
+    PermissionDenied,
+    #[error("device not found: {0}")]
+    DeviceNotFound(String),
+    #[error("connection failed: {0}")]
+    ConnectionFailed(String),
+    #[error("unknown or closed connection handle")]
+    NotConnected,
+    #[error("characteristic not found")]
+    CharacteristicNotFound,
+    #[error("invalid UUID: {0}")]
+    InvalidUuid(String),
+    #[error("Bluetooth operation failed: {0}")]
+    Failed(String),
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BleFilter {
+    /// Only report devices advertising at least one of these services.
+    #[serde(default)]
+    pub services: Vec<String>,
+    pub name_prefix: Option<String>,
+    /// Weakest signal to report, in dBm.
+    pub min_rssi: Option<i16>,
+}
+
+/// Payload of [`DEVICE_DISCOVERED_EVENT`], sent again whenever the signal strength of a
+/// device already reported changes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BleDevice {
+    pub id: String,
+    pub name: Option<String>,
+    pub rssi: i16,
+    /// Lowercase and in full, whatever form the device advertised them in.
+    pub service_uuids: Vec<String>,
+}
+
+/// [`BleFilter`] with its UUIDs parsed. Every platform applies it to what it reports,
+/// since not all of them can filter on names or signal strength.
+struct ScanFilter {
+    services: Vec<Uuid>,
+    name_prefix: Option<String>,
+    min_rssi: Option<i16>,
+}
+
+impl ScanFilter {
+    fn parse(filter: BleFilter) -> Result<Self, BleError> {
+        Ok(Self {
+            services: filter
+                .services
+                .iter()
+                .map(|uuid| parse_uuid(uuid))
+                .collect::<Result<_, _>>()?,
+            name_prefix: filter.name_prefix.filter(|prefix| !prefix.is_empty()),
+            min_rssi: filter.min_rssi,
+        })
+    }
+
+    fn matches(&self, device: &BleDevice) -> bool {
+        let services = self.services.is_empty()
+            || device
+                .service_uuids
+                .iter()
+                .filter_map(|uuid| Uuid::parse_str(uuid).ok())
+                .any(|uuid| self.services.contains(&uuid));
+        let name = match &self.name_prefix {
+            Some(prefix) => device
+                .name
+                .as_deref()
+                .is_some_and(|name| name.starts_with(prefix.as_str())),
+            None => true,
+        };
+        services && name && self.min_rssi.is_none_or(|min| device.rssi >= min)
+    }
+}
+
+fn parse_uuid(uuid: &str) -> Result<Uuid, BleError> {
+    let invalid = || BleError::InvalidUuid(uuid.to_owned());
+    let trimmed = uuid.trim();
+    if matches!(trimmed.len(), 4 | 8) {
+        let short = u32::from_str_radix(trimmed, 16).map_err(|_| invalid())?;
+        return Ok(Uuid::from_u128(BASE_UUID | (short as u128) << 96));
+    }
+    Uuid::parse_str(trimmed).map_err(|_| invalid())
+}
+
+fn emit<R: Runtime>(app: &AppHandle<R>, device: BleDevice) {
+    use tauri::Emitter;
+
+    let _ = app.emit(DEVICE_DISCOVERED_EVENT, device);
+}
+
+/// The running scan, and open connections by the handle given to the frontend.
+#[derive(Default)]
+pub struct Ble {
+    #[cfg(desktop)]
+    central: native::Central,
+    scan: Mutex<Option<native::Scan>>,
+    connections: Mutex<HashMap<String, Arc<native::Connection>>>,
+}
+
+#[cfg(mobile)]
+mod native {
+    use serde::{Deserialize, Serialize};
+    use serde_json::Value;
+    use tauri::ipc::{Channel, InvokeResponseBody};
+    use tauri::plugin::mobile::PluginInvokeError;
+    use tauri::plugin::{PluginHandle, TauriPlugin};
+    use tauri::{AppHandle, Manager, Runtime};
+    // Linked for its native halves; it has no Rust API.
+    use tauri_plugin_ble as _;
+    use uuid::Uuid;
+
+    use super::{BleDevice, BleError, ScanFilter};
+
+    #[cfg(target_os = "ios")]
+    tauri::ios_plugin_binding!(init_plugin_ble);
+
+    struct Bluetooth<R: Runtime>(PluginHandle<R>);
+
+    /// The native side tracks its single scan itself.
+    pub struct Scan;
+
+    pub struct Connection {
+        device_id: String,
+    }
+
+    #[derive(Serialize)]
+    struct ScanArgs {
+        services: Vec<String>,
+        channel: Channel,
+    }
+
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    struct DeviceArgs<'a> {
+        device_id: &'a str,
+    }
+
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    struct CharacteristicArgs<'a> {
+        device_id: &'a str,
+        service: String,
+        characteristic: String,
+    }
+
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    struct WriteArgs<'a> {
+        #[serde(flatten)]
+        target: CharacteristicArgs<'a>,
+        value: &'a [u8],
+        with_response: bool,
+    }
+
+    #[derive(Deserialize)]
+    struct ReadResponse {
+        value: Vec<u8>,
+    }
+
+    pub fn plugin<R: Runtime>() -> TauriPlugin<R> {
+        tauri::plugin::Builder::new("ble")
+            .setup(|app, api| {
+                #[cfg(target_os = "android")]
+                let handle = api.register_android_plugin("com.layers.ble", "BlePlugin")?;
+                #[cfg(target_os = "ios")]
+                let handle = api.register_ios_plugin(init_plugin_ble)?;
+                app.manage(Bluetooth(handle));
+                Ok(())
+            })
+            .build()
+    }
+
+    /// Both native halves reject with the same codes.
+    fn from_plugin(e: PluginInvokeError) -> BleError {
+        match e {
+            PluginInvokeError::InvokeRejected(response) => {
+                let message = response.message.unwrap_or_default();
+                match response.code.as_deref() {
+                    Some("AdapterUnavailable") => BleError::AdapterUnavailable,
+                    Some("PermissionDenied") => BleError::PermissionDenied,
+                    Some("DeviceNotFound") => BleError::DeviceNotFound(message),
+                    Some("ConnectionFailed") => BleError::ConnectionFailed(message),
+                    Some("CharacteristicNotFound") => BleError::CharacteristicNotFound,
+                    _ => BleError::Failed(message),
+                }
+            }
+            e => BleError::Failed(e.to_string()),
+        }
+    }
+
+    fn bluetooth<R: Runtime>(app: &AppHandle<R>) -> Result<PluginHandle<R>, BleError> {
+        app.try_state::<Bluetooth<R>>()
+            .map(|bluetooth| bluetooth.0.clone())
+            .ok_or_else(|| BleError::Failed("ble plugin not loaded".into()))
+    }
+
+    /// iOS reports short UUIDs in their short form, and in uppercase.
+    fn normalize(mut device: BleDevice) -> BleDevice {
+        device.service_uuids = device
+            .service_uuids
+            .iter()
+            .filter_map(|uuid| super::parse_uuid(uuid).ok())
+            .map(|uuid| uuid.to_string())
+            .collect();
+        device
+    }
+
+    pub fn start_scan<R: Runtime>(
+        app: &AppHandle<R>,
+        filter: ScanFilter,
+    ) -> Result<Scan, BleError> {
+        let services = filter.services.iter().map(Uuid::to_string).collect();
+        let handle = app.clone();
+        let channel = Channel::new(move |body| {
+            let InvokeResponseBody::Json(json) = body else {
+                return Ok(());
+            };
+            match serde_json::from_str::<BleDevice>(&json) {
+                Ok(device) => {
+                    let device = normalize(device);
+                    if filter.matches(&device) {
+                        super::emit(&handle, device);
+                    }
+                }
+                Err(e) => tracing::warn!(error = %e, "malformed BLE scan result"),
+            }
+            Ok(())
+        });
+        bluetooth(app)?
+            .run_mobile_plugin::<Value>("startScan", ScanArgs { services, channel })
+            .map_err(from_plugin)?;
+        Ok(Scan)
+    }
+
+    pub fn stop_scan<R: Runtime>(app: &AppHandle<R>, _scan: Scan) {
+        let result = bluetooth(app).and_then(|bluetooth| {
+            bluetooth
+                .run_mobile_plugin::<Value>("stopScan", ())
+                .map_err(from_plugin)
+        });
+        if let Err(e) = result {
+            tracing::warn!(error = %e, "failed to stop BLE scan");
+        }
+    }
+
+    pub fn connect<R: Runtime>(
+        app: &AppHandle<R>,
+        device_id: &str,
+    ) -> Result<Connection, BleError> {
+        bluetooth(app)?
+            .run_mobile_plugin::<Value>("connect", DeviceArgs { device_id })
+            .map_err(from_plugin)?;
+        Ok(Connection {
+            device_id: device_id.to_owned(),
+        })
+    }
+
+    pub fn read<R: Runtime>(
+        app: &AppHandle<R>,
+        connection: &Connection,
+        service: Uuid,
+        characteristic: Uuid,
+    ) -> Result<Vec<u8>, BleError> {
+        let response: ReadResponse = bluetooth(app)?
+            .run_mobile_plugin(
+                "read",
+                CharacteristicArgs {
+                    device_id: &connection.device_id,
+                    service: service.to_string(),
+                    characteristic: characteristic.to_string(),
+                },
+            )
+            .map_err(from_plugin)?;
+        Ok(response.value)
+    }
+
+    pub fn write<R: Runtime>(
+        app: &AppHandle<R>,
+        connection: &Connection,
+        service: Uuid,
+        characteristic: Uuid,
+        value: &[u8],
+        with_response: bool,
+    ) -> Result<(), BleError> {
+        bluetooth(app)?
+            .run_mobile_plugin::<Value>(
+                "write",
+                WriteArgs {
+                    target: CharacteristicArgs {
+                        device_id: &connection.device_id,
+                        service: service.to_string(),
+                        characteristic: characteristic.to_string(),
+                    },
+                    value,
+                    with_response,
+                },
+            )
+            .map_err(from_plugin)?;
+        Ok(())
+    }
+
+    pub fn disconnect<R: Runtime>(app: &AppHandle<R>, connection: &Connection) {
+        let result = bluetooth(app).and_then(|bluetooth| {
+            bluetooth
+                .run_mobile_plugin::<Value>(
+                    "disconnect",
+                    DeviceArgs {
+                        device_id: &connection.device_id,
+                    },
+                )
+                .map_err(from_plugin)
+        });
+        if let Err(e) = result {
+            tracing::warn!(error = %e, "failed to disconnect BLE device");
+        }
+    }
+}
+
+/// `btleplug` behind the same blocking calls as the mobile plugin; they wait on its
+/// async API from the threads [`blocking`] runs them on.
+#[cfg(desktop)]
+mod native {
+    use std::collections::HashMap;
+
+    use btleplug::api::{
+        Central as _, CentralEvent, Characteristic, Manager as _, Peripheral as _, WriteType,
+    };
+    use btleplug::platform::{Adapter, Manager, Peripheral};
+    use futures::StreamExt;
+    use tauri::async_runtime::{self, JoinHandle};
+    use tauri::{AppHandle, Manager as _, Runtime};
+    use tokio::sync::OnceCell;
+    use uuid::Uuid;
+
+    use super::{Ble, BleDevice, BleError, ScanFilter};
+
+    /// The first adapter, opened on first use. It remembers the peripherals scans have
+    /// found, which is where [`connect`] looks for them.
+    #[derive(Default)]
+    pub struct Central(OnceCell<Adapter>);
+
+    /// The task reporting what the adapter discovers.
+    pub struct Scan(JoinHandle<()>);
+
+    pub struct Connection(Peripheral);
+
+    fn failed(e: btleplug::Error) -> BleError {
+        match e {
+            btleplug::Error::NoAdapterAvailable => BleError::AdapterUnavailable,
+            btleplug::Error::PermissionDenied => BleError::PermissionDenied,
+            btleplug::Error::NotConnected => BleError::NotConnected,
+            btleplug::Error::NoSuchCharacteristic => BleError::CharacteristicNotFound,
+            e => BleError::Failed(e.to_string()),
+        }
+    }
+
+    async fn adapter<R: Runtime>(app: &AppHandle<R>) -> Result<Adapter, BleError> {
+        let ble = app.state::<Ble>();
+        ble.central
+            .0
+            .get_or_try_init(|| async {
+                let manager = Manager::new().await.map_err(failed)?;
+                manager
+                    .adapters()
+                    .await
+                    .map_err(failed)?
+                    .into_iter()
+                    .next()
+                    .ok_or(BleError::AdapterUnavailable)
+            })
+            .await
+            .cloned()
+    }
+
+    fn id_of(peripheral: &Peripheral) -> String {
+        if cfg!(target_os = "macos") {
+            peripheral.id().to_string()
+        } else {
+            peripheral.address().to_string()
+        }
+    }
+
+    /// `None` for devices with no signal reading, which are only remembered rather than
+    /// in range.
+    async fn device(peripheral: &Peripheral) -> Option<BleDevice> {
+        let properties = peripheral.properties().await.ok()??;
+        Some(BleDevice {
+            id: id_of(peripheral),
+            name: properties.local_name.or(properties.advertisement_name),
+            rssi: properties.rssi?,
+            service_uuids: properties.services.iter().map(Uuid::to_string).collect(),
+        })
+    }
+
+    pub fn start_scan<R: Runtime>(
+        app: &AppHandle<R>,
+        filter: ScanFilter,
+    ) -> Result<Scan, BleError> {
+        async_runtime::block_on(async {
+            let adapter = adapter(app).await?;
+            let mut events = adapter.events().await.map_err(failed)?;
+            adapter
+                .start_scan(btleplug::api::ScanFilter {
+                    services: filter.services.clone(),
+                })
+                .await
+                .map_err(failed)?;
+            let app = app.clone();
+            Ok(Scan(async_runtime::spawn(async move {
+                let mut reported: HashMap<String, i16> = HashMap::new();
+                while let Some(event) = events.next().await {
+                    let id = match event {
+                        CentralEvent::DeviceDiscovered(id)
+                        | CentralEvent::DeviceUpdated(id)
+                        | CentralEvent::RssiUpdate { id, .. }
+                        | CentralEvent::ServicesAdvertisement { id, .. } => id,
+                        _ => continue,
+                    };
+                    let Ok(peripheral) = adapter.peripheral(&id).await else {
+                        continue;
+                    };
+                    let Some(device) = device(&peripheral).await else {
+                        continue;
+                    };
+                    if !filter.matches(&device) || reported.get(&device.id) == Some(&device.rssi) {
+                        continue;
+                    }
+                    reported.insert(device.id.clone(), device.rssi);
+                    super::emit(&app, device);
+                }
+            })))
+        })
+    }
+
+    /// Waits for the adapter to stop, so a scan started right after isn't stopped too.
+    pub fn stop_scan<R: Runtime>(app: &AppHandle<R>, scan: Scan) {
+        scan.0.abort();
+        let result = async_runtime::block_on(async {
+            adapter(app).await?.stop_scan().await.map_err(failed)
+        });
+        if let Err(e) = result {
+            tracing::warn!(error = %e, "failed to stop BLE scan");
+        }
+    }
+
+    pub fn connect<R: Runtime>(
+        app: &AppHandle<R>,
+        device_id: &str,
+    ) -> Result<Connection, BleError> {
+        async_runtime::block_on(async {
+            let peripheral = adapter(app)
+                .await?
+                .peripherals()
+                .await
+                .map_err(failed)?
+                .into_iter()
+                .find(|peripheral| id_of(peripheral).eq_ignore_ascii_case(device_id))
+                .ok_or_else(|| BleError::DeviceNotFound(device_id.to_owned()))?;
+            peripheral
+                .connect()
+                .await
+                .map_err(|e| BleError::ConnectionFailed(e.to_string()))?;
+            if let Err(e) = peripheral.discover_services().await {
+                let _ = peripheral.disconnect().await;
+                return Err(BleError::ConnectionFailed(e.to_string()));
+            }
+            Ok(Connection(peripheral))
+        })
+    }
+
+    impl Connection {
+        fn characteristic(
+            &self,
+            service: Uuid,
+            characteristic: Uuid,
+        ) -> Result<Characteristic, BleError> {
+            self.0
+                .characteristics()
+                .into_iter()
+                .find(|found| found.service_uuid == service && found.uuid == characteristic)
+                .ok_or(BleError::CharacteristicNotFound)
+        }
+    }
+
+    pub fn read<R: Runtime>(
+        _app: &AppHandle<R>,
+        connection: &Connection,
+        service: Uuid,
+        characteristic: Uuid,
+    ) -> Result<Vec<u8>, BleError> {
+        let characteristic = connection.characteristic(service, characteristic)?;
+        async_runtime::block_on(connection.0.read(&characteristic)).map_err(failed)
+    }
+
+    pub fn write<R: Runtime>(
+        _app: &AppHandle<R>,
+        connection: &Connection,
+        service: Uuid,
+        characteristic: Uuid,
+        value: &[u8],
+        with_response: bool,
+    ) -> Result<(), BleError> {
+        let characteristic = connection.characteristic(service, characteristic)?;
+        let kind = if with_response {
+            WriteType::WithResponse
+        } else {
+            WriteType::WithoutResponse
+        };
+        async_runtime::block_on(connection.0.write(&characteristic, value, kind)).map_err(failed)
+    }
+
+    pub fn disconnect<R: Runtime>(_app: &AppHandle<R>, connection: &Connection) {
+        if let Err(e) = async_runtime::block_on(connection.0.disconnect()) {
+            tracing::warn!(error = %e, "failed to disconnect BLE device");
+        }
+    }
+}
+
+#[cfg(mobile)]
+pub fn plugin<R: Runtime>() -> tauri::plugin::TauriPlugin<R> {
+    native::plugin()
+}
+
+/// Every platform's Bluetooth API blocks or wants its own thread.
+async fn blocking<T: Send + 'static>(
+    work: impl FnOnce() -> Result<T, BleError> + Send + 'static,
+) -> Result<T, BleError> {
+    tauri::async_runtime::spawn_blocking(work)
+        .await
+        .map_err(|e| BleError::Failed(e.to_string()))?
+}
+
+fn connection(ble: &Ble, handle: &str) -> Result<Arc<native::Connection>, BleError> {
+    ble.connections
+        .lock()
+        .unwrap()
+        .get(handle)
+        .cloned()
+        .ok_or(BleError::NotConnected)
+}
+
+/// Emits [`DEVICE_DISCOVERED_EVENT`] for matching devices until [`ble_stop_scan`].
+/// Starting a scan replaces any running one. Bluetooth permission is requested first
+/// where the platform asks for it.
+#[tauri::command]
+pub async fn ble_start_scan<R: Runtime>(
+    filter: BleFilter,
+    app: AppHandle<R>,
+) -> Result<(), BleError> {
+    let filter = ScanFilter::parse(filter)?;
+    blocking(move || {
+        let ble = app.state::<Ble>();
+        let mut scan = ble.scan.lock().unwrap();
+        if let Some(previous) = scan.take() {
+            native::stop_scan(&app, previous);
+        }
+        *scan = Some(native::start_scan(&app, filter)?);
+        Ok(())
+    })
+    .await
+}
+
+/// Does nothing if no scan is running.
+#[tauri::command]
+pub fn ble_stop_scan<R: Runtime>(app: AppHandle<R>, ble: State<'_, Ble>) {
+    let scan = ble.scan.lock().unwrap().take();
+    if let Some(scan) = scan {
+        native::stop_scan(&app, scan);
+    }
+}
+
+/// Connects to a device found by a scan, returning the handle the other commands take.
+#[tauri::command]
+pub async fn ble_connect<R: Runtime>(
+    device_id: String,
+    app: AppHandle<R>,
+) -> Result<String, BleError> {
+    blocking(move || {
+        let connection = native::connect(&app, &device_id)?;
+        let handle = Uuid::new_v4().to_string();
+        app.state::<Ble>()
+            .connections
+            .lock()
+            .unwrap()
+            .insert(handle.clone(), Arc::new(connection));
+        Ok(handle)
+    })
+    .await
+}
+
+/// Unknown handles are ignored.
+#[tauri::command]
+pub async fn ble_disconnect<R: Runtime>(handle: String, app: AppHandle<R>) -> Result<(), BleError> {
+    blocking(move || {
+        let connection = app
+            .state::<Ble>()
+            .connections
+            .lock()
+            .unwrap()
+            .remove(&handle);
+        if let Some(connection) = connection {
+            native::disconnect(&app, &connection);
+        }
+        Ok(())
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn ble_read_characteristic<R: Runtime>(
+    handle: String,
+    service: String,
+    characteristic: String,
+    app: AppHandle<R>,
+) -> Result<Vec<u8>, BleError> {
+    let service = parse_uuid(&service)?;
+    let characteristic = parse_uuid(&characteristic)?;
+    blocking(move || {
+        let connection = connection(&app.state::<Ble>(), &handle)?;
+        native::read(&app, &connection, service, characteristic)
+    })
+    .await
+}
+
+/// Writes with a response unless `with_response` is false, in which case the write is
+/// only as reliable as the link.
+#[tauri::command]
+pub async fn ble_write_characteristic<R: Runtime>(
+    handle: String,
+    service: String,
+    characteristic: String,
+    value: Vec<u8>,
+    with_response: Option<bool>,
+    app: AppHandle<R>,
+) -> Result<(), BleError> {
+    let service = parse_uuid(&service)?;
+    let characteristic = parse_uuid(&characteristic)?;
+    blocking(move || {
+        let connection = connection(&app.state::<Ble>(), &handle)?;
+        native::write(
+            &app,
+            &connection,
+            service,
+            characteristic,
+            &value,
+            with_response.unwrap_or(true),
+        )
+    })
+    .await
+}
@@ -0,0 +1,441 @@
+//! The device calendars: `EKEventStore` on iOS and `CalendarContract` on Android, both in
+//! `plugins/calendar`. Desktop calendars have no common API, so there listing and
+//! querying fail with [`CalendarError::NotSupported`], and [`create_event`] hands the
+//! event to the system calendar app as an `.ics` file for the user to save.
+//!
+//! Times are Unix milliseconds. All-day events run from midnight UTC on their first day
+//! to midnight UTC after their last, whatever the device's time zone.
+
+use serde::{Deserialize, Serialize};
+use tauri::plugin::PermissionState;
+use tauri::{AppHandle, Emitter, Runtime};
+
+pub const CREATED_EVENT: &str = "calendar://event-created";
+
+/// Longest range [`query_events`] accepts; EventKit silently truncates longer ones.
+const MAX_QUERY_RANGE_MS: u64 = 4 * 365 * DAY_MS;
+const DAY_MS: u64 = 24 * 60 * 60 * 1000;
+
+#[cfg_attr(desktop, allow(dead_code))]
+#[derive(Debug, thiserror::Error, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "camelCase")]
+pub enum CalendarError {
+    #[error("calendar permission denied")]
+    PermissionDenied,
+    #[error("calendars are not supported here: {0}")]
+    NotSupported(String),
+    #[error("not found: {0}")]
+    NotFound(String),
+    #[error("invalid event: {0}")]
+    InvalidInput(String),
+    #[error("calendar request failed: {0}")]
+    Failed(String),
+}
+
+#[cfg_attr(desktop, allow(dead_code))]
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CalendarInfo {
+    pub id: String,
+    pub title: String,
+    /// `#RRGGBB`.
+    pub color: Option<String>,
+    /// Whether [`create_event`] can add to it.
+    pub writable: bool,
+    /// The calendar new events go to when no `calendar_id` is given.
+    pub primary: bool,
+    /// The account or source it syncs with, like an email address or "iCloud".
+    pub account: Option<String>,
+}
+
+#[cfg_attr(desktop, allow(dead_code))]
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CalendarEvent {
+    /// Shared by every occurrence of a recurring event.
+    pub id: String,
+    pub title: String,
+    pub start_ms: u64,
+    pub end_ms: u64,
+    pub location: Option<String>,
+    pub notes: Option<String>,
+    pub all_day: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NewCalendarEvent {
+    /// The primary calendar when absent.
+    #[serde(default)]
+    pub calendar_id: Option<String>,
+    pub title: String,
+    pub start_ms: u64,
+    pub end_ms: u64,
+    #[serde(default)]
+    pub location: Option<String>,
+    #[serde(default)]
+    pub notes: Option<String>,
+    #[serde(default)]
+    pub all_day: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventCreated {
+    pub id: String,
+    pub calendar_id: String,
+}
+
+#[cfg(mobile)]
+mod native {
+    use serde::{Deserialize, Serialize};
+    use tauri::plugin::mobile::PluginInvokeError;
+    use tauri::plugin::{PermissionState, PluginHandle, TauriPlugin};
+    use tauri::{AppHandle, Manager, Runtime};
+    // Linked for its native halves; it has no Rust API.
+    use tauri_plugin_calendar as _;
+
+    use super::{CalendarError, CalendarEvent, CalendarInfo, EventCreated, NewCalendarEvent};
+
+    #[cfg(target_os = "ios")]
+    tauri::ios_plugin_binding!(init_plugin_calendar);
+
+    /// The event is in the store by the time [`create_event`] returns.
+    pub const SAVES: bool = true;
+
+    struct Calendar<R: Runtime>(PluginHandle<R>);
+
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    struct QueryArgs {
+        calendar_id: String,
+        start_ms: u64,
+        end_ms: u64,
+    }
+
+    #[derive(Deserialize)]
+    struct PermissionResponse {
+        calendar: PermissionState,
+    }
+
+    #[derive(Deserialize)]
+    struct CalendarsResponse {
+        calendars: Vec<CalendarInfo>,
+    }
+
+    #[derive(Deserialize)]
+    struct EventsResponse {
+        events: Vec<CalendarEvent>,
+    }
+
+    pub fn plugin<R: Runtime>() -> TauriPlugin<R> {
+        tauri::plugin::Builder::new("calendar")
+            .setup(|app, api| {
+                #[cfg(target_os = "android")]
+                let handle =
+                    api.register_android_plugin("com.layers.calendar", "CalendarPlugin")?;
+                #[cfg(target_os = "ios")]
+                let handle = api.register_ios_plugin(init_plugin_calendar)?;
+                app.manage(Calendar(handle));
+                Ok(())
+            })
+            .build()
+    }
+
+    /// Both native halves reject with the same codes.
+    fn from_plugin(e: PluginInvokeError) -> CalendarError {
+        match e {
+            PluginInvokeError::InvokeRejected(response) => match response.code.as_deref() {
+                Some("PermissionDenied") => CalendarError::PermissionDenied,
+                Some("NotFound") => CalendarError::NotFound(response.message.unwrap_or_default()),
+                _ => CalendarError::Failed(response.message.unwrap_or_default()),
+            },
+            e => CalendarError::Failed(e.to_string()),
+        }
+    }
+
+    fn handle<R: Runtime>(app: &AppHandle<R>) -> Result<PluginHandle<R>, CalendarError> {
+        app.try_state::<Calendar<R>>()
+            .map(|calendar| calendar.0.clone())
+            .ok_or_else(|| CalendarError::Failed("calendar plugin not loaded".into()))
+    }
+
+    pub async fn request_permission<R: Runtime>(
+        app: &AppHandle<R>,
+    ) -> Result<PermissionState, CalendarError> {
+        handle(app)?
+            .run_mobile_plugin_async::<PermissionResponse>("requestPermission", ())
+            .await
+            .map(|response| response.calendar)
+            .map_err(from_plugin)
+    }
+
+    pub async fn list_calendars<R: Runtime>(
+        app: &AppHandle<R>,
+    ) -> Result<Vec<CalendarInfo>, CalendarError> {
+        handle(app)?
+            .run_mobile_plugin_async::<CalendarsResponse>("listCalendars", ())
+            .await
+            .map(|response| response.calendars)
+            .map_err(from_plugin)
+    }
+
+    pub async fn query_events<R: Runtime>(
+        app: &AppHandle<R>,
+        calendar_id: String,
+        start_ms: u64,
+        end_ms: u64,
+    ) -> Result<Vec<CalendarEvent>, CalendarError> {
+        let args = QueryArgs {
+            calendar_id,
+            start_ms,
+            end_ms,
+        };
+        handle(app)?
+            .run_mobile_plugin_async::<EventsResponse>("queryEvents", args)
+            .await
+            .map(|response| response.events)
+            .map_err(from_plugin)
+    }
+
+    pub async fn create_event<R: Runtime>(
+        app: &AppHandle<R>,
+        event: NewCalendarEvent,
+    ) -> Result<EventCreated, CalendarError> {
+        handle(app)?
+            .run_mobile_plugin_async("createEvent", event)
+            .await
+            .map_err(from_plugin)
+    }
+}
+
+#[cfg(desktop)]
+mod native {
+    use tauri::plugin::PermissionState;
+    use tauri::{AppHandle, Manager, Runtime};
+    use tauri_plugin_opener::OpenerExt;
+    use time::OffsetDateTime;
+
+    use super::{CalendarError, CalendarEvent, CalendarInfo, EventCreated, NewCalendarEvent};
+
+    /// The user saves the event, or doesn't, in their calendar app; nothing reports which.
+    pub const SAVES: bool = false;
+
+    fn unsupported() -> CalendarError {
+        CalendarError::NotSupported("there is no calendar API on desktop".into())
+    }
+
+    pub async fn request_permission<R: Runtime>(
+        _app: &AppHandle<R>,
+    ) -> Result<PermissionState, CalendarError> {
+        Err(unsupported())
+    }
+
+    pub async fn list_calendars<R: Runtime>(
+        _app: &AppHandle<R>,
+    ) -> Result<Vec<CalendarInfo>, CalendarError> {
+        Err(unsupported())
+    }
+
+    pub async fn query_events<R: Runtime>(
+        _app: &AppHandle<R>,
+        _calendar_id: String,
+        _start_ms: u64,
+        _end_ms: u64,
+    ) -> Result<Vec<CalendarEvent>, CalendarError> {
+        Err(unsupported())
+    }
+
+    fn timestamp(ms: u64) -> Result<OffsetDateTime, CalendarError> {
+        OffsetDateTime::from_unix_timestamp_nanos(i128::from(ms) * 1_000_000)
+            .map_err(|e| CalendarError::InvalidInput(e.to_string()))
+    }
+
+    fn date(ms: u64) -> Result<String, CalendarError> {
+        let at = timestamp(ms)?;
+        Ok(format!(
+            "{:04}{:02}{:02}",
+            at.year(),
+            u8::from(at.month()),
+            at.day()
+        ))
+    }
+
+    fn date_time(ms: u64) -> Result<String, CalendarError> {
+        let at = timestamp(ms)?;
+        Ok(format!(
+            "{}T{:02}{:02}{:02}Z",
+            date(ms)?,
+            at.hour(),
+            at.minute(),
+            at.second()
+        ))
+    }
+
+    /// RFC 5545 `TEXT`: backslash escapes, and newlines as `\n`.
+    fn text(value: &str) -> String {
+        value
+            .replace('\\', "\\\\")
+            .replace(';', "\\;")
+            .replace(',', "\\,")
+            .replace("\r\n", "\\n")
+            .replace('\n', "\\n")
+    }
+
+    /// Folds a content line at 75 octets, without splitting a character.
+    fn push_line(ics: &mut String, line: &str) {
+        let mut width = 0;
+        for c in line.chars() {
+            if width + c.len_utf8() > 75 {
+                ics.push_str("\r\n ");
+                width = 1;
+            }
+            ics.push(c);
+            width += c.len_utf8();
+        }
+        ics.push_str("\r\n");
+    }
+
+    fn ics(uid: &str, event: &NewCalendarEvent) -> Result<String, CalendarError> {
+        let (start, end) = if event.all_day {
+            (
+                format!("DTSTART;VALUE=DATE:{}", date(event.start_ms)?),
+                format!("DTEND;VALUE=DATE:{}", date(event.end_ms)?),
+            )
+        } else {
+            (
+                format!("DTSTART:{}", date_time(event.start_ms)?),
+                format!("DTEND:{}", date_time(event.end_ms)?),
+            )
+        };
+        let now = OffsetDateTime::now_utc().unix_timestamp() as u64 * 1000;
+
+        let mut lines = vec![
+            "BEGIN:VCALENDAR".to_string(),
+            "VERSION:2.0".to_string(),
+            "PRODID:-//Layers//Calendar//EN".to_string(),
+            "BEGIN:VEVENT".to_string(),
+            format!("UID:{uid}"),
+            format!("DTSTAMP:{}", date_time(now)?),
+            start,
+            end,
+            format!("SUMMARY:{}", text(&event.title)),
+        ];
+        if let Some(location) = &event.location {
+            lines.push(format!("LOCATION:{}", text(location)));
+        }
+        if let Some(notes) = &event.notes {
+            lines.push(format!("DESCRIPTION:{}", text(notes)));
+        }
+        lines.push("END:VEVENT".to_string());
+        lines.push("END:VCALENDAR".to_string());
+
+        let mut ics = String::new();
+        for line in &lines {
+            push_line(&mut ics, line);
+        }
+        Ok(ics)
+    }
+
+    /// Opens the event in the default calendar app, which asks the user where to save it.
+    pub async fn create_event<R: Runtime>(
+        app: &AppHandle<R>,
+        event: NewCalendarEvent,
+    ) -> Result<EventCreated, CalendarError> {
+        let id = uuid::Uuid::new_v4();
+        let uid = format!("{id}@layers");
+        let ics = ics(&uid, &event)?;
+        let dir = app
+            .path()
+            .app_cache_dir()
+            .map_err(|e| CalendarError::Failed(e.to_string()))?
+            .join("calendar");
+        let path = dir.join(format!("{id}.ics"));
+        tokio::fs::create_dir_all(&dir)
+            .await
+            .map_err(|e| CalendarError::Failed(e.to_string()))?;
+        tokio::fs::write(&path, ics)
+            .await
+            .map_err(|e| CalendarError::Failed(e.to_string()))?;
+        app.opener()
+            .open_path(path.to_string_lossy(), None::<&str>)
+            .map_err(|e| CalendarError::Failed(e.to_string()))?;
+        Ok(EventCreated {
+            id: uid,
+            calendar_id: String::new(),
+        })
+    }
+}
+
+#[cfg(mobile)]
+pub fn plugin<R: Runtime>() -> tauri::plugin::TauriPlugin<R> {
+    native::plugin()
+}
+
+/// Prompts for calendar access unless it was already granted or refused, and resolves
+/// with the resulting state.
+#[tauri::command]
+pub async fn request_calendar_permission<R: Runtime>(
+    app: AppHandle<R>,
+) -> Result<PermissionState, CalendarError> {
+    native::request_permission(&app).await
+}
+
+#[tauri::command]
+pub async fn list_calendars<R: Runtime>(
+    app: AppHandle<R>,
+) -> Result<Vec<CalendarInfo>, CalendarError> {
+    native::list_calendars(&app).await
+}
+
+/// Every occurrence in `calendar_id` that overlaps `start_ms..end_ms`, earliest first.
+#[tauri::command]
+pub async fn query_events<R: Runtime>(
+    calendar_id: String,
+    start_ms: u64,
+    end_ms: u64,
+    app: AppHandle<R>,
+) -> Result<Vec<CalendarEvent>, CalendarError> {
+    if end_ms <= start_ms {
+        return Err(CalendarError::InvalidInput(
+            "the range must end after it starts".into(),
+        ));
+    }
+    if end_ms - start_ms > MAX_QUERY_RANGE_MS {
+        return Err(CalendarError::InvalidInput(
+            "the range can't be longer than four years".into(),
+        ));
+    }
+    native::query_events(&app, calendar_id, start_ms, end_ms).await
+}
+
+/// Saves `event` and resolves with its id, announcing it on [`CREATED_EVENT`]. On
+/// desktop the id is the handed-off `.ics` file's `UID`, and nothing is announced.
+#[tauri::command]
+pub async fn create_event<R: Runtime>(
+    event: NewCalendarEvent,
+    app: AppHandle<R>,
+) -> Result<String, CalendarError> {
+    if event.title.trim().is_empty() {
+        return Err(CalendarError::InvalidInput("an event needs a title".into()));
+    }
+    if event.end_ms < event.start_ms {
+        return Err(CalendarError::InvalidInput(
+            "an event can't end before it starts".into(),
+        ));
+    }
+    if event.all_day
+        && (!event.start_ms.is_multiple_of(DAY_MS)
+            || !event.end_ms.is_multiple_of(DAY_MS)
+            || event.end_ms == event.start_ms)
+    {
+        return Err(CalendarError::InvalidInput(
+            "an all-day event must run between midnights UTC".into(),
+        ));
+    }
+    let created = native::create_event(&app, event).await?;
+    if native::SAVES {
+        let _ = app.emit(CREATED_EVENT, &created);
+    }
+    Ok(created.id)
+}
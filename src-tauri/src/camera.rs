@@ -0,0 +1,322 @@
+//! Photos and videos from the camera or the photo library. On iOS and Android the system
+//! camera and pickers do the capturing, through `plugins/camera`. Desktop falls back to
+//! a file picker, re-encoding photos so the quality and size limits still apply; it has
+//! no gallery, so `save_to_gallery` is ignored there.
+//!
+//! Every capture lands in the app cache and is announced with [`CAPTURE_COMPLETE_EVENT`]
+//! as well as returned, so steps after the caller's can follow along.
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Runtime};
+
+pub const CAPTURE_COMPLETE_EVENT: &str = "camera://capture-complete";
+
+#[derive(Debug, thiserror::Error, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "camelCase")]
+pub enum CameraError {
+    #[cfg_attr(desktop, allow(dead_code))]
+    #[error("camera or photo library permission denied")]
+    PermissionDenied,
+    /// No camera, no camera app, or a library that can't provide the media type.
+    #[cfg_attr(desktop, allow(dead_code))]
+    #[error("source unavailable: {0}")]
+    SourceUnavailable(String),
+    #[error("capture cancelled")]
+    Cancelled,
+    #[error("invalid options: {0}")]
+    InvalidInput(String),
+    #[error("capture failed: {0}")]
+    Failed(String),
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CameraSource {
+    #[default]
+    Camera,
+    PhotoLibrary,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct PhotoOptions {
+    /// JPEG quality, 0 to 100.
+    pub quality: u8,
+    /// Photos are scaled down, never up, to fit within both limits.
+    pub max_width: Option<u32>,
+    pub max_height: Option<u32>,
+    pub save_to_gallery: bool,
+    pub source: CameraSource,
+}
+
+impl Default for PhotoOptions {
+    fn default() -> Self {
+        Self {
+            quality: 90,
+            max_width: None,
+            max_height: None,
+            save_to_gallery: false,
+            source: CameraSource::default(),
+        }
+    }
+}
+
+/// Android's capture intent only distinguishes low from high.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum VideoQuality {
+    Low,
+    Medium,
+    #[default]
+    High,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct VideoOptions {
+    /// Recording stops by itself after this long; ignored for the library.
+    pub max_duration_secs: Option<u32>,
+    pub quality: VideoQuality,
+    pub save_to_gallery: bool,
+    pub source: CameraSource,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CapturedMedia {
+    pub path: String,
+    /// Upright, in pixels. Zero for videos picked on desktop, which can't be probed here.
+    pub width: u32,
+    pub height: u32,
+    pub size_bytes: u64,
+    pub mime_type: String,
+}
+
+#[cfg(mobile)]
+mod native {
+    use tauri::plugin::mobile::PluginInvokeError;
+    use tauri::plugin::{PluginHandle, TauriPlugin};
+    use tauri::{AppHandle, Manager, Runtime};
+    // Linked for its native halves; it has no Rust API.
+    use tauri_plugin_camera as _;
+
+    use super::{CameraError, CapturedMedia, PhotoOptions, VideoOptions};
+
+    #[cfg(target_os = "ios")]
+    tauri::ios_plugin_binding!(init_plugin_camera);
+
+    struct Camera<R: Runtime>(PluginHandle<R>);
+
+    pub fn plugin<R: Runtime>() -> TauriPlugin<R> {
+        tauri::plugin::Builder::new("camera")
+            .setup(|app, api| {
+                #[cfg(target_os = "android")]
+                let handle = api.register_android_plugin("com.layers.camera", "CameraPlugin")?;
+                #[cfg(target_os = "ios")]
+                let handle = api.register_ios_plugin(init_plugin_camera)?;
+                app.manage(Camera(handle));
+                Ok(())
+            })
+            .build()
+    }
+
+    /// Both native halves reject with the same codes.
+    fn from_plugin(e: PluginInvokeError) -> CameraError {
+        match e {
+            PluginInvokeError::InvokeRejected(response) => {
+                let message = response.message.unwrap_or_default();
+                match response.code.as_deref() {
+                    Some("PermissionDenied") => CameraError::PermissionDenied,
+                    Some("SourceUnavailable") => CameraError::SourceUnavailable(message),
+                    Some("Cancelled") => CameraError::Cancelled,
+                    _ => CameraError::Failed(message),
+                }
+            }
+            e => CameraError::Failed(e.to_string()),
+        }
+    }
+
+    fn camera<R: Runtime>(app: &AppHandle<R>) -> Result<PluginHandle<R>, CameraError> {
+        app.try_state::<Camera<R>>()
+            .map(|camera| camera.0.clone())
+            .ok_or_else(|| CameraError::Failed("camera plugin not loaded".into()))
+    }
+
+    pub async fn take_photo<R: Runtime>(
+        app: &AppHandle<R>,
+        options: PhotoOptions,
+    ) -> Result<CapturedMedia, CameraError> {
+        camera(app)?
+            .run_mobile_plugin_async("takePhoto", options)
+            .await
+            .map_err(from_plugin)
+    }
+
+    pub async fn record_video<R: Runtime>(
+        app: &AppHandle<R>,
+        options: VideoOptions,
+    ) -> Result<CapturedMedia, CameraError> {
+        camera(app)?
+            .run_mobile_plugin_async("recordVideo", options)
+            .await
+            .map_err(from_plugin)
+    }
+}
+
+#[cfg(desktop)]
+mod native {
+    use std::fs::File;
+    use std::io::BufWriter;
+    use std::path::{Path, PathBuf};
+
+    use image::codecs::jpeg::JpegEncoder;
+    use image::imageops::FilterType;
+    use image::ImageReader;
+    use tauri::{AppHandle, Manager, Runtime};
+    use tauri_plugin_dialog::DialogExt;
+
+    use super::{CameraError, CapturedMedia, PhotoOptions, VideoOptions};
+
+    fn io_error(e: impl std::fmt::Display) -> CameraError {
+        CameraError::Failed(e.to_string())
+    }
+
+    async fn pick<R: Runtime>(
+        app: &AppHandle<R>,
+        filter: &str,
+        extensions: &[&str],
+    ) -> Result<PathBuf, CameraError> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        app.dialog()
+            .file()
+            .add_filter(filter, extensions)
+            .pick_file(move |path| {
+                let _ = tx.send(path);
+            });
+        rx.await
+            .ok()
+            .flatten()
+            .ok_or(CameraError::Cancelled)?
+            .into_path()
+            .map_err(io_error)
+    }
+
+    /// A new file under `$APPCACHE/camera`, where the mobile halves put captures too.
+    fn new_file<R: Runtime>(app: &AppHandle<R>, extension: &str) -> Result<PathBuf, CameraError> {
+        let dir = app.path().app_cache_dir().map_err(io_error)?.join("camera");
+        std::fs::create_dir_all(&dir).map_err(io_error)?;
+        Ok(dir.join(format!("{}.{extension}", uuid::Uuid::new_v4())))
+    }
+
+    fn encode(src: &Path, dest: &Path, options: &PhotoOptions) -> Result<(u32, u32), CameraError> {
+        let mut image = ImageReader::open(src)
+            .map_err(io_error)?
+            .with_guessed_format()
+            .map_err(io_error)?
+            .decode()
+            .map_err(|e| CameraError::Failed(format!("not a readable image: {e}")))?;
+        let max_width = options.max_width.unwrap_or(u32::MAX);
+        let max_height = options.max_height.unwrap_or(u32::MAX);
+        if image.width() > max_width || image.height() > max_height {
+            image = image.resize(max_width, max_height, FilterType::Lanczos3);
+        }
+        let rgb = image.to_rgb8();
+        // The encoder takes 1 to 100.
+        let encoder = JpegEncoder::new_with_quality(
+            BufWriter::new(File::create(dest).map_err(io_error)?),
+            options.quality.max(1),
+        );
+        rgb.write_with_encoder(encoder).map_err(io_error)?;
+        Ok((rgb.width(), rgb.height()))
+    }
+
+    pub async fn take_photo<R: Runtime>(
+        app: &AppHandle<R>,
+        options: PhotoOptions,
+    ) -> Result<CapturedMedia, CameraError> {
+        let src = pick(app, "Images", &["jpg", "jpeg", "png", "webp", "gif"]).await?;
+        let dest = new_file(app, "jpg")?;
+        tauri::async_runtime::spawn_blocking(move || {
+            let (width, height) = encode(&src, &dest, &options).inspect_err(|_| {
+                let _ = std::fs::remove_file(&dest);
+            })?;
+            Ok(CapturedMedia {
+                size_bytes: std::fs::metadata(&dest).map_err(io_error)?.len(),
+                path: dest.display().to_string(),
+                width,
+                height,
+                mime_type: "image/jpeg".into(),
+            })
+        })
+        .await
+        .map_err(io_error)?
+    }
+
+    pub async fn record_video<R: Runtime>(
+        app: &AppHandle<R>,
+        _options: VideoOptions,
+    ) -> Result<CapturedMedia, CameraError> {
+        let src = pick(app, "Videos", &["mp4", "mov", "m4v", "webm"]).await?;
+        let extension = src
+            .extension()
+            .and_then(|e| e.to_str())
+            .map_or("mp4".into(), str::to_ascii_lowercase);
+        let dest = new_file(app, &extension)?;
+        let size_bytes = tokio::fs::copy(&src, &dest).await.map_err(io_error)?;
+        Ok(CapturedMedia {
+            mime_type: crate::media::mime_type(&dest),
+            path: dest.display().to_string(),
+            width: 0,
+            height: 0,
+            size_bytes,
+        })
+    }
+}
+
+#[cfg(mobile)]
+pub fn plugin<R: Runtime>() -> tauri::plugin::TauriPlugin<R> {
+    native::plugin()
+}
+
+fn validate_photo(options: &PhotoOptions) -> Result<(), CameraError> {
+    if options.quality > 100 {
+        return Err(CameraError::InvalidInput(format!(
+            "quality {} is not 0 to 100",
+            options.quality
+        )));
+    }
+    if options.max_width == Some(0) || options.max_height == Some(0) {
+        return Err(CameraError::InvalidInput(
+            "size limits must be at least 1".into(),
+        ));
+    }
+    Ok(())
+}
+
+/// Takes a photo, or picks one from the library. Permission is requested first;
+/// refusing it fails with [`CameraError::PermissionDenied`].
+#[tauri::command]
+pub async fn take_photo<R: Runtime>(
+    options: PhotoOptions,
+    app: AppHandle<R>,
+) -> Result<CapturedMedia, CameraError> {
+    validate_photo(&options)?;
+    let media = native::take_photo(&app, options).await?;
+    let _ = app.emit(CAPTURE_COMPLETE_EVENT, &media);
+    Ok(media)
+}
+
+#[tauri::command]
+pub async fn record_video<R: Runtime>(
+    options: VideoOptions,
+    app: AppHandle<R>,
+) -> Result<CapturedMedia, CameraError> {
+    if options.max_duration_secs == Some(0) {
+        return Err(CameraError::InvalidInput(
+            "max_duration_secs must be at least 1".into(),
+        ));
+    }
+    let media = native::record_video(&app, options).await?;
+    let _ = app.emit(CAPTURE_COMPLETE_EVENT, &media);
+    Ok(media)
+}
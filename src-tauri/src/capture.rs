@@ -0,0 +1,442 @@
+//! PNG snapshots of a window's web content for visual regression tests, taken with the
+//! platform webview's own snapshot API so they hold only the page: no title bar, frame
+//! or shadow, and nothing from other windows on the desktop.
+//!
+//! - Windows: WebView2's `CapturePreview`.
+//! - macOS: `WKWebView.takeSnapshot`, refused while the window is fully covered, since
+//!   WebKit then returns whatever it last drew.
+//! - Linux: WebKitGTK's `get_snapshot` of the visible region.
+//! - Elsewhere [`CaptureError::Unsupported`] names the platform.
+//!
+//! Regions are in CSS pixels from the top left of the page. Images are in device pixels,
+//! and each result reports the window's scale factor alongside its pixel size.
+
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tauri::ipc::Channel;
+use tauri::{AppHandle, Manager, Runtime, WebviewWindow};
+
+use crate::scope;
+
+/// Frames per sequence, and the shortest interval between them. Snapshots take a few
+/// milliseconds each, so faster than one per frame at 60 Hz only measures the capture.
+const MAX_SEQUENCE_FRAMES: u32 = 600;
+const MIN_SEQUENCE_INTERVAL: Duration = Duration::from_millis(16);
+
+#[derive(Debug, thiserror::Error, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "camelCase")]
+pub enum CaptureError {
+    /// Carries `std::env::consts::OS`.
+    #[cfg_attr(
+        any(target_os = "macos", target_os = "linux", windows),
+        allow(dead_code)
+    )]
+    #[error("window capture is not supported on {0}")]
+    Unsupported(String),
+    #[error("no window labelled {0}")]
+    WindowNotFound(String),
+    #[error("the window is minimized")]
+    Minimized,
+    #[error("the window is hidden")]
+    Hidden,
+    /// Only reported where the platform can't draw a covered window.
+    #[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+    #[error("the window is covered by other windows")]
+    Occluded,
+    #[error("invalid region: {0}")]
+    InvalidRegion(String),
+    #[error("{0}")]
+    Scope(String),
+    #[error("capture failed: {0}")]
+    Failed(String),
+    #[error("io error: {0}")]
+    Io(String),
+}
+
+impl From<std::io::Error> for CaptureError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e.to_string())
+    }
+}
+
+impl From<tauri::Error> for CaptureError {
+    fn from(e: tauri::Error) -> Self {
+        Self::Failed(e.to_string())
+    }
+}
+
+/// In CSS pixels.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Rect {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Capture {
+    pub png: Vec<u8>,
+    /// In device pixels.
+    pub width: u32,
+    pub height: u32,
+    pub scale_factor: f64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CaptureFileResult {
+    pub path: PathBuf,
+    pub width: u32,
+    pub height: u32,
+    pub scale_factor: f64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CaptureFrame {
+    pub index: u32,
+    /// Since the first frame was requested.
+    pub elapsed_ms: f64,
+    /// How long the webview took to produce this frame.
+    pub capture_ms: f64,
+    #[serde(flatten)]
+    pub capture: Capture,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CaptureSequenceStats {
+    pub frames: u32,
+    /// Between consecutive frames; a long one means the webview was busy.
+    pub mean_interval_ms: f64,
+    pub max_interval_ms: f64,
+}
+
+#[cfg(any(target_os = "macos", target_os = "linux", windows))]
+async fn receive<T>(rx: tokio::sync::oneshot::Receiver<T>) -> Result<T, CaptureError> {
+    rx.await
+        .map_err(|_| CaptureError::Failed("the webview closed before replying".into()))
+}
+
+#[cfg(windows)]
+mod native {
+    use tauri::{Runtime, WebviewWindow};
+    use webview2_com::CapturePreviewCompletedHandler;
+    use webview2_com::Microsoft::Web::WebView2::Win32::COREWEBVIEW2_CAPTURE_PREVIEW_IMAGE_FORMAT_PNG;
+    use windows::Win32::Foundation::E_OUTOFMEMORY;
+    use windows::Win32::System::Com::{IStream, STREAM_SEEK_SET};
+    use windows::Win32::UI::Shell::SHCreateMemStream;
+
+    use super::{receive, CaptureError};
+    use crate::pdf::Reply;
+
+    fn failed(e: windows::core::Error) -> CaptureError {
+        CaptureError::Failed(e.message())
+    }
+
+    unsafe fn read_all(stream: &IStream) -> windows::core::Result<Vec<u8>> {
+        stream.Seek(0, STREAM_SEEK_SET, None)?;
+        let mut png = Vec::new();
+        let mut buf = [0u8; 64 * 1024];
+        loop {
+            let mut read = 0u32;
+            stream
+                .Read(buf.as_mut_ptr().cast(), buf.len() as u32, Some(&mut read))
+                .ok()?;
+            if read == 0 {
+                return Ok(png);
+            }
+            png.extend_from_slice(&buf[..read as usize]);
+        }
+    }
+
+    /// WebView2 renders the page for the capture, so a covered window captures fine.
+    pub async fn capture_png<R: Runtime>(
+        webview: &WebviewWindow<R>,
+    ) -> Result<Vec<u8>, CaptureError> {
+        let (reply, rx) = Reply::new();
+        webview.with_webview(move |platform| {
+            let handler = reply.clone();
+            let started = unsafe {
+                (|| {
+                    let stream = SHCreateMemStream(None)
+                        .ok_or_else(|| windows::core::Error::from(E_OUTOFMEMORY))?;
+                    let written = stream.clone();
+                    platform.controller().CoreWebView2()?.CapturePreview(
+                        COREWEBVIEW2_CAPTURE_PREVIEW_IMAGE_FORMAT_PNG,
+                        &stream,
+                        &CapturePreviewCompletedHandler::create(Box::new(move |result| {
+                            handler.send(result.and_then(|()| read_all(&written)));
+                            Ok(())
+                        })),
+                    )
+                })()
+            };
+            if let Err(e) = started {
+                reply.send(Err(e));
+            }
+        })?;
+        receive(rx).await?.map_err(failed)
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod native {
+    use block2::RcBlock;
+    use objc2::MainThreadMarker;
+    use objc2_app_kit::{NSBitmapImageFileType, NSBitmapImageRep, NSImage, NSWindowOcclusionState};
+    use objc2_foundation::{NSDictionary, NSError};
+    use objc2_web_kit::{WKSnapshotConfiguration, WKWebView};
+    use tauri::{Runtime, WebviewWindow};
+
+    use super::{receive, CaptureError};
+    use crate::pdf::Reply;
+
+    fn failed(error: *mut NSError) -> CaptureError {
+        match unsafe { error.as_ref() } {
+            Some(error) => CaptureError::Failed(error.localizedDescription().to_string()),
+            None => CaptureError::Failed("WebKit returned no image".into()),
+        }
+    }
+
+    fn png(image: &NSImage) -> Option<Vec<u8>> {
+        let tiff = image.TIFFRepresentation()?;
+        let bitmap = NSBitmapImageRep::imageRepWithData(&tiff)?;
+        let png = unsafe {
+            bitmap.representationUsingType_properties(
+                NSBitmapImageFileType::PNG,
+                &NSDictionary::new(),
+            )
+        }?;
+        Some(png.to_vec())
+    }
+
+    pub async fn capture_png<R: Runtime>(
+        webview: &WebviewWindow<R>,
+    ) -> Result<Vec<u8>, CaptureError> {
+        let (reply, rx) = Reply::new();
+        webview.with_webview(move |platform| unsafe {
+            let view = &*(platform.inner() as *const WKWebView);
+            let visible = view.window().is_some_and(|window| {
+                window
+                    .occlusionState()
+                    .contains(NSWindowOcclusionState::Visible)
+            });
+            if !visible {
+                reply.send(Err(CaptureError::Occluded));
+                return;
+            }
+            let config = WKSnapshotConfiguration::new(MainThreadMarker::from(view));
+            // Wait for layout and paint that are already pending, as a screenshot would.
+            config.setAfterScreenUpdates(true);
+            let block = RcBlock::new(move |image: *mut NSImage, error: *mut NSError| {
+                let result = match image.as_ref() {
+                    Some(image) => png(image).ok_or_else(|| {
+                        CaptureError::Failed("the snapshot could not be encoded".into())
+                    }),
+                    None => Err(failed(error)),
+                };
+                reply.send(result);
+            });
+            view.takeSnapshotWithConfiguration_completionHandler(Some(&config), &block);
+        })?;
+        receive(rx).await?
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod native {
+    use tauri::{Runtime, WebviewWindow};
+    use webkit2gtk::{SnapshotOptions, SnapshotRegion, WebViewExt};
+
+    use super::{receive, CaptureError};
+    use crate::pdf::Reply;
+
+    pub async fn capture_png<R: Runtime>(
+        webview: &WebviewWindow<R>,
+    ) -> Result<Vec<u8>, CaptureError> {
+        let (reply, rx) = Reply::new();
+        webview.with_webview(move |platform| {
+            platform.inner().snapshot(
+                SnapshotRegion::Visible,
+                SnapshotOptions::empty(),
+                None::<&webkit2gtk::gio::Cancellable>,
+                move |result| {
+                    let png = result
+                        .map_err(|e| CaptureError::Failed(e.to_string()))
+                        .and_then(|surface| {
+                            let mut png = Vec::new();
+                            surface
+                                .write_to_png(&mut png)
+                                .map_err(|e| CaptureError::Failed(e.to_string()))?;
+                            Ok(png)
+                        });
+                    reply.send(png);
+                },
+            );
+        })?;
+        receive(rx).await?
+    }
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux", windows)))]
+mod native {
+    use tauri::{Runtime, WebviewWindow};
+
+    use super::CaptureError;
+
+    pub async fn capture_png<R: Runtime>(
+        _webview: &WebviewWindow<R>,
+    ) -> Result<Vec<u8>, CaptureError> {
+        Err(CaptureError::Unsupported(std::env::consts::OS.into()))
+    }
+}
+
+/// The pixel size from a PNG's `IHDR` chunk, which always comes first.
+fn png_size(png: &[u8]) -> Option<(u32, u32)> {
+    let width = png.get(16..20)?.try_into().ok()?;
+    let height = png.get(20..24)?.try_into().ok()?;
+    Some((u32::from_be_bytes(width), u32::from_be_bytes(height)))
+}
+
+/// Cuts `region` out of the full capture. The image's own width sets the ratio of device
+/// to CSS pixels, since not every webview snapshots at the window's scale factor.
+fn crop(png: Vec<u8>, region: Rect, css_width: f64) -> Result<Vec<u8>, CaptureError> {
+    if region.width <= 0.0 || region.height <= 0.0 || region.x < 0.0 || region.y < 0.0 {
+        return Err(CaptureError::InvalidRegion(format!("{region:?}")));
+    }
+    let image = image::load_from_memory_with_format(&png, image::ImageFormat::Png)
+        .map_err(|e| CaptureError::Failed(e.to_string()))?;
+    let ratio = f64::from(image.width()) / css_width;
+    let x = (region.x * ratio).round() as u32;
+    let y = (region.y * ratio).round() as u32;
+    if x >= image.width() || y >= image.height() {
+        return Err(CaptureError::InvalidRegion(format!(
+            "{region:?} is outside the page"
+        )));
+    }
+    let width = ((region.width * ratio).round() as u32).clamp(1, image.width() - x);
+    let height = ((region.height * ratio).round() as u32).clamp(1, image.height() - y);
+
+    let mut out = Vec::new();
+    image
+        .crop_imm(x, y, width, height)
+        .write_to(&mut Cursor::new(&mut out), image::ImageFormat::Png)
+        .map_err(|e| CaptureError::Failed(e.to_string()))?;
+    Ok(out)
+}
+
+fn window<R: Runtime>(app: &AppHandle<R>, label: &str) -> Result<WebviewWindow<R>, CaptureError> {
+    app.get_webview_window(label)
+        .ok_or_else(|| CaptureError::WindowNotFound(label.to_string()))
+}
+
+async fn capture<R: Runtime>(
+    webview: &WebviewWindow<R>,
+    region: Option<Rect>,
+) -> Result<Capture, CaptureError> {
+    if webview.is_minimized()? {
+        return Err(CaptureError::Minimized);
+    }
+    if !webview.is_visible()? {
+        return Err(CaptureError::Hidden);
+    }
+    let scale_factor = webview.scale_factor()?;
+    let mut png = native::capture_png(webview).await?;
+    if let Some(region) = region {
+        let css_width = f64::from(webview.inner_size()?.width) / scale_factor;
+        png = crop(png, region, css_width)?;
+    }
+    let (width, height) = png_size(&png)
+        .ok_or_else(|| CaptureError::Failed("the webview returned an invalid PNG".into()))?;
+    Ok(Capture {
+        png,
+        width,
+        height,
+        scale_factor,
+    })
+}
+
+/// Captures the window's page, or just `region` of it.
+#[tauri::command]
+pub async fn capture_window<R: Runtime>(
+    label: String,
+    region: Option<Rect>,
+    app: AppHandle<R>,
+) -> Result<Capture, CaptureError> {
+    capture(&window(&app, &label)?, region).await
+}
+
+/// Like [`capture_window`], but writes the PNG to `path` instead of returning it.
+#[tauri::command]
+pub async fn capture_to_file<R: Runtime>(
+    label: String,
+    path: String,
+    region: Option<Rect>,
+    app: AppHandle<R>,
+) -> Result<CaptureFileResult, CaptureError> {
+    let path = scope::ensure_allowed(&app, Path::new(&path)).map_err(CaptureError::Scope)?;
+    let capture = capture(&window(&app, &label)?, region).await?;
+    tokio::fs::write(&path, &capture.png).await?;
+    Ok(CaptureFileResult {
+        path,
+        width: capture.width,
+        height: capture.height,
+        scale_factor: capture.scale_factor,
+    })
+}
+
+/// Sends `count` captures to `on_frame`, one every `interval_ms` or as soon after as the
+/// webview can manage; a tick that comes due mid-capture is skipped rather than queued,
+/// so the intervals show slow frames instead of hiding them.
+#[tauri::command]
+pub async fn capture_sequence<R: Runtime>(
+    label: String,
+    interval_ms: u64,
+    count: u32,
+    on_frame: Channel<CaptureFrame>,
+    app: AppHandle<R>,
+) -> Result<CaptureSequenceStats, CaptureError> {
+    let webview = window(&app, &label)?;
+    let count = count.min(MAX_SEQUENCE_FRAMES);
+    let mut ticks =
+        tokio::time::interval(Duration::from_millis(interval_ms).max(MIN_SEQUENCE_INTERVAL));
+    ticks.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+    let started = Instant::now();
+    let mut previous: Option<Duration> = None;
+    let (mut total_interval, mut max_interval) = (Duration::ZERO, Duration::ZERO);
+    for index in 0..count {
+        ticks.tick().await;
+        let elapsed = started.elapsed();
+        let capture = capture(&webview, None).await?;
+        let capture_ms = (started.elapsed() - elapsed).as_secs_f64() * 1000.0;
+        if let Some(previous) = previous {
+            total_interval += elapsed - previous;
+            max_interval = max_interval.max(elapsed - previous);
+        }
+        previous = Some(elapsed);
+        on_frame
+            .send(CaptureFrame {
+                index,
+                elapsed_ms: elapsed.as_secs_f64() * 1000.0,
+                capture_ms,
+                capture,
+            })
+            .map_err(CaptureError::from)?;
+    }
+
+    Ok(CaptureSequenceStats {
+        frames: count,
+        mean_interval_ms: match count {
+            0 | 1 => 0.0,
+            n => total_interval.as_secs_f64() * 1000.0 / f64::from(n - 1),
+        },
+        max_interval_ms: max_interval.as_secs_f64() * 1000.0,
+    })
+}
@@ -0,0 +1,304 @@
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use tauri::{AppHandle, Runtime};
+
+const PNG_DATA_URL_PREFIX: &str = "data:image/png;base64,";
+
+#[derive(Debug, thiserror::Error, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "camelCase")]
+pub enum ClipboardError {
+    #[error("clipboard operation is not supported here: {0}")]
+    Unsupported(String),
+    #[error("clipboard is in use by another application")]
+    AccessDenied,
+    #[error("clipboard is empty")]
+    Empty,
+    #[error("invalid image: {0}")]
+    InvalidImage(String),
+    #[error("clipboard error: {0}")]
+    Failed(String),
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ClipboardFormat {
+    Text,
+    Html,
+    Image,
+}
+
+/// Clipboard contents in one of the formats the editor can paste.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value", rename_all = "camelCase")]
+pub enum ClipboardContent {
+    Text(String),
+    #[serde(rename_all = "camelCase")]
+    Html {
+        html: String,
+        fallback_text: String,
+    },
+    /// PNG bytes, exchanged with the frontend as a `data:image/png;base64,` URL.
+    Image(#[serde(with = "png_data_url")] Vec<u8>),
+}
+
+mod png_data_url {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(png: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("{PNG_DATA_URL_PREFIX}{}", BASE64.encode(png)))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let url = String::deserialize(deserializer)?;
+        let encoded = url
+            .strip_prefix(PNG_DATA_URL_PREFIX)
+            .ok_or_else(|| serde::de::Error::custom("expected a PNG data URL"))?;
+        BASE64.decode(encoded).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(desktop)]
+mod native {
+    use std::borrow::Cow;
+    use std::io::Cursor;
+    use std::sync::Mutex;
+
+    use arboard::{Clipboard, Error, ImageData};
+    use image::{ImageFormat, RgbaImage};
+    use tauri::{AppHandle, Runtime};
+
+    use super::ClipboardError;
+
+    /// One long-lived handle: on X11 the copied contents are only served while a
+    /// `Clipboard` is alive, so a per-call handle would drop what was just written.
+    static CLIPBOARD: Mutex<Option<Clipboard>> = Mutex::new(None);
+
+    impl From<Error> for ClipboardError {
+        fn from(e: Error) -> Self {
+            match e {
+                Error::ClipboardNotSupported => Self::Unsupported(e.to_string()),
+                Error::ClipboardOccupied => Self::AccessDenied,
+                Error::ConversionFailure => Self::InvalidImage(e.to_string()),
+                e => Self::Failed(e.to_string()),
+            }
+        }
+    }
+
+    fn with<T>(f: impl FnOnce(&mut Clipboard) -> Result<T, Error>) -> Result<T, ClipboardError> {
+        let mut clipboard = CLIPBOARD.lock().unwrap();
+        let clipboard = match &mut *clipboard {
+            Some(clipboard) => clipboard,
+            empty => empty.insert(Clipboard::new()?),
+        };
+        Ok(f(clipboard)?)
+    }
+
+    /// Empty clipboards and other formats read as `None` rather than an error.
+    fn read<T>(
+        f: impl FnOnce(&mut Clipboard) -> Result<T, Error>,
+    ) -> Result<Option<T>, ClipboardError> {
+        with(|clipboard| match f(clipboard) {
+            Ok(value) => Ok(Some(value)),
+            Err(Error::ContentNotAvailable) => Ok(None),
+            Err(e) => Err(e),
+        })
+    }
+
+    pub fn write_text<R: Runtime>(_app: &AppHandle<R>, text: String) -> Result<(), ClipboardError> {
+        with(|clipboard| clipboard.set_text(text))
+    }
+
+    pub fn read_text<R: Runtime>(_app: &AppHandle<R>) -> Result<Option<String>, ClipboardError> {
+        read(|clipboard| clipboard.get_text())
+    }
+
+    pub fn write_html<R: Runtime>(
+        _app: &AppHandle<R>,
+        html: String,
+        alt_text: String,
+    ) -> Result<(), ClipboardError> {
+        with(|clipboard| clipboard.set_html(html, Some(alt_text)))
+    }
+
+    pub fn read_html<R: Runtime>(_app: &AppHandle<R>) -> Result<Option<String>, ClipboardError> {
+        read(|clipboard| clipboard.get().html())
+    }
+
+    pub fn write_image<R: Runtime>(_app: &AppHandle<R>, png: &[u8]) -> Result<(), ClipboardError> {
+        let image = image::load_from_memory_with_format(png, ImageFormat::Png)
+            .map_err(|e| ClipboardError::InvalidImage(e.to_string()))?
+            .into_rgba8();
+        let (width, height) = image.dimensions();
+        with(|clipboard| {
+            clipboard.set_image(ImageData {
+                width: width as usize,
+                height: height as usize,
+                bytes: Cow::Owned(image.into_raw()),
+            })
+        })
+    }
+
+    /// arboard hands back RGBA whatever the OS stored (TIFF on macOS, DIB on Windows),
+    /// which is re-encoded so the webview always gets PNG.
+    pub fn read_image<R: Runtime>(_app: &AppHandle<R>) -> Result<Option<Vec<u8>>, ClipboardError> {
+        let Some(data) = read(|clipboard| clipboard.get_image())? else {
+            return Ok(None);
+        };
+        let image = RgbaImage::from_raw(
+            data.width as u32,
+            data.height as u32,
+            data.bytes.into_owned(),
+        )
+        .ok_or_else(|| ClipboardError::InvalidImage("pixel data has the wrong size".into()))?;
+
+        let mut png = Cursor::new(Vec::new());
+        image
+            .write_to(&mut png, ImageFormat::Png)
+            .map_err(|e| ClipboardError::InvalidImage(e.to_string()))?;
+        Ok(Some(png.into_inner()))
+    }
+}
+
+#[cfg(mobile)]
+mod native {
+    use tauri::{AppHandle, Runtime};
+    use tauri_plugin_clipboard_manager::ClipboardExt;
+
+    use super::ClipboardError;
+
+    /// The mobile clipboard plugins only handle plain text.
+    fn unsupported<T>(operation: &str) -> Result<T, ClipboardError> {
+        Err(ClipboardError::Unsupported(format!(
+            "{operation} on {}",
+            std::env::consts::OS
+        )))
+    }
+
+    pub fn write_text<R: Runtime>(app: &AppHandle<R>, text: String) -> Result<(), ClipboardError> {
+        app.clipboard()
+            .write_text(text)
+            .map_err(|e| ClipboardError::Failed(e.to_string()))
+    }
+
+    pub fn read_text<R: Runtime>(app: &AppHandle<R>) -> Result<Option<String>, ClipboardError> {
+        Ok(app
+            .clipboard()
+            .read_text()
+            .ok()
+            .filter(|text| !text.is_empty()))
+    }
+
+    pub fn write_html<R: Runtime>(
+        _app: &AppHandle<R>,
+        _html: String,
+        _alt_text: String,
+    ) -> Result<(), ClipboardError> {
+        unsupported("html clipboard")
+    }
+
+    pub fn read_html<R: Runtime>(_app: &AppHandle<R>) -> Result<Option<String>, ClipboardError> {
+        unsupported("html clipboard")
+    }
+
+    pub fn write_image<R: Runtime>(_app: &AppHandle<R>, _png: &[u8]) -> Result<(), ClipboardError> {
+        unsupported("image clipboard")
+    }
+
+    pub fn read_image<R: Runtime>(_app: &AppHandle<R>) -> Result<Option<Vec<u8>>, ClipboardError> {
+        unsupported("image clipboard")
+    }
+}
+
+#[tauri::command]
+pub fn clipboard_write_text<R: Runtime>(
+    s: String,
+    app: AppHandle<R>,
+) -> Result<(), ClipboardError> {
+    native::write_text(&app, s)
+}
+
+#[tauri::command]
+pub fn clipboard_read_text<R: Runtime>(
+    app: AppHandle<R>,
+) -> Result<Option<String>, ClipboardError> {
+    native::read_text(&app)
+}
+
+/// `alt_text` is what plain-text targets get when pasting.
+#[tauri::command]
+pub fn clipboard_write_html<R: Runtime>(
+    html: String,
+    alt_text: String,
+    app: AppHandle<R>,
+) -> Result<(), ClipboardError> {
+    native::write_html(&app, html, alt_text)
+}
+
+#[tauri::command]
+pub fn clipboard_write_image<R: Runtime>(
+    png_bytes: Vec<u8>,
+    app: AppHandle<R>,
+) -> Result<(), ClipboardError> {
+    native::write_image(&app, &png_bytes)
+}
+
+/// The clipboard image as PNG, or `None` if it doesn't hold one.
+#[tauri::command]
+pub fn clipboard_read_image<R: Runtime>(
+    app: AppHandle<R>,
+) -> Result<Option<Vec<u8>>, ClipboardError> {
+    native::read_image(&app)
+}
+
+/// Whether the clipboard currently holds `format`, for enabling paste buttons; formats
+/// the platform can't read report `false`.
+#[tauri::command]
+pub fn clipboard_has<R: Runtime>(format: ClipboardFormat, app: AppHandle<R>) -> bool {
+    let present = match format {
+        ClipboardFormat::Text => native::read_text(&app).map(|text| text.is_some()),
+        ClipboardFormat::Html => native::read_html(&app).map(|html| html.is_some()),
+        ClipboardFormat::Image => native::read_image(&app).map(|image| image.is_some()),
+    };
+    present.unwrap_or(false)
+}
+
+/// Formats the platform can't read at all are skipped rather than failing the read.
+fn if_supported<T>(result: Result<Option<T>, ClipboardError>) -> Result<Option<T>, ClipboardError> {
+    match result {
+        Err(ClipboardError::Unsupported(_)) => Ok(None),
+        result => result,
+    }
+}
+
+#[tauri::command]
+pub fn clipboard_write<R: Runtime>(
+    content: ClipboardContent,
+    app: AppHandle<R>,
+) -> Result<(), ClipboardError> {
+    match content {
+        ClipboardContent::Text(text) => native::write_text(&app, text),
+        ClipboardContent::Html {
+            html,
+            fallback_text,
+        } => native::write_html(&app, html, fallback_text),
+        ClipboardContent::Image(png) => native::write_image(&app, &png),
+    }
+}
+
+/// Reads the richest format on the clipboard: an image, then HTML, then plain text.
+#[tauri::command]
+pub fn clipboard_read<R: Runtime>(app: AppHandle<R>) -> Result<ClipboardContent, ClipboardError> {
+    if let Some(png) = if_supported(native::read_image(&app))? {
+        return Ok(ClipboardContent::Image(png));
+    }
+    let text = native::read_text(&app)?;
+    if let Some(html) = if_supported(native::read_html(&app))? {
+        return Ok(ClipboardContent::Html {
+            html,
+            fallback_text: text.unwrap_or_default(),
+        });
+    }
+    text.map(ClipboardContent::Text)
+        .ok_or(ClipboardError::Empty)
+}
@@ -0,0 +1,504 @@
+//! Compressing and extracting files on disk: gzip and zstd for single files and zip for
+//! files or whole directories.
+
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use flate2::read::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Runtime};
+use zip::result::ZipError;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::scope;
+
+pub const PROGRESS_EVENT: &str = "compression://progress";
+
+/// Inputs smaller than this finish too quickly for progress to be worth reporting.
+const PROGRESS_MIN_BYTES: u64 = 8 * 1024 * 1024;
+const DEFAULT_LEVEL: u32 = 6;
+const MAX_LEVEL: u32 = 9;
+
+#[derive(Debug, thiserror::Error, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "camelCase")]
+pub enum CompressionError {
+    #[error("unsupported format: {0}")]
+    UnsupportedFormat(String),
+    #[error("compression level {0} is out of range 0-9")]
+    InvalidLevel(u32),
+    #[error("{0}")]
+    Scope(String),
+    #[error("not a valid archive: {0}")]
+    InvalidArchive(String),
+    #[error("i/o error: {0}")]
+    Io(String),
+}
+
+impl From<io::Error> for CompressionError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e.to_string())
+    }
+}
+
+impl From<ZipError> for CompressionError {
+    fn from(e: ZipError) -> Self {
+        match e {
+            ZipError::Io(e) => Self::Io(e.to_string()),
+            e => Self::InvalidArchive(e.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CompressionFormat {
+    Gzip,
+    /// `level` goes to zstd as is, so 0 picks zstd's own default and 9 is far from its
+    /// slowest.
+    Zstd,
+    Zip,
+}
+
+impl CompressionFormat {
+    fn from_extension(path: &Path) -> Result<Self, CompressionError> {
+        let extension = path
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        match extension.as_str() {
+            "gz" | "gzip" => Ok(Self::Gzip),
+            "zst" | "zstd" => Ok(Self::Zstd),
+            "zip" => Ok(Self::Zip),
+            "" => Err(CompressionError::UnsupportedFormat(format!(
+                "{} has no extension",
+                path.display()
+            ))),
+            other => Err(CompressionError::UnsupportedFormat(format!(".{other}"))),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompressionStats {
+    pub original_size_bytes: u64,
+    pub compressed_size_bytes: u64,
+    pub duration_ms: u64,
+    /// Compressed over original size, so smaller is better.
+    pub ratio: f32,
+}
+
+impl CompressionStats {
+    fn new(original: u64, compressed: u64, started: Instant) -> Self {
+        Self {
+            original_size_bytes: original,
+            compressed_size_bytes: compressed,
+            duration_ms: started.elapsed().as_millis() as u64,
+            ratio: if original == 0 {
+                1.0
+            } else {
+                compressed as f32 / original as f32
+            },
+        }
+    }
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CompressionProgress {
+    src: String,
+    percent: u8,
+}
+
+/// Emits [`PROGRESS_EVENT`] each time another whole percent of `total` is done.
+struct Progress<'a, R: Runtime> {
+    app: &'a AppHandle<R>,
+    src: String,
+    done: u64,
+    total: u64,
+    percent: u8,
+}
+
+impl<'a, R: Runtime> Progress<'a, R> {
+    fn new(app: &'a AppHandle<R>, src: &Path, total: u64) -> Self {
+        Self {
+            app,
+            src: src.to_string_lossy().into_owned(),
+            done: 0,
+            total,
+            percent: 0,
+        }
+    }
+
+    fn advance(&mut self, n: usize) {
+        self.done += n as u64;
+        if self.total < PROGRESS_MIN_BYTES {
+            return;
+        }
+        let percent = (self.done.min(self.total) * 100 / self.total) as u8;
+        if percent > self.percent {
+            self.percent = percent;
+            let _ = self.app.emit(
+                PROGRESS_EVENT,
+                CompressionProgress {
+                    src: self.src.clone(),
+                    percent,
+                },
+            );
+        }
+    }
+}
+
+/// A reader that counts what passes through it toward a [`Progress`].
+struct Tracked<'p, 'a, T, R: Runtime> {
+    inner: T,
+    progress: &'p mut Progress<'a, R>,
+}
+
+impl<T: Read, R: Runtime> Read for Tracked<'_, '_, T, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.progress.advance(n);
+        Ok(n)
+    }
+}
+
+fn check_level(level: Option<u32>) -> Result<u32, CompressionError> {
+    match level.unwrap_or(DEFAULT_LEVEL) {
+        level if level <= MAX_LEVEL => Ok(level),
+        level => Err(CompressionError::InvalidLevel(level)),
+    }
+}
+
+/// Writes `dst` through a temporary file, so a failure never leaves a truncated
+/// output behind under the real name.
+fn write_atomic<T>(
+    dst: &Path,
+    write: impl FnOnce(&Path) -> Result<T, CompressionError>,
+) -> Result<T, CompressionError> {
+    let mut tmp = dst.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+    let result = write(&tmp).and_then(|value| {
+        fs::rename(&tmp, dst)?;
+        Ok(value)
+    });
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    result
+}
+
+/// Every file under `dir` with its archive name: relative, `/`-separated. Empty
+/// directories are listed without a path so they survive the round trip.
+fn collect(
+    dir: &Path,
+    prefix: &str,
+    entries: &mut Vec<(String, Option<PathBuf>)>,
+) -> io::Result<()> {
+    let mut children = fs::read_dir(dir)?.collect::<Result<Vec<_>, _>>()?;
+    children.sort_by_key(|entry| entry.file_name());
+    if children.is_empty() && !prefix.is_empty() {
+        entries.push((prefix.to_string(), None));
+    }
+    for child in children {
+        let name = format!("{prefix}{}", child.file_name().to_string_lossy());
+        if child.file_type()?.is_dir() {
+            collect(&child.path(), &format!("{name}/"), entries)?;
+        } else {
+            entries.push((name, Some(child.path())));
+        }
+    }
+    Ok(())
+}
+
+fn gzip<R: Runtime>(
+    app: &AppHandle<R>,
+    src: &Path,
+    dst: &Path,
+    level: u32,
+) -> Result<u64, CompressionError> {
+    let original = fs::metadata(src)?.len();
+    let mut progress = Progress::new(app, src, original);
+    write_atomic(dst, |tmp| {
+        let mut input = Tracked {
+            inner: BufReader::new(File::open(src)?),
+            progress: &mut progress,
+        };
+        encode_gzip(&mut input, BufWriter::new(File::create(tmp)?), level)?;
+        Ok(())
+    })?;
+    Ok(original)
+}
+
+fn encode_gzip(input: &mut impl Read, output: impl Write, level: u32) -> io::Result<()> {
+    let mut encoder = GzEncoder::new(output, Compression::new(level));
+    io::copy(input, &mut encoder)?;
+    encoder.finish()?.flush()
+}
+
+fn compress_zstd<R: Runtime>(
+    app: &AppHandle<R>,
+    src: &Path,
+    dst: &Path,
+    level: u32,
+) -> Result<u64, CompressionError> {
+    let original = fs::metadata(src)?.len();
+    let mut progress = Progress::new(app, src, original);
+    write_atomic(dst, |tmp| {
+        let mut input = Tracked {
+            inner: BufReader::new(File::open(src)?),
+            progress: &mut progress,
+        };
+        encode_zstd(&mut input, BufWriter::new(File::create(tmp)?), level)?;
+        Ok(())
+    })?;
+    Ok(original)
+}
+
+fn encode_zstd(input: &mut impl Read, output: impl Write, level: u32) -> io::Result<()> {
+    let mut encoder = zstd::Encoder::new(output, level as i32)?;
+    io::copy(input, &mut encoder)?;
+    encoder.finish()?.flush()
+}
+
+fn zip<R: Runtime>(
+    app: &AppHandle<R>,
+    src: &Path,
+    dst: &Path,
+    level: u32,
+) -> Result<u64, CompressionError> {
+    let mut entries = Vec::new();
+    if src.is_dir() {
+        collect(src, "", &mut entries)?;
+    } else {
+        let name = src.file_name().unwrap_or_default().to_string_lossy();
+        entries.push((name.into_owned(), Some(src.to_path_buf())));
+    }
+    let mut original = 0;
+    for (_, path) in &entries {
+        if let Some(path) = path {
+            original += fs::metadata(path)?.len();
+        }
+    }
+
+    let mut progress = Progress::new(app, src, original);
+    let options = SimpleFileOptions::default()
+        .compression_method(CompressionMethod::Deflated)
+        .compression_level(Some(i64::from(level)));
+    write_atomic(dst, |tmp| {
+        let mut zip = ZipWriter::new(BufWriter::new(File::create(tmp)?));
+        for (name, path) in &entries {
+            let Some(path) = path else {
+                zip.add_directory(name.as_str(), options)?;
+                continue;
+            };
+            let file = File::open(path)?;
+            let large = file.metadata()?.len() >= u64::from(u32::MAX);
+            zip.start_file(name.as_str(), options.large_file(large))?;
+            let mut input = Tracked {
+                inner: BufReader::new(file),
+                progress: &mut progress,
+            };
+            io::copy(&mut input, &mut zip)?;
+        }
+        zip.finish()?.flush()?;
+        Ok(())
+    })?;
+    Ok(original)
+}
+
+fn gunzip<R: Runtime>(app: &AppHandle<R>, src: &Path, dst: &Path) -> Result<u64, CompressionError> {
+    let mut progress = Progress::new(app, src, fs::metadata(src)?.len());
+    write_atomic(dst, |tmp| {
+        let input = Tracked {
+            inner: BufReader::new(File::open(src)?),
+            progress: &mut progress,
+        };
+        Ok(decode_gzip(input, BufWriter::new(File::create(tmp)?))?)
+    })
+}
+
+fn decode_gzip(input: impl Read, mut output: impl Write) -> io::Result<u64> {
+    // Multi-member files are valid gzip, as written by `cat a.gz b.gz`.
+    let written = io::copy(&mut MultiGzDecoder::new(input), &mut output)?;
+    output.flush()?;
+    Ok(written)
+}
+
+fn decompress_zstd<R: Runtime>(
+    app: &AppHandle<R>,
+    src: &Path,
+    dst: &Path,
+) -> Result<u64, CompressionError> {
+    let mut progress = Progress::new(app, src, fs::metadata(src)?.len());
+    write_atomic(dst, |tmp| {
+        let input = Tracked {
+            inner: BufReader::new(File::open(src)?),
+            progress: &mut progress,
+        };
+        Ok(decode_zstd(input, BufWriter::new(File::create(tmp)?))?)
+    })
+}
+
+fn decode_zstd(input: impl Read, mut output: impl Write) -> io::Result<u64> {
+    // Like gzip members, concatenated frames decode one after another.
+    let written = io::copy(&mut zstd::Decoder::new(input)?, &mut output)?;
+    output.flush()?;
+    Ok(written)
+}
+
+/// Extracts into the directory `dst`, refusing entries that would land outside it.
+fn unzip<R: Runtime>(app: &AppHandle<R>, src: &Path, dst: &Path) -> Result<u64, CompressionError> {
+    let mut archive = ZipArchive::new(BufReader::new(File::open(src)?))?;
+    let total = (0..archive.len())
+        .map(|i| archive.by_index(i).map(|entry| entry.size()))
+        .sum::<Result<u64, _>>()?;
+
+    let mut progress = Progress::new(app, src, total);
+    let mut written = 0;
+    fs::create_dir_all(dst)?;
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i)?;
+        let name = entry.name().unwrap_or_default().to_string();
+        let Some(relative) = entry.enclosed_name() else {
+            return Err(CompressionError::InvalidArchive(format!(
+                "entry {name} escapes the destination"
+            )));
+        };
+        let path = dst.join(relative);
+        if entry.is_dir() {
+            fs::create_dir_all(&path)?;
+            continue;
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut output = BufWriter::new(File::create(&path)?);
+        let mut input = Tracked {
+            inner: &mut entry,
+            progress: &mut progress,
+        };
+        written += io::copy(&mut input, &mut output)?;
+        output.flush()?;
+    }
+    Ok(written)
+}
+
+fn allowed<R: Runtime>(app: &AppHandle<R>, path: &str) -> Result<PathBuf, CompressionError> {
+    scope::ensure_allowed(app, Path::new(path)).map_err(CompressionError::Scope)
+}
+
+/// Compresses the file at `src` into `dst`. Zip also takes a directory, archived with
+/// paths relative to it. `level` runs from 0 (store) to 9 (smallest), default 6.
+#[tauri::command]
+pub async fn compress_file<R: Runtime>(
+    src: String,
+    dst: String,
+    format: CompressionFormat,
+    level: Option<u32>,
+    app: AppHandle<R>,
+) -> Result<CompressionStats, CompressionError> {
+    let src = allowed(&app, &src)?;
+    let dst = allowed(&app, &dst)?;
+    let level = check_level(level)?;
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let started = Instant::now();
+        let original = match format {
+            CompressionFormat::Gzip => gzip(&app, &src, &dst, level)?,
+            CompressionFormat::Zip => zip(&app, &src, &dst, level)?,
+            CompressionFormat::Zstd => compress_zstd(&app, &src, &dst, level)?,
+        };
+        let compressed = fs::metadata(&dst)?.len();
+        Ok(CompressionStats::new(original, compressed, started))
+    })
+    .await
+    .map_err(|e| CompressionError::Io(e.to_string()))?
+}
+
+/// Decompresses `src` into the file `dst`, or for zip into the directory `dst`.
+/// Without `format` it's taken from the extension of `src`.
+#[tauri::command]
+pub async fn decompress_file<R: Runtime>(
+    src: String,
+    dst: String,
+    format: Option<CompressionFormat>,
+    app: AppHandle<R>,
+) -> Result<CompressionStats, CompressionError> {
+    let src = allowed(&app, &src)?;
+    let dst = allowed(&app, &dst)?;
+    let format = match format {
+        Some(format) => format,
+        None => CompressionFormat::from_extension(&src)?,
+    };
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let started = Instant::now();
+        let compressed = fs::metadata(&src)?.len();
+        let original = match format {
+            CompressionFormat::Gzip => gunzip(&app, &src, &dst)?,
+            CompressionFormat::Zip => unzip(&app, &src, &dst)?,
+            CompressionFormat::Zstd => decompress_zstd(&app, &src, &dst)?,
+        };
+        Ok(CompressionStats::new(original, compressed, started))
+    })
+    .await
+    .map_err(|e| CompressionError::Io(e.to_string()))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Vec<u8> {
+        (0..200_000u32)
+            .flat_map(|i| (i % 251).to_le_bytes())
+            .collect()
+    }
+
+    #[test]
+    fn gzip_round_trip() {
+        let original = sample();
+        let mut compressed = Vec::new();
+        encode_gzip(&mut original.as_slice(), &mut compressed, DEFAULT_LEVEL).unwrap();
+        assert!(compressed.len() < original.len());
+
+        let mut restored = Vec::new();
+        let written = decode_gzip(compressed.as_slice(), &mut restored).unwrap();
+        assert_eq!(written, original.len() as u64);
+        assert_eq!(restored, original);
+    }
+
+    #[test]
+    fn zstd_round_trip() {
+        let original = sample();
+        for level in [0, DEFAULT_LEVEL, MAX_LEVEL] {
+            let mut compressed = Vec::new();
+            encode_zstd(&mut original.as_slice(), &mut compressed, level).unwrap();
+            assert!(compressed.len() < original.len());
+
+            let mut restored = Vec::new();
+            let written = decode_zstd(compressed.as_slice(), &mut restored).unwrap();
+            assert_eq!(written, original.len() as u64);
+            assert_eq!(restored, original);
+        }
+    }
+
+    #[test]
+    fn zstd_concatenated_frames() {
+        let (a, b) = (b"first frame ".as_slice(), b"second frame".as_slice());
+        let mut compressed = Vec::new();
+        encode_zstd(&mut { a }, &mut compressed, DEFAULT_LEVEL).unwrap();
+        encode_zstd(&mut { b }, &mut compressed, DEFAULT_LEVEL).unwrap();
+
+        let mut restored = Vec::new();
+        decode_zstd(compressed.as_slice(), &mut restored).unwrap();
+        assert_eq!(restored, [a, b].concat());
+    }
+}
@@ -0,0 +1,266 @@
+//! Operational tunables, as opposed to the user's [`crate::settings`]: how connectivity is
+//! probed, per-command rate limits, the log filter, where updates come from and crash
+//! reports go, and which origins the frontend's fetches may reach. They live in the
+//! store plugin's `config.json`, one key per section, so a build handed to testers can be
+//! re-pointed by editing the file rather than shipping a new one.
+//!
+//! Modules read the running values through the managed [`SharedConfig`].
+//! [`set_config`] and [`reset_config`], which only the main window may call, save, apply
+//! the change to the modules that hold running state, and emit [`CHANGED_EVENT`], so
+//! nothing waits for a restart. Narrower commands such as `set_connectivity_config` and
+//! `configure_rate_limit` still change the running values without saving them, and the
+//! next config change replaces what they set.
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, Runtime, State, Webview};
+use tauri_plugin_store::{Store, StoreExt};
+
+use crate::connectivity::{Connectivity, ConnectivityConfig};
+use crate::logging::{self, LogControl};
+use crate::net;
+use crate::rate_limit::{RateLimitConfig, RateLimiter};
+use crate::updates::UpdatesConfig;
+
+/// Emitted with the whole [`AppConfig`] after every change.
+pub const CHANGED_EVENT: &str = "config://changed";
+
+const STORE: &str = "config.json";
+const MAIN_WINDOW: &str = "main";
+const CONNECTIVITY_KEY: &str = "connectivity";
+const RATE_LIMITS_KEY: &str = "rateLimits";
+const LOG_LEVEL_KEY: &str = "logLevel";
+const CRASH_REPORTING_URL_KEY: &str = "crashReportingUrl";
+const ALLOWED_ORIGINS_KEY: &str = "allowedOrigins";
+/// Predates this module, and the update config already lived under it.
+const UPDATES_KEY: &str = "updates";
+
+pub type SharedConfig = Arc<RwLock<AppConfig>>;
+
+#[derive(Debug, thiserror::Error, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "camelCase")]
+pub enum ConfigError {
+    #[error("invalid config: {0}")]
+    Invalid(String),
+    #[error("store error: {0}")]
+    Store(String),
+    #[error("only the main window may change the config, not {0}")]
+    NotAllowed(String),
+}
+
+impl From<tauri_plugin_store::Error> for ConfigError {
+    fn from(e: tauri_plugin_store::Error) -> Self {
+        Self::Store(e.to_string())
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AppConfig {
+    pub connectivity: ConnectivityConfig,
+    /// Keyed by command name; commands left out aren't limited.
+    pub rate_limits: BTreeMap<String, RateLimitConfig>,
+    /// A level (`debug`) or full directives (`info,layers=trace`). `None` leaves the
+    /// filter `RUST_LOG` set at startup, or `info`.
+    pub log_level: Option<String>,
+    pub updates: UpdatesConfig,
+    /// Where [`crate::crash`] sends the reports the user agrees to send. Must be `https`.
+    pub crash_reporting_url: Option<String>,
+    /// Origins such as `https://api.example.com` or `https://*.example.com` that
+    /// [`crate::net`]'s webview-driven fetches may reach. Empty allows none.
+    pub allowed_origins: Vec<String>,
+}
+
+fn validate_rate_limits(limits: &BTreeMap<String, RateLimitConfig>) -> Result<(), String> {
+    for (command, limit) in limits {
+        if command.is_empty() {
+            return Err("rate limits need a command name".into());
+        }
+        limit.validate().map_err(|e| format!("{command}: {e}"))?;
+    }
+    Ok(())
+}
+
+fn validate_log_level(level: &Option<String>) -> Result<(), String> {
+    match level {
+        Some(level) => logging::parse_filter(level).map(drop),
+        None => Ok(()),
+    }
+}
+
+fn validate_crash_reporting_url(url: &Option<String>) -> Result<(), String> {
+    match url.as_deref().map(tauri::Url::parse) {
+        None => Ok(()),
+        Some(Ok(url)) if url.scheme() == "https" && url.has_host() => Ok(()),
+        Some(_) => Err("the crash reporting URL must be an https URL".into()),
+    }
+}
+
+impl AppConfig {
+    fn validate(&self) -> Result<(), ConfigError> {
+        self.connectivity
+            .validate()
+            .map_err(|e| format!("connectivity: {e}"))
+            .and_then(|()| validate_rate_limits(&self.rate_limits))
+            .and_then(|()| validate_log_level(&self.log_level))
+            .and_then(|()| self.updates.validate())
+            .and_then(|()| validate_crash_reporting_url(&self.crash_reporting_url))
+            .and_then(|()| net::validate_origins(&self.allowed_origins))
+            .map_err(ConfigError::Invalid)
+    }
+}
+
+/// One section of the store, or its default where it's missing or unusable; a bad
+/// section is logged and left in the file, so fixing it by hand loses nothing else.
+fn section<R: Runtime, T: DeserializeOwned + Default>(
+    store: &Store<R>,
+    key: &str,
+    validate: impl FnOnce(&T) -> Result<(), String>,
+) -> T {
+    let Some(value) = store.get(key) else {
+        return T::default();
+    };
+    let problem = match serde_json::from_value::<T>(value) {
+        Ok(section) => match validate(&section) {
+            Ok(()) => return section,
+            Err(e) => e,
+        },
+        Err(e) => e.to_string(),
+    };
+    tracing::warn!(key, %problem, "config section unusable, using defaults");
+    T::default()
+}
+
+fn load<R: Runtime>(app: &AppHandle<R>) -> AppConfig {
+    let store = match app.store(STORE) {
+        Ok(store) => store,
+        Err(e) => {
+            tracing::warn!(error = %e, "couldn't open the config store, using defaults");
+            return AppConfig::default();
+        }
+    };
+    AppConfig {
+        connectivity: section(&store, CONNECTIVITY_KEY, ConnectivityConfig::validate),
+        rate_limits: section(&store, RATE_LIMITS_KEY, validate_rate_limits),
+        log_level: section(&store, LOG_LEVEL_KEY, validate_log_level),
+        updates: section(&store, UPDATES_KEY, UpdatesConfig::validate),
+        crash_reporting_url: section(
+            &store,
+            CRASH_REPORTING_URL_KEY,
+            validate_crash_reporting_url,
+        ),
+        allowed_origins: section(&store, ALLOWED_ORIGINS_KEY, |origins: &Vec<String>| {
+            net::validate_origins(origins)
+        }),
+    }
+}
+
+fn save<R: Runtime>(app: &AppHandle<R>, config: &AppConfig) -> Result<(), ConfigError> {
+    let store = app.store(STORE)?;
+    let sections = [
+        (CONNECTIVITY_KEY, serde_json::to_value(&config.connectivity)),
+        (RATE_LIMITS_KEY, serde_json::to_value(&config.rate_limits)),
+        (LOG_LEVEL_KEY, serde_json::to_value(&config.log_level)),
+        (UPDATES_KEY, serde_json::to_value(&config.updates)),
+        (
+            CRASH_REPORTING_URL_KEY,
+            serde_json::to_value(&config.crash_reporting_url),
+        ),
+        (
+            ALLOWED_ORIGINS_KEY,
+            serde_json::to_value(&config.allowed_origins),
+        ),
+    ];
+    for (key, value) in sections {
+        store.set(key, value.map_err(|e| ConfigError::Invalid(e.to_string()))?);
+    }
+    Ok(store.save()?)
+}
+
+/// Pushes `config` into the modules that keep running copies. Ones not managed yet read
+/// [`current`] when they start instead.
+fn apply<R: Runtime>(app: &AppHandle<R>, config: &AppConfig) {
+    if let Some(connectivity) = app.try_state::<Connectivity>() {
+        connectivity.configure(config.connectivity.clone());
+    }
+    if let Some(limiter) = app.try_state::<RateLimiter>() {
+        limiter.replace_all(&config.rate_limits);
+    }
+    if let Some(control) = app.try_state::<LogControl>() {
+        if let Err(e) = control.set_filter(config.log_level.as_deref()) {
+            tracing::warn!(error = %e, "couldn't apply the configured log level");
+        }
+    }
+}
+
+/// Loads the config into managed state and applies it. Call after logging is attached
+/// and before anything that reads the config.
+pub fn init<R: Runtime>(app: &AppHandle<R>) {
+    let config = load(app);
+    apply(app, &config);
+    app.manage::<SharedConfig>(Arc::new(RwLock::new(config)));
+}
+
+/// The config as last loaded or set.
+pub fn current<R: Runtime>(app: &AppHandle<R>) -> AppConfig {
+    app.try_state::<SharedConfig>()
+        .map(|config| config.read().unwrap().clone())
+        .unwrap_or_default()
+}
+
+fn replace<R: Runtime>(
+    app: &AppHandle<R>,
+    shared: &SharedConfig,
+    config: AppConfig,
+) -> Result<(), ConfigError> {
+    {
+        // Held across the save, so two calls can't leave the file and state disagreeing.
+        let mut current = shared.write().unwrap();
+        save(app, &config)?;
+        apply(app, &config);
+        *current = config.clone();
+    }
+    tracing::info!("app config changed");
+    let _ = app.emit(CHANGED_EVENT, config);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_config(config: State<'_, SharedConfig>) -> AppConfig {
+    config.read().unwrap().clone()
+}
+
+/// The config decides what pages may reach and how hard they're limited, so only the
+/// main window may change it.
+fn ensure_main<R: Runtime>(webview: &Webview<R>) -> Result<(), ConfigError> {
+    if webview.label() != MAIN_WINDOW {
+        return Err(ConfigError::NotAllowed(webview.label().to_string()));
+    }
+    Ok(())
+}
+
+/// Replaces the whole config; if any section is invalid, nothing changes.
+#[tauri::command]
+pub fn set_config<R: Runtime>(
+    config: AppConfig,
+    app: AppHandle<R>,
+    webview: Webview<R>,
+    shared: State<'_, SharedConfig>,
+) -> Result<(), ConfigError> {
+    ensure_main(&webview)?;
+    config.validate()?;
+    replace(&app, &shared, config)
+}
+
+#[tauri::command]
+pub fn reset_config<R: Runtime>(
+    app: AppHandle<R>,
+    webview: Webview<R>,
+    shared: State<'_, SharedConfig>,
+) -> Result<(), ConfigError> {
+    ensure_main(&webview)?;
+    replace(&app, &shared, AppConfig::default())
+}
@@ -0,0 +1,324 @@
+use std::ops::RangeInclusive;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, Runtime, State};
+use tauri_plugin_http::reqwest::{self, redirect, StatusCode};
+use tokio::sync::{watch, Notify};
+
+/// Emitted whenever the device goes online or offline.
+pub const STATUS_EVENT: &str = "network://status";
+/// Emitted on every [`ConnectivityState`] transition, including captive and metered.
+pub const CHANGED_EVENT: &str = "connectivity://changed";
+
+/// Probes made in a row that must fail before the state flips to offline, so one dropped
+/// request on a flaky network doesn't flap the UI.
+const FAILURES_BEFORE_OFFLINE: u32 = 2;
+
+const INTERVAL_MS: RangeInclusive<u64> = 1_000..=3_600_000;
+const TIMEOUT_MS: RangeInclusive<u64> = 100..=60_000;
+
+/// The probe expects an empty `204`; captive portals answer with a redirect or a login
+/// page instead. This is the endpoint Android itself uses.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectivityConfig {
+    pub url: String,
+    pub interval_ms: u64,
+    pub timeout_ms: u64,
+}
+
+impl Default for ConnectivityConfig {
+    fn default() -> Self {
+        Self {
+            url: "http://connectivitycheck.gstatic.com/generate_204".into(),
+            interval_ms: 5_000,
+            timeout_ms: 2_000,
+        }
+    }
+}
+
+impl ConnectivityConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        match reqwest::Url::parse(&self.url) {
+            Ok(url) if matches!(url.scheme(), "http" | "https") => {}
+            Ok(_) => return Err("probe url must be http or https".into()),
+            Err(e) => return Err(format!("invalid probe url: {e}")),
+        }
+        if !INTERVAL_MS.contains(&self.interval_ms) {
+            return Err(format!(
+                "interval must be between {} and {} ms",
+                INTERVAL_MS.start(),
+                INTERVAL_MS.end()
+            ));
+        }
+        if !TIMEOUT_MS.contains(&self.timeout_ms) {
+            return Err(format!(
+                "timeout must be between {} and {} ms",
+                TIMEOUT_MS.start(),
+                TIMEOUT_MS.end()
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ConnectivityState {
+    Online,
+    Offline,
+    /// Behind a captive portal: the network is up but requests are intercepted.
+    Captive,
+    /// Online over a connection the frontend reported as metered, e.g. cellular.
+    Metered,
+}
+
+impl ConnectivityState {
+    fn is_online(self) -> bool {
+        matches!(self, Self::Online | Self::Metered)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectivityStatus {
+    pub state: ConnectivityState,
+    pub latency_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkStatus {
+    pub online: bool,
+    pub latency_ms: Option<u64>,
+}
+
+enum Probe {
+    Reachable(u64),
+    Captive,
+    Failed,
+}
+
+pub struct Connectivity {
+    config: Mutex<ConnectivityConfig>,
+    current: Mutex<Option<ConnectivityStatus>>,
+    /// Set by the frontend from the Network Information API; the OS type isn't exposed
+    /// to Rust without a native plugin.
+    metered: AtomicBool,
+    /// Wakes the poller early so a new config or hint takes effect immediately.
+    wake: Notify,
+    /// Probing pauses while this is false; only mobile ever sets it.
+    foreground: watch::Sender<bool>,
+    /// Assumed true until a probe says otherwise, so work isn't held back at startup.
+    online: watch::Sender<bool>,
+    shutdown: watch::Sender<bool>,
+}
+
+impl Default for Connectivity {
+    fn default() -> Self {
+        Self {
+            config: Mutex::new(ConnectivityConfig::default()),
+            current: Mutex::new(None),
+            metered: AtomicBool::new(false),
+            wake: Notify::new(),
+            foreground: watch::channel(true).0,
+            online: watch::channel(true).0,
+            shutdown: watch::channel(false).0,
+        }
+    }
+}
+
+impl Connectivity {
+    /// Follows whether the device is online, for background work that should pause
+    /// while it isn't.
+    pub fn watch_online(&self) -> watch::Receiver<bool> {
+        self.online.subscribe()
+    }
+
+    /// Swaps the config and probes with it straight away.
+    pub fn configure(&self, config: ConnectivityConfig) {
+        *self.config.lock().unwrap() = config;
+        self.wake.notify_one();
+    }
+}
+
+async fn probe(config: &ConnectivityConfig) -> Probe {
+    let client = match reqwest::Client::builder()
+        .redirect(redirect::Policy::none())
+        .timeout(Duration::from_millis(config.timeout_ms))
+        .build()
+    {
+        Ok(client) => client,
+        Err(_) => return Probe::Failed,
+    };
+
+    let started = Instant::now();
+    match client.get(&config.url).send().await {
+        Ok(response) if response.status() == StatusCode::NO_CONTENT => {
+            Probe::Reachable(started.elapsed().as_millis() as u64)
+        }
+        Ok(_) => Probe::Captive,
+        Err(_) => Probe::Failed,
+    }
+}
+
+fn transition<R: Runtime>(app: &AppHandle<R>, state: &Connectivity, next: ConnectivityStatus) {
+    let previous = state.current.lock().unwrap().replace(next);
+    if previous.map(|p| p.state) == Some(next.state) {
+        return;
+    }
+
+    tracing::info!(state = ?next.state, "connectivity changed");
+    let online = next.state.is_online();
+    state.online.send_if_modified(|current| {
+        let changed = *current != online;
+        *current = online;
+        changed
+    });
+    let _ = app.emit(CHANGED_EVENT, next);
+    if previous.map(|p| p.state.is_online()) != Some(next.state.is_online()) {
+        let _ = app.emit(
+            STATUS_EVENT,
+            NetworkStatus {
+                online: next.state.is_online(),
+                latency_ms: next.latency_ms,
+            },
+        );
+    }
+}
+
+/// Starts the background poller. There is no portable OS network-change signal, so
+/// changes are picked up by polling; config changes and returning to the foreground
+/// trigger an immediate probe.
+pub fn init<R: Runtime>(app: &AppHandle<R>) {
+    app.manage(Connectivity {
+        config: Mutex::new(crate::config::current(app).connectivity),
+        ..Default::default()
+    });
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let state = app.state::<Connectivity>();
+        let mut shutdown = state.shutdown.subscribe();
+        let mut foreground = state.foreground.subscribe();
+        let mut failures = 0;
+
+        loop {
+            if !*foreground.borrow_and_update() {
+                tokio::select! {
+                    _ = foreground.wait_for(|fg| *fg) => {}
+                    _ = shutdown.changed() => break,
+                }
+            }
+
+            let config = state.config.lock().unwrap().clone();
+            let next = match probe(&config).await {
+                Probe::Reachable(latency_ms) => {
+                    failures = 0;
+                    let state = if state.metered.load(Ordering::Relaxed) {
+                        ConnectivityState::Metered
+                    } else {
+                        ConnectivityState::Online
+                    };
+                    Some(ConnectivityStatus {
+                        state,
+                        latency_ms: Some(latency_ms),
+                    })
+                }
+                Probe::Captive => {
+                    failures = 0;
+                    Some(ConnectivityStatus {
+                        state: ConnectivityState::Captive,
+                        latency_ms: None,
+                    })
+                }
+                Probe::Failed => {
+                    failures += 1;
+                    (failures >= FAILURES_BEFORE_OFFLINE).then_some(ConnectivityStatus {
+                        state: ConnectivityState::Offline,
+                        latency_ms: None,
+                    })
+                }
+            };
+            if let Some(next) = next {
+                transition(&app, &state, next);
+            }
+
+            tokio::select! {
+                _ = tokio::time::sleep(Duration::from_millis(config.interval_ms)) => {}
+                _ = state.wake.notified() => {}
+                _ = foreground.changed() => {}
+                _ = shutdown.changed() => break,
+            }
+        }
+    });
+}
+
+/// Pauses probing while the app is backgrounded; driven by [`crate::lifecycle`] on mobile.
+#[cfg_attr(desktop, allow(dead_code))]
+pub fn set_foreground<R: Runtime>(app: &AppHandle<R>, foreground: bool) {
+    if let Some(state) = app.try_state::<Connectivity>() {
+        state.foreground.send_if_modified(|current| {
+            let changed = *current != foreground;
+            *current = foreground;
+            changed
+        });
+    }
+}
+
+/// Stops the poller; called on [`tauri::RunEvent::Exit`].
+pub fn shutdown<R: Runtime>(app: &AppHandle<R>) {
+    if let Some(state) = app.try_state::<Connectivity>() {
+        let _ = state.shutdown.send(true);
+    }
+}
+
+/// The last known state, or `None` before the first probe has finished.
+#[tauri::command]
+pub fn get_connectivity(state: State<'_, Connectivity>) -> Option<ConnectivityStatus> {
+    *state.current.lock().unwrap()
+}
+
+#[tauri::command]
+pub fn get_connectivity_config(state: State<'_, Connectivity>) -> ConnectivityConfig {
+    state.config.lock().unwrap().clone()
+}
+
+#[tauri::command]
+pub fn set_connectivity_config(
+    config: ConnectivityConfig,
+    state: State<'_, Connectivity>,
+) -> Result<(), String> {
+    config.validate()?;
+    state.configure(config);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn set_probe_config(
+    url: String,
+    interval_ms: u64,
+    state: State<'_, Connectivity>,
+) -> Result<(), String> {
+    let timeout_ms = state.config.lock().unwrap().timeout_ms;
+    set_connectivity_config(
+        ConnectivityConfig {
+            url,
+            interval_ms,
+            timeout_ms,
+        },
+        state,
+    )
+}
+
+/// Lets the frontend pass on `navigator.connection` so online states become
+/// [`ConnectivityState::Metered`] on cellular.
+#[tauri::command]
+pub fn set_metered_hint(metered: bool, state: State<'_, Connectivity>) {
+    if state.metered.swap(metered, Ordering::Relaxed) != metered {
+        state.wake.notify_one();
+    }
+}
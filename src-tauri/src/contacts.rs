@@ -0,0 +1,246 @@
+//! The device address book: `CNContactStore` on iOS and `ContactsContract` on Android,
+//! both in `plugins/contacts`. Desktop address books (Contacts.app, Outlook, Evolution)
+//! have no common API, so there every command fails with [`ContactsError::NotSupported`],
+//! which the frontend can tell apart from a refused permission.
+
+use serde::{Deserialize, Serialize};
+use tauri::plugin::PermissionState;
+use tauri::{AppHandle, Runtime};
+
+#[cfg_attr(desktop, allow(dead_code))]
+#[derive(Debug, thiserror::Error, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "camelCase")]
+pub enum ContactsError {
+    #[error("contacts permission denied")]
+    PermissionDenied,
+    #[error("contacts are not supported here: {0}")]
+    NotSupported(String),
+    #[error("invalid contact: {0}")]
+    InvalidInput(String),
+    #[error("contacts request failed: {0}")]
+    Failed(String),
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Contact {
+    /// Platform-specific and only stable on the same device.
+    pub id: String,
+    pub display_name: String,
+    pub given_name: String,
+    pub family_name: String,
+    pub phone_numbers: Vec<String>,
+    pub email_addresses: Vec<String>,
+    pub thumbnail_data: Option<Vec<u8>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NewContact {
+    #[serde(default)]
+    pub given_name: String,
+    #[serde(default)]
+    pub family_name: String,
+    #[serde(default)]
+    pub phone_numbers: Vec<String>,
+    #[serde(default)]
+    pub email_addresses: Vec<String>,
+}
+
+#[cfg(mobile)]
+mod native {
+    use base64::Engine;
+    use serde::{Deserialize, Serialize};
+    use tauri::plugin::mobile::PluginInvokeError;
+    use tauri::plugin::{PermissionState, PluginHandle, TauriPlugin};
+    use tauri::{AppHandle, Manager, Runtime};
+    // Linked for its native halves; it has no Rust API.
+    use tauri_plugin_contacts as _;
+
+    use super::{Contact, ContactsError, NewContact};
+
+    #[cfg(target_os = "ios")]
+    tauri::ios_plugin_binding!(init_plugin_contacts);
+
+    struct Contacts<R: Runtime>(PluginHandle<R>);
+
+    #[derive(Serialize)]
+    struct QueryArgs {
+        query: Option<String>,
+    }
+
+    #[derive(Deserialize)]
+    struct PermissionResponse {
+        contacts: PermissionState,
+    }
+
+    /// Thumbnails cross the bridge as base64 rather than a JSON array of numbers.
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct NativeContact {
+        id: String,
+        display_name: String,
+        given_name: String,
+        family_name: String,
+        phone_numbers: Vec<String>,
+        email_addresses: Vec<String>,
+        thumbnail: Option<String>,
+    }
+
+    #[derive(Deserialize)]
+    struct ContactsResponse {
+        contacts: Vec<NativeContact>,
+    }
+
+    #[derive(Deserialize)]
+    struct CreatedResponse {
+        id: String,
+    }
+
+    impl From<NativeContact> for Contact {
+        fn from(contact: NativeContact) -> Self {
+            let engine = base64::engine::general_purpose::STANDARD;
+            Self {
+                id: contact.id,
+                display_name: contact.display_name,
+                given_name: contact.given_name,
+                family_name: contact.family_name,
+                phone_numbers: contact.phone_numbers,
+                email_addresses: contact.email_addresses,
+                thumbnail_data: contact.thumbnail.and_then(|data| engine.decode(data).ok()),
+            }
+        }
+    }
+
+    pub fn plugin<R: Runtime>() -> TauriPlugin<R> {
+        tauri::plugin::Builder::new("contacts")
+            .setup(|app, api| {
+                #[cfg(target_os = "android")]
+                let handle =
+                    api.register_android_plugin("com.layers.contacts", "ContactsPlugin")?;
+                #[cfg(target_os = "ios")]
+                let handle = api.register_ios_plugin(init_plugin_contacts)?;
+                app.manage(Contacts(handle));
+                Ok(())
+            })
+            .build()
+    }
+
+    /// Both native halves reject with the same codes.
+    fn from_plugin(e: PluginInvokeError) -> ContactsError {
+        match e {
+            PluginInvokeError::InvokeRejected(response) => match response.code.as_deref() {
+                Some("PermissionDenied") => ContactsError::PermissionDenied,
+                _ => ContactsError::Failed(response.message.unwrap_or_default()),
+            },
+            e => ContactsError::Failed(e.to_string()),
+        }
+    }
+
+    fn handle<R: Runtime>(app: &AppHandle<R>) -> Result<PluginHandle<R>, ContactsError> {
+        app.try_state::<Contacts<R>>()
+            .map(|contacts| contacts.0.clone())
+            .ok_or_else(|| ContactsError::Failed("contacts plugin not loaded".into()))
+    }
+
+    pub async fn request_permission<R: Runtime>(
+        app: &AppHandle<R>,
+    ) -> Result<PermissionState, ContactsError> {
+        handle(app)?
+            .run_mobile_plugin_async::<PermissionResponse>("requestPermission", ())
+            .await
+            .map(|response| response.contacts)
+            .map_err(from_plugin)
+    }
+
+    pub async fn get_contacts<R: Runtime>(
+        app: &AppHandle<R>,
+        query: Option<String>,
+    ) -> Result<Vec<Contact>, ContactsError> {
+        let response: ContactsResponse = handle(app)?
+            .run_mobile_plugin_async("getContacts", QueryArgs { query })
+            .await
+            .map_err(from_plugin)?;
+        Ok(response.contacts.into_iter().map(Contact::from).collect())
+    }
+
+    pub async fn create_contact<R: Runtime>(
+        app: &AppHandle<R>,
+        contact: NewContact,
+    ) -> Result<String, ContactsError> {
+        handle(app)?
+            .run_mobile_plugin_async::<CreatedResponse>("createContact", contact)
+            .await
+            .map(|response| response.id)
+            .map_err(from_plugin)
+    }
+}
+
+#[cfg(desktop)]
+mod native {
+    use tauri::plugin::PermissionState;
+    use tauri::{AppHandle, Runtime};
+
+    use super::{Contact, ContactsError, NewContact};
+
+    fn unsupported() -> ContactsError {
+        ContactsError::NotSupported("there is no address book API on desktop".into())
+    }
+
+    pub async fn request_permission<R: Runtime>(
+        _app: &AppHandle<R>,
+    ) -> Result<PermissionState, ContactsError> {
+        Err(unsupported())
+    }
+
+    pub async fn get_contacts<R: Runtime>(
+        _app: &AppHandle<R>,
+        _query: Option<String>,
+    ) -> Result<Vec<Contact>, ContactsError> {
+        Err(unsupported())
+    }
+
+    pub async fn create_contact<R: Runtime>(
+        _app: &AppHandle<R>,
+        _contact: NewContact,
+    ) -> Result<String, ContactsError> {
+        Err(unsupported())
+    }
+}
+
+#[cfg(mobile)]
+pub fn plugin<R: Runtime>() -> tauri::plugin::TauriPlugin<R> {
+    native::plugin()
+}
+
+/// Prompts for address book access unless it was already granted or refused, and
+/// resolves with the resulting state.
+#[tauri::command]
+pub async fn request_contacts_permission<R: Runtime>(
+    app: AppHandle<R>,
+) -> Result<PermissionState, ContactsError> {
+    native::request_permission(&app).await
+}
+
+/// Every contact, or those whose name matches `query`.
+#[tauri::command]
+pub async fn get_contacts<R: Runtime>(
+    query: Option<String>,
+    app: AppHandle<R>,
+) -> Result<Vec<Contact>, ContactsError> {
+    native::get_contacts(&app, query.filter(|q| !q.trim().is_empty())).await
+}
+
+/// Saves `contact` to the default account and resolves with its new id.
+#[tauri::command]
+pub async fn create_contact<R: Runtime>(
+    contact: NewContact,
+    app: AppHandle<R>,
+) -> Result<String, ContactsError> {
+    if contact.given_name.trim().is_empty() && contact.family_name.trim().is_empty() {
+        return Err(ContactsError::InvalidInput(
+            "a contact needs a given or family name".into(),
+        ));
+    }
+    native::create_contact(&app, contact).await
+}
@@ -0,0 +1,376 @@
+//! Crash reports for panics. The hook writes each one to `$APPDATA/crashes` before the
+//! process goes down, and the next launch offers them to the frontend; nothing is
+//! uploaded until the user agrees through [`submit_crash_reports`].
+//!
+//! Reports go to `crashReportingUrl` in the app config, or, for installs configured
+//! before that existed, the endpoint in the `crashReportEndpoint` preference. The hook
+//! never uploads, even with the URL set: reports carry a backtrace and log lines, and
+//! they only leave the machine once the user has said yes to them. A panicking process
+//! may also be too broken to make a request.
+
+use std::backtrace::Backtrace;
+use std::fs;
+use std::io;
+use std::panic::PanicHookInfo;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::OnceLock;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::{AppHandle, Emitter, Manager, Runtime};
+use tauri_plugin_http::reqwest;
+use tauri_plugin_store::StoreExt;
+
+/// Emitted at launch with the [`PendingCrash`]es waiting for the user.
+pub const PENDING_EVENT: &str = "crash://pending";
+/// Emitted alongside [`PENDING_EVENT`] with just their count, for a badge or prompt that
+/// doesn't need the list.
+pub const PENDING_REPORTS_EVENT: &str = "crash://pending-reports";
+
+const CRASH_DIR: &str = "crashes";
+/// The oldest reports are evicted past this many.
+const MAX_REPORTS: usize = 20;
+const PREFERENCES_STORE: &str = "preferences.json";
+const ENDPOINT_KEY: &str = "crashReportEndpoint";
+
+#[derive(Debug, thiserror::Error, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "camelCase")]
+pub enum CrashError {
+    #[error("no crash report endpoint is configured")]
+    NotConfigured,
+    #[error("invalid crash report endpoint: {0}")]
+    InvalidEndpoint(String),
+    #[error("no crash report with id {0}")]
+    NotFound(String),
+    #[error("upload failed: {0}")]
+    Upload(String),
+    #[error("{0}")]
+    Io(String),
+}
+
+impl From<io::Error> for CrashError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e.to_string())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CrashReport {
+    pub id: String,
+    pub timestamp_ms: u64,
+    pub message: String,
+    pub location: Option<String>,
+    pub thread: Option<String>,
+    pub backtrace: String,
+    pub os: String,
+    pub os_version: String,
+    pub arch: String,
+    pub app_version: String,
+    pub uptime_ms: u64,
+    /// The last lines of the app log, as JSON.
+    pub logs: Vec<String>,
+}
+
+/// What the consent dialog shows of each report.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingCrash {
+    pub id: String,
+    pub timestamp_ms: u64,
+    pub message: String,
+}
+
+struct Hook {
+    started: Instant,
+    home: Option<String>,
+    /// Set by [`init`] once the app data dir is known.
+    dir: OnceLock<PathBuf>,
+}
+
+static HOOK: OnceLock<Hook> = OnceLock::new();
+/// Tells apart reports written in the same millisecond.
+static SEQUENCE: AtomicU32 = AtomicU32::new(0);
+
+#[derive(Clone, Serialize)]
+struct PendingReports {
+    count: usize,
+}
+
+/// Until `setup` runs, reports go here and [`init`] moves them into the data dir.
+fn early_dir() -> PathBuf {
+    std::env::temp_dir().join(format!("{}-{CRASH_DIR}", env!("CARGO_PKG_NAME")))
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
+
+/// Replaces the home directory with `~`, which otherwise puts the user's account name
+/// in every path.
+fn scrub(text: &str, home: Option<&str>) -> String {
+    match home {
+        Some(home) => text
+            .replace(home, "~")
+            .replace(&home.replace('\\', "/"), "~"),
+        None => text.to_string(),
+    }
+}
+
+fn write_report(hook: &Hook, info: &PanicHookInfo<'_>) -> io::Result<()> {
+    let home = hook.home.as_deref();
+    let message = info
+        .payload()
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| info.payload().downcast_ref::<String>().cloned())
+        .unwrap_or_default();
+    let timestamp_ms = now_ms();
+    let sequence = SEQUENCE.fetch_add(1, Ordering::Relaxed);
+    let os = os_info::get();
+    let report = CrashReport {
+        id: format!("{timestamp_ms}-{sequence}"),
+        timestamp_ms,
+        message: scrub(&message, home),
+        location: info
+            .location()
+            .map(|l| scrub(&format!("{}:{}", l.file(), l.line()), home)),
+        thread: std::thread::current().name().map(str::to_string),
+        backtrace: scrub(&Backtrace::force_capture().to_string(), home),
+        os: os.os_type().to_string(),
+        os_version: os.version().to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        uptime_ms: hook.started.elapsed().as_millis() as u64,
+        logs: crate::logging::recent_lines()
+            .iter()
+            .map(|line| scrub(line, home))
+            .collect(),
+    };
+
+    let dir = hook.dir.get().cloned().unwrap_or_else(early_dir);
+    fs::create_dir_all(&dir)?;
+    let json = serde_json::to_vec_pretty(&report).map_err(io::Error::other)?;
+    fs::write(dir.join(format!("{}.json", report.id)), json)?;
+    evict(&dir)
+}
+
+/// Installs the panic hook, ahead of the builder so panics during startup are
+/// caught too. The previously installed hook runs afterwards.
+pub fn install() {
+    let home = std::env::var(if cfg!(windows) { "USERPROFILE" } else { "HOME" })
+        .ok()
+        .filter(|home| !home.is_empty());
+    let _ = HOOK.set(Hook {
+        started: Instant::now(),
+        home,
+        dir: OnceLock::new(),
+    });
+
+    let previous = std::panic::take_hook();
+    // Only writes; see the module docs for why the upload waits for consent.
+    std::panic::set_hook(Box::new(move |info| {
+        if let Some(hook) = HOOK.get() {
+            if let Err(e) = write_report(hook, info) {
+                eprintln!("failed to write crash report: {e}");
+            }
+        }
+        previous(info);
+    }));
+}
+
+/// `{ms}-{sequence}`, or `{ms}` from before reports had a sequence, as a sort key.
+fn parse_id(id: &str) -> Option<(u64, u32)> {
+    if id.is_empty() || !id.bytes().all(|b| b.is_ascii_digit() || b == b'-') {
+        return None;
+    }
+    let (ms, sequence) = id.split_once('-').unwrap_or((id, "0"));
+    Some((ms.parse().ok()?, sequence.parse().ok()?))
+}
+
+/// Report IDs, oldest first. Anything not named like a report is ignored.
+fn report_ids(dir: &Path) -> io::Result<Vec<String>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let mut ids: Vec<((u64, u32), String)> = entries
+        .filter_map(|entry| {
+            let name = entry.ok()?.file_name();
+            let id = name.to_str()?.strip_suffix(".json")?.to_string();
+            Some((parse_id(&id)?, id))
+        })
+        .collect();
+    ids.sort_unstable();
+    Ok(ids.into_iter().map(|(_, id)| id).collect())
+}
+
+fn evict(dir: &Path) -> io::Result<()> {
+    let ids = report_ids(dir)?;
+    for id in ids.iter().take(ids.len().saturating_sub(MAX_REPORTS)) {
+        let _ = fs::remove_file(dir.join(format!("{id}.json")));
+    }
+    Ok(())
+}
+
+fn crash_dir<R: Runtime>(app: &AppHandle<R>) -> Result<PathBuf, CrashError> {
+    app.path()
+        .app_data_dir()
+        .map(|dir| dir.join(CRASH_DIR))
+        .map_err(|e| CrashError::Io(e.to_string()))
+}
+
+/// Only IDs the hook could have written, so an ID can't name a path elsewhere.
+fn report_path(dir: &Path, id: &str) -> Result<PathBuf, CrashError> {
+    if parse_id(id).is_none() {
+        return Err(CrashError::NotFound(id.to_string()));
+    }
+    Ok(dir.join(format!("{id}.json")))
+}
+
+fn read_report(dir: &Path, id: &str) -> Result<CrashReport, CrashError> {
+    let bytes = match fs::read(report_path(dir, id)?) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            return Err(CrashError::NotFound(id.to_string()))
+        }
+        Err(e) => return Err(e.into()),
+    };
+    serde_json::from_slice(&bytes).map_err(|e| CrashError::Io(e.to_string()))
+}
+
+fn pending(dir: &Path) -> Result<Vec<PendingCrash>, CrashError> {
+    let mut pending = Vec::new();
+    for id in report_ids(dir)? {
+        match read_report(dir, &id) {
+            Ok(report) => pending.push(PendingCrash {
+                id: report.id,
+                timestamp_ms: report.timestamp_ms,
+                message: report.message,
+            }),
+            Err(e) => tracing::warn!(id, error = %e, "unreadable crash report"),
+        }
+    }
+    Ok(pending)
+}
+
+/// Points the hook at the data dir, collects reports written before it was known,
+/// and emits [`PENDING_EVENT`] and [`PENDING_REPORTS_EVENT`] if any are waiting for the
+/// user.
+pub fn init<R: Runtime>(app: &AppHandle<R>) {
+    let dir = match crash_dir(app) {
+        Ok(dir) => dir,
+        Err(e) => {
+            tracing::warn!(error = %e, "no app data dir; crash reports stay in the temp dir");
+            return;
+        }
+    };
+    if let Err(e) = fs::create_dir_all(&dir) {
+        tracing::warn!(dir = %dir.display(), error = %e, "failed to create crash dir");
+        return;
+    }
+    if let Some(hook) = HOOK.get() {
+        let _ = hook.dir.set(dir.clone());
+    }
+
+    let early = early_dir();
+    for id in report_ids(&early).unwrap_or_default() {
+        let name = format!("{id}.json");
+        if fs::rename(early.join(&name), dir.join(&name)).is_err() {
+            let _ = fs::copy(early.join(&name), dir.join(&name));
+            let _ = fs::remove_file(early.join(&name));
+        }
+    }
+    let _ = evict(&dir);
+
+    match pending(&dir) {
+        Ok(pending) if !pending.is_empty() => {
+            tracing::info!(count = pending.len(), "crash reports pending");
+            let count = pending.len();
+            let _ = app.emit(PENDING_EVENT, pending);
+            let _ = app.emit(PENDING_REPORTS_EVENT, PendingReports { count });
+        }
+        Ok(_) => {}
+        Err(e) => tracing::warn!(error = %e, "failed to list crash reports"),
+    }
+}
+
+fn endpoint<R: Runtime>(app: &AppHandle<R>) -> Result<reqwest::Url, CrashError> {
+    let value = crate::config::current(app)
+        .crash_reporting_url
+        .map(Value::String)
+        .or_else(|| {
+            app.store(PREFERENCES_STORE)
+                .ok()
+                .and_then(|store| store.get(ENDPOINT_KEY))
+        });
+    match value {
+        Some(Value::String(url)) if !url.is_empty() => {
+            reqwest::Url::parse(&url).map_err(|e| CrashError::InvalidEndpoint(e.to_string()))
+        }
+        _ => Err(CrashError::NotConfigured),
+    }
+}
+
+/// The reports the frontend missed [`PENDING_EVENT`] for, oldest first.
+#[tauri::command]
+pub fn get_pending_crash_reports<R: Runtime>(
+    app: AppHandle<R>,
+) -> Result<Vec<PendingCrash>, CrashError> {
+    pending(&crash_dir(&app)?)
+}
+
+/// POSTs each report as JSON and deletes it once accepted. Stops at the first
+/// failure, leaving that report and the rest for a later attempt; returns how many
+/// were sent.
+#[tauri::command]
+pub async fn submit_crash_reports<R: Runtime>(
+    ids: Vec<String>,
+    app: AppHandle<R>,
+) -> Result<u32, CrashError> {
+    let endpoint = endpoint(&app)?;
+    let dir = crash_dir(&app)?;
+    let client = reqwest::Client::new();
+    let mut sent = 0;
+    for id in &ids {
+        let report = read_report(&dir, id)?;
+        let body = serde_json::to_vec(&report).map_err(|e| CrashError::Io(e.to_string()))?;
+        let response = client
+            .post(endpoint.clone())
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| CrashError::Upload(e.to_string()))?;
+        if !response.status().is_success() {
+            return Err(CrashError::Upload(format!(
+                "server responded {}",
+                response.status()
+            )));
+        }
+        fs::remove_file(report_path(&dir, id)?)?;
+        sent += 1;
+    }
+    tracing::info!(sent, "crash reports submitted");
+    Ok(sent)
+}
+
+#[tauri::command]
+pub fn discard_crash_reports<R: Runtime>(
+    ids: Vec<String>,
+    app: AppHandle<R>,
+) -> Result<(), CrashError> {
+    let dir = crash_dir(&app)?;
+    for id in &ids {
+        match fs::remove_file(report_path(&dir, id)?) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+            _ => {}
+        }
+    }
+    Ok(())
+}
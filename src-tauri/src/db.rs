@@ -0,0 +1,329 @@
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+use sqlx::query::Query;
+use sqlx::sqlite::{
+    SqliteArguments, SqliteConnectOptions, SqliteJournalMode, SqlitePool, SqlitePoolOptions,
+    SqliteRow,
+};
+use sqlx::{Column, Row, Sqlite, TypeInfo, ValueRef};
+use tauri::{AppHandle, Manager, Runtime, State};
+use tokio::sync::Notify;
+
+/// Database file shared with the frontend's `sqlite:layers.db` plugin connection.
+pub const DEFAULT_DB: &str = "layers.db";
+
+#[derive(Debug, thiserror::Error, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "camelCase")]
+pub enum DbError {
+    /// The database is encrypted and `db_unlock` hasn't been called yet.
+    #[cfg_attr(not(feature = "db-encryption"), allow(dead_code))]
+    #[error("database is locked; unlock it with its passphrase first")]
+    Locked,
+    #[error("database migrations have not finished yet")]
+    MigrationsPending,
+    #[error("database migration failed: {0}")]
+    MigrationFailed(String),
+    #[error("invalid input: {0}")]
+    InvalidInput(String),
+    #[error("{0}")]
+    Io(String),
+    #[error("{0}")]
+    Sql(String),
+}
+
+impl From<sqlx::Error> for DbError {
+    fn from(e: sqlx::Error) -> Self {
+        Self::Sql(e.to_string())
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Status {
+    #[cfg_attr(not(feature = "db-encryption"), allow(dead_code))]
+    Locked,
+    Pending,
+    Ready,
+    Failed(String),
+}
+
+/// Resolves a database path the same way the sql plugin does, so `sqlite:layers.db`
+/// from the frontend and `layers.db` here both end up in the app config dir.
+pub fn resolve_db_path<R: Runtime>(app: &AppHandle<R>, db_path: &str) -> Result<PathBuf, DbError> {
+    let path = PathBuf::from(db_path.strip_prefix("sqlite:").unwrap_or(db_path));
+    if path.is_absolute() {
+        return Ok(path);
+    }
+
+    let dir = app
+        .path()
+        .app_config_dir()
+        .map_err(|e| DbError::Io(e.to_string()))?;
+    std::fs::create_dir_all(&dir).map_err(|e| DbError::Io(e.to_string()))?;
+    Ok(dir.join(path))
+}
+
+/// Connection pool owned by the Rust side, pointing at the same file as the sql plugin.
+pub struct Db {
+    path: PathBuf,
+    pool: SqlitePool,
+    status: Mutex<Status>,
+    status_changed: Notify,
+}
+
+impl Db {
+    /// Opens the pool lazily so `setup` doesn't block on the first connection. An
+    /// encrypted database starts out [`DbError::Locked`].
+    pub fn open<R: Runtime>(app: &AppHandle<R>) -> Result<Self, DbError> {
+        let path = resolve_db_path(app, DEFAULT_DB)?;
+        let pool = SqlitePoolOptions::new().connect_lazy_with(Self::connect_options(&path));
+        #[cfg(feature = "db-encryption")]
+        let status = if crate::db_encryption::is_encrypted(&path) {
+            Status::Locked
+        } else {
+            Status::Pending
+        };
+        #[cfg(not(feature = "db-encryption"))]
+        let status = Status::Pending;
+        Ok(Self {
+            path,
+            pool,
+            status: Mutex::new(status),
+            status_changed: Notify::new(),
+        })
+    }
+
+    pub(crate) fn connect_options(path: &Path) -> SqliteConnectOptions {
+        SqliteConnectOptions::new()
+            .filename(path)
+            .create_if_missing(true)
+            // WAL lets the plugin's pool and ours read and write the file concurrently.
+            .journal_mode(SqliteJournalMode::Wal)
+    }
+
+    /// The pool for app queries; fails until migrations have completed.
+    pub fn pool(&self) -> Result<&SqlitePool, DbError> {
+        match &*self.status.lock().unwrap() {
+            Status::Ready => Ok(&self.pool),
+            Status::Locked => Err(DbError::Locked),
+            Status::Pending => Err(DbError::MigrationsPending),
+            Status::Failed(e) => Err(DbError::MigrationFailed(e.clone())),
+        }
+    }
+
+    /// Waits for migrations to finish, and for the database to be unlocked first if it's
+    /// encrypted, for background tasks that start with the app.
+    pub async fn ready_pool(&self) -> Result<&SqlitePool, DbError> {
+        loop {
+            // Registered before checking so a status change in between isn't missed.
+            let changed = self.status_changed.notified();
+            tokio::pin!(changed);
+            changed.as_mut().enable();
+
+            match self.pool() {
+                Err(DbError::MigrationsPending | DbError::Locked) => changed.await,
+                result => return result,
+            }
+        }
+    }
+
+    /// The pool regardless of migration state, for the migration runner itself.
+    pub(crate) fn raw_pool(&self) -> &SqlitePool {
+        &self.pool
+    }
+
+    pub(crate) fn is_locked(&self) -> bool {
+        matches!(*self.status.lock().unwrap(), Status::Locked)
+    }
+
+    #[cfg_attr(not(feature = "db-encryption"), allow(dead_code))]
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Used by every connection opened from here on; call [`Db::close_connections`] too
+    /// so idle ones don't linger with the old options.
+    #[cfg_attr(not(feature = "db-encryption"), allow(dead_code))]
+    pub(crate) fn set_connect_options(&self, options: SqliteConnectOptions) {
+        self.pool.set_connect_options(options);
+    }
+
+    /// Closes pooled connections as they come back, waiting up to `timeout` for ones in
+    /// use. Set a status other than ready first so commands stop taking new ones; returns
+    /// whether the pool emptied.
+    #[cfg_attr(not(feature = "db-encryption"), allow(dead_code))]
+    pub(crate) async fn close_connections(&self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        while self.pool.size() > 0 {
+            if Instant::now() >= deadline {
+                return false;
+            }
+            match self.pool.try_acquire() {
+                Some(conn) => {
+                    let _ = conn.close().await;
+                }
+                None => tokio::time::sleep(Duration::from_millis(20)).await,
+            }
+        }
+        true
+    }
+
+    pub(crate) fn set_pending(&self) {
+        *self.status.lock().unwrap() = Status::Pending;
+    }
+
+    pub(crate) fn set_ready(&self) {
+        *self.status.lock().unwrap() = Status::Ready;
+        self.status_changed.notify_waiters();
+    }
+
+    pub(crate) fn set_failed(&self, error: String) {
+        *self.status.lock().unwrap() = Status::Failed(error);
+        self.status_changed.notify_waiters();
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryPage {
+    pub rows: Vec<Value>,
+    pub page: u32,
+    pub page_size: u32,
+    pub has_more: bool,
+}
+
+/// Accepts only plain SQL identifiers so table and column names can be interpolated safely.
+fn quote_identifier(name: &str) -> Result<String, DbError> {
+    let mut chars = name.chars();
+    let valid = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid {
+        return Err(DbError::InvalidInput(format!("invalid identifier: {name}")));
+    }
+    Ok(format!("\"{name}\""))
+}
+
+pub(crate) fn bind_value<'q>(
+    query: Query<'q, Sqlite, SqliteArguments<'q>>,
+    value: &Value,
+) -> Query<'q, Sqlite, SqliteArguments<'q>> {
+    match value {
+        Value::Null => query.bind(None::<String>),
+        Value::Bool(b) => query.bind(*b),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => query.bind(i),
+            None => query.bind(n.as_f64()),
+        },
+        Value::String(s) => query.bind(s.clone()),
+        // Nested values are stored as JSON text, matching what the plugin does.
+        other => query.bind(other.to_string()),
+    }
+}
+
+pub(crate) fn row_to_json(row: &SqliteRow) -> Result<Value, sqlx::Error> {
+    let mut object = Map::with_capacity(row.columns().len());
+    for column in row.columns() {
+        let i = column.ordinal();
+        let raw = row.try_get_raw(i)?;
+        let value = if raw.is_null() {
+            Value::Null
+        } else {
+            match raw.type_info().name() {
+                "INTEGER" | "BOOLEAN" => Value::from(row.try_get::<i64, _>(i)?),
+                "REAL" => Value::from(row.try_get::<f64, _>(i)?),
+                "BLOB" => Value::from(row.try_get::<Vec<u8>, _>(i)?),
+                _ => Value::from(row.try_get::<String, _>(i)?),
+            }
+        };
+        object.insert(column.name().to_string(), value);
+    }
+    Ok(Value::Object(object))
+}
+
+/// Inserts every row in one transaction, reusing a single prepared statement.
+/// Columns are taken from the first row; keys missing from later rows insert `NULL`.
+#[tauri::command]
+pub async fn db_bulk_insert(
+    table: String,
+    rows: Vec<Value>,
+    db: State<'_, Db>,
+) -> Result<u64, DbError> {
+    let Some(Value::Object(first)) = rows.first() else {
+        return Ok(0);
+    };
+
+    let columns: Vec<String> = first.keys().cloned().collect();
+    let quoted = columns
+        .iter()
+        .map(|c| quote_identifier(c))
+        .collect::<Result<Vec<_>, _>>()?;
+    let sql = format!(
+        "INSERT INTO {} ({}) VALUES ({})",
+        quote_identifier(&table)?,
+        quoted.join(", "),
+        vec!["?"; columns.len()].join(", ")
+    );
+
+    let mut tx = db.pool()?.begin().await?;
+    let mut inserted = 0;
+    for row in &rows {
+        let Value::Object(row) = row else {
+            return Err(DbError::InvalidInput(
+                "every row must be a JSON object".into(),
+            ));
+        };
+        let mut query = sqlx::query(&sql);
+        for column in &columns {
+            query = bind_value(query, row.get(column).unwrap_or(&Value::Null));
+        }
+        inserted += query.execute(&mut *tx).await?.rows_affected();
+    }
+    tx.commit().await?;
+
+    Ok(inserted)
+}
+
+/// Runs a query and returns one page of it as JSON objects. `page` is zero-based.
+#[tauri::command]
+pub async fn db_query_paged(
+    sql: String,
+    params: Vec<Value>,
+    page: u32,
+    page_size: u32,
+    db: State<'_, Db>,
+) -> Result<QueryPage, DbError> {
+    let page_size = page_size.max(1);
+    let paged = format!(
+        "SELECT * FROM ({}) LIMIT ? OFFSET ?",
+        sql.trim().trim_end_matches(';')
+    );
+
+    let mut query = sqlx::query(&paged);
+    for param in &params {
+        query = bind_value(query, param);
+    }
+    // Fetch one extra row to know whether another page exists.
+    let query = query
+        .bind(i64::from(page_size) + 1)
+        .bind(i64::from(page) * i64::from(page_size));
+
+    let fetched = query.fetch_all(db.pool()?).await?;
+    let has_more = fetched.len() > page_size as usize;
+    let rows = fetched
+        .iter()
+        .take(page_size as usize)
+        .map(row_to_json)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(QueryPage {
+        rows,
+        page,
+        page_size,
+        has_more,
+    })
+}
@@ -0,0 +1,529 @@
+//! Encryption at rest for the shared database, built with `--features db-encryption`.
+//! SQLCipher encrypts the file under a raw 256-bit key derived from the passphrase with
+//! Argon2, so connecting skips SQLCipher's own key derivation. The sidecar
+//! `layers.db.keycheck` holds each key's salt and a hash of the key, which tells a wrong
+//! passphrase apart from a damaged file.
+//!
+//! An encrypted database opens locked: `Db` commands fail with
+//! [`crate::db::DbError::Locked`] and migrations wait until [`db_unlock`]. The frontend's
+//! `sqlite:layers.db` connection has no key and can't read the file once it's encrypted,
+//! so encrypted installs go through the Rust-side commands.
+
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::OsRng;
+use argon2::Argon2;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::sqlite::SqliteConnectOptions;
+use sqlx::{ConnectOptions, Connection, SqliteConnection};
+use tauri::{AppHandle, Runtime, State};
+
+use crate::db::Db;
+use crate::keychain::KeychainError;
+use crate::migrations;
+use crate::secrets::{self, SecretError, Secrets};
+
+/// The first bytes of every plaintext SQLite file; SQLCipher's start with a random salt.
+const SQLITE_HEADER: &[u8; 16] = b"SQLite format 3\0";
+/// SQLite's result code when a page doesn't decrypt to a valid database.
+const SQLITE_NOTADB: &str = "26";
+const KEY_CHECK_SUFFIX: &str = ".keycheck";
+const STAGING_SUFFIX: &str = ".encrypting";
+const SIDE_FILES: &[&str] = &["-wal", "-shm"];
+const KEYCHAIN_ACCOUNT: &str = "db-encryption-key";
+const SALT_LEN: usize = 16;
+/// Longest a conversion waits for queries already running to give their connections back.
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, thiserror::Error, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "camelCase")]
+pub enum DbEncryptionError {
+    #[error("wrong passphrase")]
+    WrongPassphrase,
+    /// The passphrase matched the key check but the file doesn't decrypt.
+    #[error("database is corrupted: {0}")]
+    Corrupt(String),
+    #[error("database is not encrypted")]
+    NotEncrypted,
+    #[error("database is already encrypted")]
+    AlreadyEncrypted,
+    #[error("database is locked")]
+    Locked,
+    #[error("database is already unlocked")]
+    AlreadyUnlocked,
+    #[error("no database key is cached in the keychain")]
+    NoCachedKey,
+    /// Linked against plain SQLite, where `PRAGMA key` is silently ignored.
+    #[error("SQLCipher is not available in this build")]
+    CipherUnavailable,
+    #[error("database connections are still in use; try again")]
+    Busy,
+    #[error("keychain error: {0}")]
+    Keychain(String),
+    #[error("invalid input: {0}")]
+    InvalidInput(String),
+    #[error("{0}")]
+    Io(String),
+    #[error("{0}")]
+    Sql(String),
+}
+
+impl From<sqlx::Error> for DbEncryptionError {
+    fn from(e: sqlx::Error) -> Self {
+        match e {
+            sqlx::Error::Io(e) => Self::Io(e.to_string()),
+            e => Self::Sql(e.to_string()),
+        }
+    }
+}
+
+impl From<std::io::Error> for DbEncryptionError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e.to_string())
+    }
+}
+
+impl From<SecretError> for DbEncryptionError {
+    fn from(e: SecretError) -> Self {
+        Self::Keychain(e.to_string())
+    }
+}
+
+impl From<KeychainError> for DbEncryptionError {
+    fn from(e: KeychainError) -> Self {
+        Self::Keychain(e.to_string())
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DbEncryptionStatus {
+    pub encrypted: bool,
+    pub locked: bool,
+}
+
+/// One accepted key. The file usually has one; during a passphrase change it also keeps
+/// the old one, so a crash before the swap still unlocks with the old passphrase.
+#[derive(Serialize, Deserialize)]
+struct KeyCheck {
+    salt: String,
+    check: String,
+}
+
+struct DbKey([u8; 32]);
+
+impl DbKey {
+    fn hex(&self) -> String {
+        self.0.iter().map(|b| format!("{b:02x}")).collect()
+    }
+
+    /// SQLCipher's raw key syntax, as a value for `ATTACH ... KEY ?`.
+    fn raw(&self) -> String {
+        format!("x'{}'", self.hex())
+    }
+
+    fn from_hex(hex: &str) -> Option<Self> {
+        let mut key = [0u8; 32];
+        if hex.len() != 64 {
+            return None;
+        }
+        for (i, byte) in key.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(hex.get(i * 2..i * 2 + 2)?, 16).ok()?;
+        }
+        Some(Self(key))
+    }
+
+    fn check(&self) -> String {
+        let digest = Sha256::new()
+            .chain_update(b"layers-db-key-check")
+            .chain_update(self.0)
+            .finalize();
+        base64::engine::general_purpose::STANDARD.encode(digest)
+    }
+
+    fn options(&self, options: SqliteConnectOptions) -> SqliteConnectOptions {
+        // Quoted so the pragma isn't parsed as a blob literal.
+        options.pragma("key", format!("\"{}\"", self.raw()))
+    }
+}
+
+/// Whether `path` holds a database SQLite can't read without a key. A missing or empty
+/// file is a new database, which starts out in plaintext.
+pub(crate) fn is_encrypted(path: &Path) -> bool {
+    let mut header = [0u8; 16];
+    match fs::File::open(path).and_then(|mut file| file.read_exact(&mut header)) {
+        Ok(()) => &header != SQLITE_HEADER,
+        Err(_) => false,
+    }
+}
+
+fn side_path(path: &Path, suffix: &str) -> PathBuf {
+    let mut side = path.as_os_str().to_owned();
+    side.push(suffix);
+    PathBuf::from(side)
+}
+
+fn derive(passphrase: &str, salt: &[u8]) -> Result<DbKey, DbEncryptionError> {
+    if passphrase.is_empty() {
+        return Err(DbEncryptionError::InvalidInput(
+            "passphrase must not be empty".into(),
+        ));
+    }
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| DbEncryptionError::Io(format!("key derivation failed: {e}")))?;
+    Ok(DbKey(key))
+}
+
+fn new_key(passphrase: &str) -> Result<(DbKey, KeyCheck), DbEncryptionError> {
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let key = derive(passphrase, &salt)?;
+    let check = KeyCheck {
+        salt: base64::engine::general_purpose::STANDARD.encode(salt),
+        check: key.check(),
+    };
+    Ok((key, check))
+}
+
+fn read_checks(db_path: &Path) -> Result<Vec<KeyCheck>, DbEncryptionError> {
+    match fs::read(side_path(db_path, KEY_CHECK_SUFFIX)) {
+        Ok(bytes) => serde_json::from_slice(&bytes)
+            .map_err(|e| DbEncryptionError::Io(format!("key check file is unreadable: {e}"))),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn write_checks(db_path: &Path, checks: &[KeyCheck]) -> Result<(), DbEncryptionError> {
+    let path = side_path(db_path, KEY_CHECK_SUFFIX);
+    let tmp = path.with_extension("keycheck.tmp");
+    let json = serde_json::to_vec(checks).map_err(|e| DbEncryptionError::Io(e.to_string()))?;
+    fs::write(&tmp, json)?;
+    fs::rename(&tmp, &path)?;
+    Ok(())
+}
+
+/// The keys `passphrase` derives that the key check file accepts, newest first, each with
+/// its entry.
+fn matching_keys(
+    db_path: &Path,
+    passphrase: &str,
+) -> Result<Vec<(DbKey, KeyCheck)>, DbEncryptionError> {
+    let engine = base64::engine::general_purpose::STANDARD;
+    let mut keys = Vec::new();
+    for entry in read_checks(db_path)? {
+        let Ok(salt) = engine.decode(&entry.salt) else {
+            continue;
+        };
+        let key = derive(passphrase, &salt)?;
+        if key.check() == entry.check {
+            keys.push((key, entry));
+        }
+    }
+    Ok(keys)
+}
+
+fn not_a_db(e: &sqlx::Error) -> bool {
+    e.as_database_error()
+        .and_then(|e| e.code())
+        .is_some_and(|code| code == SQLITE_NOTADB)
+}
+
+/// Connects and reads the schema, which is where a wrong key shows up, after making sure
+/// SQLCipher is the library actually in use.
+async fn connect_checked(
+    options: &SqliteConnectOptions,
+) -> Result<SqliteConnection, DbEncryptionError> {
+    let mut conn = options.connect().await.map_err(classify)?;
+    let cipher: Option<(String,)> = sqlx::query_as("PRAGMA cipher_version")
+        .fetch_optional(&mut conn)
+        .await?;
+    if cipher.is_none() {
+        return Err(DbEncryptionError::CipherUnavailable);
+    }
+    sqlx::query("SELECT count(*) FROM sqlite_master")
+        .execute(&mut conn)
+        .await
+        .map_err(classify)?;
+    Ok(conn)
+}
+
+/// Only called once a key has passed the key check, so a file it can't decrypt is damaged.
+fn classify(e: sqlx::Error) -> DbEncryptionError {
+    if not_a_db(&e) {
+        DbEncryptionError::Corrupt(e.to_string())
+    } else {
+        e.into()
+    }
+}
+
+/// Copies the database into a staging file encrypted under `to` and checks the copy,
+/// including the notes index, which is rebuilt if it didn't survive.
+async fn export(db: &Db, from: Option<&DbKey>, to: &DbKey) -> Result<PathBuf, DbEncryptionError> {
+    let staging = side_path(db.path(), STAGING_SUFFIX);
+    let _ = fs::remove_file(&staging);
+
+    let source = SqliteConnectOptions::new().filename(db.path());
+    let source = match from {
+        Some(key) => key.options(source),
+        None => source,
+    };
+    let mut conn = connect_checked(&source).await?;
+    let exported = async {
+        sqlx::query("ATTACH DATABASE ? AS encrypted KEY ?")
+            .bind(staging.to_string_lossy().into_owned())
+            .bind(to.raw())
+            .execute(&mut conn)
+            .await?;
+        sqlx::query("SELECT sqlcipher_export('encrypted')")
+            .execute(&mut conn)
+            .await?;
+        sqlx::query("DETACH DATABASE encrypted")
+            .execute(&mut conn)
+            .await?;
+        Ok::<_, sqlx::Error>(())
+    }
+    .await;
+    conn.close().await?;
+    exported?;
+
+    let mut conn =
+        connect_checked(&to.options(SqliteConnectOptions::new().filename(&staging))).await?;
+    let (integrity,): (String,) = sqlx::query_as("PRAGMA integrity_check")
+        .fetch_one(&mut conn)
+        .await?;
+    if integrity != "ok" {
+        return Err(DbEncryptionError::Corrupt(integrity));
+    }
+    let has_fts: Option<(i64,)> =
+        sqlx::query_as("SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'notes_fts'")
+            .fetch_optional(&mut conn)
+            .await?;
+    if has_fts.is_some() {
+        let checked = sqlx::query("INSERT INTO notes_fts (notes_fts) VALUES ('integrity-check')")
+            .execute(&mut conn)
+            .await;
+        if let Err(e) = checked {
+            tracing::warn!(error = %e, "notes index failed its check after export; rebuilding");
+            sqlx::query("INSERT INTO notes_fts (notes_fts) VALUES ('rebuild')")
+                .execute(&mut conn)
+                .await?;
+        }
+    }
+    conn.close().await?;
+    Ok(staging)
+}
+
+/// Re-encrypts the database under `to` and swaps it in. `checks` is written before the
+/// swap so either file unlocks if the app dies in between, and `to`'s check alone after.
+async fn convert<R: Runtime>(
+    app: &AppHandle<R>,
+    db: &Db,
+    from: Option<&DbKey>,
+    to: &DbKey,
+    checks: Vec<KeyCheck>,
+) -> Result<(), DbEncryptionError> {
+    db.set_pending();
+    let result = async {
+        if !db.close_connections(DRAIN_TIMEOUT).await {
+            return Err(DbEncryptionError::Busy);
+        }
+        let staging = export(db, from, to).await?;
+
+        write_checks(db.path(), &checks)?;
+        for suffix in SIDE_FILES {
+            let _ = fs::remove_file(side_path(db.path(), suffix));
+        }
+        fs::rename(&staging, db.path())?;
+        write_checks(db.path(), &checks[..1])?;
+
+        // Background tasks keep their pool and may have connected to the old file since
+        // the first drain; anything they wrote there in that window is lost.
+        db.set_connect_options(to.options(Db::connect_options(db.path())));
+        db.close_connections(DRAIN_TIMEOUT).await;
+        Ok(())
+    }
+    .await;
+    if let Err(e) = &result {
+        tracing::error!(error = %e, "database encryption failed");
+        let _ = fs::remove_file(side_path(db.path(), STAGING_SUFFIX));
+    }
+    // Puts the pool back in service either way; the schema is already current.
+    migrations::spawn(app);
+    result
+}
+
+/// Switches the pool to the first of `keys` that opens the file and starts migrations.
+async fn unlock<R: Runtime>(
+    app: &AppHandle<R>,
+    db: &Db,
+    keys: Vec<DbKey>,
+) -> Result<DbKey, DbEncryptionError> {
+    if !db.is_locked() {
+        return Err(DbEncryptionError::AlreadyUnlocked);
+    }
+    if keys.is_empty() {
+        return Err(DbEncryptionError::WrongPassphrase);
+    }
+
+    let mut error = None;
+    for key in keys {
+        let options = key.options(Db::connect_options(db.path()));
+        match connect_checked(&options).await {
+            Ok(conn) => {
+                conn.close().await?;
+                db.set_connect_options(options);
+                // Drops any connection opened without the key while locked.
+                db.close_connections(DRAIN_TIMEOUT).await;
+                db.set_pending();
+                migrations::spawn(app);
+                tracing::info!("database unlocked");
+                return Ok(key);
+            }
+            // Left over from a passphrase change that finished; try the next key.
+            Err(e @ DbEncryptionError::Corrupt(_)) => error = Some(e),
+            Err(e) => return Err(e),
+        }
+    }
+    Err(error.unwrap_or(DbEncryptionError::WrongPassphrase))
+}
+
+fn cache_key<R: Runtime>(
+    app: &AppHandle<R>,
+    secrets: &Secrets,
+    key: &DbKey,
+) -> Result<(), DbEncryptionError> {
+    secrets::keychain_entry(app, secrets, KEYCHAIN_ACCOUNT)?.set_password(&key.hex())?;
+    Ok(())
+}
+
+fn forget_key<R: Runtime>(app: &AppHandle<R>, secrets: &Secrets) -> Result<(), DbEncryptionError> {
+    match secrets::keychain_entry(app, secrets, KEYCHAIN_ACCOUNT)?.delete_credential() {
+        Ok(()) | Err(KeychainError::NotFound) => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn is_key_cached<R: Runtime>(app: &AppHandle<R>, secrets: &Secrets) -> bool {
+    secrets::keychain_entry(app, secrets, KEYCHAIN_ACCOUNT)
+        .is_ok_and(|entry| entry.get_password().is_ok())
+}
+
+/// Encrypts the plaintext database under `passphrase`: exports it into a new SQLCipher
+/// file and swaps that in. With `remember`, the derived key is also cached in the OS
+/// keychain for [`db_unlock_cached`].
+#[tauri::command]
+pub async fn db_set_passphrase<R: Runtime>(
+    passphrase: String,
+    remember: bool,
+    app: AppHandle<R>,
+    db: State<'_, Db>,
+    secrets: State<'_, Secrets>,
+) -> Result<(), DbEncryptionError> {
+    if is_encrypted(db.path()) {
+        return Err(DbEncryptionError::AlreadyEncrypted);
+    }
+    let (key, check) = new_key(&passphrase)?;
+    convert(&app, &db, None, &key, vec![check]).await?;
+    tracing::info!("database encrypted");
+    if remember {
+        cache_key(&app, &secrets, &key)?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn db_unlock<R: Runtime>(
+    passphrase: String,
+    remember: bool,
+    app: AppHandle<R>,
+    db: State<'_, Db>,
+    secrets: State<'_, Secrets>,
+) -> Result<(), DbEncryptionError> {
+    let keys = matching_keys(db.path(), &passphrase)?;
+    let key = unlock(&app, &db, keys.into_iter().map(|(key, _)| key).collect()).await?;
+    if remember {
+        cache_key(&app, &secrets, &key)?;
+    }
+    Ok(())
+}
+
+/// Unlocks with the key cached by `remember`. Reading it may show the OS's own prompt,
+/// and the frontend can gate the call behind `authenticate_biometric`.
+#[tauri::command]
+pub async fn db_unlock_cached<R: Runtime>(
+    app: AppHandle<R>,
+    db: State<'_, Db>,
+    secrets: State<'_, Secrets>,
+) -> Result<(), DbEncryptionError> {
+    let hex = match secrets::keychain_entry(&app, &secrets, KEYCHAIN_ACCOUNT)?.get_password() {
+        Ok(hex) => hex,
+        Err(KeychainError::NotFound) => return Err(DbEncryptionError::NoCachedKey),
+        Err(e) => return Err(e.into()),
+    };
+    let accepted = read_checks(db.path())?;
+    let Some(key) =
+        DbKey::from_hex(&hex).filter(|key| accepted.iter().any(|entry| entry.check == key.check()))
+    else {
+        // Cached before the passphrase last changed somewhere the keychain missed.
+        forget_key(&app, &secrets)?;
+        return Err(DbEncryptionError::NoCachedKey);
+    };
+    unlock(&app, &db, vec![key]).await?;
+    Ok(())
+}
+
+/// Re-encrypts under `new` the same way [`db_set_passphrase`] encrypts. A key cached in
+/// the keychain is replaced with the new one.
+#[tauri::command]
+pub async fn db_change_passphrase<R: Runtime>(
+    old: String,
+    new: String,
+    app: AppHandle<R>,
+    db: State<'_, Db>,
+    secrets: State<'_, Secrets>,
+) -> Result<(), DbEncryptionError> {
+    if !is_encrypted(db.path()) {
+        return Err(DbEncryptionError::NotEncrypted);
+    }
+    if db.is_locked() {
+        return Err(DbEncryptionError::Locked);
+    }
+    let (old_key, old_check) = matching_keys(db.path(), &old)?
+        .into_iter()
+        .next()
+        .ok_or(DbEncryptionError::WrongPassphrase)?;
+    let (key, check) = new_key(&new)?;
+
+    let cached = is_key_cached(&app, &secrets);
+    convert(&app, &db, Some(&old_key), &key, vec![check, old_check]).await?;
+    tracing::info!("database passphrase changed");
+    if cached {
+        cache_key(&app, &secrets, &key)?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn db_forget_cached_key<R: Runtime>(
+    app: AppHandle<R>,
+    secrets: State<'_, Secrets>,
+) -> Result<(), DbEncryptionError> {
+    forget_key(&app, &secrets)
+}
+
+#[tauri::command]
+pub fn db_encryption_status(db: State<'_, Db>) -> DbEncryptionStatus {
+    DbEncryptionStatus {
+        encrypted: is_encrypted(db.path()),
+        locked: db.is_locked(),
+    }
+}
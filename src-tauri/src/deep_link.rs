@@ -0,0 +1,140 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use serde::Serialize;
+use tauri::{App, AppHandle, Emitter, Manager, Runtime, State, Url};
+use tauri_plugin_deep_link::DeepLinkExt;
+
+use crate::deep_link_router::{DeepLinkParams, DeepLinkRouter};
+
+pub const ROUTE_EVENT: &str = "deep-link://route";
+/// Emitted with every [`ROUTE_EVENT`] for links no Rust handler matched.
+pub const UNHANDLED_EVENT: &str = "deep-link://unhandled";
+/// Where OAuth providers are told to redirect.
+pub const AUTH_CALLBACK: &str = "layers://auth/callback";
+
+/// A deep link split into routing pieces: `layers://note/42?mode=edit` becomes
+/// `path: ["note", "42"]`, `query: {"mode": "edit"}`.
+#[derive(Debug, Clone, Serialize)]
+pub struct DeepLinkRoute {
+    pub url: String,
+    pub path: Vec<String>,
+    pub query: HashMap<String, String>,
+}
+
+impl From<&Url> for DeepLinkRoute {
+    fn from(url: &Url) -> Self {
+        // Custom schemes put the first segment in the host position.
+        let path = url
+            .host_str()
+            .into_iter()
+            .chain(url.path_segments().into_iter().flatten())
+            .filter(|segment| !segment.is_empty())
+            .map(str::to_string)
+            .collect();
+
+        Self {
+            url: url.to_string(),
+            path,
+            query: url.query_pairs().into_owned().collect(),
+        }
+    }
+}
+
+/// Holds routes that arrive before the frontend has attached its listener.
+#[derive(Default)]
+pub struct DeepLinkState {
+    inner: Mutex<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    ready: bool,
+    pending: Vec<DeepLinkRoute>,
+}
+
+fn emit_route<R: Runtime>(app: &AppHandle<R>, route: DeepLinkRoute) {
+    let _ = app.emit(UNHANDLED_EVENT, &route.url);
+    let _ = app.emit(ROUTE_EVENT, route);
+}
+
+/// Runs Rust handlers for links the router knows and forwards the rest to the frontend.
+pub(crate) fn dispatch<R: Runtime>(app: &AppHandle<R>, urls: &[Url]) {
+    let router = app.state::<DeepLinkRouter>();
+    let state = app.state::<DeepLinkState>();
+    let mut inner = state.inner.lock().unwrap();
+
+    for url in urls {
+        if router.handle(url) {
+            continue;
+        }
+
+        let route = DeepLinkRoute::from(url);
+        if inner.ready {
+            emit_route(app, route);
+        } else if !inner.pending.iter().any(|p| p.url == route.url) {
+            // Some platforms report the launch URL both via `get_current` and an open event.
+            inner.pending.push(route);
+        }
+    }
+}
+
+/// The OAuth redirect lands in the browser's context; bring the app back to the front so
+/// the frontend's `onOpenUrl` listener can finish the sign-in.
+fn handle_auth_callback<R: Runtime>(
+    app: &AppHandle<R>,
+    params: DeepLinkParams,
+) -> Result<(), String> {
+    if !params.query.contains_key("code") && !params.query.contains_key("error") {
+        return Err(format!("{} has neither `code` nor `error`", params.url));
+    }
+    let window = app
+        .get_webview_window("main")
+        .ok_or("main window is not open")?;
+    window.unminimize().map_err(|e| e.to_string())?;
+    window.show().map_err(|e| e.to_string())?;
+    window.set_focus().map_err(|e| e.to_string())
+}
+
+fn router<R: Runtime>(app: &AppHandle<R>) -> DeepLinkRouter {
+    let handle = app.clone();
+    DeepLinkRouter::new().on(AUTH_CALLBACK, move |params| {
+        handle_auth_callback(&handle, params)
+    })
+}
+
+/// Subscribes to incoming links and buffers the one the app was launched with, if any.
+pub fn init<R: Runtime>(app: &App<R>) -> Result<(), Box<dyn std::error::Error>> {
+    app.manage(DeepLinkState::default());
+    app.manage(router(app.handle()));
+
+    // Schemes are only registered by installers; make `tauri dev` work too.
+    #[cfg(all(debug_assertions, any(windows, target_os = "linux")))]
+    app.deep_link().register_all()?;
+
+    let handle = app.handle().clone();
+    app.deep_link().on_open_url(move |event| {
+        dispatch(&handle, &event.urls());
+    });
+
+    // Desktop parses the launch URL from argv, mobile from the launch intent / activity.
+    if let Some(urls) = app.deep_link().get_current()? {
+        dispatch(app.handle(), &urls);
+    }
+
+    Ok(())
+}
+
+/// Called by the frontend once its `deep-link://route` listener is attached.
+#[tauri::command]
+pub fn deep_link_ready<R: Runtime>(app: AppHandle<R>, state: State<'_, DeepLinkState>) {
+    let pending = {
+        let mut inner = state.inner.lock().unwrap();
+        inner.ready = true;
+        std::mem::take(&mut inner.pending)
+    };
+
+    for route in pending {
+        emit_route(&app, route);
+    }
+}
@@ -0,0 +1,81 @@
+use std::collections::HashMap;
+
+use tauri::Url;
+
+/// What a route handler receives: the full URL and its parsed query string.
+#[derive(Debug, Clone)]
+pub struct DeepLinkParams {
+    pub url: String,
+    pub query: HashMap<String, String>,
+}
+
+type Handler = Box<dyn Fn(DeepLinkParams) -> Result<(), String> + Send + Sync + 'static>;
+
+struct Route {
+    scheme: String,
+    /// Host first, then path segments.
+    segments: Vec<String>,
+    handler: Handler,
+}
+
+/// Matches incoming links against patterns like `layers://auth/callback`, ignoring the
+/// query string, and runs the first handler that matches.
+#[derive(Default)]
+pub struct DeepLinkRouter {
+    routes: Vec<Route>,
+}
+
+fn segments(url: &Url) -> Vec<String> {
+    // Custom schemes put the first segment in the host position.
+    url.host_str()
+        .into_iter()
+        .chain(url.path_segments().into_iter().flatten())
+        .filter(|segment| !segment.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+impl DeepLinkRouter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers `handler` for `pattern`.
+    ///
+    /// # Panics
+    ///
+    /// If `pattern` is not a valid URL; patterns are compile-time constants.
+    pub fn on<F>(mut self, pattern: &str, handler: F) -> Self
+    where
+        F: Fn(DeepLinkParams) -> Result<(), String> + Send + Sync + 'static,
+    {
+        let url = Url::parse(pattern).expect("invalid deep link route pattern");
+        self.routes.push(Route {
+            scheme: url.scheme().to_string(),
+            segments: segments(&url),
+            handler: Box::new(handler),
+        });
+        self
+    }
+
+    fn matches(route: &Route, url: &Url) -> bool {
+        route.scheme == url.scheme() && route.segments == segments(url)
+    }
+
+    /// Runs the matching handler and returns whether one matched. Handler errors are
+    /// logged; the link still counts as handled.
+    pub fn handle(&self, url: &Url) -> bool {
+        let Some(route) = self.routes.iter().find(|route| Self::matches(route, url)) else {
+            return false;
+        };
+
+        let params = DeepLinkParams {
+            url: url.to_string(),
+            query: url.query_pairs().into_owned().collect(),
+        };
+        if let Err(error) = (route.handler)(params) {
+            tracing::warn!(url = %url, %error, "deep link handler failed");
+        }
+        true
+    }
+}
@@ -0,0 +1,347 @@
+//! The bundled frontend over real HTTP, for benchmarks comparing it against the custom
+//! protocol: service workers, HTTP caching and CORS all behave as on the web. Built only
+//! with the `dev-server` feature, which is off by default.
+//!
+//! The server binds an OS-picked port on `127.0.0.1` during setup, then opens a second
+//! window on it. Responses carry strong ETags and are brotli or gzip encoded when the
+//! client accepts it, after an artificial delay set with [`set_asset_latency`]. Anything
+//! not from loopback, or addressed to another host name, is refused, so neither the LAN
+//! nor a DNS-rebinding page can reach it.
+
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::io::Write;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use http_body_util::Full;
+use hyper::body::{Bytes, Incoming};
+use hyper::header::{self, HeaderMap, HeaderValue};
+use hyper::{Method, Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use tauri::{AppHandle, Manager, Runtime, State};
+use tokio::net::TcpListener;
+
+/// Long enough to make any jank show, short enough that a typo doesn't hang the window.
+const MAX_LATENCY_MS: u64 = 10_000;
+
+#[cfg(desktop)]
+const WINDOW_LABEL: &str = "dev-server";
+
+#[derive(Debug, thiserror::Error, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "camelCase")]
+pub enum DevServerError {
+    #[error("the dev server isn't running")]
+    NotRunning,
+    #[error("invalid input: {0}")]
+    InvalidInput(String),
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DevServerInfo {
+    pub port: u16,
+    pub url: String,
+    pub latency_ms: u64,
+    /// Requests per URL path, including ones answered with 304.
+    pub hits: HashMap<String, u64>,
+}
+
+/// One resolved asset and its encodings, which are compressed on first request.
+struct CachedAsset {
+    mime_type: String,
+    etag: String,
+    identity: Bytes,
+    gzip: OnceLock<Bytes>,
+    brotli: OnceLock<Bytes>,
+}
+
+#[derive(Default)]
+pub struct DevServer {
+    port: OnceLock<u16>,
+    latency_ms: AtomicU64,
+    hits: Mutex<HashMap<String, u64>>,
+    assets: Mutex<HashMap<String, Arc<CachedAsset>>>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Encoding {
+    Identity,
+    Gzip,
+    Brotli,
+}
+
+impl CachedAsset {
+    fn new(mime_type: String, bytes: Vec<u8>) -> Self {
+        let digest: String = Sha256::digest(&bytes)[..16]
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect();
+        Self {
+            mime_type,
+            etag: format!("\"{digest}\""),
+            identity: Bytes::from(bytes),
+            gzip: OnceLock::new(),
+            brotli: OnceLock::new(),
+        }
+    }
+
+    fn body(&self, encoding: Encoding) -> Bytes {
+        match encoding {
+            Encoding::Identity => self.identity.clone(),
+            Encoding::Gzip => self
+                .gzip
+                .get_or_init(|| {
+                    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+                    let _ = encoder.write_all(&self.identity);
+                    encoder.finish().unwrap_or_default().into()
+                })
+                .clone(),
+            Encoding::Brotli => self
+                .brotli
+                .get_or_init(|| {
+                    let mut out = Vec::new();
+                    {
+                        // Quality 9 of 11: most of the savings at a fraction of the time.
+                        let mut writer = brotli::CompressorWriter::new(&mut out, 4096, 9, 22);
+                        let _ = writer.write_all(&self.identity);
+                    }
+                    out.into()
+                })
+                .clone(),
+        }
+    }
+}
+
+/// Compressing images and fonts again gains nothing.
+fn compressible(mime_type: &str) -> bool {
+    mime_type.starts_with("text/")
+        || mime_type.contains("javascript")
+        || mime_type.contains("json")
+        || mime_type.contains("xml")
+        || mime_type == "image/svg+xml"
+        || mime_type == "application/wasm"
+}
+
+/// Brotli over gzip, ignoring q-values: browsers offer both at full weight.
+fn negotiate(headers: &HeaderMap, mime_type: &str) -> Encoding {
+    if !compressible(mime_type) {
+        return Encoding::Identity;
+    }
+    let accepted = headers
+        .get(header::ACCEPT_ENCODING)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    let offers = |name: &str| {
+        accepted
+            .split(',')
+            .any(|part| part.split(';').next().unwrap_or_default().trim() == name)
+    };
+    if offers("br") {
+        Encoding::Brotli
+    } else if offers("gzip") {
+        Encoding::Gzip
+    } else {
+        Encoding::Identity
+    }
+}
+
+/// Only names that resolve to loopback everywhere: a rebinding attack controls DNS for
+/// its own name, not for these.
+fn is_loopback_host(headers: &HeaderMap) -> bool {
+    let Some(host) = headers.get(header::HOST).and_then(|h| h.to_str().ok()) else {
+        return false;
+    };
+    let name = host.rsplit_once(':').map_or(host, |(name, _)| name);
+    matches!(name, "127.0.0.1" | "localhost")
+}
+
+fn respond(status: StatusCode, body: impl Into<Bytes>) -> Response<Full<Bytes>> {
+    let mut response = Response::new(Full::new(body.into()));
+    *response.status_mut() = status;
+    response
+}
+
+fn asset<R: Runtime>(
+    app: &AppHandle<R>,
+    server: &DevServer,
+    path: &str,
+) -> Option<Arc<CachedAsset>> {
+    if let Some(asset) = server.assets.lock().unwrap().get(path) {
+        return Some(asset.clone());
+    }
+    let resolved = app.asset_resolver().get(path.to_owned())?;
+    let asset = Arc::new(CachedAsset::new(resolved.mime_type, resolved.bytes));
+    server
+        .assets
+        .lock()
+        .unwrap()
+        .insert(path.to_owned(), asset.clone());
+    Some(asset)
+}
+
+async fn handle<R: Runtime>(
+    request: Request<Incoming>,
+    app: AppHandle<R>,
+) -> Result<Response<Full<Bytes>>, Infallible> {
+    if !is_loopback_host(request.headers()) {
+        return Ok(respond(StatusCode::MISDIRECTED_REQUEST, "loopback only"));
+    }
+    if !matches!(*request.method(), Method::GET | Method::HEAD) {
+        return Ok(respond(StatusCode::METHOD_NOT_ALLOWED, Bytes::new()));
+    }
+    let server = app.state::<DevServer>();
+    let path = request.uri().path().to_owned();
+    *server.hits.lock().unwrap().entry(path.clone()).or_default() += 1;
+
+    let latency = server.latency_ms.load(Ordering::Relaxed);
+    if latency > 0 {
+        tokio::time::sleep(Duration::from_millis(latency)).await;
+    }
+
+    let Some(asset) = asset(&app, &server, &path) else {
+        return Ok(respond(StatusCode::NOT_FOUND, Bytes::new()));
+    };
+    let etag = HeaderValue::from_str(&asset.etag).expect("hex ETags are valid headers");
+    let fresh = request
+        .headers()
+        .get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|tags| {
+            tags.split(',')
+                .any(|tag| tag.trim() == "*" || tag.trim().trim_start_matches("W/") == asset.etag)
+        });
+
+    let encoding = negotiate(request.headers(), &asset.mime_type);
+    let mut response = if fresh {
+        respond(StatusCode::NOT_MODIFIED, Bytes::new())
+    } else {
+        let body = asset.body(encoding);
+        let len = body.len();
+        let mut response = respond(
+            StatusCode::OK,
+            if request.method() == Method::HEAD {
+                Bytes::new()
+            } else {
+                body
+            },
+        );
+        let headers = response.headers_mut();
+        headers.insert(header::CONTENT_LENGTH, HeaderValue::from(len));
+        if let Ok(value) = HeaderValue::from_str(&asset.mime_type) {
+            headers.insert(header::CONTENT_TYPE, value);
+        }
+        match encoding {
+            Encoding::Identity => {}
+            Encoding::Gzip => {
+                headers.insert(header::CONTENT_ENCODING, HeaderValue::from_static("gzip"));
+            }
+            Encoding::Brotli => {
+                headers.insert(header::CONTENT_ENCODING, HeaderValue::from_static("br"));
+            }
+        }
+        response
+    };
+    let headers = response.headers_mut();
+    headers.insert(header::ETAG, etag);
+    headers.insert(header::VARY, HeaderValue::from_static("Accept-Encoding"));
+    // Revalidate every time, so the ETag round trip is part of what gets measured.
+    headers.insert(header::CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+    Ok(response)
+}
+
+async fn serve<R: Runtime>(app: AppHandle<R>, listener: TcpListener) {
+    loop {
+        let (stream, peer) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                tracing::warn!(error = %e, "dev server accept failed");
+                continue;
+            }
+        };
+        // The bind address already keeps other hosts out; this holds if that changes.
+        if !peer.ip().is_loopback() {
+            tracing::warn!(%peer, "dev server refused a non-loopback connection");
+            continue;
+        }
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            let service = hyper::service::service_fn(move |request| handle(request, app.clone()));
+            let served = hyper::server::conn::http1::Builder::new()
+                .serve_connection(TokioIo::new(stream), service)
+                .await;
+            if let Err(e) = served {
+                tracing::debug!(error = %e, "dev server connection closed");
+            }
+        });
+    }
+}
+
+/// Starts the server and opens its window. Failing to bind is logged rather than fatal,
+/// since the benchmarks are the only thing that need it.
+pub fn init<R: Runtime>(app: &AppHandle<R>) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let listener = match TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], 0))).await {
+            Ok(listener) => listener,
+            Err(e) => {
+                tracing::warn!(error = %e, "dev server failed to bind");
+                return;
+            }
+        };
+        let Ok(addr) = listener.local_addr() else {
+            return;
+        };
+        let _ = app.state::<DevServer>().port.set(addr.port());
+        tracing::info!(port = addr.port(), "dev server started");
+
+        #[cfg(desktop)]
+        {
+            let url = format!("http://127.0.0.1:{}/", addr.port())
+                .parse()
+                .expect("a loopback URL parses");
+            let opened = tauri::WebviewWindowBuilder::new(
+                &app,
+                WINDOW_LABEL,
+                tauri::WebviewUrl::External(url),
+            )
+            .title("Layers (HTTP)")
+            .inner_size(1000.0, 700.0)
+            .build();
+            if let Err(e) = opened {
+                tracing::warn!(error = %e, "failed to open the dev server window");
+            }
+        }
+
+        serve(app, listener).await;
+    });
+}
+
+/// Delays every response by `ms`, before anything is read; 0 turns it off.
+#[tauri::command]
+pub fn set_asset_latency(ms: u64, server: State<'_, DevServer>) -> Result<(), DevServerError> {
+    if ms > MAX_LATENCY_MS {
+        return Err(DevServerError::InvalidInput(format!(
+            "latency is capped at {MAX_LATENCY_MS} ms"
+        )));
+    }
+    server.latency_ms.store(ms, Ordering::Relaxed);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_dev_server_info(server: State<'_, DevServer>) -> Result<DevServerInfo, DevServerError> {
+    let port = *server.port.get().ok_or(DevServerError::NotRunning)?;
+    Ok(DevServerInfo {
+        port,
+        url: format!("http://127.0.0.1:{port}/"),
+        latency_ms: server.latency_ms.load(Ordering::Relaxed),
+        hits: server.hits.lock().unwrap().clone(),
+    })
+}
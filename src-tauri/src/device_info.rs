@@ -0,0 +1,369 @@
+//! Hardware details for correlating performance numbers with the device they came from,
+//! and for laying out around the notch. Memory, cores, and the OS come from `sysinfo`
+//! and `os_info` everywhere; the device name, model, and battery from
+//! `tauri-plugin-device-info` on mobile, and safe-area insets, the memory class and
+//! battery updates from our own `plugins/device`, which also passes memory warnings on
+//! to [`crate::lifecycle`].
+//!
+//! Battery readings go out as [`BATTERY_CHANGED_EVENT`] whenever the level or charging
+//! state changes, starting with the first one. Desktop polls for them.
+
+#[cfg(desktop)]
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use sysinfo::{MemoryRefreshKind, RefreshKind, System};
+use tauri::{AppHandle, Manager, Monitor, Runtime};
+
+pub const BATTERY_CHANGED_EVENT: &str = "battery://changed";
+
+const GIB: f64 = (1u64 << 30) as f64;
+/// Laptops update their charge readings about this often.
+#[cfg(desktop)]
+const BATTERY_POLL: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceInfo {
+    /// The hostname on desktop, the user-visible device name on mobile.
+    pub device_name: String,
+    /// E.g. `iPhone15,2` or `Pixel 8`; the DMI product name on desktop, which may be a
+    /// vendor code.
+    pub model: String,
+    pub os_name: String,
+    pub os_version: String,
+    /// The kernel version, or on macOS the build number such as `23B74`.
+    pub os_build: String,
+    pub memory_gb: f32,
+    /// Android's heap budget per app, in MiB; `None` elsewhere.
+    pub memory_class_mb: Option<u32>,
+    pub cpu_cores: u32,
+    /// Of the monitor showing the main window, in physical pixels.
+    pub screen_width_px: u32,
+    pub screen_height_px: u32,
+    pub screen_scale_factor: f32,
+    /// Changes with orientation, so read it again after a resize.
+    pub safe_area_insets: SafeAreaInsets,
+    /// From 0 to 1; `None` without a battery.
+    pub battery_level: Option<f32>,
+    pub is_charging: Option<bool>,
+}
+
+/// CSS pixels along each edge of the webview that the notch, status bar or home
+/// indicator cover. Zero on desktop.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct SafeAreaInsets {
+    pub top: f64,
+    pub right: f64,
+    pub bottom: f64,
+    pub left: f64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct BatteryStatus {
+    /// From 0 to 1; `None` without a battery.
+    pub level: Option<f32>,
+    pub charging: Option<bool>,
+}
+
+#[derive(Default)]
+struct Hardware {
+    device_name: Option<String>,
+    model: Option<String>,
+    battery: BatteryStatus,
+    safe_area_insets: SafeAreaInsets,
+    memory_class_mb: Option<u32>,
+}
+
+#[cfg(mobile)]
+mod native {
+    use serde::{Deserialize, Serialize};
+    use serde_json::Value;
+    use tauri::ipc::{Channel, InvokeResponseBody};
+    use tauri::plugin::{PluginHandle, TauriPlugin};
+    use tauri::{AppHandle, Emitter, Manager, Runtime};
+    // Linked for its native halves; it has no Rust API.
+    use tauri_plugin_device as _;
+    use tauri_plugin_device_info::DeviceInfoExt;
+
+    use super::{BatteryStatus, Hardware, SafeAreaInsets, BATTERY_CHANGED_EVENT};
+    use crate::lifecycle::MemoryPressure;
+
+    #[cfg(target_os = "ios")]
+    tauri::ios_plugin_binding!(init_plugin_device);
+
+    struct Device<R: Runtime>(PluginHandle<R>);
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Capabilities {
+        safe_area_insets: SafeAreaInsets,
+        memory_class_mb: Option<u32>,
+    }
+
+    #[derive(Deserialize)]
+    struct NativeMemoryWarning {
+        critical: bool,
+    }
+
+    /// Both native halves send `charging` as a plain bool.
+    #[derive(Deserialize)]
+    struct NativeBattery {
+        level: f32,
+        charging: bool,
+    }
+
+    #[derive(Serialize)]
+    struct WatchArgs {
+        channel: Channel,
+    }
+
+    pub fn plugin<R: Runtime>() -> TauriPlugin<R> {
+        tauri::plugin::Builder::new("device")
+            .setup(|app, api| {
+                #[cfg(target_os = "android")]
+                let handle = api.register_android_plugin("com.layers.device", "DevicePlugin")?;
+                #[cfg(target_os = "ios")]
+                let handle = api.register_ios_plugin(init_plugin_device)?;
+                app.manage(Device(handle));
+                Ok(())
+            })
+            .build()
+    }
+
+    fn device<R: Runtime>(app: &AppHandle<R>) -> Option<PluginHandle<R>> {
+        app.try_state::<Device<R>>().map(|device| device.0.clone())
+    }
+
+    pub fn battery<R: Runtime>(app: &AppHandle<R>) -> BatteryStatus {
+        let battery = app.device_info().get_battery_info().unwrap_or_default();
+        BatteryStatus {
+            level: battery.level.map(|percent| percent / 100.0),
+            charging: battery.is_charging,
+        }
+    }
+
+    pub fn hardware<R: Runtime>(app: &AppHandle<R>) -> Hardware {
+        let device = app
+            .device_info()
+            .get_device_info()
+            .map_err(|e| tracing::warn!(error = %e, "device info unavailable"))
+            .unwrap_or_default();
+        let capabilities = device(app).and_then(|handle| {
+            handle
+                .run_mobile_plugin::<Capabilities>("getCapabilities", ())
+                .map_err(|e| tracing::warn!(error = %e, "device capabilities unavailable"))
+                .ok()
+        });
+        Hardware {
+            device_name: device.device_name,
+            model: device.model,
+            battery: battery(app),
+            safe_area_insets: capabilities
+                .as_ref()
+                .map(|capabilities| capabilities.safe_area_insets)
+                .unwrap_or_default(),
+            memory_class_mb: capabilities.and_then(|capabilities| capabilities.memory_class_mb),
+        }
+    }
+
+    /// The native side reports changes itself.
+    pub fn watch_battery<R: Runtime>(app: &AppHandle<R>) {
+        let Some(handle) = device(app) else {
+            return;
+        };
+        let emitter = app.clone();
+        let channel = Channel::new(move |body| {
+            if let InvokeResponseBody::Json(json) = body {
+                match serde_json::from_str::<NativeBattery>(&json) {
+                    Ok(battery) => {
+                        let status = BatteryStatus {
+                            level: Some(battery.level),
+                            charging: Some(battery.charging),
+                        };
+                        let _ = emitter.emit(BATTERY_CHANGED_EVENT, status);
+                    }
+                    Err(e) => tracing::warn!(error = %e, "malformed battery update"),
+                }
+            }
+            Ok(())
+        });
+        // Not from setup's thread: the native halves answer on the main thread.
+        tauri::async_runtime::spawn(async move {
+            if let Err(e) = handle
+                .run_mobile_plugin_async::<Value>("watchBattery", WatchArgs { channel })
+                .await
+            {
+                tracing::warn!(error = %e, "battery updates unavailable");
+            }
+        });
+    }
+
+    pub fn watch_memory<R: Runtime>(app: &AppHandle<R>) {
+        let Some(handle) = device(app) else {
+            return;
+        };
+        let emitter = app.clone();
+        let channel = Channel::new(move |body| {
+            if let InvokeResponseBody::Json(json) = body {
+                match serde_json::from_str::<NativeMemoryWarning>(&json) {
+                    Ok(warning) => {
+                        let pressure = if warning.critical {
+                            MemoryPressure::Critical
+                        } else {
+                            MemoryPressure::Moderate
+                        };
+                        crate::lifecycle::memory_warning(&emitter, pressure);
+                    }
+                    Err(e) => tracing::warn!(error = %e, "malformed memory warning"),
+                }
+            }
+            Ok(())
+        });
+        tauri::async_runtime::spawn(async move {
+            if let Err(e) = handle
+                .run_mobile_plugin_async::<Value>("watchMemory", WatchArgs { channel })
+                .await
+            {
+                tracing::warn!(error = %e, "memory warnings unavailable");
+            }
+        });
+    }
+}
+
+#[cfg(desktop)]
+mod native {
+    use starship_battery::{Manager, State};
+    use sysinfo::{Product, System};
+    use tauri::{AppHandle, Emitter, Runtime};
+
+    use super::{BatteryStatus, Hardware, BATTERY_CHANGED_EVENT, BATTERY_POLL};
+
+    /// The first battery, which is the only one on nearly every laptop.
+    pub fn battery<R: Runtime>(_app: &AppHandle<R>) -> BatteryStatus {
+        let battery = Manager::new()
+            .ok()
+            .and_then(|manager| manager.batteries().ok()?.flatten().next());
+        let Some(battery) = battery else {
+            return BatteryStatus::default();
+        };
+        BatteryStatus {
+            level: Some(battery.state_of_charge().value),
+            charging: Some(matches!(battery.state(), State::Charging | State::Full)),
+        }
+    }
+
+    pub fn hardware<R: Runtime>(app: &AppHandle<R>) -> Hardware {
+        Hardware {
+            device_name: System::host_name(),
+            model: Product::name(),
+            battery: battery(app),
+            ..Hardware::default()
+        }
+    }
+
+    /// Polls while there is a battery; desktops without one never start.
+    pub fn watch_battery<R: Runtime>(app: &AppHandle<R>) {
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            let mut last: Option<BatteryStatus> = None;
+            let mut ticks = tokio::time::interval(BATTERY_POLL);
+            loop {
+                ticks.tick().await;
+                let handle = app.clone();
+                let Ok(status) =
+                    tauri::async_runtime::spawn_blocking(move || battery(&handle)).await
+                else {
+                    return;
+                };
+                if status.level.is_none() {
+                    return;
+                }
+                if last != Some(status) {
+                    last = Some(status);
+                    let _ = app.emit(BATTERY_CHANGED_EVENT, status);
+                }
+            }
+        });
+    }
+}
+
+#[cfg(mobile)]
+pub fn plugin<R: Runtime>() -> tauri::plugin::TauriPlugin<R> {
+    native::plugin()
+}
+
+/// Starts watching the battery for [`BATTERY_CHANGED_EVENT`] and, on mobile, memory
+/// pressure for [`crate::lifecycle`].
+pub fn init<R: Runtime>(app: &AppHandle<R>) {
+    native::watch_battery(app);
+    #[cfg(mobile)]
+    native::watch_memory(app);
+}
+
+#[cfg(target_os = "macos")]
+fn os_build() -> Option<String> {
+    let output = std::process::Command::new("sysctl")
+        .args(["-n", "kern.osversion"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+#[cfg(not(target_os = "macos"))]
+fn os_build() -> Option<String> {
+    System::kernel_version()
+}
+
+/// Prefers the monitor showing the main window, since that's where frames are measured.
+fn monitor<R: Runtime>(app: &AppHandle<R>) -> Option<Monitor> {
+    app.get_webview_window("main")
+        .and_then(|window| window.current_monitor().ok().flatten())
+        .or_else(|| app.primary_monitor().ok().flatten())
+}
+
+#[tauri::command]
+pub async fn get_device_info<R: Runtime>(app: AppHandle<R>) -> DeviceInfo {
+    let monitor = monitor(&app);
+    let (screen_width_px, screen_height_px) = monitor.as_ref().map_or((0, 0), |monitor| {
+        (monitor.size().width, monitor.size().height)
+    });
+    let screen_scale_factor = monitor.map_or(1.0, |monitor| monitor.scale_factor() as f32);
+
+    // Querying the battery and the mobile plugins can block briefly.
+    let hardware = tauri::async_runtime::spawn_blocking(move || native::hardware(&app))
+        .await
+        .unwrap_or_default();
+    let system = System::new_with_specifics(
+        RefreshKind::nothing().with_memory(MemoryRefreshKind::nothing().with_ram()),
+    );
+    let cpu_cores = System::physical_core_count()
+        .or_else(|| std::thread::available_parallelism().ok().map(usize::from))
+        .unwrap_or(1);
+    let os = os_info::get();
+
+    DeviceInfo {
+        device_name: hardware.device_name.unwrap_or_default(),
+        model: hardware.model.unwrap_or_default(),
+        os_name: os.os_type().to_string(),
+        os_version: os.version().to_string(),
+        os_build: os_build().unwrap_or_default(),
+        memory_gb: (system.total_memory() as f64 / GIB) as f32,
+        memory_class_mb: hardware.memory_class_mb,
+        cpu_cores: cpu_cores as u32,
+        screen_width_px,
+        screen_height_px,
+        screen_scale_factor,
+        safe_area_insets: hardware.safe_area_insets,
+        battery_level: hardware.battery.level,
+        is_charging: hardware.battery.charging,
+    }
+}
+
+#[tauri::command]
+pub async fn get_battery_status<R: Runtime>(app: AppHandle<R>) -> BatteryStatus {
+    tauri::async_runtime::spawn_blocking(move || native::battery(&app))
+        .await
+        .unwrap_or_default()
+}
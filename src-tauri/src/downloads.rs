@@ -0,0 +1,521 @@
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use sqlx::SqlitePool;
+use tauri::{AppHandle, Emitter, Manager, Runtime, State};
+use tauri_plugin_http::reqwest::{self, header, StatusCode};
+use tokio::io::AsyncWriteExt;
+use uuid::Uuid;
+
+use crate::db::Db;
+use crate::scope;
+use crate::tasks::{self, Reporter, Sink, TaskId, TaskKind, Tasks};
+
+pub const PROGRESS_EVENT: &str = "download://progress";
+
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+pub type DownloadId = Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DownloadStatus {
+    Queued,
+    Downloading,
+    Paused,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+impl DownloadStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Queued => "queued",
+            Self::Downloading => "downloading",
+            Self::Paused => "paused",
+            Self::Completed => "completed",
+            Self::Failed => "failed",
+            Self::Cancelled => "cancelled",
+        }
+    }
+
+    fn parse(status: &str) -> Self {
+        match status {
+            "completed" => Self::Completed,
+            "failed" => Self::Failed,
+            "cancelled" => Self::Cancelled,
+            // Anything that was in flight when the app died can only be resumed.
+            _ => Self::Paused,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadProgress {
+    pub id: DownloadId,
+    pub status: DownloadStatus,
+    pub downloaded: u64,
+    pub total: Option<u64>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadInfo {
+    pub id: DownloadId,
+    pub url: String,
+    pub dest: String,
+    pub status: DownloadStatus,
+    pub downloaded: u64,
+    pub error: Option<String>,
+}
+
+struct Request {
+    id: DownloadId,
+    url: String,
+    dest: PathBuf,
+    expected_sha256: Option<String>,
+}
+
+impl Request {
+    fn part_path(&self) -> PathBuf {
+        part_path(&self.dest)
+    }
+}
+
+fn part_path(dest: &Path) -> PathBuf {
+    let mut part = dest.as_os_str().to_owned();
+    part.push(".part");
+    PathBuf::from(part)
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+enum Outcome {
+    Completed(u64),
+    Paused(u64),
+    Cancelled,
+}
+
+/// A queued or running download: the task it runs as, and whether it's been asked to
+/// pause at its next chunk. Cancelling goes through the task; pausing ends it, and
+/// resuming starts another.
+struct Job {
+    task: TaskId,
+    pause: Arc<AtomicBool>,
+}
+
+/// Running and queued downloads. Paused, failed and finished ones live only in SQLite.
+/// Each runs as a task, waiting for one of the `download_concurrency` download slots.
+#[derive(Default)]
+pub struct Downloads {
+    jobs: Mutex<HashMap<DownloadId, Job>>,
+}
+
+fn emit<R: Runtime>(
+    app: &AppHandle<R>,
+    id: DownloadId,
+    status: DownloadStatus,
+    downloaded: u64,
+    total: Option<u64>,
+    error: Option<String>,
+) {
+    let _ = app.emit(
+        PROGRESS_EVENT,
+        DownloadProgress {
+            id,
+            status,
+            downloaded,
+            total,
+            error,
+        },
+    );
+}
+
+async fn set_status(
+    pool: &SqlitePool,
+    id: DownloadId,
+    status: DownloadStatus,
+    error: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "UPDATE downloads SET status = ?, error = ?, updated_at = datetime('now') WHERE id = ?",
+    )
+    .bind(status.as_str())
+    .bind(error)
+    .bind(id.to_string())
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+async fn load(pool: &SqlitePool, id: DownloadId) -> Result<(Request, DownloadStatus), String> {
+    let row: Option<(String, String, Option<String>, String)> =
+        sqlx::query_as("SELECT url, dest, expected_sha256, status FROM downloads WHERE id = ?")
+            .bind(id.to_string())
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| e.to_string())?;
+    let (url, dest, expected_sha256, status) =
+        row.ok_or_else(|| format!("no download with id {id}"))?;
+
+    let request = Request {
+        id,
+        url,
+        dest: PathBuf::from(dest),
+        expected_sha256,
+    };
+    Ok((request, DownloadStatus::parse(&status)))
+}
+
+fn file_len(path: &Path) -> u64 {
+    std::fs::metadata(path).map(|m| m.len()).unwrap_or(0)
+}
+
+async fn verify_sha256(path: PathBuf, expected: &str) -> Result<(), String> {
+    let actual = tauri::async_runtime::spawn_blocking(move || {
+        let mut file = std::fs::File::open(path)?;
+        let mut hasher = Sha256::new();
+        let mut buf = vec![0; 1024 * 1024];
+        loop {
+            let read = file.read(&mut buf)?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&buf[..read]);
+        }
+        Ok::<_, std::io::Error>(hasher.finalize())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())?;
+
+    let actual: String = actual.iter().map(|b| format!("{b:02x}")).collect();
+    if !actual.eq_ignore_ascii_case(expected) {
+        return Err(format!(
+            "checksum mismatch: expected {expected}, got {actual}"
+        ));
+    }
+    Ok(())
+}
+
+/// Streams the body into the `.part` file, continuing from its current length.
+async fn transfer<R: Runtime>(
+    reporter: &Reporter<R>,
+    request: &Request,
+    pause: &AtomicBool,
+) -> Result<Outcome, String> {
+    let app = reporter.app();
+    let part = request.part_path();
+    let mut offset = file_len(&part);
+
+    let mut builder = reqwest::Client::new().get(&request.url);
+    if offset > 0 {
+        builder = builder.header(header::RANGE, format!("bytes={offset}-"));
+    }
+    let response = builder.send().await.map_err(|e| e.to_string())?;
+    // The whole body already made it to disk before the app stopped.
+    if offset > 0 && response.status() == StatusCode::RANGE_NOT_SATISFIABLE {
+        return Ok(Outcome::Completed(offset));
+    }
+    let mut response = response.error_for_status().map_err(|e| e.to_string())?;
+
+    // A server that ignores the range starts from scratch.
+    let resumed = response.status() == StatusCode::PARTIAL_CONTENT;
+    if !resumed {
+        offset = 0;
+    }
+    let total = response.content_length().map(|len| len + offset);
+
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(resumed)
+        .truncate(!resumed)
+        .open(&part)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let mut downloaded = offset;
+    let mut last_emit = Instant::now();
+    emit(
+        app,
+        request.id,
+        DownloadStatus::Downloading,
+        downloaded,
+        total,
+        None,
+    );
+
+    while let Some(chunk) = response.chunk().await.map_err(|e| e.to_string())? {
+        file.write_all(&chunk).await.map_err(|e| e.to_string())?;
+        downloaded += chunk.len() as u64;
+
+        if reporter.is_cancelled() {
+            return Ok(Outcome::Cancelled);
+        }
+        if pause.load(Ordering::Relaxed) {
+            file.flush().await.map_err(|e| e.to_string())?;
+            return Ok(Outcome::Paused(downloaded));
+        }
+
+        if last_emit.elapsed() >= PROGRESS_INTERVAL {
+            last_emit = Instant::now();
+            emit(
+                app,
+                request.id,
+                DownloadStatus::Downloading,
+                downloaded,
+                total,
+                None,
+            );
+        }
+    }
+
+    file.sync_all().await.map_err(|e| e.to_string())?;
+    Ok(Outcome::Completed(downloaded))
+}
+
+async fn finish(request: &Request, downloaded: u64) -> Result<u64, String> {
+    let part = request.part_path();
+    if let Some(expected) = &request.expected_sha256 {
+        if let Err(e) = verify_sha256(part.clone(), expected).await {
+            // A corrupt file can't be resumed into a valid one.
+            let _ = tokio::fs::remove_file(&part).await;
+            return Err(e);
+        }
+    }
+    tokio::fs::rename(&part, &request.dest)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(downloaded)
+}
+
+fn spawn<R: Runtime>(app: AppHandle<R>, request: Request) {
+    let id = request.id;
+    let part = request.part_path();
+    emit(
+        &app,
+        id,
+        DownloadStatus::Queued,
+        file_len(&part),
+        None,
+        None,
+    );
+
+    let pause = Arc::new(AtomicBool::new(false));
+    let paused = pause.clone();
+    let (task, ended) = tasks::submit(
+        &app,
+        TaskKind::Download,
+        Sink::None,
+        move |reporter| async move {
+            if paused.load(Ordering::Relaxed) {
+                return Ok(Outcome::Paused(file_len(&request.part_path())));
+            }
+            match transfer(&reporter, &request, &paused).await {
+                Ok(Outcome::Completed(downloaded)) => {
+                    finish(&request, downloaded).await.map(Outcome::Completed)
+                }
+                other => other,
+            }
+        },
+    );
+    app.state::<Downloads>()
+        .jobs
+        .lock()
+        .unwrap()
+        .insert(id, Job { task, pause });
+
+    tauri::async_runtime::spawn(async move {
+        // Closed when the task was cancelled before it got a slot.
+        let outcome = ended.await.unwrap_or(Ok(Outcome::Cancelled));
+        app.state::<Downloads>().jobs.lock().unwrap().remove(&id);
+
+        let db = app.state::<Db>();
+        let Ok(pool) = db.pool() else {
+            return;
+        };
+        let result = match outcome {
+            Ok(Outcome::Completed(downloaded)) => {
+                emit(
+                    &app,
+                    id,
+                    DownloadStatus::Completed,
+                    downloaded,
+                    Some(downloaded),
+                    None,
+                );
+                set_status(pool, id, DownloadStatus::Completed, None).await
+            }
+            Ok(Outcome::Paused(downloaded)) => {
+                emit(&app, id, DownloadStatus::Paused, downloaded, None, None);
+                set_status(pool, id, DownloadStatus::Paused, None).await
+            }
+            Ok(Outcome::Cancelled) => {
+                let _ = tokio::fs::remove_file(&part).await;
+                emit(&app, id, DownloadStatus::Cancelled, 0, None, None);
+                set_status(pool, id, DownloadStatus::Cancelled, None).await
+            }
+            Err(error) => {
+                tracing::warn!(%id, %error, "download failed");
+                emit(
+                    &app,
+                    id,
+                    DownloadStatus::Failed,
+                    file_len(&part),
+                    None,
+                    Some(error.clone()),
+                );
+                set_status(pool, id, DownloadStatus::Failed, Some(&error)).await
+            }
+        };
+        if let Err(e) = result {
+            tracing::error!(%id, error = %e, "failed to persist download state");
+        }
+    });
+}
+
+/// Downloads `url` to `dest` via a `.part` file, verifying `expected_sha256` (hex) before
+/// moving it into place. Progress is emitted on [`PROGRESS_EVENT`].
+#[tauri::command]
+pub async fn start_download<R: Runtime>(
+    url: String,
+    dest: String,
+    expected_sha256: Option<String>,
+    app: AppHandle<R>,
+    db: State<'_, Db>,
+) -> Result<DownloadId, String> {
+    let dest = scope::ensure_allowed(&app, dest.as_ref())?;
+    let id = Uuid::new_v4();
+
+    sqlx::query(
+        "INSERT INTO downloads (id, url, dest, expected_sha256, status) VALUES (?, ?, ?, ?, ?)",
+    )
+    .bind(id.to_string())
+    .bind(&url)
+    .bind(dest.to_string_lossy())
+    .bind(&expected_sha256)
+    .bind(DownloadStatus::Queued.as_str())
+    .execute(db.pool().map_err(|e| e.to_string())?)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    spawn(
+        app,
+        Request {
+            id,
+            url,
+            dest,
+            expected_sha256,
+        },
+    );
+    Ok(id)
+}
+
+#[tauri::command]
+pub fn pause_download(id: DownloadId, downloads: State<'_, Downloads>) -> Result<(), String> {
+    match downloads.jobs.lock().unwrap().get(&id) {
+        Some(job) => {
+            job.pause.store(true, Ordering::Relaxed);
+            Ok(())
+        }
+        None => Err(format!("download {id} is not running")),
+    }
+}
+
+/// Continues a paused or failed download, including ones left over from a previous run.
+#[tauri::command]
+pub async fn resume_download<R: Runtime>(
+    id: DownloadId,
+    app: AppHandle<R>,
+    db: State<'_, Db>,
+    downloads: State<'_, Downloads>,
+) -> Result<(), String> {
+    if downloads.jobs.lock().unwrap().contains_key(&id) {
+        return Err(format!("download {id} is already running"));
+    }
+
+    let pool = db.pool().map_err(|e| e.to_string())?;
+    let (request, status) = load(pool, id).await?;
+    if matches!(
+        status,
+        DownloadStatus::Completed | DownloadStatus::Cancelled
+    ) {
+        return Err(format!("download {id} is {}", status.as_str()));
+    }
+    // Re-check the destination in case the fs scope changed since it was started.
+    scope::ensure_allowed(&app, &request.dest)?;
+
+    set_status(pool, id, DownloadStatus::Queued, None)
+        .await
+        .map_err(|e| e.to_string())?;
+    spawn(app, request);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn cancel_download(
+    id: DownloadId,
+    db: State<'_, Db>,
+    downloads: State<'_, Downloads>,
+    tasks: State<'_, Tasks>,
+) -> Result<(), String> {
+    // A running download cleans up after itself at its next chunk.
+    let task = downloads.jobs.lock().unwrap().get(&id).map(|job| job.task);
+    if task.is_some_and(|task| tasks.cancel(task)) {
+        return Ok(());
+    }
+
+    let pool = db.pool().map_err(|e| e.to_string())?;
+    let (request, _) = load(pool, id).await?;
+    let _ = tokio::fs::remove_file(request.part_path()).await;
+    set_status(pool, id, DownloadStatus::Cancelled, None)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Downloads that haven't completed or been cancelled, so the frontend can offer to
+/// resume them after a restart.
+#[tauri::command]
+pub async fn list_downloads(
+    db: State<'_, Db>,
+    downloads: State<'_, Downloads>,
+) -> Result<Vec<DownloadInfo>, String> {
+    let rows: Vec<(String, String, String, String, Option<String>)> = sqlx::query_as(
+        "SELECT id, url, dest, status, error FROM downloads
+         WHERE status NOT IN ('completed', 'cancelled') ORDER BY created_at",
+    )
+    .fetch_all(db.pool().map_err(|e| e.to_string())?)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let jobs = downloads.jobs.lock().unwrap();
+    Ok(rows
+        .into_iter()
+        .filter_map(|(id, url, dest, status, error)| {
+            let id = Uuid::parse_str(&id).ok()?;
+            let status = match status.as_str() {
+                "queued" | "downloading" if jobs.contains_key(&id) => DownloadStatus::Downloading,
+                other => DownloadStatus::parse(other),
+            };
+            let downloaded = file_len(&part_path(dest.as_ref()));
+            Some(DownloadInfo {
+                id,
+                url,
+                dest,
+                status,
+                downloaded,
+                error,
+            })
+        })
+        .collect())
+}
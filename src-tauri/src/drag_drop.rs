@@ -0,0 +1,378 @@
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use tauri::{
+    AppHandle, DragDropEvent, Emitter, Manager, PhysicalPosition, Runtime, State, Window,
+    WindowEvent,
+};
+use tauri_plugin_fs::FsExt;
+use tauri_plugin_store::StoreExt;
+
+pub const FILE_EVENT: &str = "drag-drop://file";
+/// Every accepted file of one drop, with metadata, in a single event.
+pub const FILES_EVENT: &str = "drop://files";
+pub const HOVER_EVENT: &str = "drag-drop://hover";
+pub const CANCELLED_EVENT: &str = "drag-drop://cancelled";
+pub const REJECTED_EVENT: &str = "drag-drop://rejected";
+
+/// Preferences key holding the accepted extensions, as an array or a comma-separated
+/// string from the frontend's string-only adapter.
+const PREFERENCES_STORE: &str = "preferences.json";
+const ALLOWED_EXTENSIONS_KEY: &str = "drop_allowed_extensions";
+const DEFAULT_EXTENSIONS: &[&str] = &[
+    "md", "markdown", "txt", "html", "json", "pdf", "png", "jpg", "jpeg", "gif", "webp",
+];
+/// How deep dropped directories are walked and how many files one drop may yield.
+const MAX_DEPTH_KEY: &str = "drop_max_depth";
+const MAX_FILES_KEY: &str = "drop_max_files";
+const DEFAULT_MAX_DEPTH: u64 = 3;
+const DEFAULT_MAX_FILES: u64 = 500;
+
+/// Ingested copies live here, named by content hash.
+const INGEST_DIR: &str = "dropped";
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct DropPosition {
+    pub x: f64,
+    pub y: f64,
+}
+
+impl From<&PhysicalPosition<f64>> for DropPosition {
+    fn from(position: &PhysicalPosition<f64>) -> Self {
+        Self {
+            x: position.x,
+            y: position.y,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DroppedFile {
+    pub path: String,
+    pub mime_type: String,
+    pub size_bytes: u64,
+    pub position: DropPosition,
+    /// Pixel size, for images in a format the app can decode.
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// Unix milliseconds; not every file system records creation time.
+    pub created_ms: Option<u64>,
+    pub modified_ms: Option<u64>,
+}
+
+/// Paths accepted from drops so far. [`ingest_dropped_file`] only reads these, so the
+/// webview can't use it to reach arbitrary files.
+#[derive(Default)]
+pub struct DroppedPaths(Mutex<HashSet<PathBuf>>);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DragHover {
+    /// Only known when the drag enters the window; `Over` updates carry just a position.
+    pub paths: Option<Vec<String>>,
+    pub position: DropPosition,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RejectReason {
+    NotFound,
+    NotAFile,
+    ExtensionNotAllowed,
+    /// The drop yielded more files than the configured limit.
+    TooManyFiles,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RejectedFile {
+    pub path: String,
+    pub reason: RejectReason,
+}
+
+fn preference<R: Runtime>(window: &Window<R>, key: &str) -> Option<Value> {
+    window.store(PREFERENCES_STORE).ok()?.get(key)
+}
+
+fn limit<R: Runtime>(window: &Window<R>, key: &str, default: u64) -> u64 {
+    match preference(window, key) {
+        Some(Value::Number(n)) => n.as_u64(),
+        Some(Value::String(s)) => s.parse().ok(),
+        _ => None,
+    }
+    .unwrap_or(default)
+}
+
+fn allowed_extensions<R: Runtime>(window: &Window<R>) -> Vec<String> {
+    let configured = preference(window, ALLOWED_EXTENSIONS_KEY);
+    let extensions: Vec<String> = match configured {
+        Some(Value::Array(items)) => items
+            .iter()
+            .filter_map(|item| item.as_str().map(str::to_string))
+            .collect(),
+        Some(Value::String(list)) => list.split(',').map(str::to_string).collect(),
+        _ => return DEFAULT_EXTENSIONS.iter().map(|e| e.to_string()).collect(),
+    };
+    extensions
+        .iter()
+        .map(|e| e.trim().trim_start_matches('.').to_ascii_lowercase())
+        .filter(|e| !e.is_empty())
+        .collect()
+}
+
+/// Sniffs the content first; text formats have no magic bytes, so those fall back to
+/// the extension.
+fn mime_type(path: &Path, extension: &str) -> String {
+    if let Ok(Some(kind)) = infer::get_from_path(path) {
+        return kind.mime_type().to_string();
+    }
+    match extension {
+        "md" | "markdown" => "text/markdown",
+        "txt" => "text/plain",
+        "html" | "htm" => "text/html",
+        "json" => "application/json",
+        "csv" => "text/csv",
+        _ => "application/octet-stream",
+    }
+    .to_string()
+}
+
+fn check(path: &Path, allowed: &[String]) -> Result<(fs::Metadata, String), RejectReason> {
+    let metadata = path.metadata().map_err(|_| RejectReason::NotFound)?;
+    if !metadata.is_file() {
+        return Err(RejectReason::NotAFile);
+    }
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_ascii_lowercase)
+        .unwrap_or_default();
+    if !allowed.contains(&extension) {
+        return Err(RejectReason::ExtensionNotAllowed);
+    }
+    Ok((metadata, extension))
+}
+
+/// Expands dropped directories into the files inside, breadth first down to `max_depth`.
+/// Symlinked directories aren't followed, so a link cycle can't trap the walk.
+fn expand(paths: &[PathBuf], max_depth: u64) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut level: Vec<PathBuf> = paths.to_vec();
+    for depth in 0..=max_depth {
+        let mut next = Vec::new();
+        for path in level {
+            let is_dir = fs::symlink_metadata(&path).is_ok_and(|m| m.is_dir());
+            if !is_dir {
+                files.push(path);
+            } else if depth < max_depth {
+                let Ok(entries) = fs::read_dir(&path) else {
+                    continue;
+                };
+                let mut children: Vec<PathBuf> =
+                    entries.filter_map(Result::ok).map(|e| e.path()).collect();
+                children.sort();
+                next.extend(children);
+            }
+        }
+        level = next;
+    }
+    files
+}
+
+fn unix_ms(time: io::Result<SystemTime>) -> Option<u64> {
+    Some(time.ok()?.duration_since(UNIX_EPOCH).ok()?.as_millis() as u64)
+}
+
+fn describe(
+    path: &Path,
+    metadata: &fs::Metadata,
+    extension: &str,
+    position: DropPosition,
+) -> DroppedFile {
+    let mime_type = mime_type(path, extension);
+    let dimensions = mime_type
+        .starts_with("image/")
+        .then(|| {
+            image::ImageReader::open(path)
+                .ok()?
+                .with_guessed_format()
+                .ok()?
+                .into_dimensions()
+                .ok()
+        })
+        .flatten();
+
+    DroppedFile {
+        path: path.to_string_lossy().into_owned(),
+        mime_type,
+        size_bytes: metadata.len(),
+        position,
+        width: dimensions.map(|(w, _)| w),
+        height: dimensions.map(|(_, h)| h),
+        created_ms: unix_ms(metadata.created()),
+        modified_ms: unix_ms(metadata.modified()),
+    }
+}
+
+fn reject<R: Runtime>(window: &Window<R>, path: &Path, reason: RejectReason) {
+    let path = path.to_string_lossy().into_owned();
+    tracing::debug!(%path, ?reason, "rejected dropped file");
+    let _ = window.emit_to(
+        window.label(),
+        REJECTED_EVENT,
+        RejectedFile { path, reason },
+    );
+}
+
+/// Walking directories and sniffing files can take a while, so this runs off the event
+/// loop.
+fn on_drop<R: Runtime>(window: &Window<R>, paths: &[PathBuf], position: DropPosition) {
+    let window = window.clone();
+    let paths = paths.to_vec();
+    tauri::async_runtime::spawn_blocking(move || {
+        let allowed = allowed_extensions(&window);
+        let max_depth = limit(&window, MAX_DEPTH_KEY, DEFAULT_MAX_DEPTH);
+        let max_files = limit(&window, MAX_FILES_KEY, DEFAULT_MAX_FILES) as usize;
+
+        let mut files = Vec::new();
+        for path in expand(&paths, max_depth) {
+            let (metadata, extension) = match check(&path, &allowed) {
+                Ok(checked) => checked,
+                Err(reason) => {
+                    reject(&window, &path, reason);
+                    continue;
+                }
+            };
+            if files.len() >= max_files {
+                reject(&window, &path, RejectReason::TooManyFiles);
+                continue;
+            }
+
+            let file = describe(&path, &metadata, &extension, position);
+            let _ = window.emit_to(window.label(), FILE_EVENT, file.clone());
+            files.push((path, file));
+        }
+
+        if let Some(dropped) = window.try_state::<DroppedPaths>() {
+            let mut dropped = dropped.0.lock().unwrap();
+            dropped.extend(files.iter().map(|(path, _)| path.clone()));
+        }
+        let files: Vec<DroppedFile> = files.into_iter().map(|(_, file)| file).collect();
+        let _ = window.emit_to(window.label(), FILES_EVENT, files);
+    });
+}
+
+/// Events go to the window that received the drop only.
+pub fn on_window_event<R: Runtime>(window: &Window<R>, event: &WindowEvent) {
+    let WindowEvent::DragDrop(event) = event else {
+        return;
+    };
+    let label = window.label();
+    match event {
+        DragDropEvent::Enter { paths, position } => {
+            let paths = paths
+                .iter()
+                .map(|p| p.to_string_lossy().into_owned())
+                .collect();
+            let _ = window.emit_to(
+                label,
+                HOVER_EVENT,
+                DragHover {
+                    paths: Some(paths),
+                    position: position.into(),
+                },
+            );
+        }
+        DragDropEvent::Over { position } => {
+            let _ = window.emit_to(
+                label,
+                HOVER_EVENT,
+                DragHover {
+                    paths: None,
+                    position: position.into(),
+                },
+            );
+        }
+        DragDropEvent::Drop { paths, position } => on_drop(window, paths, position.into()),
+        DragDropEvent::Leave => {
+            let _ = window.emit_to(label, CANCELLED_EVENT, ());
+        }
+        _ => {}
+    }
+}
+
+/// Copies `from` into `dir` under its SHA-256, keeping the extension. Identical files
+/// dropped twice share one copy.
+fn copy_by_hash(from: &Path, dir: &Path) -> io::Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+    let tmp = dir.join(format!(".{}.tmp", uuid::Uuid::new_v4()));
+
+    let mut hasher = Sha256::new();
+    let mut input = File::open(from)?;
+    let mut output = File::create(&tmp)?;
+    let mut buf = vec![0; 64 * 1024];
+    loop {
+        let n = input.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+        output.write_all(&buf[..n])?;
+    }
+    output.sync_all()?;
+
+    let hash: String = hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect();
+    let name = match from.extension().and_then(|e| e.to_str()) {
+        Some(extension) => format!("{hash}.{}", extension.to_ascii_lowercase()),
+        None => hash,
+    };
+    let dest = dir.join(name);
+    fs::rename(&tmp, &dest).inspect_err(|_| {
+        let _ = fs::remove_file(&tmp);
+    })?;
+    Ok(dest)
+}
+
+/// Makes a dropped file available to the webview: copied into the app data dir when
+/// `copy_into_appdata` is set, otherwise added to the fs scope where it is. Only paths
+/// from an earlier drop are accepted.
+#[tauri::command]
+pub async fn ingest_dropped_file<R: Runtime>(
+    path: String,
+    copy_into_appdata: bool,
+    app: AppHandle<R>,
+    dropped: State<'_, DroppedPaths>,
+) -> Result<String, String> {
+    let path = PathBuf::from(path);
+    if !dropped.0.lock().unwrap().contains(&path) {
+        return Err(format!("{} was not dropped onto the app", path.display()));
+    }
+
+    let ingested = if copy_into_appdata {
+        let dir = app
+            .path()
+            .app_data_dir()
+            .map_err(|e| e.to_string())?
+            .join(INGEST_DIR);
+        let from = path.clone();
+        tauri::async_runtime::spawn_blocking(move || copy_by_hash(&from, &dir))
+            .await
+            .map_err(|e| e.to_string())?
+            .map_err(|e| format!("{}: {e}", path.display()))?
+    } else {
+        app.fs_scope()
+            .allow_file(&path)
+            .map_err(|e| e.to_string())?;
+        path
+    };
+    Ok(ingested.to_string_lossy().into_owned())
+}
@@ -0,0 +1,370 @@
+//! Dumps query results to CSV or JSON Lines, writing each row as it's read so exports
+//! of any size run in constant memory. Each export runs as a task, so it waits for a
+//! slot, shows in `list_tasks`, and stops between rows when `cancel_task` is called.
+
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use futures::TryStreamExt;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::pool::PoolConnection;
+use sqlx::{Column, Executor, Sqlite, Statement};
+use tauri::{AppHandle, Emitter, Manager, Runtime};
+use tauri_plugin_dialog::DialogExt;
+use tokio::io::{AsyncWriteExt, BufWriter};
+
+use crate::db::{self, Db, DbError};
+use crate::scope;
+use crate::tasks::{self, Reporter, Sink, TaskKind};
+
+pub const PROGRESS_EVENT: &str = "export://progress";
+
+const PROGRESS_INTERVAL: u64 = 5_000;
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+/// SQLite's result code for writes refused by `PRAGMA query_only`.
+const SQLITE_READONLY: &str = "8";
+
+#[derive(Debug, thiserror::Error, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "camelCase")]
+pub enum ExportError {
+    #[error("no destination was chosen")]
+    Cancelled,
+    #[error("the export was cancelled")]
+    Stopped,
+    #[error("only SELECT queries can be exported")]
+    NotSelect,
+    #[error("invalid option: {0}")]
+    InvalidOption(String),
+    #[error("{0}")]
+    Scope(String),
+    #[error("export io failed: {0}")]
+    Io(String),
+    #[error(transparent)]
+    Db(#[from] DbError),
+}
+
+impl From<std::io::Error> for ExportError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e.to_string())
+    }
+}
+
+impl From<sqlx::Error> for ExportError {
+    fn from(e: sqlx::Error) -> Self {
+        let readonly = e
+            .as_database_error()
+            .and_then(|e| e.code())
+            .is_some_and(|code| code == SQLITE_READONLY);
+        if readonly {
+            Self::NotSelect
+        } else {
+            Self::Db(e.into())
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ExportFormat {
+    Csv,
+    Jsonl,
+}
+
+impl ExportFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Jsonl => "jsonl",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ExportOptions {
+    /// Write the column names as the first CSV line.
+    pub headers: bool,
+    pub delimiter: char,
+    /// Start CSV output with a UTF-8 byte order mark, without which Excel assumes the
+    /// system code page.
+    pub bom: bool,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self {
+            headers: true,
+            delimiter: ',',
+            bom: false,
+        }
+    }
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportProgress {
+    rows: u64,
+    bytes: u64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportSummary {
+    pub path: String,
+    pub rows: u64,
+    pub bytes: u64,
+    pub duration_ms: u64,
+}
+
+/// Skips whitespace and comments to the statement's first keyword. Writes hidden
+/// behind a `WITH` are caught by `query_only` instead.
+fn ensure_select(sql: &str) -> Result<(), ExportError> {
+    let mut rest = sql.trim_start();
+    loop {
+        if let Some(comment) = rest.strip_prefix("--") {
+            rest = comment.split_once('\n').map_or("", |(_, rest)| rest);
+        } else if let Some(comment) = rest.strip_prefix("/*") {
+            rest = comment.split_once("*/").map_or("", |(_, rest)| rest);
+        } else {
+            break;
+        }
+        rest = rest.trim_start();
+    }
+    let keyword = rest
+        .split(|c: char| !c.is_ascii_alphabetic())
+        .next()
+        .unwrap_or_default();
+    if ["select", "with", "values"]
+        .iter()
+        .any(|k| keyword.eq_ignore_ascii_case(k))
+    {
+        Ok(())
+    } else {
+        Err(ExportError::NotSelect)
+    }
+}
+
+/// Quotes a CSV field per RFC 4180 when it holds the delimiter, a quote, or a newline.
+fn push_csv_field(line: &mut String, field: &str, delimiter: char) {
+    if field.contains([delimiter, '"', '\n', '\r']) {
+        line.push('"');
+        line.push_str(&field.replace('"', "\"\""));
+        line.push('"');
+    } else {
+        line.push_str(field);
+    }
+}
+
+fn push_csv_line<'a>(
+    line: &mut String,
+    fields: impl Iterator<Item = Cow<'a, str>>,
+    delimiter: char,
+) {
+    for (i, field) in fields.enumerate() {
+        if i > 0 {
+            line.push(delimiter);
+        }
+        push_csv_field(line, &field, delimiter);
+    }
+    line.push_str("\r\n");
+}
+
+/// `NULL` is an empty field; blobs and other non-text values are written as JSON.
+fn csv_text(value: Option<&Value>) -> Cow<'_, str> {
+    match value {
+        None | Some(Value::Null) => Cow::Borrowed(""),
+        Some(Value::String(s)) => Cow::Borrowed(s),
+        Some(other) => Cow::Owned(other.to_string()),
+    }
+}
+
+async fn pick_destination<R: Runtime>(
+    app: &AppHandle<R>,
+    format: ExportFormat,
+) -> Result<PathBuf, ExportError> {
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    let extension = format.extension();
+    app.dialog()
+        .file()
+        .set_file_name(format!("export.{extension}"))
+        .add_filter(extension.to_uppercase(), &[extension])
+        .save_file(move |path| {
+            let _ = tx.send(path);
+        });
+
+    rx.await
+        .ok()
+        .flatten()
+        .ok_or(ExportError::Cancelled)?
+        .into_path()
+        .map_err(|e| ExportError::Io(e.to_string()))
+}
+
+/// Streams the query into `tmp`, returning the rows and bytes written.
+async fn write_rows<R: Runtime>(
+    reporter: &Reporter<R>,
+    conn: &mut PoolConnection<Sqlite>,
+    sql: &str,
+    params: &[Value],
+    tmp: &Path,
+    format: ExportFormat,
+    options: &ExportOptions,
+) -> Result<(u64, u64), ExportError> {
+    let statement = conn.prepare(sql).await?;
+    let columns: Vec<String> = statement
+        .columns()
+        .iter()
+        .map(|column| column.name().to_string())
+        .collect();
+    let mut query = statement.query();
+    for param in params {
+        query = db::bind_value(query, param);
+    }
+
+    let mut out = BufWriter::new(tokio::fs::File::create(tmp).await?);
+    let mut bytes = 0u64;
+    let mut line = String::new();
+    if let ExportFormat::Csv = format {
+        if options.bom {
+            out.write_all(UTF8_BOM).await?;
+            bytes += UTF8_BOM.len() as u64;
+        }
+        if options.headers {
+            let names = columns.iter().map(|name| Cow::Borrowed(name.as_str()));
+            push_csv_line(&mut line, names, options.delimiter);
+        }
+    }
+
+    let mut rows = query.fetch(&mut **conn);
+    let mut written = 0u64;
+    while let Some(row) = rows.try_next().await? {
+        if reporter.is_cancelled() {
+            return Err(ExportError::Stopped);
+        }
+        let row = db::row_to_json(&row)?;
+        match format {
+            ExportFormat::Csv => {
+                let fields = columns.iter().map(|name| csv_text(row.get(name)));
+                push_csv_line(&mut line, fields, options.delimiter);
+            }
+            ExportFormat::Jsonl => {
+                line.push_str(&row.to_string());
+                line.push('\n');
+            }
+        }
+        out.write_all(line.as_bytes()).await?;
+        bytes += line.len() as u64;
+        line.clear();
+
+        written += 1;
+        if written.is_multiple_of(PROGRESS_INTERVAL) {
+            let _ = reporter.app().emit(
+                PROGRESS_EVENT,
+                ExportProgress {
+                    rows: written,
+                    bytes,
+                },
+            );
+        }
+    }
+    // Headers of an empty result are all that's left in `line`.
+    out.write_all(line.as_bytes()).await?;
+    bytes += line.len() as u64;
+    out.flush().await?;
+
+    let _ = reporter.app().emit(
+        PROGRESS_EVENT,
+        ExportProgress {
+            rows: written,
+            bytes,
+        },
+    );
+    Ok((written, bytes))
+}
+
+/// Writes the export through a temporary file next to `dest`, renaming it into place
+/// only once every row is written.
+async fn export<R: Runtime>(
+    reporter: Reporter<R>,
+    sql: String,
+    params: Vec<Value>,
+    dest: PathBuf,
+    format: ExportFormat,
+    options: ExportOptions,
+) -> Result<ExportSummary, ExportError> {
+    let started = Instant::now();
+    let mut tmp = dest.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+
+    let db = reporter.app().state::<Db>();
+    let mut conn = db.pool()?.acquire().await?;
+    sqlx::query("PRAGMA query_only = ON")
+        .execute(&mut *conn)
+        .await?;
+    let result = write_rows(&reporter, &mut conn, &sql, &params, &tmp, format, &options).await;
+    if let Err(e) = sqlx::query("PRAGMA query_only = OFF")
+        .execute(&mut *conn)
+        .await
+    {
+        // Never hand a read-only connection back to the pool.
+        tracing::warn!(error = %e, "failed to reset query_only; closing connection");
+        drop(conn.detach());
+    }
+
+    let (rows, bytes) = match result {
+        Ok(written) => written,
+        Err(e) => {
+            let _ = tokio::fs::remove_file(&tmp).await;
+            return Err(e);
+        }
+    };
+    if let Err(e) = tokio::fs::rename(&tmp, &dest).await {
+        let _ = tokio::fs::remove_file(&tmp).await;
+        return Err(e.into());
+    }
+
+    let duration_ms = started.elapsed().as_millis() as u64;
+    tracing::info!(dest = %dest.display(), rows, bytes, duration_ms, "query exported");
+    Ok(ExportSummary {
+        path: dest.to_string_lossy().into_owned(),
+        rows,
+        bytes,
+        duration_ms,
+    })
+}
+
+/// Exports the rows of a read-only query, emitting [`PROGRESS_EVENT`] as it goes.
+/// Without `dest_path` the user picks the file in a save dialog. The connection runs
+/// with `query_only` set, so nothing that modifies the database can slip through.
+#[tauri::command]
+pub async fn export_query<R: Runtime>(
+    sql: String,
+    params: Vec<Value>,
+    dest_path: Option<String>,
+    format: ExportFormat,
+    options: Option<ExportOptions>,
+    app: AppHandle<R>,
+) -> Result<ExportSummary, ExportError> {
+    let options = options.unwrap_or_default();
+    if matches!(options.delimiter, '"' | '\n' | '\r') {
+        return Err(ExportError::InvalidOption(format!(
+            "{:?} can't be a delimiter",
+            options.delimiter
+        )));
+    }
+    ensure_select(&sql)?;
+
+    // A path typed into the OS dialog is the user's choice; one from the frontend isn't.
+    let dest = match dest_path {
+        Some(path) => scope::ensure_allowed(&app, Path::new(&path)).map_err(ExportError::Scope)?,
+        None => pick_destination(&app, format).await?,
+    };
+
+    let (_, ended) = tasks::submit(&app, TaskKind::Export, Sink::None, move |reporter| {
+        export(reporter, sql, params, dest, format, options)
+    });
+    ended.await.unwrap_or(Err(ExportError::Stopped))
+}
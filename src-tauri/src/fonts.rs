@@ -0,0 +1,212 @@
+//! Installed fonts, for the editor's font picker and for embedding into exports. The
+//! webview can't enumerate fonts portably, so `fontdb` scans the platform's font
+//! directories (and fontconfig's, on Linux) instead.
+//!
+//! Scanning reads every font's tables and takes hundreds of milliseconds on Windows, so
+//! it happens once, off the main thread, during setup; [`FONTS_READY_EVENT`] is emitted
+//! when the cache is warm, and commands that arrive earlier wait for it. Fonts installed
+//! while running appear after [`refresh_font_cache`].
+
+use std::cmp::Reverse;
+use std::sync::Arc;
+use std::time::Instant;
+
+use fontdb::{Database, Source};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, Runtime, State};
+use tokio::sync::watch;
+use ttf_parser::{Face, Permissions};
+
+/// Emitted with the number of fonts each time the cache is (re)built.
+pub const FONTS_READY_EVENT: &str = "fonts://ready";
+
+/// The weight asked for when [`get_font_data`] isn't given one.
+const REGULAR_WEIGHT: u16 = 400;
+
+#[derive(Debug, thiserror::Error, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "camelCase")]
+pub enum FontError {
+    #[error("no installed font matches {0}")]
+    NotFound(String),
+    /// The font's OS/2 table sets restricted-license embedding.
+    #[error("{0} doesn't allow embedding")]
+    NotEmbeddable(String),
+    #[error("malformed font: {0}")]
+    Malformed(String),
+    #[error("i/o error: {0}")]
+    Io(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FontStyle {
+    Normal,
+    Italic,
+    Oblique,
+}
+
+impl From<fontdb::Style> for FontStyle {
+    fn from(style: fontdb::Style) -> Self {
+        match style {
+            fontdb::Style::Normal => Self::Normal,
+            fontdb::Style::Italic => Self::Italic,
+            fontdb::Style::Oblique => Self::Oblique,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FontInfo {
+    /// The English name where the font has one.
+    pub family: String,
+    pub style: FontStyle,
+    /// 100 to 900, as in CSS.
+    pub weight: u16,
+    pub monospace: bool,
+    /// The file it's in, which for a collection (`.ttc`) holds other faces too.
+    pub path: String,
+}
+
+struct FontCache {
+    db: Database,
+    fonts: Vec<FontInfo>,
+}
+
+pub struct Fonts {
+    /// `None` until the first scan finishes.
+    cache: watch::Sender<Option<Arc<FontCache>>>,
+}
+
+impl Default for Fonts {
+    fn default() -> Self {
+        Self {
+            cache: watch::Sender::new(None),
+        }
+    }
+}
+
+impl Fonts {
+    async fn cache(&self) -> Arc<FontCache> {
+        let mut cache = self.cache.subscribe();
+        let warm = cache
+            .wait_for(Option::is_some)
+            .await
+            .expect("the sender lives as long as the state");
+        warm.clone().expect("waited for a warm cache")
+    }
+}
+
+/// Blocks for the whole scan.
+fn scan() -> FontCache {
+    let started = Instant::now();
+    let mut db = Database::new();
+    db.load_system_fonts();
+    let mut fonts: Vec<FontInfo> = db
+        .faces()
+        .filter_map(|face| {
+            let path = match &face.source {
+                Source::File(path) | Source::SharedFile(path, _) => path.display().to_string(),
+                Source::Binary(_) => return None,
+            };
+            Some(FontInfo {
+                family: face.families.first()?.0.clone(),
+                style: face.style.into(),
+                weight: face.weight.0,
+                monospace: face.monospaced,
+                path,
+            })
+        })
+        .collect();
+    fonts.sort_by(|a, b| {
+        a.family
+            .to_lowercase()
+            .cmp(&b.family.to_lowercase())
+            .then(a.style.cmp(&b.style))
+            .then(a.weight.cmp(&b.weight))
+    });
+    tracing::info!(
+        fonts = fonts.len(),
+        duration_ms = started.elapsed().as_millis() as u64,
+        "font cache warm"
+    );
+    FontCache { db, fonts }
+}
+
+async fn rebuild<R: Runtime>(app: &AppHandle<R>) -> Result<Arc<FontCache>, FontError> {
+    let cache = tauri::async_runtime::spawn_blocking(scan)
+        .await
+        .map(Arc::new)
+        .map_err(|e| FontError::Io(e.to_string()))?;
+    app.state::<Fonts>().cache.send_replace(Some(cache.clone()));
+    let _ = app.emit(FONTS_READY_EVENT, cache.fonts.len());
+    Ok(cache)
+}
+
+/// Starts the first scan; it finishes after setup does.
+pub fn init<R: Runtime>(app: &AppHandle<R>) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = rebuild(&app).await {
+            tracing::warn!(error = %e, "font scan failed");
+        }
+    });
+}
+
+/// Sorted by family, then style and weight.
+#[tauri::command]
+pub async fn list_system_fonts(fonts: State<'_, Fonts>) -> Result<Vec<FontInfo>, FontError> {
+    Ok(fonts.cache().await.fonts.clone())
+}
+
+/// Scans again and returns the new list.
+#[tauri::command]
+pub async fn refresh_font_cache<R: Runtime>(app: AppHandle<R>) -> Result<Vec<FontInfo>, FontError> {
+    Ok(rebuild(&app).await?.fonts.clone())
+}
+
+/// The whole font file behind `family` in `style`, at the installed weight closest to
+/// `weight` (regular by default), for embedding into an export. Family names match
+/// case-insensitively; the style has to match exactly, since a synthesized italic
+/// embedded under the real one's name would render wrong.
+#[tauri::command]
+pub async fn get_font_data(
+    family: String,
+    style: FontStyle,
+    weight: Option<u16>,
+    fonts: State<'_, Fonts>,
+) -> Result<Vec<u8>, FontError> {
+    let cache = fonts.cache().await;
+    let weight = weight.unwrap_or(REGULAR_WEIGHT);
+    let name = format!("{family} ({style:?}, {weight})");
+    let face = cache
+        .db
+        .faces()
+        .filter(|face| {
+            FontStyle::from(face.style) == style
+                && face
+                    .families
+                    .iter()
+                    .any(|(name, _)| name.eq_ignore_ascii_case(&family))
+        })
+        // Closest weight, the heavier of two equally close.
+        .min_by_key(|face| (face.weight.0.abs_diff(weight), Reverse(face.weight.0)))
+        .map(|face| face.id)
+        .ok_or_else(|| FontError::NotFound(name.clone()))?;
+
+    tauri::async_runtime::spawn_blocking(move || {
+        cache
+            .db
+            .with_face_data(face, |data, index| {
+                let parsed = Face::parse(data, index)
+                    .map_err(|e| FontError::Malformed(format!("{name}: {e}")))?;
+                if parsed.permissions() == Some(Permissions::Restricted) {
+                    return Err(FontError::NotEmbeddable(name.clone()));
+                }
+                Ok(data.to_vec())
+            })
+            .ok_or_else(|| FontError::Io(format!("could not read {name}")))?
+    })
+    .await
+    .map_err(|e| FontError::Io(e.to_string()))?
+}
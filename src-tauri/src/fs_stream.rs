@@ -0,0 +1,278 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use serde::Serialize;
+use tauri::ipc::Channel;
+use tauri::{AppHandle, Emitter, Manager, Runtime, State};
+
+use crate::scope;
+
+pub const PROGRESS_EVENT: &str = "fs-stream://progress";
+pub const DONE_EVENT: &str = "fs-stream://done";
+pub const ERROR_EVENT: &str = "fs-stream://error";
+
+const DEFAULT_CHUNK_SIZE: usize = 1024 * 1024;
+
+pub type TransferId = u64;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FileChunk {
+    pub offset: u64,
+    pub total: u64,
+    pub bytes: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TransferProgress {
+    pub id: TransferId,
+    pub percent: f32,
+    pub bytes: u64,
+    pub total: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TransferDone {
+    pub id: TransferId,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TransferFailed {
+    pub id: TransferId,
+    pub error: String,
+}
+
+enum Transfer {
+    /// A read or copy running on a blocking thread; it polls the flag between chunks.
+    Task { cancelled: Arc<AtomicBool> },
+    /// A write fed chunk by chunk from the frontend.
+    Write {
+        file: Arc<Mutex<File>>,
+        path: PathBuf,
+    },
+}
+
+/// In-flight transfers keyed by the token handed back to the frontend.
+#[derive(Default)]
+pub struct Transfers {
+    next_id: AtomicU64,
+    active: Mutex<HashMap<TransferId, Transfer>>,
+}
+
+impl Transfers {
+    fn next_id(&self) -> TransferId {
+        self.next_id.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    fn start_task(&self) -> (TransferId, Arc<AtomicBool>) {
+        let id = self.next_id();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        self.active.lock().unwrap().insert(
+            id,
+            Transfer::Task {
+                cancelled: cancelled.clone(),
+            },
+        );
+        (id, cancelled)
+    }
+
+    fn finish(&self, id: TransferId) {
+        self.active.lock().unwrap().remove(&id);
+    }
+}
+
+/// Runs `work` on a blocking thread and reports its outcome as a done or error event.
+fn spawn_transfer<R, F>(app: AppHandle<R>, id: TransferId, work: F)
+where
+    R: Runtime,
+    F: FnOnce() -> Result<(), String> + Send + 'static,
+{
+    tauri::async_runtime::spawn_blocking(move || {
+        let result = work();
+        app.state::<Transfers>().finish(id);
+        let _ = match result {
+            Ok(()) => app.emit(DONE_EVENT, TransferDone { id }),
+            Err(error) => app.emit(ERROR_EVENT, TransferFailed { id, error }),
+        };
+    });
+}
+
+fn check_cancelled(cancelled: &AtomicBool) -> Result<(), String> {
+    if cancelled.load(Ordering::Relaxed) {
+        return Err("cancelled".into());
+    }
+    Ok(())
+}
+
+/// Streams a file to `on_chunk` without loading it into memory. The transfer is done
+/// when `offset + bytes.length == total`, or when [`DONE_EVENT`] fires for the token.
+#[tauri::command]
+pub fn read_file_stream<R: Runtime>(
+    path: String,
+    chunk_size: Option<usize>,
+    on_chunk: Channel<FileChunk>,
+    app: AppHandle<R>,
+    transfers: State<'_, Transfers>,
+) -> Result<TransferId, String> {
+    let path = scope::ensure_allowed(&app, path.as_ref())?;
+    let mut file = File::open(&path).map_err(|e| e.to_string())?;
+    let total = file.metadata().map_err(|e| e.to_string())?.len();
+    let chunk_size = chunk_size.unwrap_or(DEFAULT_CHUNK_SIZE).max(1);
+
+    let (id, cancelled) = transfers.start_task();
+    spawn_transfer(app, id, move || {
+        let mut offset = 0;
+        let mut buf = vec![0; chunk_size];
+        loop {
+            check_cancelled(&cancelled)?;
+            let read = file.read(&mut buf).map_err(|e| e.to_string())?;
+            if read == 0 {
+                return Ok(());
+            }
+            on_chunk
+                .send(FileChunk {
+                    offset,
+                    total,
+                    bytes: buf[..read].to_vec(),
+                })
+                .map_err(|e| e.to_string())?;
+            offset += read as u64;
+        }
+    });
+
+    Ok(id)
+}
+
+/// Opens a destination for [`write_file_chunk`]. Close it with [`finish_write_stream`],
+/// or abort with [`cancel_transfer`] to delete the partial file.
+#[tauri::command]
+pub fn write_file_stream<R: Runtime>(
+    path: String,
+    app: AppHandle<R>,
+    transfers: State<'_, Transfers>,
+) -> Result<TransferId, String> {
+    let path = scope::ensure_allowed(&app, path.as_ref())?;
+    let file = File::create(&path).map_err(|e| e.to_string())?;
+
+    let id = transfers.next_id();
+    transfers.active.lock().unwrap().insert(
+        id,
+        Transfer::Write {
+            file: Arc::new(Mutex::new(file)),
+            path,
+        },
+    );
+    Ok(id)
+}
+
+fn open_write(transfers: &Transfers, id: TransferId) -> Result<Arc<Mutex<File>>, String> {
+    match transfers.active.lock().unwrap().get(&id) {
+        Some(Transfer::Write { file, .. }) => Ok(file.clone()),
+        _ => Err(format!("no open write stream with id {id}")),
+    }
+}
+
+#[tauri::command]
+pub fn write_file_chunk(
+    id: TransferId,
+    bytes: Vec<u8>,
+    transfers: State<'_, Transfers>,
+) -> Result<(), String> {
+    let file = open_write(&transfers, id)?;
+    let mut file = file.lock().unwrap();
+    file.write_all(&bytes).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn finish_write_stream(id: TransferId, transfers: State<'_, Transfers>) -> Result<(), String> {
+    let file = open_write(&transfers, id)?;
+    transfers.finish(id);
+    let file = file.lock().unwrap();
+    file.sync_all().map_err(|e| e.to_string())
+}
+
+/// Copies `src` to `dst`, emitting [`PROGRESS_EVENT`] after each chunk. A failed or
+/// cancelled copy removes the partial destination.
+#[tauri::command]
+pub fn copy_file_with_progress<R: Runtime>(
+    src: String,
+    dst: String,
+    app: AppHandle<R>,
+    transfers: State<'_, Transfers>,
+) -> Result<TransferId, String> {
+    let src = scope::ensure_allowed(&app, src.as_ref())?;
+    let dst = scope::ensure_allowed(&app, dst.as_ref())?;
+    let mut reader = File::open(&src).map_err(|e| e.to_string())?;
+    let total = reader.metadata().map_err(|e| e.to_string())?.len();
+    let mut writer = File::create(&dst).map_err(|e| e.to_string())?;
+
+    let (id, cancelled) = transfers.start_task();
+    let emitter = app.clone();
+    spawn_transfer(app, id, move || {
+        let result = (|| {
+            let mut copied = 0;
+            let mut buf = vec![0; DEFAULT_CHUNK_SIZE];
+            loop {
+                check_cancelled(&cancelled)?;
+                let read = reader.read(&mut buf).map_err(|e| e.to_string())?;
+                if read == 0 {
+                    return writer.sync_all().map_err(|e| e.to_string());
+                }
+                writer.write_all(&buf[..read]).map_err(|e| e.to_string())?;
+                copied += read as u64;
+
+                let percent = if total == 0 {
+                    100.0
+                } else {
+                    copied as f32 / total as f32 * 100.0
+                };
+                let _ = emitter.emit(
+                    PROGRESS_EVENT,
+                    TransferProgress {
+                        id,
+                        percent,
+                        bytes: copied,
+                        total,
+                    },
+                );
+            }
+        })();
+
+        if result.is_err() {
+            drop(writer);
+            remove_partial(&dst);
+        }
+        result
+    });
+
+    Ok(id)
+}
+
+fn remove_partial(path: &Path) {
+    if let Err(e) = std::fs::remove_file(path) {
+        tracing::warn!(path = %path.display(), error = %e, "failed to remove partial file");
+    }
+}
+
+/// Aborts a read, write or copy. Writes and copies delete their partial destination.
+#[tauri::command]
+pub fn cancel_transfer(id: TransferId, transfers: State<'_, Transfers>) -> Result<(), String> {
+    let mut active = transfers.active.lock().unwrap();
+    match active.get(&id) {
+        Some(Transfer::Task { cancelled }) => {
+            // The task cleans up and removes itself at its next chunk.
+            cancelled.store(true, Ordering::Relaxed);
+        }
+        Some(Transfer::Write { .. }) => {
+            if let Some(Transfer::Write { file, path }) = active.remove(&id) {
+                drop(file);
+                remove_partial(&path);
+            }
+        }
+        None => return Err(format!("no active transfer with id {id}")),
+    }
+    Ok(())
+}
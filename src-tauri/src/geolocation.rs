@@ -0,0 +1,703 @@
+//! Device position from the OS location service: `tauri-plugin-geolocation` on iOS and
+//! Android, GeoClue2 on Linux, `Windows.Devices.Geolocation` on Windows, and Core
+//! Location on macOS.
+//!
+//! The desktop services report fixes as they arrive rather than on request, so one-off
+//! reads and watches both poll the latest fix.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+#[cfg(desktop)]
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Runtime, State};
+
+pub const POSITION_EVENT: &str = "geo://position";
+
+const DEFAULT_TIMEOUT_MS: u64 = 10_000;
+/// How often a watch checks for a new fix on desktop.
+#[cfg(desktop)]
+const WATCH_INTERVAL: Duration = Duration::from_secs(1);
+/// How often a one-off read checks for a fix on desktop.
+#[cfg(desktop)]
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Clone, thiserror::Error, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "camelCase")]
+pub enum GeoError {
+    #[error("location permission was denied")]
+    PermissionDenied,
+    #[error("position unavailable: {0}")]
+    PositionUnavailable(String),
+    #[error("timed out waiting for a position")]
+    Timeout,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GeoOptions {
+    /// Ask for GPS-level accuracy where the device has it, at the cost of battery.
+    #[serde(default)]
+    pub enable_high_accuracy: bool,
+    /// Defaults to 10 seconds. Only applies to [`get_current_position`].
+    pub timeout_ms: Option<u64>,
+    /// How old a previously obtained fix may be and still be returned; 0 always waits
+    /// for a fresh one.
+    #[serde(default)]
+    pub maximum_age_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GeoPosition {
+    pub latitude: f64,
+    pub longitude: f64,
+    /// Radius in meters.
+    pub accuracy: f64,
+    /// Meters above sea level.
+    pub altitude: Option<f64>,
+    pub altitude_accuracy: Option<f64>,
+    /// Degrees clockwise from true north.
+    pub heading: Option<f64>,
+    /// Meters per second.
+    pub speed: Option<f64>,
+    /// When the fix was taken, not when it was read.
+    pub timestamp_ms: u64,
+}
+
+/// Payload of [`POSITION_EVENT`]: exactly one of `position` and `error` is set.
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PositionUpdate {
+    watch_id: u64,
+    position: Option<GeoPosition>,
+    error: Option<GeoError>,
+}
+
+/// Active [`watch_position`] calls by the id handed to the frontend.
+#[derive(Default)]
+pub struct GeoWatches {
+    next_id: AtomicU64,
+    watches: Mutex<HashMap<u64, native::Watch>>,
+}
+
+fn emit<R: Runtime>(app: &AppHandle<R>, watch_id: u64, update: Result<GeoPosition, GeoError>) {
+    use tauri::Emitter;
+
+    let (position, error) = match update {
+        Ok(position) => (Some(position), None),
+        Err(error) => (None, Some(error)),
+    };
+    let _ = app.emit(
+        POSITION_EVENT,
+        PositionUpdate {
+            watch_id,
+            position,
+            error,
+        },
+    );
+}
+
+#[cfg(desktop)]
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+#[cfg(mobile)]
+mod native {
+    use tauri::plugin::PermissionState;
+    use tauri::{AppHandle, Runtime};
+    use tauri_plugin_geolocation::{
+        GeolocationExt, PermissionType, Position, PositionOptions, WatchEvent,
+    };
+
+    use super::{GeoError, GeoOptions, GeoPosition, DEFAULT_TIMEOUT_MS};
+
+    /// The plugin's channel id.
+    pub struct Watch(u32);
+
+    impl From<Position> for GeoPosition {
+        fn from(position: Position) -> Self {
+            let coords = position.coords;
+            Self {
+                latitude: coords.latitude,
+                longitude: coords.longitude,
+                accuracy: coords.accuracy,
+                altitude: coords.altitude,
+                altitude_accuracy: coords.altitude_accuracy,
+                heading: coords.heading,
+                speed: coords.speed,
+                timestamp_ms: position.timestamp,
+            }
+        }
+    }
+
+    /// The plugin reports every failure as a message string.
+    fn failed(message: String) -> GeoError {
+        let lower = message.to_lowercase();
+        if lower.contains("denied") || lower.contains("permission") {
+            GeoError::PermissionDenied
+        } else if lower.contains("timeout") || lower.contains("timed out") {
+            GeoError::Timeout
+        } else {
+            GeoError::PositionUnavailable(message)
+        }
+    }
+
+    fn position_options(options: &GeoOptions) -> PositionOptions {
+        let clamp = |ms: u64| ms.min(u32::MAX as u64) as u32;
+        PositionOptions {
+            enable_high_accuracy: options.enable_high_accuracy,
+            timeout: clamp(options.timeout_ms.unwrap_or(DEFAULT_TIMEOUT_MS)),
+            maximum_age: clamp(options.maximum_age_ms),
+        }
+    }
+
+    /// Prompts for location access unless it was already granted or refused.
+    fn ensure_permission<R: Runtime>(app: &AppHandle<R>) -> Result<(), GeoError> {
+        let geolocation = app.geolocation();
+        let status = geolocation
+            .check_permissions()
+            .map_err(|e| failed(e.to_string()))?;
+        let state = match status.location {
+            PermissionState::Prompt | PermissionState::PromptWithRationale => {
+                geolocation
+                    .request_permissions(Some(vec![PermissionType::Location]))
+                    .map_err(|e| failed(e.to_string()))?
+                    .location
+            }
+            state => state,
+        };
+        match state {
+            PermissionState::Granted => Ok(()),
+            _ => Err(GeoError::PermissionDenied),
+        }
+    }
+
+    pub fn current<R: Runtime>(
+        app: &AppHandle<R>,
+        options: &GeoOptions,
+    ) -> Result<GeoPosition, GeoError> {
+        ensure_permission(app)?;
+        app.geolocation()
+            .get_current_position(Some(position_options(options)))
+            .map(GeoPosition::from)
+            .map_err(|e| failed(e.to_string()))
+    }
+
+    pub fn watch<R: Runtime>(
+        app: &AppHandle<R>,
+        watch_id: u64,
+        options: &GeoOptions,
+    ) -> Result<Watch, GeoError> {
+        ensure_permission(app)?;
+        let handle = app.clone();
+        app.geolocation()
+            .watch_position(position_options(options), move |event| {
+                let update = match event {
+                    WatchEvent::Position(position) => Ok(position.into()),
+                    WatchEvent::Error(message) => Err(failed(message)),
+                };
+                super::emit(&handle, watch_id, update);
+            })
+            .map(Watch)
+            .map_err(|e| failed(e.to_string()))
+    }
+
+    pub fn stop<R: Runtime>(app: &AppHandle<R>, watch: Watch) {
+        if let Err(e) = app.geolocation().clear_watch(watch.0) {
+            tracing::warn!(error = %e, "failed to clear position watch");
+        }
+    }
+}
+
+/// GeoClue2 over the system bus. Whether the app may locate the device is up to the
+/// desktop's GeoClue agent, which matches the desktop id against its `.desktop` file.
+#[cfg(target_os = "linux")]
+mod os {
+    use tauri::{AppHandle, Runtime};
+    use zbus::blocking::{Connection, Proxy};
+    use zbus::zvariant::OwnedObjectPath;
+
+    use super::{GeoError, GeoOptions, GeoPosition};
+
+    const SERVICE: &str = "org.freedesktop.GeoClue2";
+    /// `GClueAccuracyLevel` values.
+    const ACCURACY_STREET: u32 = 6;
+    const ACCURACY_EXACT: u32 = 8;
+
+    pub struct Session {
+        client: Proxy<'static>,
+        _connection: Connection,
+    }
+
+    fn failed(e: zbus::Error) -> GeoError {
+        let denied = match &e {
+            zbus::Error::MethodError(name, ..) => {
+                name.as_str() == "org.freedesktop.DBus.Error.AccessDenied"
+            }
+            zbus::Error::FDO(e) => matches!(**e, zbus::fdo::Error::AccessDenied(_)),
+            _ => false,
+        };
+        if denied {
+            GeoError::PermissionDenied
+        } else {
+            GeoError::PositionUnavailable(e.to_string())
+        }
+    }
+
+    impl Session {
+        pub fn start<R: Runtime>(
+            app: &AppHandle<R>,
+            options: &GeoOptions,
+        ) -> Result<Self, GeoError> {
+            let connection = Connection::system().map_err(failed)?;
+            let manager = Proxy::new(
+                &connection,
+                SERVICE,
+                "/org/freedesktop/GeoClue2/Manager",
+                "org.freedesktop.GeoClue2.Manager",
+            )
+            .map_err(failed)?;
+            let path: OwnedObjectPath = manager.call("GetClient", &()).map_err(failed)?;
+            let client = Proxy::new(
+                &connection,
+                SERVICE,
+                path,
+                "org.freedesktop.GeoClue2.Client",
+            )
+            .map_err(failed)?;
+
+            let level = if options.enable_high_accuracy {
+                ACCURACY_EXACT
+            } else {
+                ACCURACY_STREET
+            };
+            client
+                .set_property("DesktopId", app.config().identifier.as_str())
+                .map_err(|e| failed(e.into()))?;
+            client
+                .set_property("RequestedAccuracyLevel", level)
+                .map_err(|e| failed(e.into()))?;
+            client.call::<_, _, ()>("Start", &()).map_err(failed)?;
+            Ok(Self {
+                client,
+                _connection: connection,
+            })
+        }
+
+        pub fn latest(&mut self) -> Result<Option<GeoPosition>, GeoError> {
+            let path: OwnedObjectPath = self.client.get_property("Location").map_err(failed)?;
+            if path.as_str() == "/" {
+                return Ok(None);
+            }
+            let location = Proxy::new(
+                self.client.connection(),
+                SERVICE,
+                path,
+                "org.freedesktop.GeoClue2.Location",
+            )
+            .map_err(failed)?;
+            let get = |name: &str| location.get_property::<f64>(name).map_err(failed);
+            // GeoClue marks unknown values with out-of-range sentinels.
+            let altitude = get("Altitude")?;
+            let heading = get("Heading")?;
+            let speed = get("Speed")?;
+            let (secs, micros): (u64, u64) = location.get_property("Timestamp").map_err(failed)?;
+            Ok(Some(GeoPosition {
+                latitude: get("Latitude")?,
+                longitude: get("Longitude")?,
+                accuracy: get("Accuracy")?,
+                altitude: (altitude > f64::MIN).then_some(altitude),
+                altitude_accuracy: None,
+                heading: (heading >= 0.0).then_some(heading),
+                speed: (speed >= 0.0).then_some(speed),
+                timestamp_ms: secs * 1000 + micros / 1000,
+            }))
+        }
+    }
+
+    impl Drop for Session {
+        fn drop(&mut self) {
+            let _ = self.client.call::<_, _, ()>("Stop", &());
+        }
+    }
+}
+
+#[cfg(windows)]
+mod os {
+    use std::time::Duration;
+
+    use tauri::{AppHandle, Runtime};
+    use windows::Devices::Geolocation::{GeolocationAccessStatus, Geolocator, PositionAccuracy};
+    use windows::Foundation::TimeSpan;
+
+    use super::{GeoError, GeoOptions, GeoPosition};
+
+    /// How long one read waits for the service before reporting no fix yet.
+    const READ_TIMEOUT: Duration = Duration::from_millis(500);
+    /// `DateTime` counts 100ns ticks from 1601; this is the Unix epoch in those.
+    const UNIX_EPOCH_TICKS: i64 = 116_444_736_000_000_000;
+    const E_ACCESSDENIED: i32 = 0x8007_0005_u32 as i32;
+    /// `HRESULT_FROM_WIN32(ERROR_TIMEOUT)`.
+    const E_TIMEOUT: i32 = 0x8007_05B4_u32 as i32;
+
+    pub struct Session {
+        locator: Geolocator,
+        maximum_age: TimeSpan,
+    }
+
+    fn ticks(duration: Duration) -> TimeSpan {
+        TimeSpan {
+            Duration: (duration.as_nanos() / 100).min(i64::MAX as u128) as i64,
+        }
+    }
+
+    fn failed(e: windows::core::Error) -> GeoError {
+        if e.code().0 == E_ACCESSDENIED {
+            GeoError::PermissionDenied
+        } else {
+            GeoError::PositionUnavailable(e.message())
+        }
+    }
+
+    impl Session {
+        pub fn start<R: Runtime>(
+            _app: &AppHandle<R>,
+            options: &GeoOptions,
+        ) -> Result<Self, GeoError> {
+            let access = Geolocator::RequestAccessAsync()
+                .and_then(|operation| operation.join())
+                .map_err(failed)?;
+            if access != GeolocationAccessStatus::Allowed {
+                return Err(GeoError::PermissionDenied);
+            }
+            let locator = Geolocator::new().map_err(failed)?;
+            let accuracy = if options.enable_high_accuracy {
+                PositionAccuracy::High
+            } else {
+                PositionAccuracy::Default
+            };
+            locator.SetDesiredAccuracy(accuracy).map_err(failed)?;
+            Ok(Self {
+                locator,
+                maximum_age: ticks(Duration::from_millis(options.maximum_age_ms)),
+            })
+        }
+
+        pub fn latest(&mut self) -> Result<Option<GeoPosition>, GeoError> {
+            let position = match self
+                .locator
+                .GetGeopositionAsyncWithAgeAndTimeout(self.maximum_age, ticks(READ_TIMEOUT))
+                .and_then(|operation| operation.join())
+            {
+                Ok(position) => position,
+                Err(e) if e.code().0 == E_TIMEOUT => return Ok(None),
+                Err(e) => return Err(failed(e)),
+            };
+            let coordinate = position.Coordinate().map_err(failed)?;
+            let point = coordinate
+                .Point()
+                .and_then(|point| point.Position())
+                .map_err(failed)?;
+            let timestamp = coordinate.Timestamp().map_err(failed)?.UniversalTime;
+            Ok(Some(GeoPosition {
+                latitude: point.Latitude,
+                longitude: point.Longitude,
+                accuracy: coordinate.Accuracy().map_err(failed)?,
+                altitude: Some(point.Altitude),
+                // Absent values come back as null references, which read as errors.
+                altitude_accuracy: coordinate
+                    .AltitudeAccuracy()
+                    .and_then(|value| value.Value())
+                    .ok(),
+                heading: coordinate.Heading().and_then(|value| value.Value()).ok(),
+                speed: coordinate.Speed().and_then(|value| value.Value()).ok(),
+                timestamp_ms: ((timestamp - UNIX_EPOCH_TICKS) / 10_000).max(0) as u64,
+            }))
+        }
+    }
+}
+
+/// Core Location only delivers updates on the thread a manager was created on, so each
+/// session's manager lives on the main thread and is reached through the main queue.
+/// The app's `Info.plist` carries the usage description macOS shows when prompting.
+#[cfg(target_os = "macos")]
+mod os {
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    use dispatch2::DispatchQueue;
+    use objc2::rc::Retained;
+    use objc2_core_location::{
+        kCLLocationAccuracyBest, kCLLocationAccuracyHundredMeters, CLAuthorizationStatus,
+        CLLocation, CLLocationManager,
+    };
+    use tauri::{AppHandle, Runtime};
+
+    use super::{GeoError, GeoOptions, GeoPosition};
+
+    static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+    thread_local! {
+        static MANAGERS: RefCell<HashMap<u64, Retained<CLLocationManager>>> =
+            RefCell::new(HashMap::new());
+    }
+
+    pub struct Session(u64);
+
+    fn on_main<T: Send>(work: impl FnOnce() -> T + Send) -> T {
+        let mut result = None;
+        DispatchQueue::main().exec_sync(|| result = Some(work()));
+        result.expect("main queue ran the closure")
+    }
+
+    fn position(location: &CLLocation) -> GeoPosition {
+        // SAFETY: plain property reads on a valid `CLLocation`.
+        unsafe {
+            let coordinate = location.coordinate();
+            let vertical = location.verticalAccuracy();
+            let course = location.course();
+            let speed = location.speed();
+            // Negative accuracies, courses, and speeds mean the value is invalid.
+            GeoPosition {
+                latitude: coordinate.latitude,
+                longitude: coordinate.longitude,
+                accuracy: location.horizontalAccuracy(),
+                altitude: (vertical >= 0.0).then(|| location.altitude()),
+                altitude_accuracy: (vertical >= 0.0).then_some(vertical),
+                heading: (course >= 0.0).then_some(course),
+                speed: (speed >= 0.0).then_some(speed),
+                timestamp_ms: (location.timestamp().timeIntervalSince1970() * 1000.0).max(0.0)
+                    as u64,
+            }
+        }
+    }
+
+    fn denied(status: CLAuthorizationStatus) -> bool {
+        status == CLAuthorizationStatus::Denied || status == CLAuthorizationStatus::Restricted
+    }
+
+    impl Session {
+        pub fn start<R: Runtime>(
+            _app: &AppHandle<R>,
+            options: &GeoOptions,
+        ) -> Result<Self, GeoError> {
+            let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+            let high_accuracy = options.enable_high_accuracy;
+            let status = on_main(move || {
+                // SAFETY: on the main thread, which keeps the manager until `Drop`.
+                unsafe {
+                    let manager = CLLocationManager::new();
+                    manager.setDesiredAccuracy(if high_accuracy {
+                        kCLLocationAccuracyBest
+                    } else {
+                        kCLLocationAccuracyHundredMeters
+                    });
+                    manager.requestWhenInUseAuthorization();
+                    manager.startUpdatingLocation();
+                    let status = manager.authorizationStatus();
+                    MANAGERS.with(|managers| managers.borrow_mut().insert(id, manager));
+                    status
+                }
+            });
+            let session = Self(id);
+            if denied(status) {
+                return Err(GeoError::PermissionDenied);
+            }
+            Ok(session)
+        }
+
+        pub fn latest(&mut self) -> Result<Option<GeoPosition>, GeoError> {
+            let id = self.0;
+            on_main(move || {
+                MANAGERS.with(|managers| {
+                    let managers = managers.borrow();
+                    let Some(manager) = managers.get(&id) else {
+                        return Ok(None);
+                    };
+                    // SAFETY: on the main thread, where the manager was created.
+                    unsafe {
+                        if denied(manager.authorizationStatus()) {
+                            return Err(GeoError::PermissionDenied);
+                        }
+                        Ok(manager.location().map(|location| position(&location)))
+                    }
+                })
+            })
+        }
+    }
+
+    impl Drop for Session {
+        fn drop(&mut self) {
+            let id = self.0;
+            on_main(move || {
+                if let Some(manager) = MANAGERS.with(|managers| managers.borrow_mut().remove(&id)) {
+                    // SAFETY: on the main thread, where the manager was created.
+                    unsafe { manager.stopUpdatingLocation() };
+                }
+            });
+        }
+    }
+}
+
+#[cfg(all(desktop, not(any(target_os = "linux", target_os = "macos", windows))))]
+mod os {
+    use tauri::{AppHandle, Runtime};
+
+    use super::{GeoError, GeoOptions, GeoPosition};
+
+    pub struct Session;
+
+    impl Session {
+        pub fn start<R: Runtime>(
+            _app: &AppHandle<R>,
+            _options: &GeoOptions,
+        ) -> Result<Self, GeoError> {
+            Err(GeoError::PositionUnavailable(
+                "no location service on this platform".into(),
+            ))
+        }
+
+        pub fn latest(&mut self) -> Result<Option<GeoPosition>, GeoError> {
+            Ok(None)
+        }
+    }
+}
+
+#[cfg(desktop)]
+mod native {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    use tauri::{AppHandle, Runtime};
+
+    use super::os::Session;
+    use super::{
+        now_ms, GeoError, GeoOptions, GeoPosition, DEFAULT_TIMEOUT_MS, POLL_INTERVAL,
+        WATCH_INTERVAL,
+    };
+
+    /// Set to end the watch's polling thread, which stops its session.
+    pub struct Watch(Arc<AtomicBool>);
+
+    pub fn current<R: Runtime>(
+        app: &AppHandle<R>,
+        options: &GeoOptions,
+    ) -> Result<GeoPosition, GeoError> {
+        let deadline = Instant::now()
+            + Duration::from_millis(options.timeout_ms.unwrap_or(DEFAULT_TIMEOUT_MS));
+        let oldest_ms = now_ms().saturating_sub(options.maximum_age_ms);
+        let mut session = Session::start(app, options)?;
+        loop {
+            if let Some(position) = session.latest()? {
+                if position.timestamp_ms >= oldest_ms {
+                    return Ok(position);
+                }
+            }
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err(GeoError::Timeout);
+            }
+            std::thread::sleep(remaining.min(POLL_INTERVAL));
+        }
+    }
+
+    pub fn watch<R: Runtime>(
+        app: &AppHandle<R>,
+        watch_id: u64,
+        options: &GeoOptions,
+    ) -> Result<Watch, GeoError> {
+        let mut session = Session::start(app, options)?;
+        let stopped = Arc::new(AtomicBool::new(false));
+        let flag = stopped.clone();
+        let app = app.clone();
+        std::thread::spawn(move || {
+            let mut last_fix = None;
+            let mut failing = false;
+            while !flag.load(Ordering::SeqCst) {
+                match session.latest() {
+                    Ok(Some(position)) if last_fix != Some(position.timestamp_ms) => {
+                        last_fix = Some(position.timestamp_ms);
+                        failing = false;
+                        super::emit(&app, watch_id, Ok(position));
+                    }
+                    Ok(_) => {}
+                    Err(GeoError::PermissionDenied) => {
+                        super::emit(&app, watch_id, Err(GeoError::PermissionDenied));
+                        break;
+                    }
+                    // Reported once per outage rather than every poll.
+                    Err(e) => {
+                        if !std::mem::replace(&mut failing, true) {
+                            super::emit(&app, watch_id, Err(e));
+                        }
+                    }
+                }
+                std::thread::sleep(WATCH_INTERVAL);
+            }
+        });
+        Ok(Watch(stopped))
+    }
+
+    pub fn stop<R: Runtime>(_app: &AppHandle<R>, watch: Watch) {
+        watch.0.store(true, Ordering::SeqCst);
+    }
+}
+
+fn join_error(e: tauri::Error) -> GeoError {
+    GeoError::PositionUnavailable(e.to_string())
+}
+
+/// Prompts for location access the first time.
+#[tauri::command]
+pub async fn get_current_position<R: Runtime>(
+    options: Option<GeoOptions>,
+    app: AppHandle<R>,
+) -> Result<GeoPosition, GeoError> {
+    let options = options.unwrap_or_default();
+    tauri::async_runtime::spawn_blocking(move || native::current(&app, &options))
+        .await
+        .map_err(join_error)?
+}
+
+/// Emits [`POSITION_EVENT`] with the returned id for each new fix until
+/// [`stop_watching_position`]. A watch that loses permission reports it and ends.
+#[tauri::command]
+pub async fn watch_position<R: Runtime>(
+    options: Option<GeoOptions>,
+    app: AppHandle<R>,
+) -> Result<u64, GeoError> {
+    let options = options.unwrap_or_default();
+    tauri::async_runtime::spawn_blocking(move || {
+        use tauri::Manager;
+
+        let watches = app.state::<GeoWatches>();
+        let watch_id = watches.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let watch = native::watch(&app, watch_id, &options)?;
+        watches.watches.lock().unwrap().insert(watch_id, watch);
+        Ok(watch_id)
+    })
+    .await
+    .map_err(join_error)?
+}
+
+/// Unknown or already stopped ids are ignored.
+#[tauri::command]
+pub fn stop_watching_position<R: Runtime>(
+    watch_id: u64,
+    app: AppHandle<R>,
+    watches: State<'_, GeoWatches>,
+) {
+    let watch = watches.watches.lock().unwrap().remove(&watch_id);
+    if let Some(watch) = watch {
+        native::stop(&app, watch);
+    }
+}
@@ -0,0 +1,125 @@
+//! Haptic feedback on iOS and Android. Desktop has no haptics to speak of, so the
+//! command succeeds there without doing anything, as it does on phones and tablets
+//! without a vibrator, and callers needn't check the platform first.
+//!
+//! The native halves live in `plugins/haptics`, registered here like [`crate::share`].
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Runtime};
+
+#[cfg_attr(desktop, allow(dead_code))]
+#[derive(Debug, thiserror::Error, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "camelCase")]
+pub enum HapticError {
+    #[error("haptic feedback failed: {0}")]
+    Failed(String),
+}
+
+/// The first five match `UIImpactFeedbackStyle`, the next three
+/// `UINotificationFeedbackType`, and `Selection` is `UISelectionFeedbackGenerator`.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum HapticStyle {
+    Light,
+    Medium,
+    Heavy,
+    Rigid,
+    Soft,
+    Success,
+    Warning,
+    Error,
+    Selection,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct HapticResult {
+    /// False where nothing can be felt: desktop, and devices without a vibrator or
+    /// Taptic Engine.
+    pub supported: bool,
+}
+
+#[cfg(mobile)]
+mod native {
+    use serde::Serialize;
+    use tauri::plugin::mobile::PluginInvokeError;
+    use tauri::plugin::{PluginHandle, TauriPlugin};
+    use tauri::{AppHandle, Manager, Runtime};
+    // Linked for its native halves; it has no Rust API.
+    use tauri_plugin_haptics as _;
+
+    use super::{HapticError, HapticResult, HapticStyle};
+
+    #[cfg(target_os = "ios")]
+    tauri::ios_plugin_binding!(init_plugin_haptics);
+
+    struct Haptics<R: Runtime>(PluginHandle<R>);
+
+    #[derive(Serialize)]
+    struct FeedbackArgs {
+        style: HapticStyle,
+    }
+
+    pub fn plugin<R: Runtime>() -> TauriPlugin<R> {
+        tauri::plugin::Builder::new("haptics")
+            .setup(|app, api| {
+                #[cfg(target_os = "android")]
+                let handle = api.register_android_plugin("com.layers.haptics", "HapticsPlugin")?;
+                #[cfg(target_os = "ios")]
+                let handle = api.register_ios_plugin(init_plugin_haptics)?;
+                app.manage(Haptics(handle));
+                Ok(())
+            })
+            .build()
+    }
+
+    fn from_plugin(e: PluginInvokeError) -> HapticError {
+        match e {
+            PluginInvokeError::InvokeRejected(response) => {
+                HapticError::Failed(response.message.unwrap_or_default())
+            }
+            e => HapticError::Failed(e.to_string()),
+        }
+    }
+
+    pub async fn feedback<R: Runtime>(
+        app: &AppHandle<R>,
+        style: HapticStyle,
+    ) -> Result<HapticResult, HapticError> {
+        let handle = app
+            .try_state::<Haptics<R>>()
+            .map(|haptics| haptics.0.clone())
+            .ok_or_else(|| HapticError::Failed("haptics plugin not loaded".into()))?;
+        handle
+            .run_mobile_plugin_async("feedback", FeedbackArgs { style })
+            .await
+            .map_err(from_plugin)
+    }
+}
+
+#[cfg(desktop)]
+mod native {
+    use tauri::{AppHandle, Runtime};
+
+    use super::{HapticError, HapticResult, HapticStyle};
+
+    pub async fn feedback<R: Runtime>(
+        _app: &AppHandle<R>,
+        _style: HapticStyle,
+    ) -> Result<HapticResult, HapticError> {
+        Ok(HapticResult { supported: false })
+    }
+}
+
+#[cfg(mobile)]
+pub fn plugin<R: Runtime>() -> tauri::plugin::TauriPlugin<R> {
+    native::plugin()
+}
+
+/// Async like the badge commands: iOS fires the generators on the main thread.
+#[tauri::command]
+pub async fn haptic_feedback<R: Runtime>(
+    style: HapticStyle,
+    app: AppHandle<R>,
+) -> Result<HapticResult, HapticError> {
+    native::feedback(&app, style).await
+}
@@ -0,0 +1,96 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Mutex;
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, Runtime, State};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutState};
+
+pub const TRIGGERED_EVENT: &str = "hotkey://triggered";
+
+#[derive(Debug, thiserror::Error, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "camelCase")]
+pub enum HotkeyError {
+    #[error("`{0}` is not a valid accelerator")]
+    InvalidAccelerator(String),
+    #[error("`{0}` is already registered")]
+    AlreadyRegistered(String),
+    /// The OS refused the registration, e.g. a shortcut reserved by the system or, on
+    /// macOS, missing Accessibility permission.
+    #[error("the OS refused the hotkey: {0}")]
+    PermissionDenied(String),
+    #[error("no hotkey registered with id `{0}`")]
+    NotRegistered(String),
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HotkeyTriggered {
+    pub id: String,
+    pub accelerator: String,
+}
+
+/// Registrations keyed by the frontend's id. Kept in Rust state so they outlive
+/// webview reloads, which would otherwise leave orphaned OS hotkeys behind.
+#[derive(Default)]
+pub struct Hotkeys {
+    active: Mutex<HashMap<String, Shortcut>>,
+}
+
+fn os_error(accelerator: &str, error: tauri_plugin_global_shortcut::Error) -> HotkeyError {
+    let message = error.to_string();
+    if message.to_ascii_lowercase().contains("already registered") {
+        return HotkeyError::AlreadyRegistered(accelerator.to_string());
+    }
+    HotkeyError::PermissionDenied(message)
+}
+
+/// Registers through the global-shortcut plugin, which owns the process's only
+/// `global-hotkey` manager.
+#[tauri::command]
+pub fn register_hotkey<R: Runtime>(
+    accelerator: String,
+    id: String,
+    app: AppHandle<R>,
+) -> Result<(), HotkeyError> {
+    let shortcut = Shortcut::from_str(&accelerator)
+        .map_err(|_| HotkeyError::InvalidAccelerator(accelerator.clone()))?;
+
+    let hotkeys = app.state::<Hotkeys>();
+    let mut active = hotkeys.active.lock().unwrap();
+    if active.contains_key(&id) || app.global_shortcut().is_registered(shortcut) {
+        return Err(HotkeyError::AlreadyRegistered(accelerator));
+    }
+
+    let payload = HotkeyTriggered {
+        id: id.clone(),
+        accelerator: accelerator.clone(),
+    };
+    app.global_shortcut()
+        .on_shortcut(shortcut, move |app, _, event| {
+            if event.state == ShortcutState::Pressed {
+                let _ = app.emit(TRIGGERED_EVENT, payload.clone());
+            }
+        })
+        .map_err(|e| os_error(&accelerator, e))?;
+
+    active.insert(id, shortcut);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn unregister_hotkey<R: Runtime>(
+    id: String,
+    app: AppHandle<R>,
+    hotkeys: State<'_, Hotkeys>,
+) -> Result<(), HotkeyError> {
+    let shortcut = hotkeys
+        .active
+        .lock()
+        .unwrap()
+        .remove(&id)
+        .ok_or_else(|| HotkeyError::NotRegistered(id.clone()))?;
+
+    app.global_shortcut()
+        .unregister(shortcut)
+        .map_err(|e| os_error(&id, e))
+}
@@ -0,0 +1,279 @@
+//! A response cache for GET requests made from Rust, kept in SQLite so it survives
+//! restarts. Stale entries are revalidated with `If-None-Match`/`If-Modified-Since`
+//! rather than refetched.
+//!
+//! The cache is best-effort: if the database is unavailable (including while migrations
+//! run), requests go straight to the network.
+
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::SqlitePool;
+use tauri::{AppHandle, Runtime, State};
+use tauri_plugin_store::StoreExt;
+
+use crate::db::{Db, DbError};
+use crate::http_config::HttpResponse;
+use crate::net::{self, NetError, Sent};
+
+/// Preferences key overriding [`DEFAULT_MAX_BYTES`].
+const MAX_BYTES_KEY: &str = "http_cache_max_bytes";
+const PREFERENCES_STORE: &str = "preferences.json";
+const DEFAULT_MAX_BYTES: u64 = 50 * 1024 * 1024;
+const DEFAULT_TTL_SECS: u64 = 300;
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CachedFetchOptions {
+    /// How long a cached response is served without asking the server. Defaults to 5
+    /// minutes.
+    pub ttl_secs: Option<u64>,
+    /// Revalidate even if the cached response is still fresh.
+    #[serde(default)]
+    pub force_refresh: bool,
+    /// Defaults to the URL.
+    pub cache_key: Option<String>,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    /// Cache responses to requests with an `Authorization` header. Only set this when the
+    /// response can't differ between users of the app.
+    #[serde(default)]
+    pub cache_authorized: bool,
+    pub timeout_ms: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CachedResponse {
+    pub status: u16,
+    pub body: String,
+    pub from_cache: bool,
+    /// Seconds since the server last confirmed the body; 0 when it just did.
+    pub age_secs: u64,
+}
+
+struct Entry {
+    body: String,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    stored_at_ms: i64,
+}
+
+fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or_default()
+}
+
+fn max_bytes<R: Runtime>(app: &AppHandle<R>) -> u64 {
+    let value = app
+        .store(PREFERENCES_STORE)
+        .ok()
+        .and_then(|store| store.get(MAX_BYTES_KEY));
+    // The preferences adapter stores everything as strings.
+    let max = match value {
+        Some(Value::Number(n)) => n.as_u64(),
+        Some(Value::String(s)) => s.parse().ok(),
+        _ => None,
+    };
+    max.filter(|max| *max > 0).unwrap_or(DEFAULT_MAX_BYTES)
+}
+
+fn header<'a>(headers: &'a HashMap<String, String>, name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.as_str())
+}
+
+fn is_no_store(headers: &HashMap<String, String>) -> bool {
+    header(headers, "cache-control").is_some_and(|value| {
+        value
+            .split(',')
+            .any(|directive| directive.trim().eq_ignore_ascii_case("no-store"))
+    })
+}
+
+async fn lookup(pool: &SqlitePool, key: &str) -> Result<Option<Entry>, sqlx::Error> {
+    let row: Option<(String, Option<String>, Option<String>, i64)> = sqlx::query_as(
+        "SELECT body, etag, last_modified, stored_at_ms FROM http_cache WHERE cache_key = ?",
+    )
+    .bind(key)
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.map(|(body, etag, last_modified, stored_at_ms)| Entry {
+        body,
+        etag,
+        last_modified,
+        stored_at_ms,
+    }))
+}
+
+async fn touch(pool: &SqlitePool, key: &str, revalidated: bool) -> Result<(), sqlx::Error> {
+    let now = now_ms();
+    sqlx::query(
+        "UPDATE http_cache
+         SET last_used_ms = ?1, stored_at_ms = CASE WHEN ?2 THEN ?1 ELSE stored_at_ms END
+         WHERE cache_key = ?3",
+    )
+    .bind(now)
+    .bind(revalidated)
+    .bind(key)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+async fn store(
+    pool: &SqlitePool,
+    key: &str,
+    url: &str,
+    response: &HttpResponse,
+    max_bytes: u64,
+) -> Result<(), sqlx::Error> {
+    let size = response.body.len() as u64;
+    if size > max_bytes {
+        return Ok(());
+    }
+
+    let now = now_ms();
+    let mut tx = pool.begin().await?;
+    sqlx::query(
+        "INSERT OR REPLACE INTO http_cache
+         (cache_key, url, status, body, etag, last_modified, stored_at_ms, last_used_ms, size_bytes)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(key)
+    .bind(url)
+    .bind(response.status)
+    .bind(&response.body)
+    .bind(header(&response.headers, "etag"))
+    .bind(header(&response.headers, "last-modified"))
+    .bind(now)
+    .bind(now)
+    .bind(size as i64)
+    .execute(&mut *tx)
+    .await?;
+    // Evict least recently used entries until the rest fit.
+    sqlx::query(
+        "DELETE FROM http_cache WHERE cache_key IN (
+           SELECT cache_key FROM (
+             SELECT cache_key,
+                    SUM(size_bytes) OVER (ORDER BY last_used_ms DESC, cache_key) AS total
+             FROM http_cache
+           ) WHERE total > ?
+         )",
+    )
+    .bind(max_bytes as i64)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await
+}
+
+/// Logs and drops a cache error; the request itself carries on without the cache.
+fn best_effort<T>(result: Result<T, sqlx::Error>) -> Option<T> {
+    result
+        .map_err(|e| tracing::warn!(error = %e, "http cache unavailable"))
+        .ok()
+}
+
+/// Fetches `url` with GET, serving it from the cache while fresh and revalidating it
+/// once stale. Only `200` responses are cached, and never ones marked
+/// `Cache-Control: no-store` or, unless `cache_authorized` is set, sent with an
+/// `Authorization` header. `url` must be in the allowed origins, fresh or not. Requests
+/// that reach the network go through [`net::send`], so
+/// they share its limits and middleware chain; mocked responses are returned as-is and
+/// not cached.
+#[tauri::command]
+pub async fn cached_fetch<R: Runtime>(
+    url: String,
+    options: Option<CachedFetchOptions>,
+    app: AppHandle<R>,
+    db: State<'_, Db>,
+) -> Result<CachedResponse, NetError> {
+    net::ensure_allowed(&app, &url)?;
+    let options = options.unwrap_or_default();
+    let key = options.cache_key.clone().unwrap_or_else(|| url.clone());
+    let ttl_ms = options.ttl_secs.unwrap_or(DEFAULT_TTL_SECS) as i64 * 1000;
+
+    let cacheable = options.cache_authorized || header(&options.headers, "authorization").is_none();
+    let pool = if cacheable { db.pool().ok() } else { None };
+    let cached = match pool {
+        Some(pool) => best_effort(lookup(pool, &key).await).flatten(),
+        None => None,
+    };
+
+    if let (Some(pool), Some(entry)) = (pool, &cached) {
+        let age_ms = (now_ms() - entry.stored_at_ms).max(0);
+        if !options.force_refresh && age_ms < ttl_ms {
+            best_effort(touch(pool, &key, false).await);
+            return Ok(CachedResponse {
+                status: 200,
+                body: entry.body.clone(),
+                from_cache: true,
+                age_secs: (age_ms / 1000) as u64,
+            });
+        }
+    }
+
+    let mut headers = options.headers;
+    if let Some(entry) = &cached {
+        if let Some(etag) = &entry.etag {
+            headers.insert("If-None-Match".into(), etag.clone());
+        }
+        if let Some(last_modified) = &entry.last_modified {
+            headers.insert("If-Modified-Since".into(), last_modified.clone());
+        }
+    }
+    let no_store = is_no_store(&headers);
+    let mut req = net::get(&app, &url);
+    req.headers = headers;
+    let sent = net::send(&app, req, options.timeout_ms).await?;
+    let mocked = matches!(sent, Sent::Mocked(_));
+    let response = sent.read().await?;
+
+    match (pool.filter(|_| !mocked), cached) {
+        (Some(pool), Some(entry)) if response.status == 304 => {
+            best_effort(touch(pool, &key, true).await);
+            return Ok(CachedResponse {
+                status: 200,
+                body: entry.body,
+                from_cache: true,
+                age_secs: 0,
+            });
+        }
+        (Some(pool), _)
+            if response.status == 200 && !is_no_store(&response.headers) && !no_store =>
+        {
+            best_effort(store(pool, &key, &url, &response, max_bytes(&app)).await);
+        }
+        _ => {}
+    }
+
+    Ok(CachedResponse {
+        status: response.status,
+        body: response.body,
+        from_cache: false,
+        age_secs: 0,
+    })
+}
+
+/// Removes cached responses whose key starts with `prefix`, or all of them.
+#[tauri::command]
+pub async fn clear_http_cache(prefix: Option<String>, db: State<'_, Db>) -> Result<(), DbError> {
+    let pattern = prefix.map(|prefix| {
+        let escaped = prefix
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_");
+        format!("{escaped}%")
+    });
+    sqlx::query("DELETE FROM http_cache WHERE ?1 IS NULL OR cache_key LIKE ?1 ESCAPE '\\'")
+        .bind(pattern)
+        .execute(db.pool()?)
+        .await?;
+    Ok(())
+}
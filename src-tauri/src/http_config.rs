@@ -0,0 +1,251 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tauri::plugin::TauriPlugin;
+use tauri::{AppHandle, Emitter, Manager, Runtime, State};
+use tauri_plugin_http::reqwest::{self, Certificate, Method};
+
+use crate::http_middleware::{HttpExchange, HttpMiddleware, InterceptableRequest};
+
+pub const PIN_VIOLATION_EVENT: &str = "security://pin-violation";
+
+/// Pins bundled as resources: every `*.der` file in this directory is trusted.
+const PIN_RESOURCE_DIR: &str = "certs";
+
+#[derive(Debug, thiserror::Error, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "camelCase")]
+pub enum HttpError {
+    #[error("no pinned certificates are configured")]
+    NotConfigured,
+    #[error("certificate chain for {0} does not include a pinned certificate")]
+    PinViolation(String),
+    #[error("invalid request: {0}")]
+    InvalidRequest(String),
+    #[error("request failed: {0}")]
+    Request(String),
+}
+
+/// A DER-encoded certificate that must appear in a server's chain, typically the CA or
+/// intermediate that issues the API's certificates.
+#[derive(Debug, Clone)]
+pub struct PinnedCert {
+    pub der: Vec<u8>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HttpOptions {
+    pub method: Option<String>,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    pub body: Option<String>,
+    pub timeout_ms: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub headers: HashMap<String, String>,
+    pub body: String,
+}
+
+/// A client that trusts only the pinned certificates. It is `None` when no pins were
+/// found, so requests fail closed instead of falling back to the system roots.
+pub struct PinnedClient(Option<reqwest::Client>);
+
+/// Builds the pinned client and manages it alongside `tauri_plugin_http`. The http
+/// plugin creates its own client per `fetch`, so pinned requests go through
+/// [`pinned_http_request`] rather than the plugin's JS API.
+pub struct PinnedHttpPlugin {
+    pins: Vec<PinnedCert>,
+}
+
+impl PinnedHttpPlugin {
+    /// `pins` are trusted in addition to any `*.der` files bundled under `certs/`.
+    pub fn new(pins: Vec<PinnedCert>) -> Self {
+        Self { pins }
+    }
+
+    pub fn build<R: Runtime>(self) -> TauriPlugin<R> {
+        tauri::plugin::Builder::new("pinned-http")
+            .setup(move |app, _api| {
+                let mut pins = self.pins;
+                if let Ok(dir) = app.path().resource_dir() {
+                    pins.extend(load_pins(&dir.join(PIN_RESOURCE_DIR)));
+                }
+                app.manage(PinnedClient(build_client(&pins)?));
+                Ok(())
+            })
+            .build()
+    }
+}
+
+fn load_pins(dir: &Path) -> Vec<PinnedCert> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "der"))
+        .filter_map(|path| match std::fs::read(&path) {
+            Ok(der) => Some(PinnedCert { der }),
+            Err(e) => {
+                tracing::warn!(path = %path.display(), error = %e, "failed to read pinned cert");
+                None
+            }
+        })
+        .collect()
+}
+
+fn build_client(pins: &[PinnedCert]) -> Result<Option<reqwest::Client>, reqwest::Error> {
+    if pins.is_empty() {
+        tracing::warn!("no pinned certificates found; pinned_http_request is disabled");
+        return Ok(None);
+    }
+
+    let mut builder = reqwest::ClientBuilder::new()
+        .tls_built_in_root_certs(false)
+        .https_only(true);
+    for pin in pins {
+        builder = builder.add_root_certificate(Certificate::from_der(&pin.der)?);
+    }
+    builder.build().map(Some)
+}
+
+pub fn plugin<R: Runtime>() -> TauriPlugin<R> {
+    PinnedHttpPlugin::new(Vec::new()).build()
+}
+
+/// With only pinned roots, a chain without a pinned cert fails verification; rustls
+/// reports that as a certificate error somewhere down the connect error's sources.
+fn is_certificate_error(error: &reqwest::Error) -> bool {
+    let mut source: Option<&dyn std::error::Error> = Some(error);
+    while let Some(e) = source {
+        if e.to_string().to_ascii_lowercase().contains("certificate") {
+            return true;
+        }
+        source = e.source();
+    }
+    false
+}
+
+#[derive(Clone, Serialize)]
+struct PinViolation {
+    url: String,
+}
+
+/// A `reqwest` request for an intercepted request, on any client.
+pub(crate) fn build_request(
+    client: &reqwest::Client,
+    req: &InterceptableRequest,
+    timeout_ms: Option<u64>,
+) -> Result<reqwest::RequestBuilder, HttpError> {
+    let method = Method::from_bytes(req.method.as_bytes())
+        .map_err(|_| HttpError::InvalidRequest(format!("invalid method {}", req.method)))?;
+    let mut request = client.request(method, &req.url);
+    for (name, value) in &req.headers {
+        request = request.header(name, value);
+    }
+    if let Some(body) = &req.body {
+        request = request.body(body.clone());
+    }
+    if let Some(timeout_ms) = timeout_ms {
+        request = request.timeout(Duration::from_millis(timeout_ms));
+    }
+    Ok(request)
+}
+
+pub(crate) fn request_error(e: reqwest::Error) -> HttpError {
+    if e.is_builder() {
+        HttpError::InvalidRequest(e.to_string())
+    } else {
+        HttpError::Request(e.to_string())
+    }
+}
+
+/// Reads the whole response; header names come out lowercase.
+pub(crate) async fn read_response(response: reqwest::Response) -> Result<HttpResponse, HttpError> {
+    let status = response.status().as_u16();
+    let headers = response
+        .headers()
+        .iter()
+        .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+        .collect();
+    let body = response
+        .text()
+        .await
+        .map_err(|e| HttpError::Request(e.to_string()))?;
+
+    Ok(HttpResponse {
+        status,
+        headers,
+        body,
+    })
+}
+
+async fn send<R: Runtime>(
+    app: &AppHandle<R>,
+    client: &PinnedClient,
+    req: &InterceptableRequest,
+    timeout_ms: Option<u64>,
+) -> Result<HttpResponse, HttpError> {
+    let client = client.0.as_ref().ok_or(HttpError::NotConfigured)?;
+    let response = match build_request(client, req, timeout_ms)?.send().await {
+        Ok(response) => response,
+        Err(e) if !e.is_builder() && is_certificate_error(&e) => {
+            let url = req.url.clone();
+            tracing::warn!(%url, "certificate pin violation");
+            let _ = app.emit(PIN_VIOLATION_EVENT, PinViolation { url: url.clone() });
+            return Err(HttpError::PinViolation(url));
+        }
+        Err(e) => return Err(request_error(e)),
+    };
+    read_response(response).await
+}
+
+/// Sends a request through the [`HttpMiddleware`] chain, which may answer it with a mock
+/// before the pinned client is involved.
+#[tauri::command]
+pub async fn pinned_http_request<R: Runtime>(
+    url: String,
+    options: HttpOptions,
+    app: AppHandle<R>,
+    client: State<'_, PinnedClient>,
+    middleware: State<'_, HttpMiddleware>,
+) -> Result<HttpResponse, HttpError> {
+    let mut req = InterceptableRequest {
+        id: middleware.next_id(),
+        method: options
+            .method
+            .as_deref()
+            .unwrap_or("GET")
+            .to_ascii_uppercase(),
+        url,
+        headers: options.headers,
+        body: options.body,
+    };
+
+    let started = Instant::now();
+    let mock = middleware.intercept(&mut req).await;
+    let mocked = mock.is_some();
+    let result = match mock {
+        Some(mock) => Ok(HttpResponse {
+            status: mock.status,
+            headers: mock.headers,
+            body: mock.body,
+        }),
+        None => send(&app, &client, &req, options.timeout_ms).await,
+    };
+
+    middleware.completed(&HttpExchange {
+        request: &req,
+        status: result.as_ref().ok().map(|response| response.status),
+        error: result.as_ref().err().map(ToString::to_string),
+        mocked,
+        elapsed: started.elapsed(),
+    });
+    result
+}
@@ -0,0 +1,250 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, Runtime, State};
+
+pub const REQUEST_EVENT: &str = "http://request";
+pub const RESPONSE_EVENT: &str = "http://response";
+
+/// Mocks loaded at startup in debug builds, from the app config dir: a JSON object
+/// mapping URL patterns to [`MockHttpResponse`]s.
+const MOCKS_FILE: &str = "http-mocks.json";
+
+/// A request made through [`crate::http_config::pinned_http_request`], before it is sent.
+/// Interceptors may rewrite anything but `id`.
+#[derive(Debug, Clone, Serialize)]
+pub struct InterceptableRequest {
+    pub id: u64,
+    pub method: String,
+    pub url: String,
+    pub headers: HashMap<String, String>,
+    pub body: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MockHttpResponse {
+    #[serde(default = "default_status")]
+    pub status: u16,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    #[serde(default)]
+    pub body: String,
+}
+
+fn default_status() -> u16 {
+    200
+}
+
+pub enum InterceptResult {
+    Continue,
+    /// Answer with this instead of sending the request; later interceptors are skipped.
+    MockResponse(MockHttpResponse),
+}
+
+/// How a request ended, for interceptors that observe responses.
+pub struct HttpExchange<'a> {
+    pub request: &'a InterceptableRequest,
+    pub status: Option<u16>,
+    pub error: Option<String>,
+    pub mocked: bool,
+    pub elapsed: Duration,
+}
+
+pub trait HttpInterceptor: Send + Sync {
+    fn intercept<'a>(&'a self, req: &'a mut InterceptableRequest)
+        -> BoxFuture<'a, InterceptResult>;
+
+    fn on_response(&self, _exchange: &HttpExchange<'_>) {}
+}
+
+/// The interceptor chain, run in order for every request.
+pub struct HttpMiddleware {
+    interceptors: Vec<Arc<dyn HttpInterceptor>>,
+    mocks: Arc<MockInterceptor>,
+    next_id: AtomicU64,
+}
+
+impl HttpMiddleware {
+    /// Runs the chain, returning the mock response if an interceptor supplied one.
+    pub async fn intercept(&self, req: &mut InterceptableRequest) -> Option<MockHttpResponse> {
+        for interceptor in &self.interceptors {
+            if let InterceptResult::MockResponse(response) = interceptor.intercept(req).await {
+                return Some(response);
+            }
+        }
+        None
+    }
+
+    pub fn completed(&self, exchange: &HttpExchange<'_>) {
+        for interceptor in &self.interceptors {
+            interceptor.on_response(exchange);
+        }
+    }
+
+    pub fn next_id(&self) -> u64 {
+        self.next_id.fetch_add(1, Ordering::Relaxed) + 1
+    }
+}
+
+pub fn init<R: Runtime>(app: &AppHandle<R>) {
+    let mocks = Arc::new(MockInterceptor::default());
+    if cfg!(debug_assertions) {
+        if let Ok(dir) = app.path().app_config_dir() {
+            mocks.load(&dir.join(MOCKS_FILE));
+        }
+    }
+
+    // Logging goes first so mocked requests are logged too.
+    let logging: Arc<dyn HttpInterceptor> = Arc::new(LoggingInterceptor { app: app.clone() });
+    let mock: Arc<dyn HttpInterceptor> = mocks.clone();
+    app.manage(HttpMiddleware {
+        interceptors: vec![logging, mock],
+        mocks,
+        next_id: AtomicU64::new(0),
+    });
+}
+
+#[derive(Clone, Serialize)]
+struct RequestEvent {
+    id: u64,
+    method: String,
+    url: String,
+}
+
+#[derive(Clone, Serialize)]
+struct ResponseEvent {
+    id: u64,
+    url: String,
+    status: Option<u16>,
+    error: Option<String>,
+    mocked: bool,
+    duration_ms: u64,
+}
+
+/// Emits [`REQUEST_EVENT`] and [`RESPONSE_EVENT`] for every request.
+pub struct LoggingInterceptor<R: Runtime> {
+    app: AppHandle<R>,
+}
+
+impl<R: Runtime> HttpInterceptor for LoggingInterceptor<R> {
+    fn intercept<'a>(
+        &'a self,
+        req: &'a mut InterceptableRequest,
+    ) -> BoxFuture<'a, InterceptResult> {
+        let _ = self.app.emit(
+            REQUEST_EVENT,
+            RequestEvent {
+                id: req.id,
+                method: req.method.clone(),
+                url: req.url.clone(),
+            },
+        );
+        Box::pin(async { InterceptResult::Continue })
+    }
+
+    fn on_response(&self, exchange: &HttpExchange<'_>) {
+        let event = ResponseEvent {
+            id: exchange.request.id,
+            url: exchange.request.url.clone(),
+            status: exchange.status,
+            error: exchange.error.clone(),
+            mocked: exchange.mocked,
+            duration_ms: exchange.elapsed.as_millis() as u64,
+        };
+        tracing::debug!(
+            url = %event.url,
+            status = ?event.status,
+            duration_ms = event.duration_ms,
+            "http request finished"
+        );
+        let _ = self.app.emit(RESPONSE_EVENT, event);
+    }
+}
+
+/// Answers requests whose URL matches a pattern. `*` in a pattern matches any run of
+/// characters; the longest matching pattern wins.
+#[derive(Default)]
+pub struct MockInterceptor {
+    mocks: RwLock<HashMap<String, MockHttpResponse>>,
+}
+
+impl MockInterceptor {
+    fn load(&self, path: &std::path::Path) {
+        let Ok(json) = std::fs::read_to_string(path) else {
+            return;
+        };
+        match serde_json::from_str::<HashMap<String, MockHttpResponse>>(&json) {
+            Ok(mocks) => {
+                tracing::info!(count = mocks.len(), path = %path.display(), "loaded http mocks");
+                self.mocks.write().unwrap().extend(mocks);
+            }
+            Err(e) => tracing::warn!(path = %path.display(), error = %e, "invalid http mocks"),
+        }
+    }
+
+    fn find(&self, url: &str) -> Option<MockHttpResponse> {
+        let mocks = self.mocks.read().unwrap();
+        mocks
+            .iter()
+            .filter(|(pattern, _)| matches_pattern(pattern, url))
+            .max_by_key(|(pattern, _)| pattern.len())
+            .map(|(_, response)| response.clone())
+    }
+}
+
+fn matches_pattern(pattern: &str, url: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = url.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        // No `*`: the whole URL must match.
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+impl HttpInterceptor for MockInterceptor {
+    fn intercept<'a>(
+        &'a self,
+        req: &'a mut InterceptableRequest,
+    ) -> BoxFuture<'a, InterceptResult> {
+        let result = match self.find(&req.url) {
+            Some(response) => InterceptResult::MockResponse(response),
+            None => InterceptResult::Continue,
+        };
+        Box::pin(async { result })
+    }
+}
+
+/// Mocks `pattern` for the rest of the session. Only available in debug builds, so a
+/// release build always talks to the real server.
+#[tauri::command]
+pub fn add_mock(
+    pattern: String,
+    response: MockHttpResponse,
+    middleware: State<'_, HttpMiddleware>,
+) -> Result<(), String> {
+    if !cfg!(debug_assertions) {
+        return Err("http mocks are only available in debug builds".into());
+    }
+    middleware
+        .mocks
+        .mocks
+        .write()
+        .unwrap()
+        .insert(pattern, response);
+    Ok(())
+}
@@ -0,0 +1,503 @@
+//! The OS locale and formatting that follows it. Number, date and relative-time strings
+//! come from ICU4X's compiled CLDR data, the same data the platforms' own formatters
+//! use, so they read like the rest of the system rather than like the webview's `Intl`.
+//!
+//! Translation catalogs are bundled as resources under `i18n/<tag>.json`. Loading one
+//! merges its fallback chain, `pt-BR` over `pt` over `en`, so a partial catalog still
+//! covers every key.
+//!
+//! A change of OS locale while running emits [`LOCALE_CHANGED_EVENT`] with the new
+//! [`LocaleInfo`]; it's checked every [`POLL_INTERVAL`] and when the app regains focus.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use fixed_decimal::{Decimal, FloatPrecision};
+use icu_calendar::week::WeekInformation;
+use icu_datetime::fieldsets::{T, YMD};
+use icu_datetime::input::{Date, DateTime, Time};
+use icu_datetime::{DateTimeFormatter, NoCalendarFormatter};
+use icu_decimal::{CompactDecimalFormatter, DecimalFormatter};
+use icu_experimental::dimension::currency::formatter::CurrencyFormatter;
+use icu_experimental::dimension::currency::CurrencyType;
+use icu_experimental::dimension::percent::formatter::PercentFormatter;
+use icu_experimental::relativetime::options::Numeric;
+use icu_experimental::relativetime::{RelativeTimeFormatter, RelativeTimeFormatterOptions};
+use icu_locale_core::extensions::unicode::{key, value};
+use icu_locale_core::{locale, Locale};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::path::BaseDirectory;
+use tauri::{AppHandle, Emitter, Manager, Runtime, State, Window, WindowEvent};
+
+pub const LOCALE_CHANGED_EVENT: &str = "i18n://locale-changed";
+
+/// Neither platform announces a locale change to a running app in a way we can hook, so
+/// it's polled; users rarely switch, and mobile apps usually restart when they do.
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// The last link of every fallback chain, and the catalog that must have every key.
+const BASE_LANGUAGE: &str = "en";
+
+#[derive(Debug, thiserror::Error, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "camelCase")]
+pub enum I18nError {
+    #[error("invalid input: {0}")]
+    InvalidInput(String),
+    #[error("no locale data for {0}")]
+    MissingData(String),
+    #[error("reading the catalog failed: {0}")]
+    Catalog(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LocaleInfo {
+    /// BCP-47, e.g. `pt-BR`.
+    pub locale: String,
+    /// The ISO 3166 region, when the OS gives one.
+    pub region: Option<String>,
+    pub decimal_separator: String,
+    /// Often a non-breaking or narrow space, never a plain one.
+    pub grouping_separator: String,
+    pub uses_24_hour_clock: bool,
+    /// ISO weekday number, Monday being 1 and Sunday 7.
+    pub first_weekday: u8,
+}
+
+/// Options come with the variant: `"decimal"`, `"percent"`, `{"currency": "EUR"}` or
+/// `"compact"`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum NumberStyle {
+    /// Up to three fraction digits, as `Intl.NumberFormat` does by default.
+    #[default]
+    Decimal,
+    /// Of a fraction: 0.25 is 25%.
+    Percent,
+    /// An ISO 4217 code, shown with its symbol.
+    Currency(String),
+    /// Short forms like `1.2K`.
+    Compact,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DateStyle {
+    /// All digits, like `10/14/26`.
+    Short,
+    #[default]
+    Medium,
+    /// With the month spelled out.
+    Long,
+    /// Medium with hours and minutes.
+    DateTime,
+    /// Hours and minutes alone.
+    Time,
+}
+
+/// The OS locale and the catalogs loaded for it. Catalogs are kept until the locale
+/// changes, since the frontend asks for one per window.
+#[derive(Default)]
+pub struct I18n {
+    locale: Mutex<Option<Locale>>,
+    catalogs: Mutex<HashMap<String, Arc<HashMap<String, String>>>>,
+}
+
+impl I18n {
+    fn locale(&self) -> Locale {
+        self.locale
+            .lock()
+            .unwrap()
+            .clone()
+            .unwrap_or_else(system_locale)
+    }
+}
+
+/// `sys-locale` passes on what the OS says, which on Linux can be a POSIX name like
+/// `pt_BR.UTF-8` or `C`.
+fn system_locale() -> Locale {
+    sys_locale::get_locale()
+        .and_then(|tag| {
+            let tag = tag
+                .split(['.', '@'])
+                .next()
+                .unwrap_or_default()
+                .replace('_', "-");
+            Locale::try_from_str(&tag).ok()
+        })
+        .filter(|locale| !locale.id.language.is_unknown())
+        .unwrap_or(locale!("en-US"))
+}
+
+fn missing<E: std::fmt::Display>(locale: &Locale) -> impl Fn(E) -> I18nError + '_ {
+    move |e| I18nError::MissingData(format!("{locale}: {e}"))
+}
+
+/// Formatted with Latin digits, so what it says can be read back whatever digits the
+/// locale writes.
+fn with_latin_digits(locale: &Locale) -> Locale {
+    let mut locale = locale.clone();
+    locale
+        .extensions
+        .unicode
+        .keywords
+        .set(key!("nu"), value!("latn"));
+    locale
+}
+
+fn locale_info(locale: &Locale) -> Result<LocaleInfo, I18nError> {
+    let latin = with_latin_digits(locale);
+
+    // The first separator in 1,234,567.5 groups and the last one is the decimal point.
+    let sample = Decimal::try_from_f64(1_234_567.5, FloatPrecision::RoundTrip)
+        .map_err(|e| I18nError::InvalidInput(e.to_string()))?;
+    let number = DecimalFormatter::try_new((&latin).into(), Default::default())
+        .map_err(missing(locale))?
+        .format(&sample)
+        .to_string();
+    let separators: Vec<String> = number
+        .split(|c: char| c.is_ascii_digit())
+        .filter(|s| !s.is_empty())
+        .map(str::to_owned)
+        .collect();
+
+    let afternoon = Time::try_new(13, 0, 0, 0).expect("13:00 is a valid time");
+    let clock = NoCalendarFormatter::try_new((&latin).into(), T::hm())
+        .map_err(missing(locale))?
+        .format(&afternoon)
+        .to_string();
+
+    let week = WeekInformation::try_new(locale.into()).map_err(missing(locale))?;
+
+    Ok(LocaleInfo {
+        locale: locale.to_string(),
+        region: locale.id.region.map(|region| region.to_string()),
+        decimal_separator: separators.last().cloned().unwrap_or_else(|| ".".into()),
+        grouping_separator: if separators.len() > 1 {
+            separators[0].clone()
+        } else {
+            String::new()
+        },
+        uses_24_hour_clock: clock.contains("13"),
+        first_weekday: week.first_weekday as u8,
+    })
+}
+
+fn decimal(value: f64) -> Result<Decimal, I18nError> {
+    Decimal::try_from_f64(value, FloatPrecision::RoundTrip)
+        .map_err(|_| I18nError::InvalidInput(format!("{value} is not a finite number")))
+}
+
+fn format_number_in(locale: &Locale, value: f64, style: &NumberStyle) -> Result<String, I18nError> {
+    let mut number = decimal(value)?;
+    Ok(match style {
+        NumberStyle::Decimal => {
+            number.round(-3);
+            number.absolute.trim_end();
+            DecimalFormatter::try_new(locale.into(), Default::default())
+                .map_err(missing(locale))?
+                .format(&number)
+                .to_string()
+        }
+        NumberStyle::Percent => {
+            number.multiply_pow10(2);
+            number.round(0);
+            PercentFormatter::try_new(locale.into(), Default::default())
+                .map_err(missing(locale))?
+                .format(&number)
+                .to_string()
+        }
+        NumberStyle::Currency(code) => {
+            let currency = CurrencyType::try_from_str(&code.to_ascii_lowercase())
+                .ok()
+                .filter(|_| code.len() == 3 && code.chars().all(|c| c.is_ascii_alphabetic()))
+                .ok_or_else(|| {
+                    I18nError::InvalidInput(format!("`{code}` is not an ISO 4217 code"))
+                })?;
+            // Most currencies have cents; the data for the few that don't isn't exposed.
+            number.round(-2);
+            CurrencyFormatter::try_new_symbol(locale.into(), currency, Default::default())
+                .map_err(missing(locale))?
+                .format_fixed_decimal(&number)
+                .to_string()
+        }
+        NumberStyle::Compact => {
+            CompactDecimalFormatter::try_new_short(locale.into(), Default::default())
+                .map_err(missing(locale))?
+                .format(&number)
+                .to_string()
+        }
+    })
+}
+
+/// In the OS time zone.
+fn local_date_time(epoch_ms: i64) -> Result<DateTime<icu_calendar::Iso>, I18nError> {
+    let timestamp = jiff::Timestamp::from_millisecond(epoch_ms)
+        .map_err(|e| I18nError::InvalidInput(e.to_string()))?;
+    let civil = timestamp.to_zoned(jiff::tz::TimeZone::system()).datetime();
+    let invalid = |e: icu_calendar::RangeError| I18nError::InvalidInput(e.to_string());
+    Ok(DateTime {
+        date: Date::try_new_iso(civil.year().into(), civil.month() as u8, civil.day() as u8)
+            .map_err(|e| I18nError::InvalidInput(e.to_string()))?,
+        time: Time::try_new(
+            civil.hour() as u8,
+            civil.minute() as u8,
+            civil.second() as u8,
+            0,
+        )
+        .map_err(invalid)?,
+    })
+}
+
+fn format_date_in(locale: &Locale, epoch_ms: i64, style: DateStyle) -> Result<String, I18nError> {
+    let input = local_date_time(epoch_ms)?;
+    let prefs = locale.into();
+    let formatted =
+        match style {
+            DateStyle::Short => DateTimeFormatter::try_new(prefs, YMD::short())
+                .map(|f| f.format(&input).to_string()),
+            DateStyle::Medium => DateTimeFormatter::try_new(prefs, YMD::medium())
+                .map(|f| f.format(&input).to_string()),
+            DateStyle::Long => {
+                DateTimeFormatter::try_new(prefs, YMD::long()).map(|f| f.format(&input).to_string())
+            }
+            DateStyle::DateTime => DateTimeFormatter::try_new(prefs, YMD::medium().with_time_hm())
+                .map(|f| f.format(&input).to_string()),
+            DateStyle::Time => NoCalendarFormatter::try_new(prefs, T::hm())
+                .map(|f| f.format(&input.time).to_string()),
+        };
+    formatted.map_err(missing(locale))
+}
+
+/// The largest unit that fits, so 90 seconds ago is "1 minute ago". `Numeric::Auto`
+/// turns -1 day into "yesterday" and 0 seconds into "now".
+fn format_relative_in(locale: &Locale, epoch_ms: i64, now_ms: i64) -> Result<String, I18nError> {
+    const MINUTE: i64 = 60;
+    const HOUR: i64 = 60 * MINUTE;
+    const DAY: i64 = 24 * HOUR;
+    const WEEK: i64 = 7 * DAY;
+    const MONTH: i64 = 30 * DAY;
+    const YEAR: i64 = 365 * DAY;
+
+    let seconds = epoch_ms.saturating_sub(now_ms) / 1000;
+    let prefs = locale.into();
+    let mut options = RelativeTimeFormatterOptions::default();
+    options.numeric = Numeric::Auto;
+    let (formatter, count) = match seconds.unsigned_abs() as i64 {
+        s if s < MINUTE => (
+            RelativeTimeFormatter::try_new_long_second(prefs, options),
+            seconds,
+        ),
+        s if s < HOUR => (
+            RelativeTimeFormatter::try_new_long_minute(prefs, options),
+            seconds / MINUTE,
+        ),
+        s if s < DAY => (
+            RelativeTimeFormatter::try_new_long_hour(prefs, options),
+            seconds / HOUR,
+        ),
+        s if s < WEEK => (
+            RelativeTimeFormatter::try_new_long_day(prefs, options),
+            seconds / DAY,
+        ),
+        s if s < MONTH => (
+            RelativeTimeFormatter::try_new_long_week(prefs, options),
+            seconds / WEEK,
+        ),
+        s if s < YEAR => (
+            RelativeTimeFormatter::try_new_long_month(prefs, options),
+            seconds / MONTH,
+        ),
+        _ => (
+            RelativeTimeFormatter::try_new_long_year(prefs, options),
+            seconds / YEAR,
+        ),
+    };
+    Ok(formatter
+        .map_err(missing(locale))?
+        .format(Decimal::from(count))
+        .to_string())
+}
+
+/// `pt-BR` gives `["pt-BR", "pt", "en"]`: the tag, each shorter prefix, then the base.
+fn fallback_chain(lang: &str) -> Vec<String> {
+    let mut chain = Vec::new();
+    let mut tag = lang;
+    loop {
+        chain.push(tag.to_owned());
+        match tag.rfind('-') {
+            Some(end) => tag = &tag[..end],
+            None => break,
+        }
+    }
+    if !chain.iter().any(|tag| tag == BASE_LANGUAGE) {
+        chain.push(BASE_LANGUAGE.to_owned());
+    }
+    chain
+}
+
+/// Nested objects become dotted keys, so `{"menu": {"open": "Open"}}` is `menu.open`.
+fn flatten(prefix: &str, value: Value, out: &mut HashMap<String, String>) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                let key = if prefix.is_empty() {
+                    key
+                } else {
+                    format!("{prefix}.{key}")
+                };
+                flatten(&key, value, out);
+            }
+        }
+        Value::String(s) => {
+            out.insert(prefix.to_owned(), s);
+        }
+        other => {
+            out.insert(prefix.to_owned(), other.to_string());
+        }
+    }
+}
+
+fn read_catalog<R: Runtime>(
+    app: &AppHandle<R>,
+    tag: &str,
+) -> Result<Option<HashMap<String, String>>, I18nError> {
+    let path = app
+        .path()
+        .resolve(format!("i18n/{tag}.json"), BaseDirectory::Resource)
+        .map_err(|e| I18nError::Catalog(e.to_string()))?;
+    let text = match std::fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(I18nError::Catalog(format!("{}: {e}", path.display()))),
+    };
+    let json: Value = serde_json::from_str(&text)
+        .map_err(|e| I18nError::Catalog(format!("{}: {e}", path.display())))?;
+    let mut catalog = HashMap::new();
+    flatten("", json, &mut catalog);
+    Ok(Some(catalog))
+}
+
+fn catalog<R: Runtime>(
+    app: &AppHandle<R>,
+    i18n: &I18n,
+    lang: &str,
+) -> Result<Arc<HashMap<String, String>>, I18nError> {
+    let lang = Locale::try_from_str(lang)
+        .map_err(|_| I18nError::InvalidInput(format!("`{lang}` is not a BCP-47 tag")))?
+        .id
+        .to_string();
+    if let Some(catalog) = i18n.catalogs.lock().unwrap().get(&lang) {
+        return Ok(catalog.clone());
+    }
+    let mut merged = HashMap::new();
+    // The base first, so each more specific catalog overrides it.
+    for tag in fallback_chain(&lang).iter().rev() {
+        if let Some(catalog) = read_catalog(app, tag)? {
+            merged.extend(catalog);
+        }
+    }
+    let merged = Arc::new(merged);
+    i18n.catalogs.lock().unwrap().insert(lang, merged.clone());
+    Ok(merged)
+}
+
+fn refresh<R: Runtime>(app: &AppHandle<R>) {
+    let Some(i18n) = app.try_state::<I18n>() else {
+        return;
+    };
+    let current = system_locale();
+    {
+        let mut locale = i18n.locale.lock().unwrap();
+        if locale.as_ref() == Some(&current) {
+            return;
+        }
+        *locale = Some(current.clone());
+    }
+    i18n.catalogs.lock().unwrap().clear();
+    match locale_info(&current) {
+        Ok(info) => {
+            tracing::info!(locale = %current, "OS locale changed");
+            let _ = app.emit(LOCALE_CHANGED_EVENT, info);
+        }
+        Err(e) => tracing::warn!(error = %e, "failed to describe the new locale"),
+    }
+}
+
+pub fn init<R: Runtime>(app: &AppHandle<R>) {
+    let initial = system_locale();
+    tracing::debug!(locale = %initial, "initial OS locale");
+    *app.state::<I18n>().locale.lock().unwrap() = Some(initial);
+
+    let app = app.clone();
+    std::thread::spawn(move || loop {
+        std::thread::sleep(POLL_INTERVAL);
+        refresh(&app);
+    });
+}
+
+pub fn on_window_event<R: Runtime>(window: &Window<R>, event: &WindowEvent) {
+    if matches!(event, WindowEvent::Focused(true)) {
+        refresh(window.app_handle());
+    }
+}
+
+#[tauri::command]
+pub fn get_locale_info(i18n: State<'_, I18n>) -> Result<LocaleInfo, I18nError> {
+    locale_info(&i18n.locale())
+}
+
+#[tauri::command]
+pub fn format_number(
+    value: f64,
+    style: Option<NumberStyle>,
+    i18n: State<'_, I18n>,
+) -> Result<String, I18nError> {
+    format_number_in(&i18n.locale(), value, &style.unwrap_or_default())
+}
+
+#[tauri::command]
+pub fn format_date(
+    epoch_ms: i64,
+    style: Option<DateStyle>,
+    i18n: State<'_, I18n>,
+) -> Result<String, I18nError> {
+    format_date_in(&i18n.locale(), epoch_ms, style.unwrap_or_default())
+}
+
+/// Relative to now, like "3 hours ago" or "in 2 days".
+#[tauri::command]
+pub fn format_relative(epoch_ms: i64, i18n: State<'_, I18n>) -> Result<String, I18nError> {
+    let now_ms = jiff::Timestamp::now().as_millisecond();
+    format_relative_in(&i18n.locale(), epoch_ms, now_ms)
+}
+
+/// Every key for `lang`, falling back along its chain to `en`.
+#[tauri::command]
+pub fn load_translations<R: Runtime>(
+    lang: String,
+    app: AppHandle<R>,
+    i18n: State<'_, I18n>,
+) -> Result<HashMap<String, String>, I18nError> {
+    Ok(catalog(&app, &i18n, &lang)?.as_ref().clone())
+}
+
+/// One string; a key no catalog has comes back as itself, so the UI shows something
+/// greppable instead of failing.
+#[tauri::command]
+pub fn translate<R: Runtime>(
+    key: String,
+    lang: String,
+    app: AppHandle<R>,
+    i18n: State<'_, I18n>,
+) -> Result<String, I18nError> {
+    match catalog(&app, &i18n, &lang)?.get(&key) {
+        Some(text) => Ok(text.clone()),
+        None => {
+            tracing::debug!(%key, %lang, "missing translation");
+            Ok(key)
+        }
+    }
+}
@@ -0,0 +1,300 @@
+//! In-app purchases through StoreKit 2 on iOS and Google Play Billing on Android.
+//!
+//! Like [`crate::push`], this registers the native half of a plugin crate
+//! (`tauri-plugin-purchases`) directly so Rust holds the handle and can listen for
+//! transactions itself. Receipts are for UI; entitlements should come from the server
+//! validating `receipt_data`.
+
+use serde::Serialize;
+use tauri::{AppHandle, Runtime};
+
+#[cfg_attr(desktop, allow(dead_code))]
+pub const TRANSACTION_UPDATED_EVENT: &str = "iap://transaction-updated";
+
+#[cfg_attr(desktop, allow(dead_code))]
+#[derive(Debug, thiserror::Error, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "camelCase")]
+pub enum IapError {
+    #[error("in-app purchases are only available on iOS and Android")]
+    NotSupported,
+    #[error("the purchase was cancelled")]
+    Cancelled,
+    /// Awaiting approval, e.g. Ask to Buy; the transaction arrives later as
+    /// [`TRANSACTION_UPDATED_EVENT`].
+    #[error("the purchase is awaiting approval")]
+    Deferred,
+    #[error("store error: {0}")]
+    Store(String),
+}
+
+#[derive(Debug, Serialize)]
+pub struct IapProduct {
+    pub id: String,
+    pub title: String,
+    pub description: String,
+    pub price_formatted: String,
+    /// ISO 4217; empty when the store doesn't report it.
+    pub currency_code: String,
+    pub price_micros: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct IapReceipt {
+    pub transaction_id: String,
+    pub product_id: String,
+    pub purchase_date_ms: u64,
+    /// What the server validates: the signed StoreKit transaction on iOS, the purchase
+    /// token on Android.
+    pub receipt_data: String,
+}
+
+#[cfg(mobile)]
+mod native {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use serde::Serialize;
+    use serde_json::{json, Value};
+    use tauri::ipc::{Channel, InvokeResponseBody};
+    use tauri::plugin::mobile::PluginInvokeError;
+    use tauri::plugin::{PluginHandle, TauriPlugin};
+    use tauri::{AppHandle, Emitter, Manager, Runtime};
+    use tauri_plugin_purchases::{
+        GetProductsOptions, Product, ProductList, Purchase, PurchaseOptions, PurchaseOutcome,
+        PurchaseResult, PurchaseState, RestoredPurchases,
+    };
+
+    use super::{IapError, IapProduct, IapReceipt, TRANSACTION_UPDATED_EVENT};
+
+    #[cfg(target_os = "ios")]
+    tauri::ios_plugin_binding!(init_plugin_purchases);
+
+    struct Store<R: Runtime> {
+        handle: PluginHandle<R>,
+        listening: AtomicBool,
+    }
+
+    #[derive(Serialize)]
+    struct TransactionUpdated {
+        #[serde(flatten)]
+        receipt: IapReceipt,
+        /// Refunded or otherwise revoked by the store.
+        revoked: bool,
+    }
+
+    impl From<Product> for IapProduct {
+        fn from(product: Product) -> Self {
+            Self {
+                id: product.id,
+                title: product.title,
+                description: product.description,
+                price_formatted: product.display_price,
+                currency_code: product.currency,
+                price_micros: (product.price * 1_000_000.0).round() as i64,
+            }
+        }
+    }
+
+    impl From<Purchase> for IapReceipt {
+        fn from(purchase: Purchase) -> Self {
+            Self {
+                transaction_id: purchase.transaction_id,
+                product_id: purchase.product_id,
+                purchase_date_ms: purchase.purchased_at.max(0) as u64,
+                receipt_data: purchase.jws,
+            }
+        }
+    }
+
+    fn store_error(e: PluginInvokeError) -> IapError {
+        IapError::Store(e.to_string())
+    }
+
+    fn store<R: Runtime>(app: &AppHandle<R>) -> Result<tauri::State<'_, Store<R>>, IapError> {
+        app.try_state::<Store<R>>()
+            .ok_or_else(|| IapError::Store("purchases plugin not loaded".into()))
+    }
+
+    pub fn plugin<R: Runtime>() -> TauriPlugin<R> {
+        tauri::plugin::Builder::new("iap")
+            .setup(|app, api| {
+                #[cfg(target_os = "android")]
+                let handle =
+                    api.register_android_plugin("app.tauri.purchases", "PurchasesPlugin")?;
+                #[cfg(target_os = "ios")]
+                let handle = api.register_ios_plugin(init_plugin_purchases)?;
+                app.manage(Store {
+                    handle,
+                    listening: AtomicBool::new(false),
+                });
+                Ok(())
+            })
+            .build()
+    }
+
+    /// Transactions that completed while nobody listened stay queued by the store until
+    /// this runs, so it waits for the frontend rather than starting in setup.
+    pub async fn listen<R: Runtime>(app: &AppHandle<R>) -> Result<(), IapError> {
+        let store = store(app)?;
+        if store.listening.swap(true, Ordering::SeqCst) {
+            return Ok(());
+        }
+
+        let app = app.clone();
+        let channel = Channel::<Value>::new(move |body| {
+            if let InvokeResponseBody::Json(json) = body {
+                match serde_json::from_str::<Purchase>(&json) {
+                    Ok(purchase) => {
+                        let revoked = purchase.state == PurchaseState::Revoked;
+                        let _ = app.emit(
+                            TRANSACTION_UPDATED_EVENT,
+                            TransactionUpdated {
+                                receipt: purchase.into(),
+                                revoked,
+                            },
+                        );
+                    }
+                    Err(e) => tracing::warn!(error = %e, "unreadable transaction update"),
+                }
+            }
+            Ok(())
+        });
+        let result = async {
+            store
+                .handle
+                .run_mobile_plugin_async::<Value>(
+                    "registerListener",
+                    json!({ "event": "purchaseUpdated", "handler": channel }),
+                )
+                .await?;
+            store
+                .handle
+                .run_mobile_plugin_async::<Value>("startPurchaseUpdates", ())
+                .await
+        }
+        .await;
+        if result.is_err() {
+            store.listening.store(false, Ordering::SeqCst);
+        }
+        result.map(|_| ()).map_err(store_error)
+    }
+
+    pub async fn fetch_products<R: Runtime>(
+        app: &AppHandle<R>,
+        product_ids: Vec<String>,
+    ) -> Result<Vec<IapProduct>, IapError> {
+        let list: ProductList = store(app)?
+            .handle
+            .run_mobile_plugin_async("getProducts", GetProductsOptions { product_ids })
+            .await
+            .map_err(store_error)?;
+        Ok(list.products.into_iter().map(IapProduct::from).collect())
+    }
+
+    pub async fn purchase<R: Runtime>(
+        app: &AppHandle<R>,
+        product_id: String,
+    ) -> Result<IapReceipt, IapError> {
+        let options = PurchaseOptions {
+            product_id,
+            app_account_token: None,
+            quantity: None,
+        };
+        let result: PurchaseResult = store(app)?
+            .handle
+            .run_mobile_plugin_async("purchase", options)
+            .await
+            .map_err(store_error)?;
+        match (result.outcome, result.purchase) {
+            (PurchaseOutcome::Purchased, Some(purchase)) => Ok(purchase.into()),
+            (PurchaseOutcome::Purchased, None) => Err(IapError::Store(
+                "purchase completed without a transaction".into(),
+            )),
+            (PurchaseOutcome::Pending, _) => Err(IapError::Deferred),
+            (PurchaseOutcome::Cancelled, _) => Err(IapError::Cancelled),
+        }
+    }
+
+    pub async fn restore_purchases<R: Runtime>(
+        app: &AppHandle<R>,
+    ) -> Result<Vec<IapReceipt>, IapError> {
+        let restored: RestoredPurchases = store(app)?
+            .handle
+            .run_mobile_plugin_async("restorePurchases", ())
+            .await
+            .map_err(store_error)?;
+        Ok(restored
+            .purchases
+            .into_iter()
+            .filter(|purchase| purchase.state == PurchaseState::Purchased)
+            .map(IapReceipt::from)
+            .collect())
+    }
+}
+
+#[cfg(desktop)]
+mod native {
+    use tauri::{AppHandle, Runtime};
+
+    use super::{IapError, IapProduct, IapReceipt};
+
+    pub async fn listen<R: Runtime>(_app: &AppHandle<R>) -> Result<(), IapError> {
+        Err(IapError::NotSupported)
+    }
+
+    pub async fn fetch_products<R: Runtime>(
+        _app: &AppHandle<R>,
+        _product_ids: Vec<String>,
+    ) -> Result<Vec<IapProduct>, IapError> {
+        Err(IapError::NotSupported)
+    }
+
+    pub async fn purchase<R: Runtime>(
+        _app: &AppHandle<R>,
+        _product_id: String,
+    ) -> Result<IapReceipt, IapError> {
+        Err(IapError::NotSupported)
+    }
+
+    pub async fn restore_purchases<R: Runtime>(
+        _app: &AppHandle<R>,
+    ) -> Result<Vec<IapReceipt>, IapError> {
+        Err(IapError::NotSupported)
+    }
+}
+
+#[cfg(mobile)]
+pub fn plugin<R: Runtime>() -> tauri::plugin::TauriPlugin<R> {
+    native::plugin()
+}
+
+/// Called by the frontend once its [`TRANSACTION_UPDATED_EVENT`] listener is attached;
+/// transactions completed in the meantime, even in an earlier session, are delivered
+/// then.
+#[tauri::command]
+pub async fn iap_ready<R: Runtime>(app: AppHandle<R>) -> Result<(), IapError> {
+    native::listen(&app).await
+}
+
+/// Products unknown to the store are left out rather than failing the call.
+#[tauri::command]
+pub async fn fetch_products<R: Runtime>(
+    product_ids: Vec<String>,
+    app: AppHandle<R>,
+) -> Result<Vec<IapProduct>, IapError> {
+    native::fetch_products(&app, product_ids).await
+}
+
+#[tauri::command]
+pub async fn purchase<R: Runtime>(
+    product_id: String,
+    app: AppHandle<R>,
+) -> Result<IapReceipt, IapError> {
+    native::purchase(&app, product_id).await
+}
+
+/// Syncs with the store, which may prompt for the store account, and returns the
+/// purchases still owned.
+#[tauri::command]
+pub async fn restore_purchases<R: Runtime>(app: AppHandle<R>) -> Result<Vec<IapReceipt>, IapError> {
+    native::restore_purchases(&app).await
+}
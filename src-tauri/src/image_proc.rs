@@ -0,0 +1,317 @@
+//! Resizing, cropping and re-encoding images with the `image` crate. Sources are a path
+//! in the fs scope or a `data:` URL, so pasted and generated images work without being
+//! saved first; EXIF orientation is applied on decode, so coordinates and sizes refer to
+//! the image as it's shown.
+//!
+//! Resized and cropped images keep the format their destination's extension names;
+//! [`convert_image`] takes it explicitly instead.
+
+use std::fs;
+use std::io::{self, Cursor};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::metadata::Orientation;
+use image::{DynamicImage, ImageDecoder, ImageReader};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Runtime};
+
+use crate::scope;
+
+/// Quality for JPEG destinations picked by extension, where none was given.
+const DEFAULT_JPEG_QUALITY: u8 = 90;
+
+#[derive(Debug, thiserror::Error, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "camelCase")]
+pub enum ImageError {
+    #[error("path is not allowed: {0}")]
+    NotAllowed(String),
+    #[error("unsupported image format: {0}")]
+    UnsupportedFormat(String),
+    #[error("invalid input: {0}")]
+    InvalidInput(String),
+    #[error("could not decode image: {0}")]
+    Decode(String),
+    #[error("i/o error: {0}")]
+    Io(String),
+}
+
+impl From<io::Error> for ImageError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e.to_string())
+    }
+}
+
+impl From<image::ImageError> for ImageError {
+    fn from(e: image::ImageError) -> Self {
+        match e {
+            image::ImageError::Unsupported(e) => Self::UnsupportedFormat(e.to_string()),
+            image::ImageError::IoError(e) => Self::Io(e.to_string()),
+            e => Self::Decode(e.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ResizeFilter {
+    Nearest,
+    Triangle,
+    CatmullRom,
+    Gaussian,
+    /// The sharpest, and the slowest.
+    #[default]
+    Lanczos3,
+}
+
+impl From<ResizeFilter> for FilterType {
+    fn from(filter: ResizeFilter) -> Self {
+        match filter {
+            ResizeFilter::Nearest => FilterType::Nearest,
+            ResizeFilter::Triangle => FilterType::Triangle,
+            ResizeFilter::CatmullRom => FilterType::CatmullRom,
+            ResizeFilter::Gaussian => FilterType::Gaussian,
+            ResizeFilter::Lanczos3 => FilterType::Lanczos3,
+        }
+    }
+}
+
+/// `"png"`, `{"jpeg": {"quality": 80}}`, `"webP"`, `"gif"` or `"bmp"`.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ImageFormat {
+    Png,
+    /// Quality from 1 to 100. JPEG has no alpha, so transparency is dropped.
+    Jpeg {
+        quality: u8,
+    },
+    /// Lossless; `image` has no lossy WebP encoder.
+    WebP,
+    Gif,
+    Bmp,
+}
+
+impl ImageFormat {
+    fn from_extension(path: &Path) -> Result<Self, ImageError> {
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(str::to_ascii_lowercase)
+            .unwrap_or_default();
+        Ok(match extension.as_str() {
+            "png" => Self::Png,
+            "jpg" | "jpeg" => Self::Jpeg {
+                quality: DEFAULT_JPEG_QUALITY,
+            },
+            "webp" => Self::WebP,
+            "gif" => Self::Gif,
+            "bmp" => Self::Bmp,
+            _ => {
+                return Err(ImageError::UnsupportedFormat(format!(
+                    "no encoder for `{}`",
+                    path.display()
+                )))
+            }
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageStats {
+    /// Of the source, after EXIF orientation.
+    pub original_width: u32,
+    pub original_height: u32,
+    pub output_size_bytes: u64,
+    pub duration_ms: u64,
+}
+
+/// Either a path or the bytes from a `data:` URL.
+enum Source {
+    Path(PathBuf),
+    Bytes(Vec<u8>),
+}
+
+fn allowed<R: Runtime>(app: &AppHandle<R>, path: &str) -> Result<PathBuf, ImageError> {
+    scope::ensure_allowed(app, Path::new(path)).map_err(ImageError::NotAllowed)
+}
+
+/// Base64 or percent-encoded; the media type is ignored, since the bytes say what they are.
+fn data_url(url: &str) -> Result<Vec<u8>, ImageError> {
+    let invalid = || ImageError::InvalidInput("malformed data: URL".into());
+    let (meta, payload) = url
+        .strip_prefix("data:")
+        .and_then(|rest| rest.split_once(','))
+        .ok_or_else(invalid)?;
+    if meta.split(';').any(|param| param == "base64") {
+        BASE64.decode(payload.trim()).map_err(|_| invalid())
+    } else {
+        Ok(percent_encoding::percent_decode_str(payload).collect())
+    }
+}
+
+fn source<R: Runtime>(app: &AppHandle<R>, src: &str) -> Result<Source, ImageError> {
+    if src.starts_with("data:") {
+        data_url(src).map(Source::Bytes)
+    } else {
+        allowed(app, src).map(Source::Path)
+    }
+}
+
+fn decode(source: Source) -> Result<DynamicImage, ImageError> {
+    let mut decoder = match source {
+        Source::Path(path) => {
+            let reader = ImageReader::open(path)?.with_guessed_format()?;
+            if reader.format().is_none() {
+                return Err(ImageError::UnsupportedFormat("unrecognized image".into()));
+            }
+            Box::new(reader.into_decoder()?) as Box<dyn ImageDecoder>
+        }
+        Source::Bytes(bytes) => {
+            let reader = ImageReader::new(Cursor::new(bytes)).with_guessed_format()?;
+            if reader.format().is_none() {
+                return Err(ImageError::UnsupportedFormat("unrecognized image".into()));
+            }
+            Box::new(reader.into_decoder()?) as Box<dyn ImageDecoder>
+        }
+    };
+    let orientation = decoder.orientation().unwrap_or(Orientation::NoTransforms);
+    let mut image = DynamicImage::from_decoder(decoder)?;
+    image.apply_orientation(orientation);
+    Ok(image)
+}
+
+/// Writes beside `dest` and renames into place, so a failed encode never leaves half a
+/// file where the destination was. Returns the size written.
+fn encode(image: &DynamicImage, dest: &Path, format: ImageFormat) -> Result<u64, ImageError> {
+    let mut bytes = Vec::new();
+    let mut out = Cursor::new(&mut bytes);
+    match format {
+        ImageFormat::Jpeg { quality } => {
+            if !(1..=100).contains(&quality) {
+                return Err(ImageError::InvalidInput(format!(
+                    "JPEG quality {quality} is not 1 to 100"
+                )));
+            }
+            image
+                .to_rgb8()
+                .write_with_encoder(JpegEncoder::new_with_quality(&mut out, quality))?;
+        }
+        ImageFormat::Png => image.write_to(&mut out, image::ImageFormat::Png)?,
+        ImageFormat::WebP => image.write_to(&mut out, image::ImageFormat::WebP)?,
+        ImageFormat::Gif => image.write_to(&mut out, image::ImageFormat::Gif)?,
+        // BMP has no alpha in most readers.
+        ImageFormat::Bmp => {
+            DynamicImage::from(image.to_rgb8()).write_to(&mut out, image::ImageFormat::Bmp)?
+        }
+    }
+
+    let mut tmp = dest.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+    fs::write(&tmp, &bytes)?;
+    fs::rename(&tmp, dest).inspect_err(|_| {
+        let _ = fs::remove_file(&tmp);
+    })?;
+    Ok(bytes.len() as u64)
+}
+
+/// Decodes `src`, applies `edit`, and writes the result to `dst`, off the async runtime.
+async fn process<R: Runtime>(
+    app: &AppHandle<R>,
+    src: &str,
+    dst: &str,
+    format: Option<ImageFormat>,
+    edit: impl FnOnce(DynamicImage) -> Result<DynamicImage, ImageError> + Send + 'static,
+) -> Result<ImageStats, ImageError> {
+    let started = Instant::now();
+    let source = source(app, src)?;
+    let dest = allowed(app, dst)?;
+    let format = match format {
+        Some(format) => format,
+        None => ImageFormat::from_extension(&dest)?,
+    };
+    tauri::async_runtime::spawn_blocking(move || {
+        let image = decode(source)?;
+        let (original_width, original_height) = (image.width(), image.height());
+        let output_size_bytes = encode(&edit(image)?, &dest, format)?;
+        Ok(ImageStats {
+            original_width,
+            original_height,
+            output_size_bytes,
+            duration_ms: started.elapsed().as_millis() as u64,
+        })
+    })
+    .await
+    .map_err(|e| ImageError::Io(e.to_string()))?
+}
+
+/// Scales to exactly `width` by `height`, stretching if the aspect ratio differs.
+#[tauri::command]
+pub async fn resize_image<R: Runtime>(
+    src: String,
+    dst: String,
+    width: u32,
+    height: u32,
+    filter: Option<ResizeFilter>,
+    app: AppHandle<R>,
+) -> Result<ImageStats, ImageError> {
+    if width == 0 || height == 0 {
+        return Err(ImageError::InvalidInput(
+            "width and height must be at least 1".into(),
+        ));
+    }
+    let filter = FilterType::from(filter.unwrap_or_default());
+    process(&app, &src, &dst, None, move |image| {
+        Ok(image.resize_exact(width, height, filter))
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn crop_image<R: Runtime>(
+    src: String,
+    dst: String,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    app: AppHandle<R>,
+) -> Result<ImageStats, ImageError> {
+    if width == 0 || height == 0 {
+        return Err(ImageError::InvalidInput(
+            "width and height must be at least 1".into(),
+        ));
+    }
+    process(&app, &src, &dst, None, move |image| {
+        let fits = x
+            .checked_add(width)
+            .is_some_and(|right| right <= image.width())
+            && y.checked_add(height)
+                .is_some_and(|bottom| bottom <= image.height());
+        if !fits {
+            return Err(ImageError::InvalidInput(format!(
+                "{width}x{height} at {x},{y} doesn't fit in {}x{}",
+                image.width(),
+                image.height()
+            )));
+        }
+        Ok(image.crop_imm(x, y, width, height))
+    })
+    .await
+}
+
+/// Re-encodes `src` as `format`, whatever `dst`'s extension says.
+#[tauri::command]
+pub async fn convert_image<R: Runtime>(
+    src: String,
+    dst: String,
+    format: ImageFormat,
+    app: AppHandle<R>,
+) -> Result<ImageStats, ImageError> {
+    process(&app, &src, &dst, Some(format), Ok).await
+}
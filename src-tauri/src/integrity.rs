@@ -0,0 +1,198 @@
+//! Checks the installed resources against hashes taken at build time, so a file an
+//! antivirus quarantined, or an install that was only half copied, shows up as a damaged
+//! installation rather than as a blank window or a confusing error further on.
+//!
+//! `build.rs` hashes everything `bundle.resources` names into a manifest compiled into
+//! the binary. Hashing waits for the main window's first page load, or a few seconds if
+//! that never comes, so it doesn't hold up first paint; the report is kept for
+//! [`get_integrity_report`] and [`FAILED_EVENT`] is emitted if anything is missing or
+//! modified. Dev builds load resources from the source tree and skip the check unless
+//! [`VERIFY_ENV`] is set. Android skips it too: its resources are assets inside the APK,
+//! which the package manager has already verified against the APK signature, and
+//! `resource_dir()` there isn't a directory the files can be read from.
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use tauri::{AppHandle, Emitter, Listener, Manager, Runtime, State};
+use tokio::sync::{watch, Notify};
+
+/// Emitted with the [`IntegrityReport`] when any resource is missing or modified.
+pub const FAILED_EVENT: &str = "integrity://failed";
+
+/// Set to any value to verify in a dev build too.
+const VERIFY_ENV: &str = "LAYERS_VERIFY_RESOURCES";
+
+/// How long to wait for the first page load before verifying anyway; a damaged
+/// install may well never load one.
+const FIRST_PAINT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Bundled name to SHA-256, as hex.
+const MANIFEST: &str = include_str!(concat!(env!("OUT_DIR"), "/resources.manifest.json"));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum IntegrityStatus {
+    Intact,
+    Damaged,
+    /// A dev build, Android, or a manifest or resource dir that couldn't be read.
+    Skipped,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IntegrityReport {
+    pub status: IntegrityStatus,
+    pub checked: usize,
+    /// Bundled names, relative to the resource dir.
+    pub missing: Vec<String>,
+    pub modified: Vec<String>,
+    pub duration_ms: u64,
+}
+
+impl IntegrityReport {
+    fn skipped() -> Self {
+        Self {
+            status: IntegrityStatus::Skipped,
+            checked: 0,
+            missing: Vec::new(),
+            modified: Vec::new(),
+            duration_ms: 0,
+        }
+    }
+}
+
+pub struct Integrity {
+    /// `None` until verification finishes.
+    report: watch::Sender<Option<IntegrityReport>>,
+}
+
+impl Default for Integrity {
+    fn default() -> Self {
+        Self {
+            report: watch::Sender::new(None),
+        }
+    }
+}
+
+fn sha256(path: &Path) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut BufReader::new(File::open(path)?), &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Blocks for the whole check.
+fn verify_resources(dir: &Path, manifest: &BTreeMap<String, String>) -> IntegrityReport {
+    let started = Instant::now();
+    let mut missing = Vec::new();
+    let mut modified = Vec::new();
+    for (name, expected) in manifest {
+        match sha256(&dir.join(name)) {
+            Ok(actual) if actual == *expected => {}
+            Ok(_) => modified.push(name.clone()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => missing.push(name.clone()),
+            // Unreadable, which is what a quarantined file usually looks like on Windows.
+            Err(e) => {
+                tracing::warn!(resource = %name, error = %e, "couldn't read resource");
+                missing.push(name.clone());
+            }
+        }
+    }
+    let status = if missing.is_empty() && modified.is_empty() {
+        IntegrityStatus::Intact
+    } else {
+        IntegrityStatus::Damaged
+    };
+    IntegrityReport {
+        status,
+        checked: manifest.len(),
+        missing,
+        modified,
+        duration_ms: started.elapsed().as_millis() as u64,
+    }
+}
+
+async fn verify<R: Runtime>(app: &AppHandle<R>) -> IntegrityReport {
+    let manifest: BTreeMap<String, String> = match serde_json::from_str(MANIFEST) {
+        Ok(manifest) => manifest,
+        Err(e) => {
+            tracing::warn!(error = %e, "unreadable resource manifest");
+            return IntegrityReport::skipped();
+        }
+    };
+    let dir = match app.path().resource_dir() {
+        Ok(dir) => dir,
+        Err(e) => {
+            tracing::warn!(error = %e, "no resource dir to verify");
+            return IntegrityReport::skipped();
+        }
+    };
+    tauri::async_runtime::spawn_blocking(move || verify_resources(&dir, &manifest))
+        .await
+        .unwrap_or_else(|e| {
+            tracing::warn!(error = %e, "resource verification failed");
+            IntegrityReport::skipped()
+        })
+}
+
+/// Schedules verification for after first paint. Call early in setup, so the page load
+/// it waits for can't be missed.
+pub fn init<R: Runtime>(app: &AppHandle<R>) {
+    app.manage(Integrity::default());
+    let dev = tauri::is_dev() && std::env::var_os(VERIFY_ENV).is_none();
+    if dev || cfg!(target_os = "android") {
+        app.state::<Integrity>()
+            .report
+            .send_replace(Some(IntegrityReport::skipped()));
+        return;
+    }
+
+    let painted = Arc::new(Notify::new());
+    {
+        let painted = painted.clone();
+        app.once(crate::startup::READY_EVENT, move |_| painted.notify_one());
+    }
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let _ = tokio::time::timeout(FIRST_PAINT_TIMEOUT, painted.notified()).await;
+        let report = verify(&app).await;
+        app.state::<Integrity>()
+            .report
+            .send_replace(Some(report.clone()));
+        match report.status {
+            IntegrityStatus::Damaged => {
+                tracing::error!(
+                    missing = ?report.missing,
+                    modified = ?report.modified,
+                    "installation is damaged"
+                );
+                let _ = app.emit(FAILED_EVENT, &report);
+            }
+            IntegrityStatus::Intact => tracing::info!(
+                resources = report.checked,
+                duration_ms = report.duration_ms,
+                "resources verified"
+            ),
+            IntegrityStatus::Skipped => {}
+        }
+    });
+}
+
+/// Waits for verification if it hasn't finished yet.
+#[tauri::command]
+pub async fn get_integrity_report(
+    integrity: State<'_, Integrity>,
+) -> Result<IntegrityReport, String> {
+    let mut report = integrity.report.subscribe();
+    let report = report
+        .wait_for(Option::is_some)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(report.clone().expect("waited for a report"))
+}
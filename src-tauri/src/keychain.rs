@@ -0,0 +1,219 @@
+//! The OS credential store: keyring's native backends on desktop and iOS, and the Android
+//! Keystore through `plugins/keystore`, where keyring has no backend.
+
+use serde::Serialize;
+use tauri::{AppHandle, Runtime};
+
+/// Errors the frontend can branch on, e.g. to offer a retry after an OS permission
+/// prompt or to fall back to the password-based [`crate::secure_store`].
+#[derive(Debug, thiserror::Error, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "camelCase")]
+pub enum KeychainError {
+    #[error("no credential stored for this service and account")]
+    NotFound,
+    #[error("access to the OS credential store was denied: {0}")]
+    AccessDenied(String),
+    #[error("no OS credential store is available: {0}")]
+    BackendUnavailable(String),
+}
+
+pub(crate) use native::Entry;
+
+#[cfg(not(target_os = "android"))]
+mod native {
+    use std::marker::PhantomData;
+
+    use tauri::{AppHandle, Runtime};
+
+    use super::KeychainError;
+
+    impl From<keyring::Error> for KeychainError {
+        fn from(e: keyring::Error) -> Self {
+            match e {
+                keyring::Error::NoEntry => Self::NotFound,
+                keyring::Error::NoStorageAccess(_) => Self::AccessDenied(e.to_string()),
+                _ => Self::BackendUnavailable(e.to_string()),
+            }
+        }
+    }
+
+    /// keyring doesn't need the app; the Android entry does.
+    pub struct Entry<R: Runtime>(keyring::Entry, PhantomData<fn() -> R>);
+
+    impl<R: Runtime> Entry<R> {
+        pub fn new(
+            _app: &AppHandle<R>,
+            service: &str,
+            account: &str,
+        ) -> Result<Self, KeychainError> {
+            Ok(Self(keyring::Entry::new(service, account)?, PhantomData))
+        }
+
+        pub fn set_password(&self, secret: &str) -> Result<(), KeychainError> {
+            Ok(self.0.set_password(secret)?)
+        }
+
+        pub fn get_password(&self) -> Result<String, KeychainError> {
+            Ok(self.0.get_password()?)
+        }
+
+        pub fn delete_credential(&self) -> Result<(), KeychainError> {
+            Ok(self.0.delete_credential()?)
+        }
+    }
+}
+
+#[cfg(target_os = "android")]
+mod native {
+    use serde::{Deserialize, Serialize};
+    use serde_json::Value;
+    use tauri::plugin::mobile::PluginInvokeError;
+    use tauri::plugin::PluginHandle;
+    use tauri::{AppHandle, Manager, Runtime};
+
+    use super::KeychainError;
+
+    /// `plugins/keystore`, registered by [`super::plugin`].
+    pub struct Keystore<R: Runtime>(pub PluginHandle<R>);
+
+    #[derive(Serialize)]
+    struct EntryArgs<'a> {
+        service: &'a str,
+        account: &'a str,
+    }
+
+    #[derive(Serialize)]
+    struct SetArgs<'a> {
+        service: &'a str,
+        account: &'a str,
+        secret: &'a str,
+    }
+
+    #[derive(Deserialize)]
+    struct Secret {
+        secret: String,
+    }
+
+    fn from_plugin(e: PluginInvokeError) -> KeychainError {
+        match e {
+            PluginInvokeError::InvokeRejected(response) => {
+                let message = response.message.unwrap_or_default();
+                match response.code.as_deref() {
+                    Some("NotFound") => KeychainError::NotFound,
+                    _ => KeychainError::BackendUnavailable(message),
+                }
+            }
+            e => KeychainError::BackendUnavailable(e.to_string()),
+        }
+    }
+
+    pub struct Entry<R: Runtime> {
+        keystore: PluginHandle<R>,
+        service: String,
+        account: String,
+    }
+
+    impl<R: Runtime> Entry<R> {
+        pub fn new(
+            app: &AppHandle<R>,
+            service: &str,
+            account: &str,
+        ) -> Result<Self, KeychainError> {
+            let keystore = app
+                .try_state::<Keystore<R>>()
+                .map(|keystore| keystore.0.clone())
+                .ok_or_else(|| {
+                    KeychainError::BackendUnavailable("keystore plugin not loaded".into())
+                })?;
+            Ok(Self {
+                keystore,
+                service: service.to_string(),
+                account: account.to_string(),
+            })
+        }
+
+        fn args(&self) -> EntryArgs<'_> {
+            EntryArgs {
+                service: &self.service,
+                account: &self.account,
+            }
+        }
+
+        pub fn set_password(&self, secret: &str) -> Result<(), KeychainError> {
+            let args = SetArgs {
+                service: &self.service,
+                account: &self.account,
+                secret,
+            };
+            self.keystore
+                .run_mobile_plugin::<Value>("set", args)
+                .map(drop)
+                .map_err(from_plugin)
+        }
+
+        pub fn get_password(&self) -> Result<String, KeychainError> {
+            self.keystore
+                .run_mobile_plugin::<Secret>("get", self.args())
+                .map(|found| found.secret)
+                .map_err(from_plugin)
+        }
+
+        pub fn delete_credential(&self) -> Result<(), KeychainError> {
+            self.keystore
+                .run_mobile_plugin::<Value>("delete", self.args())
+                .map(drop)
+                .map_err(from_plugin)
+        }
+    }
+}
+
+#[cfg(target_os = "android")]
+pub fn plugin<R: Runtime>() -> tauri::plugin::TauriPlugin<R> {
+    use tauri::Manager;
+    // Linked for its native half; it has no Rust API.
+    use tauri_plugin_keystore as _;
+
+    tauri::plugin::Builder::new("keystore")
+        .setup(|app, api| {
+            let handle = api.register_android_plugin("com.layers.keystore", "KeystorePlugin")?;
+            app.manage(native::Keystore(handle));
+            Ok(())
+        })
+        .build()
+}
+
+pub(crate) fn entry<R: Runtime>(
+    app: &AppHandle<R>,
+    service: &str,
+    account: &str,
+) -> Result<Entry<R>, KeychainError> {
+    Entry::new(app, service, account)
+}
+
+#[tauri::command]
+pub async fn keychain_set<R: Runtime>(
+    service: String,
+    account: String,
+    secret: String,
+    app: AppHandle<R>,
+) -> Result<(), KeychainError> {
+    entry(&app, &service, &account)?.set_password(&secret)
+}
+
+#[tauri::command]
+pub async fn keychain_get<R: Runtime>(
+    service: String,
+    account: String,
+    app: AppHandle<R>,
+) -> Result<String, KeychainError> {
+    entry(&app, &service, &account)?.get_password()
+}
+
+#[tauri::command]
+pub async fn keychain_delete<R: Runtime>(
+    service: String,
+    account: String,
+    app: AppHandle<R>,
+) -> Result<(), KeychainError> {
+    entry(&app, &service, &account)?.delete_credential()
+}
@@ -1,22 +1,572 @@
-use std::time::Instant;
+use std::sync::Mutex;
+
+use tauri::{Manager, RunEvent};
+
+mod asset_protocol;
+mod attachments;
+mod audit;
+#[cfg(desktop)]
+mod auth_window;
+#[cfg(desktop)]
+mod autostart;
+mod backup;
+mod badge;
+mod batch;
+mod biometrics;
+mod ble;
+mod calendar;
+mod camera;
+mod capture;
+mod clipboard;
+mod compression;
+mod config;
+mod connectivity;
+mod contacts;
+mod crash;
+mod db;
+#[cfg(feature = "db-encryption")]
+mod db_encryption;
+mod deep_link;
+mod deep_link_router;
+#[cfg(feature = "dev-server")]
+mod dev_server;
+mod device_info;
+mod downloads;
+mod drag_drop;
+mod export;
+mod fonts;
+mod fs_stream;
+mod geolocation;
+mod haptics;
+#[cfg(desktop)]
+mod hotkeys;
+mod http_cache;
+mod http_config;
+mod http_middleware;
+mod i18n;
+mod iap;
+mod image_proc;
+mod integrity;
+mod keychain;
+mod lifecycle;
+mod local_server;
+mod logging;
+mod media;
+mod metrics;
+mod migrations;
+mod net;
+#[cfg(target_os = "android")]
+mod nfc;
+mod notifications;
+mod os_index;
+mod pdf;
+mod platform;
+mod profiler;
+mod push;
+#[cfg(mobile)]
+mod quick_actions;
+mod rate_limit;
+mod scanner;
+mod scope;
+mod search;
+mod secrets;
+mod secure_store;
+mod settings;
+mod share;
+#[cfg(desktop)]
+mod shortcuts;
+#[cfg(desktop)]
+mod single_instance;
+mod speech;
+mod sql_encrypted;
+mod sql_stream;
+mod startup;
+mod sync;
+mod tasks;
+mod theme;
+mod thumbnails;
+#[cfg(desktop)]
+mod titlebar;
+#[cfg(desktop)]
+mod tray;
+mod updates;
+mod watcher;
+mod websocket;
+#[cfg(desktop)]
+mod window_manager;
+mod window_state;
+
+use db::Db;
+use fs_stream::Transfers;
+use migrations::MigrationRunner;
+use secrets::Secrets;
+use startup::StartupTimer;
+use watcher::Watchers;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-    let start = Instant::now();
+    let logging = logging::init();
+    crash::install();
+    let mut timer = StartupTimer::new();
+    #[cfg(desktop)]
+    let start_hidden = autostart::launched_minimized();
+    #[cfg(mobile)]
+    let start_hidden = false;
+
+    let builder = tauri::Builder::default();
+    #[cfg(desktop)]
+    let builder = if single_instance::enabled() {
+        timer.plugin(builder, "single-instance", single_instance::plugin)
+    } else {
+        builder
+    };
+    let builder = timer.plugin(builder, "opener", tauri_plugin_opener::init);
+    let builder = timer.plugin(builder, "fs", tauri_plugin_fs::init);
+    let builder = timer.plugin(builder, "dialog", tauri_plugin_dialog::init);
+    let builder = timer.plugin(builder, "store", || {
+        tauri_plugin_store::Builder::default().build()
+    });
+    let builder = timer.plugin(builder, "sql", || {
+        tauri_plugin_sql::Builder::default().build()
+    });
+    let builder = timer.plugin(builder, "notification", tauri_plugin_notification::init);
+    let builder = timer.plugin(builder, "deep-link", tauri_plugin_deep_link::init);
+    let builder = timer.plugin(builder, "http", tauri_plugin_http::init);
+    #[cfg(mobile)]
+    let builder = timer.plugin(
+        builder,
+        "clipboard-manager",
+        tauri_plugin_clipboard_manager::init,
+    );
+    #[cfg(mobile)]
+    let builder = timer.plugin(builder, "biometric", tauri_plugin_biometric::init);
+    #[cfg(target_os = "android")]
+    let builder = timer.plugin(builder, "device-credential", biometrics::plugin);
+    #[cfg(target_os = "android")]
+    let builder = timer.plugin(builder, "keystore", keychain::plugin);
+    #[cfg(mobile)]
+    let builder = timer.plugin(builder, "device-info", tauri_plugin_device_info::init);
+    #[cfg(mobile)]
+    let builder = timer.plugin(builder, "geolocation", tauri_plugin_geolocation::init);
+    #[cfg(mobile)]
+    let builder = timer.plugin(builder, "push", push::plugin);
+    #[cfg(mobile)]
+    let builder = timer.plugin(builder, "quick-actions", quick_actions::plugin);
+    #[cfg(mobile)]
+    let builder = timer.plugin(builder, "iap", iap::plugin);
+    #[cfg(mobile)]
+    let builder = timer.plugin(builder, "share", share::plugin);
+    #[cfg(mobile)]
+    let builder = timer.plugin(builder, "pdf", pdf::plugin);
+    #[cfg(mobile)]
+    let builder = timer.plugin(builder, "scanner", scanner::plugin);
+    #[cfg(mobile)]
+    let builder = timer.plugin(builder, "contacts", contacts::plugin);
+    #[cfg(mobile)]
+    let builder = timer.plugin(builder, "calendar", calendar::plugin);
+    #[cfg(mobile)]
+    let builder = timer.plugin(builder, "camera", camera::plugin);
+    #[cfg(target_os = "android")]
+    let builder = timer.plugin(builder, "nfc", nfc::plugin);
+    #[cfg(mobile)]
+    let builder = timer.plugin(builder, "ble", ble::plugin);
+    #[cfg(mobile)]
+    let builder = timer.plugin(builder, "badge", badge::plugin);
+    #[cfg(mobile)]
+    let builder = timer.plugin(builder, "haptics", haptics::plugin);
+    #[cfg(mobile)]
+    let builder = timer.plugin(builder, "device", device_info::plugin);
+    #[cfg(mobile)]
+    let builder = timer.plugin(builder, "speech", speech::plugin);
+    let builder = timer.plugin(builder, "pinned-http", http_config::plugin);
+    let builder = timer.plugin(builder, "system-theme", theme::plugin);
+    #[cfg(desktop)]
+    let builder = timer.plugin(builder, "global-shortcut", shortcuts::plugin);
+    #[cfg(desktop)]
+    let builder = builder.on_window_event(window_manager::on_window_event);
+    #[cfg(desktop)]
+    let builder = builder.on_window_event(titlebar::on_window_event);
+
+    builder
+        .manage(Mutex::new(timer))
+        .manage(audit::AuditLog::default())
+        .manage(asset_protocol::Assets::default())
+        .manage(attachments::Attachments::default())
+        .manage(MigrationRunner::default())
+        .manage(Watchers::default())
+        .manage(Transfers::default())
+        .manage(Secrets::default())
+        .manage(ble::Ble::default())
+        .manage(downloads::Downloads::default())
+        .manage(drag_drop::DroppedPaths::default())
+        .manage(fonts::Fonts::default())
+        .manage(geolocation::GeoWatches::default())
+        .manage(i18n::I18n::default())
+        .manage(lifecycle::Lifecycle::default())
+        .manage(lifecycle::QuitGuards::default())
+        .manage(local_server::LocalServers::default())
+        .manage(metrics::Metrics::default())
+        .manage(profiler::Profiler::default())
+        .manage(push::LaunchNotification::default())
+        .manage(rate_limit::RateLimiter::default())
+        .manage(speech::Speech::default())
+        .manage(sql_encrypted::EncryptedDbs::default())
+        .manage(sql_stream::SqlStreams::default())
+        .manage(thumbnails::Thumbnails::default())
+        .manage(websocket::WebSockets::default())
+        .register_asynchronous_uri_scheme_protocol(media::SCHEME, media::handle)
+        .register_asynchronous_uri_scheme_protocol(asset_protocol::SCHEME, asset_protocol::handle)
+        .setup(move |app| {
+            let _span = tracing::info_span!("setup").entered();
+            logging.attach(app.handle().clone());
+            crash::init(app.handle());
+            integrity::init(app.handle());
+            backup::apply_pending_restore(app.handle())?;
+            settings::init(app.handle());
+            config::init(app.handle());
+            app.manage(Db::open(app.handle())?);
+            batch::init(app.handle());
+            http_middleware::init(app.handle());
+            deep_link::init(app)?;
+            window_state::init(app.handle(), !start_hidden)?;
+            #[cfg(desktop)]
+            tray::init(app)?;
+            #[cfg(desktop)]
+            shortcuts::init(app.handle())?;
+            #[cfg(desktop)]
+            app.manage(hotkeys::Hotkeys::default());
+            #[cfg(desktop)]
+            app.manage(window_manager::WindowManager::default());
+            #[cfg(desktop)]
+            app.manage(titlebar::Titlebars::default());
+            updates::init(app.handle());
+            migrations::spawn(app.handle());
+            audit::spawn(app.handle());
+            connectivity::init(app.handle());
+            sync::spawn(app.handle());
+            notifications::spawn(app.handle());
+            theme::init(app.handle());
+            device_info::init(app.handle());
+            i18n::init(app.handle());
+            fonts::init(app.handle());
+            #[cfg(feature = "dev-server")]
+            {
+                app.manage(dev_server::DevServer::default());
+                dev_server::init(app.handle());
+            }
+            tasks::init(app.handle());
+            app.manage(net::Net::new(app.handle()));
 
-    tauri::Builder::default()
-        .plugin(tauri_plugin_opener::init())
-        .plugin(tauri_plugin_fs::init())
-        .plugin(tauri_plugin_dialog::init())
-        .plugin(tauri_plugin_store::Builder::default().build())
-        .plugin(tauri_plugin_sql::Builder::default().build())
-        .plugin(tauri_plugin_notification::init())
-        .plugin(tauri_plugin_deep_link::init())
-        .plugin(tauri_plugin_http::init())
-        .setup(move |_app| {
-            println!("Tauri setup time: {:?}", start.elapsed());
+            let timer = app.state::<Mutex<StartupTimer>>();
+            let mut timer = timer.lock().unwrap();
+            timer.mark_setup_complete();
+            tracing::info!(
+                setup_duration_ms = timer.metrics().setup_duration_ms,
+                "tauri setup complete"
+            );
             Ok(())
         })
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .on_page_load(startup::on_page_load)
+        .on_window_event(window_state::on_window_event)
+        .on_window_event(watcher::on_window_event)
+        .on_window_event(notifications::on_window_event)
+        .on_window_event(theme::on_window_event)
+        .on_window_event(i18n::on_window_event)
+        .on_window_event(drag_drop::on_window_event)
+        .on_window_event(websocket::on_window_event)
+        .on_window_event(lifecycle::on_window_event)
+        .invoke_handler(audit::wrap(rate_limit::wrap(tauri::generate_handler![
+            startup::get_startup_metrics,
+            asset_protocol::register_asset,
+            asset_protocol::unregister_asset,
+            attachments::attachment_add,
+            attachments::attachment_link,
+            attachments::attachment_unlink,
+            attachments::attachment_gc,
+            audit::get_audit_log,
+            audit::clear_audit_log,
+            audit::set_audit_mode,
+            #[cfg(desktop)]
+            auth_window::open_auth_window,
+            #[cfg(desktop)]
+            auth_window::clear_webview_data,
+            #[cfg(desktop)]
+            auth_window::get_cookies,
+            #[cfg(desktop)]
+            autostart::set_autostart,
+            #[cfg(desktop)]
+            autostart::get_autostart,
+            backup::export_backup,
+            backup::import_backup,
+            badge::set_badge_count,
+            badge::clear_badge,
+            badge::get_badge_count,
+            batch::batch_invoke,
+            biometrics::authenticate_biometric,
+            biometrics::biometric_availability,
+            biometrics::authenticate_with_device_credential,
+            ble::ble_start_scan,
+            ble::ble_stop_scan,
+            ble::ble_connect,
+            ble::ble_disconnect,
+            ble::ble_read_characteristic,
+            ble::ble_write_characteristic,
+            calendar::request_calendar_permission,
+            calendar::list_calendars,
+            calendar::query_events,
+            calendar::create_event,
+            camera::take_photo,
+            camera::record_video,
+            capture::capture_window,
+            capture::capture_to_file,
+            capture::capture_sequence,
+            clipboard::clipboard_write_text,
+            clipboard::clipboard_read_text,
+            clipboard::clipboard_write_html,
+            clipboard::clipboard_write_image,
+            clipboard::clipboard_read_image,
+            clipboard::clipboard_has,
+            clipboard::clipboard_write,
+            clipboard::clipboard_read,
+            compression::compress_file,
+            compression::decompress_file,
+            config::get_config,
+            config::set_config,
+            config::reset_config,
+            connectivity::get_connectivity,
+            connectivity::get_connectivity_config,
+            connectivity::set_connectivity_config,
+            connectivity::set_probe_config,
+            connectivity::set_metered_hint,
+            contacts::request_contacts_permission,
+            contacts::get_contacts,
+            contacts::create_contact,
+            crash::get_pending_crash_reports,
+            crash::submit_crash_reports,
+            crash::discard_crash_reports,
+            db::db_bulk_insert,
+            db::db_query_paged,
+            #[cfg(feature = "db-encryption")]
+            db_encryption::db_set_passphrase,
+            #[cfg(feature = "db-encryption")]
+            db_encryption::db_unlock,
+            #[cfg(feature = "db-encryption")]
+            db_encryption::db_unlock_cached,
+            #[cfg(feature = "db-encryption")]
+            db_encryption::db_change_passphrase,
+            #[cfg(feature = "db-encryption")]
+            db_encryption::db_forget_cached_key,
+            #[cfg(feature = "db-encryption")]
+            db_encryption::db_encryption_status,
+            #[cfg(feature = "dev-server")]
+            dev_server::set_asset_latency,
+            #[cfg(feature = "dev-server")]
+            dev_server::get_dev_server_info,
+            deep_link::deep_link_ready,
+            device_info::get_device_info,
+            device_info::get_battery_status,
+            downloads::start_download,
+            downloads::pause_download,
+            downloads::resume_download,
+            downloads::cancel_download,
+            downloads::list_downloads,
+            drag_drop::ingest_dropped_file,
+            export::export_query,
+            fonts::list_system_fonts,
+            fonts::refresh_font_cache,
+            fonts::get_font_data,
+            fs_stream::read_file_stream,
+            fs_stream::write_file_stream,
+            fs_stream::write_file_chunk,
+            fs_stream::finish_write_stream,
+            fs_stream::copy_file_with_progress,
+            fs_stream::cancel_transfer,
+            geolocation::get_current_position,
+            geolocation::watch_position,
+            geolocation::stop_watching_position,
+            haptics::haptic_feedback,
+            #[cfg(desktop)]
+            hotkeys::register_hotkey,
+            #[cfg(desktop)]
+            hotkeys::unregister_hotkey,
+            http_cache::cached_fetch,
+            http_cache::clear_http_cache,
+            http_config::pinned_http_request,
+            http_middleware::add_mock,
+            i18n::get_locale_info,
+            i18n::format_number,
+            i18n::format_date,
+            i18n::format_relative,
+            i18n::load_translations,
+            i18n::translate,
+            iap::iap_ready,
+            iap::fetch_products,
+            iap::purchase,
+            iap::restore_purchases,
+            image_proc::resize_image,
+            image_proc::crop_image,
+            image_proc::convert_image,
+            integrity::get_integrity_report,
+            keychain::keychain_set,
+            keychain::keychain_get,
+            keychain::keychain_delete,
+            lifecycle::get_app_state,
+            lifecycle::register_quit_guard,
+            lifecycle::quit_guard_release,
+            local_server::start_local_server,
+            local_server::stop_local_server,
+            local_server::set_local_server_config,
+            logging::get_recent_logs,
+            logging::set_log_level,
+            logging::open_log_folder,
+            media::media_import,
+            media::media_delete,
+            metrics::get_resource_metrics,
+            metrics::start_metrics_recording,
+            metrics::stop_metrics_recording,
+            metrics::export_metrics_csv,
+            migrations::run_migrations,
+            migrations::get_schema_version,
+            net::net_fetch,
+            net::get_net_stats,
+            migrations::rollback_to,
+            #[cfg(target_os = "android")]
+            nfc::start_nfc_scan,
+            #[cfg(target_os = "android")]
+            nfc::stop_nfc_scan,
+            #[cfg(target_os = "android")]
+            nfc::write_ndef,
+            notifications::schedule_notification,
+            notifications::cancel_scheduled,
+            notifications::list_scheduled,
+            os_index::index_document,
+            os_index::remove_document,
+            os_index::reindex_all,
+            pdf::print_to_pdf,
+            pdf::generate_pdf,
+            platform::get_platform_info,
+            profiler::start_profiling,
+            profiler::stop_profiling,
+            profiler::get_current_memory,
+            push::register_for_push,
+            push::take_launch_notification,
+            #[cfg(mobile)]
+            quick_actions::set_quick_actions,
+            #[cfg(mobile)]
+            quick_actions::take_launch_quick_action,
+            rate_limit::configure_rate_limit,
+            scanner::scan_barcode,
+            search::search_notes,
+            secrets::secret_set,
+            secrets::secret_get,
+            secrets::secret_delete,
+            secrets::secret_list_keys,
+            secrets::secret_backend_info,
+            secure_store::secure_store_set,
+            secure_store::secure_store_get,
+            settings::get_settings,
+            settings::update_settings,
+            share::share,
+            share::share_text,
+            share::share_file,
+            #[cfg(desktop)]
+            shortcuts::register_shortcut,
+            #[cfg(desktop)]
+            shortcuts::unregister_shortcut,
+            #[cfg(desktop)]
+            shortcuts::list_shortcuts,
+            speech::list_voices,
+            speech::speak,
+            speech::stop_speaking,
+            speech::start_dictation,
+            speech::stop_dictation,
+            sql_encrypted::open_encrypted_db,
+            sql_encrypted::close_encrypted_db,
+            sql_encrypted::encrypted_query,
+            sql_encrypted::encrypted_execute,
+            sql_encrypted::change_passphrase,
+            sql_stream::stream_query,
+            sql_stream::cancel_stream,
+            sync::queue_mutation,
+            sync::sync_now,
+            sync::resolve_conflict,
+            sync::get_pending_count,
+            sync::get_sync_status,
+            sync::get_sync_config,
+            sync::set_sync_config,
+            tasks::spawn_task,
+            tasks::enqueue_task,
+            tasks::cancel_task,
+            tasks::list_tasks,
+            theme::get_current_theme,
+            theme::get_system_theme,
+            theme::set_window_theme,
+            thumbnails::get_thumbnail,
+            thumbnails::prewarm_thumbnails,
+            thumbnails::thumbnail_cache_size,
+            thumbnails::clear_thumbnail_cache,
+            #[cfg(desktop)]
+            tray::update_tray_tooltip,
+            #[cfg(desktop)]
+            tray::set_tray_status,
+            #[cfg(desktop)]
+            tray::quit_app,
+            watcher::start_watch,
+            watcher::stop_watch,
+            watcher::watch_path,
+            watcher::unwatch,
+            updates::check_for_update,
+            updates::download_update,
+            updates::install_update,
+            websocket::ws_connect,
+            websocket::ws_send,
+            websocket::ws_set_resubscribe,
+            websocket::ws_close,
+            #[cfg(desktop)]
+            window_manager::open_window,
+            #[cfg(desktop)]
+            window_manager::open_role_window,
+            #[cfg(desktop)]
+            window_manager::close_window,
+            #[cfg(desktop)]
+            window_manager::list_windows,
+            #[cfg(desktop)]
+            window_manager::emit_to_all,
+            #[cfg(desktop)]
+            titlebar::window_minimize,
+            #[cfg(desktop)]
+            titlebar::window_toggle_maximize,
+            #[cfg(desktop)]
+            titlebar::window_close,
+            #[cfg(desktop)]
+            titlebar::window_start_dragging,
+            #[cfg(desktop)]
+            titlebar::get_window_controls_metrics,
+            window_state::reset_window_state,
+        ])))
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| match event {
+            // Closing the last window quits on Windows and Linux, but a macOS app stays
+            // in the dock; `code` is only `None` when no explicit `exit` was requested.
+            #[cfg(target_os = "macos")]
+            RunEvent::ExitRequested {
+                code: None, api, ..
+            } => api.prevent_exit(),
+            // An exit held for quit guards comes back through here when they're done.
+            RunEvent::ExitRequested { code, api, .. }
+                if !lifecycle::before_quit(app, code, &api) =>
+            {
+                window_state::save_all(app)
+            }
+            #[cfg(target_os = "macos")]
+            RunEvent::Reopen {
+                has_visible_windows: false,
+                ..
+            } => window_manager::reopen(app),
+            RunEvent::Exit => connectivity::shutdown(app),
+            #[cfg(mobile)]
+            RunEvent::WindowEvent { event, .. } => lifecycle::handle(app, &event),
+            _ => {}
+        });
 }
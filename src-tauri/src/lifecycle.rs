@@ -0,0 +1,323 @@
+//! Foreground/background transitions on iOS and Android, emitted to the frontend and
+//! used to pause background work. Desktop apps aren't suspended, so there the state
+//! stays [`AppLifecycleState::Active`].
+//!
+//! Suspension is announced twice, as [`BACKGROUND_EVENT`] and [`SUSPEND_EVENT`], and so
+//! is resuming; the `suspend`/`resume` pair is the one to use. Memory warnings reach
+//! [`memory_warning`] through `plugins/device`.
+//!
+//! Quitting emits [`BEFORE_QUIT_EVENT`] everywhere. A window that called
+//! [`register_quit_guard`] is given [`QUIT_GRACE`] to call [`quit_guard_release`],
+//! typically once unsaved edits are flushed, before the app exits anyway. Asking to quit
+//! again while waiting quits straight away.
+
+use std::collections::HashSet;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, ExitRequestApi, Manager, Runtime, State, Window, WindowEvent};
+use tokio::sync::Notify;
+
+#[cfg_attr(desktop, allow(dead_code))]
+pub const FOREGROUND_EVENT: &str = "lifecycle://foreground";
+#[cfg_attr(desktop, allow(dead_code))]
+pub const BACKGROUND_EVENT: &str = "lifecycle://background";
+#[cfg_attr(desktop, allow(dead_code))]
+pub const SUSPEND_EVENT: &str = "lifecycle://suspend";
+#[cfg_attr(desktop, allow(dead_code))]
+pub const RESUME_EVENT: &str = "lifecycle://resume";
+#[cfg_attr(desktop, allow(dead_code))]
+pub const MEMORY_WARNING_EVENT: &str = "lifecycle://memory-warning";
+pub const BEFORE_QUIT_EVENT: &str = "lifecycle://before-quit";
+
+/// How long quitting waits for guards; long enough to write a document, short enough
+/// that a hung page doesn't look like a hung app.
+pub const QUIT_GRACE: Duration = Duration::from_secs(3);
+
+#[cfg_attr(desktop, allow(dead_code))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AppLifecycleState {
+    Active,
+    Background,
+    /// Visible but without focus, e.g. behind the iOS app switcher or a system dialog.
+    Inactive,
+}
+
+#[cfg_attr(desktop, allow(dead_code))]
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Transition {
+    timestamp_ms: u64,
+}
+
+#[cfg_attr(desktop, allow(dead_code))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum MemoryPressure {
+    /// Worth dropping caches.
+    Moderate,
+    /// The OS is about to start killing apps, this one included.
+    Critical,
+}
+
+#[cfg_attr(desktop, allow(dead_code))]
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct MemoryWarning {
+    pressure: MemoryPressure,
+    timestamp_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BeforeQuit {
+    /// Whether the app is waiting for guards, so there is time to save.
+    waiting: bool,
+    grace_ms: u64,
+}
+
+pub struct Lifecycle(Mutex<AppLifecycleState>);
+
+impl Default for Lifecycle {
+    fn default() -> Self {
+        Self(Mutex::new(AppLifecycleState::Active))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum QuitPhase {
+    #[default]
+    Running,
+    /// Exit is being held for the pending guards.
+    Waiting,
+    /// The next exit request is allowed through.
+    Exiting,
+}
+
+#[derive(Default)]
+struct QuitState {
+    phase: QuitPhase,
+    /// Labels of windows that asked to be waited for.
+    guards: HashSet<String>,
+    /// Guards not yet released in the current quit.
+    pending: HashSet<String>,
+}
+
+#[derive(Default)]
+pub struct QuitGuards {
+    state: Mutex<QuitState>,
+    released: Notify,
+}
+
+impl QuitGuards {
+    /// Drops `label` from the current quit, waking the exit once nothing is pending.
+    fn release(&self, label: &str) {
+        let mut state = self.state.lock().unwrap();
+        if state.pending.remove(label)
+            && state.pending.is_empty()
+            && state.phase == QuitPhase::Waiting
+        {
+            self.released.notify_one();
+        }
+    }
+}
+
+#[cfg_attr(desktop, allow(dead_code))]
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
+
+/// Applies a window event from the run loop. Suspension pauses the connectivity poller
+/// and syncs the log file, since the OS may kill a backgrounded app without warning.
+#[cfg(mobile)]
+pub fn handle<R: Runtime>(app: &AppHandle<R>, event: &WindowEvent) {
+    let Some(lifecycle) = app.try_state::<Lifecycle>() else {
+        return;
+    };
+    let mut state = lifecycle.0.lock().unwrap();
+    let next = match (event, *state) {
+        (WindowEvent::Suspended, _) => AppLifecycleState::Background,
+        (WindowEvent::Resumed, _) => AppLifecycleState::Active,
+        // Focus changes while backgrounded are the window going away; only
+        // `Resumed` brings the app back.
+        (WindowEvent::Focused(_), AppLifecycleState::Background) => return,
+        (WindowEvent::Focused(true), _) => AppLifecycleState::Active,
+        (WindowEvent::Focused(false), _) => AppLifecycleState::Inactive,
+        _ => return,
+    };
+    if *state == next {
+        return;
+    }
+    let previous = std::mem::replace(&mut *state, next);
+    drop(state);
+    tracing::debug!(?previous, ?next, "lifecycle transition");
+
+    let transition = Transition {
+        timestamp_ms: now_ms(),
+    };
+    match next {
+        AppLifecycleState::Background => {
+            crate::connectivity::set_foreground(app, false);
+            if let Some(control) = app.try_state::<crate::logging::LogControl>() {
+                if let Err(e) = control.flush() {
+                    tracing::warn!(error = %e, "failed to sync log file");
+                }
+            }
+            let flushing = app.clone();
+            tauri::async_runtime::spawn(async move { flush_storage(&flushing).await });
+            let _ = app.emit(BACKGROUND_EVENT, transition.clone());
+            let _ = app.emit(SUSPEND_EVENT, transition);
+        }
+        AppLifecycleState::Active if previous == AppLifecycleState::Background => {
+            crate::connectivity::set_foreground(app, true);
+            let _ = app.emit(FOREGROUND_EVENT, transition.clone());
+            let _ = app.emit(RESUME_EVENT, transition);
+        }
+        _ => {}
+    }
+}
+
+/// Android kills backgrounded apps without notice, so the WAL is folded into the
+/// database and open stores are written out while there's still time.
+#[cfg(mobile)]
+async fn flush_storage<R: Runtime>(app: &AppHandle<R>) {
+    use tauri_plugin_store::StoreExt;
+
+    if let Ok(pool) = app.state::<crate::db::Db>().pool() {
+        if let Err(e) = sqlx::query("PRAGMA wal_checkpoint(TRUNCATE)")
+            .execute(pool)
+            .await
+        {
+            tracing::warn!(error = %e, "failed to checkpoint the WAL");
+        }
+    }
+    // Stores live in the app data dir; only the ones open in this process can be dirty.
+    let Some(entries) = app
+        .path()
+        .app_data_dir()
+        .ok()
+        .and_then(|dir| std::fs::read_dir(dir).ok())
+    else {
+        return;
+    };
+    for name in entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|name| name.ends_with(".json"))
+    {
+        if let Some(store) = app.get_store(&name) {
+            if let Err(e) = store.save() {
+                tracing::warn!(store = %name, error = %e, "failed to flush store");
+            }
+        }
+    }
+}
+
+/// Called by `plugins/device` when the OS asks apps to give memory back.
+#[cfg(mobile)]
+pub fn memory_warning<R: Runtime>(app: &AppHandle<R>, pressure: MemoryPressure) {
+    tracing::warn!(?pressure, "memory warning");
+    let warning = MemoryWarning {
+        pressure,
+        timestamp_ms: now_ms(),
+    };
+    let _ = app.emit(MEMORY_WARNING_EVENT, warning);
+}
+
+/// Applies an exit request from the run loop, returning whether exit is being held for
+/// quit guards. Once they're all released or [`QUIT_GRACE`] is up, the app exits itself
+/// with the requested code.
+pub fn before_quit<R: Runtime>(
+    app: &AppHandle<R>,
+    code: Option<i32>,
+    api: &ExitRequestApi,
+) -> bool {
+    let Some(guards) = app.try_state::<QuitGuards>() else {
+        return false;
+    };
+    let mut state = guards.state.lock().unwrap();
+    match state.phase {
+        QuitPhase::Exiting => return false,
+        QuitPhase::Waiting => {
+            tracing::info!("quit requested again; not waiting for quit guards");
+            state.phase = QuitPhase::Exiting;
+            return false;
+        }
+        QuitPhase::Running => {}
+    }
+    let waiting = !state.guards.is_empty();
+    let _ = app.emit(
+        BEFORE_QUIT_EVENT,
+        BeforeQuit {
+            waiting,
+            grace_ms: QUIT_GRACE.as_millis() as u64,
+        },
+    );
+    if !waiting {
+        state.phase = QuitPhase::Exiting;
+        return false;
+    }
+    state.pending = state.guards.clone();
+    state.phase = QuitPhase::Waiting;
+    drop(state);
+    api.prevent_exit();
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let guards = app.state::<QuitGuards>();
+        if tokio::time::timeout(QUIT_GRACE, guards.released.notified())
+            .await
+            .is_err()
+        {
+            let pending = guards.state.lock().unwrap().pending.clone();
+            tracing::warn!(?pending, "quit guards not released in time");
+        }
+        let mut state = guards.state.lock().unwrap();
+        if state.phase == QuitPhase::Exiting {
+            // Quit was forced in the meantime.
+            return;
+        }
+        state.phase = QuitPhase::Exiting;
+        drop(state);
+        app.exit(code.unwrap_or(0));
+    });
+    true
+}
+
+/// A closed window can't release its guard.
+pub fn on_window_event<R: Runtime>(window: &Window<R>, event: &WindowEvent) {
+    if let WindowEvent::Destroyed = event {
+        if let Some(guards) = window.try_state::<QuitGuards>() {
+            guards.state.lock().unwrap().guards.remove(window.label());
+            guards.release(window.label());
+        }
+    }
+}
+
+/// Makes quitting wait for this window's [`quit_guard_release`], for up to
+/// [`QUIT_GRACE`]. Lasts until the window closes.
+#[tauri::command]
+pub fn register_quit_guard<R: Runtime>(window: Window<R>, guards: State<'_, QuitGuards>) {
+    guards
+        .state
+        .lock()
+        .unwrap()
+        .guards
+        .insert(window.label().to_owned());
+}
+
+/// Lets a quit in progress go ahead as far as this window is concerned. Does nothing
+/// outside of one.
+#[tauri::command]
+pub fn quit_guard_release<R: Runtime>(window: Window<R>, guards: State<'_, QuitGuards>) {
+    guards.release(window.label());
+}
+
+#[tauri::command]
+pub fn get_app_state(lifecycle: State<'_, Lifecycle>) -> AppLifecycleState {
+    *lifecycle.0.lock().unwrap()
+}
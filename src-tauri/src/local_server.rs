@@ -0,0 +1,325 @@
+//! Static file servers on `127.0.0.1` for test fixtures: pages, media and API stubs the
+//! webview can load over real HTTP, which custom protocols and `asset://` don't cover.
+//! Each server runs until stopped or the app exits, and only answers `GET`, `HEAD` and
+//! CORS preflights.
+
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+
+use http_body_util::Full;
+use hyper::body::{Bytes, Incoming};
+use hyper::header::{self, HeaderValue};
+use hyper::{Method, Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Runtime, State};
+use tokio::net::TcpListener;
+use tokio::sync::oneshot;
+
+use crate::scope;
+
+#[derive(Debug, thiserror::Error, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "camelCase")]
+pub enum LocalServerError {
+    #[error("port {0} is already in use")]
+    PortInUse(u16),
+    #[error("directory not found: {0}")]
+    DirectoryNotFound(String),
+    #[error("no local server is running on port {0}")]
+    NotRunning(u16),
+    #[error("path not allowed: {0}")]
+    Scope(String),
+    #[error("local server failed: {0}")]
+    Io(String),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CorsConfig {
+    pub allow_origin: String,
+    pub allow_methods: Vec<String>,
+    pub allow_headers: Vec<String>,
+    /// How long browsers may cache a preflight; `None` leaves it to the browser.
+    pub max_age_secs: Option<u32>,
+}
+
+impl Default for CorsConfig {
+    fn default() -> Self {
+        Self {
+            allow_origin: "*".into(),
+            allow_methods: vec!["GET".into(), "HEAD".into(), "OPTIONS".into()],
+            allow_headers: vec!["*".into()],
+            max_age_secs: None,
+        }
+    }
+}
+
+/// Applies to every running server from its next request on.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LocalServerConfig {
+    /// `None` sends no CORS headers at all.
+    pub cors: Option<CorsConfig>,
+}
+
+impl Default for LocalServerConfig {
+    fn default() -> Self {
+        Self {
+            cors: Some(CorsConfig::default()),
+        }
+    }
+}
+
+pub struct ServerHandle {
+    root: PathBuf,
+    shutdown: oneshot::Sender<()>,
+}
+
+#[derive(Default)]
+pub struct LocalServers {
+    servers: Mutex<HashMap<u16, ServerHandle>>,
+    config: Arc<RwLock<LocalServerConfig>>,
+}
+
+/// By extension only: fixtures are mostly text formats, which have no magic bytes for
+/// [`infer`] to find, and browsers refuse scripts and styles served with the wrong type.
+fn content_type(path: &Path) -> &'static str {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_ascii_lowercase)
+        .unwrap_or_default();
+    match extension.as_str() {
+        "html" | "htm" => "text/html; charset=utf-8",
+        "js" | "mjs" => "text/javascript; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "json" | "map" => "application/json",
+        "txt" => "text/plain; charset=utf-8",
+        "md" | "markdown" => "text/markdown; charset=utf-8",
+        "csv" => "text/csv; charset=utf-8",
+        "xml" => "application/xml",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "avif" => "image/avif",
+        "ico" => "image/x-icon",
+        "wasm" => "application/wasm",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "ttf" => "font/ttf",
+        "otf" => "font/otf",
+        "mp3" => "audio/mpeg",
+        "wav" => "audio/wav",
+        "ogg" => "audio/ogg",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        "pdf" => "application/pdf",
+        "zip" => "application/zip",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Maps the URL path onto `root`, or `None` if it would leave it. Symlinks inside the
+/// root are followed only as far as they stay under it.
+fn resolve(root: &Path, uri_path: &str) -> Option<PathBuf> {
+    let decoded = percent_encoding::percent_decode_str(uri_path)
+        .decode_utf8()
+        .ok()?;
+    let mut path = root.to_path_buf();
+    for component in Path::new(decoded.trim_start_matches('/')).components() {
+        match component {
+            Component::Normal(part) => path.push(part),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+    if path.is_dir() {
+        path.push("index.html");
+    }
+    let resolved = dunce::canonicalize(&path).ok()?;
+    (resolved.starts_with(root) && resolved.is_file()).then_some(resolved)
+}
+
+fn with_cors(
+    mut response: Response<Full<Bytes>>,
+    cors: Option<&CorsConfig>,
+) -> Response<Full<Bytes>> {
+    let Some(cors) = cors else {
+        return response;
+    };
+    let headers = response.headers_mut();
+    let mut set = |name, value: String| {
+        if let Ok(value) = HeaderValue::from_str(&value) {
+            headers.insert(name, value);
+        }
+    };
+    set(
+        header::ACCESS_CONTROL_ALLOW_ORIGIN,
+        cors.allow_origin.clone(),
+    );
+    set(
+        header::ACCESS_CONTROL_ALLOW_METHODS,
+        cors.allow_methods.join(", "),
+    );
+    set(
+        header::ACCESS_CONTROL_ALLOW_HEADERS,
+        cors.allow_headers.join(", "),
+    );
+    if let Some(max_age) = cors.max_age_secs {
+        set(header::ACCESS_CONTROL_MAX_AGE, max_age.to_string());
+    }
+    response
+}
+
+fn respond(
+    status: StatusCode,
+    content_type: &str,
+    body: impl Into<Bytes>,
+) -> Response<Full<Bytes>> {
+    let mut response = Response::new(Full::new(body.into()));
+    *response.status_mut() = status;
+    if let Ok(value) = HeaderValue::from_str(content_type) {
+        response.headers_mut().insert(header::CONTENT_TYPE, value);
+    }
+    response
+}
+
+fn json_error(status: StatusCode, error: &str, path: &str) -> Response<Full<Bytes>> {
+    let body = serde_json::json!({ "error": error, "path": path });
+    respond(status, "application/json", body.to_string())
+}
+
+async fn handle(
+    request: Request<Incoming>,
+    root: Arc<PathBuf>,
+    config: Arc<RwLock<LocalServerConfig>>,
+) -> Result<Response<Full<Bytes>>, Infallible> {
+    let cors = config.read().unwrap().cors.clone();
+    let uri_path = request.uri().path().to_string();
+    let response = match *request.method() {
+        Method::OPTIONS => respond(StatusCode::NO_CONTENT, "text/plain", Bytes::new()),
+        Method::GET | Method::HEAD => match resolve(&root, &uri_path) {
+            Some(path) => match tokio::fs::read(&path).await {
+                Ok(contents) => {
+                    let len = contents.len();
+                    let body = if request.method() == Method::HEAD {
+                        Bytes::new()
+                    } else {
+                        Bytes::from(contents)
+                    };
+                    let mut response = respond(StatusCode::OK, content_type(&path), body);
+                    response
+                        .headers_mut()
+                        .insert(header::CONTENT_LENGTH, HeaderValue::from(len));
+                    response
+                }
+                Err(e) => {
+                    tracing::warn!(path = %path.display(), error = %e, "local server read failed");
+                    json_error(StatusCode::INTERNAL_SERVER_ERROR, "readFailed", &uri_path)
+                }
+            },
+            None => json_error(StatusCode::NOT_FOUND, "notFound", &uri_path),
+        },
+        _ => json_error(
+            StatusCode::METHOD_NOT_ALLOWED,
+            "methodNotAllowed",
+            &uri_path,
+        ),
+    };
+    Ok(with_cors(response, cors.as_ref()))
+}
+
+/// Serves `root_dir` on `127.0.0.1:port` and returns the bound port, which is the one
+/// the OS picked when `port` is 0.
+#[tauri::command]
+pub async fn start_local_server<R: Runtime>(
+    root_dir: String,
+    port: u16,
+    app: AppHandle<R>,
+    servers: State<'_, LocalServers>,
+) -> Result<u16, LocalServerError> {
+    let root = dunce::canonicalize(&root_dir)
+        .ok()
+        .filter(|root| root.is_dir())
+        .ok_or_else(|| LocalServerError::DirectoryNotFound(root_dir.clone()))?;
+    let root = scope::ensure_allowed(&app, &root).map_err(LocalServerError::Scope)?;
+
+    let listener = TcpListener::bind(("127.0.0.1", port))
+        .await
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::AddrInUse => LocalServerError::PortInUse(port),
+            _ => LocalServerError::Io(e.to_string()),
+        })?;
+    let port = listener
+        .local_addr()
+        .map_err(|e| LocalServerError::Io(e.to_string()))?
+        .port();
+
+    let (shutdown, mut stopped) = oneshot::channel();
+    servers.servers.lock().unwrap().insert(
+        port,
+        ServerHandle {
+            root: root.clone(),
+            shutdown,
+        },
+    );
+
+    let root = Arc::new(root);
+    let config = servers.config.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let stream = tokio::select! {
+                accepted = listener.accept() => match accepted {
+                    Ok((stream, _)) => stream,
+                    Err(e) => {
+                        tracing::warn!(port, error = %e, "local server accept failed");
+                        continue;
+                    }
+                },
+                _ = &mut stopped => break,
+            };
+            let root = root.clone();
+            let config = config.clone();
+            tauri::async_runtime::spawn(async move {
+                let service = hyper::service::service_fn(move |request| {
+                    handle(request, root.clone(), config.clone())
+                });
+                let served = hyper::server::conn::http1::Builder::new()
+                    .serve_connection(TokioIo::new(stream), service)
+                    .await;
+                if let Err(e) = served {
+                    tracing::debug!(port, error = %e, "local server connection closed");
+                }
+            });
+        }
+        tracing::info!(port, "local server stopped");
+    });
+    tracing::info!(port, root = %root_dir, "local server started");
+    Ok(port)
+}
+
+/// Stops accepting connections on `port`; requests already being served finish.
+#[tauri::command]
+pub fn stop_local_server(
+    port: u16,
+    servers: State<'_, LocalServers>,
+) -> Result<(), LocalServerError> {
+    let server = servers
+        .servers
+        .lock()
+        .unwrap()
+        .remove(&port)
+        .ok_or(LocalServerError::NotRunning(port))?;
+    tracing::debug!(port, root = %server.root.display(), "stopping local server");
+    let _ = server.shutdown.send(());
+    Ok(())
+}
+
+#[tauri::command]
+pub fn set_local_server_config(config: LocalServerConfig, servers: State<'_, LocalServers>) {
+    *servers.config.write().unwrap() = config;
+}
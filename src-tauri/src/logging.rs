@@ -0,0 +1,408 @@
+use std::backtrace::Backtrace;
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, OnceLock, TryLockError};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tauri::{AppHandle, Emitter, Manager, State};
+use tauri_plugin_opener::OpenerExt;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::{Context, SubscriberExt};
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{reload, EnvFilter, Layer, Registry};
+
+pub const ENTRY_EVENT: &str = "log://entry";
+
+/// Entries logged before the app handle exists are kept and flushed once it does.
+const EARLY_BUFFER_LIMIT: usize = 256;
+
+/// JSONL log in the app log dir, rotated to `layers.1.jsonl` and so on once it reaches
+/// [`MAX_FILE_BYTES`]; the current file and four rotated ones are kept.
+const LOG_FILE_STEM: &str = "layers";
+const MAX_FILE_BYTES: u64 = 5 * 1024 * 1024;
+const KEPT_FILES: usize = 5;
+
+/// Only these reach the frontend console; everything goes to the file.
+const FORWARDED_LEVEL: Level = Level::WARN;
+
+/// Lines written to the file are also kept here, for crash reports.
+const RECENT_LIMIT: usize = 200;
+static RECENT: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogEntry {
+    pub timestamp_ms: u64,
+    pub level: String,
+    pub target: String,
+    pub message: String,
+    pub fields: Map<String, Value>,
+}
+
+#[derive(Default)]
+struct FieldVisitor {
+    message: String,
+    fields: Map<String, Value>,
+}
+
+impl Visit for FieldVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message = value.to_string();
+        } else {
+            self.fields.insert(field.name().to_string(), value.into());
+        }
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.fields.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.fields.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.fields.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.fields.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            self.message = format!("{value:?}");
+        } else {
+            self.fields
+                .insert(field.name().to_string(), format!("{value:?}").into());
+        }
+    }
+}
+
+fn to_entry(event: &Event<'_>) -> LogEntry {
+    let mut visitor = FieldVisitor::default();
+    event.record(&mut visitor);
+    let metadata = event.metadata();
+    LogEntry {
+        timestamp_ms: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis() as u64),
+        level: metadata.level().to_string(),
+        target: metadata.target().to_string(),
+        message: visitor.message,
+        fields: visitor.fields,
+    }
+}
+
+thread_local! {
+    /// Set while emitting so anything logged by the emit path itself isn't re-emitted.
+    static EMITTING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Forwards WARN and above to the frontend as [`ENTRY_EVENT`]s for the in-app console.
+#[derive(Clone, Default)]
+pub struct TauriEventLayer {
+    app: Arc<OnceLock<AppHandle>>,
+    early: Arc<Mutex<Vec<LogEntry>>>,
+}
+
+impl TauriEventLayer {
+    /// Starts emitting to the frontend and flushes anything logged during startup.
+    pub fn attach(&self, app: AppHandle) {
+        let early = std::mem::take(&mut *self.early.lock().unwrap());
+        for entry in early {
+            let _ = app.emit(ENTRY_EVENT, entry);
+        }
+        let _ = self.app.set(app);
+    }
+}
+
+impl<S: Subscriber> Layer<S> for TauriEventLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        if EMITTING.with(Cell::get) || *event.metadata().level() > FORWARDED_LEVEL {
+            return;
+        }
+
+        let entry = to_entry(event);
+
+        match self.app.get() {
+            Some(app) => {
+                EMITTING.with(|e| e.set(true));
+                let _ = app.emit(ENTRY_EVENT, entry);
+                EMITTING.with(|e| e.set(false));
+            }
+            None => {
+                let mut early = self.early.lock().unwrap();
+                if early.len() < EARLY_BUFFER_LIMIT {
+                    early.push(entry);
+                }
+            }
+        }
+    }
+}
+
+fn log_path(dir: &Path, index: usize) -> PathBuf {
+    match index {
+        0 => dir.join(format!("{LOG_FILE_STEM}.jsonl")),
+        n => dir.join(format!("{LOG_FILE_STEM}.{n}.jsonl")),
+    }
+}
+
+struct RotatingFile {
+    dir: PathBuf,
+    file: File,
+    size: u64,
+}
+
+impl RotatingFile {
+    fn open(dir: &Path) -> io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(log_path(dir, 0))?;
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            size: file.metadata()?.len(),
+            file,
+        })
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        let _ = fs::remove_file(log_path(&self.dir, KEPT_FILES - 1));
+        for index in (0..KEPT_FILES - 1).rev() {
+            let _ = fs::rename(log_path(&self.dir, index), log_path(&self.dir, index + 1));
+        }
+        *self = Self::open(&self.dir)?;
+        Ok(())
+    }
+
+    /// Unbuffered, so a line is on disk before a panic aborts the process.
+    fn write_line(&mut self, line: &[u8]) -> io::Result<()> {
+        if self.size > 0 && self.size + line.len() as u64 > MAX_FILE_BYTES {
+            self.rotate()?;
+        }
+        self.file.write_all(line)?;
+        self.size += line.len() as u64;
+        Ok(())
+    }
+}
+
+/// Writes every event that passes the filter to the rotating JSONL file.
+#[derive(Clone, Default)]
+pub struct FileLayer {
+    file: Arc<Mutex<Option<RotatingFile>>>,
+    early: Arc<Mutex<Vec<Vec<u8>>>>,
+}
+
+impl FileLayer {
+    fn attach(&self, dir: &Path) -> io::Result<()> {
+        let mut file = RotatingFile::open(dir)?;
+        for line in std::mem::take(&mut *self.early.lock().unwrap()) {
+            file.write_line(&line)?;
+        }
+        *self.file.lock().unwrap() = Some(file);
+        Ok(())
+    }
+}
+
+impl<S: Subscriber> Layer<S> for FileLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let Ok(mut line) = serde_json::to_vec(&to_entry(event)) else {
+            return;
+        };
+        if let Ok(mut recent) = RECENT.lock() {
+            if recent.len() == RECENT_LIMIT {
+                recent.pop_front();
+            }
+            recent.push_back(String::from_utf8_lossy(&line).into_owned());
+        }
+        line.push(b'\n');
+
+        match &mut *self.file.lock().unwrap() {
+            Some(file) => {
+                let _ = file.write_line(&line);
+            }
+            None => {
+                let mut early = self.early.lock().unwrap();
+                if early.len() < EARLY_BUFFER_LIMIT {
+                    early.push(line);
+                }
+            }
+        }
+    }
+}
+
+type FilterHandle = reload::Handle<EnvFilter, Registry>;
+
+/// Runtime control over logging, managed once the log dir is known.
+pub struct LogControl {
+    filter: FilterHandle,
+    dir: PathBuf,
+    file: Arc<Mutex<Option<RotatingFile>>>,
+}
+
+impl LogControl {
+    /// Lines are written unbuffered, but may still sit in the OS cache; this syncs
+    /// them before the app is backgrounded and possibly killed.
+    #[cfg_attr(desktop, allow(dead_code))]
+    pub fn flush(&self) -> io::Result<()> {
+        match &*self.file.lock().unwrap() {
+            Some(file) => file.file.sync_data(),
+            None => Ok(()),
+        }
+    }
+
+    /// Replaces the filter with `directives`, or with the startup one for `None`.
+    pub fn set_filter(&self, directives: Option<&str>) -> Result<(), String> {
+        let filter = match directives {
+            Some(directives) => parse_filter(directives)?,
+            None => default_filter(),
+        };
+        self.filter.reload(filter).map_err(|e| e.to_string())
+    }
+}
+
+/// The installed layers that need the app handle before they can do their job.
+pub struct Logging {
+    events: TauriEventLayer,
+    file: FileLayer,
+    filter: FilterHandle,
+}
+
+impl Logging {
+    /// Opens the log file, starts forwarding to the frontend, and flushes anything
+    /// logged during startup to both.
+    pub fn attach(&self, app: AppHandle) {
+        match app.path().app_log_dir() {
+            Ok(dir) => {
+                if let Err(e) = self.file.attach(&dir) {
+                    tracing::warn!(dir = %dir.display(), error = %e, "failed to open log file");
+                }
+                app.manage(LogControl {
+                    filter: self.filter.clone(),
+                    dir,
+                    file: self.file.file.clone(),
+                });
+            }
+            Err(e) => tracing::warn!(error = %e, "no log dir; file logging disabled"),
+        }
+        self.events.attach(app);
+    }
+}
+
+/// Logs panics before the default hook runs; with `panic = "abort"` in release this is
+/// the only record a crash leaves.
+fn install_panic_hook() {
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let payload = info
+            .payload()
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| info.payload().downcast_ref::<String>().cloned())
+            .unwrap_or_default();
+        let location = info
+            .location()
+            .map(|l| format!("{}:{}", l.file(), l.line()));
+        tracing::error!(
+            target: "panic",
+            location = location.as_deref(),
+            backtrace = %Backtrace::force_capture(),
+            "{payload}"
+        );
+        previous(info);
+    }));
+}
+
+fn default_filter() -> EnvFilter {
+    EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"))
+}
+
+pub fn parse_filter(directives: &str) -> Result<EnvFilter, String> {
+    EnvFilter::try_new(directives).map_err(|e| format!("invalid log filter: {e}"))
+}
+
+/// Installs the global subscriber. `RUST_LOG` sets the initial filter, defaulting to
+/// `info`; [`set_log_level`] changes it at runtime. Returns the layers so `setup` can
+/// attach the app handle once it exists.
+pub fn init() -> Logging {
+    let events = TauriEventLayer::default();
+    let file = FileLayer::default();
+    let (filter, filter_handle) = reload::Layer::new(default_filter());
+
+    let _ = Registry::default()
+        .with(filter)
+        .with(tracing_subscriber::fmt::layer())
+        .with(file.clone())
+        .with(events.clone())
+        .try_init();
+    install_panic_hook();
+
+    Logging {
+        events,
+        file,
+        filter: filter_handle,
+    }
+}
+
+/// The last lines logged to the file, oldest first. Empty if the buffer is locked,
+/// since a panic mid-log would otherwise deadlock.
+pub fn recent_lines() -> Vec<String> {
+    match RECENT.try_lock() {
+        Ok(recent) => recent.iter().cloned().collect(),
+        Err(TryLockError::Poisoned(e)) => e.into_inner().iter().cloned().collect(),
+        Err(TryLockError::WouldBlock) => Vec::new(),
+    }
+}
+
+fn parse_level(level: &str) -> Result<Level, String> {
+    Level::from_str(level).map_err(|_| format!("invalid log level: {level}"))
+}
+
+/// The last `lines` entries of the current log file at `min_level` or more severe.
+#[tauri::command]
+pub fn get_recent_logs(
+    lines: u32,
+    min_level: String,
+    control: State<'_, LogControl>,
+) -> Result<Vec<LogEntry>, String> {
+    let min_level = parse_level(&min_level)?;
+    let contents = match fs::read_to_string(log_path(&control.dir, 0)) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.to_string()),
+    };
+
+    let mut entries: Vec<LogEntry> = contents
+        .lines()
+        .rev()
+        .filter_map(|line| serde_json::from_str::<LogEntry>(line).ok())
+        .filter(|entry| Level::from_str(&entry.level).is_ok_and(|level| level <= min_level))
+        .take(lines as usize)
+        .collect();
+    entries.reverse();
+    Ok(entries)
+}
+
+/// Replaces the filter; accepts a level (`debug`) or full directives (`info,layers=trace`).
+#[tauri::command]
+pub fn set_log_level(level: String, control: State<'_, LogControl>) -> Result<(), String> {
+    control.set_filter(Some(&level))?;
+    tracing::info!(%level, "log level changed");
+    Ok(())
+}
+
+#[tauri::command]
+pub fn open_log_folder(app: AppHandle, control: State<'_, LogControl>) -> Result<(), String> {
+    app.opener()
+        .open_path(control.dir.to_string_lossy(), None::<&str>)
+        .map_err(|e| e.to_string())
+}
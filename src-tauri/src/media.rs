@@ -0,0 +1,496 @@
+//! Content-addressed media under `$APPDATA/media`, served to the webview through the
+//! `media://` scheme so pages never see a filesystem path. Files are named by the
+//! SHA-256 of their contents and looked up through the `media` table.
+
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use tauri::http::{header, Request, Response, StatusCode};
+use tauri::{AppHandle, Manager, Runtime, State, UriSchemeContext, UriSchemeResponder};
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+use crate::db::{Db, DbError};
+use crate::scope;
+
+pub const SCHEME: &str = "media";
+
+const MEDIA_DIR: &str = "media";
+/// Ranges are answered with at most this much, so seeking through a long video doesn't
+/// pull the rest of the file into memory; the player asks again from where it stopped.
+const MAX_RANGE_BYTES: u64 = 4 * 1024 * 1024;
+
+#[derive(Debug, thiserror::Error, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "camelCase")]
+pub enum MediaError {
+    #[error("not found: {0}")]
+    NotFound(String),
+    #[error("{0}")]
+    Scope(String),
+    #[error("{0}")]
+    Io(String),
+    #[error(transparent)]
+    Db(#[from] DbError),
+}
+
+impl From<io::Error> for MediaError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e.to_string())
+    }
+}
+
+impl From<sqlx::Error> for MediaError {
+    fn from(e: sqlx::Error) -> Self {
+        Self::Db(e.into())
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaUrl {
+    pub hash: String,
+    /// Ready for `src` attributes; the form differs by platform, see [`url`].
+    pub url: String,
+    pub mime: String,
+    pub size_bytes: u64,
+}
+
+fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as i64)
+}
+
+/// WebView2 and Android's WebView only load custom schemes as `http://<scheme>.localhost`.
+fn url(hash: &str) -> String {
+    if cfg!(any(windows, target_os = "android")) {
+        format!("http://{SCHEME}.localhost/{hash}")
+    } else {
+        format!("{SCHEME}://localhost/{hash}")
+    }
+}
+
+fn media_root<R: Runtime>(app: &AppHandle<R>) -> Result<PathBuf, MediaError> {
+    app.path()
+        .app_data_dir()
+        .map(|dir| dir.join(MEDIA_DIR))
+        .map_err(|e| MediaError::Io(e.to_string()))
+}
+
+pub(crate) fn is_hash(hash: &str) -> bool {
+    hash.len() == 64 && hash.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+/// Sniffed first, then by extension, since `<video>` won't play without a media type.
+pub(crate) fn mime_type(path: &Path) -> String {
+    if let Ok(Some(kind)) = infer::get_from_path(path) {
+        return kind.mime_type().to_string();
+    }
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_ascii_lowercase)
+        .unwrap_or_default();
+    match extension.as_str() {
+        "svg" => "image/svg+xml",
+        "txt" => "text/plain",
+        _ => "application/octet-stream",
+    }
+    .to_string()
+}
+
+/// Copies `src` into `root` under a temporary name while hashing it; the caller renames
+/// it once it knows whether the hash is new.
+pub(crate) fn copy_hashed(src: &Path, root: &Path) -> io::Result<(PathBuf, String, u64)> {
+    fs::create_dir_all(root)?;
+    let tmp = root.join(format!(".import-{}", uuid::Uuid::new_v4()));
+    let result = (|| {
+        let mut input = File::open(src)?;
+        let mut output = File::create(&tmp)?;
+        let mut hasher = Sha256::new();
+        let mut buf = vec![0; 1024 * 1024];
+        let mut size = 0;
+        loop {
+            let read = input.read(&mut buf)?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&buf[..read]);
+            output.write_all(&buf[..read])?;
+            size += read as u64;
+        }
+        output.sync_all()?;
+        let hash: String = hasher
+            .finalize()
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect();
+        Ok((hash, size))
+    })();
+    match result {
+        Ok((hash, size)) => Ok((tmp, hash, size)),
+        Err(e) => {
+            let _ = fs::remove_file(&tmp);
+            Err(e)
+        }
+    }
+}
+
+/// An inclusive byte range within the file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct ByteRange {
+    pub start: u64,
+    pub end: u64,
+}
+
+/// A range answered with 416, which includes multiple ranges since those would need a
+/// multipart response.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct Unsatisfiable;
+
+/// Parses a `Range` header for a file of `len` bytes. Headers that aren't a valid
+/// `bytes` range are ignored, as RFC 9110 allows, as though none had been sent.
+pub(crate) fn parse_range(header: &str, len: u64) -> Result<Option<ByteRange>, Unsatisfiable> {
+    let Some((unit, spec)) = header.trim().split_once('=') else {
+        return Ok(None);
+    };
+    if !unit.trim().eq_ignore_ascii_case("bytes") {
+        return Ok(None);
+    }
+    if spec.contains(',') {
+        return Err(Unsatisfiable);
+    }
+    let Some((start, end)) = spec.trim().split_once('-') else {
+        return Ok(None);
+    };
+    let (start, end) = (start.trim(), end.trim());
+
+    if start.is_empty() {
+        // `bytes=-500`: the last 500 bytes.
+        let Ok(suffix) = end.parse::<u64>() else {
+            return Ok(None);
+        };
+        if suffix == 0 || len == 0 {
+            return Err(Unsatisfiable);
+        }
+        return Ok(Some(capped(len.saturating_sub(suffix), len - 1)));
+    }
+
+    let Ok(start) = start.parse::<u64>() else {
+        return Ok(None);
+    };
+    let end = if end.is_empty() {
+        // `bytes=500-`: everything from byte 500.
+        len.saturating_sub(1)
+    } else {
+        match end.parse::<u64>() {
+            Ok(end) if end >= start => end.min(len.saturating_sub(1)),
+            _ => return Ok(None),
+        }
+    };
+    if start >= len {
+        return Err(Unsatisfiable);
+    }
+    Ok(Some(capped(start, end)))
+}
+
+/// What to send for a request's `Range` header, if any. Files over [`MAX_RANGE_BYTES`]
+/// are answered in part even when no range was asked for, so a plain request can't pull
+/// the whole file into memory either.
+pub(crate) fn served_range(
+    header: Option<&str>,
+    len: u64,
+) -> Result<Option<ByteRange>, Unsatisfiable> {
+    let asked = match header {
+        Some(value) => parse_range(value, len)?,
+        None => None,
+    };
+    Ok(asked.or_else(|| (len > MAX_RANGE_BYTES).then(|| capped(0, len - 1))))
+}
+
+/// At most [`MAX_RANGE_BYTES`] from `start`, whatever form the range was asked in.
+fn capped(start: u64, end: u64) -> ByteRange {
+    ByteRange {
+        start,
+        end: end.min(start.saturating_add(MAX_RANGE_BYTES - 1)),
+    }
+}
+
+pub(crate) fn status(status: StatusCode) -> Response<Vec<u8>> {
+    Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "text/plain")
+        .body(
+            status
+                .canonical_reason()
+                .unwrap_or_default()
+                .as_bytes()
+                .to_vec(),
+        )
+        .unwrap_or_default()
+}
+
+/// The indexed file, or `None` if it resolves outside the media root.
+fn contained(root: &Path, file_name: &str) -> io::Result<Option<PathBuf>> {
+    let root = dunce::canonicalize(root)?;
+    let path = dunce::canonicalize(root.join(file_name))?;
+    Ok(path.starts_with(&root).then_some(path))
+}
+
+/// Finds the file for a hash, refusing anything the index points outside the root.
+async fn resolve<R: Runtime>(
+    app: &AppHandle<R>,
+    hash: &str,
+) -> Result<(PathBuf, String), StatusCode> {
+    if !is_hash(hash) {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    let db = app.state::<Db>();
+    let pool = db.pool().map_err(|_| StatusCode::SERVICE_UNAVAILABLE)?;
+    let row: Option<(String, String)> =
+        sqlx::query_as("SELECT file_name, mime FROM media WHERE hash = ?")
+            .bind(hash)
+            .fetch_optional(pool)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let (file_name, mime) = row.ok_or(StatusCode::NOT_FOUND)?;
+
+    let root = media_root(app).map_err(|_| StatusCode::NOT_FOUND)?;
+    match contained(&root, &file_name) {
+        Ok(Some(path)) => Ok((path, mime)),
+        Ok(None) => {
+            tracing::warn!(hash, file_name, "media entry outside the media root");
+            Err(StatusCode::FORBIDDEN)
+        }
+        Err(_) => Err(StatusCode::NOT_FOUND),
+    }
+}
+
+async fn respond<R: Runtime>(app: &AppHandle<R>, request: &Request<Vec<u8>>) -> Response<Vec<u8>> {
+    let hash = request.uri().path().trim_start_matches('/');
+    let (path, mime) = match resolve(app, hash).await {
+        Ok(found) => found,
+        Err(code) => return status(code),
+    };
+
+    let result = async {
+        let mut file = tokio::fs::File::open(&path).await?;
+        let len = file.metadata().await?.len();
+        let range = served_range(
+            request
+                .headers()
+                .get(header::RANGE)
+                .and_then(|value| value.to_str().ok()),
+            len,
+        );
+
+        let builder = Response::builder()
+            .header(header::CONTENT_TYPE, &mime)
+            .header(header::ACCEPT_RANGES, "bytes")
+            // The URL is the content hash, so it never changes.
+            .header(header::CACHE_CONTROL, "public, max-age=31536000, immutable");
+        let response = match range {
+            Ok(Some(range)) => {
+                let mut body = vec![0; (range.end - range.start + 1) as usize];
+                file.seek(io::SeekFrom::Start(range.start)).await?;
+                file.read_exact(&mut body).await?;
+                builder
+                    .status(StatusCode::PARTIAL_CONTENT)
+                    .header(
+                        header::CONTENT_RANGE,
+                        format!("bytes {}-{}/{len}", range.start, range.end),
+                    )
+                    .body(body)
+            }
+            Ok(None) => {
+                let mut body = Vec::with_capacity(len as usize);
+                file.read_to_end(&mut body).await?;
+                builder.status(StatusCode::OK).body(body)
+            }
+            Err(Unsatisfiable) => builder
+                .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                .header(header::CONTENT_RANGE, format!("bytes */{len}"))
+                .body(Vec::new()),
+        };
+        response.map_err(io::Error::other)
+    }
+    .await;
+
+    result.unwrap_or_else(|e: io::Error| {
+        tracing::warn!(path = %path.display(), error = %e, "failed to serve media");
+        status(StatusCode::INTERNAL_SERVER_ERROR)
+    })
+}
+
+/// The `media://` handler, registered in `run()`. Lookups need the database, so each
+/// request is answered from the async runtime.
+pub fn handle<R: Runtime>(
+    ctx: UriSchemeContext<'_, R>,
+    request: Request<Vec<u8>>,
+    responder: UriSchemeResponder,
+) {
+    let app = ctx.app_handle().clone();
+    tauri::async_runtime::spawn(async move {
+        responder.respond(respond(&app, &request).await);
+    });
+}
+
+/// Copies a file into the media root and indexes it; importing the same contents again
+/// returns the existing entry.
+#[tauri::command]
+pub async fn media_import<R: Runtime>(
+    path: String,
+    app: AppHandle<R>,
+    db: State<'_, Db>,
+) -> Result<MediaUrl, MediaError> {
+    let src = scope::ensure_allowed(&app, Path::new(&path)).map_err(MediaError::Scope)?;
+    if !src.is_file() {
+        return Err(MediaError::NotFound(src.display().to_string()));
+    }
+    let pool = db.pool()?;
+    let root = media_root(&app)?;
+
+    let (mime, (tmp, hash, size)) = tauri::async_runtime::spawn_blocking({
+        let root = root.clone();
+        move || Ok::<_, io::Error>((mime_type(&src), copy_hashed(&src, &root)?))
+    })
+    .await
+    .map_err(|e| MediaError::Io(e.to_string()))??;
+
+    let existing: Option<(String, i64)> =
+        sqlx::query_as("SELECT mime, size_bytes FROM media WHERE hash = ?")
+            .bind(&hash)
+            .fetch_optional(pool)
+            .await?;
+    if let Some((mime, size)) = existing {
+        let _ = fs::remove_file(&tmp);
+        return Ok(MediaUrl {
+            url: url(&hash),
+            hash,
+            mime,
+            size_bytes: size as u64,
+        });
+    }
+
+    if let Err(e) = fs::rename(&tmp, root.join(&hash)) {
+        let _ = fs::remove_file(&tmp);
+        return Err(e.into());
+    }
+    sqlx::query(
+        "INSERT INTO media (hash, file_name, mime, size_bytes, created_at_ms)
+         VALUES (?, ?, ?, ?, ?)",
+    )
+    .bind(&hash)
+    .bind(&hash)
+    .bind(&mime)
+    .bind(size as i64)
+    .bind(now_ms())
+    .execute(pool)
+    .await?;
+    tracing::info!(%hash, size, "media imported");
+
+    Ok(MediaUrl {
+        url: url(&hash),
+        hash,
+        mime,
+        size_bytes: size,
+    })
+}
+
+#[tauri::command]
+pub async fn media_delete<R: Runtime>(
+    hash: String,
+    app: AppHandle<R>,
+    db: State<'_, Db>,
+) -> Result<(), MediaError> {
+    if !is_hash(&hash) {
+        return Err(MediaError::NotFound(hash));
+    }
+    let row: Option<(String,)> =
+        sqlx::query_as("DELETE FROM media WHERE hash = ? RETURNING file_name")
+            .bind(&hash)
+            .fetch_optional(db.pool()?)
+            .await?;
+    let Some((file_name,)) = row else {
+        return Err(MediaError::NotFound(hash));
+    };
+
+    match contained(&media_root(&app)?, &file_name) {
+        Ok(Some(path)) => fs::remove_file(path)?,
+        Ok(None) => tracing::warn!(%hash, file_name, "media entry outside the media root"),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e.into()),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LEN: u64 = 10_000;
+
+    fn range(start: u64, end: u64) -> Result<Option<ByteRange>, Unsatisfiable> {
+        Ok(Some(ByteRange { start, end }))
+    }
+
+    #[test]
+    fn closed() {
+        assert_eq!(parse_range("bytes=0-499", LEN), range(0, 499));
+        assert_eq!(parse_range("bytes=9000-20000", LEN), range(9000, LEN - 1));
+    }
+
+    #[test]
+    fn open_ended() {
+        assert_eq!(parse_range("bytes=500-", LEN), range(500, LEN - 1));
+        assert_eq!(parse_range("bytes=10000-", LEN), Err(Unsatisfiable));
+    }
+
+    #[test]
+    fn suffix() {
+        assert_eq!(parse_range("bytes=-500", LEN), range(9500, LEN - 1));
+        assert_eq!(parse_range("bytes=-20000", LEN), range(0, LEN - 1));
+        assert_eq!(parse_range("bytes=-0", LEN), Err(Unsatisfiable));
+        assert_eq!(parse_range("bytes=-500", 0), Err(Unsatisfiable));
+    }
+
+    #[test]
+    fn multiple_ranges_are_unsatisfiable() {
+        assert_eq!(parse_range("bytes=0-1,5-9", LEN), Err(Unsatisfiable));
+        assert_eq!(parse_range("bytes=-1, 0-", LEN), Err(Unsatisfiable));
+    }
+
+    #[test]
+    fn capped_at_max_range_bytes() {
+        let len = MAX_RANGE_BYTES * 3;
+        assert_eq!(parse_range("bytes=0-", len), range(0, MAX_RANGE_BYTES - 1));
+        assert_eq!(
+            parse_range(&format!("bytes=-{}", MAX_RANGE_BYTES * 2), len),
+            range(MAX_RANGE_BYTES, MAX_RANGE_BYTES * 2 - 1)
+        );
+    }
+
+    #[test]
+    fn large_files_without_a_range_are_sent_in_part() {
+        let len = MAX_RANGE_BYTES * 3;
+        assert_eq!(served_range(None, len), range(0, MAX_RANGE_BYTES - 1));
+        assert_eq!(
+            served_range(Some("items=0-10"), len),
+            range(0, MAX_RANGE_BYTES - 1)
+        );
+        assert_eq!(served_range(None, MAX_RANGE_BYTES), Ok(None));
+        assert_eq!(served_range(None, 0), Ok(None));
+        assert_eq!(served_range(Some("bytes=0-9"), len), range(0, 9));
+    }
+
+    #[test]
+    fn ignored() {
+        assert_eq!(parse_range("items=0-10", LEN), Ok(None));
+        assert_eq!(parse_range("bytes=abc-", LEN), Ok(None));
+        assert_eq!(parse_range("bytes=500-100", LEN), Ok(None));
+        assert_eq!(parse_range("bytes", LEN), Ok(None));
+    }
+}
@@ -0,0 +1,421 @@
+//! The app's own resource footprint, for comparing it against builds of the same app in
+//! other frameworks. Samples can be recorded into `metrics_samples` and exported as CSV.
+//!
+//! On Windows and Linux the webview renders in child processes (WebView2's
+//! `msedgewebview2.exe`, WebKitGTK's `WebKitWebProcess`), which hold most of the memory,
+//! so their RSS is added to the total. macOS launches WKWebView's helpers through
+//! launchd, not as children, so they can't be attributed there.
+
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use sqlx::SqlitePool;
+use sysinfo::{Pid, Process, ProcessRefreshKind, ProcessesToUpdate, System};
+use tauri::{AppHandle, Manager, Runtime, State};
+use tokio::sync::oneshot;
+
+use crate::db::{Db, DbError};
+use crate::scope;
+
+const MIN_INTERVAL_MS: u64 = 100;
+
+#[derive(Debug, thiserror::Error, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "camelCase")]
+pub enum MetricsError {
+    #[error("metrics are already being recorded")]
+    AlreadyRecording,
+    #[error("metrics are not being recorded")]
+    NotRecording,
+    #[error("invalid input: {0}")]
+    InvalidInput(String),
+    #[error("process metrics unavailable: {0}")]
+    Unavailable(String),
+    #[error("{0}")]
+    Scope(String),
+    #[error("{0}")]
+    Io(String),
+    #[error(transparent)]
+    Db(#[from] DbError),
+}
+
+impl From<std::io::Error> for MetricsError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e.to_string())
+    }
+}
+
+impl From<sqlx::Error> for MetricsError {
+    fn from(e: sqlx::Error) -> Self {
+        Self::Db(e.into())
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceMetrics {
+    /// The main process alone.
+    pub rss_bytes: u64,
+    /// Memory not shared with other processes; Windows and Linux only.
+    pub private_bytes: Option<u64>,
+    /// The main process and its webview helpers, as a share of all cores, so 100 means
+    /// every core is busy. The first sample after launch reads 0.
+    pub cpu_percent: f32,
+    /// Summed over the webview's helper processes, where they can be found.
+    pub webview_process_rss: Option<u64>,
+    /// `rss_bytes` plus `webview_process_rss`.
+    pub total_rss_bytes: u64,
+    /// Open handles on Windows.
+    pub open_fd_count: Option<u64>,
+    pub thread_count: Option<u64>,
+    pub uptime_ms: u64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MetricsRecording {
+    pub recording_id: String,
+    pub interval_ms: u64,
+}
+
+struct Recording {
+    id: String,
+    stop: oneshot::Sender<()>,
+}
+
+pub struct Metrics {
+    system: Mutex<System>,
+    started: Instant,
+    recording: Mutex<Option<Recording>>,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self {
+            system: Mutex::new(System::new()),
+            started: Instant::now(),
+            recording: Mutex::new(None),
+        }
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+mod native {
+    use sysinfo::Process;
+
+    /// `Private_Clean` plus `Private_Dirty`, in kB.
+    pub fn private_bytes() -> Option<u64> {
+        let rollup = std::fs::read_to_string("/proc/self/smaps_rollup").ok()?;
+        let kb: u64 = rollup
+            .lines()
+            .filter(|line| line.starts_with("Private_"))
+            .filter_map(|line| line.split_whitespace().nth(1)?.parse::<u64>().ok())
+            .sum();
+        Some(kb * 1024)
+    }
+
+    pub fn thread_count(process: &Process) -> Option<u64> {
+        process.tasks().map(|tasks| tasks.len() as u64)
+    }
+}
+
+#[cfg(windows)]
+mod native {
+    use std::mem::{size_of, zeroed};
+
+    use sysinfo::Process;
+    use windows_sys::Win32::Foundation::{CloseHandle, INVALID_HANDLE_VALUE};
+    use windows_sys::Win32::System::Diagnostics::ToolHelp::{
+        CreateToolhelp32Snapshot, Thread32First, Thread32Next, TH32CS_SNAPTHREAD, THREADENTRY32,
+    };
+    use windows_sys::Win32::System::ProcessStatus::{
+        K32GetProcessMemoryInfo, PROCESS_MEMORY_COUNTERS, PROCESS_MEMORY_COUNTERS_EX,
+    };
+    use windows_sys::Win32::System::Threading::{GetCurrentProcess, GetCurrentProcessId};
+
+    /// The commit charge, as Task Manager's "Memory" column shows it.
+    pub fn private_bytes() -> Option<u64> {
+        // SAFETY: plain-data struct; `cb` tells the call it's the extended layout.
+        let mut counters: PROCESS_MEMORY_COUNTERS_EX = unsafe { zeroed() };
+        let cb = size_of::<PROCESS_MEMORY_COUNTERS_EX>() as u32;
+        counters.cb = cb;
+        // SAFETY: the pseudo handle needs no closing, and `counters` is `cb` bytes.
+        let ok = unsafe {
+            K32GetProcessMemoryInfo(
+                GetCurrentProcess(),
+                &mut counters as *mut _ as *mut PROCESS_MEMORY_COUNTERS,
+                cb,
+            )
+        };
+        (ok != 0).then_some(counters.PrivateUsage as u64)
+    }
+
+    /// Toolhelp lists every thread in the system; ours are the ones we own.
+    pub fn thread_count(_process: &Process) -> Option<u64> {
+        // SAFETY: the snapshot handle is closed below; entries are plain data sized
+        // through `dwSize`.
+        unsafe {
+            let snapshot = CreateToolhelp32Snapshot(TH32CS_SNAPTHREAD, 0);
+            if snapshot == INVALID_HANDLE_VALUE {
+                return None;
+            }
+            let pid = GetCurrentProcessId();
+            let mut entry: THREADENTRY32 = zeroed();
+            entry.dwSize = size_of::<THREADENTRY32>() as u32;
+            let mut count = 0;
+            let mut more = Thread32First(snapshot, &mut entry) != 0;
+            while more {
+                if entry.th32OwnerProcessID == pid {
+                    count += 1;
+                }
+                more = Thread32Next(snapshot, &mut entry) != 0;
+            }
+            CloseHandle(snapshot);
+            Some(count)
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android", windows)))]
+mod native {
+    use sysinfo::Process;
+
+    pub fn private_bytes() -> Option<u64> {
+        None
+    }
+
+    pub fn thread_count(_process: &Process) -> Option<u64> {
+        None
+    }
+}
+
+/// Child processes of `root`, recursively. Linux lists threads as processes too, with
+/// the main process as their parent, so those are skipped.
+#[cfg(desktop)]
+fn descendants(system: &System, root: Pid) -> Vec<&Process> {
+    let mut found = Vec::new();
+    let mut parents = vec![root];
+    while let Some(parent) = parents.pop() {
+        for process in system.processes().values() {
+            if process.parent() == Some(parent) && process.thread_kind().is_none() {
+                parents.push(process.pid());
+                found.push(process);
+            }
+        }
+    }
+    found
+}
+
+impl Metrics {
+    /// Blocking, and the sysinfo lock is only held in here: CPU usage is measured between
+    /// refreshes, so an await under the lock would stretch that window.
+    fn sample(&self) -> Result<ResourceMetrics, MetricsError> {
+        let pid = sysinfo::get_current_pid().map_err(|e| MetricsError::Unavailable(e.into()))?;
+        let kind = ProcessRefreshKind::nothing()
+            .with_memory()
+            .with_cpu()
+            .with_tasks();
+        let mut system = self.system.lock().unwrap();
+        // Desktop needs the whole table to find the webview's processes.
+        #[cfg(desktop)]
+        system.refresh_processes_specifics(ProcessesToUpdate::All, true, kind);
+        #[cfg(mobile)]
+        system.refresh_processes_specifics(ProcessesToUpdate::Some(&[pid]), true, kind);
+
+        let process = system
+            .process(pid)
+            .ok_or_else(|| MetricsError::Unavailable("own process not listed".into()))?;
+        #[cfg(desktop)]
+        let helpers = descendants(&system, pid);
+        #[cfg(mobile)]
+        let helpers: Vec<&Process> = Vec::new();
+        let cpu = process.cpu_usage() + helpers.iter().map(|p| p.cpu_usage()).sum::<f32>();
+        let webview_process_rss =
+            (!helpers.is_empty()).then(|| helpers.iter().map(|p| p.memory()).sum::<u64>());
+
+        let cores = std::thread::available_parallelism().map_or(1, |n| n.get());
+        let rss_bytes = process.memory();
+        Ok(ResourceMetrics {
+            rss_bytes,
+            private_bytes: native::private_bytes(),
+            cpu_percent: cpu / cores as f32,
+            webview_process_rss,
+            total_rss_bytes: rss_bytes + webview_process_rss.unwrap_or(0),
+            open_fd_count: process.open_files().map(|n| n as u64),
+            thread_count: native::thread_count(process),
+            uptime_ms: self.started.elapsed().as_millis() as u64,
+        })
+    }
+}
+
+fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as i64)
+}
+
+async fn sample_blocking<R: Runtime>(app: &AppHandle<R>) -> Result<ResourceMetrics, MetricsError> {
+    let app = app.clone();
+    tauri::async_runtime::spawn_blocking(move || app.state::<Metrics>().sample())
+        .await
+        .map_err(|e| MetricsError::Unavailable(e.to_string()))?
+}
+
+async fn insert(pool: &SqlitePool, id: &str, sample: &ResourceMetrics) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO metrics_samples (recording_id, timestamp_ms, uptime_ms, rss_bytes,
+            private_bytes, cpu_percent, webview_rss_bytes, open_fd_count, thread_count)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(id)
+    .bind(now_ms())
+    .bind(sample.uptime_ms as i64)
+    .bind(sample.rss_bytes as i64)
+    .bind(sample.private_bytes.map(|n| n as i64))
+    .bind(sample.cpu_percent as f64)
+    .bind(sample.webview_process_rss.map(|n| n as i64))
+    .bind(sample.open_fd_count.map(|n| n as i64))
+    .bind(sample.thread_count.map(|n| n as i64))
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_resource_metrics<R: Runtime>(
+    app: AppHandle<R>,
+) -> Result<ResourceMetrics, MetricsError> {
+    sample_blocking(&app).await
+}
+
+/// Samples every `interval_ms` into `metrics_samples` under a new recording ID until
+/// [`stop_metrics_recording`].
+#[tauri::command]
+pub async fn start_metrics_recording<R: Runtime>(
+    interval_ms: u64,
+    app: AppHandle<R>,
+    db: State<'_, Db>,
+    metrics: State<'_, Metrics>,
+) -> Result<MetricsRecording, MetricsError> {
+    if interval_ms < MIN_INTERVAL_MS {
+        return Err(MetricsError::InvalidInput(format!(
+            "interval must be at least {MIN_INTERVAL_MS} ms"
+        )));
+    }
+    let pool = db.pool()?.clone();
+    let mut recording = metrics.recording.lock().unwrap();
+    if recording.is_some() {
+        return Err(MetricsError::AlreadyRecording);
+    }
+    let id = uuid::Uuid::new_v4().to_string();
+    let (stop, mut stopped) = oneshot::channel();
+    *recording = Some(Recording {
+        id: id.clone(),
+        stop,
+    });
+
+    let task_id = id.clone();
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_millis(interval_ms));
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = &mut stopped => break,
+            }
+            let sample = match sample_blocking(&app).await {
+                Ok(sample) => sample,
+                Err(e) => {
+                    tracing::warn!(error = %e, "metrics sample failed");
+                    continue;
+                }
+            };
+            if let Err(e) = insert(&pool, &task_id, &sample).await {
+                tracing::warn!(error = %e, "failed to store metrics sample");
+            }
+        }
+    });
+    tracing::info!(recording_id = %id, interval_ms, "metrics recording started");
+
+    Ok(MetricsRecording {
+        recording_id: id,
+        interval_ms,
+    })
+}
+
+/// Returns the ID of the recording that was stopped.
+#[tauri::command]
+pub fn stop_metrics_recording(metrics: State<'_, Metrics>) -> Result<String, MetricsError> {
+    let recording = metrics
+        .recording
+        .lock()
+        .unwrap()
+        .take()
+        .ok_or(MetricsError::NotRecording)?;
+    let _ = recording.stop.send(());
+    tracing::info!(recording_id = %recording.id, "metrics recording stopped");
+    Ok(recording.id)
+}
+
+type SampleRow = (
+    String,
+    i64,
+    i64,
+    i64,
+    Option<i64>,
+    f64,
+    Option<i64>,
+    Option<i64>,
+    Option<i64>,
+);
+
+fn opt(value: Option<i64>) -> String {
+    value.map(|n| n.to_string()).unwrap_or_default()
+}
+
+/// Writes the samples of one recording, or all of them, to `path` and returns how many.
+/// Missing values are empty fields.
+#[tauri::command]
+pub async fn export_metrics_csv<R: Runtime>(
+    path: String,
+    recording_id: Option<String>,
+    app: AppHandle<R>,
+    db: State<'_, Db>,
+) -> Result<u64, MetricsError> {
+    let dest = scope::ensure_allowed(&app, Path::new(&path)).map_err(MetricsError::Scope)?;
+    let rows: Vec<SampleRow> = sqlx::query_as(
+        "SELECT recording_id, timestamp_ms, uptime_ms, rss_bytes, private_bytes, cpu_percent,
+            webview_rss_bytes, open_fd_count, thread_count
+         FROM metrics_samples
+         WHERE ?1 IS NULL OR recording_id = ?1
+         ORDER BY timestamp_ms",
+    )
+    .bind(&recording_id)
+    .fetch_all(db.pool()?)
+    .await?;
+
+    let mut csv = String::from(
+        "recording_id,timestamp_ms,uptime_ms,rss_bytes,private_bytes,cpu_percent,\
+         webview_rss_bytes,open_fd_count,thread_count\r\n",
+    );
+    for (id, timestamp, uptime, rss, private, cpu, webview, fds, threads) in &rows {
+        csv.push_str(&format!(
+            "{id},{timestamp},{uptime},{rss},{},{cpu:.2},{},{},{}\r\n",
+            opt(*private),
+            opt(*webview),
+            opt(*fds),
+            opt(*threads),
+        ));
+    }
+
+    let mut tmp = dest.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+    tokio::fs::write(&tmp, csv).await?;
+    if let Err(e) = tokio::fs::rename(&tmp, &dest).await {
+        let _ = tokio::fs::remove_file(&tmp).await;
+        return Err(e.into());
+    }
+    Ok(rows.len() as u64)
+}
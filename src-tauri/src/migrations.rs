@@ -0,0 +1,302 @@
+use std::time::Instant;
+
+use serde::Serialize;
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
+use sqlx::Executor;
+use tauri::{AppHandle, Emitter, Manager, Runtime, State};
+
+use crate::db::{self, Db, DbError};
+
+pub const PROGRESS_EVENT: &str = "migration://progress";
+
+/// A reversible schema step. Versions must be unique and increasing.
+pub struct Migration {
+    pub version: i64,
+    pub description: &'static str,
+    pub up_sql: &'static str,
+    pub down_sql: &'static str,
+    /// For features the bundled SQLite may be compiled without, such as FTS5. If
+    /// `up_sql` fails the step is recorded as applied without its schema, and code using
+    /// it must check that its tables exist.
+    pub optional: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MigrationProgress {
+    pub version: i64,
+    pub total: usize,
+    pub elapsed_ms: u64,
+}
+
+pub struct MigrationRunner {
+    migrations: Vec<Migration>,
+}
+
+impl MigrationRunner {
+    pub fn new(mut migrations: Vec<Migration>) -> Self {
+        migrations.sort_by_key(|m| m.version);
+        Self { migrations }
+    }
+
+    /// The version the database ends up at once every known migration is applied.
+    pub fn latest_version(&self) -> i64 {
+        self.migrations.last().map_or(0, |m| m.version)
+    }
+
+    async fn ensure_table(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS _migrations (
+                version INTEGER PRIMARY KEY,
+                description TEXT NOT NULL,
+                applied_at TEXT DEFAULT (datetime('now')) NOT NULL
+            )",
+        )
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn current_version(pool: &SqlitePool) -> Result<i64, sqlx::Error> {
+        Self::ensure_table(pool).await?;
+        let (version,): (i64,) =
+            sqlx::query_as("SELECT COALESCE(MAX(version), 0) FROM _migrations")
+                .fetch_one(pool)
+                .await?;
+        Ok(version)
+    }
+
+    /// Applies every pending migration, each in its own transaction together with its
+    /// `_migrations` row, so a crash mid-run leaves the schema at the last whole step.
+    pub async fn run(
+        &self,
+        pool: &SqlitePool,
+        mut on_progress: impl FnMut(MigrationProgress),
+    ) -> Result<i64, sqlx::Error> {
+        let started = Instant::now();
+        let applied = Self::current_version(pool).await?;
+        let total = self
+            .migrations
+            .iter()
+            .filter(|m| m.version > applied)
+            .count();
+
+        let mut current = applied;
+        for migration in &self.migrations {
+            if migration.version <= applied {
+                continue;
+            }
+
+            // Dropping the transaction on error rolls it back.
+            let mut tx = pool.begin().await?;
+            match tx.execute(migration.up_sql).await {
+                Ok(_) => {}
+                Err(e) if migration.optional => {
+                    tracing::warn!(
+                        version = migration.version,
+                        error = %e,
+                        "skipping optional migration"
+                    );
+                    tx.rollback().await?;
+                    tx = pool.begin().await?;
+                }
+                Err(e) => return Err(e),
+            }
+            sqlx::query("INSERT INTO _migrations (version, description) VALUES (?, ?)")
+                .bind(migration.version)
+                .bind(migration.description)
+                .execute(&mut *tx)
+                .await?;
+            tx.commit().await?;
+
+            current = migration.version;
+            on_progress(MigrationProgress {
+                version: current,
+                total,
+                elapsed_ms: started.elapsed().as_millis() as u64,
+            });
+        }
+
+        Ok(current)
+    }
+
+    /// Reverts applied migrations newer than `target`, newest first.
+    pub async fn rollback_to(&self, pool: &SqlitePool, target: i64) -> Result<i64, sqlx::Error> {
+        let current = Self::current_version(pool).await?;
+        for migration in self.migrations.iter().rev() {
+            if migration.version <= target || migration.version > current {
+                continue;
+            }
+
+            let mut tx = pool.begin().await?;
+            tx.execute(migration.down_sql).await?;
+            sqlx::query("DELETE FROM _migrations WHERE version = ?")
+                .bind(migration.version)
+                .execute(&mut *tx)
+                .await?;
+            tx.commit().await?;
+        }
+
+        Self::current_version(pool).await
+    }
+}
+
+impl Default for MigrationRunner {
+    fn default() -> Self {
+        Self::new(vec![
+            Migration {
+                version: 1,
+                description: "initial schema",
+                up_sql: include_str!("../migrations/0001_initial_schema.up.sql"),
+                down_sql: include_str!("../migrations/0001_initial_schema.down.sql"),
+                optional: false,
+            },
+            Migration {
+                version: 2,
+                description: "downloads",
+                up_sql: include_str!("../migrations/0002_downloads.up.sql"),
+                down_sql: include_str!("../migrations/0002_downloads.down.sql"),
+                optional: false,
+            },
+            Migration {
+                version: 3,
+                description: "scheduled notifications",
+                up_sql: include_str!("../migrations/0003_scheduled_notifications.up.sql"),
+                down_sql: include_str!("../migrations/0003_scheduled_notifications.down.sql"),
+                optional: false,
+            },
+            Migration {
+                version: 4,
+                description: "notes full-text index",
+                up_sql: include_str!("../migrations/0004_notes_fts.up.sql"),
+                down_sql: include_str!("../migrations/0004_notes_fts.down.sql"),
+                optional: true,
+            },
+            Migration {
+                version: 5,
+                description: "audit log",
+                up_sql: include_str!("../migrations/0005_audit_log.up.sql"),
+                down_sql: include_str!("../migrations/0005_audit_log.down.sql"),
+                optional: false,
+            },
+            Migration {
+                version: 6,
+                description: "http cache",
+                up_sql: include_str!("../migrations/0006_http_cache.up.sql"),
+                down_sql: include_str!("../migrations/0006_http_cache.down.sql"),
+                optional: false,
+            },
+            Migration {
+                version: 7,
+                description: "sync mutations",
+                up_sql: include_str!("../migrations/0007_mutations.up.sql"),
+                down_sql: include_str!("../migrations/0007_mutations.down.sql"),
+                optional: false,
+            },
+            Migration {
+                version: 8,
+                description: "media index",
+                up_sql: include_str!("../migrations/0008_media.up.sql"),
+                down_sql: include_str!("../migrations/0008_media.down.sql"),
+                optional: false,
+            },
+            Migration {
+                version: 9,
+                description: "metrics samples",
+                up_sql: include_str!("../migrations/0009_metrics.up.sql"),
+                down_sql: include_str!("../migrations/0009_metrics.down.sql"),
+                optional: false,
+            },
+            Migration {
+                version: 10,
+                description: "attachments",
+                up_sql: include_str!("../migrations/0010_attachments.up.sql"),
+                down_sql: include_str!("../migrations/0010_attachments.down.sql"),
+                optional: false,
+            },
+            Migration {
+                version: 11,
+                description: "audit outcome",
+                up_sql: include_str!("../migrations/0011_audit_outcome.up.sql"),
+                down_sql: include_str!("../migrations/0011_audit_outcome.down.sql"),
+                optional: false,
+            },
+        ])
+    }
+}
+
+/// Migrates the shared database in the background. Call after the window is shown so a
+/// long migration doesn't delay first paint; `Db` commands fail with
+/// [`DbError::MigrationsPending`] until it finishes. Does nothing while the database is
+/// locked; unlocking it spawns this again.
+pub fn spawn<R: Runtime>(app: &AppHandle<R>) {
+    if app.state::<Db>().is_locked() {
+        return;
+    }
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let db = app.state::<Db>();
+        let runner = app.state::<MigrationRunner>();
+
+        let result = runner
+            .run(db.raw_pool(), |progress| {
+                let _ = app.emit(PROGRESS_EVENT, progress);
+            })
+            .await;
+
+        match result {
+            Ok(version) => {
+                tracing::info!(version, "database migrations complete");
+                db.set_ready();
+            }
+            Err(e) => {
+                tracing::error!(error = %e, "database migration failed");
+                db.set_failed(e.to_string());
+            }
+        }
+    });
+}
+
+/// Runs migrations against an arbitrary database file, e.g. one picked by the user.
+#[tauri::command]
+pub async fn run_migrations<R: Runtime>(
+    app: AppHandle<R>,
+    db_path: String,
+    state: State<'_, MigrationRunner>,
+) -> Result<i64, DbError> {
+    let options = SqliteConnectOptions::new()
+        .filename(db::resolve_db_path(&app, &db_path)?)
+        .create_if_missing(true);
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect_with(options)
+        .await?;
+
+    let version = state.run(&pool, |_| {}).await;
+    pool.close().await;
+    Ok(version?)
+}
+
+#[tauri::command]
+pub async fn get_schema_version(db: State<'_, Db>) -> Result<i64, DbError> {
+    // A connection opened without the key would be refused anyway.
+    if db.is_locked() {
+        return Err(DbError::Locked);
+    }
+    Ok(MigrationRunner::current_version(db.raw_pool()).await?)
+}
+
+#[tauri::command]
+pub async fn rollback_to(
+    version: i64,
+    db: State<'_, Db>,
+    runner: State<'_, MigrationRunner>,
+) -> Result<i64, DbError> {
+    let pool = db.pool()?;
+    db.set_pending();
+    let result = runner.rollback_to(pool, version).await;
+    match &result {
+        Ok(_) => db.set_ready(),
+        Err(e) => db.set_failed(e.to_string()),
+    }
+    Ok(result?)
+}
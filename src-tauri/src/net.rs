@@ -0,0 +1,503 @@
+//! A guarded fetch for the frontend: each host gets a token bucket (`net_rate_per_sec`
+//! refilled, `net_burst` deep) and all hosts share a cap on requests in flight, so a
+//! runaway retry loop queues and then fails here instead of at the API. A `Retry-After`
+//! from a server empties and pauses that host's bucket until the time it asked for.
+//!
+//! A webview can only reach the origins listed in the app config's `allowedOrigins`,
+//! checked by [`ensure_allowed`]: the capabilities grant the http plugin no origins, and
+//! these commands would otherwise fetch whatever a page asked for.
+//!
+//! Requests go through the [`HttpMiddleware`] chain like [`crate::http_config`]'s;
+//! mocked ones never reach the network and aren't limited. Every other request made
+//! from Rust, such as [`crate::http_cache`]'s, fetch tasks and update checks, goes
+//! through [`send`] or [`fetch`] to get the same treatment.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, Runtime, State};
+use tauri_plugin_http::reqwest;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::http_config::{self, HttpError, HttpOptions, HttpResponse};
+use crate::http_middleware::{HttpExchange, HttpMiddleware, InterceptableRequest};
+use crate::rate_limit::TokenBucket;
+
+/// Requests one host may have waiting for a token before new ones are refused.
+const QUEUE_CAPACITY: usize = 50;
+/// Longest a request waits for a token and an in-flight slot together.
+const QUEUE_TIMEOUT: Duration = Duration::from_secs(10);
+/// Reported when the in-flight cap, not a bucket, kept a request waiting; how soon a
+/// slot frees up depends on other hosts.
+const IN_FLIGHT_RETRY: Duration = Duration::from_secs(1);
+/// Longest pause a `Retry-After` can impose, in case a server sends something absurd.
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(15 * 60);
+
+#[derive(Debug, thiserror::Error, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "camelCase")]
+pub enum NetError {
+    #[error("rate limited; retry in {retry_after_ms} ms")]
+    #[serde(rename_all = "camelCase")]
+    RateLimited { retry_after_ms: u64 },
+    #[error("invalid request: {0}")]
+    InvalidRequest(String),
+    #[error("{0} is not an allowed origin")]
+    NotAllowed(String),
+    #[error("request failed: {0}")]
+    Request(String),
+}
+
+impl From<HttpError> for NetError {
+    fn from(e: HttpError) -> Self {
+        match e {
+            HttpError::InvalidRequest(message) => Self::InvalidRequest(message),
+            e => Self::Request(e.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NetRequest {
+    pub url: String,
+    #[serde(flatten)]
+    pub options: HttpOptions,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HostStats {
+    pub host: String,
+    /// Requests sent, whatever their outcome.
+    pub requests: u64,
+    pub too_many_requests: u64,
+    /// Requests refused with [`NetError::RateLimited`].
+    pub rejected: u64,
+    pub queue_depth: usize,
+    /// Over requests that got a response.
+    pub average_latency_ms: f64,
+    /// Time left on a `Retry-After` pause; 0 when the host isn't paused.
+    pub paused_ms: u64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NetStats {
+    pub in_flight: usize,
+    pub max_in_flight: usize,
+    pub hosts: Vec<HostStats>,
+}
+
+struct Bucket {
+    tokens: TokenBucket,
+    paused_until: Option<Instant>,
+    queued: usize,
+    requests: u64,
+    too_many_requests: u64,
+    rejected: u64,
+    responses: u64,
+    total_latency: Duration,
+}
+
+impl Bucket {
+    fn new(rate: f64, burst: f64) -> Self {
+        Self {
+            tokens: TokenBucket::new(rate, burst),
+            paused_until: None,
+            queued: 0,
+            requests: 0,
+            too_many_requests: 0,
+            rejected: 0,
+            responses: 0,
+            total_latency: Duration::ZERO,
+        }
+    }
+
+    /// Takes a token at the current settings, or says how long until one is due.
+    fn take(&mut self, rate: f64, burst: f64, now: Instant) -> Result<(), Duration> {
+        self.tokens.set_limit(rate, burst, now);
+        if let Some(until) = self.paused_until {
+            if until > now {
+                return Err(until - now);
+            }
+            self.paused_until = None;
+        }
+        self.tokens.try_take(now)
+    }
+}
+
+pub struct Net {
+    client: reqwest::Client,
+    hosts: Arc<Mutex<HashMap<String, Bucket>>>,
+    in_flight: Arc<Semaphore>,
+    max_in_flight: usize,
+}
+
+impl Net {
+    pub fn new<R: Runtime>(app: &AppHandle<R>) -> Self {
+        let max_in_flight = crate::settings::current(app).net_max_in_flight as usize;
+        Self {
+            client: reqwest::Client::new(),
+            hosts: Arc::default(),
+            in_flight: Arc::new(Semaphore::new(max_in_flight)),
+            max_in_flight,
+        }
+    }
+
+    fn bucket<T>(&self, host: &str, rate: f64, burst: f64, f: impl FnOnce(&mut Bucket) -> T) -> T {
+        let mut hosts = self.hosts.lock().unwrap();
+        f(hosts
+            .entry(host.to_string())
+            .or_insert_with(|| Bucket::new(rate, burst)))
+    }
+
+    /// Waits for a token from `host`'s bucket. Only a request that has to wait counts
+    /// against the queue.
+    async fn acquire(
+        &self,
+        host: &str,
+        rate: f64,
+        burst: f64,
+        deadline: tokio::time::Instant,
+    ) -> Result<(), NetError> {
+        let mut queued = None;
+        let result = loop {
+            let wait = match self.bucket(host, rate, burst, |b| b.take(rate, burst, Instant::now()))
+            {
+                Ok(()) => break Ok(()),
+                Err(wait) => wait,
+            };
+            if queued.is_none() {
+                let admitted = self.bucket(host, rate, burst, |b| {
+                    let admitted = b.queued < QUEUE_CAPACITY;
+                    if admitted {
+                        b.queued += 1;
+                    }
+                    admitted
+                });
+                if !admitted {
+                    break Err(wait);
+                }
+                queued = Some(Dequeue {
+                    hosts: self.hosts.clone(),
+                    host,
+                });
+            }
+            if tokio::time::Instant::now() + wait > deadline {
+                break Err(wait);
+            }
+            tokio::time::sleep(wait).await;
+        };
+        drop(queued);
+        result.map_err(|wait| {
+            self.bucket(host, rate, burst, |b| b.rejected += 1);
+            NetError::RateLimited {
+                retry_after_ms: wait.as_millis() as u64,
+            }
+        })
+    }
+}
+
+/// Takes a request back out of its host's queue however the wait ends, including the
+/// command being dropped midway.
+struct Dequeue<'a> {
+    hosts: Arc<Mutex<HashMap<String, Bucket>>>,
+    host: &'a str,
+}
+
+impl Drop for Dequeue<'_> {
+    fn drop(&mut self) {
+        if let Some(bucket) = self.hosts.lock().unwrap().get_mut(self.host) {
+            bucket.queued -= 1;
+        }
+    }
+}
+
+/// Splits `scheme://host[:port]` into its parts, or `None` if it isn't one. The host
+/// may start with `*.` to take any subdomain, though not the domain itself.
+fn parse_origin(origin: &str) -> Option<(&str, &str, Option<u16>)> {
+    let (scheme, authority) = origin.split_once("://")?;
+    let (host, port) = match authority.rsplit_once(':') {
+        // The colons of an IPv6 address sit inside its brackets.
+        Some((host, port)) if !port.contains(']') => (host, Some(port.parse().ok()?)),
+        _ => (authority, None),
+    };
+    let valid = matches!(scheme, "http" | "https")
+        && !host.is_empty()
+        && !host.contains(['/', '?', '#', '@']);
+    valid.then_some((scheme, host, port))
+}
+
+fn origin_matches(origin: &str, url: &reqwest::Url) -> bool {
+    let (Some((scheme, host, port)), Some(url_host)) = (parse_origin(origin), url.host_str())
+    else {
+        return false;
+    };
+    let host = host.to_ascii_lowercase();
+    let host_matches = match host.strip_prefix("*.") {
+        Some(domain) => url_host
+            .strip_suffix(domain)
+            .is_some_and(|sub| sub.len() > 1 && sub.ends_with('.')),
+        None => url_host == host,
+    };
+    // With no port, only the scheme's default matches; the url crate drops that one.
+    scheme == url.scheme()
+        && host_matches
+        && match port {
+            Some(port) => url.port_or_known_default() == Some(port),
+            None => url.port().is_none(),
+        }
+}
+
+/// Checks the config's `allowedOrigins` entries are origins [`ensure_allowed`] can match.
+pub fn validate_origins(origins: &[String]) -> Result<(), String> {
+    match origins.iter().find(|origin| parse_origin(origin).is_none()) {
+        Some(origin) => Err(format!(
+            "{origin} is not an http(s) origin, such as https://api.example.com"
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Refuses `url` unless its origin is in the config's `allowedOrigins`. Only the
+/// commands a webview drives call this; the app's own requests, such as update checks,
+/// go to endpoints the config already names.
+pub(crate) fn ensure_allowed<R: Runtime>(app: &AppHandle<R>, url: &str) -> Result<(), NetError> {
+    let parsed = reqwest::Url::parse(url)
+        .map_err(|_| NetError::InvalidRequest(format!("invalid url {url}")))?;
+    let allowed = crate::config::current(app)
+        .allowed_origins
+        .iter()
+        .any(|origin| origin_matches(origin, &parsed));
+    if allowed {
+        Ok(())
+    } else {
+        Err(NetError::NotAllowed(parsed.origin().ascii_serialization()))
+    }
+}
+
+/// Seconds or an HTTP date, per RFC 9110.
+fn retry_after(headers: &reqwest::header::HeaderMap) -> Option<Duration> {
+    let value = headers
+        .get(reqwest::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim();
+    let delay = match value.parse::<u64>() {
+        Ok(seconds) => Duration::from_secs(seconds),
+        Err(_) => {
+            let at =
+                time::OffsetDateTime::parse(value, &time::format_description::well_known::Rfc2822)
+                    .ok()?;
+            let seconds = (at - time::OffsetDateTime::now_utc()).whole_seconds();
+            Duration::from_secs(seconds.max(0) as u64)
+        }
+    };
+    Some(delay.min(MAX_RETRY_AFTER))
+}
+
+/// What [`send`] got back. A live response holds its in-flight slot until it's dropped,
+/// so a body being streamed still counts against the cap.
+pub(crate) enum Sent {
+    Live(reqwest::Response, OwnedSemaphorePermit),
+    Mocked(HttpResponse),
+}
+
+impl Sent {
+    pub(crate) fn status(&self) -> u16 {
+        match self {
+            Self::Live(response, _) => response.status().as_u16(),
+            Self::Mocked(response) => response.status,
+        }
+    }
+
+    pub(crate) fn content_length(&self) -> Option<u64> {
+        match self {
+            Self::Live(response, _) => response.content_length(),
+            Self::Mocked(response) => Some(response.body.len() as u64),
+        }
+    }
+
+    /// The next piece of the body; a mock's comes in one piece.
+    pub(crate) async fn chunk(&mut self) -> Result<Option<Bytes>, NetError> {
+        match self {
+            Self::Live(response, _) => response
+                .chunk()
+                .await
+                .map_err(|e| NetError::Request(e.to_string())),
+            Self::Mocked(response) => Ok(Some(std::mem::take(&mut response.body))
+                .filter(|body| !body.is_empty())
+                .map(Bytes::from)),
+        }
+    }
+
+    pub(crate) async fn read(self) -> Result<HttpResponse, NetError> {
+        match self {
+            Self::Live(response, _slot) => Ok(http_config::read_response(response).await?),
+            Self::Mocked(response) => Ok(response),
+        }
+    }
+}
+
+/// A GET for [`send`] or [`fetch`], numbered by the middleware like any other request.
+pub(crate) fn get<R: Runtime>(app: &AppHandle<R>, url: &str) -> InterceptableRequest {
+    InterceptableRequest {
+        id: app.state::<HttpMiddleware>().next_id(),
+        method: "GET".into(),
+        url: url.to_string(),
+        headers: HashMap::new(),
+        body: None,
+    }
+}
+
+/// Sends `req` through the [`HttpMiddleware`] chain, then, unless an interceptor mocked
+/// it, once its host has a token and an in-flight slot is free, waiting up to
+/// [`QUEUE_TIMEOUT`] for both. The exchange is reported to the middleware as soon as the
+/// headers are in.
+pub(crate) async fn send<R: Runtime>(
+    app: &AppHandle<R>,
+    mut req: InterceptableRequest,
+    timeout_ms: Option<u64>,
+) -> Result<Sent, NetError> {
+    let net = app.state::<Net>();
+    let middleware = app.state::<HttpMiddleware>();
+    let started = Instant::now();
+    if let Some(mock) = middleware.intercept(&mut req).await {
+        let response = HttpResponse {
+            status: mock.status,
+            headers: mock.headers,
+            body: mock.body,
+        };
+        middleware.completed(&HttpExchange {
+            request: &req,
+            status: Some(response.status),
+            error: None,
+            mocked: true,
+            elapsed: started.elapsed(),
+        });
+        return Ok(Sent::Mocked(response));
+    }
+
+    let host = reqwest::Url::parse(&req.url)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_ascii_lowercase))
+        .ok_or_else(|| NetError::InvalidRequest(format!("invalid url {}", req.url)))?;
+    let settings = crate::settings::current(app);
+    let (rate, burst) = (settings.net_rate_per_sec as f64, settings.net_burst as f64);
+    let deadline = tokio::time::Instant::now() + QUEUE_TIMEOUT;
+    net.acquire(&host, rate, burst, deadline).await?;
+    let Ok(Ok(slot)) =
+        tokio::time::timeout_at(deadline, net.in_flight.clone().acquire_owned()).await
+    else {
+        net.bucket(&host, rate, burst, |b| b.rejected += 1);
+        return Err(NetError::RateLimited {
+            retry_after_ms: IN_FLIGHT_RETRY.as_millis() as u64,
+        });
+    };
+
+    let sent = Instant::now();
+    net.bucket(&host, rate, burst, |b| b.requests += 1);
+    let result = http_config::build_request(&net.client, &req, timeout_ms)?
+        .send()
+        .await
+        .map_err(http_config::request_error);
+
+    if let Ok(response) = &result {
+        let latency = sent.elapsed();
+        let status = response.status().as_u16();
+        let pause = retry_after(response.headers())
+            .filter(|_| matches!(status, 429 | 503))
+            .map(|delay| Instant::now() + delay);
+        net.bucket(&host, rate, burst, |b| {
+            b.responses += 1;
+            b.total_latency += latency;
+            if status == 429 {
+                b.too_many_requests += 1;
+            }
+            if let Some(until) = pause {
+                b.tokens.drain();
+                b.paused_until = b.paused_until.max(Some(until));
+            }
+        });
+        if pause.is_some() {
+            tracing::warn!(%host, status, "host asked to back off");
+        }
+    }
+
+    middleware.completed(&HttpExchange {
+        request: &req,
+        status: result
+            .as_ref()
+            .ok()
+            .map(|response| response.status().as_u16()),
+        error: result.as_ref().err().map(ToString::to_string),
+        mocked: false,
+        elapsed: started.elapsed(),
+    });
+    Ok(Sent::Live(result?, slot))
+}
+
+/// [`send`], with the whole body read.
+pub(crate) async fn fetch<R: Runtime>(
+    app: &AppHandle<R>,
+    req: InterceptableRequest,
+    timeout_ms: Option<u64>,
+) -> Result<HttpResponse, NetError> {
+    send(app, req, timeout_ms).await?.read().await
+}
+
+/// [`fetch`] for the frontend.
+#[tauri::command]
+pub async fn net_fetch<R: Runtime>(
+    request: NetRequest,
+    app: AppHandle<R>,
+) -> Result<HttpResponse, NetError> {
+    let NetRequest { url, options } = request;
+    ensure_allowed(&app, &url)?;
+    let req = InterceptableRequest {
+        id: app.state::<HttpMiddleware>().next_id(),
+        method: options
+            .method
+            .as_deref()
+            .unwrap_or("GET")
+            .to_ascii_uppercase(),
+        url,
+        headers: options.headers,
+        body: options.body,
+    };
+    fetch(&app, req, options.timeout_ms).await
+}
+
+#[tauri::command]
+pub fn get_net_stats(net: State<'_, Net>) -> NetStats {
+    let now = Instant::now();
+    let mut hosts: Vec<HostStats> = net
+        .hosts
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(host, b)| HostStats {
+            host: host.clone(),
+            requests: b.requests,
+            too_many_requests: b.too_many_requests,
+            rejected: b.rejected,
+            queue_depth: b.queued,
+            average_latency_ms: match b.responses {
+                0 => 0.0,
+                n => b.total_latency.as_secs_f64() * 1000.0 / n as f64,
+            },
+            paused_ms: b.paused_until.map_or(0, |until| {
+                until.saturating_duration_since(now).as_millis() as u64
+            }),
+        })
+        .collect();
+    hosts.sort_by(|a, b| a.host.cmp(&b.host));
+    NetStats {
+        in_flight: net.max_in_flight - net.in_flight.available_permits(),
+        max_in_flight: net.max_in_flight,
+        hosts,
+    }
+}
@@ -0,0 +1,230 @@
+//! NFC tags on Android, through `plugins/nfc`. While a scan or a write is active the
+//! adapter's foreground dispatch routes every tag to the app instead of to whichever app
+//! the system would pick, and only while it's in front. iOS restricts NFC to its own
+//! reader sheet, and desktops rarely have readers, so the module is Android-only.
+//!
+//! Scanned tags go out as [`TAG_DETECTED_EVENT`]. A tag consumed by [`write_ndef`] is
+//! not reported.
+
+use std::time::Duration;
+
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::ipc::{Channel, InvokeResponseBody};
+use tauri::plugin::mobile::PluginInvokeError;
+use tauri::plugin::{PluginHandle, TauriPlugin};
+use tauri::{AppHandle, Emitter, Manager, Runtime};
+// Linked for its native half; it has no Rust API.
+use tauri_plugin_nfc as _;
+
+pub const TAG_DETECTED_EVENT: &str = "nfc://tag-detected";
+
+/// How long [`write_ndef`] waits for a tag.
+const WRITE_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, thiserror::Error, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "camelCase")]
+pub enum NfcError {
+    #[error("this device has no NFC")]
+    NotSupported,
+    /// The device has NFC but it is turned off in settings.
+    #[error("NFC is turned off")]
+    AdapterDisabled,
+    #[error("the tag moved away before the write finished")]
+    TagLost,
+    #[error("the tag is read-only")]
+    ReadOnly,
+    #[error("no tag was scanned within {}s", WRITE_TIMEOUT.as_secs())]
+    Timeout,
+    #[error("the message doesn't fit on the tag: {0}")]
+    TooLarge(String),
+    #[error("invalid NDEF record: {0}")]
+    InvalidInput(String),
+    #[error("NFC request failed: {0}")]
+    Failed(String),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NdefRecord {
+    /// Type name format, 0 to 7 (NFC Forum RTD 1.0).
+    pub tnf: u8,
+    pub type_bytes: Vec<u8>,
+    pub id: Vec<u8>,
+    pub payload: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NfcTag {
+    /// The tag's UID in lowercase hex. Some tags randomise it on every scan.
+    pub tag_id: String,
+    /// Android tag technologies, like `NfcA` or `Ndef`.
+    pub technologies: Vec<String>,
+    /// Empty unless the tag holds an NDEF message.
+    pub ndef_records: Vec<NdefRecord>,
+}
+
+/// Bytes cross the bridge as base64 rather than JSON arrays of numbers.
+#[derive(Serialize, Deserialize)]
+struct NativeRecord {
+    tnf: u8,
+    #[serde(rename = "type")]
+    type_bytes: String,
+    id: String,
+    payload: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct NativeTag {
+    tag_id: String,
+    technologies: Vec<String>,
+    ndef_records: Vec<NativeRecord>,
+}
+
+#[derive(Serialize)]
+struct ScanArgs {
+    channel: Channel,
+}
+
+#[derive(Serialize)]
+struct WriteArgs {
+    records: Vec<NativeRecord>,
+}
+
+impl From<&NdefRecord> for NativeRecord {
+    fn from(record: &NdefRecord) -> Self {
+        let engine = base64::engine::general_purpose::STANDARD;
+        Self {
+            tnf: record.tnf,
+            type_bytes: engine.encode(&record.type_bytes),
+            id: engine.encode(&record.id),
+            payload: engine.encode(&record.payload),
+        }
+    }
+}
+
+impl From<NativeRecord> for NdefRecord {
+    fn from(record: NativeRecord) -> Self {
+        let engine = base64::engine::general_purpose::STANDARD;
+        Self {
+            tnf: record.tnf,
+            type_bytes: engine.decode(record.type_bytes).unwrap_or_default(),
+            id: engine.decode(record.id).unwrap_or_default(),
+            payload: engine.decode(record.payload).unwrap_or_default(),
+        }
+    }
+}
+
+struct Nfc<R: Runtime>(PluginHandle<R>);
+
+pub fn plugin<R: Runtime>() -> TauriPlugin<R> {
+    tauri::plugin::Builder::new("nfc")
+        .setup(|app, api| {
+            let handle = api.register_android_plugin("com.layers.nfc", "NfcPlugin")?;
+            app.manage(Nfc(handle));
+            Ok(())
+        })
+        .build()
+}
+
+fn from_plugin(e: PluginInvokeError) -> NfcError {
+    match e {
+        PluginInvokeError::InvokeRejected(response) => {
+            let message = response.message.unwrap_or_default();
+            match response.code.as_deref() {
+                Some("NotSupported") => NfcError::NotSupported,
+                Some("AdapterDisabled") => NfcError::AdapterDisabled,
+                Some("TagLost") => NfcError::TagLost,
+                Some("ReadOnly") => NfcError::ReadOnly,
+                Some("Timeout") => NfcError::Timeout,
+                Some("TooLarge") => NfcError::TooLarge(message),
+                Some("InvalidInput") => NfcError::InvalidInput(message),
+                _ => NfcError::Failed(message),
+            }
+        }
+        e => NfcError::Failed(e.to_string()),
+    }
+}
+
+fn nfc<R: Runtime>(app: &AppHandle<R>) -> Result<PluginHandle<R>, NfcError> {
+    app.try_state::<Nfc<R>>()
+        .map(|nfc| nfc.0.clone())
+        .ok_or_else(|| NfcError::Failed("nfc plugin not loaded".into()))
+}
+
+/// Reports every tag held to the device as [`TAG_DETECTED_EVENT`] until
+/// [`stop_nfc_scan`]. Starting again replaces the running scan.
+#[tauri::command]
+pub async fn start_nfc_scan<R: Runtime>(app: AppHandle<R>) -> Result<(), NfcError> {
+    let handle = app.clone();
+    let channel = Channel::new(move |body| {
+        let InvokeResponseBody::Json(json) = body else {
+            return Ok(());
+        };
+        match serde_json::from_str::<NativeTag>(&json) {
+            Ok(tag) => {
+                let tag = NfcTag {
+                    tag_id: tag.tag_id,
+                    technologies: tag.technologies,
+                    ndef_records: tag.ndef_records.into_iter().map(Into::into).collect(),
+                };
+                let _ = handle.emit(TAG_DETECTED_EVENT, tag);
+            }
+            Err(e) => tracing::warn!(error = %e, "malformed NFC tag"),
+        }
+        Ok(())
+    });
+    nfc(&app)?
+        .run_mobile_plugin_async::<Value>("startScan", ScanArgs { channel })
+        .await
+        .map_err(from_plugin)?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn stop_nfc_scan<R: Runtime>(app: AppHandle<R>) -> Result<(), NfcError> {
+    nfc(&app)?
+        .run_mobile_plugin_async::<Value>("stopScan", ())
+        .await
+        .map_err(from_plugin)?;
+    Ok(())
+}
+
+/// Writes `records` as one NDEF message to the next tag scanned within
+/// [`WRITE_TIMEOUT`], formatting it for NDEF first if it's blank.
+#[tauri::command]
+pub async fn write_ndef<R: Runtime>(
+    records: Vec<NdefRecord>,
+    app: AppHandle<R>,
+) -> Result<(), NfcError> {
+    if records.is_empty() {
+        return Err(NfcError::InvalidInput("a message needs a record".into()));
+    }
+    if let Some(record) = records.iter().find(|record| record.tnf > 7) {
+        return Err(NfcError::InvalidInput(format!(
+            "tnf {} is not 0 to 7",
+            record.tnf
+        )));
+    }
+    let nfc = nfc(&app)?;
+    let args = WriteArgs {
+        records: records.iter().map(NativeRecord::from).collect(),
+    };
+    let write = nfc.run_mobile_plugin_async::<Value>("writeNdef", args);
+    match tokio::time::timeout(WRITE_TIMEOUT, write).await {
+        Ok(result) => result.map(|_| ()).map_err(from_plugin),
+        Err(_) => {
+            // Otherwise the next tag, however much later, would still be written.
+            if let Err(e) = nfc
+                .run_mobile_plugin_async::<Value>("cancelWrite", ())
+                .await
+            {
+                tracing::warn!(error = %e, "failed to cancel NFC write");
+            }
+            Err(NfcError::Timeout)
+        }
+    }
+}
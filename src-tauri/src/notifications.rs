@@ -0,0 +1,377 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use tauri::{AppHandle, Emitter, Manager, Runtime, State, Window, WindowEvent};
+use tauri_plugin_notification::NotificationExt;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+use tokio::sync::Notify;
+
+use crate::db::Db;
+
+pub const ACTIVATED_EVENT: &str = "notification://activated";
+
+/// Notifications delivered more than this long after their time are flagged `was_late`.
+const LATE_AFTER_MS: i64 = 60_000;
+/// How long after a notification fires a focus of the main window counts as a click.
+const ACTIVATION_WINDOW: Duration = Duration::from_secs(60);
+
+const DAY_MS: i64 = 24 * 60 * 60 * 1000;
+
+pub type NotificationId = i64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Repeat {
+    Daily,
+    Weekly,
+}
+
+impl Repeat {
+    fn period_ms(self) -> i64 {
+        match self {
+            Self::Daily => DAY_MS,
+            Self::Weekly => 7 * DAY_MS,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Daily => "daily",
+            Self::Weekly => "weekly",
+        }
+    }
+
+    fn parse(repeat: &str) -> Option<Self> {
+        match repeat {
+            "daily" => Some(Self::Daily),
+            "weekly" => Some(Self::Weekly),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationPayload {
+    pub title: String,
+    pub body: String,
+    /// RFC 3339 timestamp of the (next) delivery.
+    pub at: String,
+    pub repeat: Option<Repeat>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ScheduledNotification {
+    pub id: NotificationId,
+    #[serde(flatten)]
+    pub payload: NotificationPayload,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActivatedNotification {
+    pub id: NotificationId,
+    pub payload: NotificationPayload,
+    pub was_late: bool,
+}
+
+/// Wakes the scheduler when the schedule changes, and remembers the last notification
+/// delivered while the app was in the background in case the user clicks it.
+#[derive(Default)]
+pub struct NotificationScheduler {
+    wake: Notify,
+    last_fired: Mutex<Option<(Instant, ActivatedNotification)>>,
+}
+
+fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or_default()
+}
+
+fn format_ms(ms: i64) -> String {
+    OffsetDateTime::from_unix_timestamp_nanos(ms as i128 * 1_000_000)
+        .ok()
+        .and_then(|at| at.format(&Rfc3339).ok())
+        .unwrap_or_default()
+}
+
+type Row = (i64, String, String, i64, Option<String>);
+
+fn from_row((id, title, body, at_ms, repeat): Row) -> ScheduledNotification {
+    ScheduledNotification {
+        id,
+        payload: NotificationPayload {
+            title,
+            body,
+            at: format_ms(at_ms),
+            repeat: repeat.as_deref().and_then(Repeat::parse),
+        },
+    }
+}
+
+async fn next_due(pool: &SqlitePool) -> Result<Option<Row>, sqlx::Error> {
+    sqlx::query_as(
+        "SELECT id, title, body, at_ms, repeat FROM scheduled_notifications
+         WHERE native = 0 ORDER BY at_ms LIMIT 1",
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+fn deliver<R: Runtime>(app: &AppHandle<R>, notification: ActivatedNotification) {
+    let shown = app
+        .notification()
+        .builder()
+        .title(&notification.payload.title)
+        .body(&notification.payload.body)
+        .show();
+    if let Err(e) = shown {
+        tracing::warn!(id = notification.id, error = %e, "failed to show notification");
+        return;
+    }
+
+    let in_background = app
+        .get_webview_window("main")
+        .and_then(|w| w.is_focused().ok())
+        .is_none_or(|focused| !focused);
+    if in_background {
+        let scheduler = app.state::<NotificationScheduler>();
+        *scheduler.last_fired.lock().unwrap() = Some((Instant::now(), notification));
+    }
+}
+
+/// Fires one due notification and re-arms or removes it.
+async fn fire<R: Runtime>(
+    app: &AppHandle<R>,
+    pool: &SqlitePool,
+    row: Row,
+) -> Result<(), sqlx::Error> {
+    let now = now_ms();
+    let at_ms = row.3;
+    let scheduled = from_row(row);
+    let id = scheduled.id;
+
+    deliver(
+        app,
+        ActivatedNotification {
+            id,
+            payload: scheduled.payload.clone(),
+            was_late: now - at_ms > LATE_AFTER_MS,
+        },
+    );
+
+    match scheduled.payload.repeat {
+        // Occurrences missed while the app was closed collapse into the one just shown.
+        Some(repeat) => {
+            let period = repeat.period_ms();
+            let next = at_ms + ((now - at_ms) / period + 1) * period;
+            sqlx::query("UPDATE scheduled_notifications SET at_ms = ? WHERE id = ?")
+                .bind(next)
+                .bind(id)
+                .execute(pool)
+                .await?;
+        }
+        None => {
+            sqlx::query("DELETE FROM scheduled_notifications WHERE id = ?")
+                .bind(id)
+                .execute(pool)
+                .await?;
+        }
+    }
+    Ok(())
+}
+
+/// Runs the in-process scheduler. Notifications that came due while the app was closed
+/// fire as soon as migrations finish, flagged `was_late`.
+pub fn spawn<R: Runtime>(app: &AppHandle<R>) {
+    app.manage(NotificationScheduler::default());
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let db = app.state::<Db>();
+        let Ok(pool) = db.ready_pool().await else {
+            return;
+        };
+        let scheduler = app.state::<NotificationScheduler>();
+
+        loop {
+            let wake = scheduler.wake.notified();
+            tokio::pin!(wake);
+            wake.as_mut().enable();
+
+            // Platform-scheduled ones only need their row dropped once they've passed.
+            let _ =
+                sqlx::query("DELETE FROM scheduled_notifications WHERE native = 1 AND at_ms < ?")
+                    .bind(now_ms())
+                    .execute(pool)
+                    .await;
+
+            let next = match next_due(pool).await {
+                Ok(next) => next,
+                Err(e) => {
+                    tracing::error!(error = %e, "failed to load scheduled notifications");
+                    return;
+                }
+            };
+            let Some(row) = next else {
+                wake.await;
+                continue;
+            };
+
+            let delay = Duration::from_millis((row.3 - now_ms()).max(0) as u64);
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => {
+                    if let Err(e) = fire(&app, pool, row).await {
+                        tracing::error!(error = %e, "failed to re-arm scheduled notification");
+                    }
+                }
+                _ = &mut wake => {}
+            }
+        }
+    });
+}
+
+/// Desktop notification backends don't report clicks, but clicking one brings the app to
+/// the front; the first focus of the main window soon after a background delivery is
+/// treated as activating that notification.
+pub fn on_window_event<R: Runtime>(window: &Window<R>, event: &WindowEvent) {
+    if !matches!(event, WindowEvent::Focused(true)) || window.label() != "main" {
+        return;
+    }
+    let Some(scheduler) = window.try_state::<NotificationScheduler>() else {
+        return;
+    };
+    let fired = scheduler.last_fired.lock().unwrap().take();
+    if let Some((at, notification)) = fired {
+        if at.elapsed() <= ACTIVATION_WINDOW {
+            let _ = window.emit(ACTIVATED_EVENT, notification);
+        }
+    }
+}
+
+/// One-shot notifications on mobile go to the OS scheduler so they fire even if the app
+/// is killed; repeating ones use the in-process scheduler everywhere.
+#[cfg(mobile)]
+fn schedule_native<R: Runtime>(
+    app: &AppHandle<R>,
+    id: NotificationId,
+    payload: &NotificationPayload,
+    at: OffsetDateTime,
+) -> Result<bool, String> {
+    use tauri_plugin_notification::Schedule;
+
+    if payload.repeat.is_some() {
+        return Ok(false);
+    }
+    app.notification()
+        .builder()
+        .id(id as i32)
+        .title(&payload.title)
+        .body(&payload.body)
+        .schedule(Schedule::At {
+            date: at,
+            repeating: false,
+            allow_while_idle: true,
+        })
+        .show()
+        .map_err(|e| e.to_string())?;
+    Ok(true)
+}
+
+#[cfg(desktop)]
+fn schedule_native<R: Runtime>(
+    _app: &AppHandle<R>,
+    _id: NotificationId,
+    _payload: &NotificationPayload,
+    _at: OffsetDateTime,
+) -> Result<bool, String> {
+    Ok(false)
+}
+
+#[cfg(mobile)]
+fn cancel_native<R: Runtime>(app: &AppHandle<R>, id: NotificationId) -> Result<(), String> {
+    app.notification()
+        .cancel(vec![id as i32])
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(desktop)]
+fn cancel_native<R: Runtime>(_app: &AppHandle<R>, _id: NotificationId) -> Result<(), String> {
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn schedule_notification<R: Runtime>(
+    payload: NotificationPayload,
+    app: AppHandle<R>,
+    db: State<'_, Db>,
+    scheduler: State<'_, NotificationScheduler>,
+) -> Result<NotificationId, String> {
+    let at = OffsetDateTime::parse(&payload.at, &Rfc3339)
+        .map_err(|e| format!("invalid `at` timestamp: {e}"))?;
+    let at_ms = (at.unix_timestamp_nanos() / 1_000_000) as i64;
+    let pool = db.pool().map_err(|e| e.to_string())?;
+
+    let id = sqlx::query(
+        "INSERT INTO scheduled_notifications (title, body, at_ms, repeat) VALUES (?, ?, ?, ?)",
+    )
+    .bind(&payload.title)
+    .bind(&payload.body)
+    .bind(at_ms)
+    .bind(payload.repeat.map(Repeat::as_str))
+    .execute(pool)
+    .await
+    .map_err(|e| e.to_string())?
+    .last_insert_rowid();
+
+    if schedule_native(&app, id, &payload, at)? {
+        sqlx::query("UPDATE scheduled_notifications SET native = 1 WHERE id = ?")
+            .bind(id)
+            .execute(pool)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+
+    scheduler.wake.notify_one();
+    Ok(id)
+}
+
+#[tauri::command]
+pub async fn cancel_scheduled<R: Runtime>(
+    id: NotificationId,
+    app: AppHandle<R>,
+    db: State<'_, Db>,
+    scheduler: State<'_, NotificationScheduler>,
+) -> Result<(), String> {
+    let pool = db.pool().map_err(|e| e.to_string())?;
+    let native: Option<bool> =
+        sqlx::query_scalar("DELETE FROM scheduled_notifications WHERE id = ? RETURNING native")
+            .bind(id)
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| e.to_string())?;
+
+    match native {
+        None => return Err(format!("no scheduled notification with id {id}")),
+        Some(true) => cancel_native(&app, id)?,
+        Some(false) => {}
+    }
+
+    scheduler.wake.notify_one();
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn list_scheduled(db: State<'_, Db>) -> Result<Vec<ScheduledNotification>, String> {
+    let rows: Vec<Row> = sqlx::query_as(
+        "SELECT id, title, body, at_ms, repeat FROM scheduled_notifications ORDER BY at_ms",
+    )
+    .fetch_all(db.pool().map_err(|e| e.to_string())?)
+    .await
+    .map_err(|e| e.to_string())?;
+    Ok(rows.into_iter().map(from_row).collect())
+}
@@ -0,0 +1,349 @@
+//! Puts notes into the OS search index, so Spotlight and Windows Search find them. Each
+//! note gets a small stub file under `search-index/<id>/` in the app's local data
+//! directory, named after its title and opening `layers://note/<id>`, which comes back
+//! through [`crate::deep_link`] like any other link.
+//!
+//! - macOS: a `.webloc` with the body, title and tags in `com.apple.metadata:` extended
+//!   attributes, which Spotlight imports as the matching `kMDItem` attributes.
+//! - Windows: a `.url` with the same in its property section, in a folder added to the
+//!   indexer's crawl scope the first time anything is indexed.
+//! - Elsewhere every command returns [`OsIndexError::Unsupported`].
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use tauri::{AppHandle, Manager, Runtime, State};
+
+use crate::db::{Db, DbError};
+
+const INDEX_DIR: &str = "search-index";
+/// Notes read from SQLite per round of [`reindex_all`].
+const BATCH: i64 = 200;
+/// Indexers only look at so much of a file's metadata; longer bodies are cut here.
+const BODY_LIMIT: usize = 16 * 1024;
+const TITLE_LIMIT: usize = 100;
+
+#[derive(Debug, thiserror::Error, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "camelCase")]
+pub enum OsIndexError {
+    #[cfg_attr(any(target_os = "macos", windows), allow(dead_code))]
+    #[error("OS search indexing is not supported on this platform")]
+    Unsupported,
+    #[error("invalid note id: {0}")]
+    InvalidId(String),
+    #[error("index io failed: {0}")]
+    Io(String),
+    #[error(transparent)]
+    Db(#[from] DbError),
+}
+
+impl From<io::Error> for OsIndexError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e.to_string())
+    }
+}
+
+impl From<tauri::Error> for OsIndexError {
+    fn from(e: tauri::Error) -> Self {
+        Self::Io(e.to_string())
+    }
+}
+
+impl From<sqlx::Error> for OsIndexError {
+    fn from(e: sqlx::Error) -> Self {
+        Self::Db(e.into())
+    }
+}
+
+/// What a stub file carries, already trimmed to fit.
+#[cfg_attr(not(any(target_os = "macos", windows)), allow(dead_code))]
+struct Document {
+    /// `layers://note/<id>`.
+    url: String,
+    title: String,
+    body: String,
+    tags: Vec<String>,
+}
+
+fn truncate(text: &str, limit: usize) -> &str {
+    match text.char_indices().nth(limit) {
+        Some((end, _)) => &text[..end],
+        None => text,
+    }
+}
+
+/// Ids become directory names, so only the characters note ids are made of get through.
+fn check_id(id: &str) -> Result<(), OsIndexError> {
+    let valid = !id.is_empty()
+        && id.len() <= 64
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(OsIndexError::InvalidId(id.to_string()))
+    }
+}
+
+/// The title as a file name, which is what search results show.
+fn file_stem(title: &str) -> String {
+    let stem: String = truncate(title.trim(), TITLE_LIMIT)
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '-',
+            c if c.is_control() => ' ',
+            c => c,
+        })
+        .collect();
+    // Windows won't have names ending in a dot or space, and a leading dot hides the file.
+    let stem = stem.trim_matches(|c: char| c == '.' || c.is_whitespace());
+    if stem.is_empty() {
+        "Untitled".into()
+    } else {
+        stem.to_string()
+    }
+}
+
+fn index_root<R: Runtime>(app: &AppHandle<R>) -> Result<PathBuf, OsIndexError> {
+    let root = app.path().app_local_data_dir()?.join(INDEX_DIR);
+    fs::create_dir_all(&root)?;
+    os::prepare(&root)?;
+    Ok(root)
+}
+
+/// Replaces the note's stub, whose name changes with its title.
+fn write_document(
+    root: &Path,
+    id: &str,
+    title: &str,
+    body: &str,
+    tags: Vec<String>,
+) -> Result<(), OsIndexError> {
+    check_id(id)?;
+    let dir = root.join(id);
+    match fs::remove_dir_all(&dir) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+        _ => {}
+    }
+    fs::create_dir_all(&dir)?;
+    let document = Document {
+        url: format!("layers://note/{id}"),
+        title: title.to_string(),
+        body: truncate(body, BODY_LIMIT).to_string(),
+        tags,
+    };
+    Ok(os::write_stub(&dir, &file_stem(title), &document)?)
+}
+
+#[cfg(target_os = "macos")]
+mod os {
+    use std::io;
+    use std::path::Path;
+
+    use plist::Value;
+
+    use super::{Document, OsIndexError};
+
+    /// Spotlight watches the file system itself, so there is nothing to register.
+    pub fn prepare(_root: &Path) -> Result<(), OsIndexError> {
+        Ok(())
+    }
+
+    /// Spotlight reads `com.apple.metadata:<key>` attributes holding binary plists as
+    /// the `<key>` attribute of the file.
+    fn set_metadata(path: &Path, key: &str, value: Value) -> io::Result<()> {
+        let mut bytes = Vec::new();
+        value
+            .to_writer_binary(&mut bytes)
+            .map_err(io::Error::other)?;
+        xattr::set(path, format!("com.apple.metadata:{key}"), &bytes)
+    }
+
+    pub fn write_stub(dir: &Path, stem: &str, document: &Document) -> io::Result<()> {
+        let path = dir.join(format!("{stem}.webloc"));
+        let mut webloc = plist::Dictionary::new();
+        webloc.insert("URL".into(), Value::String(document.url.clone()));
+        Value::Dictionary(webloc)
+            .to_file_xml(&path)
+            .map_err(io::Error::other)?;
+
+        set_metadata(&path, "kMDItemTitle", Value::String(document.title.clone()))?;
+        set_metadata(
+            &path,
+            "kMDItemFinderComment",
+            Value::String(document.body.clone()),
+        )?;
+        let tags = document.tags.iter().cloned().map(Value::String).collect();
+        set_metadata(&path, "kMDItemKeywords", Value::Array(tags))
+    }
+}
+
+#[cfg(windows)]
+mod os {
+    use std::io;
+    use std::path::Path;
+    use std::sync::Once;
+
+    use windows::core::{w, HSTRING};
+    use windows::Win32::System::Com::{
+        CoCreateInstance, CoInitializeEx, CLSCTX_LOCAL_SERVER, COINIT_MULTITHREADED,
+    };
+    use windows::Win32::System::Search::{CSearchManager, ISearchManager, FF_INDEXCOMPLEXURLS};
+
+    use super::{Document, OsIndexError};
+
+    /// `FMTID_SummaryInformation`, whose title, keywords and comment properties the
+    /// indexer reads from a shortcut's property section.
+    const SUMMARY_INFORMATION: &str = "{F29F85E0-4FF9-1068-AB91-08002B27B3D9}";
+    /// `VT_LPWSTR`.
+    const STRING: u32 = 31;
+
+    /// The app's local data folder isn't indexed by default, so it's added to the crawl
+    /// scope once per run. Failing that, stubs are still written and show up if the user
+    /// adds the folder to the index themselves.
+    pub fn prepare(root: &Path) -> Result<(), OsIndexError> {
+        static REGISTER: Once = Once::new();
+        REGISTER.call_once(|| {
+            if let Err(e) = register(root) {
+                tracing::warn!(error = %e, "couldn't add the search index folder to Windows Search");
+            }
+        });
+        Ok(())
+    }
+
+    fn register(root: &Path) -> windows::core::Result<()> {
+        let url = HSTRING::from(format!("file:///{}\\", root.display()));
+        unsafe {
+            // Already initialized on this thread is fine; any model will do.
+            let _ = CoInitializeEx(None, COINIT_MULTITHREADED);
+            let manager: ISearchManager =
+                CoCreateInstance(&CSearchManager, None, CLSCTX_LOCAL_SERVER)?;
+            let scope = manager
+                .GetCatalog(w!("SystemIndex"))?
+                .GetCrawlScopeManager()?;
+            if !scope.IncludedInCrawlScope(&url)?.as_bool() {
+                scope.AddUserScopeRule(&url, true, false, FF_INDEXCOMPLEXURLS.0 as u32)?;
+                scope.SaveAll()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// INI values end at a line break.
+    fn one_line(text: &str) -> String {
+        text.split(['\r', '\n'])
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    pub fn write_stub(dir: &Path, stem: &str, document: &Document) -> io::Result<()> {
+        let ini = format!(
+            "[InternetShortcut]\r\nURL={url}\r\n[{SUMMARY_INFORMATION}]\r\nProp2={STRING},{title}\r\nProp5={STRING},{tags}\r\nProp6={STRING},{body}\r\n",
+            url = document.url,
+            title = one_line(&document.title),
+            tags = one_line(&document.tags.join("; ")),
+            body = one_line(&document.body),
+        );
+        // UTF-16 with a BOM is what the shell reads as a Unicode INI file.
+        let mut bytes = vec![0xFF, 0xFE];
+        bytes.extend(ini.encode_utf16().flat_map(u16::to_le_bytes));
+        std::fs::write(dir.join(format!("{stem}.url")), bytes)
+    }
+}
+
+#[cfg(not(any(target_os = "macos", windows)))]
+mod os {
+    use std::io;
+    use std::path::Path;
+
+    use super::{Document, OsIndexError};
+
+    pub fn prepare(_root: &Path) -> Result<(), OsIndexError> {
+        Err(OsIndexError::Unsupported)
+    }
+
+    pub fn write_stub(_dir: &Path, _stem: &str, _document: &Document) -> io::Result<()> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+}
+
+/// Adds or updates one note in the OS index. The body is cut to 16 KiB.
+#[tauri::command]
+pub fn index_document<R: Runtime>(
+    id: String,
+    title: String,
+    body: String,
+    tags: Vec<String>,
+    app: AppHandle<R>,
+) -> Result<(), OsIndexError> {
+    let root = index_root(&app)?;
+    write_document(&root, &id, &title, &body, tags)
+}
+
+/// Does nothing for notes that were never indexed.
+#[tauri::command]
+pub fn remove_document<R: Runtime>(id: String, app: AppHandle<R>) -> Result<(), OsIndexError> {
+    check_id(&id)?;
+    let root = index_root(&app)?;
+    match fs::remove_dir_all(root.join(&id)) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+/// Rebuilds the index from every note that isn't deleted, reading `BATCH` notes at a
+/// time. Returns how many were indexed.
+#[tauri::command]
+pub async fn reindex_all<R: Runtime>(
+    app: AppHandle<R>,
+    db: State<'_, Db>,
+) -> Result<u64, OsIndexError> {
+    let root = index_root(&app)?;
+    let pool = db.pool()?;
+    {
+        let root = root.clone();
+        tauri::async_runtime::spawn_blocking(move || -> io::Result<()> {
+            fs::remove_dir_all(&root)?;
+            fs::create_dir_all(&root)
+        })
+        .await
+        .map_err(|e| OsIndexError::Io(e.to_string()))??;
+    }
+
+    let mut indexed = 0;
+    let mut after = String::new();
+    loop {
+        let notes: Vec<(String, String, String)> = sqlx::query_as(
+            "SELECT id, title, COALESCE(content_text, '') FROM notes
+             WHERE is_deleted = 0 AND id > ? ORDER BY id LIMIT ?",
+        )
+        .bind(&after)
+        .bind(BATCH)
+        .fetch_all(pool)
+        .await?;
+        let Some((last, _, _)) = notes.last() else {
+            break;
+        };
+        after = last.clone();
+
+        let root = root.clone();
+        indexed += tauri::async_runtime::spawn_blocking(move || {
+            let mut written = 0;
+            for (id, title, body) in notes {
+                // Notes aren't tagged yet, so only the title and body are indexed.
+                match write_document(&root, &id, &title, &body, Vec::new()) {
+                    Ok(()) => written += 1,
+                    Err(e) => tracing::warn!(%id, error = %e, "couldn't index note"),
+                }
+            }
+            written
+        })
+        .await
+        .map_err(|e| OsIndexError::Io(e.to_string()))?;
+    }
+    Ok(indexed)
+}
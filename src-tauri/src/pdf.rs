@@ -0,0 +1,856 @@
+//! Saves a webview's page as a PDF using the platform webview's own renderer, so the
+//! file looks like the screen rather than a JS library's approximation of it.
+//!
+//! - Windows: WebView2's `PrintToPdf`, paginated with the requested page size, margins
+//!   and orientation.
+//! - macOS: `WKWebView.createPDF`, which renders the whole page as one page the width of
+//!   the webview; the page options don't apply there and backgrounds are always drawn.
+//! - Elsewhere [`PdfError::Unsupported`] names the platform, so the frontend can fall back
+//!   to rendering it in JS.
+//!
+//! [`generate_pdf`] renders an HTML string instead, for exports that never appear on
+//! screen. Desktop hands it to `wkhtmltopdf`, bundled beside the executable or found on
+//! `PATH`, which is the only renderer that takes header and footer HTML; mobile prints it
+//! with the platform's print renderer, through `plugins/pdf`.
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, Runtime, WebviewWindow};
+use tauri_plugin_dialog::DialogExt;
+
+use crate::scope;
+
+pub const PROGRESS_EVENT: &str = "pdf://progress";
+
+/// How long to wait for `document.readyState` to reach `complete`.
+const READY_TIMEOUT: Duration = Duration::from_secs(30);
+const READY_POLL: Duration = Duration::from_millis(100);
+
+#[derive(Debug, thiserror::Error, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "camelCase")]
+pub enum PdfError {
+    /// Carries `std::env::consts::OS`.
+    #[error("PDF export is not supported on {0}")]
+    Unsupported(String),
+    #[error("no window labelled {0}")]
+    WindowNotFound(String),
+    #[error("no destination was chosen")]
+    Cancelled,
+    #[error("{0}")]
+    Scope(String),
+    #[error("the page did not finish loading")]
+    Timeout,
+    #[error("PDF export failed: {0}")]
+    Failed(String),
+    #[error("io error: {0}")]
+    Io(String),
+    #[error("no PDF renderer: {0}")]
+    RendererNotFound(String),
+    #[error("could not render the HTML: {0}")]
+    HtmlParseError(String),
+    #[error("could not write the PDF: {0}")]
+    OutputWriteFailed(String),
+}
+
+impl From<std::io::Error> for PdfError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e.to_string())
+    }
+}
+
+impl From<tauri::Error> for PdfError {
+    fn from(e: tauri::Error) -> Self {
+        Self::Failed(e.to_string())
+    }
+}
+
+#[cfg_attr(not(windows), allow(dead_code))]
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PageSize {
+    A3,
+    #[default]
+    A4,
+    A5,
+    Letter,
+    Legal,
+}
+
+#[cfg_attr(not(any(windows, mobile)), allow(dead_code))]
+impl PageSize {
+    /// Portrait width and height in inches, which WebView2 takes.
+    fn inches(self) -> (f64, f64) {
+        match self {
+            Self::A3 => (11.69, 16.54),
+            Self::A4 => (8.27, 11.69),
+            Self::A5 => (5.83, 8.27),
+            Self::Letter => (8.5, 11.0),
+            Self::Legal => (8.5, 14.0),
+        }
+    }
+
+    /// As `wkhtmltopdf --page-size` takes it.
+    #[cfg(desktop)]
+    fn name(self) -> &'static str {
+        match self {
+            Self::A3 => "A3",
+            Self::A4 => "A4",
+            Self::A5 => "A5",
+            Self::Letter => "Letter",
+            Self::Legal => "Legal",
+        }
+    }
+}
+
+#[cfg_attr(not(windows), allow(dead_code))]
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PdfOptions {
+    pub page_size: PageSize,
+    /// Applied to all four sides.
+    pub margins_mm: f64,
+    pub landscape: bool,
+    pub print_background: bool,
+    /// How long to wait after the page has loaded, for fonts, images and animations
+    /// that finish after `load`.
+    pub settle_ms: u64,
+}
+
+impl Default for PdfOptions {
+    fn default() -> Self {
+        Self {
+            page_size: PageSize::A4,
+            margins_mm: 10.0,
+            landscape: false,
+            print_background: true,
+            settle_ms: 500,
+        }
+    }
+}
+
+/// What [`generate_pdf`] takes; [`PdfOptions`] is for a webview that's already laid out.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HtmlPdfOptions {
+    pub output_path: String,
+    #[serde(default)]
+    pub page_size: PageSize,
+    /// Applied to all four sides.
+    #[serde(default = "default_margin_mm")]
+    pub margin_mm: f32,
+    #[serde(default)]
+    pub landscape: bool,
+    /// Complete documents, repeated on every page: in the margins on desktop and iOS,
+    /// inside them on Android. wkhtmltopdf requires a `<!DOCTYPE>`.
+    pub header_html: Option<String>,
+    pub footer_html: Option<String>,
+}
+
+fn default_margin_mm() -> f32 {
+    10.0
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PdfStage {
+    Loading,
+    Settling,
+    Rendering,
+    Done,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PdfProgress<'a> {
+    window_label: &'a str,
+    stage: PdfStage,
+    /// Only known once the PDF is written.
+    pages: Option<u32>,
+}
+
+/// [`PROGRESS_EVENT`]'s payload for [`generate_pdf`], told apart from [`PdfProgress`] by
+/// having an `outputPath` instead of a `windowLabel`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GenerateProgress {
+    output_path: PathBuf,
+    percent: u8,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PdfResult {
+    pub path: PathBuf,
+    pub pages: u32,
+}
+
+/// A oneshot sender the native callbacks can share: the webview calls back from blocks
+/// and handlers that may be called through `Fn`, and setup failures need to reply too.
+/// [`crate::capture`] uses it the same way.
+#[cfg(any(target_os = "macos", target_os = "linux", windows))]
+pub(crate) struct Reply<T>(
+    std::sync::Arc<std::sync::Mutex<Option<tokio::sync::oneshot::Sender<T>>>>,
+);
+
+#[cfg(any(target_os = "macos", target_os = "linux", windows))]
+impl<T> Clone for Reply<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+#[cfg(any(target_os = "macos", target_os = "linux", windows))]
+impl<T> Reply<T> {
+    pub(crate) fn new() -> (Self, tokio::sync::oneshot::Receiver<T>) {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        (
+            Self(std::sync::Arc::new(std::sync::Mutex::new(Some(tx)))),
+            rx,
+        )
+    }
+
+    pub(crate) fn send(&self, value: T) {
+        if let Some(tx) = self.0.lock().unwrap().take() {
+            let _ = tx.send(value);
+        }
+    }
+}
+
+#[cfg(any(target_os = "macos", windows))]
+async fn receive<T>(rx: tokio::sync::oneshot::Receiver<T>) -> Result<T, PdfError> {
+    rx.await
+        .map_err(|_| PdfError::Failed("the webview closed before replying".into()))
+}
+
+#[cfg(windows)]
+mod native {
+    use std::path::Path;
+
+    use tauri::{Runtime, WebviewWindow};
+    use webview2_com::Microsoft::Web::WebView2::Win32::{
+        ICoreWebView2Environment6, ICoreWebView2_7, COREWEBVIEW2_PRINT_ORIENTATION_LANDSCAPE,
+        COREWEBVIEW2_PRINT_ORIENTATION_PORTRAIT,
+    };
+    use webview2_com::{ExecuteScriptCompletedHandler, PrintToPdfCompletedHandler};
+    use windows::core::{w, Interface, HSTRING};
+
+    use super::{receive, PdfError, PdfOptions, Reply};
+
+    pub const SUPPORTED: bool = true;
+
+    fn failed(e: windows::core::Error) -> PdfError {
+        PdfError::Failed(e.message())
+    }
+
+    pub async fn ready_state<R: Runtime>(webview: &WebviewWindow<R>) -> Result<String, PdfError> {
+        let (reply, rx) = Reply::new();
+        webview.with_webview(move |platform| {
+            let handler = reply.clone();
+            let started = unsafe {
+                platform.controller().CoreWebView2().and_then(|core| {
+                    core.ExecuteScript(
+                        w!("document.readyState"),
+                        &ExecuteScriptCompletedHandler::create(Box::new(move |result, json| {
+                            handler.send(result.map(|()| json));
+                            Ok(())
+                        })),
+                    )
+                })
+            };
+            if let Err(e) = started {
+                reply.send(Err(e));
+            }
+        })?;
+        // A JSON string, quotes and all.
+        let json = receive(rx).await?.map_err(failed)?;
+        Ok(json.trim_matches('"').to_string())
+    }
+
+    pub async fn render<R: Runtime>(
+        webview: &WebviewWindow<R>,
+        options: &PdfOptions,
+        path: &Path,
+    ) -> Result<(), PdfError> {
+        let (reply, rx) = Reply::new();
+        let options = options.clone();
+        let path = HSTRING::from(path.as_os_str());
+        webview.with_webview(move |platform| {
+            let handler = reply.clone();
+            let started = unsafe {
+                (|| {
+                    let core: ICoreWebView2_7 = platform.controller().CoreWebView2()?.cast()?;
+                    let environment: ICoreWebView2Environment6 = platform.environment().cast()?;
+                    let settings = environment.CreatePrintSettings()?;
+                    let (width, height) = options.page_size.inches();
+                    settings.SetOrientation(if options.landscape {
+                        COREWEBVIEW2_PRINT_ORIENTATION_LANDSCAPE
+                    } else {
+                        COREWEBVIEW2_PRINT_ORIENTATION_PORTRAIT
+                    })?;
+                    settings.SetPageWidth(width)?;
+                    settings.SetPageHeight(height)?;
+                    let margin = options.margins_mm / 25.4;
+                    settings.SetMarginTop(margin)?;
+                    settings.SetMarginBottom(margin)?;
+                    settings.SetMarginLeft(margin)?;
+                    settings.SetMarginRight(margin)?;
+                    settings.SetShouldPrintBackgrounds(options.print_background)?;
+                    core.PrintToPdf(
+                        &path,
+                        &settings,
+                        &PrintToPdfCompletedHandler::create(Box::new(move |result, written| {
+                            handler.send(result.map(|()| written));
+                            Ok(())
+                        })),
+                    )
+                })()
+            };
+            if let Err(e) = started {
+                reply.send(Err(e));
+            }
+        })?;
+        match receive(rx).await?.map_err(failed)? {
+            true => Ok(()),
+            false => Err(PdfError::Failed("WebView2 did not write the PDF".into())),
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod native {
+    use std::cell::Cell;
+    use std::path::{Path, PathBuf};
+
+    use block2::RcBlock;
+    use objc2::runtime::AnyObject;
+    use objc2_foundation::{NSData, NSError, NSString};
+    use objc2_web_kit::WKWebView;
+    use tauri::{Runtime, WebviewWindow};
+
+    use super::{receive, PdfError, PdfOptions, Reply};
+
+    pub const SUPPORTED: bool = true;
+
+    fn failed(error: *mut NSError) -> PdfError {
+        match unsafe { error.as_ref() } {
+            Some(error) => PdfError::Failed(error.localizedDescription().to_string()),
+            None => PdfError::Failed("WebKit returned no result".into()),
+        }
+    }
+
+    pub async fn ready_state<R: Runtime>(webview: &WebviewWindow<R>) -> Result<String, PdfError> {
+        let (reply, rx) = Reply::new();
+        webview.with_webview(move |platform| unsafe {
+            let view = &*(platform.inner() as *const WKWebView);
+            let block = RcBlock::new(move |result: *mut AnyObject, error: *mut NSError| {
+                let state = result
+                    .as_ref()
+                    .and_then(|result| result.downcast_ref::<NSString>())
+                    .map(|state| state.to_string());
+                reply.send(state.ok_or_else(|| failed(error)));
+            });
+            view.evaluateJavaScript_completionHandler(
+                &NSString::from_str("document.readyState"),
+                Some(&block),
+            );
+        })?;
+        receive(rx).await?
+    }
+
+    pub async fn render<R: Runtime>(
+        webview: &WebviewWindow<R>,
+        _options: &PdfOptions,
+        path: &Path,
+    ) -> Result<(), PdfError> {
+        let (reply, rx) = Reply::new();
+        let path: PathBuf = path.to_path_buf();
+        webview.with_webview(move |platform| unsafe {
+            let view = &*(platform.inner() as *const WKWebView);
+            // The block is `Fn`; WebKit calls it once.
+            let path = Cell::new(Some(path));
+            let block = RcBlock::new(move |data: *mut NSData, error: *mut NSError| {
+                let result = match (data.as_ref(), path.take()) {
+                    (Some(data), Some(path)) => {
+                        std::fs::write(path, data.to_vec()).map_err(Into::into)
+                    }
+                    _ => Err(failed(error)),
+                };
+                reply.send(result);
+            });
+            view.createPDFWithConfiguration_completionHandler(None, &block);
+        })?;
+        receive(rx).await?
+    }
+}
+
+#[cfg(not(any(target_os = "macos", windows)))]
+mod native {
+    use std::path::Path;
+
+    use tauri::{Runtime, WebviewWindow};
+
+    use super::{PdfError, PdfOptions};
+
+    pub const SUPPORTED: bool = false;
+
+    fn unsupported() -> PdfError {
+        PdfError::Unsupported(std::env::consts::OS.into())
+    }
+
+    pub async fn ready_state<R: Runtime>(_webview: &WebviewWindow<R>) -> Result<String, PdfError> {
+        Err(unsupported())
+    }
+
+    pub async fn render<R: Runtime>(
+        _webview: &WebviewWindow<R>,
+        _options: &PdfOptions,
+        _path: &Path,
+    ) -> Result<(), PdfError> {
+        Err(unsupported())
+    }
+}
+
+#[cfg(desktop)]
+mod wkhtmltopdf {
+    use std::fs;
+    use std::io::Read;
+    use std::path::{Path, PathBuf};
+    use std::process::{Command, Stdio};
+
+    use super::{HtmlPdfOptions, PdfError};
+
+    const BINARY: &str = "wkhtmltopdf";
+
+    /// A copy bundled beside the executable, as Tauri places sidecars, wins over `PATH`.
+    pub fn find() -> Result<PathBuf, PdfError> {
+        let bundled = std::env::current_exe().ok().and_then(|exe| {
+            let path = exe.with_file_name(format!("{BINARY}{}", std::env::consts::EXE_SUFFIX));
+            path.is_file().then_some(path)
+        });
+        match bundled {
+            Some(path) => Ok(path),
+            None => which::which(BINARY).map_err(|_| {
+                PdfError::RendererNotFound(format!("{BINARY} isn't bundled or on PATH"))
+            }),
+        }
+    }
+
+    /// Tracks the stderr progress lines: a `Loading pages (1/6)` heading per phase, then a
+    /// bar ending in a percentage or, while printing, `Page 2 of 5`.
+    #[derive(Default)]
+    struct Progress {
+        phase: u32,
+        phases: u32,
+        reported: u8,
+    }
+
+    impl Progress {
+        /// The whole job's percentage, if `line` moved it forward.
+        fn update(&mut self, line: &str) -> Option<u8> {
+            let within = if let Some((phase, phases)) = line
+                .rsplit_once('(')
+                .and_then(|(_, rest)| rest.strip_suffix(')'))
+                .and_then(|counts| counts.split_once('/'))
+            {
+                self.phase = phase.trim().parse().ok()?;
+                self.phases = phases.trim().parse().ok()?;
+                0
+            } else if let Some((_, pages)) = line.split_once("] Page ") {
+                let (page, of) = pages.split_once(" of ")?;
+                let (page, of): (u32, u32) = (page.trim().parse().ok()?, of.trim().parse().ok()?);
+                page * 100 / of.max(1)
+            } else if line.starts_with('[') {
+                line.rsplit_once(']')?
+                    .1
+                    .trim()
+                    .strip_suffix('%')?
+                    .parse()
+                    .ok()?
+            } else {
+                return None;
+            };
+            if self.phase == 0 || self.phases == 0 {
+                return None;
+            }
+            let done = (self.phase - 1) * 100 + within.min(100);
+            // Never 100 here: that's for once the file is in place.
+            let percent = (done / self.phases).min(99) as u8;
+            (percent > self.reported).then(|| {
+                self.reported = percent;
+                percent
+            })
+        }
+    }
+
+    /// Writes the documents to `dir` and renders them to `out`, blocking until done.
+    pub fn render(
+        binary: &Path,
+        html: &str,
+        options: &HtmlPdfOptions,
+        dir: &Path,
+        out: &Path,
+        mut progress: impl FnMut(u8),
+    ) -> Result<(), PdfError> {
+        // wkhtmltopdf recognises local files by their extension.
+        let body = dir.join("body.html");
+        fs::write(&body, html)?;
+        let margin = format!("{}mm", options.margin_mm.max(0.0));
+        let mut command = Command::new(binary);
+        command
+            .args(["--page-size", options.page_size.name()])
+            .args([
+                "--orientation",
+                if options.landscape {
+                    "Landscape"
+                } else {
+                    "Portrait"
+                },
+            ])
+            .args(["--margin-top", &margin, "--margin-bottom", &margin])
+            .args(["--margin-left", &margin, "--margin-right", &margin])
+            .args(["--encoding", "utf-8", "--enable-local-file-access"]);
+        for (flag, name, html) in [
+            ("--header-html", "header.html", &options.header_html),
+            ("--footer-html", "footer.html", &options.footer_html),
+        ] {
+            if let Some(html) = html {
+                let path = dir.join(name);
+                fs::write(&path, html)?;
+                command.arg(flag).arg(path);
+            }
+        }
+        let mut child = command
+            .arg(&body)
+            .arg(out)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| PdfError::RendererNotFound(format!("{}: {e}", binary.display())))?;
+
+        // Bars redraw in place with `\r`, so both it and `\n` end a line.
+        let mut stderr = child.stderr.take().expect("stderr is piped");
+        let mut tracker = Progress::default();
+        let mut messages = Vec::new();
+        let mut line = Vec::new();
+        let mut buf = [0u8; 1024];
+        loop {
+            let read = stderr.read(&mut buf)?;
+            for &byte in &buf[..read] {
+                if byte != b'\r' && byte != b'\n' {
+                    line.push(byte);
+                    continue;
+                }
+                let text = String::from_utf8_lossy(&line).trim().to_string();
+                line.clear();
+                if text.is_empty() {
+                    continue;
+                }
+                match tracker.update(&text) {
+                    Some(percent) => progress(percent),
+                    None if !text.starts_with('[') => messages.push(text),
+                    None => {}
+                }
+            }
+            if read == 0 {
+                break;
+            }
+        }
+        let status = child.wait()?;
+        if status.success() {
+            return Ok(());
+        }
+
+        let message = messages.join("\n");
+        // A missing image or stylesheet fails the run, but the PDF is written all the same.
+        if message.contains("network error") && out.is_file() {
+            tracing::warn!(%message, "wkhtmltopdf could not load every resource");
+            return Ok(());
+        }
+        Err(if message.contains("Unable to write") {
+            PdfError::OutputWriteFailed(message)
+        } else if message.contains("Failed loading page") {
+            PdfError::HtmlParseError(message)
+        } else {
+            PdfError::Failed(message)
+        })
+    }
+}
+
+#[cfg(mobile)]
+mod print {
+    use std::path::Path;
+
+    use serde::{Deserialize, Serialize};
+    use serde_json::Value;
+    use tauri::ipc::{Channel, InvokeResponseBody};
+    use tauri::plugin::{PluginHandle, PluginInvokeError, TauriPlugin};
+    use tauri::{AppHandle, Manager, Runtime};
+    // Linked for its native halves; it has no Rust API.
+    use tauri_plugin_pdf as _;
+
+    use super::{HtmlPdfOptions, PdfError};
+
+    #[cfg(target_os = "ios")]
+    tauri::ios_plugin_binding!(init_plugin_pdf);
+
+    const POINTS_PER_INCH: f64 = 72.0;
+
+    struct Pdf<R: Runtime>(PluginHandle<R>);
+
+    /// Sizes in points; the page is already turned for landscape.
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    struct GenerateArgs<'a> {
+        html: &'a str,
+        path: &'a Path,
+        page_width: f64,
+        page_height: f64,
+        margin: f64,
+        header_html: Option<&'a str>,
+        footer_html: Option<&'a str>,
+        on_progress: Channel,
+    }
+
+    #[derive(Deserialize)]
+    struct NativeProgress {
+        percent: u8,
+    }
+
+    pub fn plugin<R: Runtime>() -> TauriPlugin<R> {
+        tauri::plugin::Builder::new("pdf")
+            .setup(|app, api| {
+                #[cfg(target_os = "android")]
+                let handle = api.register_android_plugin("com.layers.pdf", "PdfPlugin")?;
+                #[cfg(target_os = "ios")]
+                let handle = api.register_ios_plugin(init_plugin_pdf)?;
+                app.manage(Pdf(handle));
+                Ok(())
+            })
+            .build()
+    }
+
+    fn from_code(code: Option<&str>, message: String) -> PdfError {
+        match code {
+            Some("htmlParseError") => PdfError::HtmlParseError(message),
+            Some("outputWriteFailed") => PdfError::OutputWriteFailed(message),
+            Some("rendererNotFound") => PdfError::RendererNotFound(message),
+            _ => PdfError::Failed(message),
+        }
+    }
+
+    pub async fn render<R: Runtime>(
+        app: &AppHandle<R>,
+        html: &str,
+        options: &HtmlPdfOptions,
+        out: &Path,
+        progress: impl Fn(u8) + Send + Sync + 'static,
+    ) -> Result<(), PdfError> {
+        let handle = app
+            .try_state::<Pdf<R>>()
+            .map(|pdf| pdf.0.clone())
+            .ok_or_else(|| PdfError::RendererNotFound("pdf plugin not loaded".into()))?;
+        let (width, height) = options.page_size.inches();
+        let (width, height) = if options.landscape {
+            (height, width)
+        } else {
+            (width, height)
+        };
+        let channel = Channel::new(move |body| {
+            if let InvokeResponseBody::Json(json) = body {
+                match serde_json::from_str::<NativeProgress>(&json) {
+                    Ok(update) => progress(update.percent.min(99)),
+                    Err(e) => tracing::warn!(error = %e, "malformed PDF progress"),
+                }
+            }
+            Ok(())
+        });
+        let args = GenerateArgs {
+            html,
+            path: out,
+            page_width: width * POINTS_PER_INCH,
+            page_height: height * POINTS_PER_INCH,
+            margin: f64::from(options.margin_mm.max(0.0)) / 25.4 * POINTS_PER_INCH,
+            header_html: options.header_html.as_deref(),
+            footer_html: options.footer_html.as_deref(),
+            on_progress: channel,
+        };
+        match handle
+            .run_mobile_plugin_async::<Value>("generatePdf", args)
+            .await
+        {
+            Ok(_) => Ok(()),
+            Err(PluginInvokeError::InvokeRejected(response)) => Err(from_code(
+                response.code.as_deref(),
+                response.message.unwrap_or_default(),
+            )),
+            Err(e) => Err(PdfError::Failed(e.to_string())),
+        }
+    }
+}
+
+#[cfg(mobile)]
+pub fn plugin<R: Runtime>() -> tauri::plugin::TauriPlugin<R> {
+    print::plugin()
+}
+
+async fn pick_destination<R: Runtime>(app: &AppHandle<R>) -> Result<PathBuf, PdfError> {
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    app.dialog()
+        .file()
+        .set_file_name("export.pdf")
+        .add_filter("PDF", &["pdf"])
+        .save_file(move |path| {
+            let _ = tx.send(path);
+        });
+
+    rx.await
+        .ok()
+        .flatten()
+        .ok_or(PdfError::Cancelled)?
+        .into_path()
+        .map_err(|e| PdfError::Io(e.to_string()))
+}
+
+async fn wait_until_loaded<R: Runtime>(webview: &WebviewWindow<R>) -> Result<(), PdfError> {
+    let started = Instant::now();
+    while native::ready_state(webview).await? != "complete" {
+        if started.elapsed() > READY_TIMEOUT {
+            return Err(PdfError::Timeout);
+        }
+        tokio::time::sleep(READY_POLL).await;
+    }
+    Ok(())
+}
+
+/// Page objects in the file. Both renderers write one uncompressed `/Type /Page`
+/// dictionary per page, with or without the space.
+fn count_pages(pdf: &[u8]) -> u32 {
+    let mut pages = 0;
+    for needle in [&b"/Type /Page"[..], &b"/Type/Page"[..]] {
+        pages += pdf
+            .windows(needle.len() + 1)
+            .filter(|window| window.starts_with(needle) && window[needle.len()] != b's')
+            .count() as u32;
+    }
+    pages
+}
+
+/// Renders the webview's current page to `dest_path`, or to a file the user picks if
+/// it's omitted. Emits [`PROGRESS_EVENT`] at each stage, then with the page count once
+/// the file is written.
+#[tauri::command]
+pub async fn print_to_pdf<R: Runtime>(
+    window_label: String,
+    dest_path: Option<String>,
+    opts: Option<PdfOptions>,
+    app: AppHandle<R>,
+) -> Result<PdfResult, PdfError> {
+    if !native::SUPPORTED {
+        return Err(PdfError::Unsupported(std::env::consts::OS.into()));
+    }
+    let options = opts.unwrap_or_default();
+    let webview = app
+        .get_webview_window(&window_label)
+        .ok_or_else(|| PdfError::WindowNotFound(window_label.clone()))?;
+    let dest = match dest_path {
+        Some(path) => scope::ensure_allowed(&app, Path::new(&path)).map_err(PdfError::Scope)?,
+        None => pick_destination(&app).await?,
+    };
+    let progress = |stage, pages| {
+        let _ = app.emit(
+            PROGRESS_EVENT,
+            PdfProgress {
+                window_label: &window_label,
+                stage,
+                pages,
+            },
+        );
+    };
+
+    progress(PdfStage::Loading, None);
+    wait_until_loaded(&webview).await?;
+    progress(PdfStage::Settling, None);
+    tokio::time::sleep(Duration::from_millis(options.settle_ms)).await;
+
+    progress(PdfStage::Rendering, None);
+    let mut tmp = dest.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+    if let Err(e) = native::render(&webview, &options, &tmp).await {
+        let _ = tokio::fs::remove_file(&tmp).await;
+        return Err(e);
+    }
+    let pages = count_pages(&tokio::fs::read(&tmp).await?);
+    tokio::fs::rename(&tmp, &dest).await?;
+
+    progress(PdfStage::Done, Some(pages));
+    Ok(PdfResult { path: dest, pages })
+}
+
+/// Renders `html` to `options.output_path` and returns the path written. Emits
+/// [`PROGRESS_EVENT`] with a percentage as the renderer reports it, and 100 once the file
+/// is in place.
+#[tauri::command]
+pub async fn generate_pdf<R: Runtime>(
+    html: String,
+    options: HtmlPdfOptions,
+    app: AppHandle<R>,
+) -> Result<String, PdfError> {
+    if html.trim().is_empty() {
+        return Err(PdfError::HtmlParseError("no HTML given".into()));
+    }
+    let dest =
+        scope::ensure_allowed(&app, Path::new(&options.output_path)).map_err(PdfError::Scope)?;
+    let progress = {
+        let app = app.clone();
+        let output_path = dest.clone();
+        move |percent| {
+            let _ = app.emit(
+                PROGRESS_EVENT,
+                GenerateProgress {
+                    output_path: output_path.clone(),
+                    percent,
+                },
+            );
+        }
+    };
+    progress(0);
+
+    let mut tmp = dest.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+    #[cfg(desktop)]
+    let rendered = {
+        let binary = wkhtmltopdf::find()?;
+        let dir = app
+            .path()
+            .app_cache_dir()?
+            .join("pdf")
+            .join(uuid::Uuid::new_v4().to_string());
+        let tmp = tmp.clone();
+        let progress = progress.clone();
+        tauri::async_runtime::spawn_blocking(move || {
+            std::fs::create_dir_all(&dir)?;
+            let rendered = wkhtmltopdf::render(&binary, &html, &options, &dir, &tmp, progress);
+            let _ = std::fs::remove_dir_all(&dir);
+            rendered
+        })
+        .await
+        .map_err(|e| PdfError::Failed(e.to_string()))?
+    };
+    #[cfg(mobile)]
+    let rendered = print::render(&app, &html, &options, &tmp, progress.clone()).await;
+    if let Err(e) = rendered {
+        let _ = tokio::fs::remove_file(&tmp).await;
+        return Err(e);
+    }
+    tokio::fs::rename(&tmp, &dest)
+        .await
+        .map_err(|e| PdfError::OutputWriteFailed(e.to_string()))?;
+
+    progress(100);
+    Ok(dest.display().to_string())
+}
@@ -0,0 +1,70 @@
+use serde::Serialize;
+use tauri::{AppHandle, Runtime};
+use tauri_plugin_deep_link::DeepLinkExt;
+use tauri_plugin_notification::NotificationExt;
+
+/// What the current build and OS can actually do, so the frontend doesn't have to guess
+/// from `navigator.userAgent`.
+#[derive(Debug, Clone, Serialize)]
+pub struct PlatformInfo {
+    pub os: String,
+    pub os_version: String,
+    pub arch: String,
+    pub is_mobile: bool,
+    pub supports_notifications: bool,
+    pub supports_deep_link: bool,
+    pub supports_biometrics: bool,
+}
+
+#[cfg(target_os = "macos")]
+const OS: &str = "macos";
+#[cfg(target_os = "windows")]
+const OS: &str = "windows";
+#[cfg(target_os = "linux")]
+const OS: &str = "linux";
+#[cfg(target_os = "ios")]
+const OS: &str = "ios";
+#[cfg(target_os = "android")]
+const OS: &str = "android";
+#[cfg(not(any(
+    target_os = "macos",
+    target_os = "windows",
+    target_os = "linux",
+    target_os = "ios",
+    target_os = "android"
+)))]
+const OS: &str = std::env::consts::OS;
+
+/// The notification plugin errors when the platform has no notification service (e.g. a
+/// Linux session without a D-Bus notification daemon); a denied permission still counts.
+fn probe_notifications<R: Runtime>(app: &AppHandle<R>) -> bool {
+    app.notification().permission_state().is_ok()
+}
+
+/// Windows and Linux can check the live scheme registration. macOS and mobile register
+/// through the bundle and report `UnsupportedPlatform`, so fall back to whether the
+/// plugin can read launch URLs at all.
+fn probe_deep_link<R: Runtime>(app: &AppHandle<R>) -> bool {
+    let deep_link = app.deep_link();
+    deep_link
+        .is_registered("layers")
+        .unwrap_or_else(|_| deep_link.get_current().is_ok())
+}
+
+/// No biometric plugin is installed; only the mobile OS prompts are reachable.
+fn probe_biometrics() -> bool {
+    cfg!(mobile)
+}
+
+#[tauri::command]
+pub fn get_platform_info<R: Runtime>(app: AppHandle<R>) -> PlatformInfo {
+    PlatformInfo {
+        os: OS.to_string(),
+        os_version: os_info::get().version().to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        is_mobile: cfg!(mobile),
+        supports_notifications: probe_notifications(&app),
+        supports_deep_link: probe_deep_link(&app),
+        supports_biometrics: probe_biometrics(),
+    }
+}
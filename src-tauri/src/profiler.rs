@@ -0,0 +1,166 @@
+//! On-demand CPU and memory sampling of the Rust process, kept in memory and handed back
+//! as one report when profiling stops. Unlike [`crate::metrics`] it only looks at the
+//! main process, which makes each sample cheap enough for short intervals, and nothing
+//! is written to the database.
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, System};
+use tauri::{AppHandle, Manager, Runtime, State};
+use tokio::sync::oneshot;
+
+const MIN_INTERVAL_MS: u64 = 10;
+
+#[derive(Debug, thiserror::Error, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "camelCase")]
+pub enum ProfilerError {
+    #[error("profiling is already running")]
+    AlreadyRunning,
+    #[error("profiling is not running")]
+    NotRunning,
+    #[error("invalid input: {0}")]
+    InvalidInput(String),
+    #[error("process metrics unavailable: {0}")]
+    Unavailable(String),
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileSample {
+    pub timestamp_ms: u64,
+    /// As a share of all cores, as in [`crate::metrics`]. The first sample reads 0.
+    pub cpu_percent: f32,
+    pub rss_bytes: u64,
+    pub virtual_bytes: u64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfilerReport {
+    pub sample_interval_ms: u64,
+    pub samples: Vec<ProfileSample>,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MemorySnapshot {
+    pub rss_bytes: u64,
+    pub virtual_bytes: u64,
+}
+
+struct Session {
+    interval_ms: u64,
+    samples: Arc<Mutex<Vec<ProfileSample>>>,
+    stop: oneshot::Sender<()>,
+}
+
+#[derive(Default)]
+pub struct Profiler {
+    session: Mutex<Option<Session>>,
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
+
+/// Refreshes only our own process; CPU usage is measured since the previous refresh of
+/// the same `system`.
+fn sample(system: &mut System, pid: Pid) -> Option<ProfileSample> {
+    let kind = ProcessRefreshKind::nothing().with_memory().with_cpu();
+    system.refresh_processes_specifics(ProcessesToUpdate::Some(&[pid]), true, kind);
+    let process = system.process(pid)?;
+    let cores = std::thread::available_parallelism().map_or(1, |n| n.get());
+    Some(ProfileSample {
+        timestamp_ms: now_ms(),
+        cpu_percent: process.cpu_usage() / cores as f32,
+        rss_bytes: process.memory(),
+        virtual_bytes: process.virtual_memory(),
+    })
+}
+
+/// Samples every `sample_interval_ms` until [`stop_profiling`].
+#[tauri::command]
+pub fn start_profiling<R: Runtime>(
+    sample_interval_ms: u64,
+    app: AppHandle<R>,
+) -> Result<(), ProfilerError> {
+    if sample_interval_ms < MIN_INTERVAL_MS {
+        return Err(ProfilerError::InvalidInput(format!(
+            "interval must be at least {MIN_INTERVAL_MS} ms"
+        )));
+    }
+    let pid = sysinfo::get_current_pid().map_err(|e| ProfilerError::Unavailable(e.into()))?;
+    let profiler = app.state::<Profiler>();
+    let mut session = profiler.session.lock().unwrap();
+    if session.is_some() {
+        return Err(ProfilerError::AlreadyRunning);
+    }
+    let samples = Arc::new(Mutex::new(Vec::new()));
+    let (stop, mut stopped) = oneshot::channel();
+    *session = Some(Session {
+        interval_ms: sample_interval_ms,
+        samples: samples.clone(),
+        stop,
+    });
+
+    tauri::async_runtime::spawn(async move {
+        let mut system = System::new();
+        let mut interval = tokio::time::interval(Duration::from_millis(sample_interval_ms));
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = &mut stopped => break,
+            }
+            match sample(&mut system, pid) {
+                Some(sample) => samples.lock().unwrap().push(sample),
+                None => tracing::warn!("profiler sample failed: own process not listed"),
+            }
+        }
+    });
+    tracing::info!(sample_interval_ms, "profiling started");
+    Ok(())
+}
+
+/// Stops sampling and returns everything collected since [`start_profiling`].
+#[tauri::command]
+pub fn stop_profiling(profiler: State<'_, Profiler>) -> Result<ProfilerReport, ProfilerError> {
+    let session = profiler
+        .session
+        .lock()
+        .unwrap()
+        .take()
+        .ok_or(ProfilerError::NotRunning)?;
+    let _ = session.stop.send(());
+    let samples = std::mem::take(&mut *session.samples.lock().unwrap());
+    tracing::info!(samples = samples.len(), "profiling stopped");
+    Ok(ProfilerReport {
+        sample_interval_ms: session.interval_ms,
+        samples,
+    })
+}
+
+/// Zeroes if the process can't be read, which sysinfo only reports on unusual sandboxes.
+#[tauri::command]
+pub fn get_current_memory() -> MemorySnapshot {
+    let Ok(pid) = sysinfo::get_current_pid() else {
+        return MemorySnapshot::default();
+    };
+    let mut system = System::new();
+    system.refresh_processes_specifics(
+        ProcessesToUpdate::Some(&[pid]),
+        true,
+        ProcessRefreshKind::nothing().with_memory(),
+    );
+    system
+        .process(pid)
+        .map(|process| MemorySnapshot {
+            rss_bytes: process.memory(),
+            virtual_bytes: process.virtual_memory(),
+        })
+        .unwrap_or_default()
+}
@@ -0,0 +1,210 @@
+//! Remote push through APNs on iOS and FCM on Android.
+//!
+//! The native half comes from `tauri-plugin-push-notifications`, but it is registered
+//! here instead of through that crate's `init`, so Rust holds the plugin handle. The
+//! native plugin only reports pushes to listener channels; registering those from Rust
+//! lets pushes become app events without the webview subscribing first.
+//!
+//! Android additionally needs Firebase configured in the generated project
+//! (`google-services.json`), and iOS the Push Notifications capability.
+
+use std::sync::Mutex;
+#[cfg(mobile)]
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use serde_json::Value;
+use tauri::{AppHandle, Runtime, State};
+
+#[cfg_attr(desktop, allow(dead_code))]
+pub const RECEIVED_EVENT: &str = "push://received";
+#[cfg_attr(desktop, allow(dead_code))]
+pub const LAUNCH_EVENT: &str = "push://launch";
+#[cfg_attr(desktop, allow(dead_code))]
+pub const TAPPED_EVENT: &str = "push://tapped";
+
+/// How long after startup a tap still counts as the one that launched the app. iOS
+/// replays that tap to the notification delegate shortly after launch, not before the
+/// listeners are armed.
+#[cfg(mobile)]
+const LAUNCH_WINDOW: Duration = Duration::from_secs(5);
+
+#[cfg_attr(desktop, allow(dead_code))]
+#[derive(Debug, thiserror::Error, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "camelCase")]
+pub enum PushError {
+    #[error("push notifications are only available on iOS and Android")]
+    NotSupported,
+    #[error("notification permission was denied")]
+    PermissionDenied,
+    #[error("push registration failed: {0}")]
+    RegistrationFailed(String),
+}
+
+/// The notification whose tap launched the app, kept until the frontend asks for it:
+/// [`LAUNCH_EVENT`] is emitted before the webview is likely to be listening.
+#[derive(Default)]
+pub struct LaunchNotification(Mutex<Option<Value>>);
+
+#[cfg(mobile)]
+fn deliver<R: Runtime>(app: &AppHandle<R>, tapped: bool, payload: Value, started: Instant) {
+    use tauri::{Emitter, Manager};
+
+    if !tapped {
+        let _ = app.emit(RECEIVED_EVENT, payload);
+        return;
+    }
+    let launch = app.state::<LaunchNotification>();
+    let is_launch = started.elapsed() < LAUNCH_WINDOW && {
+        let mut slot = launch.0.lock().unwrap();
+        let first = slot.is_none();
+        if first {
+            *slot = Some(payload.clone());
+        }
+        first
+    };
+    let event = if is_launch {
+        LAUNCH_EVENT
+    } else {
+        TAPPED_EVENT
+    };
+    let _ = app.emit(event, payload);
+}
+
+#[cfg(mobile)]
+mod native {
+    use std::time::Instant;
+
+    use serde::Deserialize;
+    use serde_json::{json, Value};
+    use tauri::ipc::{Channel, InvokeResponseBody};
+    use tauri::plugin::mobile::PluginInvokeError;
+    use tauri::plugin::{PluginHandle, TauriPlugin};
+    use tauri::{AppHandle, Manager, Runtime};
+
+    use super::PushError;
+
+    #[cfg(target_os = "ios")]
+    tauri::ios_plugin_binding!(init_plugin_push_notifications);
+
+    struct Push<R: Runtime>(PluginHandle<R>);
+
+    #[derive(Deserialize)]
+    struct Permission {
+        granted: bool,
+    }
+
+    #[derive(Deserialize)]
+    struct Registration {
+        token: String,
+    }
+
+    pub fn plugin<R: Runtime>() -> TauriPlugin<R> {
+        tauri::plugin::Builder::new("push")
+            .setup(|app, api| {
+                #[cfg(target_os = "android")]
+                let handle = api.register_android_plugin(
+                    "app.tauri.pushnotifications",
+                    "PushNotificationsPlugin",
+                )?;
+                #[cfg(target_os = "ios")]
+                let handle = api.register_ios_plugin(init_plugin_push_notifications)?;
+                app.manage(Push(handle.clone()));
+
+                let app = app.clone();
+                tauri::async_runtime::spawn(async move {
+                    if let Err(e) = listen(&app, &handle).await {
+                        tracing::warn!(error = %e, "failed to listen for push notifications");
+                    }
+                });
+                Ok(())
+            })
+            .build()
+    }
+
+    /// Registers a channel per native event, then arms delivery, which replays anything
+    /// that arrived first, including a tap that launched the app.
+    async fn listen<R: Runtime>(
+        app: &AppHandle<R>,
+        handle: &PluginHandle<R>,
+    ) -> Result<(), PluginInvokeError> {
+        let started = Instant::now();
+        for (event, tapped) in [
+            ("notificationReceived", false),
+            ("notificationTapped", true),
+        ] {
+            let app = app.clone();
+            let channel = Channel::<Value>::new(move |body| {
+                if let InvokeResponseBody::Json(json) = body {
+                    if let Ok(payload) = serde_json::from_str(&json) {
+                        super::deliver(&app, tapped, payload, started);
+                    }
+                }
+                Ok(())
+            });
+            handle
+                .run_mobile_plugin_async::<Value>(
+                    "registerListener",
+                    json!({ "event": event, "handler": channel }),
+                )
+                .await?;
+        }
+        handle
+            .run_mobile_plugin_async::<Value>("startNotificationEvents", ())
+            .await
+            .map(|_| ())
+    }
+
+    fn failed(e: PluginInvokeError) -> PushError {
+        PushError::RegistrationFailed(e.to_string())
+    }
+
+    pub async fn register<R: Runtime>(app: &AppHandle<R>) -> Result<String, PushError> {
+        let push = app
+            .try_state::<Push<R>>()
+            .ok_or_else(|| PushError::RegistrationFailed("push plugin not loaded".into()))?;
+        let permission: Permission = push
+            .0
+            .run_mobile_plugin_async("requestPermission", ())
+            .await
+            .map_err(failed)?;
+        if !permission.granted {
+            return Err(PushError::PermissionDenied);
+        }
+        push.0
+            .run_mobile_plugin_async::<Registration>("registerForPush", ())
+            .await
+            .map(|registration| registration.token)
+            .map_err(failed)
+    }
+}
+
+#[cfg(desktop)]
+mod native {
+    use tauri::{AppHandle, Runtime};
+
+    use super::PushError;
+
+    pub async fn register<R: Runtime>(_app: &AppHandle<R>) -> Result<String, PushError> {
+        Err(PushError::NotSupported)
+    }
+}
+
+#[cfg(mobile)]
+pub fn plugin<R: Runtime>() -> tauri::plugin::TauriPlugin<R> {
+    native::plugin()
+}
+
+/// Asks for notification permission if needed and returns the device token: the hex
+/// APNs token on iOS, the FCM registration token on Android. Tokens rotate, so call this
+/// on every launch and send the result to the server.
+#[tauri::command]
+pub async fn register_for_push<R: Runtime>(app: AppHandle<R>) -> Result<String, PushError> {
+    native::register(&app).await
+}
+
+/// Returns the payload of the notification that launched the app, once.
+#[tauri::command]
+pub fn take_launch_notification(launch: State<'_, LaunchNotification>) -> Option<Value> {
+    launch.0.lock().unwrap().take()
+}
@@ -0,0 +1,251 @@
+//! Home screen quick actions, the menu shown when the app icon is long-pressed: dynamic
+//! shortcuts through `ShortcutManager` on Android, `UIApplicationShortcutItem` on iOS.
+//! The native halves live in `plugins/quick-actions`. Desktop has no equivalent, so the
+//! module is mobile-only; `shortcuts` covers desktop's global shortcuts.
+//!
+//! Opening the app from an action emits [`LAUNCH_EVENT`] with its ID. The action that
+//! started the app is also kept for [`take_launch_quick_action`], since the webview is
+//! unlikely to be listening yet.
+
+use std::collections::HashSet;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::ipc::{Channel, InvokeResponseBody};
+use tauri::plugin::mobile::PluginInvokeError;
+use tauri::plugin::{PluginHandle, TauriPlugin};
+use tauri::{AppHandle, Emitter, Manager, Runtime, State};
+// Linked for its native halves; it has no Rust API.
+use tauri_plugin_quick_actions as _;
+
+pub const LAUNCH_EVENT: &str = "shortcuts://launch";
+
+/// iOS shows no more than four; Android launchers vary, but four is the common limit.
+pub const MAX_QUICK_ACTIONS: usize = 4;
+
+/// How long after startup an action still counts as the one that launched the app, as
+/// for push taps in [`crate::push`].
+const LAUNCH_WINDOW: Duration = Duration::from_secs(5);
+
+#[cfg(target_os = "ios")]
+tauri::ios_plugin_binding!(init_plugin_quick_actions);
+
+#[derive(Debug, thiserror::Error, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "camelCase")]
+pub enum QuickActionError {
+    #[error("at most {MAX_QUICK_ACTIONS} quick actions fit, got {0}")]
+    TooMany(usize),
+    #[error("invalid quick action: {0}")]
+    InvalidInput(String),
+    #[error("{0}")]
+    InvalidIcon(String),
+    #[error("updating quick actions failed: {0}")]
+    Failed(String),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum QuickActionIcon {
+    Compose,
+    Search,
+    Share,
+    Add,
+    Favorite,
+    Time,
+    Mail,
+    Location,
+    /// An image file on Android. On iOS, the name of an image in the app bundle, since
+    /// shortcut icons can't come from anywhere else.
+    Custom(String),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuickAction {
+    /// Reported back in [`LAUNCH_EVENT`].
+    pub id: String,
+    pub title: String,
+    /// Shown under the title on iOS; Android has nowhere to put it.
+    pub subtitle: Option<String>,
+    pub icon: QuickActionIcon,
+}
+
+/// The action that launched the app, kept until the frontend asks for it.
+#[derive(Default)]
+pub struct LaunchQuickAction(Mutex<Option<String>>);
+
+struct QuickActions<R: Runtime>(PluginHandle<R>);
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct NativeAction<'a> {
+    id: &'a str,
+    title: &'a str,
+    subtitle: Option<&'a str>,
+    icon: Option<&'static str>,
+    icon_path: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+struct SetArgs<'a> {
+    actions: Vec<NativeAction<'a>>,
+}
+
+#[derive(Serialize)]
+struct WatchArgs {
+    channel: Channel,
+}
+
+#[derive(Deserialize)]
+struct Launch {
+    id: String,
+}
+
+impl<'a> From<&'a QuickAction> for NativeAction<'a> {
+    fn from(action: &'a QuickAction) -> Self {
+        let (icon, icon_path) = match &action.icon {
+            QuickActionIcon::Compose => (Some("compose"), None),
+            QuickActionIcon::Search => (Some("search"), None),
+            QuickActionIcon::Share => (Some("share"), None),
+            QuickActionIcon::Add => (Some("add"), None),
+            QuickActionIcon::Favorite => (Some("favorite"), None),
+            QuickActionIcon::Time => (Some("time"), None),
+            QuickActionIcon::Mail => (Some("mail"), None),
+            QuickActionIcon::Location => (Some("location"), None),
+            QuickActionIcon::Custom(path) => (None, Some(path.as_str())),
+        };
+        Self {
+            id: &action.id,
+            title: &action.title,
+            subtitle: action.subtitle.as_deref(),
+            icon,
+            icon_path,
+        }
+    }
+}
+
+pub fn plugin<R: Runtime>() -> TauriPlugin<R> {
+    tauri::plugin::Builder::new("quick-actions")
+        .setup(|app, api| {
+            #[cfg(target_os = "android")]
+            let handle =
+                api.register_android_plugin("com.layers.quickactions", "QuickActionsPlugin")?;
+            #[cfg(target_os = "ios")]
+            let handle = api.register_ios_plugin(init_plugin_quick_actions)?;
+            app.manage(QuickActions(handle.clone()));
+            app.manage(LaunchQuickAction::default());
+
+            let app = app.clone();
+            tauri::async_runtime::spawn(async move {
+                if let Err(e) = listen(&app, &handle).await {
+                    tracing::warn!(error = %e, "failed to listen for quick actions");
+                }
+            });
+            Ok(())
+        })
+        .build()
+}
+
+/// Arms delivery, which replays launches that arrived first, including the one that
+/// started the app.
+async fn listen<R: Runtime>(
+    app: &AppHandle<R>,
+    handle: &PluginHandle<R>,
+) -> Result<(), PluginInvokeError> {
+    let started = Instant::now();
+    let emitter = app.clone();
+    let channel = Channel::new(move |body| {
+        if let InvokeResponseBody::Json(json) = body {
+            match serde_json::from_str::<Launch>(&json) {
+                Ok(launch) => deliver(&emitter, launch.id, started),
+                Err(e) => tracing::warn!(error = %e, "malformed quick action launch"),
+            }
+        }
+        Ok(())
+    });
+    handle
+        .run_mobile_plugin_async::<Value>("watchLaunches", WatchArgs { channel })
+        .await
+        .map(|_| ())
+}
+
+fn deliver<R: Runtime>(app: &AppHandle<R>, id: String, started: Instant) {
+    if started.elapsed() < LAUNCH_WINDOW {
+        let launch = app.state::<LaunchQuickAction>();
+        let mut slot = launch.0.lock().unwrap();
+        if slot.is_none() {
+            *slot = Some(id.clone());
+        }
+    }
+    let _ = app.emit(LAUNCH_EVENT, id);
+}
+
+fn from_plugin(e: PluginInvokeError) -> QuickActionError {
+    match e {
+        PluginInvokeError::InvokeRejected(response) => {
+            let message = response.message.unwrap_or_default();
+            match response.code.as_deref() {
+                Some("InvalidIcon") => QuickActionError::InvalidIcon(message),
+                Some("InvalidInput") => QuickActionError::InvalidInput(message),
+                _ => QuickActionError::Failed(message),
+            }
+        }
+        e => QuickActionError::Failed(e.to_string()),
+    }
+}
+
+fn validate(actions: &[QuickAction]) -> Result<(), QuickActionError> {
+    if actions.len() > MAX_QUICK_ACTIONS {
+        return Err(QuickActionError::TooMany(actions.len()));
+    }
+    let mut ids = HashSet::new();
+    for action in actions {
+        if action.id.is_empty() || action.title.trim().is_empty() {
+            return Err(QuickActionError::InvalidInput(
+                "an action needs an id and a title".into(),
+            ));
+        }
+        if !ids.insert(action.id.as_str()) {
+            return Err(QuickActionError::InvalidInput(format!(
+                "`{}` is used twice",
+                action.id
+            )));
+        }
+        if matches!(&action.icon, QuickActionIcon::Custom(path) if path.is_empty()) {
+            return Err(QuickActionError::InvalidIcon(format!(
+                "`{}` has an empty custom icon",
+                action.id
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Replaces the app's quick actions; an empty list removes them all.
+#[tauri::command]
+pub async fn set_quick_actions<R: Runtime>(
+    actions: Vec<QuickAction>,
+    app: AppHandle<R>,
+) -> Result<(), QuickActionError> {
+    validate(&actions)?;
+    let handle = app
+        .try_state::<QuickActions<R>>()
+        .map(|quick_actions| quick_actions.0.clone())
+        .ok_or_else(|| QuickActionError::Failed("quick actions plugin not loaded".into()))?;
+    let args = SetArgs {
+        actions: actions.iter().map(NativeAction::from).collect(),
+    };
+    handle
+        .run_mobile_plugin_async::<Value>("setQuickActions", args)
+        .await
+        .map_err(from_plugin)?;
+    Ok(())
+}
+
+/// Returns the ID of the quick action that launched the app, once.
+#[tauri::command]
+pub fn take_launch_quick_action(launch: State<'_, LaunchQuickAction>) -> Option<String> {
+    launch.0.lock().unwrap().take()
+}
@@ -0,0 +1,189 @@
+//! Per-command rate limits, checked before a command runs so a page stuck in a loop, or
+//! one that means harm, can't keep an expensive command busy. Commands have no limit
+//! until the app config or [`configure_rate_limit`], from the main window, gives them one.
+//!
+//! Each limit is a token bucket holding `max_calls` tokens and refilling all of them
+//! over `window_seconds`, so bursts up to the limit pass and sustained rates above it
+//! don't. A throttled call is rejected with [`RateLimitError::Throttled`].
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tauri::ipc::Invoke;
+use tauri::{Manager, Runtime, State, Webview};
+
+/// The app's own window; the only one trusted to change limits.
+const MAIN_WINDOW: &str = "main";
+
+#[derive(Debug, thiserror::Error, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "camelCase")]
+pub enum RateLimitError {
+    #[error("too many calls; retry in {retry_after_ms} ms")]
+    #[serde(rename_all = "camelCase")]
+    Throttled { retry_after_ms: u64 },
+    #[error("invalid rate limit: {0}")]
+    InvalidInput(String),
+    #[error("only the main window may change rate limits, not {0}")]
+    NotAllowed(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RateLimitConfig {
+    pub max_calls: u32,
+    pub window_seconds: u64,
+}
+
+impl RateLimitConfig {
+    pub fn validate(&self) -> Result<(), RateLimitError> {
+        if self.max_calls == 0 || self.window_seconds == 0 {
+            return Err(RateLimitError::InvalidInput(
+                "max_calls and window_seconds must be at least 1".into(),
+            ));
+        }
+        Ok(())
+    }
+
+    fn bucket(&self) -> TokenBucket {
+        let capacity = f64::from(self.max_calls);
+        TokenBucket::new(
+            capacity / Duration::from_secs(self.window_seconds).as_secs_f64(),
+            capacity,
+        )
+    }
+}
+
+/// A bucket `capacity` tokens deep, refilled at `rate` tokens a second. Shared with
+/// [`crate::net`]'s per-host limits.
+#[derive(Debug, Clone)]
+pub struct TokenBucket {
+    capacity: f64,
+    tokens: f64,
+    rate: f64,
+    refilled_at: Instant,
+}
+
+impl TokenBucket {
+    /// Starts full.
+    pub fn new(rate: f64, capacity: f64) -> Self {
+        Self {
+            capacity,
+            tokens: capacity,
+            rate,
+            refilled_at: Instant::now(),
+        }
+    }
+
+    /// Changes the limit, keeping the tokens already in the bucket up to the new capacity.
+    pub fn set_limit(&mut self, rate: f64, capacity: f64, now: Instant) {
+        self.refill(now);
+        self.rate = rate;
+        self.capacity = capacity;
+        self.tokens = self.tokens.min(capacity);
+    }
+
+    pub fn drain(&mut self) {
+        self.tokens = 0.0;
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now
+            .saturating_duration_since(self.refilled_at)
+            .as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.capacity);
+        self.refilled_at = now;
+    }
+
+    /// Takes a token, or says how long until one is back.
+    pub fn try_take(&mut self, now: Instant) -> Result<(), Duration> {
+        self.refill(now);
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - self.tokens) / self.rate))
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct RateLimiter {
+    buckets: Mutex<HashMap<String, TokenBucket>>,
+}
+
+impl RateLimiter {
+    pub(crate) fn check(&self, command: &str) -> Result<(), RateLimitError> {
+        let mut buckets = self.buckets.lock().unwrap();
+        let Some(bucket) = buckets.get_mut(command) else {
+            return Ok(());
+        };
+        bucket
+            .try_take(Instant::now())
+            .map_err(|wait| RateLimitError::Throttled {
+                // Rounded up, so retrying on time never lands just short of a token.
+                retry_after_ms: wait.as_micros().div_ceil(1000) as u64,
+            })
+    }
+
+    /// Replaces every limit, including ones [`configure_rate_limit`] set, each starting
+    /// with a full bucket.
+    pub fn replace_all(&self, limits: &BTreeMap<String, RateLimitConfig>) {
+        *self.buckets.lock().unwrap() = limits
+            .iter()
+            .map(|(command, limit)| (command.clone(), limit.bucket()))
+            .collect();
+    }
+}
+
+/// Wraps the app's generated invoke handler so limited commands are checked before they
+/// run. Goes inside [`crate::audit::wrap`], so throttled calls still show in the log.
+pub fn wrap<R: Runtime>(
+    handler: impl Fn(Invoke<R>) -> bool + Send + Sync + 'static,
+) -> impl Fn(Invoke<R>) -> bool + Send + Sync + 'static {
+    move |invoke| {
+        let webview = invoke.message.webview();
+        let Some(limiter) = webview.try_state::<RateLimiter>() else {
+            return handler(invoke);
+        };
+        match limiter.check(invoke.message.command()) {
+            Ok(()) => handler(invoke),
+            Err(e) => {
+                tracing::debug!(command = invoke.message.command(), "command throttled");
+                invoke.resolver.reject(e);
+                true
+            }
+        }
+    }
+}
+
+/// Allows `command` at most `max_calls` times per `window_seconds`, replacing any
+/// earlier limit and starting with a full bucket. Only the main window may ask; a page
+/// in any other window could otherwise lift the limit meant to hold it back.
+#[tauri::command]
+pub fn configure_rate_limit<R: Runtime>(
+    command: String,
+    max_calls: u32,
+    window_seconds: u64,
+    webview: Webview<R>,
+    limiter: State<'_, RateLimiter>,
+) -> Result<(), RateLimitError> {
+    if webview.label() != MAIN_WINDOW {
+        return Err(RateLimitError::NotAllowed(webview.label().to_string()));
+    }
+    if command.is_empty() {
+        return Err(RateLimitError::InvalidInput("no command given".into()));
+    }
+    let limit = RateLimitConfig {
+        max_calls,
+        window_seconds,
+    };
+    limit.validate()?;
+    limiter
+        .buckets
+        .lock()
+        .unwrap()
+        .insert(command, limit.bucket());
+    Ok(())
+}
@@ -0,0 +1,251 @@
+//! Barcode scanning with the device camera: Vision on iOS and ZXing on Android, both in
+//! `plugins/scanner`. Desktop has no camera scanner, so there the user picks an image
+//! and `rxing` decodes it.
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Runtime};
+
+#[cfg_attr(desktop, allow(dead_code))]
+#[derive(Debug, thiserror::Error, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "camelCase")]
+pub enum ScanError {
+    #[error("camera permission denied")]
+    PermissionDenied,
+    #[error("scan cancelled")]
+    Cancelled,
+    #[error("scanning is not supported here: {0}")]
+    Unsupported(String),
+    #[error("scan failed: {0}")]
+    Failed(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum BarcodeFormat {
+    QrCode,
+    Ean13,
+    Ean8,
+    Code128,
+    DataMatrix,
+    Pdf417,
+}
+
+/// In pixels of the scanned frame, from its top-left corner.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Rect {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScanResult {
+    pub raw_value: String,
+    pub format: BarcodeFormat,
+    /// Android's scanner doesn't report where the code was found.
+    pub bounding_box: Option<Rect>,
+}
+
+#[cfg(mobile)]
+mod native {
+    use serde::Serialize;
+    use tauri::plugin::mobile::PluginInvokeError;
+    use tauri::plugin::{PluginHandle, TauriPlugin};
+    use tauri::{AppHandle, Manager, Runtime};
+    // Linked for its native halves; it has no Rust API.
+    use tauri_plugin_scanner as _;
+
+    use super::{BarcodeFormat, ScanError, ScanResult};
+
+    #[cfg(target_os = "ios")]
+    tauri::ios_plugin_binding!(init_plugin_scanner);
+
+    struct Scanner<R: Runtime>(PluginHandle<R>);
+
+    #[derive(Serialize)]
+    struct ScanArgs {
+        formats: Vec<BarcodeFormat>,
+    }
+
+    pub fn plugin<R: Runtime>() -> TauriPlugin<R> {
+        tauri::plugin::Builder::new("scanner")
+            .setup(|app, api| {
+                #[cfg(target_os = "android")]
+                let handle = api.register_android_plugin("com.layers.scanner", "ScannerPlugin")?;
+                #[cfg(target_os = "ios")]
+                let handle = api.register_ios_plugin(init_plugin_scanner)?;
+                app.manage(Scanner(handle));
+                Ok(())
+            })
+            .build()
+    }
+
+    /// Both native halves reject with the same codes.
+    fn from_plugin(e: PluginInvokeError) -> ScanError {
+        match e {
+            PluginInvokeError::InvokeRejected(response) => match response.code.as_deref() {
+                Some("PermissionDenied") => ScanError::PermissionDenied,
+                Some("Cancelled") => ScanError::Cancelled,
+                _ => ScanError::Failed(response.message.unwrap_or_default()),
+            },
+            e => ScanError::Failed(e.to_string()),
+        }
+    }
+
+    pub async fn scan<R: Runtime>(
+        app: &AppHandle<R>,
+        formats: Vec<BarcodeFormat>,
+    ) -> Result<ScanResult, ScanError> {
+        let handle = app
+            .try_state::<Scanner<R>>()
+            .map(|scanner| scanner.0.clone())
+            .ok_or_else(|| ScanError::Failed("scanner plugin not loaded".into()))?;
+        handle
+            .run_mobile_plugin_async("scan", ScanArgs { formats })
+            .await
+            .map_err(from_plugin)
+    }
+}
+
+#[cfg(desktop)]
+mod native {
+    use std::collections::HashSet;
+    use std::path::{Path, PathBuf};
+
+    use image::ImageReader;
+    use rxing::{DecodeHints, Exceptions};
+    use tauri::{AppHandle, Runtime};
+    use tauri_plugin_dialog::DialogExt;
+
+    use super::{BarcodeFormat, Rect, ScanError, ScanResult};
+
+    fn to_rxing(format: BarcodeFormat) -> rxing::BarcodeFormat {
+        match format {
+            BarcodeFormat::QrCode => rxing::BarcodeFormat::QR_CODE,
+            BarcodeFormat::Ean13 => rxing::BarcodeFormat::EAN_13,
+            BarcodeFormat::Ean8 => rxing::BarcodeFormat::EAN_8,
+            BarcodeFormat::Code128 => rxing::BarcodeFormat::CODE_128,
+            BarcodeFormat::DataMatrix => rxing::BarcodeFormat::DATA_MATRIX,
+            BarcodeFormat::Pdf417 => rxing::BarcodeFormat::PDF_417,
+        }
+    }
+
+    fn from_rxing(format: &rxing::BarcodeFormat) -> Option<BarcodeFormat> {
+        Some(match format {
+            rxing::BarcodeFormat::QR_CODE => BarcodeFormat::QrCode,
+            rxing::BarcodeFormat::EAN_13 => BarcodeFormat::Ean13,
+            rxing::BarcodeFormat::EAN_8 => BarcodeFormat::Ean8,
+            rxing::BarcodeFormat::CODE_128 => BarcodeFormat::Code128,
+            rxing::BarcodeFormat::DATA_MATRIX => BarcodeFormat::DataMatrix,
+            rxing::BarcodeFormat::PDF_417 => BarcodeFormat::Pdf417,
+            _ => return None,
+        })
+    }
+
+    async fn pick<R: Runtime>(app: &AppHandle<R>) -> Result<PathBuf, ScanError> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        app.dialog()
+            .file()
+            .add_filter("Images", &["png", "jpg", "jpeg", "gif", "bmp", "webp"])
+            .pick_file(move |path| {
+                let _ = tx.send(path);
+            });
+        rx.await
+            .ok()
+            .flatten()
+            .ok_or(ScanError::Cancelled)?
+            .into_path()
+            .map_err(|e| ScanError::Failed(e.to_string()))
+    }
+
+    /// Only the formats asked for are tried, which is faster as well as stricter.
+    fn decode(path: &Path, formats: &[BarcodeFormat]) -> Result<ScanResult, ScanError> {
+        let image = ImageReader::open(path)
+            .and_then(ImageReader::with_guessed_format)
+            .map_err(|e| ScanError::Failed(e.to_string()))?
+            .decode()
+            .map_err(|e| ScanError::Failed(format!("not a readable image: {e}")))?
+            .into_luma8();
+        let (width, height) = image.dimensions();
+        let wanted = if formats.is_empty() {
+            &[
+                BarcodeFormat::QrCode,
+                BarcodeFormat::Ean13,
+                BarcodeFormat::Ean8,
+                BarcodeFormat::Code128,
+                BarcodeFormat::DataMatrix,
+                BarcodeFormat::Pdf417,
+            ][..]
+        } else {
+            formats
+        };
+        let mut hints = DecodeHints {
+            PossibleFormats: Some(wanted.iter().copied().map(to_rxing).collect::<HashSet<_>>()),
+            ..DecodeHints::default()
+        };
+        let result = rxing::helpers::detect_in_luma_with_hints(
+            image.into_raw(),
+            width,
+            height,
+            None,
+            &mut hints,
+        )
+        .map_err(|e| match e {
+            Exceptions::NotFoundException(_) => {
+                ScanError::Failed("no barcode found in the image".into())
+            }
+            e => ScanError::Failed(e.to_string()),
+        })?;
+        let format = from_rxing(result.getBarcodeFormat())
+            .ok_or_else(|| ScanError::Failed("no barcode found in the image".into()))?;
+        let points = result.getPoints();
+        let bounding_box = (!points.is_empty()).then(|| {
+            let (mut min, mut max) = ((f32::MAX, f32::MAX), (f32::MIN, f32::MIN));
+            for point in points {
+                min = (min.0.min(point.x), min.1.min(point.y));
+                max = (max.0.max(point.x), max.1.max(point.y));
+            }
+            Rect {
+                x: min.0.into(),
+                y: min.1.into(),
+                width: (max.0 - min.0).into(),
+                height: (max.1 - min.1).into(),
+            }
+        });
+        Ok(ScanResult {
+            raw_value: result.getText().to_owned(),
+            format,
+            bounding_box,
+        })
+    }
+
+    pub async fn scan<R: Runtime>(
+        app: &AppHandle<R>,
+        formats: Vec<BarcodeFormat>,
+    ) -> Result<ScanResult, ScanError> {
+        let path = pick(app).await?;
+        tauri::async_runtime::spawn_blocking(move || decode(&path, &formats))
+            .await
+            .map_err(|e| ScanError::Failed(e.to_string()))?
+    }
+}
+
+#[cfg(mobile)]
+pub fn plugin<R: Runtime>() -> tauri::plugin::TauriPlugin<R> {
+    native::plugin()
+}
+
+/// Opens the camera and resolves with the first code in one of `formats`, or any of
+/// them if empty. Camera permission is requested first; refusing it fails with
+/// [`ScanError::PermissionDenied`] instead of showing the scanner. On desktop the code is
+/// read from an image the user picks instead, and a 1D barcode, located by a line
+/// across it, gets a box with no height.
+#[tauri::command]
+pub async fn scan_barcode<R: Runtime>(
+    formats: Vec<BarcodeFormat>,
+    app: AppHandle<R>,
+) -> Result<ScanResult, ScanError> {
+    native::scan(&app, formats).await
+}
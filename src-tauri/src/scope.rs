@@ -0,0 +1,51 @@
+use std::path::{Path, PathBuf};
+
+use tauri::{AppHandle, Manager, Runtime};
+use tauri_plugin_fs::FsExt;
+
+/// Checks a path against the same rules the fs plugin enforces, so Rust-side commands
+/// that touch the file system can't be used to sidestep the permission model.
+///
+/// A path is allowed if it is in the fs plugin's runtime scope or inside one of the
+/// app-specific directories that `fs:default` grants.
+pub fn ensure_allowed<R: Runtime>(app: &AppHandle<R>, path: &Path) -> Result<PathBuf, String> {
+    // Resolve `..` and symlinks first; a path that doesn't exist yet is checked via its parent.
+    let resolved = match dunce::canonicalize(path) {
+        Ok(resolved) => resolved,
+        Err(_) => {
+            let parent = path.parent().ok_or("path has no parent directory")?;
+            let name = path.file_name().ok_or("path has no file name")?;
+            dunce::canonicalize(parent)
+                .map_err(|e| format!("{}: {e}", parent.display()))?
+                .join(name)
+        }
+    };
+
+    let scope = app.fs_scope();
+    if scope.is_forbidden(&resolved) {
+        return Err(format!("{} is forbidden by the fs scope", path.display()));
+    }
+    if scope.is_allowed(&resolved) || in_app_dirs(app, &resolved) {
+        return Ok(resolved);
+    }
+
+    Err(format!(
+        "{} is outside the allowed fs scope",
+        path.display()
+    ))
+}
+
+fn in_app_dirs<R: Runtime>(app: &AppHandle<R>, path: &Path) -> bool {
+    let resolver = app.path();
+    [
+        resolver.app_config_dir(),
+        resolver.app_data_dir(),
+        resolver.app_local_data_dir(),
+        resolver.app_cache_dir(),
+        resolver.app_log_dir(),
+    ]
+    .into_iter()
+    .flatten()
+    .filter_map(|dir| dunce::canonicalize(dir).ok())
+    .any(|dir| path.starts_with(dir))
+}
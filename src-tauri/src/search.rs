@@ -0,0 +1,308 @@
+use serde::Serialize;
+use sqlx::SqlitePool;
+use tauri::State;
+
+use crate::db::{Db, DbError};
+
+const MAX_LIMIT: u32 = 100;
+const HIGHLIGHT_START: &str = "<mark>";
+const HIGHLIGHT_END: &str = "</mark>";
+/// Tokens of context `snippet()` keeps around a match; the LIKE path uses characters.
+const SNIPPET_TOKENS: i64 = 12;
+const SNIPPET_CHARS: usize = 60;
+
+/// Which path served a search, so benchmarks can tell FTS5 and the LIKE fallback apart.
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SearchEngine {
+    Fts5,
+    Like,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchHit {
+    pub id: String,
+    pub title: String,
+    /// Raw note text with matches wrapped in `<mark>`; escape the rest before rendering
+    /// it as HTML.
+    pub snippet: String,
+    /// bm25 score, lower is better; `None` from the LIKE fallback, which orders by recency.
+    pub rank: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchResults {
+    pub results: Vec<SearchHit>,
+    pub total: i64,
+    pub engine: SearchEngine,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Term { text: String, prefix: bool },
+    Phrase(String),
+    And,
+    Or,
+}
+
+/// Splits user input into terms, `"quoted phrases"`, and `AND`/`OR`. Everything else
+/// is literal text, so FTS5 syntax characters in the input can't change the query.
+fn tokenize(query: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = query.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '"' {
+            chars.next();
+            let phrase: String = chars.by_ref().take_while(|&c| c != '"').collect();
+            if !phrase.trim().is_empty() {
+                tokens.push(Token::Phrase(phrase.trim().to_string()));
+            }
+        } else {
+            let mut word = String::new();
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() || c == '"' {
+                    break;
+                }
+                word.push(c);
+                chars.next();
+            }
+            tokens.push(match word.as_str() {
+                "AND" => Token::And,
+                "OR" => Token::Or,
+                _ => {
+                    let prefix = word.ends_with('*');
+                    let text = word.trim_end_matches('*').to_string();
+                    if text.is_empty() {
+                        continue;
+                    }
+                    Token::Term { text, prefix }
+                }
+            });
+        }
+    }
+
+    // Operators need a term on both sides; drop leading, trailing, and repeated ones.
+    let mut cleaned: Vec<Token> = Vec::with_capacity(tokens.len());
+    for token in tokens {
+        let is_operator = matches!(token, Token::And | Token::Or);
+        let after_operator = cleaned
+            .last()
+            .is_none_or(|last| matches!(last, Token::And | Token::Or));
+        if is_operator && after_operator {
+            continue;
+        }
+        cleaned.push(token);
+    }
+    if matches!(cleaned.last(), Some(Token::And | Token::Or)) {
+        cleaned.pop();
+    }
+    cleaned
+}
+
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('"', "\"\""))
+}
+
+/// Every term and phrase becomes a quoted FTS5 string; adjacent terms are implicitly
+/// ANDed, as in FTS5 itself.
+fn match_expression(tokens: &[Token]) -> String {
+    tokens
+        .iter()
+        .map(|token| match token {
+            Token::Term { text, prefix: true } => format!("{}*", quote(text)),
+            Token::Term { text, .. } | Token::Phrase(text) => quote(text),
+            Token::And => "AND".into(),
+            Token::Or => "OR".into(),
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+async fn has_fts(pool: &SqlitePool) -> Result<bool, sqlx::Error> {
+    let found: Option<i64> = sqlx::query_scalar(
+        "SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'notes_fts'",
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(found.is_some())
+}
+
+async fn search_fts(
+    pool: &SqlitePool,
+    tokens: &[Token],
+    limit: u32,
+    offset: u32,
+) -> Result<SearchResults, sqlx::Error> {
+    let expression = match_expression(tokens);
+
+    let total: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM notes_fts JOIN notes n ON n.rowid = notes_fts.rowid
+         WHERE notes_fts MATCH ? AND n.is_deleted = 0",
+    )
+    .bind(&expression)
+    .fetch_one(pool)
+    .await?;
+
+    let rows: Vec<(String, String, String, f64)> = sqlx::query_as(
+        "SELECT n.id, n.title, snippet(notes_fts, -1, ?, ?, '…', ?), notes_fts.rank
+         FROM notes_fts JOIN notes n ON n.rowid = notes_fts.rowid
+         WHERE notes_fts MATCH ? AND n.is_deleted = 0
+         ORDER BY notes_fts.rank LIMIT ? OFFSET ?",
+    )
+    .bind(HIGHLIGHT_START)
+    .bind(HIGHLIGHT_END)
+    .bind(SNIPPET_TOKENS)
+    .bind(&expression)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(SearchResults {
+        results: rows
+            .into_iter()
+            .map(|(id, title, snippet, rank)| SearchHit {
+                id,
+                title,
+                snippet,
+                rank: Some(rank),
+            })
+            .collect(),
+        total,
+        engine: SearchEngine::Fts5,
+    })
+}
+
+fn like_pattern(text: &str) -> String {
+    let escaped = text
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("%{escaped}%")
+}
+
+/// Highlights the first match of any term with some context around it, the way
+/// `snippet()` would.
+fn like_snippet(text: &str, terms: &[&str]) -> String {
+    let lower = text.to_lowercase();
+    // Lowercasing can change byte lengths, so only trust offsets when it didn't.
+    let found = (lower.len() == text.len())
+        .then(|| {
+            terms
+                .iter()
+                .filter_map(|term| Some((lower.find(&term.to_lowercase())?, term.len())))
+                .min()
+        })
+        .flatten();
+    let Some((start, len)) = found else {
+        return text.chars().take(SNIPPET_CHARS * 2).collect();
+    };
+
+    let before: String = text[..start].chars().rev().take(SNIPPET_CHARS).collect();
+    let before: String = before.chars().rev().collect();
+    let after: String = text[start + len..].chars().take(SNIPPET_CHARS).collect();
+    let ellipsis = |cut: bool| if cut { "…" } else { "" };
+    format!(
+        "{}{before}{HIGHLIGHT_START}{}{HIGHLIGHT_END}{after}{}",
+        ellipsis(before.len() < start),
+        &text[start..start + len],
+        ellipsis(start + len + after.len() < text.len()),
+    )
+}
+
+/// Scans with `LIKE` when FTS5 isn't available. `AND`/`OR` are honoured; prefix `*` is
+/// implied since `LIKE` matches substrings anyway.
+async fn search_like(
+    pool: &SqlitePool,
+    tokens: &[Token],
+    limit: u32,
+    offset: u32,
+) -> Result<SearchResults, sqlx::Error> {
+    let mut condition = String::new();
+    let mut patterns = Vec::new();
+    let mut terms = Vec::new();
+    for token in tokens {
+        match token {
+            Token::And => condition.push_str(" AND "),
+            Token::Or => condition.push_str(" OR "),
+            Token::Term { text, .. } | Token::Phrase(text) => {
+                if !condition.is_empty() && !condition.ends_with(' ') {
+                    condition.push_str(" AND ");
+                }
+                condition
+                    .push_str("(n.title LIKE ? ESCAPE '\\' OR n.content_text LIKE ? ESCAPE '\\')");
+                patterns.push(like_pattern(text));
+                terms.push(text.as_str());
+            }
+        }
+    }
+    let filter = format!("n.is_deleted = 0 AND ({condition})");
+
+    let count_sql = format!("SELECT COUNT(*) FROM notes n WHERE {filter}");
+    let mut count = sqlx::query_scalar(&count_sql);
+    for pattern in &patterns {
+        count = count.bind(pattern).bind(pattern);
+    }
+    let total: i64 = count.fetch_one(pool).await?;
+
+    let select_sql = format!(
+        "SELECT n.id, n.title, COALESCE(n.content_text, '') FROM notes n WHERE {filter}
+         ORDER BY n.updated_at DESC LIMIT ? OFFSET ?"
+    );
+    let mut select = sqlx::query_as(&select_sql);
+    for pattern in &patterns {
+        select = select.bind(pattern).bind(pattern);
+    }
+    let rows: Vec<(String, String, String)> =
+        select.bind(limit).bind(offset).fetch_all(pool).await?;
+
+    Ok(SearchResults {
+        results: rows
+            .into_iter()
+            .map(|(id, title, text)| {
+                let source = if text.is_empty() { &title } else { &text };
+                SearchHit {
+                    snippet: like_snippet(source, &terms),
+                    id,
+                    title,
+                    rank: None,
+                }
+            })
+            .collect(),
+        total,
+        engine: SearchEngine::Like,
+    })
+}
+
+/// Ranked full-text search over notes; `limit` is capped at 100.
+#[tauri::command]
+pub async fn search_notes(
+    query: String,
+    limit: u32,
+    offset: u32,
+    db: State<'_, Db>,
+) -> Result<SearchResults, DbError> {
+    let pool = db.pool()?;
+    let tokens = tokenize(&query);
+    let limit = limit.clamp(1, MAX_LIMIT);
+    let engine = if has_fts(pool).await? {
+        SearchEngine::Fts5
+    } else {
+        SearchEngine::Like
+    };
+
+    if tokens.is_empty() {
+        return Ok(SearchResults {
+            results: Vec::new(),
+            total: 0,
+            engine,
+        });
+    }
+
+    Ok(match engine {
+        SearchEngine::Fts5 => search_fts(pool, &tokens, limit, offset).await?,
+        SearchEngine::Like => search_like(pool, &tokens, limit, offset).await?,
+    })
+}
@@ -0,0 +1,273 @@
+use std::fs;
+use std::sync::{Mutex, OnceLock};
+
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::{KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key};
+use serde::Serialize;
+use tauri::{AppHandle, Runtime, State};
+use tauri_plugin_store::StoreExt;
+
+use crate::keychain::{self, KeychainError};
+use crate::secure_store::{self, SecureStoreError};
+
+/// Names of stored secrets; the OS keychains can't enumerate an app's entries.
+const INDEX_STORE: &str = "secrets-index.json";
+const INDEX_KEY: &str = "keys";
+/// Encrypted values for the file fallback, and the random key generated for this install.
+pub(crate) const VAULT_STORE: &str = "secrets-vault.json";
+const VAULT_KEY_FILE: &str = "secrets.key";
+const PROBE_ACCOUNT: &str = "__backend_probe__";
+
+#[derive(Debug, thiserror::Error, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "camelCase")]
+pub enum SecretError {
+    #[error("no secret stored under `{0}`")]
+    NotFound(String),
+    #[error("keychain error: {0}")]
+    Keychain(String),
+    #[error("secret storage io failed: {0}")]
+    Io(String),
+    #[error("secret could not be decrypted")]
+    Decryption,
+}
+
+impl From<KeychainError> for SecretError {
+    fn from(e: KeychainError) -> Self {
+        Self::Keychain(e.to_string())
+    }
+}
+
+impl From<SecureStoreError> for SecretError {
+    fn from(e: SecureStoreError) -> Self {
+        match e {
+            SecureStoreError::Decryption => Self::Decryption,
+            e => Self::Io(e.to_string()),
+        }
+    }
+}
+
+impl From<tauri_plugin_store::Error> for SecretError {
+    fn from(e: tauri_plugin_store::Error) -> Self {
+        Self::Io(e.to_string())
+    }
+}
+
+impl From<std::io::Error> for SecretError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e.to_string())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SecretBackend {
+    Keychain,
+    EncryptedFile,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SecretBackendInfo {
+    pub backend: SecretBackend,
+    pub os: String,
+    /// Why the keychain was skipped, when it was.
+    pub fallback_reason: Option<String>,
+}
+
+/// The backend is probed once per launch; the index lock serialises read-modify-write
+/// of the key list.
+#[derive(Default)]
+pub struct Secrets {
+    backend: OnceLock<SecretBackendInfo>,
+    index: Mutex<()>,
+}
+
+fn service<R: Runtime>(app: &AppHandle<R>) -> String {
+    app.config().identifier.clone()
+}
+
+/// Uses the keychain if a lookup either succeeds or cleanly reports a missing entry;
+/// anything else (no Secret Service on a headless Linux box) falls back.
+fn backend<'a, R: Runtime>(app: &AppHandle<R>, secrets: &'a Secrets) -> &'a SecretBackendInfo {
+    secrets.backend.get_or_init(|| {
+        let probe = keychain::entry(app, &service(app), PROBE_ACCOUNT)
+            .and_then(|entry| entry.get_password());
+        let fallback_reason = match probe {
+            Ok(_) | Err(KeychainError::NotFound) => None,
+            Err(e) => Some(e.to_string()),
+        };
+        if let Some(reason) = &fallback_reason {
+            tracing::warn!(%reason, "keychain unavailable; using encrypted file for secrets");
+        }
+
+        SecretBackendInfo {
+            backend: match fallback_reason {
+                None => SecretBackend::Keychain,
+                Some(_) => SecretBackend::EncryptedFile,
+            },
+            os: std::env::consts::OS.to_string(),
+            fallback_reason,
+        }
+    })
+}
+
+/// The keychain entry for `account`, never the file fallback: its key sits in a file
+/// beside the vault, so it can't guard anything meant to stay off the disk.
+#[cfg_attr(not(feature = "db-encryption"), allow(dead_code))]
+pub(crate) fn keychain_entry<R: Runtime>(
+    app: &AppHandle<R>,
+    secrets: &Secrets,
+    account: &str,
+) -> Result<keychain::Entry<R>, SecretError> {
+    let info = backend(app, secrets);
+    if info.backend != SecretBackend::Keychain {
+        return Err(SecretError::Keychain(
+            info.fallback_reason.clone().unwrap_or_default(),
+        ));
+    }
+    Ok(keychain::entry(app, &service(app), account)?)
+}
+
+fn vault_cipher<R: Runtime>(app: &AppHandle<R>) -> Result<Aes256Gcm, SecretError> {
+    let path = tauri_plugin_store::resolve_store_path(app, VAULT_KEY_FILE)?;
+    let key = match fs::read(&path) {
+        Ok(key) if key.len() == 32 => key,
+        Ok(_) => return Err(SecretError::Io(format!("{} is corrupted", path.display()))),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            let mut key = vec![0; 32];
+            OsRng.fill_bytes(&mut key);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(&path, &key)?;
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                fs::set_permissions(&path, fs::Permissions::from_mode(0o600))?;
+            }
+            key
+        }
+        Err(e) => return Err(e.into()),
+    };
+    Ok(Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key)))
+}
+
+fn read_index<R: Runtime>(app: &AppHandle<R>) -> Result<Vec<String>, SecretError> {
+    let keys = app.store(INDEX_STORE)?.get(INDEX_KEY).unwrap_or_default();
+    Ok(serde_json::from_value(keys).unwrap_or_default())
+}
+
+fn update_index<R: Runtime>(
+    app: &AppHandle<R>,
+    secrets: &Secrets,
+    update: impl FnOnce(&mut Vec<String>),
+) -> Result<(), SecretError> {
+    let _guard = secrets.index.lock().unwrap();
+    let mut keys = read_index(app)?;
+    update(&mut keys);
+
+    let store = app.store(INDEX_STORE)?;
+    store.set(INDEX_KEY, keys);
+    store.save()?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn secret_set<R: Runtime>(
+    key: String,
+    value: String,
+    app: AppHandle<R>,
+    secrets: State<'_, Secrets>,
+) -> Result<(), SecretError> {
+    match backend(&app, &secrets).backend {
+        SecretBackend::Keychain => {
+            keychain::entry(&app, &service(&app), &key)?.set_password(&value)?;
+        }
+        SecretBackend::EncryptedFile => {
+            let blob = secure_store::seal(&vault_cipher(&app)?, &key, value.as_bytes())?;
+            let store = app.store(VAULT_STORE)?;
+            store.set(key.clone(), blob);
+            store.save()?;
+        }
+    }
+
+    update_index(&app, &secrets, |keys| {
+        if !keys.contains(&key) {
+            keys.push(key);
+        }
+    })
+}
+
+/// [`secret_get`] for Rust callers that use a secret without handing it to the frontend.
+pub(crate) fn get<R: Runtime>(
+    app: &AppHandle<R>,
+    secrets: &Secrets,
+    key: &str,
+) -> Result<String, SecretError> {
+    match backend(app, secrets).backend {
+        SecretBackend::Keychain => match keychain::entry(app, &service(app), key)?.get_password() {
+            Ok(value) => Ok(value),
+            Err(KeychainError::NotFound) => Err(SecretError::NotFound(key.to_string())),
+            Err(e) => Err(e.into()),
+        },
+        SecretBackend::EncryptedFile => {
+            let encoded = app
+                .store(VAULT_STORE)?
+                .get(key)
+                .and_then(|v| v.as_str().map(str::to_string))
+                .ok_or_else(|| SecretError::NotFound(key.to_string()))?;
+            let plaintext = secure_store::open(&vault_cipher(app)?, key, &encoded)?;
+            String::from_utf8(plaintext).map_err(|_| SecretError::Decryption)
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn secret_get<R: Runtime>(
+    key: String,
+    app: AppHandle<R>,
+    secrets: State<'_, Secrets>,
+) -> Result<String, SecretError> {
+    get(&app, &secrets, &key)
+}
+
+#[tauri::command]
+pub async fn secret_delete<R: Runtime>(
+    key: String,
+    app: AppHandle<R>,
+    secrets: State<'_, Secrets>,
+) -> Result<(), SecretError> {
+    match backend(&app, &secrets).backend {
+        SecretBackend::Keychain => {
+            match keychain::entry(&app, &service(&app), &key)?.delete_credential() {
+                Ok(()) | Err(KeychainError::NotFound) => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        SecretBackend::EncryptedFile => {
+            let store = app.store(VAULT_STORE)?;
+            store.delete(&key);
+            store.save()?;
+        }
+    }
+
+    update_index(&app, &secrets, |keys| keys.retain(|k| k != &key))
+}
+
+#[tauri::command]
+pub async fn secret_list_keys<R: Runtime>(
+    app: AppHandle<R>,
+    secrets: State<'_, Secrets>,
+) -> Result<Vec<String>, SecretError> {
+    let _guard = secrets.index.lock().unwrap();
+    read_index(&app)
+}
+
+#[tauri::command]
+pub fn secret_backend_info<R: Runtime>(
+    app: AppHandle<R>,
+    secrets: State<'_, Secrets>,
+) -> SecretBackendInfo {
+    backend(&app, &secrets).clone()
+}
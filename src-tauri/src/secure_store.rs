@@ -0,0 +1,158 @@
+use std::fs;
+
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::Serialize;
+use serde_json::Value;
+use sha2::Sha256;
+use tauri::{AppHandle, Runtime};
+use tauri_plugin_store::StoreExt;
+
+/// Store file holding base64 `nonce || ciphertext` blobs, one per key.
+const STORE_FILE: &str = "secure-store.json";
+/// Random salt kept next to the store file; created on first write.
+const SALT_FILE: &str = "secure-store.salt";
+
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+const PBKDF2_ROUNDS: u32 = 600_000;
+
+#[derive(Debug, thiserror::Error, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "camelCase")]
+pub enum SecureStoreError {
+    #[error("secure store io failed: {0}")]
+    Io(String),
+    #[error("no value stored under `{0}`")]
+    NotFound(String),
+    #[error("decryption failed; wrong password or corrupted data")]
+    Decryption,
+    #[error("value could not be (de)serialized: {0}")]
+    Serialization(String),
+}
+
+impl From<std::io::Error> for SecureStoreError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e.to_string())
+    }
+}
+
+impl From<tauri_plugin_store::Error> for SecureStoreError {
+    fn from(e: tauri_plugin_store::Error) -> Self {
+        Self::Io(e.to_string())
+    }
+}
+
+fn load_or_create_salt<R: Runtime>(app: &AppHandle<R>) -> Result<Vec<u8>, SecureStoreError> {
+    let path = tauri_plugin_store::resolve_store_path(app, SALT_FILE)?;
+    match fs::read(&path) {
+        Ok(salt) if salt.len() == SALT_LEN => Ok(salt),
+        Ok(_) => Err(SecureStoreError::Io(format!(
+            "{} is corrupted",
+            path.display()
+        ))),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            let mut salt = vec![0; SALT_LEN];
+            OsRng.fill_bytes(&mut salt);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(&path, &salt)?;
+            Ok(salt)
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn derive_cipher(password: &str, salt: &[u8]) -> Aes256Gcm {
+    let mut key = [0u8; 32];
+    pbkdf2::pbkdf2_hmac::<Sha256>(password.as_bytes(), salt, PBKDF2_ROUNDS, &mut key);
+    Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key))
+}
+
+/// Encrypts `plaintext` into base64 `nonce || ciphertext`. The entry key is bound as
+/// associated data so a ciphertext can't be moved to a different key.
+pub(crate) fn seal(
+    cipher: &Aes256Gcm,
+    key: &str,
+    plaintext: &[u8],
+) -> Result<String, SecureStoreError> {
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(
+            &nonce,
+            Payload {
+                msg: plaintext,
+                aad: key.as_bytes(),
+            },
+        )
+        .map_err(|_| SecureStoreError::Decryption)?;
+
+    let mut blob = nonce.to_vec();
+    blob.extend_from_slice(&ciphertext);
+    Ok(BASE64.encode(blob))
+}
+
+/// Reverses [`seal`] for the same entry key.
+pub(crate) fn open(
+    cipher: &Aes256Gcm,
+    key: &str,
+    encoded: &str,
+) -> Result<Vec<u8>, SecureStoreError> {
+    let blob = BASE64
+        .decode(encoded)
+        .map_err(|_| SecureStoreError::Decryption)?;
+    if blob.len() < NONCE_LEN {
+        return Err(SecureStoreError::Decryption);
+    }
+    let (nonce, ciphertext) = blob.split_at(NONCE_LEN);
+
+    cipher
+        .decrypt(
+            Nonce::from_slice(nonce),
+            Payload {
+                msg: ciphertext,
+                aad: key.as_bytes(),
+            },
+        )
+        .map_err(|_| SecureStoreError::Decryption)
+}
+
+/// Encrypts `value` under a password-derived key.
+#[tauri::command]
+pub async fn secure_store_set<R: Runtime>(
+    app: AppHandle<R>,
+    key: String,
+    value: Value,
+    password: String,
+) -> Result<(), SecureStoreError> {
+    let salt = load_or_create_salt(&app)?;
+    let plaintext =
+        serde_json::to_vec(&value).map_err(|e| SecureStoreError::Serialization(e.to_string()))?;
+
+    let blob = seal(&derive_cipher(&password, &salt), &key, &plaintext)?;
+
+    let store = app.store(STORE_FILE)?;
+    store.set(key, blob);
+    store.save()?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn secure_store_get<R: Runtime>(
+    app: AppHandle<R>,
+    key: String,
+    password: String,
+) -> Result<Value, SecureStoreError> {
+    let store = app.store(STORE_FILE)?;
+    let encoded = store
+        .get(&key)
+        .and_then(|v| v.as_str().map(str::to_string))
+        .ok_or_else(|| SecureStoreError::NotFound(key.clone()))?;
+    let salt = load_or_create_salt(&app)?;
+    let plaintext = open(&derive_cipher(&password, &salt), &key, &encoded)?;
+
+    serde_json::from_slice(&plaintext).map_err(|e| SecureStoreError::Serialization(e.to_string()))
+}
@@ -0,0 +1,330 @@
+//! Typed app settings, kept in `settings.json` beside the store plugin's files in the
+//! app data directory, so backups pick it up with the stores. The frontend reads and
+//! patches them through commands rather than writing store keys directly, which lets a
+//! misspelled key fail validation instead of quietly becoming a new setting.
+//!
+//! Keys this version doesn't know are kept as they are, so settings written by a newer
+//! version survive a round trip through an older one.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tauri::{AppHandle, Emitter, Manager, Runtime, State};
+
+pub const CHANGED_EVENT: &str = "settings://changed";
+
+const SETTINGS_FILE: &str = "settings.json";
+const DOWNLOAD_CONCURRENCY: std::ops::RangeInclusive<u8> = 1..=8;
+const TASK_CONCURRENCY: std::ops::RangeInclusive<u8> = 1..=8;
+const NET_RATE: std::ops::RangeInclusive<u32> = 1..=1000;
+const NET_MAX_IN_FLIGHT: std::ops::RangeInclusive<u8> = 1..=64;
+const ASSET_MAX_SIZE: std::ops::RangeInclusive<u64> = 1024..=1024 * 1024 * 1024;
+
+#[derive(Debug, thiserror::Error, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "camelCase")]
+pub enum SettingsError {
+    #[error("invalid settings: {0}")]
+    Invalid(String),
+    #[error("io error: {0}")]
+    Io(String),
+}
+
+impl From<io::Error> for SettingsError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e.to_string())
+    }
+}
+
+impl From<tauri::Error> for SettingsError {
+    fn from(e: tauri::Error) -> Self {
+        Self::Io(e.to_string())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ThemePreference {
+    #[default]
+    System,
+    Light,
+    Dark,
+}
+
+/// Keys are stored and reported in snake_case, as the other preferences are.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub theme: ThemePreference,
+    /// BCP 47 tag such as `en-GB`; `None` follows the OS.
+    pub language: Option<String>,
+    /// Must be `https`. `None` leaves sync with its built-in endpoint.
+    pub sync_endpoint: Option<String>,
+    pub telemetry_opt_in: bool,
+    /// Downloads allowed to transfer at once.
+    pub download_concurrency: u8,
+    /// Background tasks allowed to run at once.
+    pub task_concurrency: u8,
+    /// Sustained requests per second to one host through `net_fetch`.
+    pub net_rate_per_sec: u32,
+    /// Requests to one host that may go out at once before `net_rate_per_sec` applies.
+    pub net_burst: u32,
+    /// `net_fetch` requests in flight across all hosts; read when the app starts.
+    pub net_max_in_flight: u8,
+    /// Largest asset `register_asset` and its streaming variant will hold in memory.
+    pub asset_max_size_bytes: u64,
+    pub spellcheck: bool,
+    #[serde(flatten)]
+    unknown: Map<String, Value>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            theme: ThemePreference::System,
+            language: None,
+            sync_endpoint: None,
+            telemetry_opt_in: false,
+            download_concurrency: 3,
+            task_concurrency: 2,
+            net_rate_per_sec: 10,
+            net_burst: 20,
+            net_max_in_flight: 16,
+            asset_max_size_bytes: 64 * 1024 * 1024,
+            spellcheck: true,
+            unknown: Map::new(),
+        }
+    }
+}
+
+impl Settings {
+    fn validate(&self) -> Result<(), SettingsError> {
+        let invalid = |message: String| Err(SettingsError::Invalid(message));
+        if !DOWNLOAD_CONCURRENCY.contains(&self.download_concurrency) {
+            return invalid(format!(
+                "download_concurrency must be between {} and {}",
+                DOWNLOAD_CONCURRENCY.start(),
+                DOWNLOAD_CONCURRENCY.end()
+            ));
+        }
+        if !TASK_CONCURRENCY.contains(&self.task_concurrency) {
+            return invalid(format!(
+                "task_concurrency must be between {} and {}",
+                TASK_CONCURRENCY.start(),
+                TASK_CONCURRENCY.end()
+            ));
+        }
+        for (name, value) in [
+            ("net_rate_per_sec", self.net_rate_per_sec),
+            ("net_burst", self.net_burst),
+        ] {
+            if !NET_RATE.contains(&value) {
+                return invalid(format!(
+                    "{name} must be between {} and {}",
+                    NET_RATE.start(),
+                    NET_RATE.end()
+                ));
+            }
+        }
+        if !NET_MAX_IN_FLIGHT.contains(&self.net_max_in_flight) {
+            return invalid(format!(
+                "net_max_in_flight must be between {} and {}",
+                NET_MAX_IN_FLIGHT.start(),
+                NET_MAX_IN_FLIGHT.end()
+            ));
+        }
+        if !ASSET_MAX_SIZE.contains(&self.asset_max_size_bytes) {
+            return invalid(format!(
+                "asset_max_size_bytes must be between {} and {}",
+                ASSET_MAX_SIZE.start(),
+                ASSET_MAX_SIZE.end()
+            ));
+        }
+        if let Some(endpoint) = &self.sync_endpoint {
+            match tauri::Url::parse(endpoint) {
+                Ok(url) if url.scheme() == "https" && url.has_host() => {}
+                _ => return invalid("sync_endpoint must be an https URL".into()),
+            }
+        }
+        if let Some(language) = &self.language {
+            let tag = !language.is_empty()
+                && language
+                    .split('-')
+                    .all(|part| (1..=8).contains(&part.len()))
+                && language
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-');
+            if !tag {
+                return invalid("language must be a BCP 47 tag such as en-GB".into());
+            }
+        }
+        Ok(())
+    }
+
+    fn to_map(&self) -> Map<String, Value> {
+        match serde_json::to_value(self) {
+            Ok(Value::Object(map)) => map,
+            _ => Map::new(),
+        }
+    }
+}
+
+fn settings_path<R: Runtime>(app: &AppHandle<R>) -> Result<PathBuf, SettingsError> {
+    Ok(app.path().app_data_dir()?.join(SETTINGS_FILE))
+}
+
+/// Writes `bytes` beside `dest` and renames it into place, so a crash mid-write leaves
+/// the previous settings rather than a truncated file.
+fn write_atomic(dest: &Path, bytes: &[u8]) -> io::Result<()> {
+    if let Some(dir) = dest.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut tmp = dest.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+    fs::write(&tmp, bytes)?;
+    fs::rename(&tmp, dest).inspect_err(|_| {
+        let _ = fs::remove_file(&tmp);
+    })
+}
+
+fn save(path: &Path, settings: &Settings) -> Result<(), SettingsError> {
+    let bytes =
+        serde_json::to_vec_pretty(settings).map_err(|e| SettingsError::Invalid(e.to_string()))?;
+    Ok(write_atomic(path, &bytes)?)
+}
+
+/// Reads the settings file, setting aside one that can't be used as
+/// `settings.json.<unix ms>.bak` and starting over from the defaults.
+fn load(path: &Path) -> Settings {
+    let bytes = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Settings::default(),
+        Err(e) => {
+            // Left in place: it may well be fine once it can be read again.
+            tracing::warn!(error = %e, "couldn't read settings, using defaults");
+            return Settings::default();
+        }
+    };
+    let problem = match serde_json::from_slice::<Settings>(&bytes) {
+        Ok(settings) => match settings.validate() {
+            Ok(()) => return settings,
+            Err(e) => e.to_string(),
+        },
+        Err(e) => e.to_string(),
+    };
+
+    let stamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or_default();
+    let mut backup = path.as_os_str().to_owned();
+    backup.push(format!(".{stamp}.bak"));
+    let backup = PathBuf::from(backup);
+    tracing::warn!(%problem, backup = %backup.display(), "settings file unusable, resetting to defaults");
+    if let Err(e) = fs::rename(path, &backup) {
+        tracing::warn!(error = %e, "couldn't back up settings file");
+    }
+    let settings = Settings::default();
+    if let Err(e) = save(path, &settings) {
+        tracing::warn!(error = %e, "couldn't write default settings");
+    }
+    settings
+}
+
+/// Loads the settings into managed state. Call before anything that reads them.
+pub fn init<R: Runtime>(app: &AppHandle<R>) {
+    let settings = match settings_path(app) {
+        Ok(path) => load(&path),
+        Err(e) => {
+            tracing::warn!(error = %e, "no settings path, using defaults");
+            Settings::default()
+        }
+    };
+    app.manage(RwLock::new(settings));
+}
+
+/// The settings as last loaded or updated.
+pub fn current<R: Runtime>(app: &AppHandle<R>) -> Settings {
+    app.try_state::<RwLock<Settings>>()
+        .map(|settings| settings.read().unwrap().clone())
+        .unwrap_or_default()
+}
+
+/// JSON Merge Patch (RFC 7396): objects merge key by key, and `null` removes a key,
+/// which puts a known setting back to its default.
+fn merge(target: &mut Value, patch: Value) {
+    match (target, patch) {
+        (Value::Object(target), Value::Object(patch)) => {
+            for (key, value) in patch {
+                if value.is_null() {
+                    target.remove(&key);
+                } else if let Some(existing) = target.get_mut(&key) {
+                    merge(existing, value);
+                } else {
+                    target.insert(key, value);
+                }
+            }
+        }
+        (target, patch) => *target = patch,
+    }
+}
+
+/// Keys whose values differ, with their new values; removed keys map to `null`.
+fn changed(before: &Settings, after: &Settings) -> Map<String, Value> {
+    let before = before.to_map();
+    let after = after.to_map();
+    let mut changed: Map<String, Value> = after
+        .iter()
+        .filter(|(key, value)| before.get(*key) != Some(value))
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect();
+    for key in before.keys().filter(|key| !after.contains_key(*key)) {
+        changed.insert(key.clone(), Value::Null);
+    }
+    changed
+}
+
+#[tauri::command]
+pub fn get_settings(settings: State<'_, RwLock<Settings>>) -> Settings {
+    settings.read().unwrap().clone()
+}
+
+/// Merges `patch` into the settings, then validates and saves the result, or leaves
+/// everything as it was if any part of it is invalid. Emits [`CHANGED_EVENT`] with the
+/// keys that actually changed.
+#[tauri::command]
+pub fn update_settings<R: Runtime>(
+    patch: Value,
+    app: AppHandle<R>,
+    settings: State<'_, RwLock<Settings>>,
+) -> Result<Settings, SettingsError> {
+    let Value::Object(keys) = &patch else {
+        return Err(SettingsError::Invalid("patch must be an object".into()));
+    };
+    // Unknown keys read from the file are kept, but never created from here.
+    let known = Settings::default().to_map();
+    if let Some(key) = keys.keys().find(|key| !known.contains_key(*key)) {
+        return Err(SettingsError::Invalid(format!("unknown setting {key}")));
+    }
+    let mut current = settings.write().unwrap();
+    let mut merged = Value::Object(current.to_map());
+    merge(&mut merged, patch);
+    let next: Settings =
+        serde_json::from_value(merged).map_err(|e| SettingsError::Invalid(e.to_string()))?;
+    next.validate()?;
+
+    let changed = changed(&current, &next);
+    if changed.is_empty() {
+        return Ok(next);
+    }
+    save(&settings_path(&app)?, &next)?;
+    *current = next.clone();
+    drop(current);
+    let _ = app.emit(CHANGED_EVENT, changed);
+    Ok(next)
+}
@@ -0,0 +1,307 @@
+//! Sharing notes to other apps through the system share sheet on iOS and Android.
+//! Desktop has no equivalent, so text goes to the clipboard and files are revealed in
+//! the file manager, with the result saying which was done.
+//!
+//! The native halves live in `plugins/share`; like [`crate::push`], they are registered
+//! here so Rust holds the plugin handle.
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Runtime};
+
+use crate::scope;
+
+#[derive(Debug, thiserror::Error, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "camelCase")]
+pub enum ShareError {
+    #[error("file not found: {0}")]
+    NotFound(String),
+    #[error("{0}")]
+    Scope(String),
+    #[error("invalid URL: {0}")]
+    InvalidUrl(String),
+    #[error("nothing to share")]
+    Empty,
+    #[error("sharing failed: {0}")]
+    Failed(String),
+}
+
+#[cfg_attr(mobile, allow(dead_code))]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ShareFallback {
+    Clipboard,
+    RevealedInFileManager,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShareResult {
+    /// Whether the user picked a target. Android only reports the choice, not whether
+    /// the target app went through with it.
+    pub completed: bool,
+    /// The activity type on iOS, e.g. `com.apple.UIKit.activity.Mail`, or the chosen
+    /// app's package on Android.
+    pub activity: Option<String>,
+    /// Set on desktop, where there is no share sheet.
+    pub fallback: Option<ShareFallback>,
+}
+
+/// What [`share`] hands to the share sheet.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", rename_all_fields = "camelCase")]
+pub enum ShareContent {
+    Text(String),
+    Url(String),
+    File { path: String, mime_type: String },
+    Multiple(Vec<ShareContent>),
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "outcome", rename_all = "camelCase")]
+pub enum ShareOutcome {
+    /// `app` is the activity type on iOS, the chosen app's package on Android, and
+    /// `"clipboard"` on desktop.
+    Completed {
+        app: Option<String>,
+    },
+    Dismissed,
+}
+
+/// [`ShareContent`] flattened and checked, as the native halves take it.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+enum ShareItem {
+    Text { text: String },
+    Url { url: String },
+    File { path: PathBuf, mime: String },
+}
+
+fn flatten<R: Runtime>(
+    app: &AppHandle<R>,
+    content: ShareContent,
+    items: &mut Vec<ShareItem>,
+) -> Result<(), ShareError> {
+    match content {
+        ShareContent::Text(text) => items.push(ShareItem::Text { text }),
+        ShareContent::Url(url) => {
+            tauri::Url::parse(&url).map_err(|_| ShareError::InvalidUrl(url.clone()))?;
+            items.push(ShareItem::Url { url });
+        }
+        ShareContent::File { path, mime_type } => {
+            let path = scope::ensure_allowed(app, Path::new(&path)).map_err(ShareError::Scope)?;
+            if !path.is_file() {
+                return Err(ShareError::NotFound(path.display().to_string()));
+            }
+            items.push(ShareItem::File {
+                path,
+                mime: mime_type,
+            });
+        }
+        ShareContent::Multiple(contents) => {
+            for content in contents {
+                flatten(app, content, items)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(mobile)]
+mod native {
+    use std::path::PathBuf;
+
+    use serde::Serialize;
+    use tauri::plugin::{PluginHandle, TauriPlugin};
+    use tauri::{AppHandle, Manager, Runtime};
+    // Linked for its native halves; it has no Rust API.
+    use tauri_plugin_share as _;
+
+    use super::{ShareError, ShareItem, ShareResult};
+
+    #[cfg(target_os = "ios")]
+    tauri::ios_plugin_binding!(init_plugin_share);
+
+    struct Share<R: Runtime>(PluginHandle<R>);
+
+    #[derive(Serialize)]
+    struct ShareTextArgs {
+        text: String,
+        title: Option<String>,
+    }
+
+    #[derive(Serialize)]
+    struct ShareFileArgs {
+        path: PathBuf,
+        mime: Option<String>,
+    }
+
+    #[derive(Serialize)]
+    struct ShareItemsArgs {
+        items: Vec<ShareItem>,
+    }
+
+    pub fn plugin<R: Runtime>() -> TauriPlugin<R> {
+        tauri::plugin::Builder::new("share")
+            .setup(|app, api| {
+                #[cfg(target_os = "android")]
+                let handle = api.register_android_plugin("com.layers.share", "SharePlugin")?;
+                #[cfg(target_os = "ios")]
+                let handle = api.register_ios_plugin(init_plugin_share)?;
+                app.manage(Share(handle));
+                Ok(())
+            })
+            .build()
+    }
+
+    fn handle<R: Runtime>(app: &AppHandle<R>) -> Result<PluginHandle<R>, ShareError> {
+        app.try_state::<Share<R>>()
+            .map(|share| share.0.clone())
+            .ok_or_else(|| ShareError::Failed("share plugin not loaded".into()))
+    }
+
+    pub async fn share_text<R: Runtime>(
+        app: &AppHandle<R>,
+        text: String,
+        title: Option<String>,
+    ) -> Result<ShareResult, ShareError> {
+        handle(app)?
+            .run_mobile_plugin_async("shareText", ShareTextArgs { text, title })
+            .await
+            .map_err(|e| ShareError::Failed(e.to_string()))
+    }
+
+    pub async fn share_file<R: Runtime>(
+        app: &AppHandle<R>,
+        path: PathBuf,
+        mime: Option<String>,
+    ) -> Result<ShareResult, ShareError> {
+        handle(app)?
+            .run_mobile_plugin_async("shareFile", ShareFileArgs { path, mime })
+            .await
+            .map_err(|e| ShareError::Failed(e.to_string()))
+    }
+
+    pub async fn share<R: Runtime>(
+        app: &AppHandle<R>,
+        items: Vec<ShareItem>,
+    ) -> Result<ShareResult, ShareError> {
+        handle(app)?
+            .run_mobile_plugin_async("share", ShareItemsArgs { items })
+            .await
+            .map_err(|e| ShareError::Failed(e.to_string()))
+    }
+}
+
+#[cfg(desktop)]
+mod native {
+    use std::path::PathBuf;
+
+    use tauri::{AppHandle, Runtime};
+    use tauri_plugin_opener::OpenerExt;
+
+    use super::{ShareError, ShareFallback, ShareItem, ShareResult};
+
+    fn fallback(fallback: ShareFallback) -> ShareResult {
+        ShareResult {
+            completed: true,
+            activity: None,
+            fallback: Some(fallback),
+        }
+    }
+
+    pub async fn share_text<R: Runtime>(
+        app: &AppHandle<R>,
+        text: String,
+        _title: Option<String>,
+    ) -> Result<ShareResult, ShareError> {
+        crate::clipboard::clipboard_write_text(text, app.clone())
+            .map_err(|e| ShareError::Failed(e.to_string()))?;
+        Ok(fallback(ShareFallback::Clipboard))
+    }
+
+    pub async fn share_file<R: Runtime>(
+        app: &AppHandle<R>,
+        path: PathBuf,
+        _mime: Option<String>,
+    ) -> Result<ShareResult, ShareError> {
+        app.opener()
+            .reveal_item_in_dir(&path)
+            .map_err(|e| ShareError::Failed(e.to_string()))?;
+        Ok(fallback(ShareFallback::RevealedInFileManager))
+    }
+
+    /// Everything goes to the clipboard as text, one item per line, with files as
+    /// their paths.
+    pub async fn share<R: Runtime>(
+        app: &AppHandle<R>,
+        items: Vec<ShareItem>,
+    ) -> Result<ShareResult, ShareError> {
+        let lines: Vec<String> = items
+            .into_iter()
+            .map(|item| match item {
+                ShareItem::Text { text } => text,
+                ShareItem::Url { url } => url,
+                ShareItem::File { path, .. } => path.display().to_string(),
+            })
+            .collect();
+        share_text(app, lines.join("\n"), None).await
+    }
+}
+
+#[cfg(mobile)]
+pub fn plugin<R: Runtime>() -> tauri::plugin::TauriPlugin<R> {
+    native::plugin()
+}
+
+/// Resolves once the share sheet is dismissed; `title` becomes the subject where the
+/// target has one, such as an email.
+#[tauri::command]
+pub async fn share_text<R: Runtime>(
+    text: String,
+    title: Option<String>,
+    app: AppHandle<R>,
+) -> Result<ShareResult, ShareError> {
+    native::share_text(&app, text, title).await
+}
+
+/// `mime` defaults to one guessed from the extension. On Android the file is handed out
+/// through a `FileProvider` URI, copied into the cache first if it's outside the app's
+/// own directories.
+#[tauri::command]
+pub async fn share_file<R: Runtime>(
+    path: String,
+    mime: Option<String>,
+    app: AppHandle<R>,
+) -> Result<ShareResult, ShareError> {
+    let path: PathBuf = scope::ensure_allowed(&app, Path::new(&path)).map_err(ShareError::Scope)?;
+    if !path.is_file() {
+        return Err(ShareError::NotFound(path.display().to_string()));
+    }
+    native::share_file(&app, path, mime).await
+}
+
+/// Shares any mix of text, links and files in one sheet. Resolves once the sheet is
+/// dismissed; Android can only say which app was picked, not whether it finished.
+#[tauri::command]
+pub async fn share<R: Runtime>(
+    content: ShareContent,
+    app: AppHandle<R>,
+) -> Result<ShareOutcome, ShareError> {
+    let mut items = Vec::new();
+    flatten(&app, content, &mut items)?;
+    if items.is_empty() {
+        return Err(ShareError::Empty);
+    }
+    let result = native::share(&app, items).await?;
+    Ok(match (result.completed, result.fallback) {
+        (true, Some(ShareFallback::Clipboard)) => ShareOutcome::Completed {
+            app: Some("clipboard".into()),
+        },
+        (true, _) => ShareOutcome::Completed {
+            app: result.activity,
+        },
+        (false, _) => ShareOutcome::Dismissed,
+    })
+}
@@ -0,0 +1,192 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Mutex;
+
+use serde::Serialize;
+use serde_json::Value;
+use tauri::plugin::TauriPlugin;
+use tauri::{AppHandle, Emitter, Manager, Runtime, State};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutEvent, ShortcutState};
+use tauri_plugin_store::StoreExt;
+
+/// Accelerator -> action pairs, re-registered on launch.
+const STORE_FILE: &str = "shortcuts.json";
+/// Triggered shortcuts emit `shortcut://{action}`.
+pub const EVENT_PREFIX: &str = "shortcut://";
+
+#[derive(Debug, thiserror::Error, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "camelCase")]
+pub enum ShortcutError {
+    #[error("`{0}` is not a valid accelerator")]
+    InvalidAccelerator(String),
+    #[error("`{0}` is not a valid action name")]
+    InvalidAction(String),
+    #[error("`{0}` is already registered")]
+    AlreadyRegistered(String),
+    #[error("`{0}` is not registered")]
+    NotRegistered(String),
+    #[error("shortcut registration failed: {0}")]
+    Registration(String),
+    #[error("shortcut store failed: {0}")]
+    Store(String),
+}
+
+impl From<tauri_plugin_store::Error> for ShortcutError {
+    fn from(e: tauri_plugin_store::Error) -> Self {
+        Self::Store(e.to_string())
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ShortcutBinding {
+    pub accelerator: String,
+    pub action: String,
+}
+
+/// Bindings keyed by the parsed shortcut's id, so `Cmd+Shift+Space` and
+/// `shift+super+space` are recognised as the same shortcut.
+#[derive(Default)]
+pub struct Shortcuts {
+    bindings: Mutex<HashMap<u32, ShortcutBinding>>,
+}
+
+fn parse(accelerator: &str) -> Result<Shortcut, ShortcutError> {
+    Shortcut::from_str(accelerator)
+        .map_err(|_| ShortcutError::InvalidAccelerator(accelerator.to_string()))
+}
+
+/// Actions become part of an event name, so they are limited to the characters Tauri
+/// accepts there.
+fn validate_action(action: &str) -> Result<(), ShortcutError> {
+    let valid = !action.is_empty()
+        && action
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | ':' | '/'));
+    if !valid {
+        return Err(ShortcutError::InvalidAction(action.to_string()));
+    }
+    Ok(())
+}
+
+fn on_shortcut<R: Runtime>(app: &AppHandle<R>, shortcut: &Shortcut, event: ShortcutEvent) {
+    if event.state != ShortcutState::Pressed {
+        return;
+    }
+    let action = app
+        .state::<Shortcuts>()
+        .bindings
+        .lock()
+        .unwrap()
+        .get(&shortcut.id())
+        .map(|binding| binding.action.clone());
+    if let Some(action) = action {
+        let _ = app.emit(&format!("{EVENT_PREFIX}{action}"), ());
+    }
+}
+
+pub fn plugin<R: Runtime>() -> TauriPlugin<R> {
+    tauri_plugin_global_shortcut::Builder::new()
+        .with_handler(on_shortcut)
+        .build()
+}
+
+fn register<R: Runtime>(
+    app: &AppHandle<R>,
+    accelerator: &str,
+    action: &str,
+) -> Result<(), ShortcutError> {
+    let shortcut = parse(accelerator)?;
+    validate_action(action)?;
+
+    let shortcuts = app.state::<Shortcuts>();
+    let mut bindings = shortcuts.bindings.lock().unwrap();
+    // Also catches shortcuts the frontend registered through the plugin directly.
+    if let Some(existing) = bindings.get(&shortcut.id()) {
+        return Err(ShortcutError::AlreadyRegistered(
+            existing.accelerator.clone(),
+        ));
+    }
+    if app.global_shortcut().is_registered(shortcut) {
+        return Err(ShortcutError::AlreadyRegistered(accelerator.to_string()));
+    }
+
+    app.global_shortcut()
+        .register(shortcut)
+        .map_err(|e| ShortcutError::Registration(e.to_string()))?;
+    bindings.insert(
+        shortcut.id(),
+        ShortcutBinding {
+            accelerator: accelerator.to_string(),
+            action: action.to_string(),
+        },
+    );
+    Ok(())
+}
+
+/// Re-registers the shortcuts saved by previous sessions. Ones that no longer parse or
+/// that another app has claimed in the meantime are logged and skipped.
+pub fn init<R: Runtime>(app: &AppHandle<R>) -> Result<(), ShortcutError> {
+    app.manage(Shortcuts::default());
+
+    for (accelerator, action) in app.store(STORE_FILE)?.entries() {
+        let Value::String(action) = action else {
+            continue;
+        };
+        if let Err(e) = register(app, &accelerator, &action) {
+            tracing::warn!(%accelerator, error = %e, "failed to restore global shortcut");
+        }
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn register_shortcut<R: Runtime>(
+    accelerator: String,
+    action: String,
+    app: AppHandle<R>,
+) -> Result<(), ShortcutError> {
+    register(&app, &accelerator, &action)?;
+
+    let store = app.store(STORE_FILE)?;
+    store.set(accelerator, action);
+    store.save()?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn unregister_shortcut<R: Runtime>(
+    accelerator: String,
+    app: AppHandle<R>,
+    shortcuts: State<'_, Shortcuts>,
+) -> Result<(), ShortcutError> {
+    let shortcut = parse(&accelerator)?;
+    let binding = shortcuts
+        .bindings
+        .lock()
+        .unwrap()
+        .remove(&shortcut.id())
+        .ok_or_else(|| ShortcutError::NotRegistered(accelerator.clone()))?;
+
+    app.global_shortcut()
+        .unregister(shortcut)
+        .map_err(|e| ShortcutError::Registration(e.to_string()))?;
+
+    // Saved under the spelling it was registered with, which may differ from this one.
+    let store = app.store(STORE_FILE)?;
+    store.delete(&binding.accelerator);
+    store.save()?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn list_shortcuts(shortcuts: State<'_, Shortcuts>) -> Vec<ShortcutBinding> {
+    let mut bindings: Vec<_> = shortcuts
+        .bindings
+        .lock()
+        .unwrap()
+        .values()
+        .cloned()
+        .collect();
+    bindings.sort_by(|a, b| a.accelerator.cmp(&b.accelerator));
+    bindings
+}
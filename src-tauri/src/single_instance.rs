@@ -0,0 +1,39 @@
+//! One desktop instance per user. A second launch hands its arguments and working
+//! directory to the running instance and exits before `run`; the plugin passes them
+//! over a named mutex and window message on Windows, D-Bus on Linux, and a Unix socket
+//! on macOS.
+//!
+//! A crashed primary doesn't block the next launch: the OS releases the mutex and the
+//! D-Bus name with the process, and the plugin replaces a macOS socket nobody answers.
+
+use serde::Serialize;
+use tauri::plugin::TauriPlugin;
+use tauri::{AppHandle, Emitter, Runtime};
+
+pub const ARGS_EVENT: &str = "single-instance://args";
+
+/// Set to any value to allow several instances, as the benchmark scripts need.
+const ALLOW_MULTIPLE_ENV: &str = "LAYERS_ALLOW_MULTIPLE_INSTANCES";
+
+#[derive(Clone, Serialize)]
+struct ForwardedArgs {
+    /// Including the executable path, as in [`std::env::args`].
+    args: Vec<String>,
+    cwd: String,
+}
+
+pub fn enabled() -> bool {
+    std::env::var_os(ALLOW_MULTIPLE_ENV).is_none()
+}
+
+fn on_second_instance<R: Runtime>(app: &AppHandle<R>, args: Vec<String>, cwd: String) {
+    tracing::info!(?args, "second instance forwarded its arguments");
+    crate::tray::show_main_window(app);
+    let _ = app.emit(ARGS_EVENT, ForwardedArgs { args, cwd });
+}
+
+/// Must be the first plugin registered. Deep links among the forwarded arguments reach
+/// `tauri_plugin_deep_link::on_open_url` before [`ARGS_EVENT`] is emitted.
+pub fn plugin<R: Runtime>() -> TauriPlugin<R> {
+    tauri_plugin_single_instance::init(on_second_instance)
+}
@@ -0,0 +1,1615 @@
+//! Reading text aloud and dictation through the OS speech engines, which sound far better
+//! than the webview's `speechSynthesis` where it exists at all (WebKitGTK has none).
+//! Speech comes from AVSpeechSynthesizer on macOS and iOS, `Windows.Media.SpeechSynthesis`
+//! on Windows, speech-dispatcher on Linux, and `TextToSpeech` on Android. Dictation uses
+//! Apple's Speech framework and Android's `SpeechRecognizer`; Windows and Linux have no
+//! recognizer we can rely on, so it's [`SpeechError::Unsupported`] there.
+//!
+//! One utterance plays at a time, and speaking again interrupts the last one.
+//! [`BOUNDARY_EVENT`] marks each word as it's read, with offsets in UTF-16 code units so
+//! they index the frontend's string directly, and [`END_EVENT`] follows every utterance,
+//! finished or not. Dictation asks for the microphone (and, on Apple platforms, speech
+//! recognition) the first time it starts; a refusal is [`SpeechError::PermissionDenied`].
+
+use std::ops::RangeInclusive;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use tauri::ipc::Channel;
+use tauri::{AppHandle, Emitter, Runtime, State};
+
+/// Emitted with a [`WordBoundary`] as each word starts.
+pub const BOUNDARY_EVENT: &str = "speech://boundary";
+/// Emitted with an [`UtteranceEnd`] once per utterance.
+pub const END_EVENT: &str = "speech://end";
+
+/// As multiples of the voice's normal rate and pitch.
+const RATE: RangeInclusive<f32> = 0.25..=4.0;
+const PITCH: RangeInclusive<f32> = 0.5..=2.0;
+
+#[derive(Debug, Clone, thiserror::Error, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "camelCase")]
+#[cfg_attr(not(any(target_os = "macos", mobile)), allow(dead_code))]
+pub enum SpeechError {
+    #[cfg_attr(target_os = "macos", allow(dead_code))]
+    #[error("not supported on this platform")]
+    Unsupported,
+    /// Names the permission: the microphone, or speech recognition on Apple platforms.
+    #[error("{0} permission was denied")]
+    PermissionDenied(String),
+    #[error("no voice with id {0}")]
+    VoiceNotFound(String),
+    #[error("invalid input: {0}")]
+    InvalidInput(String),
+    #[error("dictation is already running")]
+    Busy,
+    /// The speech service is missing or not running.
+    #[error("speech unavailable: {0}")]
+    Unavailable(String),
+    #[error("speech failed: {0}")]
+    Failed(String),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Voice {
+    /// What [`SpeakOptions::voice_id`] takes.
+    pub id: String,
+    pub name: String,
+    /// A BCP 47 tag.
+    pub language: String,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpeakOptions {
+    /// The platform's default voice when unset.
+    pub voice_id: Option<String>,
+    /// 0.25 to 4 times the voice's normal rate; engines with a narrower range clamp it.
+    pub rate: Option<f32>,
+    /// 0.5 to 2 times the voice's normal pitch.
+    pub pitch: Option<f32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WordBoundary {
+    pub utterance_id: u64,
+    /// In UTF-16 code units, into the text given to [`speak`].
+    pub char_index: u32,
+    pub char_length: u32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UtteranceEnd {
+    pub utterance_id: u64,
+    /// Stopped, or cut off by the next utterance, before the end.
+    pub interrupted: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Transcript {
+    /// Everything heard so far, not just what's new; the recognizer revises earlier words
+    /// as more context arrives.
+    pub text: String,
+    pub is_final: bool,
+}
+
+/// An utterance with its options checked and defaulted.
+#[cfg_attr(
+    not(any(target_os = "linux", target_os = "macos", windows, mobile)),
+    allow(dead_code)
+)]
+struct Utterance {
+    id: u64,
+    text: String,
+    voice_id: Option<String>,
+    rate: f32,
+    pitch: f32,
+}
+
+enum SpeechEvent {
+    Boundary(WordBoundary),
+    End(UtteranceEnd),
+}
+
+/// Where a backend reports what it's reading, from whichever thread the engine calls
+/// back on.
+type Sink = Arc<dyn Fn(SpeechEvent) + Send + Sync>;
+type Transcripts = Arc<dyn Fn(Transcript) + Send + Sync>;
+
+fn sink<R: Runtime>(app: &AppHandle<R>) -> Sink {
+    let app = app.clone();
+    Arc::new(move |event| {
+        let _ = match event {
+            SpeechEvent::Boundary(boundary) => app.emit(BOUNDARY_EVENT, boundary),
+            SpeechEvent::End(end) => app.emit(END_EVENT, end),
+        };
+    })
+}
+
+#[derive(Default)]
+pub struct Speech {
+    next_id: AtomicU64,
+    backend: os::Backend,
+}
+
+/// speech-dispatcher over SSIP, its line protocol, on the per-user socket. It has no word
+/// events of its own, so each word is preceded by an SSML mark named for the word's range.
+#[cfg(target_os = "linux")]
+mod os {
+    use std::collections::{HashMap, VecDeque};
+    use std::fmt::Write as _;
+    use std::path::PathBuf;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    use tauri::{AppHandle, Runtime};
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::unix::{OwnedReadHalf, OwnedWriteHalf};
+    use tokio::net::UnixStream;
+    use tokio::sync::{oneshot, Mutex as AsyncMutex};
+
+    use super::{
+        Sink, SpeechError, SpeechEvent, Transcripts, Utterance, UtteranceEnd, Voice, WordBoundary,
+    };
+
+    /// Event codes; every other reply answers a command, in order.
+    const INDEX_MARK: u16 = 700;
+    const END: u16 = 702;
+    const CANCELED: u16 = 703;
+
+    struct Reply {
+        code: u16,
+        /// The text of each line, without its code.
+        lines: Vec<String>,
+    }
+
+    #[derive(Default)]
+    struct Messages {
+        /// speech-dispatcher's message id to ours, while it's being read.
+        utterances: HashMap<u64, u64>,
+        /// Messages that ended before their `SPEAK` reply was handled, and whether they
+        /// were cancelled.
+        ended: HashMap<u64, bool>,
+    }
+
+    struct Connection {
+        /// Held from a command to its reply, so replies arrive in the order of `replies`.
+        writer: AsyncMutex<OwnedWriteHalf>,
+        replies: Mutex<VecDeque<oneshot::Sender<Reply>>>,
+        messages: Mutex<Messages>,
+        sink: Sink,
+        closed: AtomicBool,
+    }
+
+    #[derive(Default)]
+    pub struct Backend {
+        connection: AsyncMutex<Option<Arc<Connection>>>,
+    }
+
+    fn unavailable(e: std::io::Error) -> SpeechError {
+        SpeechError::Unavailable(e.to_string())
+    }
+
+    fn socket_path() -> Option<PathBuf> {
+        match std::env::var("SPEECHD_ADDRESS") {
+            Ok(address) => address.strip_prefix("unix_socket:").map(PathBuf::from),
+            Err(_) => std::env::var_os("XDG_RUNTIME_DIR")
+                .map(|dir| PathBuf::from(dir).join("speech-dispatcher/speechd.sock")),
+        }
+    }
+
+    impl Connection {
+        /// Sends `data` and waits for its reply. `writer` is the locked [`Self::writer`].
+        async fn send(
+            &self,
+            writer: &mut OwnedWriteHalf,
+            data: &str,
+        ) -> Result<Reply, SpeechError> {
+            let (reply, replied) = oneshot::channel();
+            self.replies.lock().unwrap().push_back(reply);
+            writer
+                .write_all(data.as_bytes())
+                .await
+                .map_err(unavailable)?;
+            let reply = replied
+                .await
+                .map_err(|_| SpeechError::Unavailable("speech-dispatcher hung up".into()))?;
+            if reply.code >= 300 {
+                let message = reply.lines.last().cloned().unwrap_or_default();
+                return Err(SpeechError::Failed(format!("{} {message}", reply.code)));
+            }
+            Ok(reply)
+        }
+
+        async fn command(
+            &self,
+            writer: &mut OwnedWriteHalf,
+            line: &str,
+        ) -> Result<Reply, SpeechError> {
+            self.send(writer, &format!("{line}\r\n")).await
+        }
+
+        /// An event block's lines are the message id, the client id, and then any detail.
+        fn event(&self, code: u16, lines: &[String]) {
+            match code {
+                INDEX_MARK => {
+                    if let Some(boundary) = lines.get(2).and_then(|mark| parse_mark(mark)) {
+                        (self.sink)(SpeechEvent::Boundary(boundary));
+                    }
+                }
+                END | CANCELED => {
+                    let Some(message) = lines.first().and_then(|id| id.parse().ok()) else {
+                        return;
+                    };
+                    let interrupted = code == CANCELED;
+                    let mut messages = self.messages.lock().unwrap();
+                    match messages.utterances.remove(&message) {
+                        Some(utterance_id) => {
+                            drop(messages);
+                            (self.sink)(SpeechEvent::End(UtteranceEnd {
+                                utterance_id,
+                                interrupted,
+                            }));
+                        }
+                        None => {
+                            messages.ended.insert(message, interrupted);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Each line is `NNN-text` within a block and `NNN text` at its end.
+    async fn read(connection: Arc<Connection>, reader: OwnedReadHalf) {
+        let mut lines = BufReader::new(reader).lines();
+        let mut block = Vec::new();
+        while let Ok(Some(line)) = lines.next_line().await {
+            let line = line.trim_end_matches('\r');
+            let (Some(code), Some(separator)) = (
+                line.get(..3).and_then(|code| code.parse::<u16>().ok()),
+                line.as_bytes().get(3),
+            ) else {
+                continue;
+            };
+            block.push(line.get(4..).unwrap_or_default().to_string());
+            if *separator == b'-' {
+                continue;
+            }
+            let lines = std::mem::take(&mut block);
+            if code / 100 == 7 {
+                connection.event(code, &lines);
+            } else if let Some(reply) = connection.replies.lock().unwrap().pop_front() {
+                let _ = reply.send(Reply { code, lines });
+            }
+        }
+        connection.closed.store(true, Ordering::Relaxed);
+        // Dropping the senders fails the commands still waiting.
+        connection.replies.lock().unwrap().clear();
+    }
+
+    async fn connect(sink: Sink) -> Result<Arc<Connection>, SpeechError> {
+        let path = socket_path()
+            .ok_or_else(|| SpeechError::Unavailable("no speech-dispatcher socket".into()))?;
+        let stream = match UnixStream::connect(&path).await {
+            Ok(stream) => stream,
+            Err(_) => {
+                // Started on demand, as libspeechd does; it exits again once idle.
+                let spawned = tauri::async_runtime::spawn_blocking(|| {
+                    std::process::Command::new("speech-dispatcher")
+                        .arg("--spawn")
+                        .status()
+                })
+                .await;
+                if !matches!(spawned, Ok(Ok(status)) if status.success()) {
+                    return Err(SpeechError::Unavailable(
+                        "speech-dispatcher isn't installed or wouldn't start".into(),
+                    ));
+                }
+                UnixStream::connect(&path).await.map_err(unavailable)?
+            }
+        };
+        let (reader, writer) = stream.into_split();
+        let connection = Arc::new(Connection {
+            writer: AsyncMutex::new(writer),
+            replies: Mutex::default(),
+            messages: Mutex::default(),
+            sink,
+            closed: AtomicBool::new(false),
+        });
+        tauri::async_runtime::spawn(read(connection.clone(), reader));
+        let mut writer = connection.writer.lock().await;
+        for command in [
+            "SET self CLIENT_NAME user:layers:speech",
+            "SET self NOTIFICATION index_marks on",
+            "SET self NOTIFICATION end on",
+            "SET self NOTIFICATION cancel on",
+            "SET self SSML_MODE on",
+        ] {
+            connection.command(&mut writer, command).await?;
+        }
+        drop(writer);
+        Ok(connection)
+    }
+
+    /// A multiple of normal as SSIP's -100 to 100, where 0 is normal and `doubling` is
+    /// how far twice normal moves it.
+    fn scale(multiple: f32, doubling: f32) -> i32 {
+        (multiple.log2() * doubling).round().clamp(-100.0, 100.0) as i32
+    }
+
+    fn escape(out: &mut String, text: &str) {
+        for c in text.chars() {
+            match c {
+                '&' => out.push_str("&amp;"),
+                '<' => out.push_str("&lt;"),
+                '>' => out.push_str("&gt;"),
+                '"' => out.push_str("&quot;"),
+                c => out.push(c),
+            }
+        }
+    }
+
+    /// `text` as SSML with a mark before each word, named `utterance:start:length` in
+    /// UTF-16 units.
+    fn ssml(utterance: u64, text: &str) -> String {
+        let mut out = String::from("<speak>");
+        let mark = |out: &mut String, word: &str, start: u32, end: u32| {
+            let _ = write!(out, r#"<mark name="{utterance}:{start}:{}"/>"#, end - start);
+            escape(out, word);
+        };
+        // The current word's byte and UTF-16 offsets.
+        let mut word: Option<(usize, u32)> = None;
+        let mut offset = 0;
+        for (i, c) in text.char_indices() {
+            if c.is_whitespace() {
+                if let Some((start, start16)) = word.take() {
+                    mark(&mut out, &text[start..i], start16, offset);
+                }
+                out.push(c);
+            } else if word.is_none() {
+                word = Some((i, offset));
+            }
+            offset += c.len_utf16() as u32;
+        }
+        if let Some((start, start16)) = word {
+            mark(&mut out, &text[start..], start16, offset);
+        }
+        out.push_str("</speak>");
+        out
+    }
+
+    fn parse_mark(mark: &str) -> Option<WordBoundary> {
+        let mut parts = mark.split(':');
+        let utterance_id = parts.next()?.parse().ok()?;
+        let char_index = parts.next()?.parse().ok()?;
+        let char_length = parts.next()?.parse().ok()?;
+        Some(WordBoundary {
+            utterance_id,
+            char_index,
+            char_length,
+        })
+    }
+
+    /// The message body after `SPEAK`: CRLF lines with a leading `.` doubled, ended by a
+    /// line holding only `.`.
+    fn message_data(text: &str) -> String {
+        let mut out = String::with_capacity(text.len() + 8);
+        for line in text.lines() {
+            if line.starts_with('.') {
+                out.push('.');
+            }
+            out.push_str(line);
+            out.push_str("\r\n");
+        }
+        out.push_str(".\r\n");
+        out
+    }
+
+    impl Backend {
+        /// The open connection, or a new one if the daemon went away since.
+        async fn connection<R: Runtime>(
+            &self,
+            app: &AppHandle<R>,
+        ) -> Result<Arc<Connection>, SpeechError> {
+            let mut connection = self.connection.lock().await;
+            if let Some(open) = connection
+                .as_ref()
+                .filter(|c| !c.closed.load(Ordering::Relaxed))
+            {
+                return Ok(open.clone());
+            }
+            let open = connect(super::sink(app)).await?;
+            *connection = Some(open.clone());
+            Ok(open)
+        }
+
+        pub async fn voices<R: Runtime>(
+            &self,
+            app: &AppHandle<R>,
+        ) -> Result<Vec<Voice>, SpeechError> {
+            let connection = self.connection(app).await?;
+            let mut writer = connection.writer.lock().await;
+            let reply = connection
+                .command(&mut writer, "LIST SYNTHESIS_VOICES")
+                .await?;
+            // Every line but the last `OK` is `name\tlanguage\tvariant`.
+            let voices = &reply.lines[..reply.lines.len().saturating_sub(1)];
+            Ok(voices
+                .iter()
+                .filter_map(|line| {
+                    let mut fields = line.split('\t');
+                    let name = fields.next()?;
+                    Some(Voice {
+                        id: name.to_string(),
+                        name: name.to_string(),
+                        language: fields.next().unwrap_or_default().to_string(),
+                    })
+                })
+                .collect())
+        }
+
+        pub async fn speak<R: Runtime>(
+            &self,
+            app: &AppHandle<R>,
+            utterance: Utterance,
+        ) -> Result<(), SpeechError> {
+            if let Some(voice) = &utterance.voice_id {
+                // It goes into a command line.
+                if voice.is_empty() || voice.chars().any(char::is_control) {
+                    return Err(SpeechError::VoiceNotFound(voice.clone()));
+                }
+            }
+            let connection = self.connection(app).await?;
+            let mut writer = connection.writer.lock().await;
+            connection.command(&mut writer, "CANCEL self").await?;
+            let rate = format!("SET self RATE {}", scale(utterance.rate, 50.0));
+            connection.command(&mut writer, &rate).await?;
+            let pitch = format!("SET self PITCH {}", scale(utterance.pitch, 100.0));
+            connection.command(&mut writer, &pitch).await?;
+            match &utterance.voice_id {
+                Some(voice) => {
+                    let voice = format!("SET self SYNTHESIS_VOICE {voice}");
+                    connection.command(&mut writer, &voice).await?
+                }
+                // Back to speech-dispatcher's stock default after a chosen voice.
+                None => {
+                    connection
+                        .command(&mut writer, "SET self VOICE_TYPE MALE1")
+                        .await?
+                }
+            };
+            connection.command(&mut writer, "SPEAK").await?;
+            let data = message_data(&ssml(utterance.id, &utterance.text));
+            let reply = connection.send(&mut writer, &data).await?;
+            drop(writer);
+
+            let message = reply
+                .lines
+                .first()
+                .and_then(|id| id.parse().ok())
+                .ok_or_else(|| SpeechError::Failed("no message id in the reply".into()))?;
+            let ended = {
+                let mut messages = connection.messages.lock().unwrap();
+                let ended = messages.ended.remove(&message);
+                if ended.is_none() {
+                    messages.utterances.insert(message, utterance.id);
+                }
+                ended
+            };
+            if let Some(interrupted) = ended {
+                (connection.sink)(SpeechEvent::End(UtteranceEnd {
+                    utterance_id: utterance.id,
+                    interrupted,
+                }));
+            }
+            Ok(())
+        }
+
+        pub async fn stop<R: Runtime>(
+            &self,
+            app: &AppHandle<R>,
+            id: u64,
+        ) -> Result<(), SpeechError> {
+            let connection = self.connection(app).await?;
+            let message = connection
+                .messages
+                .lock()
+                .unwrap()
+                .utterances
+                .iter()
+                .find_map(|(message, utterance)| (*utterance == id).then_some(*message));
+            let Some(message) = message else {
+                return Ok(());
+            };
+            let mut writer = connection.writer.lock().await;
+            connection
+                .command(&mut writer, &format!("CANCEL {message}"))
+                .await
+                .map(drop)
+        }
+
+        pub async fn start_dictation<R: Runtime>(
+            &self,
+            _app: &AppHandle<R>,
+            _language: Option<String>,
+            _on_transcript: Transcripts,
+        ) -> Result<(), SpeechError> {
+            Err(SpeechError::Unsupported)
+        }
+
+        pub async fn stop_dictation<R: Runtime>(
+            &self,
+            _app: &AppHandle<R>,
+        ) -> Result<(), SpeechError> {
+            Err(SpeechError::Unsupported)
+        }
+    }
+}
+
+/// `Windows.Media.SpeechSynthesis` renders each utterance to a stream carrying a track of
+/// word cues, which a `MediaPlayer` plays and raises as each word comes up.
+#[cfg(windows)]
+mod os {
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    use tauri::{AppHandle, Runtime};
+    use windows::core::{Interface, HSTRING};
+    use windows::Foundation::Collections::{CollectionChange, IVectorChangedEventArgs};
+    use windows::Foundation::TypedEventHandler;
+    use windows::Media::Core::{MediaCueEventArgs, MediaSource, SpeechCue, TimedMetadataTrack};
+    use windows::Media::Playback::{
+        MediaPlaybackItem, MediaPlayer, TimedMetadataTrackPresentationMode,
+    };
+    use windows::Media::SpeechSynthesis::SpeechSynthesizer;
+
+    use super::{
+        Sink, SpeechError, SpeechEvent, Transcripts, Utterance, UtteranceEnd, Voice, WordBoundary,
+    };
+
+    /// The id of the track `IncludeWordBoundaryMetadata` adds.
+    const WORD_TRACK: &str = "SpeechWord";
+
+    /// Players by utterance, until they end.
+    type Players = Arc<Mutex<HashMap<u64, MediaPlayer>>>;
+
+    #[derive(Default)]
+    pub struct Backend {
+        players: Players,
+    }
+
+    fn failed(e: windows::core::Error) -> SpeechError {
+        SpeechError::Failed(e.message())
+    }
+
+    fn joined(e: tauri::Error) -> SpeechError {
+        SpeechError::Failed(e.to_string())
+    }
+
+    fn voices() -> windows::core::Result<Vec<Voice>> {
+        SpeechSynthesizer::AllVoices()?
+            .into_iter()
+            .map(|voice| {
+                Ok(Voice {
+                    id: voice.Id()?.to_string(),
+                    name: voice.DisplayName()?.to_string(),
+                    language: voice.Language()?.to_string(),
+                })
+            })
+            .collect()
+    }
+
+    fn synthesizer(utterance: &Utterance) -> Result<SpeechSynthesizer, SpeechError> {
+        let synthesizer = SpeechSynthesizer::new().map_err(failed)?;
+        if let Some(id) = &utterance.voice_id {
+            let voice = SpeechSynthesizer::AllVoices()
+                .map_err(failed)?
+                .into_iter()
+                .find(|voice| voice.Id().is_ok_and(|voice| voice == id.as_str()))
+                .ok_or_else(|| SpeechError::VoiceNotFound(id.clone()))?;
+            synthesizer.SetVoice(&voice).map_err(failed)?;
+        }
+        Ok(synthesizer)
+    }
+
+    fn synthesize(
+        synthesizer: &SpeechSynthesizer,
+        utterance: &Utterance,
+    ) -> windows::core::Result<MediaPlaybackItem> {
+        let options = synthesizer.Options()?;
+        options.SetIncludeWordBoundaryMetadata(true)?;
+        // Windows takes rates from 0.5 to 6 times normal.
+        options.SetSpeakingRate(f64::from(utterance.rate).clamp(0.5, 6.0))?;
+        options.SetAudioPitch(f64::from(utterance.pitch))?;
+        let stream = synthesizer
+            .SynthesizeTextToStreamAsync(&HSTRING::from(utterance.text.as_str()))?
+            .join()?;
+        let source = MediaSource::CreateFromStream(&stream, &stream.ContentType()?)?;
+        MediaPlaybackItem::Create(&source)
+    }
+
+    /// Ends `id` if it's still playing.
+    fn finish(players: &Players, id: u64, interrupted: bool, sink: &Sink) {
+        let Some(player) = players.lock().unwrap().remove(&id) else {
+            return;
+        };
+        if interrupted {
+            let _ = player.Pause();
+        }
+        sink(SpeechEvent::End(UtteranceEnd {
+            utterance_id: id,
+            interrupted,
+        }));
+    }
+
+    /// Raises the cues of the track at `index` if it's the word track. Cues on tracks the
+    /// app doesn't present itself are never raised.
+    fn watch_words(
+        item: &MediaPlaybackItem,
+        index: u32,
+        id: u64,
+        sink: &Sink,
+    ) -> windows::core::Result<()> {
+        let tracks = item.TimedMetadataTracks()?;
+        if tracks.GetAt(index)?.Id()? != WORD_TRACK {
+            return Ok(());
+        }
+        let sink = sink.clone();
+        tracks.GetAt(index)?.CueEntered(&TypedEventHandler::<
+            TimedMetadataTrack,
+            MediaCueEventArgs,
+        >::new(move |_, args| {
+            let Some(args) = args.as_ref() else {
+                return Ok(());
+            };
+            let cue: SpeechCue = args.Cue()?.cast()?;
+            let start = cue.StartPositionInInput()?.Value()?;
+            // Inclusive, and like the start in UTF-16 units.
+            let end = cue.EndPositionInInput()?.Value()?;
+            sink(SpeechEvent::Boundary(WordBoundary {
+                utterance_id: id,
+                char_index: start.max(0) as u32,
+                char_length: (end - start + 1).max(0) as u32,
+            }));
+            Ok(())
+        }))?;
+        tracks.SetPresentationMode(
+            index,
+            TimedMetadataTrackPresentationMode::ApplicationPresented,
+        )
+    }
+
+    fn play(
+        players: &Players,
+        id: u64,
+        item: MediaPlaybackItem,
+        sink: &Sink,
+    ) -> windows::core::Result<()> {
+        for index in 0..item.TimedMetadataTracks()?.Size()? {
+            watch_words(&item, index, id, sink)?;
+        }
+        // The word track may only show up once the stream is opened.
+        let words = sink.clone();
+        item.TimedMetadataTracksChanged(&TypedEventHandler::<
+            MediaPlaybackItem,
+            IVectorChangedEventArgs,
+        >::new(move |item, args| {
+            if let (Some(item), Some(args)) = (item.as_ref(), args.as_ref()) {
+                if args.CollectionChange()? == CollectionChange::ItemInserted {
+                    watch_words(item, args.Index()?, id, &words)?;
+                }
+            }
+            Ok(())
+        }))?;
+
+        let player = MediaPlayer::new()?;
+        player.SetSource(&item)?;
+        let (ended, ended_sink) = (players.clone(), sink.clone());
+        player.MediaEnded(&TypedEventHandler::new(move |_, _| {
+            finish(&ended, id, false, &ended_sink);
+            Ok(())
+        }))?;
+        let (failed, failed_sink) = (players.clone(), sink.clone());
+        player.MediaFailed(&TypedEventHandler::new(move |_, _| {
+            finish(&failed, id, true, &failed_sink);
+            Ok(())
+        }))?;
+        players.lock().unwrap().insert(id, player.clone());
+        player.Play()
+    }
+
+    impl Backend {
+        pub async fn voices<R: Runtime>(
+            &self,
+            _app: &AppHandle<R>,
+        ) -> Result<Vec<Voice>, SpeechError> {
+            tauri::async_runtime::spawn_blocking(voices)
+                .await
+                .map_err(joined)?
+                .map_err(failed)
+        }
+
+        pub async fn speak<R: Runtime>(
+            &self,
+            app: &AppHandle<R>,
+            utterance: Utterance,
+        ) -> Result<(), SpeechError> {
+            let sink = super::sink(app);
+            let players = self.players.clone();
+            tauri::async_runtime::spawn_blocking(move || {
+                let synthesizer = synthesizer(&utterance)?;
+                let item = synthesize(&synthesizer, &utterance).map_err(failed)?;
+                let playing: Vec<u64> = players.lock().unwrap().keys().copied().collect();
+                for id in playing {
+                    finish(&players, id, true, &sink);
+                }
+                play(&players, utterance.id, item, &sink).map_err(failed)
+            })
+            .await
+            .map_err(joined)?
+        }
+
+        pub async fn stop<R: Runtime>(
+            &self,
+            app: &AppHandle<R>,
+            id: u64,
+        ) -> Result<(), SpeechError> {
+            finish(&self.players, id, true, &super::sink(app));
+            Ok(())
+        }
+
+        pub async fn start_dictation<R: Runtime>(
+            &self,
+            _app: &AppHandle<R>,
+            _language: Option<String>,
+            _on_transcript: Transcripts,
+        ) -> Result<(), SpeechError> {
+            Err(SpeechError::Unsupported)
+        }
+
+        pub async fn stop_dictation<R: Runtime>(
+            &self,
+            _app: &AppHandle<R>,
+        ) -> Result<(), SpeechError> {
+            Err(SpeechError::Unsupported)
+        }
+    }
+}
+
+/// AVSpeechSynthesizer for speech, and the Speech framework fed from an `AVAudioEngine`
+/// microphone tap for dictation. Both are kept on the main thread, and the app's
+/// `Info.plist` carries the usage descriptions macOS shows when prompting.
+#[cfg(target_os = "macos")]
+mod os {
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+    use std::ptr::NonNull;
+    use std::sync::Mutex;
+
+    use block2::RcBlock;
+    use dispatch2::DispatchQueue;
+    use objc2::rc::Retained;
+    use objc2::runtime::{Bool, ProtocolObject};
+    use objc2::{define_class, msg_send, AllocAnyThread, DefinedClass};
+    use objc2_av_foundation::{AVAuthorizationStatus, AVCaptureDevice, AVMediaTypeAudio};
+    use objc2_avf_audio::{
+        AVAudioEngine, AVAudioPCMBuffer, AVAudioTime, AVSpeechBoundary, AVSpeechSynthesisVoice,
+        AVSpeechSynthesizer, AVSpeechSynthesizerDelegate, AVSpeechUtterance,
+        AVSpeechUtteranceDefaultSpeechRate, AVSpeechUtteranceMaximumSpeechRate,
+        AVSpeechUtteranceMinimumSpeechRate,
+    };
+    use objc2_foundation::{NSError, NSLocale, NSObject, NSObjectProtocol, NSRange, NSString};
+    use objc2_speech::{
+        SFSpeechAudioBufferRecognitionRequest, SFSpeechRecognitionResult, SFSpeechRecognitionTask,
+        SFSpeechRecognizer, SFSpeechRecognizerAuthorizationStatus,
+    };
+    use tauri::{AppHandle, Runtime};
+    use tokio::sync::oneshot;
+
+    use super::{
+        Sink, SpeechError, SpeechEvent, Transcript, Transcripts, Utterance, UtteranceEnd, Voice,
+        WordBoundary,
+    };
+
+    /// Samples per microphone buffer handed to the recognizer.
+    const TAP_BUFFER_SIZE: u32 = 1024;
+
+    struct DelegateIvars {
+        sink: Sink,
+        /// Utterances being read, by address, to our ids.
+        utterances: Mutex<HashMap<usize, u64>>,
+    }
+
+    define_class!(
+        // SAFETY: `NSObject` has no subclassing requirements, and `Delegate` doesn't
+        // implement `Drop`.
+        #[unsafe(super(NSObject))]
+        #[name = "LayersSpeechDelegate"]
+        #[ivars = DelegateIvars]
+        struct Delegate;
+
+        unsafe impl NSObjectProtocol for Delegate {}
+
+        // SAFETY: the signatures match the protocol's.
+        unsafe impl AVSpeechSynthesizerDelegate for Delegate {
+            #[unsafe(method(speechSynthesizer:willSpeakRangeOfSpeechString:utterance:))]
+            fn will_speak(
+                &self,
+                _synthesizer: &AVSpeechSynthesizer,
+                range: NSRange,
+                utterance: &AVSpeechUtterance,
+            ) {
+                let id = self
+                    .ivars()
+                    .utterances
+                    .lock()
+                    .unwrap()
+                    .get(&key(utterance))
+                    .copied();
+                if let Some(utterance_id) = id {
+                    (self.ivars().sink)(SpeechEvent::Boundary(WordBoundary {
+                        utterance_id,
+                        char_index: range.location as u32,
+                        char_length: range.length as u32,
+                    }));
+                }
+            }
+
+            #[unsafe(method(speechSynthesizer:didFinishSpeechUtterance:))]
+            fn did_finish(
+                &self,
+                _synthesizer: &AVSpeechSynthesizer,
+                utterance: &AVSpeechUtterance,
+            ) {
+                self.end(utterance, false);
+            }
+
+            #[unsafe(method(speechSynthesizer:didCancelSpeechUtterance:))]
+            fn did_cancel(
+                &self,
+                _synthesizer: &AVSpeechSynthesizer,
+                utterance: &AVSpeechUtterance,
+            ) {
+                self.end(utterance, true);
+            }
+        }
+    );
+
+    impl Delegate {
+        fn new(sink: Sink) -> Retained<Self> {
+            let this = Self::alloc().set_ivars(DelegateIvars {
+                sink,
+                utterances: Mutex::default(),
+            });
+            // SAFETY: `NSObject`'s designated initializer.
+            unsafe { msg_send![super(this), init] }
+        }
+
+        fn track(&self, utterance: &AVSpeechUtterance, id: u64) {
+            self.ivars()
+                .utterances
+                .lock()
+                .unwrap()
+                .insert(key(utterance), id);
+        }
+
+        fn reading(&self, id: u64) -> bool {
+            self.ivars()
+                .utterances
+                .lock()
+                .unwrap()
+                .values()
+                .any(|reading| *reading == id)
+        }
+
+        fn end(&self, utterance: &AVSpeechUtterance, interrupted: bool) {
+            let id = self
+                .ivars()
+                .utterances
+                .lock()
+                .unwrap()
+                .remove(&key(utterance));
+            if let Some(utterance_id) = id {
+                (self.ivars().sink)(SpeechEvent::End(UtteranceEnd {
+                    utterance_id,
+                    interrupted,
+                }));
+            }
+        }
+    }
+
+    /// The synthesizer holds on to an utterance until it's done with it, so its address
+    /// stays unique that long.
+    fn key(utterance: &AVSpeechUtterance) -> usize {
+        utterance as *const AVSpeechUtterance as usize
+    }
+
+    struct Synthesizer {
+        synthesizer: Retained<AVSpeechSynthesizer>,
+        /// The synthesizer only keeps a weak reference.
+        delegate: Retained<Delegate>,
+    }
+
+    struct Dictation {
+        engine: Retained<AVAudioEngine>,
+        request: Retained<SFSpeechAudioBufferRecognitionRequest>,
+        /// Kept until the final transcript, as the task needs its recognizer.
+        _recognizer: Retained<SFSpeechRecognizer>,
+        _task: Retained<SFSpeechRecognitionTask>,
+    }
+
+    thread_local! {
+        static SYNTHESIZER: RefCell<Option<Synthesizer>> = const { RefCell::new(None) };
+        static DICTATION: RefCell<Option<Dictation>> = const { RefCell::new(None) };
+    }
+
+    #[derive(Default)]
+    pub struct Backend;
+
+    fn on_main<T: Send>(work: impl FnOnce() -> T + Send) -> T {
+        let mut result = None;
+        DispatchQueue::main().exec_sync(|| result = Some(work()));
+        result.expect("main queue ran the closure")
+    }
+
+    /// AVSpeech rates run from a minimum through a default to a maximum rather than as
+    /// multiples; a quarter or four times normal reaches either end.
+    fn rate(multiple: f32) -> f32 {
+        // SAFETY: constants.
+        let (minimum, default, maximum) = unsafe {
+            (
+                AVSpeechUtteranceMinimumSpeechRate,
+                AVSpeechUtteranceDefaultSpeechRate,
+                AVSpeechUtteranceMaximumSpeechRate,
+            )
+        };
+        let steps = (multiple.log2() / 2.0).clamp(-1.0, 1.0);
+        if steps >= 0.0 {
+            default + (maximum - default) * steps
+        } else {
+            default + (default - minimum) * steps
+        }
+    }
+
+    async fn authorize_recognition() -> Result<(), SpeechError> {
+        // SAFETY: a class method with no preconditions.
+        let mut status = unsafe { SFSpeechRecognizer::authorizationStatus() };
+        if status == SFSpeechRecognizerAuthorizationStatus::NotDetermined {
+            let (answer, answered) = oneshot::channel();
+            let answer = Mutex::new(Some(answer));
+            {
+                // Dropped before the await, since a block isn't `Send`; the framework
+                // keeps its own copy.
+                let handler = RcBlock::new(move |status: SFSpeechRecognizerAuthorizationStatus| {
+                    if let Some(answer) = answer.lock().unwrap().take() {
+                        let _ = answer.send(status);
+                    }
+                });
+                // SAFETY: the handler may run on any queue, which it's fine with.
+                unsafe { SFSpeechRecognizer::requestAuthorization(&handler) };
+            }
+            status = answered
+                .await
+                .unwrap_or(SFSpeechRecognizerAuthorizationStatus::Denied);
+        }
+        if status == SFSpeechRecognizerAuthorizationStatus::Authorized {
+            Ok(())
+        } else {
+            Err(SpeechError::PermissionDenied("speech recognition".into()))
+        }
+    }
+
+    async fn authorize_microphone() -> Result<(), SpeechError> {
+        // SAFETY: a constant.
+        let audio = unsafe { AVMediaTypeAudio }
+            .ok_or_else(|| SpeechError::Unavailable("no audio media type".into()))?;
+        // SAFETY: class methods with no preconditions; the handler may run on any queue.
+        let granted = match unsafe { AVCaptureDevice::authorizationStatusForMediaType(audio) } {
+            AVAuthorizationStatus::Authorized => true,
+            AVAuthorizationStatus::NotDetermined => {
+                let (answer, answered) = oneshot::channel();
+                let answer = Mutex::new(Some(answer));
+                {
+                    // Dropped before the await, like the recognition handler.
+                    let handler = RcBlock::new(move |granted: Bool| {
+                        if let Some(answer) = answer.lock().unwrap().take() {
+                            let _ = answer.send(granted.as_bool());
+                        }
+                    });
+                    unsafe {
+                        AVCaptureDevice::requestAccessForMediaType_completionHandler(
+                            audio, &handler,
+                        )
+                    };
+                }
+                answered.await.unwrap_or(false)
+            }
+            _ => false,
+        };
+        if granted {
+            Ok(())
+        } else {
+            Err(SpeechError::PermissionDenied("microphone".into()))
+        }
+    }
+
+    /// Stops the microphone; the recognizer goes on to send its final transcript.
+    fn stop_listening() {
+        DICTATION.with(|dictation| {
+            if let Some(dictation) = dictation.borrow().as_ref() {
+                // SAFETY: on the main thread, where dictation was set up.
+                unsafe {
+                    dictation.engine.stop();
+                    dictation.engine.inputNode().removeTapOnBus(0);
+                    dictation.request.endAudio();
+                }
+            }
+        });
+    }
+
+    fn finish_dictation() {
+        stop_listening();
+        DICTATION.with(|dictation| dictation.borrow_mut().take());
+    }
+
+    /// Must run on the main thread.
+    unsafe fn listen(
+        language: Option<&str>,
+        on_transcript: Transcripts,
+    ) -> Result<Dictation, SpeechError> {
+        let recognizer = match language {
+            Some(language) => {
+                let locale = NSLocale::initWithLocaleIdentifier(
+                    NSLocale::alloc(),
+                    &NSString::from_str(language),
+                );
+                SFSpeechRecognizer::initWithLocale(SFSpeechRecognizer::alloc(), &locale)
+            }
+            None => SFSpeechRecognizer::init(SFSpeechRecognizer::alloc()),
+        }
+        .ok_or_else(|| {
+            SpeechError::Unavailable(format!(
+                "no recognizer for {}",
+                language.unwrap_or("the system language")
+            ))
+        })?;
+        if !recognizer.isAvailable() {
+            return Err(SpeechError::Unavailable(
+                "speech recognition isn't available right now".into(),
+            ));
+        }
+
+        let request = SFSpeechAudioBufferRecognitionRequest::new();
+        request.setShouldReportPartialResults(true);
+        let engine = AVAudioEngine::new();
+        let input = engine.inputNode();
+        let format = input.outputFormatForBus(0);
+        let tapped = request.clone();
+        let tap: RcBlock<dyn Fn(NonNull<AVAudioPCMBuffer>, NonNull<AVAudioTime>)> =
+            RcBlock::new(move |buffer: NonNull<AVAudioPCMBuffer>, _when| {
+                tapped.appendAudioPCMBuffer(buffer.as_ref());
+            });
+        input.installTapOnBus_bufferSize_format_block(
+            0,
+            TAP_BUFFER_SIZE,
+            Some(&format),
+            RcBlock::as_ptr(&tap),
+        );
+        engine.prepare();
+        if let Err(e) = engine.startAndReturnError() {
+            input.removeTapOnBus(0);
+            return Err(SpeechError::Failed(e.localizedDescription().to_string()));
+        }
+
+        // The last partial transcript, sent as the final one if recognition fails, and
+        // `None` once the final one is sent.
+        let last = Mutex::new(Some(String::new()));
+        let handler: RcBlock<dyn Fn(*mut SFSpeechRecognitionResult, *mut NSError)> = RcBlock::new(
+            move |result: *mut SFSpeechRecognitionResult, error: *mut NSError| {
+                let mut last = last.lock().unwrap();
+                let Some(previous) = last.as_mut() else {
+                    return;
+                };
+                // SAFETY: the framework passes a valid result or error, or null.
+                let transcript = match unsafe { (result.as_ref(), error.as_ref()) } {
+                    (Some(result), _) => unsafe {
+                        Transcript {
+                            text: result.bestTranscription().formattedString().to_string(),
+                            is_final: result.isFinal(),
+                        }
+                    },
+                    (None, error) => {
+                        if let Some(error) = error {
+                            tracing::warn!(error = %error.localizedDescription(), "dictation failed");
+                        }
+                        Transcript {
+                            text: std::mem::take(previous),
+                            is_final: true,
+                        }
+                    }
+                };
+                if transcript.is_final {
+                    *last = None;
+                    DispatchQueue::main().exec_async(finish_dictation);
+                } else {
+                    previous.clone_from(&transcript.text);
+                }
+                on_transcript(transcript);
+            },
+        );
+        let task = recognizer.recognitionTaskWithRequest_resultHandler(&request, &handler);
+        Ok(Dictation {
+            engine,
+            request,
+            _recognizer: recognizer,
+            _task: task,
+        })
+    }
+
+    impl Backend {
+        pub async fn voices<R: Runtime>(
+            &self,
+            _app: &AppHandle<R>,
+        ) -> Result<Vec<Voice>, SpeechError> {
+            // SAFETY: plain reads of the installed voices.
+            Ok(on_main(|| unsafe {
+                AVSpeechSynthesisVoice::speechVoices()
+                    .iter()
+                    .map(|voice| Voice {
+                        id: voice.identifier().to_string(),
+                        name: voice.name().to_string(),
+                        language: voice.language().to_string(),
+                    })
+                    .collect()
+            }))
+        }
+
+        pub async fn speak<R: Runtime>(
+            &self,
+            app: &AppHandle<R>,
+            utterance: Utterance,
+        ) -> Result<(), SpeechError> {
+            let sink = super::sink(app);
+            // SAFETY: on the main thread, which keeps the synthesizer.
+            on_main(move || unsafe {
+                let voice = match &utterance.voice_id {
+                    Some(id) => Some(
+                        AVSpeechSynthesisVoice::voiceWithIdentifier(&NSString::from_str(id))
+                            .ok_or_else(|| SpeechError::VoiceNotFound(id.clone()))?,
+                    ),
+                    None => None,
+                };
+                let spoken = AVSpeechUtterance::speechUtteranceWithString(&NSString::from_str(
+                    &utterance.text,
+                ));
+                spoken.setVoice(voice.as_deref());
+                spoken.setRate(rate(utterance.rate));
+                spoken.setPitchMultiplier(utterance.pitch);
+                SYNTHESIZER.with(|synthesizer| {
+                    let mut synthesizer = synthesizer.borrow_mut();
+                    let synthesizer = synthesizer.get_or_insert_with(|| {
+                        let synthesizer = AVSpeechSynthesizer::new();
+                        let delegate = Delegate::new(sink);
+                        synthesizer.setDelegate(Some(ProtocolObject::from_ref(&*delegate)));
+                        Synthesizer {
+                            synthesizer,
+                            delegate,
+                        }
+                    });
+                    synthesizer
+                        .synthesizer
+                        .stopSpeakingAtBoundary(AVSpeechBoundary::Immediate);
+                    synthesizer.delegate.track(&spoken, utterance.id);
+                    synthesizer.synthesizer.speakUtterance(&spoken);
+                });
+                Ok(())
+            })
+        }
+
+        pub async fn stop<R: Runtime>(
+            &self,
+            _app: &AppHandle<R>,
+            id: u64,
+        ) -> Result<(), SpeechError> {
+            on_main(move || {
+                SYNTHESIZER.with(|synthesizer| {
+                    if let Some(synthesizer) = synthesizer.borrow().as_ref() {
+                        // One utterance plays at a time, so stopping everything stops it.
+                        if synthesizer.delegate.reading(id) {
+                            // SAFETY: on the main thread, which keeps the synthesizer.
+                            unsafe {
+                                synthesizer
+                                    .synthesizer
+                                    .stopSpeakingAtBoundary(AVSpeechBoundary::Immediate)
+                            };
+                        }
+                    }
+                })
+            });
+            Ok(())
+        }
+
+        pub async fn start_dictation<R: Runtime>(
+            &self,
+            _app: &AppHandle<R>,
+            language: Option<String>,
+            on_transcript: Transcripts,
+        ) -> Result<(), SpeechError> {
+            authorize_recognition().await?;
+            authorize_microphone().await?;
+            on_main(move || {
+                DICTATION.with(|dictation| {
+                    let mut dictation = dictation.borrow_mut();
+                    if dictation.is_some() {
+                        return Err(SpeechError::Busy);
+                    }
+                    // SAFETY: on the main thread, which keeps the dictation.
+                    *dictation = Some(unsafe { listen(language.as_deref(), on_transcript)? });
+                    Ok(())
+                })
+            })
+        }
+
+        pub async fn stop_dictation<R: Runtime>(
+            &self,
+            _app: &AppHandle<R>,
+        ) -> Result<(), SpeechError> {
+            on_main(stop_listening);
+            Ok(())
+        }
+    }
+}
+
+#[cfg(mobile)]
+mod os {
+    use serde::{Deserialize, Serialize};
+    use serde_json::Value;
+    use tauri::ipc::{Channel, InvokeResponseBody};
+    use tauri::plugin::mobile::PluginInvokeError;
+    use tauri::plugin::{PluginHandle, TauriPlugin};
+    use tauri::{AppHandle, Manager, Runtime};
+    // Linked for its native halves; it has no Rust API.
+    use tauri_plugin_speech as _;
+
+    use super::{
+        SpeechError, SpeechEvent, Transcript, Transcripts, Utterance, UtteranceEnd, Voice,
+        WordBoundary,
+    };
+
+    #[cfg(target_os = "ios")]
+    tauri::ios_plugin_binding!(init_plugin_speech);
+
+    struct SpeechPlugin<R: Runtime>(PluginHandle<R>);
+
+    /// The native side tracks its one utterance and dictation itself.
+    #[derive(Default)]
+    pub struct Backend;
+
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    struct SpeakArgs<'a> {
+        id: u64,
+        text: &'a str,
+        voice_id: Option<&'a str>,
+        rate: f32,
+        pitch: f32,
+        on_event: Channel,
+    }
+
+    #[derive(Serialize)]
+    struct StopArgs {
+        id: u64,
+    }
+
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    struct DictationArgs {
+        language: Option<String>,
+        on_transcript: Channel,
+    }
+
+    /// What `onEvent` carries, with offsets in UTF-16 units.
+    #[derive(Deserialize)]
+    #[serde(tag = "type", rename_all = "camelCase")]
+    enum NativeEvent {
+        Boundary { start: u32, length: u32 },
+        End { interrupted: bool },
+    }
+
+    #[derive(Deserialize)]
+    struct VoicesResponse {
+        voices: Vec<Voice>,
+    }
+
+    pub fn plugin<R: Runtime>() -> TauriPlugin<R> {
+        tauri::plugin::Builder::new("speech")
+            .setup(|app, api| {
+                #[cfg(target_os = "android")]
+                let handle = api.register_android_plugin("com.layers.speech", "SpeechPlugin")?;
+                #[cfg(target_os = "ios")]
+                let handle = api.register_ios_plugin(init_plugin_speech)?;
+                app.manage(SpeechPlugin(handle));
+                Ok(())
+            })
+            .build()
+    }
+
+    /// Both native halves reject with the same codes.
+    fn from_plugin(e: PluginInvokeError) -> SpeechError {
+        match e {
+            PluginInvokeError::InvokeRejected(response) => {
+                let message = response.message.unwrap_or_default();
+                match response.code.as_deref() {
+                    Some("PermissionDenied") => SpeechError::PermissionDenied(message),
+                    Some("VoiceNotFound") => SpeechError::VoiceNotFound(message),
+                    Some("InvalidInput") => SpeechError::InvalidInput(message),
+                    Some("Busy") => SpeechError::Busy,
+                    Some("Unavailable") => SpeechError::Unavailable(message),
+                    _ => SpeechError::Failed(message),
+                }
+            }
+            e => SpeechError::Failed(e.to_string()),
+        }
+    }
+
+    fn speech<R: Runtime>(app: &AppHandle<R>) -> Result<PluginHandle<R>, SpeechError> {
+        app.try_state::<SpeechPlugin<R>>()
+            .map(|speech| speech.0.clone())
+            .ok_or_else(|| SpeechError::Failed("speech plugin not loaded".into()))
+    }
+
+    impl Backend {
+        pub async fn voices<R: Runtime>(
+            &self,
+            app: &AppHandle<R>,
+        ) -> Result<Vec<Voice>, SpeechError> {
+            let response: VoicesResponse = speech(app)?
+                .run_mobile_plugin_async("listVoices", ())
+                .await
+                .map_err(from_plugin)?;
+            Ok(response.voices)
+        }
+
+        pub async fn speak<R: Runtime>(
+            &self,
+            app: &AppHandle<R>,
+            utterance: Utterance,
+        ) -> Result<(), SpeechError> {
+            let sink = super::sink(app);
+            let utterance_id = utterance.id;
+            let on_event = Channel::new(move |body| {
+                if let InvokeResponseBody::Json(json) = body {
+                    match serde_json::from_str::<NativeEvent>(&json) {
+                        Ok(NativeEvent::Boundary { start, length }) => {
+                            sink(SpeechEvent::Boundary(WordBoundary {
+                                utterance_id,
+                                char_index: start,
+                                char_length: length,
+                            }))
+                        }
+                        Ok(NativeEvent::End { interrupted }) => {
+                            sink(SpeechEvent::End(UtteranceEnd {
+                                utterance_id,
+                                interrupted,
+                            }))
+                        }
+                        Err(e) => tracing::warn!(error = %e, "unreadable speech event"),
+                    }
+                }
+                Ok(())
+            });
+            let args = SpeakArgs {
+                id: utterance.id,
+                text: &utterance.text,
+                voice_id: utterance.voice_id.as_deref(),
+                rate: utterance.rate,
+                pitch: utterance.pitch,
+                on_event,
+            };
+            speech(app)?
+                .run_mobile_plugin_async::<Value>("speak", args)
+                .await
+                .map(drop)
+                .map_err(from_plugin)
+        }
+
+        pub async fn stop<R: Runtime>(
+            &self,
+            app: &AppHandle<R>,
+            id: u64,
+        ) -> Result<(), SpeechError> {
+            speech(app)?
+                .run_mobile_plugin_async::<Value>("stop", StopArgs { id })
+                .await
+                .map(drop)
+                .map_err(from_plugin)
+        }
+
+        pub async fn start_dictation<R: Runtime>(
+            &self,
+            app: &AppHandle<R>,
+            language: Option<String>,
+            on_transcript: Transcripts,
+        ) -> Result<(), SpeechError> {
+            let channel = Channel::new(move |body| {
+                if let InvokeResponseBody::Json(json) = body {
+                    match serde_json::from_str::<Transcript>(&json) {
+                        Ok(transcript) => on_transcript(transcript),
+                        Err(e) => tracing::warn!(error = %e, "unreadable transcript"),
+                    }
+                }
+                Ok(())
+            });
+            let args = DictationArgs {
+                language,
+                on_transcript: channel,
+            };
+            speech(app)?
+                .run_mobile_plugin_async::<Value>("startDictation", args)
+                .await
+                .map(drop)
+                .map_err(from_plugin)
+        }
+
+        pub async fn stop_dictation<R: Runtime>(
+            &self,
+            app: &AppHandle<R>,
+        ) -> Result<(), SpeechError> {
+            speech(app)?
+                .run_mobile_plugin_async::<Value>("stopDictation", ())
+                .await
+                .map(drop)
+                .map_err(from_plugin)
+        }
+    }
+}
+
+#[cfg(all(desktop, not(any(target_os = "linux", target_os = "macos", windows))))]
+mod os {
+    use tauri::{AppHandle, Runtime};
+
+    use super::{SpeechError, Transcripts, Utterance, Voice};
+
+    #[derive(Default)]
+    pub struct Backend;
+
+    impl Backend {
+        pub async fn voices<R: Runtime>(
+            &self,
+            _app: &AppHandle<R>,
+        ) -> Result<Vec<Voice>, SpeechError> {
+            Err(SpeechError::Unsupported)
+        }
+
+        pub async fn speak<R: Runtime>(
+            &self,
+            _app: &AppHandle<R>,
+            _utterance: Utterance,
+        ) -> Result<(), SpeechError> {
+            Err(SpeechError::Unsupported)
+        }
+
+        pub async fn stop<R: Runtime>(
+            &self,
+            _app: &AppHandle<R>,
+            _id: u64,
+        ) -> Result<(), SpeechError> {
+            Err(SpeechError::Unsupported)
+        }
+
+        pub async fn start_dictation<R: Runtime>(
+            &self,
+            _app: &AppHandle<R>,
+            _language: Option<String>,
+            _on_transcript: Transcripts,
+        ) -> Result<(), SpeechError> {
+            Err(SpeechError::Unsupported)
+        }
+
+        pub async fn stop_dictation<R: Runtime>(
+            &self,
+            _app: &AppHandle<R>,
+        ) -> Result<(), SpeechError> {
+            Err(SpeechError::Unsupported)
+        }
+    }
+}
+
+#[cfg(mobile)]
+pub fn plugin<R: Runtime>() -> tauri::plugin::TauriPlugin<R> {
+    os::plugin()
+}
+
+#[tauri::command]
+pub async fn list_voices<R: Runtime>(
+    app: AppHandle<R>,
+    speech: State<'_, Speech>,
+) -> Result<Vec<Voice>, SpeechError> {
+    speech.backend.voices(&app).await
+}
+
+/// Starts reading `text` aloud, cutting off anything still being read, and returns the id
+/// [`BOUNDARY_EVENT`] and [`END_EVENT`] carry for it.
+#[tauri::command]
+pub async fn speak<R: Runtime>(
+    text: String,
+    options: Option<SpeakOptions>,
+    app: AppHandle<R>,
+    speech: State<'_, Speech>,
+) -> Result<u64, SpeechError> {
+    let options = options.unwrap_or_default();
+    if text.trim().is_empty() {
+        return Err(SpeechError::InvalidInput("there's nothing to say".into()));
+    }
+    let rate = options.rate.unwrap_or(1.0);
+    if !RATE.contains(&rate) {
+        return Err(SpeechError::InvalidInput(format!(
+            "the rate must be {} to {} times normal",
+            RATE.start(),
+            RATE.end()
+        )));
+    }
+    let pitch = options.pitch.unwrap_or(1.0);
+    if !PITCH.contains(&pitch) {
+        return Err(SpeechError::InvalidInput(format!(
+            "the pitch must be {} to {} times normal",
+            PITCH.start(),
+            PITCH.end()
+        )));
+    }
+    let id = speech.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+    let utterance = Utterance {
+        id,
+        text,
+        voice_id: options.voice_id,
+        rate,
+        pitch,
+    };
+    speech.backend.speak(&app, utterance).await?;
+    Ok(id)
+}
+
+/// Stops `id` early, and it ends as interrupted; an id that already ended is ignored.
+#[tauri::command]
+pub async fn stop_speaking<R: Runtime>(
+    id: u64,
+    app: AppHandle<R>,
+    speech: State<'_, Speech>,
+) -> Result<(), SpeechError> {
+    speech.backend.stop(&app, id).await
+}
+
+/// Listens for speech in `language` (a BCP 47 tag, the system's by default) and sends
+/// transcripts to `on_transcript` as they form, the last with `isFinal` set. It ends at
+/// [`stop_dictation`], or on its own after a pause or an error.
+#[tauri::command]
+pub async fn start_dictation<R: Runtime>(
+    language: Option<String>,
+    on_transcript: Channel<Transcript>,
+    app: AppHandle<R>,
+    speech: State<'_, Speech>,
+) -> Result<(), SpeechError> {
+    let on_transcript: Transcripts = Arc::new(move |transcript| {
+        let _ = on_transcript.send(transcript);
+    });
+    speech
+        .backend
+        .start_dictation(&app, language, on_transcript)
+        .await
+}
+
+/// Stops listening; the final transcript follows once the recognizer catches up.
+#[tauri::command]
+pub async fn stop_dictation<R: Runtime>(
+    app: AppHandle<R>,
+    speech: State<'_, Speech>,
+) -> Result<(), SpeechError> {
+    speech.backend.stop_dictation(&app).await
+}
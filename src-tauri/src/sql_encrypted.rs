@@ -0,0 +1,248 @@
+//! Databases encrypted with SQLCipher, each behind its own pool and addressed by a
+//! handle. The key never leaves this module; the pool holds it in its connect options.
+//! SQLCipher is only linked with `--features db-encryption`; other builds answer every
+//! open with [`EncryptedDbError::CipherUnavailable`].
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
+use sqlx::{ConnectOptions, Connection};
+use tauri::{AppHandle, Runtime, State};
+
+use crate::db;
+use crate::scope;
+
+/// SQLite's result code when a page doesn't decrypt to a valid database.
+const SQLITE_NOTADB: &str = "26";
+
+#[derive(Debug, thiserror::Error, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "camelCase")]
+pub enum EncryptedDbError {
+    #[error("wrong passphrase, or not an encrypted database")]
+    WrongPassphrase,
+    /// Linked against plain SQLite, where `PRAGMA key` is silently ignored.
+    #[error("SQLCipher is not available in this build")]
+    CipherUnavailable,
+    #[error("no open database with handle {0}")]
+    NotFound(String),
+    #[error("invalid input: {0}")]
+    InvalidInput(String),
+    #[error("{0}")]
+    Scope(String),
+    #[error("{0}")]
+    Io(String),
+    #[error("{0}")]
+    Sql(String),
+}
+
+impl From<sqlx::Error> for EncryptedDbError {
+    fn from(e: sqlx::Error) -> Self {
+        if let sqlx::Error::Io(e) = &e {
+            return Self::Io(e.to_string());
+        }
+        let not_a_db = e
+            .as_database_error()
+            .and_then(|e| e.code())
+            .is_some_and(|code| code == SQLITE_NOTADB);
+        if not_a_db {
+            Self::WrongPassphrase
+        } else {
+            Self::Sql(e.to_string())
+        }
+    }
+}
+
+impl From<db::DbError> for EncryptedDbError {
+    fn from(e: db::DbError) -> Self {
+        Self::Io(e.to_string())
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExecuteResult {
+    pub rows_affected: u64,
+    pub last_insert_id: i64,
+}
+
+struct EncryptedDb {
+    path: PathBuf,
+    pool: SqlitePool,
+}
+
+/// Open encrypted databases by handle.
+#[derive(Default)]
+pub struct EncryptedDbs {
+    open: Mutex<HashMap<String, EncryptedDb>>,
+}
+
+impl EncryptedDbs {
+    fn pool(&self, handle: &str) -> Result<SqlitePool, EncryptedDbError> {
+        self.open
+            .lock()
+            .unwrap()
+            .get(handle)
+            .map(|db| db.pool.clone())
+            .ok_or_else(|| EncryptedDbError::NotFound(handle.to_string()))
+    }
+}
+
+/// A string literal for a `PRAGMA key`-style statement, which can't take parameters.
+fn quote(passphrase: &str) -> Result<String, EncryptedDbError> {
+    if passphrase.is_empty() {
+        // An empty key means "no encryption" to SQLCipher.
+        return Err(EncryptedDbError::InvalidInput(
+            "passphrase must not be empty".into(),
+        ));
+    }
+    Ok(format!("'{}'", passphrase.replace('\'', "''")))
+}
+
+/// sqlx always issues `key` before any other pragma, as SQLCipher requires.
+fn connect_options(
+    path: &Path,
+    passphrase: &str,
+) -> Result<SqliteConnectOptions, EncryptedDbError> {
+    Ok(SqliteConnectOptions::new()
+        .filename(path)
+        .create_if_missing(true)
+        .pragma("key", quote(passphrase)?))
+}
+
+/// Proves the key is right by reading the schema, which fails with `SQLITE_NOTADB`
+/// otherwise, and that SQLCipher is really the library in use.
+async fn verify(options: &SqliteConnectOptions) -> Result<(), EncryptedDbError> {
+    let mut conn = options.connect().await?;
+    let cipher: Option<(String,)> = sqlx::query_as("PRAGMA cipher_version")
+        .fetch_optional(&mut conn)
+        .await?;
+    if cipher.is_none() {
+        return Err(EncryptedDbError::CipherUnavailable);
+    }
+    sqlx::query("SELECT count(*) FROM sqlite_master")
+        .execute(&mut conn)
+        .await?;
+    conn.close().await?;
+    Ok(())
+}
+
+/// Opens or creates the encrypted database at `path` and returns a handle for the
+/// other commands here. Relative paths resolve like the sql plugin's.
+#[tauri::command]
+pub async fn open_encrypted_db<R: Runtime>(
+    path: String,
+    passphrase: String,
+    app: AppHandle<R>,
+    dbs: State<'_, EncryptedDbs>,
+) -> Result<String, EncryptedDbError> {
+    let path = db::resolve_db_path(&app, &path)?;
+    let path = scope::ensure_allowed(&app, &path).map_err(EncryptedDbError::Scope)?;
+    let options = connect_options(&path, &passphrase)?;
+    verify(&options).await?;
+
+    let pool = SqlitePoolOptions::new().connect_lazy_with(options);
+    let handle = uuid::Uuid::new_v4().to_string();
+    dbs.open
+        .lock()
+        .unwrap()
+        .insert(handle.clone(), EncryptedDb { path, pool });
+    Ok(handle)
+}
+
+#[tauri::command]
+pub async fn close_encrypted_db(
+    handle: String,
+    dbs: State<'_, EncryptedDbs>,
+) -> Result<(), EncryptedDbError> {
+    let db = dbs
+        .open
+        .lock()
+        .unwrap()
+        .remove(&handle)
+        .ok_or(EncryptedDbError::NotFound(handle))?;
+    db.pool.close().await;
+    Ok(())
+}
+
+/// Runs a statement and returns its rows as JSON objects.
+#[tauri::command]
+pub async fn encrypted_query(
+    handle: String,
+    sql: String,
+    params: Vec<Value>,
+    dbs: State<'_, EncryptedDbs>,
+) -> Result<Vec<Value>, EncryptedDbError> {
+    let pool = dbs.pool(&handle)?;
+    let mut query = sqlx::query(&sql);
+    for param in &params {
+        query = db::bind_value(query, param);
+    }
+    let rows = query.fetch_all(&pool).await?;
+    Ok(rows.iter().map(db::row_to_json).collect::<Result<_, _>>()?)
+}
+
+#[tauri::command]
+pub async fn encrypted_execute(
+    handle: String,
+    sql: String,
+    params: Vec<Value>,
+    dbs: State<'_, EncryptedDbs>,
+) -> Result<ExecuteResult, EncryptedDbError> {
+    let pool = dbs.pool(&handle)?;
+    let mut query = sqlx::query(&sql);
+    for param in &params {
+        query = db::bind_value(query, param);
+    }
+    let result = query.execute(&pool).await?;
+    Ok(ExecuteResult {
+        rows_affected: result.rows_affected(),
+        last_insert_id: result.last_insert_rowid(),
+    })
+}
+
+/// Re-encrypts the database under `new` with `PRAGMA rekey`. The pool is closed first,
+/// since rekeying needs the only connection, and reopened with whichever key is in
+/// effect afterwards.
+#[tauri::command]
+pub async fn change_passphrase(
+    handle: String,
+    old: String,
+    new: String,
+    dbs: State<'_, EncryptedDbs>,
+) -> Result<(), EncryptedDbError> {
+    let (path, pool) = {
+        let open = dbs.open.lock().unwrap();
+        let db = open
+            .get(&handle)
+            .ok_or_else(|| EncryptedDbError::NotFound(handle.clone()))?;
+        (db.path.clone(), db.pool.clone())
+    };
+    let old_options = connect_options(&path, &old)?;
+    let rekey = format!("PRAGMA rekey = {}", quote(&new)?);
+    verify(&old_options).await?;
+
+    pool.close().await;
+    let rekeyed = async {
+        let mut conn = old_options.connect().await?;
+        sqlx::query(&rekey).execute(&mut conn).await?;
+        conn.close().await
+    }
+    .await;
+    let options = match &rekeyed {
+        Ok(()) => connect_options(&path, &new)?,
+        Err(e) => {
+            tracing::warn!(error = %e, "rekey failed; keeping the old passphrase");
+            old_options
+        }
+    };
+
+    let pool = SqlitePoolOptions::new().connect_lazy_with(options);
+    if let Some(db) = dbs.open.lock().unwrap().get_mut(&handle) {
+        db.pool = pool;
+    }
+    Ok(rekeyed?)
+}
@@ -0,0 +1,159 @@
+//! Streams query results to the frontend in chunks, for result sets too large to
+//! return from `tauri_plugin_sql` in one IPC message.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use futures::TryStreamExt;
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::SqlitePool;
+use tauri::{AppHandle, Emitter, Manager, Runtime, State};
+use tauri_plugin_sql::{DbInstances, DbPool};
+
+use crate::db::{self, Db, DbError};
+
+pub const CHUNK_EVENT: &str = "sql://chunk";
+
+#[derive(Debug, thiserror::Error, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "camelCase")]
+pub enum SqlStreamError {
+    /// The frontend hasn't called `Database.load` for this path.
+    #[error("database is not loaded: {0}")]
+    NotLoaded(String),
+    #[error("no active stream with id {0}")]
+    NotFound(String),
+    #[error(transparent)]
+    Db(#[from] DbError),
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Chunk {
+    stream_id: String,
+    rows: Vec<Value>,
+    /// Set on the last chunk, including after an error or cancellation.
+    done: bool,
+    error: Option<String>,
+}
+
+/// Cancellation flags of running streams, keyed by stream ID.
+#[derive(Default)]
+pub struct SqlStreams {
+    active: Mutex<HashMap<String, Arc<AtomicBool>>>,
+}
+
+/// The sql plugin's pool for `db_path`, so streamed queries see the same connection
+/// settings as the frontend's. Our own pool serves the default database as well.
+async fn pool<R: Runtime>(app: &AppHandle<R>, db_path: &str) -> Result<SqlitePool, SqlStreamError> {
+    let key = if db_path.starts_with("sqlite:") {
+        db_path.to_string()
+    } else {
+        format!("sqlite:{db_path}")
+    };
+    if let Some(instances) = app.try_state::<DbInstances>() {
+        if let Some(DbPool::Sqlite(pool)) = instances.0.read().await.get(&key) {
+            return Ok(pool.clone());
+        }
+    }
+
+    let path = db::resolve_db_path(app, db_path)?;
+    if path == db::resolve_db_path(app, db::DEFAULT_DB)? {
+        return Ok(app.state::<Db>().pool()?.clone());
+    }
+    Err(SqlStreamError::NotLoaded(db_path.to_string()))
+}
+
+async fn run<R: Runtime>(
+    app: &AppHandle<R>,
+    pool: &SqlitePool,
+    stream_id: &str,
+    sql: &str,
+    params: &[Value],
+    chunk_size: usize,
+    cancelled: &AtomicBool,
+) -> Result<Vec<Value>, sqlx::Error> {
+    let mut query = sqlx::query(sql);
+    for param in params {
+        query = db::bind_value(query, param);
+    }
+
+    let mut rows = query.fetch(pool);
+    let mut chunk = Vec::with_capacity(chunk_size);
+    while let Some(row) = rows.try_next().await? {
+        if cancelled.load(Ordering::Relaxed) {
+            return Ok(Vec::new());
+        }
+        chunk.push(db::row_to_json(&row)?);
+        if chunk.len() == chunk_size {
+            let rows = std::mem::replace(&mut chunk, Vec::with_capacity(chunk_size));
+            let _ = app.emit(
+                CHUNK_EVENT,
+                Chunk {
+                    stream_id: stream_id.to_string(),
+                    rows,
+                    done: false,
+                    error: None,
+                },
+            );
+        }
+    }
+    Ok(chunk)
+}
+
+/// Starts `sql` and returns a stream ID at once. Rows arrive as [`CHUNK_EVENT`]s of up
+/// to `chunk_size` rows each, the last one marked `done`.
+#[tauri::command]
+pub async fn stream_query<R: Runtime>(
+    db_path: String,
+    sql: String,
+    params: Vec<Value>,
+    chunk_size: u32,
+    app: AppHandle<R>,
+) -> Result<String, SqlStreamError> {
+    let pool = pool(&app, &db_path).await?;
+    let stream_id = uuid::Uuid::new_v4().to_string();
+    let cancelled = Arc::new(AtomicBool::new(false));
+    app.state::<SqlStreams>()
+        .active
+        .lock()
+        .unwrap()
+        .insert(stream_id.clone(), cancelled.clone());
+
+    let id = stream_id.clone();
+    tauri::async_runtime::spawn(async move {
+        let chunk_size = chunk_size.max(1) as usize;
+        let result = run(&app, &pool, &id, &sql, &params, chunk_size, &cancelled).await;
+        if let Err(e) = &result {
+            tracing::warn!(stream_id = %id, error = %e, "sql stream failed");
+        }
+        app.state::<SqlStreams>().active.lock().unwrap().remove(&id);
+        let error = result.as_ref().err().map(ToString::to_string);
+        let _ = app.emit(
+            CHUNK_EVENT,
+            Chunk {
+                stream_id: id,
+                rows: result.unwrap_or_default(),
+                done: true,
+                error,
+            },
+        );
+    });
+    Ok(stream_id)
+}
+
+/// Stops a stream before its next row; it still sends a final empty `done` chunk.
+#[tauri::command]
+pub fn cancel_stream(
+    stream_id: String,
+    streams: State<'_, SqlStreams>,
+) -> Result<(), SqlStreamError> {
+    match streams.active.lock().unwrap().get(&stream_id) {
+        Some(cancelled) => {
+            cancelled.store(true, Ordering::Relaxed);
+            Ok(())
+        }
+        None => Err(SqlStreamError::NotFound(stream_id)),
+    }
+}
@@ -0,0 +1,200 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use serde_json::Value as JsonValue;
+use tauri::ipc::Invoke;
+use tauri::plugin::Plugin;
+use tauri::webview::{PageLoadEvent, PageLoadPayload};
+use tauri::{AppHandle, Builder, Emitter, Manager, RunEvent, Runtime, State, Url, Webview, Window};
+use tauri_runtime::webview::InitializationScript;
+
+/// Event emitted once the main window finishes its first page load.
+pub const READY_EVENT: &str = "startup://ready";
+
+/// Time spent constructing a single plugin and running its setup hook.
+#[derive(Debug, Clone, Serialize)]
+pub struct PluginTiming {
+    pub name: &'static str,
+    pub duration_ms: f64,
+}
+
+/// Startup milestones, all measured from the moment `run()` started building the app.
+#[derive(Debug, Clone, Serialize)]
+pub struct StartupMetrics {
+    pub plugins: Vec<PluginTiming>,
+    /// Sum of all individual plugin timings; 0 until the plugins have run their setup.
+    pub plugin_init_duration_ms: u64,
+    /// Time until the setup hook finished; 0 until it has.
+    pub setup_duration_ms: u64,
+    pub first_page_load_ms: Option<f64>,
+}
+
+/// Records startup timings and is kept in managed state for the lifetime of the app.
+pub struct StartupTimer {
+    start: Instant,
+    /// Filled in as each plugin is constructed and then set up, which happens while the
+    /// app is being built, after the timer has been handed over.
+    plugins: Arc<Mutex<Vec<(&'static str, Duration)>>>,
+    setup_complete: Option<Duration>,
+    first_page_load: Option<Duration>,
+}
+
+impl StartupTimer {
+    pub fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            plugins: Arc::default(),
+            setup_complete: None,
+            first_page_load: None,
+        }
+    }
+
+    /// Constructs a plugin and registers it on the builder, recording how long it took to
+    /// construct and, once the app is built, to set up.
+    pub fn plugin<R, P, F>(
+        &mut self,
+        builder: Builder<R>,
+        name: &'static str,
+        init: F,
+    ) -> Builder<R>
+    where
+        R: Runtime,
+        P: Plugin<R> + 'static,
+        F: FnOnce() -> P,
+    {
+        let started = Instant::now();
+        let inner = init();
+        let constructed = started.elapsed();
+        builder.plugin(Timed {
+            inner,
+            name,
+            constructed,
+            plugins: self.plugins.clone(),
+        })
+    }
+
+    pub fn mark_setup_complete(&mut self) {
+        self.setup_complete = Some(self.start.elapsed());
+    }
+
+    /// Records the first page load and returns `true` only the first time it is called.
+    fn mark_first_page_load(&mut self) -> bool {
+        if self.first_page_load.is_some() {
+            return false;
+        }
+        self.first_page_load = Some(self.start.elapsed());
+        true
+    }
+
+    pub fn metrics(&self) -> StartupMetrics {
+        let plugins = self.plugins.lock().unwrap();
+        let plugin_init: Duration = plugins.iter().map(|(_, d)| *d).sum();
+        StartupMetrics {
+            plugins: plugins
+                .iter()
+                .map(|(name, duration)| PluginTiming {
+                    name,
+                    duration_ms: as_ms(*duration),
+                })
+                .collect(),
+            plugin_init_duration_ms: plugin_init.as_millis() as u64,
+            setup_duration_ms: self.setup_complete.map_or(0, |d| d.as_millis() as u64),
+            first_page_load_ms: self.first_page_load.map(as_ms),
+        }
+    }
+}
+
+impl Default for StartupTimer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Wraps a plugin to time its setup hook, which Tauri runs from `initialize` while the
+/// app is built. Everything else goes straight to the plugin.
+struct Timed<P> {
+    inner: P,
+    name: &'static str,
+    constructed: Duration,
+    plugins: Arc<Mutex<Vec<(&'static str, Duration)>>>,
+}
+
+impl<R: Runtime, P: Plugin<R>> Plugin<R> for Timed<P> {
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    fn initialize(
+        &mut self,
+        app: &AppHandle<R>,
+        config: JsonValue,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let started = Instant::now();
+        let result = self.inner.initialize(app, config);
+        self.plugins
+            .lock()
+            .unwrap()
+            .push((self.name, self.constructed + started.elapsed()));
+        result
+    }
+
+    fn initialization_script(&self) -> Option<String> {
+        self.inner.initialization_script()
+    }
+
+    fn initialization_script_2(&self) -> Option<InitializationScript> {
+        self.inner.initialization_script_2()
+    }
+
+    fn window_created(&mut self, window: Window<R>) {
+        self.inner.window_created(window)
+    }
+
+    fn webview_created(&mut self, webview: Webview<R>) {
+        self.inner.webview_created(webview)
+    }
+
+    fn on_navigation(&mut self, webview: &Webview<R>, url: &Url) -> bool {
+        self.inner.on_navigation(webview, url)
+    }
+
+    fn on_page_load(&mut self, webview: &Webview<R>, payload: &PageLoadPayload<'_>) {
+        self.inner.on_page_load(webview, payload)
+    }
+
+    fn on_event(&mut self, app: &AppHandle<R>, event: &RunEvent) {
+        self.inner.on_event(app, event)
+    }
+
+    fn extend_api(&mut self, invoke: Invoke<R>) -> bool {
+        self.inner.extend_api(invoke)
+    }
+}
+
+fn as_ms(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+/// Page load hook: emits [`READY_EVENT`] the first time the main window finishes loading.
+pub fn on_page_load<R: Runtime>(webview: &Webview<R>, payload: &PageLoadPayload<'_>) {
+    if payload.event() != PageLoadEvent::Finished || webview.label() != "main" {
+        return;
+    }
+
+    let state = webview.state::<Mutex<StartupTimer>>();
+    let metrics = {
+        let mut timer = state.lock().unwrap();
+        if !timer.mark_first_page_load() {
+            return;
+        }
+        timer.metrics()
+    };
+
+    let _ = webview.app_handle().emit(READY_EVENT, metrics);
+}
+
+#[tauri::command]
+pub fn get_startup_metrics(state: State<'_, Mutex<StartupTimer>>) -> StartupMetrics {
+    state.lock().unwrap().metrics()
+}
@@ -0,0 +1,448 @@
+//! An outbox for offline-first edits. Mutations are queued in SQLite and replayed in
+//! order to the configured endpoint by a background task, which pauses while
+//! [`Connectivity`] reports the device offline and backs off exponentially on failure.
+//!
+//! Each mutation is POSTed as `{ id, entity, op, payload, force }`, with `id` repeated in
+//! an `Idempotency-Key` header so a retry after a lost response is safe. The server
+//! answers `2xx` once it has applied the mutation, or `409` with its current version as
+//! the body. Conflicts are parked until [`resolve_conflict`] and don't hold up the rest;
+//! any other failure stops the replay at that mutation, keeping later ones in order.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sqlx::SqlitePool;
+use tauri::{AppHandle, Emitter, Manager, Runtime, State};
+use tauri_plugin_http::reqwest;
+use tokio::sync::Notify;
+use uuid::Uuid;
+
+use crate::connectivity::Connectivity;
+use crate::db::{Db, DbError};
+use crate::http_config::{self, HttpResponse};
+use crate::http_middleware::{HttpExchange, HttpMiddleware, InterceptableRequest};
+
+pub const STATUS_EVENT: &str = "sync://status";
+pub const CONFLICT_EVENT: &str = "sync://conflict";
+
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(5 * 60);
+const REQUEST_TIMEOUT_MS: u64 = 30_000;
+
+#[derive(Debug, thiserror::Error, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "camelCase")]
+pub enum SyncError {
+    #[error("no sync endpoint is configured")]
+    NotConfigured,
+    #[error("invalid sync endpoint: {0}")]
+    InvalidEndpoint(String),
+    #[error("no conflicted mutation with id {0}")]
+    NotFound(Uuid),
+    #[error("{0}")]
+    Db(String),
+}
+
+impl From<DbError> for SyncError {
+    fn from(e: DbError) -> Self {
+        Self::Db(e.to_string())
+    }
+}
+
+impl From<sqlx::Error> for SyncError {
+    fn from(e: sqlx::Error) -> Self {
+        Self::Db(e.to_string())
+    }
+}
+
+/// Kept in memory only, since `headers` typically carries credentials; the frontend
+/// sets it once signed in, and nothing is sent until then.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncConfig {
+    pub endpoint: Option<String>,
+    /// Sent with every request, e.g. `Authorization`.
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SyncState {
+    #[default]
+    Idle,
+    Syncing,
+    /// The last attempt failed; the engine retries with backoff.
+    Error,
+    Offline,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct SyncStatus {
+    pub state: SyncState,
+    /// Why the last attempt failed, in [`SyncState::Error`].
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SyncConflict {
+    pub id: Uuid,
+    pub entity: String,
+    pub op: String,
+    /// The queued payload.
+    pub local: Value,
+    /// The server's version, from the `409` body; a string if it wasn't JSON.
+    pub remote: Value,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum ConflictResolution {
+    /// Resend the queued payload with `force` set, asking the server to overwrite.
+    KeepLocal,
+    /// Drop the mutation.
+    KeepRemote,
+    /// Resend with `payload` instead, also with `force` set.
+    Merged { payload: Value },
+}
+
+/// State of the outbox task started by [`spawn`].
+#[derive(Default)]
+pub struct SyncEngine {
+    client: reqwest::Client,
+    config: Mutex<SyncConfig>,
+    status: Mutex<SyncStatus>,
+    /// Something was queued; only looked at while idle.
+    queued: Notify,
+    /// `sync_now` or a config change; also cuts a backoff short.
+    retry: Notify,
+}
+
+type Row = (String, String, String, String, bool);
+
+struct Mutation {
+    id: String,
+    entity: String,
+    op: String,
+    payload: Value,
+    force: bool,
+}
+
+fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or_default()
+}
+
+fn backoff(failures: u32) -> Duration {
+    INITIAL_BACKOFF
+        .saturating_mul(1 << failures.saturating_sub(1).min(16))
+        .min(MAX_BACKOFF)
+}
+
+impl SyncEngine {
+    fn set_status<R: Runtime>(&self, app: &AppHandle<R>, state: SyncState, error: Option<String>) {
+        let next = SyncStatus { state, error };
+        let previous = std::mem::replace(&mut *self.status.lock().unwrap(), next.clone());
+        if previous != next {
+            let _ = app.emit(STATUS_EVENT, next);
+        }
+    }
+
+    /// Replays pending mutations until none are left or one fails.
+    async fn drain<R: Runtime>(&self, app: &AppHandle<R>, pool: &SqlitePool) -> Result<(), String> {
+        loop {
+            let config = self.config.lock().unwrap().clone();
+            let Some(endpoint) = config.endpoint else {
+                return Ok(());
+            };
+            let Some(mutation) = next_pending(pool).await.map_err(|e| e.to_string())? else {
+                return Ok(());
+            };
+            self.set_status(app, SyncState::Syncing, None);
+
+            let result = self.send(app, &endpoint, config.headers, &mutation).await;
+            let failure = match result {
+                Ok(response) if (200..300).contains(&response.status) => {
+                    sqlx::query("DELETE FROM mutations WHERE id = ?")
+                        .bind(&mutation.id)
+                        .execute(pool)
+                        .await
+                        .map_err(|e| e.to_string())?;
+                    continue;
+                }
+                Ok(response) if response.status == 409 => {
+                    park(app, pool, mutation, response.body)
+                        .await
+                        .map_err(|e| e.to_string())?;
+                    continue;
+                }
+                Ok(response) => format!("server answered {}", response.status),
+                Err(e) => e.to_string(),
+            };
+            sqlx::query(
+                "UPDATE mutations SET attempts = attempts + 1, last_error = ? WHERE id = ?",
+            )
+            .bind(&failure)
+            .bind(&mutation.id)
+            .execute(pool)
+            .await
+            .map_err(|e| e.to_string())?;
+            return Err(failure);
+        }
+    }
+
+    /// POSTs one mutation through the [`HttpMiddleware`] chain.
+    async fn send<R: Runtime>(
+        &self,
+        app: &AppHandle<R>,
+        endpoint: &str,
+        mut headers: HashMap<String, String>,
+        mutation: &Mutation,
+    ) -> Result<HttpResponse, http_config::HttpError> {
+        let middleware = app.state::<HttpMiddleware>();
+        headers.insert("Content-Type".into(), "application/json".into());
+        headers.insert("Idempotency-Key".into(), mutation.id.clone());
+        let body = json!({
+            "id": mutation.id,
+            "entity": mutation.entity,
+            "op": mutation.op,
+            "payload": mutation.payload,
+            "force": mutation.force,
+        });
+        let mut req = InterceptableRequest {
+            id: middleware.next_id(),
+            method: "POST".into(),
+            url: endpoint.to_string(),
+            headers,
+            body: Some(body.to_string()),
+        };
+
+        let started = Instant::now();
+        let mock = middleware.intercept(&mut req).await;
+        let mocked = mock.is_some();
+        let result = match mock {
+            Some(mock) => Ok(HttpResponse {
+                status: mock.status,
+                headers: mock.headers,
+                body: mock.body,
+            }),
+            None => match http_config::build_request(&self.client, &req, Some(REQUEST_TIMEOUT_MS))?
+                .send()
+                .await
+            {
+                Ok(response) => http_config::read_response(response).await,
+                Err(e) => Err(http_config::request_error(e)),
+            },
+        };
+        middleware.completed(&HttpExchange {
+            request: &req,
+            status: result.as_ref().ok().map(|response| response.status),
+            error: result.as_ref().err().map(ToString::to_string),
+            mocked,
+            elapsed: started.elapsed(),
+        });
+        result
+    }
+}
+
+/// Starts the background task, which waits for migrations before replaying.
+pub fn spawn<R: Runtime>(app: &AppHandle<R>) {
+    app.manage(SyncEngine::default());
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let db = app.state::<Db>();
+        let Ok(pool) = db.ready_pool().await else {
+            return;
+        };
+        let engine = app.state::<SyncEngine>();
+        let mut online = app.state::<Connectivity>().watch_online();
+        let mut failures = 0;
+
+        loop {
+            if !*online.borrow_and_update() {
+                engine.set_status(&app, SyncState::Offline, None);
+                if online.wait_for(|online| *online).await.is_err() {
+                    return;
+                }
+                continue;
+            }
+
+            match engine.drain(&app, pool).await {
+                Ok(()) => {
+                    failures = 0;
+                    engine.set_status(&app, SyncState::Idle, None);
+                    tokio::select! {
+                        _ = engine.queued.notified() => {}
+                        _ = engine.retry.notified() => {}
+                        _ = online.changed() => {}
+                    }
+                }
+                Err(e) => {
+                    failures += 1;
+                    tracing::warn!(error = %e, failures, "sync failed");
+                    engine.set_status(&app, SyncState::Error, Some(e));
+                    tokio::select! {
+                        _ = tokio::time::sleep(backoff(failures)) => {}
+                        _ = engine.retry.notified() => failures = 0,
+                        _ = online.changed() => {}
+                    }
+                }
+            }
+        }
+    });
+}
+
+async fn next_pending(pool: &SqlitePool) -> Result<Option<Mutation>, sqlx::Error> {
+    let row: Option<Row> = sqlx::query_as(
+        "SELECT id, entity, op, payload, force FROM mutations
+         WHERE status = 'pending' ORDER BY seq LIMIT 1",
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.map(|(id, entity, op, payload, force)| Mutation {
+        id,
+        entity,
+        op,
+        payload: serde_json::from_str(&payload).unwrap_or(Value::String(payload)),
+        force,
+    }))
+}
+
+/// Sets a `409`ed mutation aside with the server's version and reports both.
+async fn park<R: Runtime>(
+    app: &AppHandle<R>,
+    pool: &SqlitePool,
+    mutation: Mutation,
+    remote: String,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE mutations SET status = 'conflict', remote = ? WHERE id = ?")
+        .bind(&remote)
+        .bind(&mutation.id)
+        .execute(pool)
+        .await?;
+    let Ok(id) = Uuid::parse_str(&mutation.id) else {
+        return Ok(());
+    };
+    let _ = app.emit(
+        CONFLICT_EVENT,
+        SyncConflict {
+            id,
+            entity: mutation.entity,
+            op: mutation.op,
+            local: mutation.payload,
+            remote: serde_json::from_str(&remote).unwrap_or(Value::String(remote)),
+        },
+    );
+    Ok(())
+}
+
+/// Adds a mutation to the outbox and returns its id, which the server sees as the
+/// idempotency key.
+#[tauri::command]
+pub async fn queue_mutation(
+    entity: String,
+    op: String,
+    payload: Value,
+    db: State<'_, Db>,
+    engine: State<'_, SyncEngine>,
+) -> Result<Uuid, SyncError> {
+    let id = Uuid::new_v4();
+    sqlx::query(
+        "INSERT INTO mutations (id, entity, op, payload, created_ms) VALUES (?, ?, ?, ?, ?)",
+    )
+    .bind(id.to_string())
+    .bind(&entity)
+    .bind(&op)
+    .bind(payload.to_string())
+    .bind(now_ms())
+    .execute(db.pool()?)
+    .await?;
+    engine.queued.notify_one();
+    Ok(id)
+}
+
+/// Replays the outbox now, skipping any backoff in progress. Does nothing while offline.
+#[tauri::command]
+pub fn sync_now(engine: State<'_, SyncEngine>) -> Result<(), SyncError> {
+    if engine.config.lock().unwrap().endpoint.is_none() {
+        return Err(SyncError::NotConfigured);
+    }
+    engine.retry.notify_one();
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn resolve_conflict(
+    id: Uuid,
+    resolution: ConflictResolution,
+    db: State<'_, Db>,
+    engine: State<'_, SyncEngine>,
+) -> Result<(), SyncError> {
+    let pool = db.pool()?;
+    let requeue = "UPDATE mutations
+                   SET status = 'pending', force = 1, remote = NULL, attempts = 0,
+                       last_error = NULL, payload = COALESCE(?, payload)
+                   WHERE id = ? AND status = 'conflict'";
+    let result = match resolution {
+        ConflictResolution::KeepRemote => {
+            sqlx::query("DELETE FROM mutations WHERE id = ? AND status = 'conflict'")
+                .bind(id.to_string())
+                .execute(pool)
+                .await?
+        }
+        ConflictResolution::KeepLocal => {
+            sqlx::query(requeue)
+                .bind(None::<String>)
+                .bind(id.to_string())
+                .execute(pool)
+                .await?
+        }
+        ConflictResolution::Merged { payload } => {
+            sqlx::query(requeue)
+                .bind(payload.to_string())
+                .bind(id.to_string())
+                .execute(pool)
+                .await?
+        }
+    };
+    if result.rows_affected() == 0 {
+        return Err(SyncError::NotFound(id));
+    }
+    engine.queued.notify_one();
+    Ok(())
+}
+
+/// Mutations not yet accepted by the server, conflicted ones included.
+#[tauri::command]
+pub async fn get_pending_count(db: State<'_, Db>) -> Result<u64, SyncError> {
+    let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM mutations")
+        .fetch_one(db.pool()?)
+        .await?;
+    Ok(count as u64)
+}
+
+#[tauri::command]
+pub fn get_sync_status(engine: State<'_, SyncEngine>) -> SyncStatus {
+    engine.status.lock().unwrap().clone()
+}
+
+#[tauri::command]
+pub fn get_sync_config(engine: State<'_, SyncEngine>) -> SyncConfig {
+    engine.config.lock().unwrap().clone()
+}
+
+/// Replaces the config and retries straight away; `endpoint: null` pauses syncing.
+#[tauri::command]
+pub fn set_sync_config(config: SyncConfig, engine: State<'_, SyncEngine>) -> Result<(), SyncError> {
+    if let Some(endpoint) = &config.endpoint {
+        reqwest::Url::parse(endpoint).map_err(|e| SyncError::InvalidEndpoint(e.to_string()))?;
+    }
+    *engine.config.lock().unwrap() = config;
+    engine.retry.notify_one();
+    Ok(())
+}
@@ -0,0 +1,631 @@
+//! Long-running background work with progress and cancellation. Each task gets a
+//! [`CancellationToken`] and reports to the frontend over its own [`Channel`], while
+//! [`list_tasks`] shows running tasks and ones that finished in the last ten minutes.
+//!
+//! Tasks wait for one of the `task_concurrency` slots in the settings, or downloads for
+//! one of the `download_concurrency` ones, and move from queued to running to completed,
+//! cancelled or failed. Changing either setting resizes its slots as tasks run.
+//! Cancelling drops a running task's future at once; work handed to a blocking thread
+//! also checks the token between files, so it winds down within a file's worth of IO.
+//!
+//! [`enqueue_task`] is the older way in: the same tasks, reported on the app-wide
+//! [`PROGRESS_EVENT`], [`COMPLETE_EVENT`] and [`ERROR_EVENT`] instead of a channel.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tauri::ipc::Channel;
+use tauri::{AppHandle, Emitter, Listener, Manager, Runtime, State};
+use tokio::sync::{oneshot, Semaphore};
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
+
+use crate::db::{self, Db};
+use crate::net;
+use crate::scope;
+use crate::settings::{self, Settings};
+
+pub const PROGRESS_EVENT: &str = "task://progress";
+pub const COMPLETE_EVENT: &str = "task://complete";
+pub const ERROR_EVENT: &str = "task://error";
+
+/// How long finished tasks stay in [`list_tasks`].
+const HISTORY: Duration = Duration::from_secs(10 * 60);
+
+pub type TaskId = Uuid;
+
+#[derive(Debug, thiserror::Error, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "camelCase")]
+pub enum TaskError {
+    #[error("no task with id {0}")]
+    NotFound(TaskId),
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TaskKind {
+    /// `{ "sql": "...", "params": [...] }` against the shared database.
+    SqlQuery,
+    /// `{ "path": "..." }`; counts files and bytes below a directory.
+    FileScan,
+    /// `{ "url": "..." }`; downloads the body as text.
+    HttpFetch,
+    /// The kinds below are started by their own commands, which take care of what
+    /// `params` would hold, and can't be passed to [`spawn_task`].
+    #[serde(skip_deserializing)]
+    Export,
+    #[serde(skip_deserializing)]
+    Download,
+    #[serde(skip_deserializing)]
+    PrewarmThumbnails,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "state", rename_all = "camelCase")]
+pub enum TaskState {
+    Queued,
+    Running,
+    Completed,
+    Cancelled,
+    Failed { error: String },
+}
+
+impl TaskState {
+    fn is_finished(&self) -> bool {
+        !matches!(self, Self::Queued | Self::Running)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskInfo {
+    pub id: TaskId,
+    pub kind: TaskKind,
+    #[serde(flatten)]
+    pub state: TaskState,
+    /// Percent done, where the task can tell.
+    pub progress: f32,
+    /// Unix milliseconds; `None` while queued.
+    pub started_at: Option<u64>,
+    pub finished_at: Option<u64>,
+}
+
+/// What [`enqueue_task`] called a task's kind.
+pub type TaskType = TaskKind;
+
+/// Sent over the channel passed to [`spawn_task`]. Exactly one of the last three ends
+/// the stream.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "camelCase")]
+pub enum TaskEvent {
+    Started,
+    Progress { percent: f32, message: String },
+    Completed { result: Value },
+    Cancelled,
+    Failed { error: String },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskProgress {
+    pub id: TaskId,
+    pub percent: f32,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskComplete {
+    pub id: TaskId,
+    pub result: Value,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskFailed {
+    pub id: TaskId,
+    pub error: String,
+}
+
+/// Where a task's [`TaskEvent`]s go.
+#[derive(Clone)]
+pub(crate) enum Sink {
+    Channel(Channel<TaskEvent>),
+    /// The `task://` events [`enqueue_task`] has always emitted.
+    Events,
+    /// Tasks started from Rust, whose callers report in their own way.
+    None,
+}
+
+struct Entry {
+    info: TaskInfo,
+    token: CancellationToken,
+    finished: Option<Instant>,
+}
+
+/// A semaphore whose number of permits follows a setting.
+struct Slots {
+    semaphore: Arc<Semaphore>,
+    size: Mutex<usize>,
+}
+
+impl Slots {
+    fn new(size: u8) -> Self {
+        Self {
+            semaphore: Arc::new(Semaphore::new(size as usize)),
+            size: Mutex::new(size as usize),
+        }
+    }
+
+    /// Growing frees the new slots at once. Shrinking takes back idle permits, and the
+    /// rest as running tasks return them, so nothing already running is stopped.
+    fn resize(&self, size: u8) {
+        let size = size as usize;
+        let mut current = self.size.lock().unwrap();
+        if size > *current {
+            self.semaphore.add_permits(size - *current);
+        } else if size < *current {
+            let excess = *current - size;
+            let owed = excess - self.semaphore.forget_permits(excess);
+            if owed > 0 {
+                let semaphore = self.semaphore.clone();
+                tauri::async_runtime::spawn(async move {
+                    if let Ok(permits) = semaphore.acquire_many_owned(owed as u32).await {
+                        permits.forget();
+                    }
+                });
+            }
+        }
+        *current = size;
+    }
+}
+
+/// Every task still running or within [`HISTORY`] of finishing.
+pub struct Tasks {
+    entries: Mutex<HashMap<TaskId, Entry>>,
+    slots: Slots,
+    /// Downloads wait for `download_concurrency` slots of their own instead.
+    download_slots: Slots,
+}
+
+impl Tasks {
+    fn new(settings: &Settings) -> Self {
+        Self {
+            entries: Default::default(),
+            slots: Slots::new(settings.task_concurrency),
+            download_slots: Slots::new(settings.download_concurrency),
+        }
+    }
+
+    fn slots(&self, kind: TaskKind) -> Arc<Semaphore> {
+        match kind {
+            TaskKind::Download => self.download_slots.semaphore.clone(),
+            _ => self.slots.semaphore.clone(),
+        }
+    }
+
+    /// Cancels a queued or running task, returning whether there was one.
+    pub(crate) fn cancel(&self, id: TaskId) -> bool {
+        let entries = self.entries.lock().unwrap();
+        match entries.get(&id) {
+            Some(entry) if !entry.info.state.is_finished() => {
+                entry.token.cancel();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn update(&self, id: TaskId, update: impl FnOnce(&mut TaskInfo)) {
+        if let Some(entry) = self.entries.lock().unwrap().get_mut(&id) {
+            update(&mut entry.info);
+        }
+    }
+
+    fn finish(&self, id: TaskId, state: TaskState) {
+        if let Some(entry) = self.entries.lock().unwrap().get_mut(&id) {
+            entry.info.state = state;
+            entry.info.finished_at = Some(now_ms());
+            entry.finished = Some(Instant::now());
+        }
+    }
+
+    fn prune(entries: &mut HashMap<TaskId, Entry>) {
+        entries.retain(|_, entry| entry.finished.is_none_or(|at| at.elapsed() < HISTORY));
+    }
+}
+
+/// Manages [`Tasks`], sized from the settings and resized whenever they change. Call
+/// after `settings::init`.
+pub fn init<R: Runtime>(app: &AppHandle<R>) {
+    app.manage(Tasks::new(&settings::current(app)));
+    let handle = app.clone();
+    // The payload isn't trusted, since a webview can emit the same event; the settings
+    // themselves are.
+    app.listen_any(settings::CHANGED_EVENT, move |_| {
+        let settings = settings::current(&handle);
+        let tasks = handle.state::<Tasks>();
+        tasks.slots.resize(settings.task_concurrency);
+        tasks.download_slots.resize(settings.download_concurrency);
+    });
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+/// Handed to each task body; cheap to clone into a blocking thread.
+pub(crate) struct Reporter<R: Runtime> {
+    app: AppHandle<R>,
+    id: TaskId,
+    token: CancellationToken,
+    sink: Sink,
+}
+
+// Derived `Clone` would need `R: Clone`.
+impl<R: Runtime> Clone for Reporter<R> {
+    fn clone(&self) -> Self {
+        Self {
+            app: self.app.clone(),
+            id: self.id,
+            token: self.token.clone(),
+            sink: self.sink.clone(),
+        }
+    }
+}
+
+impl<R: Runtime> Reporter<R> {
+    pub(crate) fn app(&self) -> &AppHandle<R> {
+        &self.app
+    }
+
+    pub(crate) fn progress(&self, percent: f32, message: impl Into<String>) {
+        self.app
+            .state::<Tasks>()
+            .update(self.id, |info| info.progress = percent);
+        self.send(TaskEvent::Progress {
+            percent,
+            message: message.into(),
+        });
+    }
+
+    pub(crate) fn is_cancelled(&self) -> bool {
+        self.token.is_cancelled()
+    }
+
+    /// For work on a blocking thread, which a dropped future can't stop.
+    fn check_cancelled(&self) -> Result<(), String> {
+        if self.is_cancelled() {
+            return Err("cancelled".into());
+        }
+        Ok(())
+    }
+
+    /// Runs `work` until it finishes or the task is cancelled, dropping it in the
+    /// latter case. Only for work that leaves nothing behind when dropped.
+    async fn until_cancelled<T>(
+        &self,
+        work: impl Future<Output = Result<T, String>>,
+    ) -> Result<T, String> {
+        tokio::select! {
+            result = work => result,
+            _ = self.token.cancelled() => Err("cancelled".into()),
+        }
+    }
+
+    fn send(&self, event: TaskEvent) {
+        let id = self.id;
+        match &self.sink {
+            Sink::Channel(channel) => {
+                let _ = channel.send(event);
+            }
+            Sink::Events => {
+                let _ = match event {
+                    TaskEvent::Started => self.app.emit(
+                        PROGRESS_EVENT,
+                        TaskProgress {
+                            id,
+                            percent: 0.0,
+                            message: "started".into(),
+                        },
+                    ),
+                    TaskEvent::Progress { percent, message } => self.app.emit(
+                        PROGRESS_EVENT,
+                        TaskProgress {
+                            id,
+                            percent,
+                            message,
+                        },
+                    ),
+                    TaskEvent::Completed { result } => {
+                        self.app.emit(COMPLETE_EVENT, TaskComplete { id, result })
+                    }
+                    TaskEvent::Cancelled => self.app.emit(
+                        ERROR_EVENT,
+                        TaskFailed {
+                            id,
+                            error: "cancelled".into(),
+                        },
+                    ),
+                    TaskEvent::Failed { error } => {
+                        self.app.emit(ERROR_EVENT, TaskFailed { id, error })
+                    }
+                };
+            }
+            Sink::None => {}
+        }
+    }
+}
+
+fn payload_str<'a>(payload: &'a Value, key: &str) -> Result<&'a str, String> {
+    payload
+        .get(key)
+        .and_then(Value::as_str)
+        .ok_or_else(|| format!("payload is missing string field `{key}`"))
+}
+
+async fn run<R: Runtime>(
+    kind: TaskKind,
+    params: Value,
+    reporter: &Reporter<R>,
+) -> Result<Value, String> {
+    match kind {
+        TaskKind::SqlQuery => sql_query(&params, reporter).await,
+        TaskKind::FileScan => file_scan(&params, reporter).await,
+        TaskKind::HttpFetch => http_fetch(&params, reporter).await,
+        TaskKind::Export | TaskKind::Download | TaskKind::PrewarmThumbnails => {
+            Err(format!("{kind:?} tasks can't be spawned directly"))
+        }
+    }
+}
+
+async fn sql_query<R: Runtime>(params: &Value, reporter: &Reporter<R>) -> Result<Value, String> {
+    let sql = payload_str(params, "sql")?;
+
+    let mut query = sqlx::query(sql);
+    if let Some(params) = params.get("params").and_then(Value::as_array) {
+        for param in params {
+            query = db::bind_value(query, param);
+        }
+    }
+
+    let pool = reporter.app.state::<Db>();
+    let rows = query
+        .fetch_all(pool.pool().map_err(|e| e.to_string())?)
+        .await
+        .map_err(|e| e.to_string())?;
+    reporter.progress(50.0, format!("fetched {} rows", rows.len()));
+
+    let rows = rows
+        .iter()
+        .map(db::row_to_json)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    reporter.progress(100.0, "done");
+    Ok(Value::Array(rows))
+}
+
+async fn file_scan<R: Runtime>(params: &Value, reporter: &Reporter<R>) -> Result<Value, String> {
+    let root = PathBuf::from(payload_str(params, "path")?);
+    let root = scope::ensure_allowed(&reporter.app, &root)?;
+    let reporter = reporter.clone();
+    tauri::async_runtime::spawn_blocking(move || scan_dir(&root, &reporter))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+fn scan_dir<R: Runtime>(root: &Path, reporter: &Reporter<R>) -> Result<Value, String> {
+    // Progress is reported per top-level entry, which is known up front.
+    let entries = std::fs::read_dir(root)
+        .map_err(|e| e.to_string())?
+        .filter_map(Result::ok)
+        .collect::<Vec<_>>();
+    let (mut files, mut bytes) = (0u64, 0u64);
+
+    for (i, entry) in entries.iter().enumerate() {
+        let mut stack = vec![entry.path()];
+        while let Some(path) = stack.pop() {
+            reporter.check_cancelled()?;
+            let Ok(metadata) = std::fs::symlink_metadata(&path) else {
+                continue;
+            };
+            if metadata.is_dir() {
+                if let Ok(children) = std::fs::read_dir(&path) {
+                    stack.extend(children.filter_map(Result::ok).map(|c| c.path()));
+                }
+            } else {
+                files += 1;
+                bytes += metadata.len();
+            }
+        }
+
+        let percent = (i + 1) as f32 / entries.len() as f32 * 100.0;
+        reporter.progress(percent, format!("{files} files scanned"));
+    }
+
+    Ok(json!({ "files": files, "bytes": bytes }))
+}
+
+async fn http_fetch<R: Runtime>(params: &Value, reporter: &Reporter<R>) -> Result<Value, String> {
+    let url = payload_str(params, "url")?;
+    let app = reporter.app();
+    net::ensure_allowed(app, url).map_err(|e| e.to_string())?;
+    let mut response = net::send(app, net::get(app, url), None)
+        .await
+        .map_err(|e| e.to_string())?;
+    let status = response.status();
+    let total = response.content_length();
+
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await.map_err(|e| e.to_string())? {
+        body.extend_from_slice(&chunk);
+        if let Some(total) = total.filter(|t| *t > 0) {
+            let percent = body.len() as f32 / total as f32 * 100.0;
+            reporter.progress(percent, format!("{} of {total} bytes", body.len()));
+        }
+    }
+
+    Ok(json!({
+        "status": status,
+        "body": String::from_utf8_lossy(&body),
+    }))
+}
+
+/// Queues `body` as a task of `kind`, listed and cancellable like any other, and
+/// returns its id with a receiver for what the body returned. The receiver closes
+/// without a value if the task is cancelled before it gets a slot.
+///
+/// The body is never dropped part way; it should check [`Reporter::is_cancelled`] as
+/// it goes, or hand work that's safe to drop to [`Reporter::until_cancelled`].
+pub(crate) fn submit<R, T, E, F, Fut>(
+    app: &AppHandle<R>,
+    kind: TaskKind,
+    sink: Sink,
+    body: F,
+) -> (TaskId, oneshot::Receiver<Result<T, E>>)
+where
+    R: Runtime,
+    T: Serialize + Send + 'static,
+    E: ToString + Send + 'static,
+    F: FnOnce(Reporter<R>) -> Fut + Send + 'static,
+    Fut: Future<Output = Result<T, E>> + Send + 'static,
+{
+    let id = Uuid::new_v4();
+    let token = CancellationToken::new();
+    let tasks = app.state::<Tasks>();
+    {
+        let mut entries = tasks.entries.lock().unwrap();
+        Tasks::prune(&mut entries);
+        entries.insert(
+            id,
+            Entry {
+                info: TaskInfo {
+                    id,
+                    kind,
+                    state: TaskState::Queued,
+                    progress: 0.0,
+                    started_at: None,
+                    finished_at: None,
+                },
+                token: token.clone(),
+                finished: None,
+            },
+        );
+    }
+
+    let slots = tasks.slots(kind);
+    let reporter = Reporter {
+        app: app.clone(),
+        id,
+        token: token.clone(),
+        sink,
+    };
+    let (sender, receiver) = oneshot::channel();
+    tauri::async_runtime::spawn(async move {
+        let tasks = reporter.app.state::<Tasks>();
+        let _permit = tokio::select! {
+            permit = slots.acquire_owned() => match permit {
+                Ok(permit) => permit,
+                Err(_) => return,
+            },
+            _ = token.cancelled() => {
+                tasks.finish(id, TaskState::Cancelled);
+                reporter.send(TaskEvent::Cancelled);
+                return;
+            }
+        };
+
+        tasks.update(id, |info| {
+            info.state = TaskState::Running;
+            info.started_at = Some(now_ms());
+        });
+        reporter.send(TaskEvent::Started);
+        let result = body(reporter.clone()).await;
+
+        let (state, event) = match &result {
+            _ if token.is_cancelled() => (TaskState::Cancelled, TaskEvent::Cancelled),
+            Ok(value) => (
+                TaskState::Completed,
+                TaskEvent::Completed {
+                    result: serde_json::to_value(value).unwrap_or_default(),
+                },
+            ),
+            Err(error) => {
+                let error = error.to_string();
+                (
+                    TaskState::Failed {
+                        error: error.clone(),
+                    },
+                    TaskEvent::Failed { error },
+                )
+            }
+        };
+        tasks.finish(id, state);
+        reporter.send(event);
+        let _ = sender.send(result);
+    });
+    (id, receiver)
+}
+
+/// The built-in kinds, which hold nothing that needs undoing when dropped.
+fn submit_builtin<R: Runtime>(
+    app: &AppHandle<R>,
+    kind: TaskKind,
+    params: Value,
+    sink: Sink,
+) -> TaskId {
+    let (id, _) = submit(app, kind, sink, move |reporter| async move {
+        reporter.until_cancelled(run(kind, params, &reporter)).await
+    });
+    id
+}
+
+/// Queues a task and returns its id at once; `on_event` hears when it starts, how it's
+/// getting on, and how it ended.
+#[tauri::command]
+pub fn spawn_task<R: Runtime>(
+    kind: TaskKind,
+    params: Value,
+    on_event: Channel<TaskEvent>,
+    app: AppHandle<R>,
+) -> TaskId {
+    submit_builtin(&app, kind, params, Sink::Channel(on_event))
+}
+
+/// [`spawn_task`] for frontends written before it, reporting on [`PROGRESS_EVENT`],
+/// [`COMPLETE_EVENT`] and [`ERROR_EVENT`]. A cancelled task ends with an error of
+/// `"cancelled"`.
+#[tauri::command]
+pub fn enqueue_task<R: Runtime>(
+    task_type: TaskType,
+    payload: Value,
+    app: AppHandle<R>,
+) -> Result<TaskId, String> {
+    Ok(submit_builtin(&app, task_type, payload, Sink::Events))
+}
+
+/// Cancels a queued or running task. Finished tasks are left as they ended.
+#[tauri::command]
+pub fn cancel_task(id: TaskId, tasks: State<'_, Tasks>) -> Result<(), TaskError> {
+    let entries = tasks.entries.lock().unwrap();
+    let entry = entries.get(&id).ok_or(TaskError::NotFound(id))?;
+    if !entry.info.state.is_finished() {
+        entry.token.cancel();
+    }
+    Ok(())
+}
+
+/// Oldest first.
+#[tauri::command]
+pub fn list_tasks(tasks: State<'_, Tasks>) -> Vec<TaskInfo> {
+    let mut entries = tasks.entries.lock().unwrap();
+    Tasks::prune(&mut entries);
+    let mut list: Vec<TaskInfo> = entries.values().map(|entry| entry.info.clone()).collect();
+    list.sort_by_key(|info| (info.started_at.unwrap_or(u64::MAX), info.id));
+    list
+}
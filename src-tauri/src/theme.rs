@@ -0,0 +1,397 @@
+use std::sync::Mutex;
+#[cfg(target_os = "linux")]
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::plugin::TauriPlugin;
+use tauri::{AppHandle, Emitter, Manager, Runtime, Theme, Window, WindowEvent};
+
+pub const CHANGED_EVENT: &str = "theme://changed";
+
+const UNKNOWN: &str = "unknown";
+/// Linux has no portable change notification short of D-Bus, so settings are re-read
+/// this often.
+#[cfg(target_os = "linux")]
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// OS appearance settings the frontend can't get from CSS media queries alone.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SystemTheme {
+    /// `"light"` or `"dark"`.
+    pub mode: &'static str,
+    /// `#rrggbb`, where the platform has an accent color.
+    pub accent: Option<String>,
+    pub high_contrast: bool,
+    pub reduced_motion: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ThemeChanged {
+    /// Same as `mode`, kept for listeners that predate the other fields.
+    pub theme: &'static str,
+    #[serde(flatten)]
+    pub system: SystemTheme,
+}
+
+impl From<SystemTheme> for ThemeChanged {
+    fn from(system: SystemTheme) -> Self {
+        Self {
+            theme: system.mode,
+            system,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum WindowTheme {
+    Light,
+    Dark,
+    System,
+}
+
+/// The last settings reported to the frontend. Every window gets its own
+/// `ThemeChanged`, so this keeps one OS switch from emitting once per window.
+#[derive(Default)]
+pub struct CurrentTheme(Mutex<Option<SystemTheme>>);
+
+fn name(theme: Theme) -> &'static str {
+    match theme {
+        Theme::Dark => "dark",
+        Theme::Light => "light",
+        _ => UNKNOWN,
+    }
+}
+
+/// What the OS reports directly; `dark` is `None` where only the window theme is known.
+#[derive(Default)]
+struct Native {
+    dark: Option<bool>,
+    accent: Option<String>,
+    high_contrast: bool,
+    reduced_motion: bool,
+}
+
+#[cfg(target_os = "linux")]
+mod native {
+    use std::process::Command;
+
+    use super::Native;
+
+    /// Reads a GNOME setting; other desktops mostly mirror these keys or lack them.
+    fn gsettings(schema: &str, key: &str) -> Option<String> {
+        let output = Command::new("gsettings")
+            .args(["get", schema, key])
+            .output()
+            .ok()
+            .filter(|output| output.status.success())?;
+        let value = String::from_utf8_lossy(&output.stdout);
+        Some(value.trim().trim_matches('\'').to_string())
+    }
+
+    /// GNOME 47 accent names, with libadwaita's colors.
+    fn accent_hex(name: &str) -> Option<&'static str> {
+        Some(match name {
+            "blue" => "#3584e4",
+            "teal" => "#2190a4",
+            "green" => "#3a944a",
+            "yellow" => "#c88800",
+            "orange" => "#ed5b00",
+            "red" => "#e62d42",
+            "pink" => "#d56199",
+            "purple" => "#9141ac",
+            "slate" => "#6f8396",
+            _ => return None,
+        })
+    }
+
+    pub fn detect() -> Native {
+        const INTERFACE: &str = "org.gnome.desktop.interface";
+        let gtk_theme = gsettings(INTERFACE, "gtk-theme");
+        let dark = match gsettings(INTERFACE, "color-scheme").as_deref() {
+            Some("prefer-dark") => Some(true),
+            Some("prefer-light") => Some(false),
+            _ => gtk_theme.as_deref().map(|theme| theme.ends_with("-dark")),
+        };
+        let high_contrast = gsettings("org.gnome.desktop.a11y.interface", "high-contrast")
+            .as_deref()
+            == Some("true")
+            || gtk_theme.is_some_and(|theme| theme.contains("HighContrast"));
+        Native {
+            dark,
+            accent: gsettings(INTERFACE, "accent-color")
+                .as_deref()
+                .and_then(accent_hex)
+                .map(str::to_string),
+            high_contrast,
+            reduced_motion: gsettings(INTERFACE, "enable-animations").as_deref() == Some("false"),
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod native {
+    use std::process::Command;
+
+    use super::Native;
+
+    fn defaults(domain: &str, key: &str) -> Option<String> {
+        let output = Command::new("defaults")
+            .args(["read", domain, key])
+            .output()
+            .ok()
+            .filter(|output| output.status.success())?;
+        Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    /// `AppleAccentColor` values; it's unset for the default multicolor (blue) accent.
+    fn accent_hex(value: Option<&str>) -> &'static str {
+        match value {
+            Some("-1") => "#8c8c8c",
+            Some("0") => "#ff5257",
+            Some("1") => "#f7821b",
+            Some("2") => "#ffc600",
+            Some("3") => "#62ba46",
+            Some("5") => "#a550a7",
+            Some("6") => "#f74f9e",
+            _ => "#007aff",
+        }
+    }
+
+    pub fn detect() -> Native {
+        const ACCESSIBILITY: &str = "com.apple.universalaccess";
+        Native {
+            dark: Some(defaults("-g", "AppleInterfaceStyle").as_deref() == Some("Dark")),
+            accent: Some(accent_hex(defaults("-g", "AppleAccentColor").as_deref()).to_string()),
+            high_contrast: defaults(ACCESSIBILITY, "increaseContrast").as_deref() == Some("1"),
+            reduced_motion: defaults(ACCESSIBILITY, "reduceMotion").as_deref() == Some("1"),
+        }
+    }
+}
+
+#[cfg(windows)]
+mod native {
+    use std::ffi::c_void;
+    use std::mem::size_of;
+    use std::ptr::null_mut;
+
+    use windows_sys::Win32::System::Registry::{RegGetValueW, HKEY_CURRENT_USER, RRF_RT_REG_DWORD};
+    use windows_sys::Win32::UI::Accessibility::{HCF_HIGHCONTRASTON, HIGHCONTRASTW};
+    use windows_sys::Win32::UI::WindowsAndMessaging::{
+        SystemParametersInfoW, SPI_GETCLIENTAREAANIMATION, SPI_GETHIGHCONTRAST,
+    };
+
+    use super::Native;
+
+    fn wide(s: &str) -> Vec<u16> {
+        s.encode_utf16().chain(Some(0)).collect()
+    }
+
+    fn read_dword(key: &str, value: &str) -> Option<u32> {
+        let (key, value) = (wide(key), wide(value));
+        let mut data = 0u32;
+        let mut size = size_of::<u32>() as u32;
+        // SAFETY: both strings are NUL-terminated and `data` is a DWORD-sized buffer
+        // whose size is passed in `size`.
+        let status = unsafe {
+            RegGetValueW(
+                HKEY_CURRENT_USER,
+                key.as_ptr(),
+                value.as_ptr(),
+                RRF_RT_REG_DWORD,
+                null_mut(),
+                (&mut data as *mut u32).cast::<c_void>(),
+                &mut size,
+            )
+        };
+        (status == 0).then_some(data)
+    }
+
+    fn high_contrast() -> bool {
+        let mut info = HIGHCONTRASTW {
+            cbSize: size_of::<HIGHCONTRASTW>() as u32,
+            dwFlags: 0,
+            lpszDefaultScheme: null_mut(),
+        };
+        // SAFETY: SPI_GETHIGHCONTRAST fills a HIGHCONTRASTW whose size is in `cbSize`.
+        let ok = unsafe {
+            SystemParametersInfoW(
+                SPI_GETHIGHCONTRAST,
+                info.cbSize,
+                (&mut info as *mut HIGHCONTRASTW).cast::<c_void>(),
+                0,
+            )
+        };
+        ok != 0 && info.dwFlags & HCF_HIGHCONTRASTON != 0
+    }
+
+    fn animations_enabled() -> bool {
+        let mut enabled = 1i32;
+        // SAFETY: SPI_GETCLIENTAREAANIMATION writes a BOOL.
+        let ok = unsafe {
+            SystemParametersInfoW(
+                SPI_GETCLIENTAREAANIMATION,
+                0,
+                (&mut enabled as *mut i32).cast::<c_void>(),
+                0,
+            )
+        };
+        ok == 0 || enabled != 0
+    }
+
+    pub fn detect() -> Native {
+        let light = read_dword(
+            r"Software\Microsoft\Windows\CurrentVersion\Themes\Personalize",
+            "AppsUseLightTheme",
+        );
+        // Stored as 0xAABBGGRR.
+        let accent = read_dword(r"Software\Microsoft\Windows\DWM", "AccentColor").map(|abgr| {
+            let [r, g, b, _] = abgr.to_le_bytes();
+            format!("#{r:02x}{g:02x}{b:02x}")
+        });
+        Native {
+            dark: light.map(|light| light == 0),
+            accent,
+            high_contrast: high_contrast(),
+            reduced_motion: !animations_enabled(),
+        }
+    }
+}
+
+/// Elsewhere, including mobile where the webview follows the system settings itself,
+/// only the mode is known, from the window.
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+mod native {
+    use super::Native;
+
+    pub fn detect() -> Native {
+        Native::default()
+    }
+}
+
+impl Native {
+    fn into_theme(self, dark: bool) -> SystemTheme {
+        SystemTheme {
+            mode: if dark { "dark" } else { "light" },
+            accent: self.accent,
+            high_contrast: self.high_contrast,
+            reduced_motion: self.reduced_motion,
+        }
+    }
+}
+
+fn system_theme<R: Runtime>(app: &AppHandle<R>) -> SystemTheme {
+    let native = native::detect();
+    let dark = native.dark.unwrap_or_else(|| {
+        app.get_webview_window("main")
+            .and_then(|window| window.theme().ok())
+            .is_some_and(|theme| theme == Theme::Dark)
+    });
+    native.into_theme(dark)
+}
+
+/// Exposes the settings at startup as `window.__SYSTEM_THEME__` before any page script
+/// runs, so the first paint already uses them; `mode` is `null` where only a window can
+/// tell. Windows opened later get the same snapshot, and `theme://changed` brings them
+/// up to date.
+pub fn plugin<R: Runtime>() -> TauriPlugin<R> {
+    let native = native::detect();
+    let dark = native.dark;
+    let mut theme = serde_json::to_value(native.into_theme(dark == Some(true))).unwrap_or_default();
+    if dark.is_none() {
+        theme["mode"] = Value::Null;
+    }
+    tauri::plugin::Builder::new("system-theme")
+        .js_init_script(format!("window.__SYSTEM_THEME__ = {theme};"))
+        .build()
+}
+
+/// Emits [`CHANGED_EVENT`] if anything differs from what was last reported.
+fn refresh<R: Runtime>(app: &AppHandle<R>) {
+    let Some(current) = app.try_state::<CurrentTheme>() else {
+        return;
+    };
+    let theme = system_theme(app);
+    let previous = current.0.lock().unwrap().replace(theme.clone());
+    if previous.as_ref() != Some(&theme) {
+        let _ = app.emit(CHANGED_EVENT, ThemeChanged::from(theme));
+    }
+}
+
+/// Records the settings the app starts with, and on Linux starts polling for changes.
+pub fn init<R: Runtime>(app: &AppHandle<R>) {
+    let initial = system_theme(app);
+    tracing::debug!(theme = ?initial, "initial system theme");
+    app.manage(CurrentTheme(Mutex::new(Some(initial))));
+
+    #[cfg(target_os = "linux")]
+    {
+        let app = app.clone();
+        std::thread::spawn(move || loop {
+            std::thread::sleep(POLL_INTERVAL);
+            refresh(&app);
+        });
+    }
+}
+
+/// A theme switch is reported through the window; accent and accessibility changes
+/// aren't, so they're picked up when the app regains focus, typically on return from
+/// the system settings.
+pub fn on_window_event<R: Runtime>(window: &Window<R>, event: &WindowEvent) {
+    if matches!(
+        event,
+        WindowEvent::ThemeChanged(_) | WindowEvent::Focused(true)
+    ) {
+        refresh(window.app_handle());
+    }
+}
+
+/// `"dark"` or `"light"`, or `"unknown"` where the platform can't report it.
+#[tauri::command]
+pub fn get_current_theme<R: Runtime>(app: AppHandle<R>) -> String {
+    app.get_webview_window("main")
+        .and_then(|window| window.theme().ok())
+        .map(name)
+        .or_else(|| {
+            let current = app.state::<CurrentTheme>();
+            let current = current.0.lock().unwrap();
+            current.as_ref().map(|theme| theme.mode)
+        })
+        .unwrap_or(UNKNOWN)
+        .to_string()
+}
+
+#[tauri::command]
+pub fn get_system_theme<R: Runtime>(app: AppHandle<R>) -> SystemTheme {
+    system_theme(&app)
+}
+
+/// Forces a window light or dark, or back to following the system. On Linux and macOS
+/// the theme is app-wide, so this affects every window there; it's unsupported on
+/// mobile.
+#[tauri::command]
+pub fn set_window_theme<R: Runtime>(
+    label: String,
+    theme: WindowTheme,
+    app: AppHandle<R>,
+) -> Result<(), String> {
+    let window = app
+        .get_webview_window(&label)
+        .ok_or_else(|| format!("no window labelled `{label}`"))?;
+    let forced = match theme {
+        WindowTheme::Light => Some(Theme::Light),
+        WindowTheme::Dark => Some(Theme::Dark),
+        WindowTheme::System => None,
+    };
+    window.set_theme(forced).map_err(|e| e.to_string())?;
+
+    // Tell the page what it now looks like, since the OS setting didn't change.
+    let mut shown = system_theme(&app);
+    if let Some(forced) = forced {
+        shown.mode = name(forced);
+    }
+    window
+        .emit_to(&label, CHANGED_EVENT, ThemeChanged::from(shown))
+        .map_err(|e| e.to_string())
+}
@@ -0,0 +1,379 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use image::metadata::Orientation;
+use image::{DynamicImage, ImageDecoder, ImageError, ImageFormat, ImageReader};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tauri::{AppHandle, Emitter, Manager, Runtime};
+
+use crate::scope;
+use crate::tasks::{self, Sink, TaskKind};
+
+pub const PROGRESS_EVENT: &str = "thumbnail://progress";
+
+/// Thumbnails live here, named `<content sha256>_<max_dim>.<format>`.
+const CACHE_DIR: &str = "thumbs";
+const DEFAULT_MAX_DIM: u32 = 256;
+
+/// `ftyp` brands of HEIF files, which `image` can't decode.
+const HEIF_BRANDS: &[&[u8; 4]] = &[
+    b"heic", b"heix", b"hevc", b"hevx", b"heim", b"heis", b"mif1", b"msf1",
+];
+
+#[derive(Debug, thiserror::Error, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "camelCase")]
+pub enum ThumbnailError {
+    #[error("path is not allowed: {0}")]
+    NotAllowed(String),
+    #[error("unsupported image format: {0}")]
+    Unsupported(String),
+    #[error("could not decode image: {0}")]
+    Decode(String),
+    #[error("i/o error: {0}")]
+    Io(String),
+    #[error("prewarming was cancelled")]
+    Cancelled,
+}
+
+impl From<io::Error> for ThumbnailError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e.to_string())
+    }
+}
+
+impl From<ImageError> for ThumbnailError {
+    fn from(e: ImageError) -> Self {
+        match e {
+            ImageError::Unsupported(e) => Self::Unsupported(e.to_string()),
+            ImageError::IoError(e) => Self::Io(e.to_string()),
+            e => Self::Decode(e.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ThumbnailFormat {
+    /// Lossless; `image` has no lossy WebP encoder.
+    #[default]
+    Webp,
+    Png,
+}
+
+impl ThumbnailFormat {
+    fn image_format(self) -> ImageFormat {
+        match self {
+            Self::Webp => ImageFormat::WebP,
+            Self::Png => ImageFormat::Png,
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            Self::Webp => "webp",
+            Self::Png => "png",
+        }
+    }
+
+    fn mime(self) -> &'static str {
+        match self {
+            Self::Webp => "image/webp",
+            Self::Png => "image/png",
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ThumbnailOptions {
+    /// Return the thumbnail inline as a data URL instead of its cached path.
+    #[serde(default)]
+    pub inline: bool,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ThumbnailResult {
+    pub width: u32,
+    pub height: u32,
+    /// Set unless `inline` was requested.
+    pub path: Option<PathBuf>,
+    /// `data:image/...;base64,` URL, when `inline` was requested.
+    pub data_url: Option<String>,
+    /// Whether the thumbnail already existed.
+    pub cached: bool,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PrewarmProgress {
+    path: String,
+    done: usize,
+    total: usize,
+    error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ThumbnailCacheStats {
+    pub files: u64,
+    pub bytes: u64,
+}
+
+/// Content hashes of source files, so scrolling back over a gallery doesn't rehash
+/// every image. Keyed by path, length, and modification time.
+#[derive(Default)]
+pub struct Thumbnails {
+    hashes: Mutex<HashMap<(PathBuf, u64, SystemTime), String>>,
+}
+
+impl Thumbnails {
+    fn content_hash(&self, path: &Path) -> io::Result<String> {
+        let metadata = fs::metadata(path)?;
+        let key = (path.to_path_buf(), metadata.len(), metadata.modified()?);
+        if let Some(hash) = self.hashes.lock().unwrap().get(&key) {
+            return Ok(hash.clone());
+        }
+
+        let mut hasher = Sha256::new();
+        let mut input = File::open(path)?;
+        let mut buf = vec![0; 64 * 1024];
+        loop {
+            let n = input.read(&mut buf)?;
+            if n == 0 {
+                break;
+            }
+            hasher.update(&buf[..n]);
+        }
+        let hash: String = hasher
+            .finalize()
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect();
+        self.hashes.lock().unwrap().insert(key, hash.clone());
+        Ok(hash)
+    }
+}
+
+fn cache_dir<R: Runtime>(app: &AppHandle<R>) -> Result<PathBuf, ThumbnailError> {
+    app.path()
+        .app_data_dir()
+        .map(|dir| dir.join(CACHE_DIR))
+        .map_err(|e| ThumbnailError::Io(e.to_string()))
+}
+
+fn is_heif(path: &Path) -> bool {
+    let mut header = [0; 12];
+    let Ok(mut file) = File::open(path) else {
+        return false;
+    };
+    file.read_exact(&mut header).is_ok()
+        && &header[4..8] == b"ftyp"
+        && HEIF_BRANDS.iter().any(|brand| header[8..12] == brand[..])
+}
+
+/// Decodes `src` upright and writes it scaled to fit `max_dim` into `dest`. Images
+/// already smaller are re-encoded at their own size.
+fn render(
+    src: &Path,
+    dest: &Path,
+    max_dim: u32,
+    format: ThumbnailFormat,
+) -> Result<(u32, u32), ThumbnailError> {
+    if is_heif(src) {
+        return Err(ThumbnailError::Unsupported("HEIC/HEIF".into()));
+    }
+    let reader = ImageReader::open(src)?.with_guessed_format()?;
+    if reader.format().is_none() {
+        return Err(ThumbnailError::Unsupported("unrecognized image".into()));
+    }
+    let mut decoder = reader.into_decoder()?;
+    let orientation = decoder.orientation().unwrap_or(Orientation::NoTransforms);
+    let mut image = DynamicImage::from_decoder(decoder)?;
+    image.apply_orientation(orientation);
+    if image.width() > max_dim || image.height() > max_dim {
+        image = image.thumbnail(max_dim, max_dim);
+    }
+
+    let dir = dest.parent().expect("cache entries have a parent");
+    fs::create_dir_all(dir)?;
+    let tmp = dir.join(format!(".{}.tmp", uuid::Uuid::new_v4()));
+    image
+        .save_with_format(&tmp, format.image_format())
+        .inspect_err(|_| {
+            let _ = fs::remove_file(&tmp);
+        })?;
+    fs::rename(&tmp, dest).inspect_err(|_| {
+        let _ = fs::remove_file(&tmp);
+    })?;
+    Ok((image.width(), image.height()))
+}
+
+/// The cached thumbnail for `path`, rendering it first if needed. Returns the cache
+/// entry, its dimensions, and whether it was already there.
+fn thumbnail(
+    thumbnails: &Thumbnails,
+    dir: &Path,
+    path: &Path,
+    max_dim: u32,
+    format: ThumbnailFormat,
+) -> Result<(PathBuf, u32, u32, bool), ThumbnailError> {
+    let hash = thumbnails.content_hash(path)?;
+    let dest = dir.join(format!("{hash}_{max_dim}.{}", format.extension()));
+    if dest.exists() {
+        if let Ok((width, height)) = image::image_dimensions(&dest) {
+            return Ok((dest, width, height, true));
+        }
+    }
+    let (width, height) = render(path, &dest, max_dim, format)?;
+    Ok((dest, width, height, false))
+}
+
+fn allowed<R: Runtime>(app: &AppHandle<R>, path: &str) -> Result<PathBuf, ThumbnailError> {
+    scope::ensure_allowed(app, Path::new(path)).map_err(ThumbnailError::NotAllowed)
+}
+
+/// A thumbnail of the image at `path` no larger than `max_dim` on either side, with EXIF
+/// orientation applied. Results are cached by content, so moved or renamed files hit
+/// the cache too.
+#[tauri::command]
+pub async fn get_thumbnail<R: Runtime>(
+    path: String,
+    max_dim: u32,
+    format: ThumbnailFormat,
+    options: Option<ThumbnailOptions>,
+    app: AppHandle<R>,
+) -> Result<ThumbnailResult, ThumbnailError> {
+    let path = allowed(&app, &path)?;
+    let dir = cache_dir(&app)?;
+    let options = options.unwrap_or_default();
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let thumbnails = app.state::<Thumbnails>();
+        let (dest, width, height, cached) =
+            thumbnail(&thumbnails, &dir, &path, max_dim.max(1), format)?;
+        let data_url = options
+            .inline
+            .then(|| fs::read(&dest))
+            .transpose()?
+            .map(|bytes| format!("data:{};base64,{}", format.mime(), BASE64.encode(bytes)));
+        Ok(ThumbnailResult {
+            width,
+            height,
+            path: (!options.inline).then_some(dest),
+            data_url,
+            cached,
+        })
+    })
+    .await
+    .map_err(|e| ThumbnailError::Io(e.to_string()))?
+}
+
+/// Renders thumbnails for `paths` in parallel, emitting [`PROGRESS_EVENT`] per file.
+/// Failures are reported in the event and don't stop the rest. Returns how many
+/// thumbnails are now cached. Runs as a task, so `cancel_task` stops it between files.
+#[tauri::command]
+pub async fn prewarm_thumbnails<R: Runtime>(
+    paths: Vec<String>,
+    max_dim: Option<u32>,
+    format: Option<ThumbnailFormat>,
+    app: AppHandle<R>,
+) -> Result<usize, ThumbnailError> {
+    let dir = cache_dir(&app)?;
+    let max_dim = max_dim.unwrap_or(DEFAULT_MAX_DIM).max(1);
+    let format = format.unwrap_or_default();
+
+    let (_, ended) = tasks::submit(
+        &app,
+        TaskKind::PrewarmThumbnails,
+        Sink::None,
+        move |reporter| async move {
+            tauri::async_runtime::spawn_blocking(move || {
+                let app = reporter.app();
+                let thumbnails = app.state::<Thumbnails>();
+                let total = paths.len();
+                let done = AtomicUsize::new(0);
+                let cached = paths
+                    .par_iter()
+                    .filter(|path| {
+                        if reporter.is_cancelled() {
+                            return false;
+                        }
+                        let result = allowed(app, path)
+                            .and_then(|src| thumbnail(&thumbnails, &dir, &src, max_dim, format));
+                        let done = done.fetch_add(1, Ordering::Relaxed) + 1;
+                        reporter.progress(
+                            done as f32 / total as f32 * 100.0,
+                            format!("{done} of {total} thumbnails"),
+                        );
+                        let _ = app.emit(
+                            PROGRESS_EVENT,
+                            PrewarmProgress {
+                                path: path.to_string(),
+                                done,
+                                total,
+                                error: result.as_ref().err().map(ToString::to_string),
+                            },
+                        );
+                        result.is_ok()
+                    })
+                    .count();
+                if reporter.is_cancelled() {
+                    return Err(ThumbnailError::Cancelled);
+                }
+                Ok(cached)
+            })
+            .await
+            .map_err(|e| ThumbnailError::Io(e.to_string()))?
+        },
+    );
+    ended.await.unwrap_or(Err(ThumbnailError::Cancelled))
+}
+
+fn cache_stats(dir: &Path) -> io::Result<ThumbnailCacheStats> {
+    let mut stats = ThumbnailCacheStats { files: 0, bytes: 0 };
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(stats),
+        Err(e) => return Err(e),
+    };
+    for entry in entries {
+        let metadata = entry?.metadata()?;
+        if metadata.is_file() {
+            stats.files += 1;
+            stats.bytes += metadata.len();
+        }
+    }
+    Ok(stats)
+}
+
+#[tauri::command]
+pub fn thumbnail_cache_size<R: Runtime>(
+    app: AppHandle<R>,
+) -> Result<ThumbnailCacheStats, ThumbnailError> {
+    Ok(cache_stats(&cache_dir(&app)?)?)
+}
+
+/// Deletes every cached thumbnail, returning what was freed.
+#[tauri::command]
+pub fn clear_thumbnail_cache<R: Runtime>(
+    app: AppHandle<R>,
+) -> Result<ThumbnailCacheStats, ThumbnailError> {
+    let dir = cache_dir(&app)?;
+    let stats = cache_stats(&dir)?;
+    match fs::remove_dir_all(&dir) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e.into()),
+    }
+    Ok(stats)
+}
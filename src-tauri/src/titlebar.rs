@@ -0,0 +1,384 @@
+//! Commands behind a CSS titlebar. Windows opened with `customTitlebar` keep the native
+//! traffic lights on macOS (the overlay titlebar style) and drop decorations elsewhere,
+//! where the frontend draws its own minimize, maximize and close buttons in the space
+//! [`get_window_controls_metrics`] reports.
+//!
+//! On Windows the maximize button is also answered as `HTMAXBUTTON` to hit-testing, so
+//! hovering it opens Snap Layouts like a native one. Clicks there go to the window rather
+//! than the webview, so the window toggles maximize itself.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, Runtime, State, Window, WindowEvent};
+use tauri::{WebviewWindow, WebviewWindowBuilder};
+
+use crate::window_manager::WindowError;
+
+pub const MAXIMIZE_CHANGED_EVENT: &str = "window://maximize-changed";
+
+/// Two drag starts this close together are a double-click on the drag region. Both
+/// macOS and Windows default to about this.
+const DOUBLE_CLICK: Duration = Duration::from_millis(500);
+
+/// Windows 11's caption buttons, in logical pixels.
+#[cfg(not(target_os = "macos"))]
+const CAPTION_BUTTON_WIDTH: f64 = 46.0;
+#[cfg(not(target_os = "macos"))]
+const TITLEBAR_HEIGHT: f64 = 32.0;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MaximizeChanged {
+    pub label: String,
+    pub maximized: bool,
+}
+
+/// Logical pixels the frontend should keep clear of its own titlebar content.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WindowControlsMetrics {
+    /// The macOS traffic lights and the gap after them.
+    pub left_inset: f64,
+    /// Where the frontend's own caption buttons go, right to left close, maximize,
+    /// minimize. Zero on macOS.
+    pub right_inset: f64,
+    pub caption_button_width: f64,
+    pub titlebar_height: f64,
+    /// True where the OS draws the buttons and the frontend must not.
+    pub native_controls: bool,
+}
+
+/// The last drag start per label, for double-clicks, and the last maximized state
+/// reported per label.
+#[derive(Default)]
+pub struct Titlebars {
+    drag_starts: Mutex<HashMap<String, Instant>>,
+    maximized: Mutex<HashMap<String, bool>>,
+}
+
+/// Builder settings for a custom titlebar window.
+pub fn style<'a, R: Runtime, M: Manager<R>>(
+    builder: WebviewWindowBuilder<'a, R, M>,
+) -> WebviewWindowBuilder<'a, R, M> {
+    #[cfg(target_os = "macos")]
+    let builder = builder
+        .title_bar_style(tauri::TitleBarStyle::Overlay)
+        .hidden_title(true);
+    #[cfg(not(target_os = "macos"))]
+    let builder = builder.decorations(false);
+    builder
+}
+
+/// Hooks the Snap Layouts hit-test into a window built with [`style`]. Nothing to do
+/// elsewhere.
+pub fn install<R: Runtime>(window: &WebviewWindow<R>) {
+    #[cfg(windows)]
+    match window.hwnd() {
+        Ok(hwnd) => snap::install(hwnd.0 as _),
+        Err(e) => tracing::warn!(label = window.label(), error = %e, "no hwnd for snap layouts"),
+    }
+    #[cfg(not(windows))]
+    let _ = window;
+}
+
+fn window<R: Runtime>(app: &AppHandle<R>, label: &str) -> Result<WebviewWindow<R>, WindowError> {
+    app.get_webview_window(label)
+        .ok_or_else(|| WindowError::NotFound(label.to_string()))
+}
+
+fn toggle_maximize<R: Runtime>(window: &WebviewWindow<R>) -> Result<(), WindowError> {
+    if window.is_maximized()? {
+        window.unmaximize()?;
+    } else {
+        window.maximize()?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn window_minimize<R: Runtime>(label: String, app: AppHandle<R>) -> Result<(), WindowError> {
+    Ok(window(&app, &label)?.minimize()?)
+}
+
+#[tauri::command]
+pub fn window_toggle_maximize<R: Runtime>(
+    label: String,
+    app: AppHandle<R>,
+) -> Result<(), WindowError> {
+    toggle_maximize(&window(&app, &label)?)
+}
+
+#[tauri::command]
+pub fn window_close<R: Runtime>(label: String, app: AppHandle<R>) -> Result<(), WindowError> {
+    Ok(window(&app, &label)?.close()?)
+}
+
+/// Call on `mousedown` in the drag region. A second call within [`DOUBLE_CLICK`] toggles
+/// maximize instead, as double-clicking a native titlebar does.
+#[tauri::command]
+pub fn window_start_dragging<R: Runtime>(
+    label: String,
+    app: AppHandle<R>,
+    titlebars: State<'_, Titlebars>,
+) -> Result<(), WindowError> {
+    let window = window(&app, &label)?;
+    let now = Instant::now();
+    let previous = titlebars
+        .drag_starts
+        .lock()
+        .unwrap()
+        .insert(label.clone(), now);
+    if previous.is_some_and(|previous| now.duration_since(previous) < DOUBLE_CLICK) {
+        titlebars.drag_starts.lock().unwrap().remove(&label);
+        return toggle_maximize(&window);
+    }
+    Ok(window.start_dragging()?)
+}
+
+#[tauri::command]
+pub fn get_window_controls_metrics<R: Runtime>(
+    label: String,
+    app: AppHandle<R>,
+) -> Result<WindowControlsMetrics, WindowError> {
+    metrics(&window(&app, &label)?)
+}
+
+/// Measured from the zoom button, so a moved `trafficLightPosition` is accounted for.
+#[cfg(target_os = "macos")]
+fn metrics<R: Runtime>(window: &WebviewWindow<R>) -> Result<WindowControlsMetrics, WindowError> {
+    use objc2_app_kit::{NSWindow, NSWindowButton};
+
+    /// Gap between the last traffic light and titlebar content in Apple's apps.
+    const TRAFFIC_LIGHT_PADDING: f64 = 12.0;
+
+    let ns_window = window.ns_window()?;
+    // SAFETY: Tauri's pointer is the live `NSWindow`, and commands run on the main thread.
+    let ns_window = unsafe { &*ns_window.cast::<NSWindow>() };
+    let titlebar_height = ns_window.frame().size.height - ns_window.contentLayoutRect().size.height;
+    let left_inset = ns_window
+        .standardWindowButton(NSWindowButton::ZoomButton)
+        .map(|zoom| {
+            let frame = zoom.frame();
+            frame.origin.x + frame.size.width + TRAFFIC_LIGHT_PADDING
+        })
+        .unwrap_or_default();
+    Ok(WindowControlsMetrics {
+        left_inset,
+        titlebar_height,
+        native_controls: true,
+        ..Default::default()
+    })
+}
+
+/// Everywhere else the frontend draws all three buttons, sized like Windows 11's.
+#[cfg(not(target_os = "macos"))]
+fn metrics<R: Runtime>(_window: &WebviewWindow<R>) -> Result<WindowControlsMetrics, WindowError> {
+    Ok(WindowControlsMetrics {
+        right_inset: CAPTION_BUTTON_WIDTH * 3.0,
+        caption_button_width: CAPTION_BUTTON_WIDTH,
+        titlebar_height: TITLEBAR_HEIGHT,
+        ..Default::default()
+    })
+}
+
+/// Emits [`MAXIMIZE_CHANGED_EVENT`] when a resize changes the maximized state, so the
+/// frontend can swap its maximize and restore icons.
+pub fn on_window_event<R: Runtime>(window: &Window<R>, event: &WindowEvent) {
+    match event {
+        WindowEvent::Resized(_) => {}
+        WindowEvent::Destroyed => {
+            if let Some(titlebars) = window.try_state::<Titlebars>() {
+                titlebars.maximized.lock().unwrap().remove(window.label());
+                titlebars.drag_starts.lock().unwrap().remove(window.label());
+            }
+            return;
+        }
+        _ => return,
+    }
+    let (Some(titlebars), Ok(maximized)) = (window.try_state::<Titlebars>(), window.is_maximized())
+    else {
+        return;
+    };
+    let previous = titlebars
+        .maximized
+        .lock()
+        .unwrap()
+        .insert(window.label().to_string(), maximized);
+    // Windows open unmaximized, so a first report of `true` is a change too.
+    if previous.unwrap_or(false) != maximized {
+        let _ = window.emit(
+            MAXIMIZE_CHANGED_EVENT,
+            MaximizeChanged {
+                label: window.label().to_string(),
+                maximized,
+            },
+        );
+    }
+}
+
+/// The webview covers the whole client area, so its windows would get the hit-test
+/// instead of ours. A nearly transparent child sits above it over the maximize button and
+/// answers `HTTRANSPARENT`, which hands the hit-test to the parent; the parent's subclass
+/// answers `HTMAXBUTTON` there, and handles the click the webview never sees.
+#[cfg(windows)]
+mod snap {
+    use std::sync::Once;
+
+    use windows_sys::Win32::Foundation::{HWND, LPARAM, LRESULT, POINT, RECT, WPARAM};
+    use windows_sys::Win32::Graphics::Gdi::ScreenToClient;
+    use windows_sys::Win32::System::LibraryLoader::GetModuleHandleW;
+    use windows_sys::Win32::UI::HiDpi::GetDpiForWindow;
+    use windows_sys::Win32::UI::Shell::{DefSubclassProc, RemoveWindowSubclass, SetWindowSubclass};
+    use windows_sys::Win32::UI::WindowsAndMessaging::{
+        CreateWindowExW, DefWindowProcW, GetClientRect, IsZoomed, RegisterClassW,
+        SetLayeredWindowAttributes, SetWindowPos, ShowWindow, HTMAXBUTTON, HTTRANSPARENT, HWND_TOP,
+        LWA_ALPHA, SWP_NOACTIVATE, SW_MAXIMIZE, SW_RESTORE, WM_DPICHANGED, WM_NCDESTROY,
+        WM_NCHITTEST, WM_NCLBUTTONDBLCLK, WM_NCLBUTTONDOWN, WM_NCLBUTTONUP, WM_SIZE, WNDCLASSW,
+        WS_CHILD, WS_EX_LAYERED, WS_EX_NOACTIVATE, WS_VISIBLE,
+    };
+
+    use super::{CAPTION_BUTTON_WIDTH, TITLEBAR_HEIGHT};
+
+    const SUBCLASS_ID: usize = 0x4c59_5253;
+
+    fn wide(s: &str) -> Vec<u16> {
+        s.encode_utf16().chain(Some(0)).collect()
+    }
+
+    /// The maximize button's client rect: the middle of the three caption buttons.
+    unsafe fn button_rect(parent: HWND) -> RECT {
+        let scale = GetDpiForWindow(parent) as f64 / 96.0;
+        let mut client = RECT {
+            left: 0,
+            top: 0,
+            right: 0,
+            bottom: 0,
+        };
+        GetClientRect(parent, &mut client);
+        let width = (CAPTION_BUTTON_WIDTH * scale).round() as i32;
+        RECT {
+            left: client.right - width * 2,
+            top: 0,
+            right: client.right - width,
+            bottom: (TITLEBAR_HEIGHT * scale).round() as i32,
+        }
+    }
+
+    unsafe fn place(parent: HWND, overlay: HWND) {
+        let rect = button_rect(parent);
+        SetWindowPos(
+            overlay,
+            HWND_TOP,
+            rect.left,
+            rect.top,
+            rect.right - rect.left,
+            rect.bottom - rect.top,
+            SWP_NOACTIVATE,
+        );
+    }
+
+    unsafe extern "system" fn overlay_proc(
+        hwnd: HWND,
+        msg: u32,
+        wparam: WPARAM,
+        lparam: LPARAM,
+    ) -> LRESULT {
+        if msg == WM_NCHITTEST {
+            return HTTRANSPARENT as LRESULT;
+        }
+        DefWindowProcW(hwnd, msg, wparam, lparam)
+    }
+
+    unsafe extern "system" fn parent_proc(
+        hwnd: HWND,
+        msg: u32,
+        wparam: WPARAM,
+        lparam: LPARAM,
+        _id: usize,
+        overlay: usize,
+    ) -> LRESULT {
+        let overlay = overlay as HWND;
+        match msg {
+            WM_NCHITTEST => {
+                let mut point = POINT {
+                    x: (lparam & 0xffff) as i16 as i32,
+                    y: ((lparam >> 16) & 0xffff) as i16 as i32,
+                };
+                ScreenToClient(hwnd, &mut point);
+                let rect = button_rect(hwnd);
+                if point.x >= rect.left
+                    && point.x < rect.right
+                    && point.y >= rect.top
+                    && point.y < rect.bottom
+                {
+                    return HTMAXBUTTON as LRESULT;
+                }
+            }
+            // Without a caption, the default handling would start a move or do nothing.
+            WM_NCLBUTTONDOWN | WM_NCLBUTTONDBLCLK if wparam == HTMAXBUTTON as WPARAM => return 0,
+            WM_NCLBUTTONUP if wparam == HTMAXBUTTON as WPARAM => {
+                let command = if IsZoomed(hwnd) != 0 {
+                    SW_RESTORE
+                } else {
+                    SW_MAXIMIZE
+                };
+                ShowWindow(hwnd, command);
+                return 0;
+            }
+            WM_SIZE | WM_DPICHANGED => {
+                let result = DefSubclassProc(hwnd, msg, wparam, lparam);
+                place(hwnd, overlay);
+                return result;
+            }
+            // The overlay goes with its parent, as child windows do.
+            WM_NCDESTROY => {
+                RemoveWindowSubclass(hwnd, Some(parent_proc), SUBCLASS_ID);
+            }
+            _ => {}
+        }
+        DefSubclassProc(hwnd, msg, wparam, lparam)
+    }
+
+    pub fn install(parent: HWND) {
+        static REGISTER: Once = Once::new();
+        let class = wide("LayersSnapOverlay");
+        // SAFETY: plain Win32 calls on a window owned by this (the main) thread.
+        unsafe {
+            let instance = GetModuleHandleW(std::ptr::null());
+            REGISTER.call_once(|| {
+                let wc = WNDCLASSW {
+                    lpfnWndProc: Some(overlay_proc),
+                    hInstance: instance,
+                    lpszClassName: class.as_ptr(),
+                    ..std::mem::zeroed()
+                };
+                RegisterClassW(&wc);
+            });
+            let overlay = CreateWindowExW(
+                WS_EX_LAYERED | WS_EX_NOACTIVATE,
+                class.as_ptr(),
+                std::ptr::null(),
+                WS_CHILD | WS_VISIBLE,
+                0,
+                0,
+                0,
+                0,
+                parent,
+                std::ptr::null_mut(),
+                instance,
+                std::ptr::null(),
+            );
+            if overlay.is_null() {
+                tracing::warn!("snap layouts overlay could not be created");
+                return;
+            }
+            // Layered children need some alpha to be hit-tested at all.
+            SetLayeredWindowAttributes(overlay, 0, 1, LWA_ALPHA);
+            place(parent, overlay);
+            SetWindowSubclass(parent, Some(parent_proc), SUBCLASS_ID, overlay as usize);
+        }
+    }
+}
@@ -0,0 +1,190 @@
+use serde::Serialize;
+use serde_json::Value;
+use tauri::menu::{Menu, MenuEvent, MenuItem, PredefinedMenuItem};
+use tauri::tray::{MouseButton, MouseButtonState, TrayIcon, TrayIconBuilder, TrayIconEvent};
+use tauri::{App, AppHandle, Emitter, Manager, Runtime, WebviewWindow, WindowEvent};
+use tauri_plugin_store::StoreExt;
+
+pub const TRAY_ID: &str = "main";
+pub const TRAY_EVENT: &str = "tray://event";
+pub const NEW_NOTE_EVENT: &str = "tray://new-note";
+pub const SYNC_EVENT: &str = "tray://sync";
+
+/// The frontend's preferences store, and the key that makes closing the main window
+/// hide it to the tray instead of quitting.
+const PREFERENCES_STORE: &str = "preferences.json";
+const MINIMIZE_TO_TRAY_KEY: &str = "minimize_to_tray";
+
+/// Menu items that commands update after the tray is built.
+struct TrayMenu<R: Runtime> {
+    status: MenuItem<R>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrayEventType {
+    LeftClick,
+    RightClick,
+    MenuItem,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TrayEventPayload {
+    pub item_id: String,
+    pub event_type: TrayEventType,
+}
+
+fn emit<R: Runtime>(app: &AppHandle<R>, item_id: &str, event_type: TrayEventType) {
+    let _ = app.emit(
+        TRAY_EVENT,
+        TrayEventPayload {
+            item_id: item_id.to_string(),
+            event_type,
+        },
+    );
+}
+
+pub(crate) fn show_main_window<R: Runtime>(app: &AppHandle<R>) {
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.unminimize();
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+}
+
+fn toggle_main_window<R: Runtime>(app: &AppHandle<R>) {
+    let Some(window) = app.get_webview_window("main") else {
+        return;
+    };
+    let visible = window.is_visible().unwrap_or(false);
+    let focused = window.is_focused().unwrap_or(false);
+    if visible && focused {
+        let _ = window.hide();
+    } else {
+        show_main_window(app);
+    }
+}
+
+fn minimize_to_tray<R: Runtime>(app: &AppHandle<R>) -> bool {
+    let Ok(store) = app.store(PREFERENCES_STORE) else {
+        return false;
+    };
+    // The preferences adapter stores everything as strings.
+    match store.get(MINIMIZE_TO_TRAY_KEY) {
+        Some(Value::Bool(enabled)) => enabled,
+        Some(Value::String(enabled)) => enabled == "true",
+        _ => false,
+    }
+}
+
+fn on_menu_event<R: Runtime>(app: &AppHandle<R>, event: MenuEvent) {
+    let id = event.id().as_ref();
+    emit(app, id, TrayEventType::MenuItem);
+
+    match id {
+        "toggle" => toggle_main_window(app),
+        "new-note" => {
+            show_main_window(app);
+            let _ = app.emit(NEW_NOTE_EVENT, ());
+        }
+        "sync" => {
+            let _ = app.emit(SYNC_EVENT, ());
+        }
+        "quit" => app.exit(0),
+        _ => {}
+    }
+}
+
+fn on_tray_icon_event<R: Runtime>(tray: &TrayIcon<R>, event: TrayIconEvent) {
+    let TrayIconEvent::Click {
+        button,
+        button_state: MouseButtonState::Up,
+        ..
+    } = event
+    else {
+        return;
+    };
+
+    let app = tray.app_handle();
+    match button {
+        MouseButton::Left => {
+            emit(app, TRAY_ID, TrayEventType::LeftClick);
+            toggle_main_window(app);
+        }
+        MouseButton::Right => emit(app, TRAY_ID, TrayEventType::RightClick),
+        MouseButton::Middle => {}
+    }
+}
+
+/// Hides the main window instead of closing it when `minimize_to_tray` is enabled, so the
+/// app keeps running in the tray until it is quit from the tray menu or [`quit_app`].
+fn hide_on_close<R: Runtime>(window: WebviewWindow<R>) {
+    window.clone().on_window_event(move |event| {
+        if let WindowEvent::CloseRequested { api, .. } = event {
+            if minimize_to_tray(window.app_handle()) {
+                api.prevent_close();
+                let _ = window.hide();
+            }
+        }
+    });
+}
+
+/// Builds the tray icon. The icon is the bundle icon embedded at compile time, so there
+/// is no resource path to resolve at runtime.
+pub fn init<R: Runtime>(app: &App<R>) -> tauri::Result<()> {
+    let status = MenuItem::with_id(app, "status", "Up to date", false, None::<&str>)?;
+    let menu = Menu::with_items(
+        app,
+        &[
+            &status,
+            &PredefinedMenuItem::separator(app)?,
+            &MenuItem::with_id(app, "toggle", "Show/Hide", true, None::<&str>)?,
+            &MenuItem::with_id(app, "new-note", "New Note", true, None::<&str>)?,
+            &MenuItem::with_id(app, "sync", "Sync Now", true, None::<&str>)?,
+            &MenuItem::with_id(app, "preferences", "Preferences", true, None::<&str>)?,
+            &PredefinedMenuItem::separator(app)?,
+            &MenuItem::with_id(app, "quit", "Quit", true, None::<&str>)?,
+        ],
+    )?;
+    app.manage(TrayMenu { status });
+
+    let mut builder = TrayIconBuilder::with_id(TRAY_ID)
+        .menu(&menu)
+        .tooltip(&app.package_info().name)
+        .show_menu_on_left_click(false)
+        .on_menu_event(on_menu_event)
+        .on_tray_icon_event(on_tray_icon_event);
+    if let Some(icon) = app.default_window_icon() {
+        builder = builder.icon(icon.clone());
+    }
+    builder.build(app)?;
+
+    if let Some(window) = app.get_webview_window("main") {
+        hide_on_close(window);
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+pub fn update_tray_tooltip<R: Runtime>(text: String, app: AppHandle<R>) -> Result<(), String> {
+    let tray = app
+        .tray_by_id(TRAY_ID)
+        .ok_or_else(|| "tray icon is not initialized".to_string())?;
+    tray.set_tooltip(Some(text)).map_err(|e| e.to_string())
+}
+
+/// Shows sync state in the disabled status line at the top of the tray menu.
+#[tauri::command]
+pub fn set_tray_status<R: Runtime>(text: String, app: AppHandle<R>) -> Result<(), String> {
+    let menu = app
+        .try_state::<TrayMenu<R>>()
+        .ok_or_else(|| "tray icon is not initialized".to_string())?;
+    menu.status.set_text(text).map_err(|e| e.to_string())
+}
+
+/// Quits even when `minimize_to_tray` would otherwise keep the app alive.
+#[tauri::command]
+pub fn quit_app<R: Runtime>(app: AppHandle<R>) {
+    app.exit(0);
+}
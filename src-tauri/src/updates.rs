@@ -0,0 +1,524 @@
+//! Update checks against our own release manifest, for builds handed to testers outside
+//! the stores. Nothing here runs on its own: the frontend checks, then downloads, then
+//! installs, each with an explicit call, so an update never lands unasked.
+//!
+//! The manifest looks like
+//!
+//! ```json
+//! {
+//!   "version": "1.4.0",
+//!   "notes": "…",
+//!   "platforms": {
+//!     "darwin-aarch64": { "url": "…/Layers.dmg", "sha256": "…", "signature": "…" },
+//!     "ios": { "url": "https://apps.apple.com/…" }
+//!   }
+//! }
+//! ```
+//!
+//! keyed by [`platform_key`]. `signature` is a base64 ed25519 signature over the
+//! installer's raw SHA-256 digest, checked against the configured public key. Mobile
+//! entries only carry the store URL.
+//!
+//! Both the manifest and the installer are fetched through [`crate::net`], so they pass
+//! the middleware chain and the per-host limits like any other request. They don't use
+//! the pinned client: its pins are for the API, and the installer's signature is what
+//! vouches for it.
+
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tauri::{AppHandle, Emitter, Manager, Runtime, State};
+use tauri_plugin_http::reqwest;
+
+use crate::net;
+
+pub const PROGRESS_EVENT: &str = "update://progress";
+
+/// Under the app cache dir, one directory per version.
+const STAGING_DIR: &str = "updates";
+
+#[cfg_attr(mobile, allow(dead_code))]
+#[derive(Debug, thiserror::Error, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "camelCase")]
+pub enum UpdateError {
+    #[error("no update endpoint is configured")]
+    NoUpdateEndpoint,
+    #[error("no update public key is configured")]
+    NoPublicKey,
+    #[error("the manifest has no build for {0}")]
+    NoBuild(String),
+    #[error("invalid update manifest: {0}")]
+    InvalidManifest(String),
+    #[error("update download failed: {0}")]
+    DownloadFailed(String),
+    #[error("update checksum mismatch: expected {expected}, got {actual}")]
+    ChecksumMismatch { expected: String, actual: String },
+    #[error("update signature is invalid")]
+    SignatureInvalid,
+    #[error("no update is available")]
+    NoUpdate,
+    #[error("no downloaded update is ready to install")]
+    NotDownloaded,
+    #[error("updates on this platform come from the app store")]
+    Unsupported,
+    #[error("update failed: {0}")]
+    Failed(String),
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdatesConfig {
+    /// URL of the release manifest.
+    pub endpoint: Option<String>,
+    /// Base64 of the 32-byte ed25519 key the installers are signed with.
+    pub pubkey: Option<String>,
+}
+
+impl UpdatesConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(endpoint) = &self.endpoint {
+            match tauri::Url::parse(endpoint) {
+                Ok(url) if url.scheme() == "https" && url.has_host() => {}
+                _ => return Err("the update endpoint must be an https URL".into()),
+            }
+        }
+        if let Some(pubkey) = &self.pubkey {
+            let key = base64::engine::general_purpose::STANDARD.decode(pubkey.trim());
+            if !key.is_ok_and(|key| key.len() == 32) {
+                return Err("the update public key must be 32 bytes of base64".into());
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Manifest {
+    version: String,
+    #[serde(default)]
+    notes: String,
+    platforms: HashMap<String, PlatformBuild>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct PlatformBuild {
+    url: String,
+    sha256: Option<String>,
+    signature: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateInfo {
+    pub version: String,
+    pub current_version: String,
+    pub notes: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum UpdateCheck {
+    #[serde(rename_all = "camelCase")]
+    UpToDate {
+        current_version: String,
+    },
+    Available(UpdateInfo),
+    /// Mobile only: where the store lists the app, whatever version it has.
+    #[serde(rename_all = "camelCase")]
+    Store {
+        url: String,
+    },
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateProgress {
+    pub downloaded_bytes: u64,
+    /// 0 when the server doesn't report a `Content-Length`.
+    pub total_bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateReady {
+    pub version: String,
+    pub path: PathBuf,
+}
+
+#[derive(Clone)]
+struct Pending {
+    version: String,
+    build: PlatformBuild,
+}
+
+#[cfg_attr(mobile, allow(dead_code))]
+#[derive(Clone)]
+struct Downloaded {
+    version: String,
+    path: PathBuf,
+    sha256: String,
+    /// Held from verification to launch; see [`open_sealed`].
+    file: Arc<File>,
+}
+
+/// The build found by the last check, and the installer [`download_update`] verified,
+/// which is all [`install_update`] will run. Where to check comes from the app config.
+#[derive(Default)]
+pub struct Updates {
+    pending: Mutex<Option<Pending>>,
+    downloaded: Mutex<Option<Downloaded>>,
+}
+
+pub fn init<R: Runtime>(app: &AppHandle<R>) {
+    app.manage(Updates::default());
+}
+
+/// The manifest key for this build: `android` and `ios` on mobile, otherwise the
+/// `{os}-{arch}` pairs Tauri's bundler uses, such as `darwin-aarch64`.
+fn platform_key() -> String {
+    match std::env::consts::OS {
+        os @ ("android" | "ios") => os.to_string(),
+        "macos" => format!("darwin-{}", std::env::consts::ARCH),
+        os => format!("{os}-{}", std::env::consts::ARCH),
+    }
+}
+
+async fn fetch_manifest<R: Runtime>(
+    app: &AppHandle<R>,
+    config: &UpdatesConfig,
+) -> Result<Manifest, UpdateError> {
+    let endpoint = config
+        .endpoint
+        .as_deref()
+        .ok_or(UpdateError::NoUpdateEndpoint)?;
+    let response = net::fetch(app, net::get(app, endpoint), None)
+        .await
+        .map_err(|e| UpdateError::DownloadFailed(e.to_string()))?;
+    if !(200..300).contains(&response.status) {
+        return Err(UpdateError::DownloadFailed(format!(
+            "HTTP {} from {endpoint}",
+            response.status
+        )));
+    }
+    serde_json::from_str(&response.body).map_err(|e| UpdateError::InvalidManifest(e.to_string()))
+}
+
+fn hex(digest: &[u8]) -> String {
+    digest.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Checks the signature over `digest`, the installer's SHA-256.
+fn verify_signature(pubkey: &str, signature: &str, digest: &[u8]) -> Result<(), UpdateError> {
+    let engine = base64::engine::general_purpose::STANDARD;
+    let pubkey = engine
+        .decode(pubkey.trim())
+        .map_err(|e| UpdateError::Failed(format!("public key: {e}")))?;
+    let signature = engine
+        .decode(signature.trim())
+        .map_err(|_| UpdateError::SignatureInvalid)?;
+    ring::signature::UnparsedPublicKey::new(&ring::signature::ED25519, pubkey)
+        .verify(digest, &signature)
+        .map_err(|_| UpdateError::SignatureInvalid)
+}
+
+/// On desktop, compares the manifest version against ours and remembers the build for
+/// [`download_update`]. Mobile builds only get the store URL back.
+#[tauri::command]
+pub async fn check_for_update<R: Runtime>(
+    app: AppHandle<R>,
+    updates: State<'_, Updates>,
+) -> Result<UpdateCheck, UpdateError> {
+    let manifest = fetch_manifest(&app, &crate::config::current(&app).updates).await?;
+    let key = platform_key();
+    let build = manifest
+        .platforms
+        .get(&key)
+        .cloned()
+        .ok_or(UpdateError::NoBuild(key))?;
+    if cfg!(mobile) {
+        return Ok(UpdateCheck::Store { url: build.url });
+    }
+
+    let current = app.package_info().version.clone();
+    let latest = semver::Version::parse(manifest.version.trim_start_matches('v'))
+        .map_err(|e| UpdateError::InvalidManifest(format!("version: {e}")))?;
+    if latest <= current {
+        *updates.pending.lock().unwrap() = None;
+        return Ok(UpdateCheck::UpToDate {
+            current_version: current.to_string(),
+        });
+    }
+
+    tracing::info!(%current, %latest, "update available");
+    *updates.pending.lock().unwrap() = Some(Pending {
+        version: latest.to_string(),
+        build,
+    });
+    Ok(UpdateCheck::Available(UpdateInfo {
+        version: latest.to_string(),
+        current_version: current.to_string(),
+        notes: manifest.notes,
+    }))
+}
+
+/// Creates `dir` and the version directory under it owner-only, then `path` afresh, so
+/// nothing another account planted there, a link included, is written through. The
+/// per-user cache dir on Windows is already private to the account.
+fn create_staged(dir: &Path, path: &Path) -> io::Result<File> {
+    let version_dir = path.parent().unwrap_or(dir);
+    let mut builder = std::fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+        builder.mode(0o700).create(version_dir)?;
+        // Directories left by an earlier run keep their mode, so tighten those too.
+        for dir in [dir, version_dir] {
+            std::fs::set_permissions(dir, std::fs::Permissions::from_mode(0o700))?;
+        }
+    }
+    #[cfg(not(unix))]
+    builder.create(version_dir)?;
+
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+        _ => {}
+    }
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)
+}
+
+/// Opens the verified installer for [`install_update`] to rehash and launch. Windows
+/// shares it read-only, so nothing can rewrite or replace it while the handle is held;
+/// elsewhere the owner-only directory keeps other accounts out, and the rehash reads
+/// through this handle rather than the path.
+fn open_sealed(path: &Path) -> io::Result<File> {
+    let mut options = OpenOptions::new();
+    options.read(true);
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::OpenOptionsExt;
+        options.share_mode(windows_sys::Win32::Storage::FileSystem::FILE_SHARE_READ);
+    }
+    options.open(path)
+}
+
+/// Streams the installer found by the last [`check_for_update`] into the app cache dir,
+/// hashing as it goes. A file whose checksum or signature doesn't match is deleted.
+#[tauri::command]
+pub async fn download_update<R: Runtime>(
+    app: AppHandle<R>,
+    updates: State<'_, Updates>,
+) -> Result<UpdateReady, UpdateError> {
+    if cfg!(mobile) {
+        return Err(UpdateError::Unsupported);
+    }
+    let pubkey = crate::config::current(&app)
+        .updates
+        .pubkey
+        .ok_or(UpdateError::NoPublicKey)?;
+    let Pending { version, build } = updates
+        .pending
+        .lock()
+        .unwrap()
+        .clone()
+        .ok_or(UpdateError::NoUpdate)?;
+    let (Some(expected), Some(signature)) = (build.sha256, build.signature) else {
+        return Err(UpdateError::InvalidManifest(
+            "desktop builds need a sha256 and a signature".into(),
+        ));
+    };
+
+    let file_name = reqwest::Url::parse(&build.url)
+        .ok()
+        .and_then(|url| url.path_segments()?.next_back().map(str::to_string))
+        .filter(|name| !name.is_empty())
+        .ok_or_else(|| UpdateError::InvalidManifest(format!("url: {}", build.url)))?;
+    let dir = app
+        .path()
+        .app_cache_dir()
+        .map_err(|e| UpdateError::Failed(e.to_string()))?
+        .join(STAGING_DIR);
+    let path = dir.join(&version).join(file_name);
+    let file = create_staged(&dir, &path).map_err(|e| UpdateError::Failed(e.to_string()))?;
+
+    let download_failed = |e: String| UpdateError::DownloadFailed(e);
+    let mut response = net::send(&app, net::get(&app, &build.url), None)
+        .await
+        .map_err(|e| download_failed(e.to_string()))?;
+    if !(200..300).contains(&response.status()) {
+        return Err(download_failed(format!("HTTP {}", response.status())));
+    }
+    let total_bytes = response.content_length().unwrap_or(0);
+    let mut file = tokio::fs::File::from_std(file);
+    let mut hasher = Sha256::new();
+    let mut downloaded_bytes = 0u64;
+    let streamed: Result<(), UpdateError> = async {
+        use tokio::io::AsyncWriteExt;
+        while let Some(chunk) = response
+            .chunk()
+            .await
+            .map_err(|e| download_failed(e.to_string()))?
+        {
+            file.write_all(&chunk)
+                .await
+                .map_err(|e| download_failed(e.to_string()))?;
+            hasher.update(&chunk);
+            downloaded_bytes += chunk.len() as u64;
+            let _ = app.emit(
+                PROGRESS_EVENT,
+                UpdateProgress {
+                    downloaded_bytes,
+                    total_bytes,
+                },
+            );
+        }
+        file.sync_all()
+            .await
+            .map_err(|e| download_failed(e.to_string()))
+    }
+    .await;
+
+    let digest = hasher.finalize();
+    let actual = hex(&digest);
+    drop(file);
+    let verified = streamed
+        .and_then(|()| {
+            if !actual.eq_ignore_ascii_case(expected.trim()) {
+                return Err(UpdateError::ChecksumMismatch {
+                    expected: expected.clone(),
+                    actual: actual.clone(),
+                });
+            }
+            verify_signature(&pubkey, &signature, &digest)
+        })
+        .and_then(|()| open_sealed(&path).map_err(|e| UpdateError::Failed(e.to_string())));
+    let sealed = match verified {
+        Ok(sealed) => sealed,
+        Err(e) => {
+            tracing::error!(%version, error = %e, "update download rejected");
+            let _ = tokio::fs::remove_file(&path).await;
+            return Err(e);
+        }
+    };
+
+    tracing::info!(%version, path = %path.display(), "update downloaded");
+    *updates.downloaded.lock().unwrap() = Some(Downloaded {
+        version: version.clone(),
+        path: path.clone(),
+        sha256: actual,
+        file: Arc::new(sealed),
+    });
+    Ok(UpdateReady { version, path })
+}
+
+/// Windows runs the `.exe` or `.msi`; either replaces the app once we've exited.
+#[cfg(windows)]
+fn launch(path: &Path, _file: &File) -> io::Result<()> {
+    use std::process::Command;
+
+    let is_msi = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("msi"));
+    if is_msi {
+        Command::new("msiexec").arg("/i").arg(path).spawn()?;
+    } else {
+        Command::new(path).spawn()?;
+    }
+    Ok(())
+}
+
+/// Finder mounts the `.dmg` (or Installer opens a `.pkg`) for the user to finish.
+#[cfg(target_os = "macos")]
+fn launch(path: &Path, _file: &File) -> io::Result<()> {
+    std::process::Command::new("open").arg(path).spawn()?;
+    Ok(())
+}
+
+/// A running AppImage is swapped for the new one, copied from the verified handle, and
+/// relaunched; other packages open in the desktop's package installer.
+#[cfg(target_os = "linux")]
+fn launch(path: &Path, file: &File) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    use std::process::Command;
+
+    let is_appimage = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("appimage"));
+    let Some(current) = std::env::var_os("APPIMAGE").filter(|_| is_appimage) else {
+        Command::new("xdg-open").arg(path).spawn()?;
+        return Ok(());
+    };
+    let current = PathBuf::from(current);
+    let mut staged = current.as_os_str().to_owned();
+    staged.push(".tmp");
+    let staged = PathBuf::from(staged);
+    let mut source = file;
+    source.seek(SeekFrom::Start(0))?;
+    io::copy(&mut source, &mut File::create(&staged)?)?;
+    std::fs::set_permissions(&staged, std::fs::Permissions::from_mode(0o755))?;
+    std::fs::rename(&staged, &current)?;
+    Command::new(&current).spawn()?;
+    Ok(())
+}
+
+/// Runs the installer [`download_update`] verified, then exits so it can replace us.
+/// The file is hashed again first, through the handle kept since it was verified, in
+/// case it changed on disk since.
+#[tauri::command]
+pub async fn install_update<R: Runtime>(
+    app: AppHandle<R>,
+    updates: State<'_, Updates>,
+) -> Result<(), UpdateError> {
+    #[cfg(mobile)]
+    {
+        let _ = (app, updates);
+        Err(UpdateError::Unsupported)
+    }
+    #[cfg(desktop)]
+    {
+        let downloaded = updates
+            .downloaded
+            .lock()
+            .unwrap()
+            .clone()
+            .ok_or(UpdateError::NotDownloaded)?;
+        let file = downloaded.file.clone();
+        let actual = tauri::async_runtime::spawn_blocking(move || {
+            let mut file = &*file;
+            file.seek(SeekFrom::Start(0))?;
+            let mut hasher = Sha256::new();
+            io::copy(&mut file, &mut hasher)?;
+            Ok::<_, io::Error>(hex(&hasher.finalize()))
+        })
+        .await
+        .map_err(|e| UpdateError::Failed(e.to_string()))?
+        .map_err(|e| UpdateError::Failed(e.to_string()))?;
+        if actual != downloaded.sha256 {
+            let _ = tokio::fs::remove_file(&downloaded.path).await;
+            *updates.downloaded.lock().unwrap() = None;
+            return Err(UpdateError::ChecksumMismatch {
+                expected: downloaded.sha256,
+                actual,
+            });
+        }
+
+        launch(&downloaded.path, &downloaded.file)
+            .map_err(|e| UpdateError::Failed(e.to_string()))?;
+        tracing::info!(version = %downloaded.version, "update installer launched, exiting");
+        app.exit(0);
+        Ok(())
+    }
+}
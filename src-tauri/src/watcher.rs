@@ -0,0 +1,188 @@
+use std::any::Any;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use notify::event::{EventKind, ModifyKind};
+use notify::{RecursiveMode, Watcher};
+use notify_debouncer_full::DebounceEventResult;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, Runtime, State, Window, WindowEvent};
+
+use crate::scope;
+
+pub const CHANGE_EVENT: &str = "fs://change";
+/// Debounced watches emit on `fs-watch://{id}`.
+pub const WATCH_EVENT_PREFIX: &str = "fs-watch://";
+
+const DEFAULT_DEBOUNCE_MS: u64 = 200;
+
+pub type WatchId = u64;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WatchEvent {
+    pub id: WatchId,
+    pub kind: String,
+    pub paths: Vec<String>,
+}
+
+struct ActiveWatch {
+    /// Label of the window that created the watch; it is removed when that window closes.
+    owner: Option<String>,
+    /// The notify watcher or debouncer; dropping it unregisters it from the OS.
+    _handle: Box<dyn Any + Send>,
+}
+
+/// Active watchers keyed by the id handed back to the frontend.
+#[derive(Default)]
+pub struct Watchers {
+    next_id: AtomicU64,
+    active: Mutex<HashMap<WatchId, ActiveWatch>>,
+}
+
+impl Watchers {
+    fn next_id(&self) -> WatchId {
+        self.next_id.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    fn insert(&self, id: WatchId, owner: Option<String>, handle: Box<dyn Any + Send>) {
+        self.active.lock().unwrap().insert(
+            id,
+            ActiveWatch {
+                owner,
+                _handle: handle,
+            },
+        );
+    }
+
+    fn remove(&self, id: WatchId) -> Result<(), String> {
+        self.active
+            .lock()
+            .unwrap()
+            .remove(&id)
+            .map(|_| ())
+            .ok_or_else(|| format!("no active watcher with id {id}"))
+    }
+}
+
+fn kind_name(kind: &EventKind) -> &'static str {
+    match kind {
+        EventKind::Create(_) => "create",
+        EventKind::Modify(ModifyKind::Name(_)) => "rename",
+        EventKind::Modify(_) => "modify",
+        EventKind::Remove(_) => "remove",
+        EventKind::Access(_) => "access",
+        EventKind::Any | EventKind::Other => "other",
+    }
+}
+
+fn recursive_mode(recursive: bool) -> RecursiveMode {
+    if recursive {
+        RecursiveMode::Recursive
+    } else {
+        RecursiveMode::NonRecursive
+    }
+}
+
+fn path_strings(paths: &[std::path::PathBuf]) -> Vec<String> {
+    paths
+        .iter()
+        .map(|p| p.to_string_lossy().into_owned())
+        .collect()
+}
+
+/// Emits every raw notify event on [`CHANGE_EVENT`].
+#[tauri::command]
+pub fn start_watch<R: Runtime>(
+    path: String,
+    recursive: bool,
+    app: AppHandle<R>,
+    watchers: State<'_, Watchers>,
+) -> Result<WatchId, String> {
+    let path = scope::ensure_allowed(&app, path.as_ref())?;
+    let id = watchers.next_id();
+
+    let mut watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+        let Ok(event) = res else {
+            return;
+        };
+        let payload = WatchEvent {
+            id,
+            kind: kind_name(&event.kind).to_string(),
+            paths: path_strings(&event.paths),
+        };
+        let _ = app.emit(CHANGE_EVENT, payload);
+    })
+    .map_err(|e| e.to_string())?;
+    watcher
+        .watch(&path, recursive_mode(recursive))
+        .map_err(|e| e.to_string())?;
+
+    watchers.insert(id, None, Box::new(watcher));
+    Ok(id)
+}
+
+#[tauri::command]
+pub fn stop_watch(id: WatchId, watchers: State<'_, Watchers>) -> Result<(), String> {
+    watchers.remove(id)
+}
+
+/// Watches a path and emits debounced changes on `fs-watch://{id}`. The watch is owned
+/// by the calling window and torn down when it closes.
+#[tauri::command]
+pub fn watch_path<R: Runtime>(
+    path: String,
+    recursive: bool,
+    debounce_ms: Option<u64>,
+    app: AppHandle<R>,
+    window: Window<R>,
+    watchers: State<'_, Watchers>,
+) -> Result<WatchId, String> {
+    let path = scope::ensure_allowed(&app, path.as_ref())?;
+    let id = watchers.next_id();
+    let event_name = format!("{WATCH_EVENT_PREFIX}{id}");
+    let debounce = Duration::from_millis(debounce_ms.unwrap_or(DEFAULT_DEBOUNCE_MS));
+
+    let mut debouncer =
+        notify_debouncer_full::new_debouncer(debounce, None, move |result: DebounceEventResult| {
+            let Ok(events) = result else {
+                return;
+            };
+            for event in events {
+                let kind = kind_name(&event.kind);
+                if matches!(kind, "access" | "other") {
+                    continue;
+                }
+                let payload = WatchEvent {
+                    id,
+                    kind: kind.to_string(),
+                    paths: path_strings(&event.paths),
+                };
+                let _ = app.emit(&event_name, payload);
+            }
+        })
+        .map_err(|e| e.to_string())?;
+    debouncer
+        .watch(&path, recursive_mode(recursive))
+        .map_err(|e| e.to_string())?;
+
+    watchers.insert(id, Some(window.label().to_string()), Box::new(debouncer));
+    Ok(id)
+}
+
+#[tauri::command]
+pub fn unwatch(id: WatchId, watchers: State<'_, Watchers>) -> Result<(), String> {
+    watchers.remove(id)
+}
+
+pub fn on_window_event<R: Runtime>(window: &Window<R>, event: &WindowEvent) {
+    if let WindowEvent::Destroyed = event {
+        let watchers = window.state::<Watchers>();
+        watchers
+            .active
+            .lock()
+            .unwrap()
+            .retain(|_, watch| watch.owner.as_deref() != Some(window.label()));
+    }
+}
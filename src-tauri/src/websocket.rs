@@ -0,0 +1,693 @@
+//! Persistent WebSocket connections, each identified by the id [`ws_connect`] returns.
+//! Connections live in Rust rather than the webview, so they survive the page being
+//! suspended and come back on their own after the network drops.
+//!
+//! The protocol is tokio-tungstenite's, over rustls with the same webpki roots reqwest
+//! uses. It reassembles fragmented messages and answers pings; pings of our own catch
+//! connections that died without a word, as they do across sleep.
+//!
+//! Everything about connection `{id}` arrives as events named `ws://{id}/…`:
+//!
+//! - `message`: an [`IncomingMessage`], binary data as base64 unless `ws_connect` was
+//!   given a channel for it.
+//! - `state`: each [`WsState`] transition, ending with `closed`.
+//! - `error`: protocol errors and failed reconnects, as an [`ErrorEvent`].
+//!
+//! A connection that drops is reopened with exponential backoff, the registered
+//! resubscribe messages are sent first, and then anything `ws_send` queued meanwhile. It
+//! is closed for good by `ws_close`, a normal close from the server, running out of
+//! attempts, or the window that opened it going away.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use base64::Engine;
+use futures::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use tauri::ipc::{Channel, InvokeResponseBody, JavaScriptChannelId};
+use tauri::{AppHandle, Emitter, Manager, Runtime, State, Webview, Window, WindowEvent};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::http::header::{self, HeaderName, HeaderValue};
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+use tokio_tungstenite::tungstenite::protocol::{CloseFrame, WebSocketConfig};
+use tokio_tungstenite::tungstenite::{self, Message};
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+use uuid::Uuid;
+
+use crate::secrets::{self, Secrets};
+
+/// Largest message, after reassembly, that a connection will accept.
+const MAX_MESSAGE_BYTES: usize = 64 * 1024 * 1024;
+/// Largest single frame; tungstenite reads a frame's payload as it arrives rather than
+/// allocating its announced length up front.
+const MAX_FRAME_BYTES: usize = 16 * 1024 * 1024;
+/// How long to wait for the server to answer our close frame before dropping it.
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
+/// Messages `ws_send` may queue while reconnecting; older ones are dropped past this.
+const MAX_PENDING: usize = 256;
+
+const CLOSE_NORMAL: u16 = 1000;
+const CLOSE_GOING_AWAY: u16 = 1001;
+const CLOSE_PROTOCOL_ERROR: u16 = 1002;
+const CLOSE_INVALID_DATA: u16 = 1007;
+const CLOSE_TOO_BIG: u16 = 1009;
+/// Reported, never sent: the connection ended without a close frame.
+const CLOSE_ABNORMAL: u16 = 1006;
+
+type Stream = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+#[derive(Debug, thiserror::Error, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "camelCase")]
+pub enum WsError {
+    #[error("invalid websocket url: {0}")]
+    InvalidUrl(String),
+    #[error("websocket handshake failed: {0}")]
+    Handshake(String),
+    /// The server answered the upgrade with this HTTP status instead.
+    #[error("server refused the websocket with HTTP {0}")]
+    Refused(u16),
+    #[error("no open websocket with id {0}")]
+    NotConnected(String),
+    #[error("invalid close frame: {0}")]
+    InvalidClose(String),
+    #[error("invalid websocket id: {0}")]
+    InvalidId(String),
+    #[error("secret header unavailable: {0}")]
+    Secret(String),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", content = "data", rename_all = "camelCase")]
+pub enum WsMessage {
+    Text(String),
+    Binary(Vec<u8>),
+}
+
+/// A received message as the `message` event carries it.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", content = "data", rename_all = "camelCase")]
+pub enum IncomingMessage {
+    Text(String),
+    /// Base64.
+    Binary(String),
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(
+    tag = "state",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum WsState {
+    /// Reopening after a drop; `attempt` counts from 1.
+    Connecting {
+        attempt: u32,
+    },
+    /// `protocol` is the subprotocol the server picked, if any.
+    Open {
+        protocol: Option<String>,
+    },
+    /// Waiting `delay_ms` before the next attempt.
+    Reconnecting {
+        attempt: u32,
+        delay_ms: u64,
+    },
+    Closing,
+    /// Final. 1006 means the connection was lost rather than closed.
+    Closed {
+        code: u16,
+        reason: String,
+    },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ErrorEvent {
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct WsOptions {
+    /// Header name to the key of a [`crate::secrets`] entry to send as its value. Looked
+    /// up for every attempt, so a refreshed token is picked up, and never sent to JS.
+    pub secret_headers: HashMap<String, String>,
+    /// Reconnects after a drop before giving up; 0 turns reconnecting off.
+    pub max_reconnect_attempts: u32,
+    /// The first reconnect's delay, doubling per attempt up to `max_reconnect_delay_ms`.
+    pub reconnect_delay_ms: u64,
+    pub max_reconnect_delay_ms: u64,
+    /// How often to ping the server; a connection with no traffic for two intervals is
+    /// treated as lost. 0 sends no pings.
+    pub ping_interval_ms: u64,
+}
+
+impl Default for WsOptions {
+    fn default() -> Self {
+        Self {
+            secret_headers: HashMap::new(),
+            max_reconnect_attempts: 10,
+            reconnect_delay_ms: 500,
+            max_reconnect_delay_ms: 30_000,
+            ping_interval_ms: 30_000,
+        }
+    }
+}
+
+enum Outgoing {
+    Message(WsMessage),
+    Close(u16, String),
+    Resubscribe(Vec<WsMessage>),
+}
+
+pub struct WsHandle {
+    outgoing: mpsc::Sender<Outgoing>,
+    /// Label of the window that opened the connection; it is closed when that window is.
+    owner: String,
+}
+
+#[derive(Default)]
+pub struct WebSockets(Mutex<HashMap<String, WsHandle>>);
+
+impl From<WsMessage> for Message {
+    fn from(message: WsMessage) -> Self {
+        match message {
+            WsMessage::Text(text) => Message::text(text),
+            WsMessage::Binary(data) => Message::binary(data),
+        }
+    }
+}
+
+fn close_message(code: u16, reason: String) -> Message {
+    Message::Close(Some(CloseFrame {
+        code: CloseCode::from(code),
+        reason: reason.into(),
+    }))
+}
+
+/// The close code and reason to fail the connection with over a bad frame or message,
+/// or `None` when the error means the connection itself is gone. Reasons are kept short,
+/// since a close frame has room for 123 bytes of one.
+fn failure(error: &tungstenite::Error) -> Option<(u16, String)> {
+    let (code, reason) = match error {
+        tungstenite::Error::Protocol(_) => (CLOSE_PROTOCOL_ERROR, "protocol error"),
+        tungstenite::Error::Capacity(_) => (CLOSE_TOO_BIG, "message too large"),
+        tungstenite::Error::Utf8(_) => (CLOSE_INVALID_DATA, "text is not UTF-8"),
+        _ => return None,
+    };
+    Some((code, reason.to_string()))
+}
+
+/// Whether a failed reconnect is worth trying again. Network and TLS failures pass, as do
+/// refusals a later attempt can get past: a stale token (secret headers are looked up
+/// again each time), a timeout or throttling, or a server error. Other 4xx answers mean
+/// the endpoint is gone or won't take us.
+fn is_retryable(error: &WsError) -> bool {
+    match error {
+        WsError::Refused(status) => {
+            !matches!(status, 400..=499) || matches!(status, 401 | 403 | 408 | 429)
+        }
+        WsError::InvalidUrl(_) => false,
+        _ => true,
+    }
+}
+
+/// Where and how to connect, kept for reconnecting.
+struct Target {
+    url: String,
+    protocols: Vec<String>,
+    headers: HashMap<String, String>,
+    options: WsOptions,
+}
+
+fn header(name: &str, value: &str) -> Result<(HeaderName, HeaderValue), WsError> {
+    let invalid = |e: &dyn std::fmt::Display| WsError::Handshake(format!("header {name}: {e}"));
+    Ok((
+        HeaderName::from_bytes(name.as_bytes()).map_err(|e| invalid(&e))?,
+        HeaderValue::from_str(value).map_err(|e| invalid(&e))?,
+    ))
+}
+
+async fn handshake<R: Runtime>(
+    app: &AppHandle<R>,
+    target: &Target,
+) -> Result<(Stream, Option<String>), WsError> {
+    let mut request = target
+        .url
+        .as_str()
+        .into_client_request()
+        .map_err(|e| WsError::InvalidUrl(e.to_string()))?;
+    let headers = request.headers_mut();
+    for (name, value) in &target.headers {
+        let (name, value) = header(name, value)?;
+        headers.insert(name, value);
+    }
+    let secrets = app.state::<Secrets>();
+    for (name, secret) in &target.options.secret_headers {
+        let value = secrets::get(app, &secrets, secret)
+            .map_err(|e| WsError::Secret(format!("{name}: {e}")))?;
+        let (name, value) = header(name, &value)?;
+        headers.insert(name, value);
+    }
+    if !target.protocols.is_empty() {
+        let (name, value) = header(
+            header::SEC_WEBSOCKET_PROTOCOL.as_str(),
+            &target.protocols.join(", "),
+        )?;
+        headers.insert(name, value);
+    }
+
+    let config = WebSocketConfig::default()
+        .max_message_size(Some(MAX_MESSAGE_BYTES))
+        .max_frame_size(Some(MAX_FRAME_BYTES));
+    // tungstenite checks Sec-WebSocket-Accept and that any subprotocol was one we asked for.
+    let (stream, response) =
+        tokio_tungstenite::connect_async_with_config(request, Some(config), false)
+            .await
+            .map_err(|e| match e {
+                tungstenite::Error::Url(e) => WsError::InvalidUrl(e.to_string()),
+                tungstenite::Error::Http(response) => WsError::Refused(response.status().as_u16()),
+                e => WsError::Handshake(e.to_string()),
+            })?;
+    let protocol = response
+        .headers()
+        .get(header::SEC_WEBSOCKET_PROTOCOL)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    Ok((stream, protocol))
+}
+
+/// How one connection attempt ended.
+struct SessionEnd {
+    code: u16,
+    reason: String,
+    /// False once we asked to close, the server closed normally, or every handle is gone.
+    reconnect: bool,
+}
+
+struct Connection<R: Runtime> {
+    app: AppHandle<R>,
+    id: String,
+    target: Target,
+    outgoing: mpsc::Receiver<Outgoing>,
+    /// Where binary messages go instead of the `message` event, when given.
+    binary: Option<Channel<InvokeResponseBody>>,
+    resubscribe: Vec<WsMessage>,
+    /// Sent while reconnecting, delivered once the connection is back.
+    pending: VecDeque<WsMessage>,
+}
+
+impl<R: Runtime> Connection<R> {
+    fn emit(&self, kind: &str, payload: impl Serialize + Clone) {
+        let _ = self.app.emit(&format!("ws://{}/{kind}", self.id), payload);
+    }
+
+    fn set_state(&self, state: WsState) {
+        tracing::debug!(id = %self.id, ?state, "websocket state");
+        self.emit("state", state);
+    }
+
+    fn emit_error(&self, message: String) {
+        tracing::warn!(id = %self.id, error = %message, "websocket error");
+        self.emit("error", ErrorEvent { message });
+    }
+
+    fn deliver(&self, data: WsMessage) {
+        let message = match (data, &self.binary) {
+            (WsMessage::Binary(data), Some(channel)) => {
+                let _ = channel.send(InvokeResponseBody::Raw(data));
+                return;
+            }
+            (WsMessage::Binary(data), None) => {
+                IncomingMessage::Binary(base64::engine::general_purpose::STANDARD.encode(data))
+            }
+            (WsMessage::Text(text), _) => IncomingMessage::Text(text),
+        };
+        self.emit("message", message);
+    }
+
+    fn queue(&mut self, message: WsMessage) {
+        if self.pending.len() == MAX_PENDING {
+            self.pending.pop_front();
+            self.emit_error("dropped the oldest message queued while reconnecting".into());
+        }
+        self.pending.push_back(message);
+    }
+
+    /// Drives one open connection until either side closes it or it's lost.
+    async fn session(&mut self, stream: Stream, reconnected: bool) -> SessionEnd {
+        // Reading is cancel-safe, since tungstenite keeps a partly read frame buffered,
+        // so both halves can sit in one `select!`.
+        let (mut writer, mut reader) = stream.split();
+
+        let mut end = SessionEnd {
+            code: CLOSE_ABNORMAL,
+            reason: "connection lost".into(),
+            reconnect: true,
+        };
+
+        let mut replay: Vec<WsMessage> = Vec::new();
+        if reconnected {
+            replay.extend(self.resubscribe.iter().cloned());
+        }
+        replay.extend(self.pending.drain(..));
+        for message in replay {
+            if writer.feed(message.into()).await.is_err() {
+                return end;
+            }
+        }
+        if writer.flush().await.is_err() {
+            return end;
+        }
+
+        let mut closing = false;
+        let close_deadline = tokio::time::sleep(CLOSE_TIMEOUT);
+        tokio::pin!(close_deadline);
+        let ping_every = Duration::from_millis(self.target.options.ping_interval_ms);
+        let mut pings = tokio::time::interval(ping_every.max(Duration::from_millis(1)));
+        pings.reset();
+        let mut last_heard = Instant::now();
+
+        loop {
+            // Set when we start the closing handshake, by request or over a protocol error.
+            let mut close_with: Option<(u16, String)> = None;
+            tokio::select! {
+                message = reader.next() => {
+                    last_heard = Instant::now();
+                    match message {
+                        Some(Ok(Message::Text(text))) => self.deliver(WsMessage::Text(text.to_string())),
+                        Some(Ok(Message::Binary(data))) => self.deliver(WsMessage::Binary(data.into())),
+                        // tungstenite queued the pong; flushing sends it.
+                        Some(Ok(Message::Ping(_))) => {
+                            if writer.flush().await.is_err() {
+                                break;
+                            }
+                        }
+                        Some(Ok(Message::Pong(_) | Message::Frame(_))) => {}
+                        Some(Ok(Message::Close(frame))) => {
+                            let (code, reason) = frame.map_or((CLOSE_NORMAL, String::new()), |frame| {
+                                (u16::from(frame.code), frame.reason.to_string())
+                            });
+                            if !closing {
+                                end.reconnect = code != CLOSE_NORMAL;
+                                // Sends the reply tungstenite queued.
+                                let _ = writer.flush().await;
+                            }
+                            (end.code, end.reason) = (code, reason);
+                            break;
+                        }
+                        Some(Err(e)) if closing => {
+                            tracing::debug!(id = %self.id, error = %e, "error while closing");
+                            break;
+                        }
+                        Some(Err(e)) => {
+                            self.emit_error(e.to_string());
+                            match failure(&e) {
+                                Some(failure) => close_with = Some(failure),
+                                None => break,
+                            }
+                        }
+                        None => break,
+                    }
+                }
+                message = self.outgoing.recv(), if !closing => match message {
+                    Some(Outgoing::Message(message)) => {
+                        if let Err(e) = writer.send(message.into()).await {
+                            self.emit_error(e.to_string());
+                            break;
+                        }
+                    }
+                    Some(Outgoing::Close(code, reason)) => close_with = Some((code, reason)),
+                    Some(Outgoing::Resubscribe(messages)) => self.resubscribe = messages,
+                    // Every handle is gone: the app is shutting down or the owning window
+                    // closed.
+                    None => close_with = Some((CLOSE_GOING_AWAY, String::new())),
+                },
+                _ = pings.tick(), if !closing && !ping_every.is_zero() => {
+                    if last_heard.elapsed() > ping_every * 2 {
+                        self.emit_error("no response from the server".into());
+                        break;
+                    }
+                    if writer.send(Message::Ping(Default::default())).await.is_err() {
+                        break;
+                    }
+                }
+                () = &mut close_deadline, if closing => break,
+            }
+
+            if let Some((code, reason)) = close_with {
+                closing = true;
+                end.reconnect = false;
+                self.set_state(WsState::Closing);
+                close_deadline
+                    .as_mut()
+                    .reset(Instant::now() + CLOSE_TIMEOUT);
+                (end.code, end.reason) = (code, reason.clone());
+                if writer.send(close_message(code, reason)).await.is_err() {
+                    break;
+                }
+            }
+        }
+        end
+    }
+
+    /// Waits out `delay` while still taking commands: messages are queued, and a close
+    /// request (or every handle going away) ends the connection, returned as `Err`.
+    async fn wait(&mut self, delay: Duration) -> Result<(), SessionEnd> {
+        let sleep = tokio::time::sleep(delay);
+        tokio::pin!(sleep);
+        loop {
+            tokio::select! {
+                () = &mut sleep => return Ok(()),
+                message = self.outgoing.recv() => match message {
+                    Some(Outgoing::Message(message)) => self.queue(message),
+                    Some(Outgoing::Resubscribe(messages)) => self.resubscribe = messages,
+                    Some(Outgoing::Close(code, reason)) => {
+                        return Err(SessionEnd { code, reason, reconnect: false });
+                    }
+                    None => {
+                        return Err(SessionEnd {
+                            code: CLOSE_GOING_AWAY,
+                            reason: String::new(),
+                            reconnect: false,
+                        });
+                    }
+                },
+            }
+        }
+    }
+
+    /// Reopens the connection with backoff, or returns how it ended for good.
+    async fn reconnect(&mut self, lost: SessionEnd) -> Result<Stream, SessionEnd> {
+        let options = &self.target.options;
+        let (max_attempts, first, max) = (
+            options.max_reconnect_attempts,
+            Duration::from_millis(options.reconnect_delay_ms),
+            Duration::from_millis(options.max_reconnect_delay_ms),
+        );
+        for attempt in 1..=max_attempts {
+            let delay = first.saturating_mul(1 << (attempt - 1).min(16)).min(max);
+            self.set_state(WsState::Reconnecting {
+                attempt,
+                delay_ms: delay.as_millis() as u64,
+            });
+            self.wait(delay).await?;
+            self.set_state(WsState::Connecting { attempt });
+            match handshake(&self.app, &self.target).await {
+                Ok((stream, protocol)) => {
+                    tracing::info!(id = %self.id, attempt, "websocket reconnected");
+                    self.set_state(WsState::Open { protocol });
+                    return Ok(stream);
+                }
+                Err(e) => {
+                    self.emit_error(e.to_string());
+                    if !is_retryable(&e) {
+                        return Err(SessionEnd {
+                            reason: format!("{}; {e}", lost.reason),
+                            ..lost
+                        });
+                    }
+                }
+            }
+        }
+        Err(SessionEnd {
+            reason: match max_attempts {
+                0 => lost.reason,
+                n => format!("{}; gave up after {n} reconnect attempts", lost.reason),
+            },
+            ..lost
+        })
+    }
+
+    /// Runs sessions until one ends without a reconnect, then forgets the connection
+    /// and emits the `closed` state exactly once.
+    async fn run(mut self, mut stream: Stream, protocol: Option<String>) {
+        self.set_state(WsState::Open { protocol });
+        let mut reconnected = false;
+        let end = loop {
+            let end = self.session(stream, reconnected).await;
+            if !end.reconnect {
+                break end;
+            }
+            tracing::info!(id = %self.id, code = end.code, "websocket lost");
+            match self.reconnect(end).await {
+                Ok(next) => stream = next,
+                Err(end) => break end,
+            }
+            reconnected = true;
+        };
+
+        if let Some(sockets) = self.app.try_state::<WebSockets>() {
+            sockets.0.lock().unwrap().remove(&self.id);
+        }
+        tracing::info!(id = %self.id, code = end.code, "websocket closed");
+        self.set_state(WsState::Closed {
+            code: end.code,
+            reason: end.reason,
+        });
+    }
+}
+
+fn handle(sockets: &WebSockets, id: &str) -> Result<mpsc::Sender<Outgoing>, WsError> {
+    sockets
+        .0
+        .lock()
+        .unwrap()
+        .get(id)
+        .map(|handle| handle.outgoing.clone())
+        .ok_or_else(|| WsError::NotConnected(id.to_string()))
+}
+
+/// Closes the connections a window opened once it's gone; dropping their handles starts
+/// the closing handshake.
+pub fn on_window_event<R: Runtime>(window: &Window<R>, event: &WindowEvent) {
+    if let WindowEvent::Destroyed = event {
+        window
+            .state::<WebSockets>()
+            .0
+            .lock()
+            .unwrap()
+            .retain(|_, socket| socket.owner != window.label());
+    }
+}
+
+/// Opens a connection to a `ws://` or `wss://` URL and returns its id once the first
+/// handshake has succeeded; that one is not retried. Pass `id` so the frontend can
+/// listen for the connection's events before any are sent, and `on_binary` to receive
+/// binary messages as `ArrayBuffer`s instead of base64.
+#[tauri::command]
+pub async fn ws_connect<R: Runtime>(
+    url: String,
+    protocols: Option<Vec<String>>,
+    headers: Option<HashMap<String, String>>,
+    options: Option<WsOptions>,
+    id: Option<String>,
+    on_binary: Option<JavaScriptChannelId>,
+    webview: Webview<R>,
+) -> Result<String, WsError> {
+    let app = webview.app_handle().clone();
+    let sockets = app.state::<WebSockets>();
+    let id = match id {
+        Some(id) => {
+            let valid = !id.is_empty()
+                && id
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+            if !valid {
+                return Err(WsError::InvalidId(format!(
+                    "{id:?} must be letters, digits, - and _"
+                )));
+            }
+            if sockets.0.lock().unwrap().contains_key(&id) {
+                return Err(WsError::InvalidId(format!("{id} is already in use")));
+            }
+            id
+        }
+        None => Uuid::new_v4().to_string(),
+    };
+    let target = Target {
+        url,
+        protocols: protocols.unwrap_or_default(),
+        headers: headers.unwrap_or_default(),
+        options: options.unwrap_or_default(),
+    };
+    let (stream, protocol) = handshake(&app, &target).await?;
+
+    let (outgoing, receiver) = mpsc::channel(64);
+    let owner = webview.window().label().to_string();
+    {
+        let mut sockets = sockets.0.lock().unwrap();
+        if sockets.contains_key(&id) {
+            return Err(WsError::InvalidId(format!("{id} is already in use")));
+        }
+        sockets.insert(id.clone(), WsHandle { outgoing, owner });
+    }
+    tracing::info!(id = %id, url = %target.url, "websocket connected");
+    let connection = Connection {
+        app,
+        id: id.clone(),
+        target,
+        outgoing: receiver,
+        binary: on_binary.map(|channel| channel.channel_on(webview)),
+        resubscribe: Vec::new(),
+        pending: VecDeque::new(),
+    };
+    tauri::async_runtime::spawn(connection.run(stream, protocol));
+    Ok(id)
+}
+
+/// Sends `message`, or queues it while the connection is reconnecting.
+#[tauri::command]
+pub async fn ws_send(
+    id: String,
+    message: WsMessage,
+    sockets: State<'_, WebSockets>,
+) -> Result<(), WsError> {
+    handle(&sockets, &id)?
+        .send(Outgoing::Message(message))
+        .await
+        .map_err(|_| WsError::NotConnected(id))
+}
+
+/// Registers the messages to send first after every reconnect, typically the
+/// subscriptions the server forgot; replaces any registered before.
+#[tauri::command]
+pub async fn ws_set_resubscribe(
+    id: String,
+    messages: Vec<WsMessage>,
+    sockets: State<'_, WebSockets>,
+) -> Result<(), WsError> {
+    handle(&sockets, &id)?
+        .send(Outgoing::Resubscribe(messages))
+        .await
+        .map_err(|_| WsError::NotConnected(id))
+}
+
+/// Starts the closing handshake, or stops reconnecting; the `closed` state follows once
+/// the server answers, or after [`CLOSE_TIMEOUT`] if it doesn't.
+#[tauri::command]
+pub async fn ws_close(
+    id: String,
+    code: Option<u16>,
+    reason: Option<String>,
+    sockets: State<'_, WebSockets>,
+) -> Result<(), WsError> {
+    let code = code.unwrap_or(CLOSE_NORMAL);
+    // 1000 and the 3000-4999 application range are the only codes a client may send.
+    if code != CLOSE_NORMAL && !(3000..=4999).contains(&code) {
+        return Err(WsError::InvalidClose(format!("code {code} is reserved")));
+    }
+    let reason = reason.unwrap_or_default();
+    if reason.len() > 123 {
+        return Err(WsError::InvalidClose(
+            "reason is longer than 123 bytes".into(),
+        ));
+    }
+    handle(&sockets, &id)?
+        .send(Outgoing::Close(code, reason))
+        .await
+        .map_err(|_| WsError::NotConnected(id))
+}
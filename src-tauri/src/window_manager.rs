@@ -0,0 +1,298 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::{AppHandle, Emitter, Manager, Runtime, State, WebviewUrl, WebviewWindowBuilder};
+use tauri::{Window, WindowEvent};
+
+use crate::{titlebar, window_state};
+
+#[derive(Debug, thiserror::Error, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "camelCase")]
+pub enum WindowError {
+    #[error("a window labelled `{0}` is already open")]
+    AlreadyOpen(String),
+    #[error("no window labelled `{0}`")]
+    NotFound(String),
+    #[error("invalid url: {0}")]
+    InvalidUrl(String),
+    #[error("{0}")]
+    Window(String),
+}
+
+impl From<tauri::Error> for WindowError {
+    fn from(e: tauri::Error) -> Self {
+        Self::Window(e.to_string())
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct WindowOptions {
+    pub title: Option<String>,
+    pub width: Option<f64>,
+    pub height: Option<f64>,
+    pub resizable: Option<bool>,
+    pub decorations: Option<bool>,
+    /// Label of the window this one is attached to; it stays above its parent.
+    pub parent: Option<String>,
+    /// Disables the parent until this window closes. Requires `parent`.
+    pub modal: bool,
+    /// Focus an already-open window with the same label instead of failing.
+    pub singleton: bool,
+    /// Leave the titlebar to the page; see [`crate::titlebar`]. Overrides `decorations`.
+    pub custom_titlebar: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WindowInfo {
+    pub label: String,
+    pub title: String,
+    pub url: String,
+    pub visible: bool,
+    pub focused: bool,
+}
+
+/// What a window is for; singleton roles can only be open once.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum WindowRole {
+    Main,
+    Preferences,
+    About,
+    Overlay,
+}
+
+impl WindowRole {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Main => "main",
+            Self::Preferences => "preferences",
+            Self::About => "about",
+            Self::Overlay => "overlay",
+        }
+    }
+
+    fn is_singleton(self) -> bool {
+        matches!(self, Self::Main | Self::Preferences)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WindowConfig {
+    pub url: String,
+    pub width: f64,
+    pub height: f64,
+    pub resizable: bool,
+    pub always_on_top: bool,
+}
+
+/// Modal windows by label, mapped to the parent they disabled, and the role of each
+/// window opened by role.
+#[derive(Default)]
+pub struct WindowManager {
+    modal_parents: Mutex<HashMap<String, String>>,
+    roles: Mutex<HashMap<String, WindowRole>>,
+    next_id: AtomicU32,
+}
+
+impl WindowManager {
+    /// The open window with `role`, counting the `main` window from the config as
+    /// [`WindowRole::Main`].
+    fn find_role<R: Runtime>(
+        &self,
+        app: &AppHandle<R>,
+        role: WindowRole,
+    ) -> Option<tauri::WebviewWindow<R>> {
+        if role == WindowRole::Main {
+            if let Some(main) = app.get_webview_window("main") {
+                return Some(main);
+            }
+        }
+        let roles = self.roles.lock().unwrap();
+        roles
+            .iter()
+            .filter(|(_, r)| **r == role)
+            .find_map(|(label, _)| app.get_webview_window(label))
+    }
+}
+
+/// Only bundled pages may be opened: extra windows share the main window's capability,
+/// which must never be granted to a remote origin.
+fn app_url(url: &str) -> Result<WebviewUrl, WindowError> {
+    if url.contains("://") || url.starts_with("//") {
+        return Err(WindowError::InvalidUrl(format!(
+            "{url} is not an app-relative path"
+        )));
+    }
+    Ok(WebviewUrl::App(url.trim_start_matches('/').into()))
+}
+
+fn focus<R: Runtime>(window: &tauri::WebviewWindow<R>) {
+    let _ = window.unminimize();
+    let _ = window.show();
+    let _ = window.set_focus();
+}
+
+#[tauri::command]
+pub async fn open_window<R: Runtime>(
+    label: String,
+    url: String,
+    opts: WindowOptions,
+    app: AppHandle<R>,
+    manager: State<'_, WindowManager>,
+) -> Result<(), WindowError> {
+    if let Some(existing) = app.get_webview_window(&label) {
+        if opts.singleton {
+            focus(&existing);
+            return Ok(());
+        }
+        return Err(WindowError::AlreadyOpen(label));
+    }
+
+    let parent = match &opts.parent {
+        Some(parent) => Some(
+            app.get_webview_window(parent)
+                .ok_or_else(|| WindowError::NotFound(parent.clone()))?,
+        ),
+        None => None,
+    };
+
+    // Built hidden so saved geometry is applied before the first paint, like `main`.
+    let mut builder = WebviewWindowBuilder::new(&app, &label, app_url(&url)?)
+        .title(opts.title.as_deref().unwrap_or("Layers"))
+        .inner_size(opts.width.unwrap_or(800.0), opts.height.unwrap_or(600.0))
+        .resizable(opts.resizable.unwrap_or(true))
+        .decorations(opts.decorations.unwrap_or(true))
+        .visible(false);
+    if let Some(parent) = &parent {
+        builder = builder.parent(parent)?;
+    }
+    if opts.custom_titlebar {
+        builder = titlebar::style(builder);
+    }
+    let window = builder.build()?;
+    if opts.custom_titlebar {
+        titlebar::install(&window);
+    }
+
+    window_state::restore(&window.as_ref().window())?;
+    window.show()?;
+
+    if let (true, Some(parent)) = (opts.modal, parent) {
+        parent.set_enabled(false)?;
+        manager
+            .modal_parents
+            .lock()
+            .unwrap()
+            .insert(label, parent.label().to_string());
+    }
+    Ok(())
+}
+
+/// Opens a window for `role` labelled `<role>-<n>` and returns the label. Opening a
+/// singleton role twice focuses the existing window and fails with
+/// [`WindowError::AlreadyOpen`].
+#[tauri::command]
+pub async fn open_role_window<R: Runtime>(
+    role: WindowRole,
+    config: WindowConfig,
+    app: AppHandle<R>,
+    manager: State<'_, WindowManager>,
+) -> Result<String, WindowError> {
+    if role.is_singleton() {
+        if let Some(existing) = manager.find_role(&app, role) {
+            focus(&existing);
+            return Err(WindowError::AlreadyOpen(existing.label().to_string()));
+        }
+    }
+
+    let id = manager.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+    let label = format!("{}-{id}", role.as_str());
+    let window = WebviewWindowBuilder::new(&app, &label, app_url(&config.url)?)
+        .title("Layers")
+        .inner_size(config.width, config.height)
+        .resizable(config.resizable)
+        .always_on_top(config.always_on_top)
+        .visible(false)
+        .build()?;
+    manager.roles.lock().unwrap().insert(label.clone(), role);
+
+    window_state::restore(&window.as_ref().window())?;
+    window.show()?;
+    Ok(label)
+}
+
+#[tauri::command]
+pub fn close_window<R: Runtime>(label: String, app: AppHandle<R>) -> Result<(), WindowError> {
+    let window = app
+        .get_webview_window(&label)
+        .ok_or(WindowError::NotFound(label))?;
+    window.close()?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn list_windows<R: Runtime>(app: AppHandle<R>) -> Vec<WindowInfo> {
+    let mut windows: Vec<WindowInfo> = app
+        .webview_windows()
+        .into_iter()
+        .map(|(label, window)| WindowInfo {
+            title: window.title().unwrap_or_default(),
+            url: window.url().map(|url| url.to_string()).unwrap_or_default(),
+            visible: window.is_visible().unwrap_or(false),
+            focused: window.is_focused().unwrap_or(false),
+            label,
+        })
+        .collect();
+    windows.sort_by(|a, b| a.label.cmp(&b.label));
+    windows
+}
+
+/// Broadcasts an event to every window, for state that all windows mirror.
+#[tauri::command]
+pub fn emit_to_all<R: Runtime>(
+    event: String,
+    payload: Value,
+    app: AppHandle<R>,
+) -> Result<(), WindowError> {
+    Ok(app.emit(&event, payload)?)
+}
+
+/// Forgets a destroyed window's role, and re-enables its parent if it was modal.
+pub fn on_window_event<R: Runtime>(window: &Window<R>, event: &WindowEvent) {
+    if !matches!(event, WindowEvent::Destroyed) {
+        return;
+    }
+    let Some(manager) = window.try_state::<WindowManager>() else {
+        return;
+    };
+    manager.roles.lock().unwrap().remove(window.label());
+    let parent = manager.modal_parents.lock().unwrap().remove(window.label());
+    if let Some(parent) = parent.and_then(|label| window.get_webview_window(&label)) {
+        let _ = parent.set_enabled(true);
+        let _ = parent.set_focus();
+    }
+}
+
+/// Clicking the dock icon with no windows open brings `main` back from the config.
+#[cfg(target_os = "macos")]
+pub fn reopen<R: Runtime>(app: &AppHandle<R>) {
+    if let Some(window) = app.get_webview_window("main") {
+        focus(&window);
+        return;
+    }
+    let Some(config) = app.config().app.windows.first().cloned() else {
+        return;
+    };
+    match WebviewWindowBuilder::from_config(app, &config).and_then(|b| b.build()) {
+        Ok(window) => {
+            let _ = window_state::restore(&window.as_ref().window());
+            focus(&window);
+        }
+        Err(e) => tracing::error!(error = %e, "failed to reopen main window"),
+    }
+}
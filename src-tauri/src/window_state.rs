@@ -0,0 +1,200 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tauri::{
+    AppHandle, Manager, Monitor, PhysicalPosition, PhysicalSize, Runtime, Window, WindowEvent,
+};
+use tauri_plugin_store::StoreExt;
+
+/// Store file holding one [`WindowGeometry`] per window label.
+const STORE_FILE: &str = "window-state.json";
+/// Resize and move events arrive many times per second while dragging.
+const SAVE_DEBOUNCE: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WindowGeometry {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub maximized: bool,
+    pub fullscreen: bool,
+}
+
+/// Per-label change counters used to debounce saves.
+#[derive(Default)]
+pub struct WindowStateTracker {
+    generations: Mutex<HashMap<String, u64>>,
+}
+
+fn load<R: Runtime>(app: &AppHandle<R>, label: &str) -> Option<WindowGeometry> {
+    let store = app.store(STORE_FILE).ok()?;
+    serde_json::from_value(store.get(label)?).ok()
+}
+
+fn save<R: Runtime>(window: &Window<R>) -> tauri::Result<()> {
+    let maximized = window.is_maximized()?;
+    let fullscreen = window.is_fullscreen()?;
+    let previous = load(window.app_handle(), window.label());
+
+    // Keep the last normal geometry while maximized so un-maximizing after a restart
+    // returns to where the user had the window.
+    let geometry = match previous {
+        Some(previous) if maximized || fullscreen => WindowGeometry {
+            maximized,
+            fullscreen,
+            ..previous
+        },
+        _ => {
+            let position = window.outer_position()?;
+            let size = window.inner_size()?;
+            WindowGeometry {
+                x: position.x,
+                y: position.y,
+                width: size.width,
+                height: size.height,
+                maximized,
+                fullscreen,
+            }
+        }
+    };
+
+    let Ok(store) = window.app_handle().store(STORE_FILE) else {
+        return Ok(());
+    };
+    store.set(window.label(), serde_json::to_value(geometry)?);
+    let _ = store.save();
+    Ok(())
+}
+
+fn contains(monitor: &Monitor, x: i32, y: i32) -> bool {
+    let area = monitor.work_area();
+    x >= area.position.x
+        && y >= area.position.y
+        && x < area.position.x + area.size.width as i32
+        && y < area.position.y + area.size.height as i32
+}
+
+/// Moves the saved position onto a connected monitor, e.g. after an external display was
+/// unplugged, shrinking the window if it no longer fits.
+fn clamp_to_monitor<R: Runtime>(window: &Window<R>, geometry: &mut WindowGeometry) {
+    let monitors = window.available_monitors().unwrap_or_default();
+    if monitors.iter().any(|m| contains(m, geometry.x, geometry.y)) {
+        return;
+    }
+
+    let fallback = window
+        .primary_monitor()
+        .ok()
+        .flatten()
+        .or_else(|| monitors.into_iter().next());
+    let Some(monitor) = fallback else {
+        return;
+    };
+
+    let area = monitor.work_area();
+    geometry.width = geometry.width.min(area.size.width);
+    geometry.height = geometry.height.min(area.size.height);
+    geometry.x = area.position.x + (area.size.width - geometry.width) as i32 / 2;
+    geometry.y = area.position.y + (area.size.height - geometry.height) as i32 / 2;
+}
+
+/// Applies saved geometry to a window. Does nothing on mobile, where windows are fullscreen.
+pub fn restore<R: Runtime>(window: &Window<R>) -> tauri::Result<()> {
+    if cfg!(mobile) {
+        return Ok(());
+    }
+    let Some(mut geometry) = load(window.app_handle(), window.label()) else {
+        return Ok(());
+    };
+
+    clamp_to_monitor(window, &mut geometry);
+    window.set_size(PhysicalSize::new(geometry.width, geometry.height))?;
+    window.set_position(PhysicalPosition::new(geometry.x, geometry.y))?;
+    if geometry.maximized {
+        window.maximize()?;
+    }
+    if geometry.fullscreen {
+        window.set_fullscreen(true)?;
+    }
+    Ok(())
+}
+
+/// Restores every window declared in `tauri.conf.json`; they start hidden so the user
+/// never sees them jump into place. With `show` unset they stay hidden, for launches
+/// that should start in the tray.
+pub fn init<R: Runtime>(app: &AppHandle<R>, show: bool) -> tauri::Result<()> {
+    app.manage(WindowStateTracker::default());
+    for window in app.webview_windows().values() {
+        restore(&window.as_ref().window())?;
+        if show {
+            window.show()?;
+        }
+    }
+    Ok(())
+}
+
+pub fn on_window_event<R: Runtime>(window: &Window<R>, event: &WindowEvent) {
+    if cfg!(mobile) {
+        return;
+    }
+
+    match event {
+        WindowEvent::Resized(_) | WindowEvent::Moved(_) => {
+            let tracker = window.state::<WindowStateTracker>();
+            let generation = {
+                let mut generations = tracker.generations.lock().unwrap();
+                let generation = generations.entry(window.label().to_string()).or_default();
+                *generation += 1;
+                *generation
+            };
+
+            let window = window.clone();
+            tauri::async_runtime::spawn(async move {
+                tokio::time::sleep(SAVE_DEBOUNCE).await;
+                let tracker = window.state::<WindowStateTracker>();
+                let latest = tracker
+                    .generations
+                    .lock()
+                    .unwrap()
+                    .get(window.label())
+                    .copied();
+                if latest == Some(generation) {
+                    let _ = save(&window);
+                }
+            });
+        }
+        // Geometry can no longer be read once `Destroyed` fires, so the final save
+        // happens on close and `Destroyed` only drops the debounce bookkeeping.
+        WindowEvent::CloseRequested { .. } => {
+            let _ = save(window);
+        }
+        WindowEvent::Destroyed => {
+            let tracker = window.state::<WindowStateTracker>();
+            tracker.generations.lock().unwrap().remove(window.label());
+        }
+        _ => {}
+    }
+}
+
+/// Saves every open window; called on [`tauri::RunEvent::ExitRequested`] because
+/// `AppHandle::exit` (e.g. tray "Quit") closes windows without `CloseRequested`.
+pub fn save_all<R: Runtime>(app: &AppHandle<R>) {
+    if cfg!(mobile) {
+        return;
+    }
+    for window in app.webview_windows().values() {
+        let _ = save(&window.as_ref().window());
+    }
+}
+
+/// Forgets saved geometry for every window; the next launch uses the configured defaults.
+#[tauri::command]
+pub fn reset_window_state<R: Runtime>(app: AppHandle<R>) -> Result<(), String> {
+    let store = app.store(STORE_FILE).map_err(|e| e.to_string())?;
+    store.clear();
+    store.save().map_err(|e| e.to_string())
+}